@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+/// Apps that take focus only briefly, e.g. for a search field or an
+/// alert, and hand it back to the app before them.
+pub const TRANSIENT_APPS: &[&str] = &[
+    "com.apple.Spotlight",
+    "com.apple.notificationcenterui",
+    "com.apple.UserNotificationCenter",
+    "com.apple.controlcenter",
+    "com.apple.systemuiserver",
+    "com.apple.loginwindow",
+];
+
+/// Filters app activations before they switch rules: activations of
+/// transient apps are dropped and the others are held back until no other
+/// activation followed for the debounce time, so focus flapping between
+/// apps doesn't switch rules back and forth.
+#[derive(Debug, Default)]
+pub struct ActivationFilter {
+    debounce: Duration,
+    /// Transient apps on top of `TRANSIENT_APPS`.
+    transient: Vec<Box<str>>,
+    /// Latest activation held back, and when it applies.
+    pending: Option<(String, Instant)>,
+}
+
+impl ActivationFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the debounce time, zero forwards activations immediately, and
+    /// transient apps in addition to `TRANSIENT_APPS`.
+    pub fn configure(&mut self, debounce: Duration, transient: Vec<Box<str>>) {
+        self.debounce = debounce;
+        self.transient = transient;
+        if debounce.is_zero() {
+            self.pending = None;
+        }
+    }
+
+    /// Records an activation at `now`. Returns the app if it applies right
+    /// away, otherwise it replaces the one held back.
+    pub fn push(&mut self, bundle_id: String, now: Instant) -> Option<String> {
+        if self.is_transient(&bundle_id) {
+            return None;
+        }
+        if self.debounce.is_zero() {
+            return Some(bundle_id);
+        }
+        self.pending = Some((bundle_id, now + self.debounce));
+        None
+    }
+
+    /// The activation held back, once it settled by `now`.
+    pub fn take_due(&mut self, now: Instant) -> Option<String> {
+        match self.pending.take() {
+            Some((bundle_id, due)) if due <= now => Some(bundle_id),
+            pending => {
+                self.pending = pending;
+                None
+            }
+        }
+    }
+
+    /// When the activation held back settles.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|(_, due)| *due)
+    }
+
+    fn is_transient(&self, bundle_id: &str) -> bool {
+        TRANSIENT_APPS.contains(&bundle_id)
+            || self.transient.iter().any(|id| id.as_ref() == bundle_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_transient_apps_and_debounces() {
+        let now = Instant::now();
+        let mut filter = ActivationFilter::new();
+        assert_eq!(filter.push("com.apple.Spotlight".into(), now), None);
+        assert_eq!(
+            filter.push("com.apple.Safari".into(), now).as_deref(),
+            Some("com.apple.Safari")
+        );
+
+        filter
+            .configure(Duration::from_millis(100), vec!["com.raycast.macos".into()]);
+        assert_eq!(filter.push("com.raycast.macos".into(), now), None);
+        assert_eq!(filter.next_due(), None);
+        assert_eq!(filter.push("com.apple.mail".into(), now), None);
+        let later = now + Duration::from_millis(50);
+        assert_eq!(filter.push("com.apple.Safari".into(), later), None);
+        assert_eq!(filter.take_due(now + Duration::from_millis(100)), None);
+        assert_eq!(filter.next_due(), Some(later + Duration::from_millis(100)));
+        assert_eq!(
+            filter
+                .take_due(later + Duration::from_millis(100))
+                .as_deref(),
+            Some("com.apple.Safari")
+        );
+        assert_eq!(filter.next_due(), None);
+    }
+}
@@ -0,0 +1,163 @@
+/// An application the user can switch to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningApp {
+    /// Bundle id on macOS, `WM_CLASS` class on Linux.
+    pub id: String,
+    /// Whether the application is frontmost.
+    pub active: bool,
+}
+
+/// Lists running applications with a user interface, in a stable order.
+#[cfg(target_os = "macos")]
+pub fn running_applications() -> Vec<RunningApp> {
+    use objc2_app_kit::{NSApplicationActivationPolicy, NSWorkspace};
+
+    // SAFETY: `runningApplications` is thread safe and returns an
+    // immutable snapshot, its items are only read.
+    unsafe {
+        NSWorkspace::sharedWorkspace()
+            .runningApplications()
+            .iter()
+            .filter(|app| {
+                app.activationPolicy() == NSApplicationActivationPolicy::Regular
+                    && !app.isTerminated()
+            })
+            .filter_map(|app| {
+                Some(RunningApp {
+                    id: app.bundleIdentifier()?.to_string(),
+                    active: app.isActive(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lists windowed applications, ordered by their first window.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn running_applications() -> Vec<RunningApp> {
+    crate::x11::running_applications()
+}
+
+/// Brings the running application `id` to the front, unhiding it when
+/// needed. Returns `false` when it is not running or refused activation.
+#[cfg(target_os = "macos")]
+pub fn activate_application(id: &str) -> bool {
+    use objc2_app_kit::{NSApplicationActivationOptions, NSRunningApplication};
+    use objc2_foundation::NSString;
+
+    // SAFETY: the application object is retained while it is messaged.
+    unsafe {
+        let apps = NSRunningApplication::runningApplicationsWithBundleIdentifier(
+            &NSString::from_str(id),
+        );
+        let Some(app) = apps.firstObject() else {
+            return false;
+        };
+        if app.isHidden() {
+            app.unhide();
+        }
+        app.activateWithOptions(NSApplicationActivationOptions::ActivateAllWindows)
+    }
+}
+
+/// Process id of the running application `id`, `None` when it is not
+/// running.
+#[cfg(target_os = "macos")]
+pub fn application_pid(id: &str) -> Option<i32> {
+    use objc2_app_kit::NSRunningApplication;
+    use objc2_foundation::NSString;
+
+    // SAFETY: the application object is retained while it is messaged.
+    unsafe {
+        NSRunningApplication::runningApplicationsWithBundleIdentifier(
+            &NSString::from_str(id),
+        )
+        .iter()
+        .find(|app| !app.isTerminated())
+        .map(|app| app.processIdentifier())
+    }
+}
+
+/// Posting events to a process is only supported on macOS.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn application_pid(_id: &str) -> Option<i32> {
+    None
+}
+
+/// Asks the window manager to activate the first window of application
+/// `id`. Returns `false` when it has no window or the request failed.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn activate_application(id: &str) -> bool {
+    crate::x11::activate_application(id)
+}
+
+/// Picks the application to switch to when cycling through a group.
+///
+/// `rank` gives the position of an application in the group, `None` for
+/// applications outside of it. Running members are ordered by rank, then by
+/// `running` order, and the one after the active member is returned. The
+/// first member is returned while the active application is not in the
+/// group. `None` when no other member is running.
+pub fn next_application<F>(running: &[RunningApp], rank: F) -> Option<&RunningApp>
+where
+    F: Fn(&str) -> Option<usize>,
+{
+    let mut members: Vec<(usize, &RunningApp)> = running
+        .iter()
+        .filter_map(|app| rank(&app.id).map(|rank| (rank, app)))
+        .collect();
+    // Stable, so apps of the same rank keep the running order
+    members.sort_by_key(|(rank, _)| *rank);
+    let next = match members.iter().position(|(_, app)| app.active) {
+        Some(active) => members.get(active + 1).or(members.first()),
+        None => members.first(),
+    }?;
+    (!next.1.active).then_some(next.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(id: &str, active: bool) -> RunningApp {
+        RunningApp {
+            id: id.to_string(),
+            active,
+        }
+    }
+
+    fn rank(group: &'static [&'static str]) -> impl Fn(&str) -> Option<usize> {
+        move |id| group.iter().position(|member| *member == id)
+    }
+
+    #[test]
+    fn cycles_in_group_order() {
+        let group = &["code", "zed", "xcode"];
+        let running = [
+            app("xcode", false),
+            app("finder", false),
+            app("code", true),
+            app("zed", false),
+        ];
+        let next = next_application(&running, rank(group));
+        assert_eq!(next.map(|app| app.id.as_str()), Some("zed"));
+
+        let running = [app("xcode", true), app("code", false)];
+        let next = next_application(&running, rank(group));
+        assert_eq!(next.map(|app| app.id.as_str()), Some("code"));
+    }
+
+    #[test]
+    fn starts_with_first_member_outside_of_group() {
+        let running = [app("finder", true), app("xcode", false), app("zed", false)];
+        let next = next_application(&running, rank(&["code", "zed", "xcode"]));
+        assert_eq!(next.map(|app| app.id.as_str()), Some("zed"));
+    }
+
+    #[test]
+    fn nothing_to_switch_to() {
+        let running = [app("code", true), app("finder", false)];
+        assert_eq!(next_application(&running, rank(&["code", "zed"])), None);
+        assert_eq!(next_application(&running, rank(&["zed"])), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! Focused window title and fullscreen state via the macOS Accessibility API.
+//!
+//! Requires the same Accessibility permission that is needed to send key events.
+//! The screen lock state is read from the login session, which needs none.
+
+use std::ffi::c_void;
+use std::ptr;
+
+use gamacros_cf::{CFStringRef, CFTypeRef, CfObject};
+
+type AXUIElementRef = CFTypeRef;
+type AXError = i32;
+
+const AX_ERROR_SUCCESS: AXError = 0;
+const AX_VALUE_CG_POINT_TYPE: u32 = 1;
+const AX_VALUE_CG_SIZE_TYPE: u32 = 2;
+/// Upper bound of displays checked for a covering window.
+const MAX_DISPLAYS: usize = 16;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CGSize {
+    width: f64,
+    height: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CGRect {
+    origin: CGPoint,
+    size: CGSize,
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+    fn AXUIElementCopyAttributeValue(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        value: *mut CFTypeRef,
+    ) -> AXError;
+    fn AXValueGetValue(value: CFTypeRef, kind: u32, out: *mut c_void) -> u8;
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGGetActiveDisplayList(
+        max_displays: u32,
+        displays: *mut u32,
+        count: *mut u32,
+    ) -> i32;
+    fn CGDisplayBounds(display: u32) -> CGRect;
+    fn CGSessionCopyCurrentDictionary() -> CFTypeRef;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFGetTypeID(cf: CFTypeRef) -> usize;
+    fn CFBooleanGetTypeID() -> usize;
+    fn CFBooleanGetValue(boolean: CFTypeRef) -> u8;
+    fn CFDictionaryGetValue(dictionary: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+}
+
+/// Accessibility reads of an element or of an attribute value.
+trait AxObject {
+    /// Copies an accessibility attribute of this element.
+    fn attribute(&self, name: &CfObject) -> Option<CfObject>;
+
+    /// Reads an `AXValue` holding a `T` of the given AX value type.
+    fn ax_value<T: Default>(&self, kind: u32) -> Option<T>;
+}
+
+impl AxObject for CfObject {
+    fn attribute(&self, name: &CfObject) -> Option<CfObject> {
+        let mut value: CFTypeRef = ptr::null();
+        // SAFETY: both objects are alive, the copied value is owned by us.
+        unsafe {
+            let error = AXUIElementCopyAttributeValue(
+                self.as_ptr(),
+                name.as_ptr(),
+                &mut value,
+            );
+            if error != AX_ERROR_SUCCESS {
+                return None;
+            }
+            CfObject::new(value)
+        }
+    }
+
+    fn ax_value<T: Default>(&self, kind: u32) -> Option<T> {
+        let mut value = T::default();
+        // SAFETY: kind matches the layout of T, the object is alive.
+        let ok = unsafe {
+            AXValueGetValue(self.as_ptr(), kind, (&mut value as *mut T).cast())
+        };
+        (ok != 0).then_some(value)
+    }
+}
+
+/// Reads the title of the focused window of the frontmost application.
+pub(crate) struct TitleSource {
+    system: CfObject,
+    focused_application: CfObject,
+    focused_window: CfObject,
+    title: CfObject,
+}
+
+impl TitleSource {
+    pub(crate) fn new() -> Option<Self> {
+        Some(Self {
+            // SAFETY: returns a new owned element or null.
+            system: unsafe { CfObject::new(AXUIElementCreateSystemWide()) }?,
+            focused_application: CfObject::string("AXFocusedApplication")?,
+            focused_window: CfObject::string("AXFocusedWindow")?,
+            title: CfObject::string("AXTitle")?,
+        })
+    }
+
+    pub(crate) fn title(&self) -> Option<String> {
+        let application = self.system.attribute(&self.focused_application)?;
+        let window = application.attribute(&self.focused_window)?;
+        window.attribute(&self.title)?.string_value()
+    }
+}
+
+/// Reads whether the focused window is fullscreen: either in native
+/// fullscreen mode or covering a whole display, as borderless games do.
+pub(crate) struct FullscreenSource {
+    system: CfObject,
+    focused_application: CfObject,
+    focused_window: CfObject,
+    full_screen: CfObject,
+    position: CfObject,
+    size: CfObject,
+}
+
+impl FullscreenSource {
+    pub(crate) fn new() -> Option<Self> {
+        Some(Self {
+            // SAFETY: returns a new owned element or null.
+            system: unsafe { CfObject::new(AXUIElementCreateSystemWide()) }?,
+            focused_application: CfObject::string("AXFocusedApplication")?,
+            focused_window: CfObject::string("AXFocusedWindow")?,
+            full_screen: CfObject::string("AXFullScreen")?,
+            position: CfObject::string("AXPosition")?,
+            size: CfObject::string("AXSize")?,
+        })
+    }
+
+    pub(crate) fn is_fullscreen(&self) -> Option<bool> {
+        let Some(application) = self.system.attribute(&self.focused_application)
+        else {
+            return Some(false);
+        };
+        let Some(window) = application.attribute(&self.focused_window) else {
+            return Some(false);
+        };
+        if let Some(true) = window
+            .attribute(&self.full_screen)
+            .and_then(|value| value.bool_value())
+        {
+            return Some(true);
+        }
+        let origin: CGPoint = window
+            .attribute(&self.position)?
+            .ax_value(AX_VALUE_CG_POINT_TYPE)?;
+        let size: CGSize = window
+            .attribute(&self.size)?
+            .ax_value(AX_VALUE_CG_SIZE_TYPE)?;
+        let frame = CGRect { origin, size };
+        Some(display_bounds().contains(&frame))
+    }
+}
+
+/// Reads whether the screen is locked: the session shows the lock screen,
+/// or another user's session is on the console.
+pub(crate) struct LockSource {
+    screen_locked: CfObject,
+    on_console: CfObject,
+}
+
+impl LockSource {
+    pub(crate) fn new() -> Option<Self> {
+        Some(Self {
+            screen_locked: CfObject::string("CGSSessionScreenIsLocked")?,
+            on_console: CfObject::string("kCGSSessionOnConsoleKey")?,
+        })
+    }
+
+    /// `None` without a window server session, e.g. over SSH.
+    pub(crate) fn is_locked(&self) -> Option<bool> {
+        // SAFETY: returns a new owned dictionary or null.
+        let session = unsafe { CfObject::new(CGSessionCopyCurrentDictionary()) }?;
+        let flag = |key: &CfObject| {
+            // SAFETY: the dictionary is alive, the value is borrowed from it
+            // and checked to be a CFBoolean.
+            unsafe {
+                let value = CFDictionaryGetValue(session.as_ptr(), key.as_ptr());
+                if value.is_null() || CFGetTypeID(value) != CFBooleanGetTypeID() {
+                    return None;
+                }
+                Some(CFBooleanGetValue(value) != 0)
+            }
+        };
+        let locked = flag(&self.screen_locked).unwrap_or(false);
+        let on_console = flag(&self.on_console).unwrap_or(true);
+        Some(locked || !on_console)
+    }
+}
+
+/// Bounds of the active displays in global coordinates.
+fn display_bounds() -> Vec<CGRect> {
+    let mut displays = [0u32; MAX_DISPLAYS];
+    let mut count = 0u32;
+    // SAFETY: the buffer holds MAX_DISPLAYS ids, count is a valid out pointer.
+    let error = unsafe {
+        CGGetActiveDisplayList(
+            MAX_DISPLAYS as u32,
+            displays.as_mut_ptr(),
+            &mut count,
+        )
+    };
+    if error != 0 {
+        return Vec::new();
+    }
+    displays[..count as usize]
+        .iter()
+        // SAFETY: the id comes from the active display list.
+        .map(|display| unsafe { CGDisplayBounds(*display) })
+        .collect()
+}
@@ -0,0 +1,112 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How often the active tab is queried while a browser is frontmost.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// AppleScript expression returning the active tab URL, by browser bundle id.
+#[cfg(target_os = "macos")]
+const TAB_URL_SCRIPTS: &[(&str, &str)] = &[
+    ("com.apple.Safari", "URL of front document"),
+    ("com.apple.SafariTechnologyPreview", "URL of front document"),
+    ("com.google.Chrome", "URL of active tab of front window"),
+    ("com.brave.Browser", "URL of active tab of front window"),
+    ("com.microsoft.edgemac", "URL of active tab of front window"),
+    ("com.vivaldi.Vivaldi", "URL of active tab of front window"),
+    (
+        "company.thebrowser.Browser",
+        "URL of active tab of front window",
+    ),
+];
+
+/// Starts tracking the host of the active browser tab on a background thread.
+///
+/// Send the bundle id of every activated app to the returned sender. The
+/// receiver gets the host each time it changes, an empty string when the
+/// frontmost app is not a supported browser. The thread stops when the
+/// sender is dropped.
+pub fn spawn_browser_monitor() -> (mpsc::Sender<String>, mpsc::Receiver<String>) {
+    let (apps_tx, apps_rx) = mpsc::channel::<String>();
+    let (hosts_tx, hosts_rx) = mpsc::channel();
+
+    let _ = std::thread::Builder::new()
+        .name("browser-host".into())
+        .spawn(move || {
+            let mut app: Option<String> = None;
+            let mut last_host = String::new();
+            loop {
+                match apps_rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(next) => app = Some(next),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+                let host = app
+                    .as_deref()
+                    .and_then(tab_url)
+                    .and_then(|url| host_of(&url))
+                    .unwrap_or_default();
+                if host != last_host {
+                    if hosts_tx.send(host.clone()).is_err() {
+                        return;
+                    }
+                    last_host = host;
+                }
+            }
+        });
+
+    (apps_tx, hosts_rx)
+}
+
+/// Queries the active tab URL of a supported browser via AppleScript.
+#[cfg(target_os = "macos")]
+fn tab_url(bundle_id: &str) -> Option<String> {
+    let (_, expression) = TAB_URL_SCRIPTS.iter().find(|(id, _)| *id == bundle_id)?;
+    let script = format!("tell application id \"{bundle_id}\" to get {expression}");
+    let output = std::process::Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!url.is_empty()).then_some(url)
+}
+
+/// Tab URLs are only readable through AppleScript.
+#[cfg(not(target_os = "macos"))]
+fn tab_url(_bundle_id: &str) -> Option<String> {
+    None
+}
+
+/// Extracts the lowercase host of a URL, without user info and port.
+fn host_of(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::host_of;
+
+    #[test]
+    fn host_of_strips_everything_but_host() {
+        assert_eq!(
+            host_of("https://user@Gist.GitHub.com:443/path?q=1").as_deref(),
+            Some("gist.github.com")
+        );
+        assert_eq!(
+            host_of("http://localhost#anchor").as_deref(),
+            Some("localhost")
+        );
+    }
+
+    #[test]
+    fn host_of_rejects_urls_without_host() {
+        assert_eq!(host_of("about:blank"), None);
+        assert_eq!(host_of("file:///Users/me"), None);
+    }
+}
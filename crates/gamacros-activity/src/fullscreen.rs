@@ -0,0 +1,49 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crate::ax::FullscreenSource;
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::x11::FullscreenSource;
+
+/// How often the focused window is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Starts polling whether the focused window is fullscreen on a background
+/// thread.
+///
+/// The receiver gets the state each time it changes. The thread stops when
+/// a stop signal is sent or the receiver is dropped. If windows are not
+/// readable, the receiver is closed.
+pub fn spawn_fullscreen_monitor() -> (mpsc::Receiver<bool>, mpsc::Sender<()>) {
+    let (events_tx, events_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = mpsc::channel();
+
+    let _ = std::thread::Builder::new()
+        .name("fullscreen".into())
+        .spawn(move || {
+            let Some(source) = FullscreenSource::new() else {
+                return;
+            };
+            let mut last: Option<bool> = None;
+            loop {
+                // Keep the last state while the window is unreadable
+                if let Some(fullscreen) = source.is_fullscreen() {
+                    if last != Some(fullscreen) {
+                        if events_tx.send(fullscreen).is_err() {
+                            return;
+                        }
+                        last = Some(fullscreen);
+                    }
+                }
+                if let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stop_rx.recv_timeout(POLL_INTERVAL)
+                {
+                    continue;
+                }
+                return;
+            }
+        });
+
+    (events_rx, stop_tx)
+}
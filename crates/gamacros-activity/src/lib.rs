@@ -0,0 +1,66 @@
+//! Frontmost application monitoring.
+//!
+//! On macOS the monitor is backed by `NSWorkspace` notifications.
+//! On Linux the active X11 window is polled via `_NET_ACTIVE_WINDOW`
+//! and its `WM_CLASS` is reported in place of a bundle id.
+//!
+//! The focused window title is polled separately, see `spawn_title_monitor`,
+//! as is its fullscreen state, see `spawn_fullscreen_monitor`.
+//! On macOS the active tab host of supported browsers is tracked by
+//! `spawn_browser_monitor`. The monitor also reports when the screen locks
+//! and unlocks, see `Monitor::subscribe_screen_lock`.
+//!
+//! Running applications are listed and activated by `running_applications`
+//! and `activate_application`. `AppNames` resolves ids to display names.
+//! `ActivationFilter` debounces activations and drops transient apps such
+//! as Spotlight before they switch rules.
+
+#[cfg(target_os = "macos")]
+mod macos;
+
+#[cfg(target_os = "macos")]
+pub use macos::{ActivityError, Event, Events, Monitor, NotificationListener};
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use x11::{ActivityError, Event, Monitor, NotificationListener};
+
+#[cfg(target_os = "macos")]
+mod ax;
+
+#[cfg(unix)]
+mod title;
+
+#[cfg(unix)]
+pub use title::spawn_title_monitor;
+
+#[cfg(unix)]
+mod fullscreen;
+
+#[cfg(unix)]
+pub use fullscreen::spawn_fullscreen_monitor;
+
+#[cfg(unix)]
+mod browser;
+
+#[cfg(unix)]
+pub use browser::spawn_browser_monitor;
+
+#[cfg(unix)]
+mod apps;
+
+#[cfg(unix)]
+pub use apps::{
+    activate_application, application_pid, next_application, running_applications,
+    RunningApp,
+};
+
+mod names;
+
+pub use names::AppNames;
+
+mod activation;
+
+pub use activation::{ActivationFilter, TRANSIENT_APPS};
@@ -0,0 +1,202 @@
+//! `NSWorkspace` app notifications and the screen lock notifications of the
+//! distributed notification center, as one stream of events.
+
+use std::ffi::c_void;
+use std::sync::mpsc;
+
+use gamacros_cf::{CFStringRef, CFTypeRef, CfObject};
+pub use nsworkspace::{NSWorkspaceError as ActivityError, NotificationListener};
+
+use crate::ax::LockSource;
+
+const SCREEN_LOCKED: &str = "com.apple.screenIsLocked";
+const SCREEN_UNLOCKED: &str = "com.apple.screenIsUnlocked";
+/// `CFNotificationSuspensionBehaviorDeliverImmediately`
+const DELIVER_IMMEDIATELY: isize = 4;
+
+type NotificationCallback = extern "C" fn(
+    center: CFTypeRef,
+    observer: *mut c_void,
+    name: CFStringRef,
+    object: CFTypeRef,
+    user_info: CFTypeRef,
+);
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFNotificationCenterGetDistributedCenter() -> CFTypeRef;
+    fn CFNotificationCenterAddObserver(
+        center: CFTypeRef,
+        observer: *const c_void,
+        callback: NotificationCallback,
+        name: CFStringRef,
+        object: CFTypeRef,
+        suspension_behavior: isize,
+    );
+    fn CFNotificationCenterRemoveEveryObserver(
+        center: CFTypeRef,
+        observer: *const c_void,
+    );
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    // Activation/focus
+    DidActivateApplication(String),
+    DidDeactivateApplication(String),
+
+    // Session
+    ScreenLocked,
+    ScreenUnlocked,
+
+    Error(ActivityError),
+}
+
+impl Event {
+    /// Other `NSWorkspace` notifications are never subscribed to.
+    fn from_workspace(event: nsworkspace::Event) -> Option<Self> {
+        match event {
+            nsworkspace::Event::DidActivateApplication(id) => {
+                Some(Event::DidActivateApplication(id))
+            }
+            nsworkspace::Event::DidDeactivateApplication(id) => {
+                Some(Event::DidDeactivateApplication(id))
+            }
+            nsworkspace::Event::Error(error) => Some(Event::Error(error)),
+            _ => None,
+        }
+    }
+}
+
+/// Receiver of the events of a `Monitor`.
+pub struct Events {
+    workspace: mpsc::Receiver<nsworkspace::Event>,
+    session: mpsc::Receiver<Event>,
+}
+
+impl Events {
+    /// Returns a pending event without blocking, screen lock changes first.
+    pub fn try_recv(&self) -> Result<Event, mpsc::TryRecvError> {
+        if let Ok(event) = self.session.try_recv() {
+            return Ok(event);
+        }
+        loop {
+            if let Some(event) = Event::from_workspace(self.workspace.try_recv()?) {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/// Frontmost application and screen lock monitor. Notifications are
+/// delivered to the main thread while `run` runs its run loop.
+pub struct Monitor {
+    workspace: nsworkspace::Monitor,
+    /// Observer of the distributed notification center, owned by the monitor.
+    session: *mut mpsc::Sender<Event>,
+}
+
+impl Monitor {
+    /// Creates a new `Monitor` instance. Must be called on the main thread.
+    pub fn new() -> Option<(Self, Events, mpsc::Sender<()>)> {
+        let (workspace, workspace_rx, stop_tx) = nsworkspace::Monitor::new()?;
+        let (session_tx, session_rx) = mpsc::channel();
+        let monitor = Self {
+            workspace,
+            session: Box::into_raw(Box::new(session_tx)),
+        };
+        let events = Events {
+            workspace: workspace_rx,
+            session: session_rx,
+        };
+        Some((monitor, events, stop_tx))
+    }
+
+    /// Runs the main run loop until a stop signal is received.
+    pub fn run(&self) {
+        self.workspace.run();
+    }
+
+    /// Returns the bundle identifier of the currently active application.
+    pub fn get_active_application(&self) -> Option<String> {
+        self.workspace.get_active_application()
+    }
+
+    /// Subscribes to the given notification listeners.
+    pub fn subscribe(&self, listeners: NotificationListener) {
+        self.workspace.subscribe(listeners);
+    }
+
+    /// Subscribes to `ScreenLocked` and `ScreenUnlocked`, starting with
+    /// `ScreenLocked` if the screen is locked already.
+    pub fn subscribe_screen_lock(&self) {
+        let (Some(locked), Some(unlocked)) = (
+            CfObject::string(SCREEN_LOCKED),
+            CfObject::string(SCREEN_UNLOCKED),
+        ) else {
+            return;
+        };
+        // SAFETY: the observer outlives the subscription, it is removed
+        // before the sender is freed. The center copies the names.
+        unsafe {
+            let center = CFNotificationCenterGetDistributedCenter();
+            for (name, callback) in [
+                (&locked, on_screen_locked as NotificationCallback),
+                (&unlocked, on_screen_unlocked),
+            ] {
+                CFNotificationCenterAddObserver(
+                    center,
+                    self.session.cast_const().cast(),
+                    callback,
+                    name.as_ptr(),
+                    std::ptr::null(),
+                    DELIVER_IMMEDIATELY,
+                );
+            }
+        }
+        if LockSource::new().and_then(|source| source.is_locked()) == Some(true) {
+            // SAFETY: the sender is alive as long as the monitor.
+            let _ = unsafe { &*self.session }.send(Event::ScreenLocked);
+        }
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        // SAFETY: no callback runs after the observer is removed, so the
+        // sender is freed exactly once and no longer used.
+        unsafe {
+            CFNotificationCenterRemoveEveryObserver(
+                CFNotificationCenterGetDistributedCenter(),
+                self.session.cast_const().cast(),
+            );
+            drop(Box::from_raw(self.session));
+        }
+    }
+}
+
+extern "C" fn on_screen_locked(
+    _center: CFTypeRef,
+    observer: *mut c_void,
+    _name: CFStringRef,
+    _object: CFTypeRef,
+    _user_info: CFTypeRef,
+) {
+    send(observer, Event::ScreenLocked);
+}
+
+extern "C" fn on_screen_unlocked(
+    _center: CFTypeRef,
+    observer: *mut c_void,
+    _name: CFStringRef,
+    _object: CFTypeRef,
+    _user_info: CFTypeRef,
+) {
+    send(observer, Event::ScreenUnlocked);
+}
+
+fn send(observer: *mut c_void, event: Event) {
+    // SAFETY: the observer is the sender of a live `Monitor`.
+    let sender = unsafe { &*observer.cast::<mpsc::Sender<Event>>() };
+    let _ = sender.send(event);
+}
@@ -0,0 +1,86 @@
+use ahash::AHashMap;
+
+/// Resolves application ids to localized names, so logs can show
+/// `Safari (com.apple.Safari)` instead of a raw bundle id.
+///
+/// Results, including unknown ids, are cached, so `NSWorkspace` is asked
+/// once per application. Linux ids are window classes and have no names.
+#[derive(Debug, Default)]
+pub struct AppNames {
+    cache: AHashMap<String, Option<String>>,
+}
+
+impl AppNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Localized name of application `id`, `None` when it is unknown.
+    pub fn name(&mut self, id: &str) -> Option<&str> {
+        if !self.cache.contains_key(id) {
+            self.cache.insert(id.to_string(), lookup(id));
+        }
+        self.cache[id].as_deref()
+    }
+
+    /// `Name (id)`, or the bare id when the name is unknown.
+    pub fn describe(&mut self, id: &str) -> String {
+        match self.name(id) {
+            Some(name) if name != id => format!("{name} ({id})"),
+            _ => id.to_string(),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn lookup(id: &str) -> Option<String> {
+    use objc2_app_kit::{NSRunningApplication, NSWorkspace};
+    use objc2_foundation::{NSFileManager, NSString};
+
+    let bundle_id = NSString::from_str(id);
+    // SAFETY: the returned objects are retained while they are messaged.
+    unsafe {
+        let running = NSRunningApplication::runningApplicationsWithBundleIdentifier(
+            &bundle_id,
+        );
+        if let Some(name) = running.firstObject().and_then(|app| app.localizedName())
+        {
+            return Some(name.to_string());
+        }
+        // Not running, use the display name of the installed bundle
+        let url = NSWorkspace::sharedWorkspace()
+            .URLForApplicationWithBundleIdentifier(&bundle_id)?;
+        let name = NSFileManager::defaultManager()
+            .displayNameAtPath(&url.path()?)
+            .to_string();
+        Some(
+            name.strip_suffix(".app")
+                .map(str::to_string)
+                .unwrap_or(name),
+        )
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn lookup(_id: &str) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_with_cached_names() {
+        let mut names = AppNames::new();
+        names
+            .cache
+            .insert("com.apple.Safari".to_string(), Some("Safari".to_string()));
+        names.cache.insert("code".to_string(), None);
+        assert_eq!(
+            names.describe("com.apple.Safari"),
+            "Safari (com.apple.Safari)"
+        );
+        assert_eq!(names.describe("code"), "code");
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use crate::ax::TitleSource;
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::x11::TitleSource;
+
+/// How often the focused window title is polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Starts polling the title of the focused window on a background thread.
+///
+/// The receiver gets the title each time it changes, an empty string when
+/// no window is focused. The thread stops when a stop signal is sent or the
+/// receiver is dropped. If titles are not readable, the receiver is closed.
+pub fn spawn_title_monitor() -> (mpsc::Receiver<String>, mpsc::Sender<()>) {
+    let (events_tx, events_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = mpsc::channel();
+
+    let _ = std::thread::Builder::new()
+        .name("window-title".into())
+        .spawn(move || {
+            let Some(source) = TitleSource::new() else {
+                return;
+            };
+            let mut last: Option<String> = None;
+            loop {
+                let title = source.title().unwrap_or_default();
+                if last.as_deref() != Some(title.as_str()) {
+                    if events_tx.send(title.clone()).is_err() {
+                        return;
+                    }
+                    last = Some(title);
+                }
+                if let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stop_rx.recv_timeout(POLL_INTERVAL)
+                {
+                    continue;
+                }
+                return;
+            }
+        });
+
+    (events_rx, stop_tx)
+}
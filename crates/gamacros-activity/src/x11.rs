@@ -0,0 +1,447 @@
+use std::cell::{Cell, RefCell};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use thiserror::Error;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+use crate::RunningApp;
+
+/// How often the active window is polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Maximum `WM_CLASS` length in 32-bit units.
+const WM_CLASS_MAX_LEN: u32 = 256;
+
+/// Maximum window title length in 32-bit units.
+const WM_NAME_MAX_LEN: u32 = 1024;
+
+/// Maximum number of `_NET_WM_STATE` atoms read.
+const WM_STATE_MAX_LEN: u32 = 32;
+
+/// Maximum number of `_NET_CLIENT_LIST` windows read.
+const CLIENT_LIST_MAX_LEN: u32 = 1024;
+
+/// `_NET_ACTIVE_WINDOW` source indication of pagers and taskbars, which
+/// window managers honour without focus stealing prevention.
+const SOURCE_PAGER: u32 = 2;
+
+#[derive(Error, Clone, Debug)]
+pub enum ActivityError {
+    #[error("failed to connect to X server: {0}")]
+    Connect(String),
+    #[error("X11 request failed: {0}")]
+    Request(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    // Activation/focus
+    DidActivateApplication(String),
+    DidDeactivateApplication(String),
+
+    // Session, never reported under X11
+    ScreenLocked,
+    ScreenUnlocked,
+
+    Error(ActivityError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationListener {
+    DidActivateApplication,
+    DidDeactivateApplication,
+}
+
+impl NotificationListener {
+    const fn bit(self) -> u8 {
+        match self {
+            NotificationListener::DidActivateApplication => 1 << 0,
+            NotificationListener::DidDeactivateApplication => 1 << 1,
+        }
+    }
+}
+
+struct Display {
+    conn: RustConnection,
+    root: Window,
+    net_active_window: Atom,
+    net_client_list: Atom,
+    net_wm_name: Atom,
+    net_wm_state: Atom,
+    net_wm_state_fullscreen: Atom,
+    utf8_string: Atom,
+}
+
+impl Display {
+    fn connect() -> Result<Self, ActivityError> {
+        let (conn, screen) = x11rb::connect(None)
+            .map_err(|e| ActivityError::Connect(e.to_string()))?;
+        let root = conn.setup().roots[screen].root;
+        let intern = |name: &[u8]| -> Result<Atom, ActivityError> {
+            Ok(conn
+                .intern_atom(false, name)
+                .map_err(|e| ActivityError::Request(e.to_string()))?
+                .reply()
+                .map_err(|e| ActivityError::Request(e.to_string()))?
+                .atom)
+        };
+        let net_active_window = intern(b"_NET_ACTIVE_WINDOW")?;
+        let net_client_list = intern(b"_NET_CLIENT_LIST")?;
+        let net_wm_name = intern(b"_NET_WM_NAME")?;
+        let net_wm_state = intern(b"_NET_WM_STATE")?;
+        let net_wm_state_fullscreen = intern(b"_NET_WM_STATE_FULLSCREEN")?;
+        let utf8_string = intern(b"UTF8_STRING")?;
+
+        Ok(Self {
+            conn,
+            root,
+            net_active_window,
+            net_client_list,
+            net_wm_name,
+            net_wm_state,
+            net_wm_state_fullscreen,
+            utf8_string,
+        })
+    }
+
+    fn active_window(&self) -> Result<Option<Window>, ActivityError> {
+        let active = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.net_active_window,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )
+            .map_err(|e| ActivityError::Request(e.to_string()))?
+            .reply()
+            .map_err(|e| ActivityError::Request(e.to_string()))?;
+        let Some(window) = active.value32().and_then(|mut v| v.next()) else {
+            return Ok(None);
+        };
+        if window == x11rb::NONE {
+            return Ok(None);
+        }
+        Ok(Some(window))
+    }
+
+    /// Returns the class part of the active window's `WM_CLASS`.
+    fn active_application(&self) -> Result<Option<String>, ActivityError> {
+        let Some(window) = self.active_window()? else {
+            return Ok(None);
+        };
+        self.window_class(window)
+    }
+
+    /// Returns the class part of a window's `WM_CLASS`.
+    fn window_class(&self, window: Window) -> Result<Option<String>, ActivityError> {
+        let class = self
+            .conn
+            .get_property(
+                false,
+                window,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                0,
+                WM_CLASS_MAX_LEN,
+            )
+            .map_err(|e| ActivityError::Request(e.to_string()))?
+            .reply()
+            .map_err(|e| ActivityError::Request(e.to_string()))?;
+
+        Ok(parse_wm_class(&class.value))
+    }
+
+    /// Returns the active window's `_NET_WM_NAME`, falling back to `WM_NAME`.
+    fn active_title(&self) -> Result<Option<String>, ActivityError> {
+        let Some(window) = self.active_window()? else {
+            return Ok(None);
+        };
+        for (property, kind) in [
+            (self.net_wm_name, self.utf8_string),
+            (AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()),
+        ] {
+            let name = self
+                .conn
+                .get_property(false, window, property, kind, 0, WM_NAME_MAX_LEN)
+                .map_err(|e| ActivityError::Request(e.to_string()))?
+                .reply()
+                .map_err(|e| ActivityError::Request(e.to_string()))?;
+            if !name.value.is_empty() {
+                return Ok(Some(String::from_utf8_lossy(&name.value).into_owned()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Managed top-level windows in `_NET_CLIENT_LIST`, oldest first.
+    fn clients(&self) -> Result<Vec<Window>, ActivityError> {
+        let clients = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.net_client_list,
+                AtomEnum::WINDOW,
+                0,
+                CLIENT_LIST_MAX_LEN,
+            )
+            .map_err(|e| ActivityError::Request(e.to_string()))?
+            .reply()
+            .map_err(|e| ActivityError::Request(e.to_string()))?;
+        Ok(clients
+            .value32()
+            .map(|windows| windows.collect())
+            .unwrap_or_default())
+    }
+
+    /// Asks the window manager to activate `window`.
+    fn activate(&self, window: Window) -> Result<(), ActivityError> {
+        let event = ClientMessageEvent::new(
+            32,
+            window,
+            self.net_active_window,
+            [SOURCE_PAGER, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(|e| ActivityError::Request(e.to_string()))?;
+        self.conn
+            .flush()
+            .map_err(|e| ActivityError::Request(e.to_string()))
+    }
+
+    /// Whether `_NET_WM_STATE` of the active window includes fullscreen.
+    fn active_fullscreen(&self) -> Result<bool, ActivityError> {
+        let Some(window) = self.active_window()? else {
+            return Ok(false);
+        };
+        let state = self
+            .conn
+            .get_property(
+                false,
+                window,
+                self.net_wm_state,
+                AtomEnum::ATOM,
+                0,
+                WM_STATE_MAX_LEN,
+            )
+            .map_err(|e| ActivityError::Request(e.to_string()))?
+            .reply()
+            .map_err(|e| ActivityError::Request(e.to_string()))?;
+        Ok(state.value32().is_some_and(|mut atoms| {
+            atoms.any(|a| a == self.net_wm_state_fullscreen)
+        }))
+    }
+}
+
+/// Reads the fullscreen state of the focused X11 window.
+pub(crate) struct FullscreenSource {
+    display: Display,
+}
+
+impl FullscreenSource {
+    pub(crate) fn new() -> Option<Self> {
+        Display::connect().ok().map(|display| Self { display })
+    }
+
+    pub(crate) fn is_fullscreen(&self) -> Option<bool> {
+        self.display.active_fullscreen().ok()
+    }
+}
+
+/// Reads the title of the focused X11 window.
+pub(crate) struct TitleSource {
+    display: Display,
+}
+
+impl TitleSource {
+    pub(crate) fn new() -> Option<Self> {
+        Display::connect().ok().map(|display| Self { display })
+    }
+
+    pub(crate) fn title(&self) -> Option<String> {
+        self.display.active_title().ok().flatten()
+    }
+}
+
+/// Applications of the managed windows, each once, ordered by their first
+/// window.
+pub(crate) fn running_applications() -> Vec<RunningApp> {
+    let Ok(display) = Display::connect() else {
+        return Vec::new();
+    };
+    let active = display.active_application().ok().flatten();
+    let mut apps: Vec<RunningApp> = Vec::new();
+    for window in display.clients().unwrap_or_default() {
+        let Ok(Some(id)) = display.window_class(window) else {
+            continue;
+        };
+        if apps.iter().all(|app| app.id != id) {
+            apps.push(RunningApp {
+                active: active.as_deref() == Some(id.as_str()),
+                id,
+            });
+        }
+    }
+    apps
+}
+
+/// Activates the first managed window of application `id`.
+pub(crate) fn activate_application(id: &str) -> bool {
+    let Ok(display) = Display::connect() else {
+        return false;
+    };
+    let window = display
+        .clients()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|window| {
+            display.window_class(*window).ok().flatten().as_deref() == Some(id)
+        });
+    window.is_some_and(|window| display.activate(window).is_ok())
+}
+
+/// `WM_CLASS` is two NUL-terminated strings: instance and class.
+/// The class is the stable application name, so prefer it.
+fn parse_wm_class(raw: &[u8]) -> Option<String> {
+    let mut parts = raw.split(|b| *b == 0).filter(|p| !p.is_empty());
+    let instance = parts.next();
+    let class = parts.next().or(instance)?;
+    Some(String::from_utf8_lossy(class).into_owned())
+}
+
+/// X11 monitor that polls `_NET_ACTIVE_WINDOW` and reports focus changes.
+///
+/// Under Wayland only XWayland clients are visible to this monitor.
+pub struct Monitor {
+    display: Option<Display>,
+    events: mpsc::Sender<Event>,
+    stop_rx: mpsc::Receiver<()>,
+    listeners: Cell<u8>,
+    active: RefCell<Option<String>>,
+}
+
+impl Monitor {
+    /// Creates a new `Monitor` instance.
+    /// If the X server is unreachable, the monitor still runs but reports an error
+    /// event and never detects application changes.
+    pub fn new() -> Option<(Self, mpsc::Receiver<Event>, mpsc::Sender<()>)> {
+        let (events_tx, events_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel();
+
+        let display = match Display::connect() {
+            Ok(display) => Some(display),
+            Err(e) => {
+                let _ = events_tx.send(Event::Error(e));
+                None
+            }
+        };
+
+        let monitor = Self {
+            display,
+            events: events_tx,
+            stop_rx,
+            listeners: Cell::new(0),
+            active: RefCell::new(None),
+        };
+
+        Some((monitor, events_rx, stop_tx))
+    }
+
+    /// Polls the active window until a stop signal is received.
+    pub fn run(&self) {
+        *self.active.borrow_mut() = self.get_active_application();
+        while let Err(mpsc::RecvTimeoutError::Timeout) =
+            self.stop_rx.recv_timeout(POLL_INTERVAL)
+        {
+            self.poll();
+        }
+    }
+
+    /// Returns the class of the currently active application.
+    pub fn get_active_application(&self) -> Option<String> {
+        self.display.as_ref()?.active_application().ok().flatten()
+    }
+
+    /// Subscribes to the given notification listener.
+    pub fn subscribe(&self, listener: NotificationListener) {
+        self.listeners.set(self.listeners.get() | listener.bit());
+    }
+
+    /// The lock state is not read under X11, so this is a no-op.
+    pub fn subscribe_screen_lock(&self) {}
+
+    fn poll(&self) {
+        let Some(display) = self.display.as_ref() else {
+            return;
+        };
+        let current = match display.active_application() {
+            Ok(current) => current,
+            Err(e) => {
+                let _ = self.events.send(Event::Error(e));
+                return;
+            }
+        };
+        // Focus moved to the desktop or a window without class.
+        let Some(current) = current else {
+            return;
+        };
+
+        let previous = self.active.replace(Some(current.clone()));
+        if previous.as_deref() == Some(current.as_str()) {
+            return;
+        }
+        if let Some(previous) = previous {
+            self.send(
+                NotificationListener::DidDeactivateApplication,
+                Event::DidDeactivateApplication(previous),
+            );
+        }
+        self.send(
+            NotificationListener::DidActivateApplication,
+            Event::DidActivateApplication(current),
+        );
+    }
+
+    fn send(&self, listener: NotificationListener, event: Event) {
+        if self.listeners.get() & listener.bit() != 0 {
+            let _ = self.events.send(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_wm_class;
+
+    #[test]
+    fn wm_class_prefers_class_over_instance() {
+        assert_eq!(
+            parse_wm_class(b"navigator\0firefox\0").as_deref(),
+            Some("firefox")
+        );
+    }
+
+    #[test]
+    fn wm_class_falls_back_to_instance() {
+        assert_eq!(parse_wm_class(b"xterm\0").as_deref(), Some("xterm"));
+    }
+
+    #[test]
+    fn wm_class_empty_is_none() {
+        assert_eq!(parse_wm_class(b""), None);
+    }
+}
@@ -1,4 +1,5 @@
 use gamacros_bit_derive::Bit;
+use gamacros_bit_mask::Bitmask;
 
 #[derive(Bit, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Button {
@@ -8,9 +9,28 @@ enum Button {
     Y,
 }
 
+// The derive imports `Bitable`, so a module holds one derived enum.
+mod leds {
+    /// Explicit discriminants choose the bit, later variants continue from them.
+    #[derive(gamacros_bit_derive::Bit, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Led {
+        Power = 0,
+        Player1 = 4,
+        Player2,
+    }
+}
+
 fn main() {
     assert_eq!(Button::A.bit(), 1u64 << 0);
     assert_eq!(Button::B.bit(), 1u64 << 1);
     assert_eq!(Button::X.bit(), 1u64 << 2);
     assert_eq!(Button::Y.bit(), 1u64 << 3);
+    assert_eq!(Button::ALL, &[Button::A, Button::B, Button::X, Button::Y]);
+    assert_eq!(Button::from_index(2), Some(Button::X));
+
+    use leds::Led;
+    assert_eq!(Led::Player1.index(), 4);
+    assert_eq!(Led::Player2.bit(), 1u64 << 5);
+    let lit = Bitmask::new(&[Led::Player2, Led::Power]);
+    assert_eq!(lit.iter().collect::<Vec<_>>(), [Led::Power, Led::Player2]);
 }
@@ -1,42 +1,135 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Data, Fields};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, Lit};
+
+/// Number of bits in the mask backing a `Bitmask`.
+const MAX_BITS: u64 = 64;
 
 pub fn handle_derive_bit(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
+    match expand(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
 
-    // Collect variant idents in declared order
-    let variants: Vec<syn::Ident> = match input.data {
-        Data::Enum(e) => e
-            .variants
-            .into_iter()
-            .map(|v| match v.fields {
-                Fields::Unit => v.ident,
-                _ => panic!("Bit supports only fieldless enum variants"),
-            })
-            .collect(),
-        _ => panic!("Bit can be derived only for enums"),
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = input.ident;
+    let Data::Enum(data) = input.data else {
+        return Err(syn::Error::new_spanned(
+            name,
+            "Bit can be derived only for enums",
+        ));
     };
 
-    // Assign discriminants implicitly by index and generate a bit() method
-    let arms = variants.iter().enumerate().map(|(i, v)| {
-        let idx = i as u64;
-        quote! { #name::#v => 1u64 << #idx }
+    // Bit indices follow discriminants: explicit `= n` values are used as is,
+    // other variants continue from the previous one like Rust does.
+    let mut variants: Vec<(syn::Ident, u64)> = Vec::new();
+    let mut next = 0u64;
+    for variant in data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Bit supports only fieldless enum variants",
+            ));
+        }
+        let index = match &variant.discriminant {
+            Some((_, expr)) => discriminant(expr)?,
+            None => next,
+        };
+        if index >= MAX_BITS {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                format!("Bit index {index} does not fit into a 64-bit mask"),
+            ));
+        }
+        if let Some((other, _)) = variants.iter().find(|(_, i)| *i == index) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                format!("Bit index {index} is already used by {other}"),
+            ));
+        }
+        variants.push((variant.ident, index));
+        next = index + 1;
+    }
+
+    let bit_arms = variants.iter().map(|(v, i)| {
+        quote! { #name::#v => 1u64 << #i }
+    });
+    let index_arms = variants.iter().map(|(v, i)| {
+        let i = *i as u32;
+        quote! { #name::#v => #i }
     });
+    let from_index_arms = variants.iter().map(|(v, i)| {
+        let i = *i as u32;
+        quote! { #i => ::core::option::Option::Some(#name::#v) }
+    });
+    let all = variants.iter().map(|(v, _)| quote! { #name::#v });
 
-    let expanded = quote! {
+    Ok(quote! {
         use gamacros_bit_mask::Bitable;
         impl Bitable for #name {
+            const ALL: &'static [Self] = &[ #( #all, )* ];
+
             #[inline]
             fn bit(&self) -> u64 {
-                match self { #( #arms, )* }
+                match self { #( #bit_arms, )* }
             }
 
             #[inline]
-            fn index(&self) -> u32 { self.bit().trailing_zeros() }
+            fn index(&self) -> u32 {
+                match self { #( #index_arms, )* }
+            }
+
+            #[inline]
+            fn from_index(index: u32) -> ::core::option::Option<Self> {
+                match index {
+                    #( #from_index_arms, )*
+                    _ => ::core::option::Option::None,
+                }
+            }
         }
-    };
+    })
+}
+
+/// Reads an explicit discriminant, which must be an integer literal.
+fn discriminant(expr: &Expr) -> syn::Result<u64> {
+    if let Expr::Lit(lit) = expr {
+        if let Lit::Int(int) = &lit.lit {
+            return int.base10_parse();
+        }
+    }
+    Err(syn::Error::new_spanned(
+        expr,
+        "Bit supports only integer literal discriminants",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: &str) -> String {
+        let input = syn::parse_str::<DeriveInput>(input).unwrap();
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn rejects_indices_outside_of_mask() {
+        assert_eq!(
+            error("enum E { A = 63, B }"),
+            "Bit index 64 does not fit into a 64-bit mask"
+        );
+        let variants: Vec<String> = (0..65).map(|i| format!("V{i}")).collect();
+        let input = format!("enum E {{ {} }}", variants.join(", "));
+        assert!(error(&input).contains("64-bit mask"));
+    }
 
-    TokenStream::from(expanded)
+    #[test]
+    fn rejects_duplicate_indices() {
+        assert_eq!(
+            error("enum E { A = 1, B = 1 }"),
+            "Bit index 1 is already used by A"
+        );
+    }
 }
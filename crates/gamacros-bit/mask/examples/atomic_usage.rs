@@ -12,6 +12,8 @@ enum Sensor {
 }
 
 impl Bitable for Sensor {
+    const ALL: &'static [Self] = &[Sensor::Temperature, Sensor::Motion];
+
     fn bit(&self) -> u64 {
         match self {
             Sensor::Temperature => 1 << 0,
@@ -25,6 +27,14 @@ impl Bitable for Sensor {
             Sensor::Motion => 3,
         }
     }
+
+    fn from_index(index: u32) -> Option<Self> {
+        match index {
+            0 => Some(Sensor::Temperature),
+            3 => Some(Sensor::Motion),
+            _ => None,
+        }
+    }
 }
 
 fn main() {
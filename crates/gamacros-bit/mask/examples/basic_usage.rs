@@ -10,6 +10,13 @@ enum Permission {
 }
 
 impl Bitable for Permission {
+    const ALL: &'static [Self] = &[
+        Permission::Read,
+        Permission::Write,
+        Permission::Execute,
+        Permission::Delete,
+    ];
+
     fn bit(&self) -> u64 {
         match self {
             Permission::Read => 1 << 0,
@@ -27,6 +34,10 @@ impl Bitable for Permission {
             Permission::Delete => 3,
         }
     }
+
+    fn from_index(index: u32) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
 }
 
 fn main() {
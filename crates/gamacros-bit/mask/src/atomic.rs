@@ -86,6 +86,9 @@ mod tests {
     }
 
     impl Bitable for TestFlag {
+        const ALL: &'static [Self] =
+            &[TestFlag::A, TestFlag::B, TestFlag::C, TestFlag::D];
+
         fn bit(&self) -> u64 {
             1u64 << (*self as u64)
         }
@@ -93,6 +96,10 @@ mod tests {
         fn index(&self) -> u32 {
             *self as u32
         }
+
+        fn from_index(index: u32) -> Option<Self> {
+            Self::ALL.get(index as usize).copied()
+        }
     }
 
     #[test]
@@ -3,6 +3,11 @@ use std::marker::PhantomData;
 use crate::Bitable;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Bitmask<T: Bitable>(pub u64, PhantomData<T>);
 
 impl<T: Bitable> Bitmask<T> {
@@ -70,6 +75,45 @@ impl<T: Bitable> Bitmask<T> {
     pub fn count(&self) -> u32 {
         self.0.count_ones()
     }
+
+    /// Iterate over the values in the bitmask, lowest bit first.
+    #[inline]
+    pub fn iter(&self) -> Iter<T> {
+        Iter(self.0, PhantomData)
+    }
+}
+
+impl<T: Bitable> IntoIterator for Bitmask<T> {
+    type Item = T;
+    type IntoIter = Iter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the values of a `Bitmask`.
+#[derive(Debug, Clone)]
+pub struct Iter<T: Bitable>(u64, PhantomData<T>);
+
+impl<T: Bitable> Iterator for Iter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while self.0 != 0 {
+            let index = self.0.trailing_zeros();
+            self.0 &= self.0 - 1;
+            // Bits without a value are skipped
+            if let Some(value) = T::from_index(index) {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.0.count_ones() as usize))
+    }
 }
 
 #[cfg(test)]
@@ -86,6 +130,9 @@ mod tests {
     }
 
     impl Bitable for TestFlag {
+        const ALL: &'static [Self] =
+            &[TestFlag::A, TestFlag::B, TestFlag::C, TestFlag::D];
+
         fn bit(&self) -> u64 {
             1u64 << (*self as u64)
         }
@@ -93,6 +140,10 @@ mod tests {
         fn index(&self) -> u32 {
             *self as u32
         }
+
+        fn from_index(index: u32) -> Option<Self> {
+            Self::ALL.get(index as usize).copied()
+        }
     }
 
     #[test]
@@ -149,6 +200,19 @@ mod tests {
         assert!(mask.is_empty());
     }
 
+    #[test]
+    fn iter_yields_values_in_bit_order() {
+        let mask = Bitmask::new(&[TestFlag::D, TestFlag::A, TestFlag::C]);
+        let values: Vec<_> = mask.iter().collect();
+        assert_eq!(values, [TestFlag::A, TestFlag::C, TestFlag::D]);
+        assert_eq!(Bitmask::<TestFlag>::empty().iter().count(), 0);
+        // Bits outside of the enum are skipped
+        assert_eq!(
+            Bitmask::<TestFlag>::from_value(1 << 40 | 1).iter().count(),
+            1
+        );
+    }
+
     #[test]
     fn is_subset_works() {
         let empty = Bitmask::<TestFlag>::empty();
@@ -1,10 +1,16 @@
 mod bitmask;
 mod atomic;
 
-pub use bitmask::Bitmask;
+pub use bitmask::{Bitmask, Iter};
 pub use atomic::AtomicBitmask;
 
-pub trait Bitable {
+pub trait Bitable: Sized + 'static {
+    /// Every value, in declaration order.
+    const ALL: &'static [Self];
+
     fn bit(&self) -> u64;
     fn index(&self) -> u32;
+
+    /// Returns the value with the given bit index.
+    fn from_index(index: u32) -> Option<Self>;
 }
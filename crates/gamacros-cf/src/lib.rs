@@ -0,0 +1,117 @@
+//! Owned Core Foundation objects, shared by the macOS backends.
+//!
+//! Empty on other platforms.
+
+#![cfg(target_os = "macos")]
+
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr::{self, NonNull};
+
+pub type CFTypeRef = *const c_void;
+pub type CFStringRef = CFTypeRef;
+
+const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFRelease(cf: CFTypeRef);
+    fn CFGetTypeID(cf: CFTypeRef) -> usize;
+    fn CFStringGetTypeID() -> usize;
+    fn CFBooleanGetTypeID() -> usize;
+    fn CFBooleanGetValue(boolean: CFTypeRef) -> u8;
+    fn CFStringCreateWithBytes(
+        alloc: CFTypeRef,
+        bytes: *const u8,
+        num_bytes: isize,
+        encoding: u32,
+        is_external_representation: u8,
+    ) -> CFStringRef;
+    fn CFStringGetLength(string: CFStringRef) -> isize;
+    fn CFStringGetMaximumSizeForEncoding(length: isize, encoding: u32) -> isize;
+    fn CFStringGetCString(
+        string: CFStringRef,
+        buffer: *mut c_char,
+        buffer_size: isize,
+        encoding: u32,
+    ) -> u8;
+}
+
+/// An owned Core Foundation object, released on drop.
+#[derive(Debug)]
+pub struct CfObject(NonNull<c_void>);
+
+impl CfObject {
+    /// Takes ownership of an object from a Create or Copy function, `None`
+    /// when it returned null.
+    ///
+    /// # Safety
+    ///
+    /// `raw` is null or an object the caller owns and no longer releases.
+    pub unsafe fn new(raw: CFTypeRef) -> Option<Self> {
+        NonNull::new(raw.cast_mut()).map(Self)
+    }
+
+    /// A new `CFString` with a copy of `value`.
+    pub fn string(value: &str) -> Option<Self> {
+        // SAFETY: the bytes are valid UTF-8 for the duration of the call, the
+        // created string is owned by us.
+        unsafe {
+            Self::new(CFStringCreateWithBytes(
+                ptr::null(),
+                value.as_ptr(),
+                value.len() as isize,
+                CF_STRING_ENCODING_UTF8,
+                0,
+            ))
+        }
+    }
+
+    /// The object, borrowed for as long as `self` is alive.
+    pub fn as_ptr(&self) -> CFTypeRef {
+        self.0.as_ptr().cast_const()
+    }
+
+    /// Contents of the object, `None` when it is not a `CFString`.
+    pub fn string_value(&self) -> Option<String> {
+        // SAFETY: the object is alive and is checked to be a CFString.
+        unsafe {
+            if CFGetTypeID(self.as_ptr()) != CFStringGetTypeID() {
+                return None;
+            }
+            let length = CFStringGetLength(self.as_ptr());
+            let size =
+                CFStringGetMaximumSizeForEncoding(length, CF_STRING_ENCODING_UTF8)
+                    + 1;
+            let mut buffer = vec![0u8; usize::try_from(size).ok()?];
+            if CFStringGetCString(
+                self.as_ptr(),
+                buffer.as_mut_ptr().cast(),
+                size,
+                CF_STRING_ENCODING_UTF8,
+            ) == 0
+            {
+                return None;
+            }
+            let value = CStr::from_bytes_until_nul(&buffer).ok()?;
+            Some(value.to_string_lossy().into_owned())
+        }
+    }
+
+    /// Value of the object, `None` when it is not a `CFBoolean`.
+    pub fn bool_value(&self) -> Option<bool> {
+        // SAFETY: the object is alive and is checked to be a CFBoolean.
+        unsafe {
+            if CFGetTypeID(self.as_ptr()) != CFBooleanGetTypeID() {
+                return None;
+            }
+            Some(CFBooleanGetValue(self.as_ptr()) != 0)
+        }
+    }
+}
+
+impl Drop for CfObject {
+    fn drop(&mut self) {
+        // SAFETY: the object is owned and released exactly once.
+        unsafe { CFRelease(self.as_ptr()) }
+    }
+}
@@ -1,4 +1,4 @@
-use gamacros_control::Performer;
+use gamacros_control::{Performer, ScrollUnit};
 
 fn parse_i32_opt(arg: Option<String>, name: &str) -> Option<i32> {
     arg.map(|v| match v.parse::<i32>() {
@@ -32,14 +32,14 @@ fn main() {
     };
 
     if x != 0 {
-        if let Err(err) = performer.scroll_x(x) {
+        if let Err(err) = performer.scroll_x(x, ScrollUnit::default()) {
             eprintln!("Failed to scroll horizontally by {x}: {err}");
             std::process::exit(1);
         }
     }
 
     if y != 0 {
-        if let Err(err) = performer.scroll_y(y) {
+        if let Err(err) = performer.scroll_y(y, ScrollUnit::default()) {
             eprintln!("Failed to scroll vertically by {y}: {err}");
             std::process::exit(1);
         }
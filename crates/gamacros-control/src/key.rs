@@ -1,7 +1,10 @@
+use std::fmt;
+
 use enigo::Key as EnigoKey;
+use serde::{Deserialize, Serialize};
 
 /// A key that can be emulated.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Key {
     Unicode(char),
     Control,
@@ -30,10 +33,14 @@ pub enum Key {
     VolumeUp,
     VolumeDown,
     VolumeMute,
+    MediaFastForward,
+    MediaRewind,
     #[cfg(target_os = "macos")]
     BrightnessUp,
     #[cfg(target_os = "macos")]
     BrightnessDown,
+    IlluminationUp,
+    IlluminationDown,
     F1,
     F2,
     F3,
@@ -54,6 +61,21 @@ pub enum Key {
     F18,
     F19,
     F20,
+    #[cfg(not(target_os = "macos"))]
+    F21,
+    #[cfg(not(target_os = "macos"))]
+    F22,
+    #[cfg(not(target_os = "macos"))]
+    F23,
+    #[cfg(not(target_os = "macos"))]
+    F24,
+
+    // JIS keyboards
+    Yen,
+    Ro,
+    Eisu,
+    Kana,
+    ContextMenu,
 
     Apostrophe,
     Semicolon,
@@ -63,65 +85,6 @@ pub enum Key {
     Other(u32),
 }
 
-pub(crate) fn key_code_for_key_string(ch: char) -> u16 {
-    match ch {
-        'a' => 0,
-        's' => 1,
-        'd' => 2,
-        'f' => 3,
-        'h' => 4,
-        'g' => 5,
-        'z' => 6,
-        'x' => 7,
-        'c' => 8,
-        'v' => 9,
-        'b' => 11,
-        'q' => 12,
-        'w' => 13,
-        'e' => 14,
-        'r' => 15,
-        'y' => 16,
-        't' => 17,
-        // '1' => 18,
-        // '2' => 19,
-        // '3' => 20,
-        // '4' => 21,
-        // '6' => 22,
-        // '5' => 23,
-        '=' => 24,
-        // '9' => 25,
-        // '7' => 26,
-        '-' => 27,
-        '8' => 28,
-        '0' => 29,
-        ']' => 30,
-        'o' => 31,
-        'u' => 32,
-        '[' => 33,
-        'i' => 34,
-        'p' => 35,
-        'l' => 37,
-        'j' => 38,
-        '\'' => 39,
-        'k' => 40,
-        ';' => 41,
-        '\\' => 42,
-        ',' => 43,
-        '/' => 44,
-        'n' => 45,
-        'm' => 46,
-        '.' => 47,
-        '*' => 67,
-        '+' => 69,
-        '`' => 50,
-        _ => {
-            unreachable!()
-            //eprintln!("keyString {} Not Found. Aborting...", key_string);
-            //std::process::exit(1);
-        }
-    }
-}
-
 impl From<Key> for EnigoKey {
     fn from(key: Key) -> Self {
         key.to_enigo()
@@ -135,6 +98,17 @@ impl From<&Key> for EnigoKey {
 }
 
 impl Key {
+    /// macOS virtual keycode of the key on the current layout, `None` for
+    /// characters that no key produces.
+    #[cfg(target_os = "macos")]
+    pub fn key_code(&self) -> Option<u16> {
+        match self.to_enigo() {
+            EnigoKey::Other(code) => u16::try_from(code).ok(),
+            EnigoKey::Unicode(_) => None,
+            key => u16::try_from(key).ok(),
+        }
+    }
+
     pub fn to_enigo(&self) -> EnigoKey {
         match self {
             Key::Control => EnigoKey::Control,
@@ -145,6 +119,9 @@ impl Key {
             Key::Shift => EnigoKey::Shift,
             Key::RShift => EnigoKey::RShift,
             Key::Alt => EnigoKey::Alt,
+            #[cfg(target_os = "macos")]
+            Key::RAlt => EnigoKey::ROption,
+            #[cfg(not(target_os = "macos"))]
             Key::RAlt => EnigoKey::Alt,
             Key::Home => EnigoKey::Home,
             Key::End => EnigoKey::End,
@@ -164,9 +141,27 @@ impl Key {
             Key::VolumeDown => EnigoKey::VolumeDown,
             Key::VolumeMute => EnigoKey::VolumeMute,
             #[cfg(target_os = "macos")]
+            Key::MediaFastForward => EnigoKey::MediaFast,
+            #[cfg(target_os = "macos")]
+            Key::MediaRewind => EnigoKey::MediaRewind,
+            // XF86AudioForward/Rewind keysyms
+            #[cfg(not(target_os = "macos"))]
+            Key::MediaFastForward => EnigoKey::Other(0x1008_FF97),
+            #[cfg(not(target_os = "macos"))]
+            Key::MediaRewind => EnigoKey::Other(0x1008_FF3E),
+            #[cfg(target_os = "macos")]
             Key::BrightnessUp => EnigoKey::BrightnessUp,
             #[cfg(target_os = "macos")]
             Key::BrightnessDown => EnigoKey::BrightnessDown,
+            #[cfg(target_os = "macos")]
+            Key::IlluminationUp => EnigoKey::IlluminationUp,
+            #[cfg(target_os = "macos")]
+            Key::IlluminationDown => EnigoKey::IlluminationDown,
+            // XF86KbdBrightnessUp/Down keysyms
+            #[cfg(not(target_os = "macos"))]
+            Key::IlluminationUp => EnigoKey::Other(0x1008_FF05),
+            #[cfg(not(target_os = "macos"))]
+            Key::IlluminationDown => EnigoKey::Other(0x1008_FF06),
             Key::F1 => EnigoKey::F1,
             Key::F2 => EnigoKey::F2,
             Key::F3 => EnigoKey::F3,
@@ -187,16 +182,211 @@ impl Key {
             Key::F18 => EnigoKey::F18,
             Key::F19 => EnigoKey::F19,
             Key::F20 => EnigoKey::F20,
-            Key::Unicode(ch) => EnigoKey::Other(key_code_for_key_string(*ch) as u32),
-            Key::Apostrophe => EnigoKey::Other(key_code_for_key_string('\'') as u32),
-            Key::Semicolon => EnigoKey::Other(key_code_for_key_string(';') as u32),
-            Key::Backslash => EnigoKey::Other(key_code_for_key_string('\\') as u32),
-            Key::Grave => EnigoKey::Other(key_code_for_key_string('`') as u32),
+            #[cfg(not(target_os = "macos"))]
+            Key::F21 => EnigoKey::F21,
+            #[cfg(not(target_os = "macos"))]
+            Key::F22 => EnigoKey::F22,
+            #[cfg(not(target_os = "macos"))]
+            Key::F23 => EnigoKey::F23,
+            #[cfg(not(target_os = "macos"))]
+            Key::F24 => EnigoKey::F24,
+            Key::Yen | Key::Ro | Key::Eisu | Key::Kana | Key::ContextMenu => {
+                international_key(*self)
+            }
+            Key::Unicode(ch) => unicode_key(*ch),
+            // ANSI positions, these keys are named after the US layout
+            Key::Apostrophe => EnigoKey::Other(0x27),
+            Key::Semicolon => EnigoKey::Other(0x29),
+            Key::Backslash => EnigoKey::Other(0x2A),
+            Key::Grave => EnigoKey::Other(0x32),
             Key::Other(code) => EnigoKey::Other(*code),
         }
     }
 }
 
+/// Writes the key as in profiles, e.g. `arrow_up`. Keys without a name
+/// are written as `raw:` codes.
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Key::Unicode(ch) => return write!(f, "{ch}"),
+            Key::Control => "ctrl",
+            Key::RControl => "rctrl",
+            Key::Meta => "cmd",
+            #[cfg(target_os = "macos")]
+            Key::RCommand => "rcmd",
+            Key::Shift => "shift",
+            Key::RShift => "rshift",
+            Key::Alt => "alt",
+            Key::RAlt => "ralt",
+            Key::Home => "home",
+            Key::End => "end",
+            Key::PageUp => "page_up",
+            Key::PageDown => "page_down",
+            Key::UpArrow => "arrow_up",
+            Key::DownArrow => "arrow_down",
+            Key::LeftArrow => "arrow_left",
+            Key::RightArrow => "arrow_right",
+            Key::Delete => "delete",
+            Key::Backspace => "backspace",
+            Key::Escape => "escape",
+            Key::Tab => "tab",
+            Key::Space => "space",
+            Key::Return => "enter",
+            Key::VolumeUp => "volume_up",
+            Key::VolumeDown => "volume_down",
+            Key::VolumeMute => "volume_mute",
+            Key::MediaFastForward => "media_forward",
+            Key::MediaRewind => "media_rewind",
+            #[cfg(target_os = "macos")]
+            Key::BrightnessUp => "brightness_up",
+            #[cfg(target_os = "macos")]
+            Key::BrightnessDown => "brightness_down",
+            Key::IlluminationUp => "illumination_up",
+            Key::IlluminationDown => "illumination_down",
+            Key::F1 => "f1",
+            Key::F2 => "f2",
+            Key::F3 => "f3",
+            Key::F4 => "f4",
+            Key::F5 => "f5",
+            Key::F6 => "f6",
+            Key::F7 => "f7",
+            Key::F8 => "f8",
+            Key::F9 => "f9",
+            Key::F10 => "f10",
+            Key::F11 => "f11",
+            Key::F12 => "f12",
+            Key::F13 => "f13",
+            Key::F14 => "f14",
+            Key::F15 => "f15",
+            Key::F16 => "f16",
+            Key::F17 => "f17",
+            Key::F18 => "f18",
+            Key::F19 => "f19",
+            Key::F20 => "f20",
+            #[cfg(not(target_os = "macos"))]
+            Key::F21 => "f21",
+            #[cfg(not(target_os = "macos"))]
+            Key::F22 => "f22",
+            #[cfg(not(target_os = "macos"))]
+            Key::F23 => "f23",
+            #[cfg(not(target_os = "macos"))]
+            Key::F24 => "f24",
+            Key::Yen => "yen",
+            Key::Ro => "ro",
+            Key::Eisu => "eisu",
+            Key::Kana => "kana",
+            Key::ContextMenu => "menu",
+            Key::Apostrophe => "'",
+            Key::Semicolon => ";",
+            Key::Backslash => "\\",
+            Key::Grave => "`",
+            // macOS virtual key codes, `Other` holds keysyms under X11
+            #[cfg(target_os = "macos")]
+            Key::Other(0x2f) => ".",
+            #[cfg(target_os = "macos")]
+            Key::Other(0x2b) => ",",
+            #[cfg(target_os = "macos")]
+            Key::Other(0x2c) => "/",
+            #[cfg(target_os = "macos")]
+            Key::Other(0x1b) => "-",
+            #[cfg(target_os = "macos")]
+            Key::Other(0x18) => "=",
+            Key::Other(code) => return write!(f, "raw:{code:#X}"),
+        };
+        f.write_str(name)
+    }
+}
+
+/// Virtual key codes from `Events.h`.
+#[cfg(target_os = "macos")]
+fn international_key(key: Key) -> EnigoKey {
+    EnigoKey::Other(match key {
+        Key::Yen => 0x5D,
+        Key::Ro => 0x5E,
+        Key::Eisu => 0x66,
+        Key::Kana => 0x68,
+        _ => 0x6E,
+    })
+}
+
+/// X11 keysyms.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn international_key(key: Key) -> EnigoKey {
+    match key {
+        Key::Yen => EnigoKey::Other(0x00A5),
+        Key::Ro => EnigoKey::Other(0x005F),
+        Key::Eisu => EnigoKey::Other(0xFF30),
+        Key::Kana => EnigoKey::Other(0xFF27),
+        _ => EnigoKey::LMenu,
+    }
+}
+
+/// Virtual-key codes of Japanese keyboards.
+#[cfg(target_os = "windows")]
+fn international_key(key: Key) -> EnigoKey {
+    match key {
+        Key::Yen => EnigoKey::Other(0xDC),
+        Key::Ro => EnigoKey::Other(0xE2),
+        Key::Eisu => EnigoKey::Other(0xF0),
+        Key::Kana => EnigoKey::Kana,
+        _ => EnigoKey::Apps,
+    }
+}
+
+/// Key of the current layout that types a character, and whether it
+/// takes shift, e.g. `?` on US layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LayoutKey {
+    pub(crate) code: u16,
+    pub(crate) shift: bool,
+}
+
+/// Key that types `ch` on the current layout.
+#[cfg(target_os = "macos")]
+pub(crate) fn layout_key(ch: char) -> Option<LayoutKey> {
+    crate::layout::key_code(ch)
+}
+
+/// Enigo resolves characters against the active layout itself.
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn layout_key(_ch: char) -> Option<LayoutKey> {
+    None
+}
+
+/// Key that types `ch` on the current layout, without the shift some
+/// characters take, see `KeyCombo`. Characters that no key produces stay
+/// `Unicode` and are typed as text by `KeyCombo` on macOS.
+fn unicode_key(ch: char) -> EnigoKey {
+    layout_key(ch).map_or(EnigoKey::Unicode(ch), |key| {
+        EnigoKey::Other(key.code.into())
+    })
+}
+
+/// Largest key code accepted by `raw:` keys.
+#[cfg(target_os = "macos")]
+const MAX_RAW_KEY_CODE: u32 = 0xFF;
+/// Keysyms are 29-bit values.
+#[cfg(not(target_os = "macos"))]
+const MAX_RAW_KEY_CODE: u32 = 0x1FFF_FFFF;
+
+/// Parse a `raw:0x6F` key: a platform key code in hex, a macOS virtual
+/// keycode or an X11 keysym on Linux.
+pub(crate) fn parse_raw_key(code: &str) -> Result<Key, String> {
+    let digits = code
+        .strip_prefix("0x")
+        .or_else(|| code.strip_prefix("0X"))
+        .ok_or_else(|| format!("Raw key code must be hex, e.g. 0x6F: {code}"))?;
+    let value = u32::from_str_radix(digits, 16)
+        .map_err(|_| format!("Invalid raw key code: {code}"))?;
+    if value > MAX_RAW_KEY_CODE {
+        return Err(format!(
+            "Raw key code {code} is out of range (max {MAX_RAW_KEY_CODE:#X})"
+        ));
+    }
+    Ok(Key::Other(value))
+}
+
 /// Parse a key string into a `Key` enum.
 ///
 /// This function is used to parse a key string into a `Key` enum.
@@ -213,10 +403,11 @@ pub(crate) fn parse_key(input: &str) -> Option<Key> {
         return None;
     }
 
-    if input.len() == 1 {
-        let ch = input.chars().next().expect("input must be not empty");
-        if ch.is_ascii_lowercase() {
-            return Some(Key::Other(key_code_for_key_string(ch) as u32));
+    // Letters and digits follow the current keyboard layout
+    let mut chars = input.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        if ch.is_lowercase() || ch.is_ascii_digit() {
+            return Some(Key::Unicode(ch));
         }
     }
 
@@ -237,8 +428,11 @@ pub(crate) fn parse_key(input: &str) -> Option<Key> {
         #[cfg(target_os = "macos")]
         "rsuper" => Some(Key::RCommand),
         "shift" => Some(Key::Shift),
+        "rshift" => Some(Key::RShift),
         "alt" => Some(Key::Alt),
+        "ralt" => Some(Key::RAlt),
         "option" => Some(Key::Alt),
+        "roption" => Some(Key::RAlt),
 
         // Navigation
         "home" => Some(Key::Home),
@@ -262,11 +456,15 @@ pub(crate) fn parse_key(input: &str) -> Option<Key> {
         "volume_up" => Some(Key::VolumeUp),
         "volume_down" => Some(Key::VolumeDown),
         "volume_mute" => Some(Key::VolumeMute),
+        "media_forward" | "fast_forward" => Some(Key::MediaFastForward),
+        "media_rewind" | "rewind" => Some(Key::MediaRewind),
 
         #[cfg(target_os = "macos")]
         "brightness_up" => Some(Key::BrightnessUp),
         #[cfg(target_os = "macos")]
         "brightness_down" => Some(Key::BrightnessDown),
+        "illumination_up" | "backlight_up" => Some(Key::IlluminationUp),
+        "illumination_down" | "backlight_down" => Some(Key::IlluminationDown),
 
         // Special characters
         // Using codes from
@@ -324,6 +522,21 @@ pub(crate) fn parse_key(input: &str) -> Option<Key> {
         "f18" => Some(Key::F18),
         "f19" => Some(Key::F19),
         "f20" => Some(Key::F20),
+        #[cfg(not(target_os = "macos"))]
+        "f21" => Some(Key::F21),
+        #[cfg(not(target_os = "macos"))]
+        "f22" => Some(Key::F22),
+        #[cfg(not(target_os = "macos"))]
+        "f23" => Some(Key::F23),
+        #[cfg(not(target_os = "macos"))]
+        "f24" => Some(Key::F24),
+
+        // JIS and context menu keys
+        "yen" | "¥" => Some(Key::Yen),
+        "ro" | "jis_underscore" => Some(Key::Ro),
+        "eisu" | "alphanumeric" => Some(Key::Eisu),
+        "kana" => Some(Key::Kana),
+        "menu" | "context_menu" | "apps" => Some(Key::ContextMenu),
         _ => None,
     }
 }
@@ -1,20 +1,20 @@
 use crate::{
-    key::{parse_key, Key},
-    Modifier, Modifiers,
+    key::{layout_key, parse_key, parse_raw_key, Key, LayoutKey},
+    KeyboardBackend, Modifier, Modifiers,
 };
 use enigo::{
-    Direction::{Click, Press, Release},
-    Enigo, InputResult, Keyboard,
+    Direction::{self, Click, Press, Release},
+    InputResult, Key as EnigoKey,
 };
 use smallvec::SmallVec;
 use serde::{
     de::{value::Error as DeError, IntoDeserializer},
     Deserializer,
 };
-use serde::{de::Visitor, Deserialize};
+use serde::{de::Visitor, Deserialize, Serialize, Serializer};
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct KeyCombo {
     pub modifiers: Modifiers,
     pub keys: SmallVec<[Key; 4]>,
@@ -33,11 +33,127 @@ impl KeyCombo {
     }
 }
 
+/// Sends a key of a combo. Characters typed with shift on the current
+/// layout get it pressed around them, unless the combo holds shift already.
+/// On macOS, characters that no key of the current layout produces are typed
+/// as text. Text can't be held, so it is typed on press and release is a
+/// no-op.
+fn send(
+    keyboard: &mut dyn KeyboardBackend,
+    key: &Key,
+    direction: Direction,
+    modifiers: Modifiers,
+) -> InputResult<()> {
+    let typed = match key {
+        Key::Unicode(ch) => layout_key(*ch),
+        _ => None,
+    };
+    if let Some(typed) = typed {
+        let shift_held = modifiers.contains(Modifier::Shift)
+            || modifiers.contains(Modifier::RShift);
+        return send_layout_key(keyboard, typed, direction, shift_held);
+    }
+    match EnigoKey::from(key) {
+        #[cfg(target_os = "macos")]
+        EnigoKey::Unicode(ch) => match direction {
+            Release => Ok(()),
+            Press | Click => keyboard.text(ch.encode_utf8(&mut [0; 4])),
+        },
+        key => keyboard.key(key, direction),
+    }
+}
+
+fn send_layout_key(
+    keyboard: &mut dyn KeyboardBackend,
+    key: LayoutKey,
+    direction: Direction,
+    shift_held: bool,
+) -> InputResult<()> {
+    let code = EnigoKey::Other(key.code.into());
+    if !key.shift || shift_held {
+        return keyboard.key(code, direction);
+    }
+    let shift = Modifier::Shift.key().into();
+    match direction {
+        Press => {
+            keyboard.key(shift, Press)?;
+            keyboard.key(code, Press)
+        }
+        Release => {
+            keyboard.key(code, Release)?;
+            keyboard.key(shift, Release)
+        }
+        Click => {
+            keyboard.key(shift, Press)?;
+            keyboard.key(code, Click)?;
+            keyboard.key(shift, Release)
+        }
+    }
+}
+
+/// Modifiers of the `hyper` key, as remapped caps lock is commonly set up.
+const HYPER: [Modifier; 4] = [
+    Modifier::Ctrl,
+    Modifier::Meta,
+    Modifier::Shift,
+    Modifier::Alt,
+];
+
+/// Writes the combo as in profiles, modifiers first, e.g. `cmd+shift+a`.
+/// The four left modifiers are written as `hyper`.
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers == Modifiers::from_values(&HYPER) {
+            f.write_str("hyper")?;
+            for key in &self.keys {
+                write!(f, "+{key}")?;
+            }
+            return Ok(());
+        }
+        let mut first = true;
+        let mut separate = |f: &mut fmt::Formatter<'_>| {
+            if !std::mem::take(&mut first) {
+                f.write_str("+")?;
+            }
+            Ok(())
+        };
+        for modifier in self.modifiers.iter() {
+            separate(f)?;
+            f.write_str(modifier.name())?;
+        }
+        for key in &self.keys {
+            separate(f)?;
+            write!(f, "{key}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Written as in profiles, e.g. `cmd+s`, by human-readable formats and as
+/// modifiers and keys by binary ones, e.g. to cache parsed profiles.
+impl Serialize for KeyCombo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (self.modifiers, &self.keys).serialize(serializer)
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for KeyCombo {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            let (modifiers, keys) = Deserialize::deserialize(deserializer)?;
+            return Ok(KeyCombo { modifiers, keys });
+        }
+
         struct KeyComboVisitor;
 
         impl Visitor<'_> for KeyComboVisitor {
@@ -55,9 +171,20 @@ impl<'de> Deserialize<'de> for KeyCombo {
                 let mut keys: SmallVec<[Key; 4]> = SmallVec::new();
                 for combo in v.split('+') {
                     let part = combo.trim();
+                    // Hyper key: all four modifiers at once
+                    if part == "hyper" {
+                        for modifier in HYPER {
+                            modifiers.add(modifier);
+                        }
+                        continue;
+                    }
+                    if let Some(code) = part.strip_prefix("raw:") {
+                        keys.push(parse_raw_key(code).map_err(E::custom)?);
+                        continue;
+                    }
                     match parse_key(part) {
                         Some(k) => match k {
-                            Key::Control | Key::Meta | Key::Shift | Key::Alt => {
+                            k if is_modifier(k) => {
                                 modifiers.add(Modifier::from(k));
                             }
                             _ => {
@@ -78,81 +205,102 @@ impl<'de> Deserialize<'de> for KeyCombo {
     }
 }
 
-impl KeyCombo {
-    pub fn perform(&self, enigo: &mut Enigo) -> InputResult<()> {
-        if self.modifiers.contains(Modifier::Ctrl) {
-            enigo.key(Key::Control.into(), Press)?;
-        }
-        if self.modifiers.contains(Modifier::Meta) {
-            enigo.key(Key::Meta.into(), Press)?;
-        }
-        if self.modifiers.contains(Modifier::Shift) {
-            enigo.key(Key::Shift.into(), Press)?;
-        }
-        if self.modifiers.contains(Modifier::Alt) {
-            enigo.key(Key::Alt.into(), Press)?;
-        }
+/// What a [`KeyEvent`] presses or releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stroke {
+    Modifier(Modifier),
+    Key(Key),
+}
 
-        for key in self.keys.iter() {
-            enigo.key(key.into(), Click)?;
-        }
+/// One key event of a combo. Sent one at a time, so callers can wait
+/// between the events of a combo without blocking, see [`KeyCombo::events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    stroke: Stroke,
+    direction: Direction,
+    /// Modifiers of the combo, so shift is not pressed twice around
+    /// characters typed with it.
+    modifiers: Modifiers,
+}
 
-        if self.modifiers.contains(Modifier::Ctrl) {
-            enigo.key(Key::Control.into(), Release)?;
-        }
-        if self.modifiers.contains(Modifier::Meta) {
-            enigo.key(Key::Meta.into(), Release)?;
-        }
-        if self.modifiers.contains(Modifier::Shift) {
-            enigo.key(Key::Shift.into(), Release)?;
+impl KeyEvent {
+    pub(crate) fn send(
+        &self,
+        keyboard: &mut dyn KeyboardBackend,
+    ) -> InputResult<()> {
+        match self.stroke {
+            Stroke::Modifier(modifier) => {
+                keyboard.key(modifier.key().into(), self.direction)
+            }
+            Stroke::Key(key) => send(keyboard, &key, self.direction, self.modifiers),
         }
-        if self.modifiers.contains(Modifier::Alt) {
-            enigo.key(Key::Alt.into(), Release)?;
+    }
+}
+
+impl KeyCombo {
+    /// Key events of clicking (`Click`), pressing or releasing the combo,
+    /// in the order they are sent.
+    pub fn events(&self, direction: Direction) -> SmallVec<[KeyEvent; 8]> {
+        let event = |stroke, direction| KeyEvent {
+            stroke,
+            direction,
+            modifiers: self.modifiers,
+        };
+        let modifiers = || self.modifiers.iter().map(Stroke::Modifier);
+        let keys = self.keys.iter().copied().map(Stroke::Key);
+        let mut events = SmallVec::new();
+        match direction {
+            Click => {
+                events.extend(modifiers().map(|m| event(m, Press)));
+                events.extend(keys.map(|k| event(k, Click)));
+                events.extend(modifiers().map(|m| event(m, Release)));
+            }
+            Press | Release => {
+                events.extend(modifiers().map(|m| event(m, direction)));
+                events.extend(keys.map(|k| event(k, direction)));
+            }
         }
+        events
+    }
 
-        Ok(())
+    /// Presses and releases the combo.
+    pub fn perform(&self, keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+        self.send_events(keyboard, Click)
     }
 
-    pub fn press(&self, enigo: &mut Enigo) -> InputResult<()> {
-        if self.modifiers.contains(Modifier::Ctrl) {
-            enigo.key(Key::Control.into(), Press)?;
-        }
-        if self.modifiers.contains(Modifier::Meta) {
-            enigo.key(Key::Meta.into(), Press)?;
-        }
-        if self.modifiers.contains(Modifier::Shift) {
-            enigo.key(Key::Shift.into(), Press)?;
-        }
-        if self.modifiers.contains(Modifier::Alt) {
-            enigo.key(Key::Alt.into(), Press)?;
-        }
-        for key in self.keys.iter() {
-            enigo.key(key.into(), Press)?;
-        }
+    /// Presses the combo.
+    pub fn press(&self, keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+        self.send_events(keyboard, Press)
+    }
 
-        Ok(())
+    /// Releases the combo.
+    pub fn release(&self, keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+        self.send_events(keyboard, Release)
     }
 
-    pub fn release(&self, enigo: &mut Enigo) -> InputResult<()> {
-        if self.modifiers.contains(Modifier::Ctrl) {
-            enigo.key(Key::Control.into(), Release)?;
-        }
-        if self.modifiers.contains(Modifier::Meta) {
-            enigo.key(Key::Meta.into(), Release)?;
-        }
-        if self.modifiers.contains(Modifier::Shift) {
-            enigo.key(Key::Shift.into(), Release)?;
-        }
-        if self.modifiers.contains(Modifier::Alt) {
-            enigo.key(Key::Alt.into(), Release)?;
-        }
-        for key in self.keys.iter() {
-            enigo.key(key.into(), Release)?;
+    fn send_events(
+        &self,
+        keyboard: &mut dyn KeyboardBackend,
+        direction: Direction,
+    ) -> InputResult<()> {
+        for event in self.events(direction) {
+            event.send(keyboard)?;
         }
         Ok(())
     }
 }
 
+/// Whether `key` is held as a modifier of a combo, on either side.
+fn is_modifier(key: Key) -> bool {
+    match key {
+        Key::Control | Key::Meta | Key::Shift | Key::Alt => true,
+        Key::RControl | Key::RShift | Key::RAlt => true,
+        #[cfg(target_os = "macos")]
+        Key::RCommand => true,
+        _ => false,
+    }
+}
+
 impl std::str::FromStr for KeyCombo {
     type Err = String;
 
@@ -164,8 +312,6 @@ impl std::str::FromStr for KeyCombo {
 
 #[cfg(test)]
 mod tests {
-    use crate::key::key_code_for_key_string;
-
     use super::*;
     use serde::de::value::Error as DeError;
     use serde::de::IntoDeserializer;
@@ -175,6 +321,64 @@ mod tests {
             .map_err(|e: DeError| e.to_string())
     }
 
+    /// Keyboard recording the key events sent to it.
+    #[derive(Default)]
+    struct Recorder(Vec<(EnigoKey, Direction)>);
+
+    impl KeyboardBackend for Recorder {
+        fn key(&mut self, key: EnigoKey, direction: Direction) -> InputResult<()> {
+            self.0.push((key, direction));
+            Ok(())
+        }
+
+        fn text(&mut self, _text: &str) -> InputResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn shifted_characters_press_shift() {
+        // `?` is shift+/ on US layouts
+        let question = LayoutKey {
+            code: 0x2C,
+            shift: true,
+        };
+        let slash = EnigoKey::Other(0x2C);
+        let shift = EnigoKey::from(Key::Shift);
+
+        let mut keyboard = Recorder::default();
+        send_layout_key(&mut keyboard, question, Click, false).unwrap();
+        assert_eq!(
+            keyboard.0,
+            [(shift, Press), (slash, Click), (shift, Release)]
+        );
+
+        let mut keyboard = Recorder::default();
+        send_layout_key(&mut keyboard, question, Press, false).unwrap();
+        send_layout_key(&mut keyboard, question, Release, false).unwrap();
+        assert_eq!(
+            keyboard.0,
+            [
+                (shift, Press),
+                (slash, Press),
+                (slash, Release),
+                (shift, Release)
+            ]
+        );
+
+        // Shift of the combo is not pressed twice
+        let mut keyboard = Recorder::default();
+        send_layout_key(&mut keyboard, question, Click, true).unwrap();
+        assert_eq!(keyboard.0, [(slash, Click)]);
+        let mut keyboard = Recorder::default();
+        let unshifted = LayoutKey {
+            shift: false,
+            ..question
+        };
+        send_layout_key(&mut keyboard, unshifted, Click, false).unwrap();
+        assert_eq!(keyboard.0, [(slash, Click)]);
+    }
+
     #[test]
     fn test_single_modifier() {
         let kc = parse("ctrl").unwrap();
@@ -191,6 +395,14 @@ mod tests {
         assert!(kc.keys.is_empty());
     }
 
+    #[test]
+    fn test_hyper() {
+        let kc = parse("hyper+j").unwrap();
+        assert_eq!(kc.modifiers.len(), 4);
+        assert_eq!(kc.keys.as_slice(), [Key::Unicode('j')]);
+        assert_eq!(kc.to_string(), "hyper+j");
+    }
+
     #[test]
     fn test_synonyms() {
         let kc = parse("cmd+option").unwrap();
@@ -199,6 +411,66 @@ mod tests {
         assert!(kc.keys.is_empty());
     }
 
+    #[test]
+    fn test_display_round_trips() {
+        for input in ["cmd+shift+a", "ctrl+arrow_left", "enter", "alt+raw:0x6F"] {
+            let kc = parse(input).unwrap();
+            assert_eq!(kc.to_string(), input);
+            assert_eq!(parse(&kc.to_string()).unwrap(), kc);
+        }
+    }
+
+    #[test]
+    fn test_display_of_platform_key_codes() {
+        let expected = if cfg!(target_os = "macos") {
+            "cmd+."
+        } else {
+            "cmd+raw:0x2F"
+        };
+        assert_eq!(parse("cmd+period").unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn test_right_modifiers() {
+        let kc = parse("ralt+rshift+a").unwrap();
+        assert!(kc.modifiers.contains(Modifier::RAlt));
+        assert!(!kc.modifiers.contains(Modifier::Alt));
+        assert_eq!(kc.keys.as_slice(), [Key::Unicode('a')]);
+        assert_eq!(kc.to_string(), "rshift+ralt+a");
+
+        // Hyper is only the left modifiers
+        let kc = parse("rctrl+cmd+shift+alt+k").unwrap();
+        assert_eq!(kc.to_string(), "rctrl+cmd+shift+alt+k");
+        assert_eq!(parse(&kc.to_string()).unwrap(), kc);
+    }
+
+    #[test]
+    fn test_events_send_modifiers_around_keys() {
+        let kc = parse("cmd+shift+s").unwrap();
+        let directions = |direction| -> Vec<Direction> {
+            kc.events(direction).iter().map(|e| e.direction).collect()
+        };
+        assert_eq!(directions(Click), [Press, Press, Click, Release, Release]);
+        assert_eq!(directions(Press), [Press, Press, Press]);
+
+        let mut keyboard = Recorder::default();
+        kc.perform(&mut keyboard).unwrap();
+        let mut events = Recorder::default();
+        for event in kc.events(Click) {
+            event.send(&mut events).unwrap();
+        }
+        assert_eq!(keyboard.0, events.0);
+        assert_eq!(keyboard.0.len(), 5);
+    }
+
+    #[test]
+    fn test_serialize_round_trips() {
+        let kc = parse("shift+cmd+p").unwrap();
+        let json = serde_json::to_string(&kc).unwrap();
+        assert_eq!(json, r#""cmd+shift+p""#);
+        assert_eq!(serde_json::from_str::<KeyCombo>(&json).unwrap(), kc);
+    }
+
     #[test]
     fn test_invalid_modifier() {
         let err = parse("ctrl+foo").unwrap_err();
@@ -212,6 +484,56 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_layout_characters() {
+        let kc = parse("cmd+1").unwrap();
+        assert_eq!(kc.keys[..], [Key::Unicode('1')]);
+        let kc = parse("ctrl+й").unwrap();
+        assert_eq!(kc.keys[..], [Key::Unicode('й')]);
+        assert!(parse("ctrl+A").is_err());
+    }
+
+    #[test]
+    fn test_international_keys() {
+        let kc = parse("ctrl+eisu").unwrap();
+        assert!(kc.modifiers.contains(Modifier::Ctrl));
+        assert_eq!(kc.keys[..], [Key::Eisu]);
+        assert_eq!(parse("kana").unwrap().keys[..], [Key::Kana]);
+        assert_eq!(parse("¥").unwrap().keys[..], [Key::Yen]);
+        assert_eq!(parse("yen").unwrap().keys[..], [Key::Yen]);
+        assert_eq!(parse("ro").unwrap().keys[..], [Key::Ro]);
+        assert_eq!(parse("shift+menu").unwrap().keys[..], [Key::ContextMenu]);
+        assert_ne!(EnigoKey::from(Key::Eisu), EnigoKey::from(Key::Kana));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_f21_to_f24() {
+        let kc = parse("f21+f24").unwrap();
+        assert_eq!(kc.keys[..], [Key::F21, Key::F24]);
+        assert_eq!(EnigoKey::from(Key::F24), EnigoKey::F24);
+    }
+
+    #[test]
+    fn test_raw_key_codes() {
+        let kc = parse("cmd+raw:0x6F").unwrap();
+        assert!(kc.modifiers.contains(Modifier::Meta));
+        assert_eq!(kc.keys[..], [Key::Other(0x6F)]);
+        assert_eq!(parse("raw:0X0a").unwrap().keys[..], [Key::Other(0x0A)]);
+        assert!(parse("raw:111").unwrap_err().contains("must be hex"));
+        assert!(parse("raw:0xZZ")
+            .unwrap_err()
+            .contains("Invalid raw key code"));
+        assert!(parse("raw:0x").is_err());
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_raw_key_code_range() {
+        assert!(parse("raw:0xFF").is_ok());
+        assert!(parse("raw:0x100").unwrap_err().contains("out of range"));
+    }
+
     #[test]
     fn test_key_combo() {
         let kc = parse("ctrl+alt+shift+a").unwrap();
@@ -219,6 +541,6 @@ mod tests {
         assert!(kc.modifiers.contains(Modifier::Alt));
         assert!(kc.modifiers.contains(Modifier::Shift));
         assert_eq!(kc.keys.len(), 1);
-        assert_eq!(kc.keys[0], Key::Other(key_code_for_key_string('a') as u32));
+        assert_eq!(kc.keys[0], Key::Unicode('a'));
     }
 }
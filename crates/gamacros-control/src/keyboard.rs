@@ -0,0 +1,79 @@
+use std::fmt;
+use std::str::FromStr;
+
+use enigo::{Direction, Enigo, InputResult, Key as EnigoKey, Keyboard};
+use serde::{Deserialize, Serialize};
+
+/// Sends keyboard events for combos, system actions and Space switching.
+pub trait KeyboardBackend {
+    /// Presses, releases or clicks a key.
+    fn key(&mut self, key: EnigoKey, direction: Direction) -> InputResult<()>;
+
+    /// Types text no key of the layout produces.
+    fn text(&mut self, text: &str) -> InputResult<()>;
+}
+
+impl KeyboardBackend for Enigo {
+    fn key(&mut self, key: EnigoKey, direction: Direction) -> InputResult<()> {
+        Keyboard::key(self, key, direction)
+    }
+
+    fn text(&mut self, text: &str) -> InputResult<()> {
+        Keyboard::text(self, text)
+    }
+}
+
+/// Keyboard backend selectable at runtime.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize,
+)]
+pub enum KeyboardBackendKind {
+    /// Events sent by enigo, on every platform.
+    #[default]
+    Enigo,
+    /// Events posted with `CGEvent` directly, with the flags and keyboard
+    /// type of real keystrokes (macOS only).
+    Native,
+}
+
+impl KeyboardBackendKind {
+    /// Name of the backend as used in profiles.
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyboardBackendKind::Enigo => "enigo",
+            KeyboardBackendKind::Native => "native",
+        }
+    }
+}
+
+impl fmt::Display for KeyboardBackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for KeyboardBackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "enigo" => Ok(KeyboardBackendKind::Enigo),
+            "native" | "cgevent" => Ok(KeyboardBackendKind::Native),
+            other => Err(format!("unknown keyboard backend: {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names() {
+        for kind in [KeyboardBackendKind::Enigo, KeyboardBackendKind::Native] {
+            assert_eq!(kind.name().parse::<KeyboardBackendKind>(), Ok(kind));
+        }
+        assert_eq!("cgevent".parse(), Ok(KeyboardBackendKind::Native));
+        assert!("uinput".parse::<KeyboardBackendKind>().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Key codes for characters on the current macOS keyboard layout.
+//!
+//! Characters are looked up with `UCKeyTranslate`, so letters hit the right
+//! key on AZERTY, Dvorak and non-Latin layouts instead of ANSI-US positions.
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::ops::RangeInclusive;
+use std::sync::{Mutex, PoisonError};
+
+use crate::key::LayoutKey;
+
+type CFTypeRef = *const c_void;
+type CFStringRef = CFTypeRef;
+type CFDataRef = CFTypeRef;
+type TISInputSourceRef = CFTypeRef;
+
+/// `kUCKeyActionDisplay`
+const KEY_ACTION_DISPLAY: u16 = 3;
+/// `kUCKeyTranslateNoDeadKeysMask`
+const NO_DEAD_KEYS: u32 = 1;
+/// `shiftKey >> 8` as expected by `UCKeyTranslate`.
+const SHIFT: u32 = 0x02;
+/// Virtual key codes that can produce characters.
+const KEY_CODES: RangeInclusive<u16> = 0..=0x7F;
+/// Keypad keys, skipped so digits resolve to the main row.
+const KEYPAD: RangeInclusive<u16> = 0x41..=0x5C;
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    #[allow(non_upper_case_globals)]
+    static kTISPropertyUnicodeKeyLayoutData: CFStringRef;
+    fn TISCopyCurrentKeyboardLayoutInputSource() -> TISInputSourceRef;
+    fn TISGetInputSourceProperty(
+        source: TISInputSourceRef,
+        key: CFStringRef,
+    ) -> CFDataRef;
+    #[allow(clippy::too_many_arguments)]
+    fn UCKeyTranslate(
+        layout: *const u8,
+        key_code: u16,
+        key_action: u16,
+        modifier_key_state: u32,
+        keyboard_type: u32,
+        options: u32,
+        dead_key_state: *mut u32,
+        max_length: usize,
+        actual_length: *mut usize,
+        string: *mut u16,
+    ) -> i32;
+    fn LMGetKbdType() -> u8;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFRelease(cf: CFTypeRef);
+    fn CFDataGetBytePtr(data: CFDataRef) -> *const u8;
+}
+
+/// Reverse lookup table of the layout that was active last.
+struct Layout {
+    /// Address of the layout data, changes when the user switches layouts.
+    data: usize,
+    codes: HashMap<char, LayoutKey>,
+}
+
+static LAYOUT: Mutex<Option<Layout>> = Mutex::new(None);
+
+/// Returns the key that types `ch` on the current layout, with or without
+/// shift. `None` when no key produces it.
+pub(crate) fn key_code(ch: char) -> Option<LayoutKey> {
+    // SAFETY: the input source is retained until the end of the block, the
+    // layout data it owns is only read while it is alive.
+    unsafe {
+        let source = TISCopyCurrentKeyboardLayoutInputSource();
+        if source.is_null() {
+            return None;
+        }
+        let data =
+            TISGetInputSourceProperty(source, kTISPropertyUnicodeKeyLayoutData);
+        let code = if data.is_null() {
+            None
+        } else {
+            let mut layout = LAYOUT.lock().unwrap_or_else(PoisonError::into_inner);
+            if layout.as_ref().map(|layout| layout.data) != Some(data as usize) {
+                *layout = Some(Layout {
+                    data: data as usize,
+                    codes: translate_all(CFDataGetBytePtr(data)),
+                });
+            }
+            layout
+                .as_ref()
+                .and_then(|layout| layout.codes.get(&ch).copied())
+        };
+        CFRelease(source);
+        code
+    }
+}
+
+/// Builds the character to key code table. Unshifted characters win over
+/// shifted ones, lower key codes over higher ones.
+unsafe fn translate_all(layout: *const u8) -> HashMap<char, LayoutKey> {
+    let keyboard_type = u32::from(LMGetKbdType());
+    let mut codes = HashMap::new();
+    for modifiers in [0, SHIFT] {
+        for code in KEY_CODES.filter(|code| !KEYPAD.contains(code)) {
+            if let Some(ch) = translate(layout, code, modifiers, keyboard_type) {
+                let shift = modifiers == SHIFT;
+                codes.entry(ch).or_insert(LayoutKey { code, shift });
+            }
+        }
+    }
+    codes
+}
+
+/// Character typed by a key, `None` for keys without a single character.
+unsafe fn translate(
+    layout: *const u8,
+    key_code: u16,
+    modifiers: u32,
+    keyboard_type: u32,
+) -> Option<char> {
+    let mut dead_key_state = 0u32;
+    let mut length = 0usize;
+    let mut buffer = [0u16; 4];
+    let status = UCKeyTranslate(
+        layout,
+        key_code,
+        KEY_ACTION_DISPLAY,
+        modifiers,
+        keyboard_type,
+        NO_DEAD_KEYS,
+        &mut dead_key_state,
+        buffer.len(),
+        &mut length,
+        buffer.as_mut_ptr(),
+    );
+    if status != 0 {
+        return None;
+    }
+    let mut chars =
+        char::decode_utf16(buffer[..length.min(buffer.len())].iter().copied());
+    match (chars.next(), chars.next()) {
+        (Some(Ok(ch)), None) if !ch.is_control() => Some(ch),
+        _ => None,
+    }
+}
@@ -1,9 +1,23 @@
 mod key;
 mod key_combo;
+mod keyboard;
+#[cfg(target_os = "macos")]
+mod layout;
 mod modifiers;
+mod mouse;
+#[cfg(target_os = "macos")]
+mod native_keyboard;
 mod performer;
+mod permissions;
+mod space;
+mod system;
 
-pub use key_combo::{KeyCombo};
+pub use key_combo::{KeyCombo, KeyEvent};
 pub use key::Key;
+pub use keyboard::{KeyboardBackend, KeyboardBackendKind};
 pub use modifiers::{Modifier, Modifiers};
-pub use performer::Performer;
+pub use mouse::{warp_position, DisplayBounds, MouseButton, MouseTarget};
+pub use performer::{Performer, ScrollUnit, SYNTHETIC_EVENT_TAG};
+pub use permissions::{missing_permissions, Permission};
+pub use space::SpaceTarget;
+pub use system::SystemAction;
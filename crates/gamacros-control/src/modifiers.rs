@@ -1,11 +1,19 @@
+use serde::{Deserialize, Serialize};
+
 use crate::key::Key;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+/// A modifier key. The plain variants are the left keys, the `R` variants
+/// the right ones, for apps that tell them apart, e.g. right option.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Modifier {
     Ctrl,
     Meta,
     Shift,
     Alt,
+    RCtrl,
+    RMeta,
+    RShift,
+    RAlt,
 }
 
 impl Modifier {
@@ -13,6 +21,22 @@ impl Modifier {
     pub const META: u8 = 1 << 1;
     pub const SHIFT: u8 = 1 << 2;
     pub const ALT: u8 = 1 << 3;
+    pub const RCTRL: u8 = 1 << 4;
+    pub const RMETA: u8 = 1 << 5;
+    pub const RSHIFT: u8 = 1 << 6;
+    pub const RALT: u8 = 1 << 7;
+
+    /// All modifiers in the order they are written and pressed.
+    pub const ALL: [Modifier; 8] = [
+        Modifier::Ctrl,
+        Modifier::RCtrl,
+        Modifier::Meta,
+        Modifier::RMeta,
+        Modifier::Shift,
+        Modifier::RShift,
+        Modifier::Alt,
+        Modifier::RAlt,
+    ];
 
     pub const fn to_bitmap(&self) -> u8 {
         match self {
@@ -20,6 +44,42 @@ impl Modifier {
             Modifier::Meta => Self::META,
             Modifier::Shift => Self::SHIFT,
             Modifier::Alt => Self::ALT,
+            Modifier::RCtrl => Self::RCTRL,
+            Modifier::RMeta => Self::RMETA,
+            Modifier::RShift => Self::RSHIFT,
+            Modifier::RAlt => Self::RALT,
+        }
+    }
+
+    /// Name as written in profiles, e.g. `rcmd`.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "ctrl",
+            Modifier::Meta => "cmd",
+            Modifier::Shift => "shift",
+            Modifier::Alt => "alt",
+            Modifier::RCtrl => "rctrl",
+            Modifier::RMeta => "rcmd",
+            Modifier::RShift => "rshift",
+            Modifier::RAlt => "ralt",
+        }
+    }
+
+    /// The key pressed for the modifier. Right command only exists on
+    /// macOS, elsewhere the left one is pressed.
+    pub const fn key(&self) -> Key {
+        match self {
+            Modifier::Ctrl => Key::Control,
+            Modifier::Meta => Key::Meta,
+            Modifier::Shift => Key::Shift,
+            Modifier::Alt => Key::Alt,
+            Modifier::RCtrl => Key::RControl,
+            #[cfg(target_os = "macos")]
+            Modifier::RMeta => Key::RCommand,
+            #[cfg(not(target_os = "macos"))]
+            Modifier::RMeta => Key::Meta,
+            Modifier::RShift => Key::RShift,
+            Modifier::RAlt => Key::RAlt,
         }
     }
 }
@@ -33,6 +93,11 @@ impl From<Key> for Modifier {
             Key::Meta => Modifier::Meta,
             Key::Shift => Modifier::Shift,
             Key::Alt => Modifier::Alt,
+            Key::RControl => Modifier::RCtrl,
+            #[cfg(target_os = "macos")]
+            Key::RCommand => Modifier::RMeta,
+            Key::RShift => Modifier::RShift,
+            Key::RAlt => Modifier::RAlt,
             _ => panic!("Invalid modifier key"),
         }
     }
@@ -44,12 +109,16 @@ impl From<u8> for Modifier {
             Self::META => Modifier::Meta,
             Self::SHIFT => Modifier::Shift,
             Self::ALT => Modifier::Alt,
+            Self::RCTRL => Modifier::RCtrl,
+            Self::RMETA => Modifier::RMeta,
+            Self::RSHIFT => Modifier::RShift,
+            Self::RALT => Modifier::RAlt,
             _ => panic!("Invalid modifier bitmap"),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct Modifiers(u8);
 
 impl Modifiers {
@@ -89,6 +158,16 @@ impl Modifiers {
     pub const fn len(&self) -> usize {
         self.0.count_ones() as usize
     }
+
+    /// The same modifiers with the right keys replaced by the left ones.
+    pub const fn to_left(&self) -> Self {
+        Self((self.0 | self.0 >> 4) & 0x0F)
+    }
+
+    /// The modifiers in the order of [`Modifier::ALL`].
+    pub fn iter(&self) -> impl Iterator<Item = Modifier> + '_ {
+        Modifier::ALL.into_iter().filter(|m| self.contains(*m))
+    }
 }
 
 #[cfg(test)]
@@ -101,6 +180,24 @@ mod tests {
         assert_eq!(Modifier::from(Modifier::Meta.to_bitmap()), Modifier::Meta);
         assert_eq!(Modifier::from(Modifier::Shift.to_bitmap()), Modifier::Shift);
         assert_eq!(Modifier::from(Modifier::Alt.to_bitmap()), Modifier::Alt);
+        for modifier in Modifier::ALL {
+            assert_eq!(Modifier::from(modifier.to_bitmap()), modifier);
+        }
+    }
+
+    #[test]
+    fn test_modifiers_tell_sides_apart() {
+        let mods = Modifiers::from_values(&[Modifier::RAlt, Modifier::Ctrl]);
+        assert!(mods.contains(Modifier::RAlt));
+        assert!(!mods.contains(Modifier::Alt));
+        assert_eq!(
+            mods.iter().collect::<Vec<_>>(),
+            [Modifier::Ctrl, Modifier::RAlt]
+        );
+        assert_eq!(
+            mods.to_left(),
+            Modifiers::from_values(&[Modifier::Ctrl, Modifier::Alt])
+        );
     }
 
     #[test]
@@ -0,0 +1,278 @@
+//! Cursor jumps within and between displays.
+
+use std::fmt;
+use std::str::FromStr;
+
+use enigo::{Coordinate, Enigo, InputError, InputResult, Mouse};
+use serde::{Deserialize, Serialize};
+
+/// Where a cursor jump lands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MouseTarget {
+    /// Center of the display under the cursor.
+    Center,
+    /// The same relative position on the next display.
+    NextDisplay,
+    /// The same relative position on the previous display.
+    PrevDisplay,
+}
+
+impl MouseTarget {
+    pub const ALL: [MouseTarget; 3] = [
+        MouseTarget::Center,
+        MouseTarget::NextDisplay,
+        MouseTarget::PrevDisplay,
+    ];
+
+    /// Name of the target as used in profiles.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MouseTarget::Center => "center",
+            MouseTarget::NextDisplay => "next_display",
+            MouseTarget::PrevDisplay => "prev_display",
+        }
+    }
+
+    pub(crate) fn warp(&self, enigo: &mut Enigo) -> InputResult<()> {
+        let displays = displays(enigo)?;
+        let (x, y) = enigo.location()?;
+        let (x, y) = warp_position(*self, (x as f64, y as f64), &displays)
+            .ok_or(InputError::Simulate("no active displays"))?;
+        enigo.move_mouse(x.round() as i32, y.round() as i32, Coordinate::Abs)
+    }
+}
+
+impl fmt::Display for MouseTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MouseTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        MouseTarget::ALL
+            .into_iter()
+            .find(|target| target.name() == name)
+            .ok_or_else(|| format!("Invalid mouse target: {s}"))
+    }
+}
+
+/// A mouse button clicked by actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 3] =
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+    /// Name of the button as used in profiles.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MouseButton::Left => "left",
+            MouseButton::Right => "right",
+            MouseButton::Middle => "middle",
+        }
+    }
+}
+
+impl From<MouseButton> for enigo::Button {
+    fn from(button: MouseButton) -> Self {
+        match button {
+            MouseButton::Left => enigo::Button::Left,
+            MouseButton::Right => enigo::Button::Right,
+            MouseButton::Middle => enigo::Button::Middle,
+        }
+    }
+}
+
+impl fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MouseButton {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        MouseButton::ALL
+            .into_iter()
+            .find(|button| button.name() == name)
+            .ok_or_else(|| format!("Invalid mouse button: {s}"))
+    }
+}
+
+/// Bounds of a display in global coordinates, origin at the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayBounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl DisplayBounds {
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        x >= self.x
+            && x < self.x + self.width
+            && y >= self.y
+            && y < self.y + self.height
+    }
+}
+
+/// Position the cursor at `cursor` jumps to, `None` without displays.
+pub fn warp_position(
+    target: MouseTarget,
+    cursor: (f64, f64),
+    displays: &[DisplayBounds],
+) -> Option<(f64, f64)> {
+    let index = displays
+        .iter()
+        .position(|display| display.contains(cursor))
+        .unwrap_or(0);
+    let current = *displays.get(index)?;
+    let next = match target {
+        MouseTarget::Center => {
+            return Some((
+                current.x + current.width / 2.0,
+                current.y + current.height / 2.0,
+            ))
+        }
+        MouseTarget::NextDisplay => displays[(index + 1) % displays.len()],
+        MouseTarget::PrevDisplay => {
+            displays[(index + displays.len() - 1) % displays.len()]
+        }
+    };
+    let fx = ((cursor.0 - current.x) / current.width).clamp(0.0, 1.0);
+    let fy = ((cursor.1 - current.y) / current.height).clamp(0.0, 1.0);
+    Some((next.x + fx * next.width, next.y + fy * next.height))
+}
+
+/// Bounds of the active displays, from CoreGraphics.
+#[cfg(target_os = "macos")]
+fn displays(_enigo: &Enigo) -> InputResult<Vec<DisplayBounds>> {
+    use native::{CGDisplayBounds, CGGetActiveDisplayList, MAX_DISPLAYS};
+
+    let mut ids = [0u32; MAX_DISPLAYS];
+    let mut count = 0u32;
+    // SAFETY: the buffer holds MAX_DISPLAYS ids, count is a valid out pointer.
+    let error = unsafe {
+        CGGetActiveDisplayList(MAX_DISPLAYS as u32, ids.as_mut_ptr(), &mut count)
+    };
+    if error != 0 {
+        return Err(InputError::Simulate("failed to list displays"));
+    }
+    Ok(ids[..count as usize]
+        .iter()
+        .map(|id| {
+            // SAFETY: the id comes from the active display list.
+            let bounds = unsafe { CGDisplayBounds(*id) };
+            DisplayBounds {
+                x: bounds.origin.x,
+                y: bounds.origin.y,
+                width: bounds.size.width,
+                height: bounds.size.height,
+            }
+        })
+        .collect())
+}
+
+/// Only the main display is known on other platforms.
+#[cfg(not(target_os = "macos"))]
+fn displays(enigo: &Enigo) -> InputResult<Vec<DisplayBounds>> {
+    let (width, height) = enigo.main_display()?;
+    Ok(vec![DisplayBounds {
+        x: 0.0,
+        y: 0.0,
+        width: width as f64,
+        height: height as f64,
+    }])
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    /// Upper bound of displays the cursor can jump between.
+    pub(super) const MAX_DISPLAYS: usize = 16;
+
+    #[repr(C)]
+    pub(super) struct CGPoint {
+        pub(super) x: f64,
+        pub(super) y: f64,
+    }
+
+    #[repr(C)]
+    pub(super) struct CGSize {
+        pub(super) width: f64,
+        pub(super) height: f64,
+    }
+
+    #[repr(C)]
+    pub(super) struct CGRect {
+        pub(super) origin: CGPoint,
+        pub(super) size: CGSize,
+    }
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub(super) fn CGGetActiveDisplayList(
+            max_displays: u32,
+            displays: *mut u32,
+            count: *mut u32,
+        ) -> i32;
+        pub(super) fn CGDisplayBounds(display: u32) -> CGRect;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: DisplayBounds = DisplayBounds {
+        x: 0.0,
+        y: 0.0,
+        width: 1600.0,
+        height: 1000.0,
+    };
+    const SIDE: DisplayBounds = DisplayBounds {
+        x: 1600.0,
+        y: 0.0,
+        width: 800.0,
+        height: 500.0,
+    };
+
+    #[test]
+    fn jumps_within_and_between_displays() {
+        let displays = [MAIN, SIDE];
+        let cursor = (400.0, 250.0);
+        assert_eq!(
+            warp_position(MouseTarget::Center, cursor, &displays),
+            Some((800.0, 500.0))
+        );
+        assert_eq!(
+            warp_position(MouseTarget::NextDisplay, cursor, &displays),
+            Some((1800.0, 125.0))
+        );
+        assert_eq!(
+            warp_position(MouseTarget::PrevDisplay, (1800.0, 125.0), &displays),
+            Some(cursor)
+        );
+        assert_eq!(warp_position(MouseTarget::Center, cursor, &[]), None);
+    }
+
+    #[test]
+    fn parses_names() {
+        for target in MouseTarget::ALL {
+            assert_eq!(target.name().parse::<MouseTarget>(), Ok(target));
+        }
+        assert!("top_left".parse::<MouseTarget>().is_err());
+        assert_eq!("Right".parse::<MouseButton>(), Ok(MouseButton::Right));
+    }
+}
@@ -0,0 +1,280 @@
+//! Keyboard events posted with `CGEvent` directly.
+//!
+//! Unlike enigo, modifier keys are sent as flag changes with the device
+//! flags of their side, every event carries the keyboard type of the
+//! current layout and media keys carry no modifier flags, as from real
+//! keyboards. Some apps ignore media keys or shortcuts otherwise.
+
+use std::ffi::c_void;
+
+use enigo::{Direction, InputError, InputResult, Key as EnigoKey};
+use objc2::encode::{Encoding, RefEncode};
+use objc2::msg_send;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2_foundation::NSPoint;
+
+use crate::keyboard::KeyboardBackend;
+use crate::SYNTHETIC_EVENT_TAG;
+
+type CFTypeRef = *const c_void;
+
+/// `CGEventRef`, typed so the encoding of `-[NSEvent CGEvent]` matches.
+#[repr(C)]
+struct CGEvent {
+    _private: [u8; 0],
+}
+
+// SAFETY: the encoding of `CGEventRef`.
+unsafe impl RefEncode for CGEvent {
+    const ENCODING_REF: Encoding =
+        Encoding::Pointer(&Encoding::Struct("__CGEvent", &[]));
+}
+
+const HID_SYSTEM_STATE: i32 = 1;
+const HID_EVENT_TAP: u32 = 0;
+const EVENT_FLAGS_CHANGED: u32 = 12;
+const KEYBOARD_EVENT_KEYBOARD_TYPE: u32 = 10;
+const EVENT_SOURCE_USER_DATA: u32 = 42;
+
+const FLAG_NON_COALESCED: u64 = 0x0000_0100;
+const FLAG_SHIFT: u64 = 0x0002_0000;
+const FLAG_CONTROL: u64 = 0x0004_0000;
+const FLAG_ALTERNATE: u64 = 0x0008_0000;
+const FLAG_COMMAND: u64 = 0x0010_0000;
+
+/// Modifier keys by virtual key code, with their flag and the device flag
+/// of their side from IOLLEvent.h.
+const MODIFIERS: [(u16, u64); 8] = [
+    (0x3B, FLAG_CONTROL | 0x0000_0001),
+    (0x3E, FLAG_CONTROL | 0x0000_2000),
+    (0x38, FLAG_SHIFT | 0x0000_0002),
+    (0x3C, FLAG_SHIFT | 0x0000_0004),
+    (0x3A, FLAG_ALTERNATE | 0x0000_0020),
+    (0x3D, FLAG_ALTERNATE | 0x0000_0040),
+    (0x37, FLAG_COMMAND | 0x0000_0008),
+    (0x36, FLAG_COMMAND | 0x0000_0010),
+];
+
+/// `NSEventTypeSystemDefined`, and its subtype for auxiliary keys.
+const SYSTEM_DEFINED: usize = 14;
+const AUX_CONTROL_BUTTONS: i16 = 8;
+/// Modifier flags of media keys from real keyboards.
+const AUX_FLAGS: usize = 0x0A00;
+
+pub(crate) struct NativeKeyboard {
+    source: CFTypeRef,
+    /// Process events are posted to instead of the HID event tap.
+    pid: Option<i32>,
+    /// Modifier keys held down, by key code.
+    held: Vec<u16>,
+}
+
+impl NativeKeyboard {
+    pub(crate) fn new() -> InputResult<Self> {
+        Self::with_pid(None)
+    }
+
+    /// Keyboard posting to the process `pid`, whether it is frontmost or
+    /// not.
+    pub(crate) fn for_pid(pid: i32) -> InputResult<Self> {
+        Self::with_pid(Some(pid))
+    }
+
+    fn with_pid(pid: Option<i32>) -> InputResult<Self> {
+        // SAFETY: creating an event source has no preconditions.
+        let source = unsafe { CGEventSourceCreate(HID_SYSTEM_STATE) };
+        if source.is_null() {
+            return Err(InputError::Simulate("failed to create an event source"));
+        }
+        Ok(Self {
+            source,
+            pid,
+            held: Vec::new(),
+        })
+    }
+
+    /// Flags of the modifiers held down.
+    fn flags(&self) -> u64 {
+        MODIFIERS
+            .iter()
+            .filter(|(code, _)| self.held.contains(code))
+            .fold(FLAG_NON_COALESCED, |flags, (_, flag)| flags | flag)
+    }
+
+    fn post_key(&mut self, code: u16, down: bool) -> InputResult<()> {
+        let modifier = MODIFIERS.iter().any(|(held, _)| *held == code);
+        if modifier {
+            self.held.retain(|held| *held != code);
+            if down {
+                self.held.push(code);
+            }
+        }
+        // SAFETY: the event is created, posted and released here.
+        unsafe {
+            let event = CGEventCreateKeyboardEvent(self.source, code, down);
+            if event.is_null() {
+                return Err(InputError::Simulate("failed to create a key event"));
+            }
+            if modifier {
+                CGEventSetType(event, EVENT_FLAGS_CHANGED);
+            }
+            CGEventSetFlags(event, self.flags());
+            CGEventSetIntegerValueField(
+                event,
+                KEYBOARD_EVENT_KEYBOARD_TYPE,
+                i64::from(LMGetKbdType()),
+            );
+            self.post(event);
+            CFRelease(event.cast_const().cast());
+        }
+        Ok(())
+    }
+
+    /// Marks the event as injected by gamacros and posts it.
+    unsafe fn post(&self, event: *mut CGEvent) {
+        CGEventSetIntegerValueField(
+            event,
+            EVENT_SOURCE_USER_DATA,
+            SYNTHETIC_EVENT_TAG,
+        );
+        match self.pid {
+            Some(pid) => CGEventPostToPid(pid, event),
+            None => CGEventPost(HID_EVENT_TAP, event),
+        }
+    }
+
+    /// Posts a media key as the system defined event keyboards send.
+    fn post_aux(&mut self, key_type: isize, down: bool) -> InputResult<()> {
+        let class = AnyClass::get(c"NSEvent")
+            .ok_or(InputError::Simulate("NSEvent is unavailable"))?;
+        let state: isize = if down { 0x0A } else { 0x0B };
+        // SAFETY: the arguments match the method signature, the CGEvent is
+        // owned by the NSEvent, which outlives the post.
+        unsafe {
+            let event: Option<Retained<AnyObject>> = msg_send![
+                class,
+                otherEventWithType: SYSTEM_DEFINED,
+                location: NSPoint::ZERO,
+                modifierFlags: AUX_FLAGS,
+                timestamp: 0.0f64,
+                windowNumber: 0isize,
+                context: None::<&AnyObject>,
+                subtype: AUX_CONTROL_BUTTONS,
+                data1: (key_type << 16) | (state << 8),
+                data2: -1isize
+            ];
+            let event =
+                event.ok_or(InputError::Simulate("failed to create a media key"))?;
+            let cg_event: *mut CGEvent = msg_send![&*event, CGEvent];
+            if cg_event.is_null() {
+                return Err(InputError::Simulate("failed to create a media key"));
+            }
+            self.post(cg_event.cast());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for NativeKeyboard {
+    fn drop(&mut self) {
+        // SAFETY: the source was created by this keyboard.
+        unsafe { CFRelease(self.source) };
+    }
+}
+
+impl KeyboardBackend for NativeKeyboard {
+    fn key(&mut self, key: EnigoKey, direction: Direction) -> InputResult<()> {
+        let directions: &[bool] = match direction {
+            Direction::Press => &[true],
+            Direction::Release => &[false],
+            Direction::Click => &[true, false],
+        };
+        if let Some(key_type) = aux_key_type(key) {
+            for down in directions {
+                self.post_aux(key_type, *down)?;
+            }
+            return Ok(());
+        }
+        let code = match key {
+            EnigoKey::Other(code) => u16::try_from(code).ok(),
+            key => u16::try_from(key).ok(),
+        }
+        .ok_or(InputError::InvalidInput("key has no virtual key code"))?;
+        for down in directions {
+            self.post_key(code, *down)?;
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> InputResult<()> {
+        let text: Vec<u16> = text.encode_utf16().collect();
+        for down in [true, false] {
+            // SAFETY: the event is created, posted and released here, the
+            // string outlives the call that copies it.
+            unsafe {
+                let event = CGEventCreateKeyboardEvent(self.source, 0, down);
+                if event.is_null() {
+                    return Err(InputError::Simulate(
+                        "failed to create a key event",
+                    ));
+                }
+                CGEventKeyboardSetUnicodeString(event, text.len(), text.as_ptr());
+                CGEventSetFlags(event, self.flags());
+                self.post(event);
+                CFRelease(event.cast_const().cast());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `NX_KEYTYPE_*` of media and brightness keys.
+fn aux_key_type(key: EnigoKey) -> Option<isize> {
+    Some(match key {
+        EnigoKey::VolumeUp => 0,
+        EnigoKey::VolumeDown => 1,
+        EnigoKey::BrightnessUp => 2,
+        EnigoKey::BrightnessDown => 3,
+        EnigoKey::VolumeMute => 7,
+        EnigoKey::MediaPlayPause => 16,
+        EnigoKey::MediaNextTrack => 17,
+        EnigoKey::MediaPrevTrack => 18,
+        EnigoKey::MediaFast => 19,
+        EnigoKey::MediaRewind => 20,
+        EnigoKey::IlluminationUp => 21,
+        EnigoKey::IlluminationDown => 22,
+        EnigoKey::IlluminationToggle => 23,
+        _ => return None,
+    })
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceCreate(state: i32) -> CFTypeRef;
+    fn CGEventCreateKeyboardEvent(
+        source: CFTypeRef,
+        key_code: u16,
+        key_down: bool,
+    ) -> *mut CGEvent;
+    fn CGEventSetType(event: *mut CGEvent, kind: u32);
+    fn CGEventSetFlags(event: *mut CGEvent, flags: u64);
+    fn CGEventSetIntegerValueField(event: *mut CGEvent, field: u32, value: i64);
+    fn CGEventKeyboardSetUnicodeString(
+        event: *mut CGEvent,
+        length: usize,
+        string: *const u16,
+    );
+    fn CGEventPost(tap: u32, event: *mut CGEvent);
+    fn CGEventPostToPid(pid: i32, event: *mut CGEvent);
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFRelease(cf: CFTypeRef);
+}
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    fn LMGetKbdType() -> u8;
+}
@@ -1,9 +1,57 @@
-use enigo::{Axis, Coordinate, Enigo, InputResult, Mouse, NewConError, Settings};
+use std::time::Instant;
 
-use crate::KeyCombo;
+use enigo::{
+    Axis, Coordinate, Direction, Enigo, InputResult, Mouse, NewConError, Settings,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    KeyCombo, KeyEvent, KeyboardBackend, KeyboardBackendKind, MouseButton,
+    MouseTarget, SpaceTarget, SystemAction,
+};
+
+/// Marks events injected by a `Performer` (event source user data on macOS,
+/// `dwExtraInfo` on Windows), so they can be told apart from real input.
+pub const SYNTHETIC_EVENT_TAG: i64 = 0x6761_6d61;
+
+/// Pixels in a scrolled line, for platforms without pixel scrolling.
+#[cfg(not(target_os = "macos"))]
+const PIXELS_PER_LINE: i32 = 10;
+
+/// Unit of scroll amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollUnit {
+    Lines,
+    /// Smooth scrolling. Converted to lines where the platform only
+    /// scrolls by lines.
+    Pixels,
+}
+
+impl Default for ScrollUnit {
+    /// Pixels on macOS, where scrolling has always been smooth.
+    fn default() -> Self {
+        if cfg!(target_os = "macos") {
+            ScrollUnit::Pixels
+        } else {
+            ScrollUnit::Lines
+        }
+    }
+}
 
 pub struct Performer {
     enigo: Enigo,
+    /// Keyboard used instead of enigo, see `set_keyboard_backend`.
+    keyboard: Option<Box<dyn KeyboardBackend>>,
+    keyboard_kind: KeyboardBackendKind,
+    /// Process keys are posted to instead of the frontmost app.
+    target: Option<i32>,
+    /// Keyboard posting to a process, with its pid, kept so keys pressed
+    /// in it are released with the same modifier flags.
+    app_keyboard: Option<(i32, Box<dyn KeyboardBackend>)>,
+    last_injection: Option<Instant>,
+    /// Pixels not yet scrolled as a whole line, horizontal and vertical.
+    #[cfg(not(target_os = "macos"))]
+    pixel_remainder: (i32, i32),
 }
 
 // SAFETY: This is safe because we're only accessing Enigo through a Mutex,
@@ -15,54 +63,211 @@ unsafe impl Sync for Performer {}
 impl Performer {
     /// Create a new performer.
     pub fn new() -> Result<Self, NewConError> {
-        let settings = Settings::default();
+        let settings = Settings {
+            event_source_user_data: Some(SYNTHETIC_EVENT_TAG),
+            windows_dw_extra_info: Some(SYNTHETIC_EVENT_TAG as usize),
+            ..Settings::default()
+        };
         let enigo = Enigo::new(&settings)?;
-        Ok(Self { enigo })
+        Ok(Self {
+            enigo,
+            keyboard: None,
+            keyboard_kind: KeyboardBackendKind::Enigo,
+            target: None,
+            app_keyboard: None,
+            last_injection: None,
+            #[cfg(not(target_os = "macos"))]
+            pixel_remainder: (0, 0),
+        })
+    }
+
+    /// Returns when input was injected last.
+    pub fn last_injection(&self) -> Option<Instant> {
+        self.last_injection
+    }
+
+    /// Returns the backend keys are sent with.
+    pub fn keyboard_backend(&self) -> KeyboardBackendKind {
+        self.keyboard_kind
+    }
+
+    /// Switches the backend keys are sent with. The mouse is always moved
+    /// by enigo.
+    pub fn set_keyboard_backend(
+        &mut self,
+        kind: KeyboardBackendKind,
+    ) -> InputResult<()> {
+        self.keyboard = match kind {
+            KeyboardBackendKind::Enigo => None,
+            #[cfg(target_os = "macos")]
+            KeyboardBackendKind::Native => {
+                Some(Box::new(crate::native_keyboard::NativeKeyboard::new()?))
+            }
+            #[cfg(not(target_os = "macos"))]
+            KeyboardBackendKind::Native => {
+                return Err(enigo::InputError::Simulate(
+                    "the native keyboard backend is only supported on macOS",
+                ));
+            }
+        };
+        self.keyboard_kind = kind;
+        Ok(())
+    }
+
+    /// Returns the process keys are posted to, `None` for the frontmost
+    /// app.
+    pub fn target(&self) -> Option<i32> {
+        self.target
+    }
+
+    /// Posts keys to the process `pid` instead of the frontmost app, so
+    /// apps in the background can be controlled (macOS only). Keys are
+    /// always posted natively then, whatever the keyboard backend.
+    pub fn set_target(&mut self, pid: Option<i32>) -> InputResult<()> {
+        let cached = self.app_keyboard.as_ref().map(|(pid, _)| *pid);
+        if let Some(pid) = pid.filter(|pid| cached != Some(*pid)) {
+            #[cfg(target_os = "macos")]
+            {
+                let keyboard = crate::native_keyboard::NativeKeyboard::for_pid(pid)?;
+                self.app_keyboard = Some((pid, Box::new(keyboard)));
+            }
+            #[cfg(not(target_os = "macos"))]
+            {
+                let _ = pid;
+                return Err(enigo::InputError::Simulate(
+                    "posting keys to an app is only supported on macOS",
+                ));
+            }
+        }
+        self.target = pid;
+        Ok(())
+    }
+
+    fn keyboard(&mut self) -> &mut dyn KeyboardBackend {
+        if let (Some(_), Some((_, keyboard))) =
+            (self.target, self.app_keyboard.as_mut())
+        {
+            return keyboard.as_mut();
+        }
+        match self.keyboard.as_deref_mut() {
+            Some(keyboard) => keyboard,
+            None => &mut self.enigo,
+        }
     }
 
     /// Perform key combo.
     /// This will press and release the keys in the key combo.
     pub fn perform(&mut self, key_combo: &KeyCombo) -> InputResult<()> {
-        key_combo.perform(&mut self.enigo)
+        self.last_injection = Some(Instant::now());
+        key_combo.perform(self.keyboard())
+    }
+
+    /// Perform key combos one after another, e.g. a macro.
+    pub fn perform_all(&mut self, key_combos: &[KeyCombo]) -> InputResult<()> {
+        for key_combo in key_combos {
+            self.perform(key_combo)?;
+        }
+        Ok(())
     }
 
     /// Press keys.
     pub fn press(&mut self, key_combo: &KeyCombo) -> InputResult<()> {
-        key_combo.press(&mut self.enigo)
+        self.last_injection = Some(Instant::now());
+        key_combo.press(self.keyboard())
     }
 
     /// Release keys.
     pub fn release(&mut self, key_combo: &KeyCombo) -> InputResult<()> {
-        key_combo.release(&mut self.enigo)
+        self.last_injection = Some(Instant::now());
+        key_combo.release(self.keyboard())
+    }
+
+    /// Sends a single key event of a combo, e.g. to wait between the events
+    /// of a combo without blocking.
+    pub fn send(&mut self, event: &KeyEvent) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        event.send(self.keyboard())
+    }
+
+    /// Trigger a system function.
+    pub fn system(&mut self, action: SystemAction) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        action.perform(self.keyboard())
+    }
+
+    /// Switch to another Space.
+    pub fn switch_space(&mut self, target: SpaceTarget) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        target.switch(self.keyboard())
     }
 
     /// Move mouse.
     pub fn mouse_move(&mut self, x: i32, y: i32) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
         self.enigo.move_mouse(x, y, Coordinate::Rel)
     }
 
+    /// Click a mouse button.
+    pub fn click(&mut self, button: MouseButton) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        self.enigo.button(button.into(), Direction::Click)
+    }
+
+    /// Jump the cursor to another position or display.
+    pub fn warp_mouse(&mut self, target: MouseTarget) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        target.warp(&mut self.enigo)
+    }
+
     /// Scroll horizontally.
-    /// Uses macOS specific smooth scrolling.
-    #[cfg(target_os = "macos")]
-    pub fn scroll_x(&mut self, value: i32) -> InputResult<()> {
-        self.enigo.smooth_scroll(value, Axis::Horizontal)
+    pub fn scroll_x(&mut self, value: i32, unit: ScrollUnit) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        self.scroll(value, Axis::Horizontal, unit)
     }
 
     /// Scroll vertically.
-    /// Uses macOS specific smooth scrolling.
-    #[cfg(target_os = "macos")]
-    pub fn scroll_y(&mut self, value: i32) -> InputResult<()> {
-        self.enigo.smooth_scroll(value, Axis::Vertical)
+    pub fn scroll_y(&mut self, value: i32, unit: ScrollUnit) -> InputResult<()> {
+        self.last_injection = Some(Instant::now());
+        self.scroll(value, Axis::Vertical, unit)
     }
 
-    /// Fallback for non-macOS systems
-    #[cfg(not(target_os = "macos"))]
-    pub fn scroll_x(&mut self, value: i32) -> InputResult<()> {
-        self.enigo.scroll(value, Axis::Horizontal)
+    #[cfg(target_os = "macos")]
+    fn scroll(
+        &mut self,
+        value: i32,
+        axis: Axis,
+        unit: ScrollUnit,
+    ) -> InputResult<()> {
+        match unit {
+            ScrollUnit::Lines => self.enigo.scroll(value, axis),
+            ScrollUnit::Pixels => self.enigo.smooth_scroll(value, axis),
+        }
     }
 
+    /// Fallback for non-macOS systems, pixels add up to lines.
     #[cfg(not(target_os = "macos"))]
-    pub fn scroll_y(&mut self, value: i32) -> InputResult<()> {
-        self.enigo.scroll(value, Axis::Vertical)
+    fn scroll(
+        &mut self,
+        value: i32,
+        axis: Axis,
+        unit: ScrollUnit,
+    ) -> InputResult<()> {
+        let lines = match unit {
+            ScrollUnit::Lines => value,
+            ScrollUnit::Pixels => {
+                let remainder = match axis {
+                    Axis::Horizontal => &mut self.pixel_remainder.0,
+                    Axis::Vertical => &mut self.pixel_remainder.1,
+                };
+                *remainder += value;
+                let lines = *remainder / PIXELS_PER_LINE;
+                *remainder -= lines * PIXELS_PER_LINE;
+                lines
+            }
+        };
+        if lines == 0 {
+            return Ok(());
+        }
+        self.enigo.scroll(lines, axis)
     }
 }
@@ -0,0 +1,116 @@
+//! Pre-flight checks of the macOS privacy permissions gamacros needs.
+//!
+//! The checks ask the system whether posting an event or opening HID
+//! devices would be allowed, without doing either or prompting the user.
+//! Other platforms have no such permissions and report them as granted.
+
+use std::fmt;
+use std::io;
+use std::process::Command;
+
+/// A macOS privacy permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Sending key and mouse events.
+    Accessibility,
+    /// Reading HID devices.
+    InputMonitoring,
+}
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    /// `kIOHIDRequestTypeListenEvent`
+    pub(super) const IOHID_REQUEST_TYPE_LISTEN_EVENT: u32 = 1;
+    /// `kIOHIDAccessTypeGranted`
+    pub(super) const IOHID_ACCESS_TYPE_GRANTED: u32 = 0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub(super) fn CGPreflightPostEventAccess() -> bool;
+    }
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        pub(super) fn IOHIDCheckAccess(request: u32) -> u32;
+    }
+}
+
+impl Permission {
+    pub const ALL: [Permission; 2] =
+        [Permission::Accessibility, Permission::InputMonitoring];
+
+    /// Name of the permission in System Settings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Permission::Accessibility => "Accessibility",
+            Permission::InputMonitoring => "Input Monitoring",
+        }
+    }
+
+    /// What gamacros cannot do without the permission.
+    pub fn purpose(&self) -> &'static str {
+        match self {
+            Permission::Accessibility => "send key presses and mouse events",
+            Permission::InputMonitoring => "read controllers connected over HID",
+        }
+    }
+
+    /// URL of the System Settings pane that grants the permission.
+    pub fn settings_url(&self) -> &'static str {
+        match self {
+            Permission::Accessibility => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility"
+            }
+            Permission::InputMonitoring => {
+                "x-apple.systempreferences:com.apple.preference.security?Privacy_ListenEvent"
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn is_granted(&self) -> bool {
+        match self {
+            Permission::Accessibility => unsafe {
+                ffi::CGPreflightPostEventAccess()
+            },
+            Permission::InputMonitoring => unsafe {
+                ffi::IOHIDCheckAccess(ffi::IOHID_REQUEST_TYPE_LISTEN_EVENT)
+                    == ffi::IOHID_ACCESS_TYPE_GRANTED
+            },
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn is_granted(&self) -> bool {
+        true
+    }
+
+    /// Opens the System Settings pane that grants the permission.
+    pub fn open_settings(&self) -> io::Result<()> {
+        if !cfg!(target_os = "macos") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "system settings are only available on macOS",
+            ));
+        }
+        let status = Command::new("open").arg(self.settings_url()).status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("open exited with {status}")));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Permissions the process does not have.
+pub fn missing_permissions() -> Vec<Permission> {
+    Permission::ALL
+        .into_iter()
+        .filter(|permission| !permission.is_granted())
+        .collect()
+}
@@ -0,0 +1,339 @@
+use std::fmt;
+use std::str::FromStr;
+
+use enigo::{InputError, InputResult};
+use serde::{Deserialize, Serialize};
+
+use crate::KeyboardBackend;
+
+/// Highest desktop number macOS has a "Switch to Desktop" shortcut for.
+const MAX_SPACE_INDEX: u8 = 16;
+
+/// The Space (virtual desktop) to switch to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpaceTarget {
+    Next,
+    Previous,
+    /// A Space on the active display, numbered from 1.
+    Index(u8),
+}
+
+impl SpaceTarget {
+    pub(crate) fn switch(
+        &self,
+        keyboard: &mut dyn KeyboardBackend,
+    ) -> InputResult<()> {
+        switch(*self, keyboard)
+    }
+}
+
+impl fmt::Display for SpaceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpaceTarget::Next => f.write_str("next"),
+            SpaceTarget::Previous => f.write_str("prev"),
+            SpaceTarget::Index(index) => write!(f, "{index}"),
+        }
+    }
+}
+
+impl FromStr for SpaceTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "next" => Ok(SpaceTarget::Next),
+            "prev" | "previous" => Ok(SpaceTarget::Previous),
+            name => name
+                .parse::<u8>()
+                .ok()
+                .filter(|index| (1..=MAX_SPACE_INDEX).contains(index))
+                .map(SpaceTarget::Index)
+                .ok_or_else(|| format!("Invalid space: {s}")),
+        }
+    }
+}
+
+/// Switches Spaces with the Mission Control shortcuts as configured in
+/// System Settings, so rebound or enabled "Switch to Desktop" shortcuts are
+/// honoured. Moving to a numbered Space without its own shortcut steps
+/// through the Spaces between, located with the window server Space list.
+#[cfg(target_os = "macos")]
+fn switch(
+    target: SpaceTarget,
+    keyboard: &mut dyn KeyboardBackend,
+) -> InputResult<()> {
+    use native::{current_space, hotkey, Hotkey, MOVE_LEFT, MOVE_RIGHT};
+
+    let steps = match target {
+        SpaceTarget::Next => 1,
+        SpaceTarget::Previous => -1,
+        SpaceTarget::Index(index) => {
+            if let Some(shortcut) = hotkey(Hotkey::desktop(index)) {
+                return shortcut.click(keyboard);
+            }
+            let (current, count) = current_space()
+                .ok_or(InputError::Simulate("failed to read the current space"))?;
+            let index = usize::from(index) - 1;
+            if index >= count {
+                return Err(InputError::Simulate("space does not exist"));
+            }
+            index as isize - current as isize
+        }
+    };
+    let id = if steps > 0 { MOVE_RIGHT } else { MOVE_LEFT };
+    let shortcut = hotkey(id).ok_or(InputError::Simulate(
+        "the Mission Control shortcut to move between spaces is disabled",
+    ))?;
+    for _ in 0..steps.unsigned_abs() {
+        shortcut.click(keyboard)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn switch(
+    _target: SpaceTarget,
+    _keyboard: &mut dyn KeyboardBackend,
+) -> InputResult<()> {
+    Err(InputError::Simulate(
+        "switching spaces is only supported on macOS",
+    ))
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    //! Window server Space list and the symbolic hotkeys preferences.
+
+    use std::ffi::c_void;
+
+    use enigo::{InputResult, Key as EnigoKey};
+    use gamacros_cf::{CFStringRef, CFTypeRef, CfObject};
+
+    use crate::system::chord;
+    use crate::KeyboardBackend;
+
+    /// `kCFNumberSInt64Type`
+    const CF_NUMBER_SINT64: isize = 4;
+
+    /// "Move left a space"
+    pub(super) const MOVE_LEFT: u32 = 79;
+    /// "Move right a space"
+    pub(super) const MOVE_RIGHT: u32 = 81;
+    /// "Switch to Desktop 1", the following desktops count up from it.
+    const DESKTOP_1: u32 = 118;
+
+    const KEY_LEFT_ARROW: u16 = 0x7B;
+    const KEY_RIGHT_ARROW: u16 = 0x7C;
+
+    // NSEvent modifier flags stored in the hotkey parameters.
+    const FLAG_SHIFT: i64 = 1 << 17;
+    const FLAG_CONTROL: i64 = 1 << 18;
+    const FLAG_OPTION: i64 = 1 << 19;
+    const FLAG_COMMAND: i64 = 1 << 20;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFEqual(a: CFTypeRef, b: CFTypeRef) -> u8;
+        fn CFGetTypeID(cf: CFTypeRef) -> usize;
+        fn CFBooleanGetTypeID() -> usize;
+        fn CFBooleanGetValue(boolean: CFTypeRef) -> u8;
+        fn CFNumberGetTypeID() -> usize;
+        fn CFNumberGetValue(
+            number: CFTypeRef,
+            kind: isize,
+            value: *mut c_void,
+        ) -> u8;
+        fn CFArrayGetTypeID() -> usize;
+        fn CFArrayGetCount(array: CFTypeRef) -> isize;
+        fn CFArrayGetValueAtIndex(array: CFTypeRef, index: isize) -> CFTypeRef;
+        fn CFDictionaryGetTypeID() -> usize;
+        fn CFDictionaryGetValue(dict: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+        fn CFPreferencesCopyAppValue(
+            key: CFStringRef,
+            application: CFStringRef,
+        ) -> CFTypeRef;
+    }
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGSMainConnectionID() -> i32;
+        fn CGSCopyManagedDisplaySpaces(connection: i32) -> CFTypeRef;
+        fn CGSCopyActiveMenuBarDisplayIdentifier(connection: i32) -> CFStringRef;
+    }
+
+    /// Value of `key` in a dictionary, borrowed from the dictionary.
+    unsafe fn get(dict: CFTypeRef, key: &str) -> Option<CFTypeRef> {
+        if CFGetTypeID(dict) != CFDictionaryGetTypeID() {
+            return None;
+        }
+        let key = CfObject::string(key)?;
+        let value = CFDictionaryGetValue(dict, key.as_ptr());
+        (!value.is_null()).then_some(value)
+    }
+
+    /// Items of an array, borrowed from the array.
+    unsafe fn items(array: CFTypeRef) -> Vec<CFTypeRef> {
+        if CFGetTypeID(array) != CFArrayGetTypeID() {
+            return Vec::new();
+        }
+        (0..CFArrayGetCount(array))
+            .map(|index| CFArrayGetValueAtIndex(array, index))
+            .collect()
+    }
+
+    unsafe fn number(value: CFTypeRef) -> Option<i64> {
+        if CFGetTypeID(value) != CFNumberGetTypeID() {
+            return None;
+        }
+        let mut number = 0i64;
+        let ok = CFNumberGetValue(
+            value,
+            CF_NUMBER_SINT64,
+            (&mut number as *mut i64).cast(),
+        );
+        (ok != 0).then_some(number)
+    }
+
+    /// Reads a flag stored either as a boolean or as a number.
+    unsafe fn flag(value: CFTypeRef) -> bool {
+        if CFGetTypeID(value) == CFBooleanGetTypeID() {
+            CFBooleanGetValue(value) != 0
+        } else {
+            number(value).is_some_and(|number| number != 0)
+        }
+    }
+
+    /// A keyboard shortcut from the symbolic hotkeys preferences.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(super) struct Hotkey {
+        key_code: u16,
+        flags: i64,
+    }
+
+    impl Hotkey {
+        /// Symbolic hotkey id of "Switch to Desktop `index`".
+        pub(super) fn desktop(index: u8) -> u32 {
+            DESKTOP_1 + u32::from(index) - 1
+        }
+
+        /// Shortcut macOS ships with, `None` when it is disabled by default.
+        fn default_for(id: u32) -> Option<Self> {
+            let key_code = match id {
+                MOVE_LEFT => KEY_LEFT_ARROW,
+                MOVE_RIGHT => KEY_RIGHT_ARROW,
+                _ => return None,
+            };
+            Some(Hotkey {
+                key_code,
+                flags: FLAG_CONTROL,
+            })
+        }
+
+        pub(super) fn click(
+            &self,
+            keyboard: &mut dyn KeyboardBackend,
+        ) -> InputResult<()> {
+            let modifiers: Vec<EnigoKey> = [
+                (FLAG_CONTROL, EnigoKey::Control),
+                (FLAG_OPTION, EnigoKey::Option),
+                (FLAG_SHIFT, EnigoKey::Shift),
+                (FLAG_COMMAND, EnigoKey::Meta),
+            ]
+            .into_iter()
+            .filter(|(flag, _)| self.flags & flag != 0)
+            .map(|(_, key)| key)
+            .collect();
+            chord(
+                keyboard,
+                &modifiers,
+                EnigoKey::Other(u32::from(self.key_code)),
+            )
+        }
+    }
+
+    /// The shortcut bound to symbolic hotkey `id`, `None` when it is disabled.
+    pub(super) fn hotkey(id: u32) -> Option<Hotkey> {
+        let key = CfObject::string("AppleSymbolicHotKeys")?;
+        let domain = CfObject::string("com.apple.symbolichotkeys")?;
+        // SAFETY: the copied dictionary is owned, values read from it are
+        // borrowed and used while it is alive.
+        unsafe {
+            let Some(hotkeys) = CfObject::new(CFPreferencesCopyAppValue(
+                key.as_ptr(),
+                domain.as_ptr(),
+            )) else {
+                return Hotkey::default_for(id);
+            };
+            let Some(entry) = get(hotkeys.as_ptr(), &id.to_string()) else {
+                return Hotkey::default_for(id);
+            };
+            if !get(entry, "enabled").is_some_and(|enabled| flag(enabled)) {
+                return None;
+            }
+            let parameters = items(get(get(entry, "value")?, "parameters")?);
+            let key_code = number(*parameters.get(1)?)?;
+            let flags = number(*parameters.get(2)?)?;
+            Some(Hotkey {
+                key_code: u16::try_from(key_code).ok()?,
+                flags,
+            })
+        }
+    }
+
+    /// Index of the current Space and the number of Spaces on the display
+    /// with the active menu bar, full screen apps included.
+    pub(super) fn current_space() -> Option<(usize, usize)> {
+        // SAFETY: the copied objects are owned, values read from them are
+        // borrowed and used while they are alive.
+        unsafe {
+            let connection = CGSMainConnectionID();
+            let displays = CfObject::new(CGSCopyManagedDisplaySpaces(connection))?;
+            let active =
+                CfObject::new(CGSCopyActiveMenuBarDisplayIdentifier(connection));
+            let displays = items(displays.as_ptr());
+            // With a single set of Spaces for all displays the only entry is
+            // named "Main" instead of the display identifier.
+            let display = active
+                .and_then(|active| {
+                    displays.iter().copied().find(|display| {
+                        get(*display, "Display Identifier")
+                            .is_some_and(|id| CFEqual(id, active.as_ptr()) != 0)
+                    })
+                })
+                .or_else(|| displays.first().copied())?;
+            let space_id = |space: CFTypeRef| {
+                get(space, "ManagedSpaceID").and_then(|id| number(id))
+            };
+            let current = space_id(get(display, "Current Space")?)?;
+            let spaces = items(get(display, "Spaces")?);
+            let index = spaces
+                .iter()
+                .position(|space| space_id(*space) == Some(current))?;
+            Some((index, spaces.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_targets() {
+        assert_eq!("next".parse::<SpaceTarget>(), Ok(SpaceTarget::Next));
+        assert_eq!(" Prev ".parse::<SpaceTarget>(), Ok(SpaceTarget::Previous));
+        assert_eq!("previous".parse::<SpaceTarget>(), Ok(SpaceTarget::Previous));
+        assert_eq!("3".parse::<SpaceTarget>(), Ok(SpaceTarget::Index(3)));
+        assert!("0".parse::<SpaceTarget>().is_err());
+        assert!("17".parse::<SpaceTarget>().is_err());
+        assert!("last".parse::<SpaceTarget>().is_err());
+        for target in [
+            SpaceTarget::Next,
+            SpaceTarget::Previous,
+            SpaceTarget::Index(5),
+        ] {
+            assert_eq!(target.to_string().parse::<SpaceTarget>(), Ok(target));
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+
+use enigo::{Direction::Click, InputError, InputResult, Key as EnigoKey};
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_os = "macos")]
+use crate::Key;
+use crate::KeyboardBackend;
+
+/// A system function that can be triggered without writing a shell command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SystemAction {
+    LockScreen,
+    SleepDisplay,
+    MissionControl,
+    Launchpad,
+    Screenshot,
+    PlayPause,
+}
+
+impl SystemAction {
+    pub const ALL: [SystemAction; 6] = [
+        SystemAction::LockScreen,
+        SystemAction::SleepDisplay,
+        SystemAction::MissionControl,
+        SystemAction::Launchpad,
+        SystemAction::Screenshot,
+        SystemAction::PlayPause,
+    ];
+
+    /// Name of the action as used in profiles.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SystemAction::LockScreen => "lock_screen",
+            SystemAction::SleepDisplay => "sleep_display",
+            SystemAction::MissionControl => "mission_control",
+            SystemAction::Launchpad => "launchpad",
+            SystemAction::Screenshot => "screenshot",
+            SystemAction::PlayPause => "play_pause",
+        }
+    }
+
+    pub(crate) fn perform(
+        &self,
+        keyboard: &mut dyn KeyboardBackend,
+    ) -> InputResult<()> {
+        match self {
+            SystemAction::LockScreen => lock_screen(keyboard),
+            SystemAction::SleepDisplay => sleep_display(),
+            SystemAction::MissionControl => mission_control(keyboard),
+            SystemAction::Launchpad => launchpad(keyboard),
+            SystemAction::Screenshot => screenshot(keyboard),
+            SystemAction::PlayPause => keyboard.key(EnigoKey::MediaPlayPause, Click),
+        }
+    }
+}
+
+impl fmt::Display for SystemAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SystemAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        SystemAction::ALL
+            .into_iter()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| format!("Invalid system action: {s}"))
+    }
+}
+
+/// Holds `modifiers` while clicking `key`.
+#[cfg(target_os = "macos")]
+pub(crate) fn chord(
+    keyboard: &mut dyn KeyboardBackend,
+    modifiers: &[EnigoKey],
+    key: EnigoKey,
+) -> InputResult<()> {
+    use enigo::Direction::{Press, Release};
+
+    for modifier in modifiers {
+        keyboard.key(*modifier, Press)?;
+    }
+    let result = keyboard.key(key, Click);
+    for modifier in modifiers.iter().rev() {
+        keyboard.key(*modifier, Release)?;
+    }
+    result
+}
+
+/// Starts a system utility without capturing its output. It is not waited
+/// for, so a slow utility never holds up the caller; a thread reaps it.
+fn run(program: &str, args: &[&str]) -> InputResult<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| InputError::Simulate("failed to run system utility"))?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn lock_screen(keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    chord(
+        keyboard,
+        &[EnigoKey::Control, EnigoKey::Meta],
+        Key::Unicode('q').into(),
+    )
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn lock_screen(_keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    run("loginctl", &["lock-session"])
+}
+
+#[cfg(target_os = "windows")]
+fn lock_screen(_keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    run("rundll32.exe", &["user32.dll,LockWorkStation"])
+}
+
+#[cfg(target_os = "macos")]
+fn sleep_display() -> InputResult<()> {
+    run("pmset", &["displaysleepnow"])
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn sleep_display() -> InputResult<()> {
+    run("xset", &["dpms", "force", "off"])
+}
+
+#[cfg(target_os = "windows")]
+fn sleep_display() -> InputResult<()> {
+    Err(InputError::Simulate(
+        "sleep_display is not supported on Windows",
+    ))
+}
+
+#[cfg(target_os = "macos")]
+fn mission_control(keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    keyboard.key(EnigoKey::MissionControl, Click)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn mission_control(_keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    Err(InputError::Simulate(
+        "mission_control is only supported on macOS",
+    ))
+}
+
+#[cfg(target_os = "macos")]
+fn launchpad(keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    keyboard.key(EnigoKey::Launchpad, Click)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn launchpad(_keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    Err(InputError::Simulate("launchpad is only supported on macOS"))
+}
+
+/// Captures the whole screen the way the system shortcut does.
+#[cfg(target_os = "macos")]
+fn screenshot(keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    // The system shortcut is bound to the key position, not the character
+    const ANSI_3: u32 = 0x14;
+    chord(
+        keyboard,
+        &[EnigoKey::Meta, EnigoKey::Shift],
+        EnigoKey::Other(ANSI_3),
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+fn screenshot(keyboard: &mut dyn KeyboardBackend) -> InputResult<()> {
+    keyboard.key(EnigoKey::PrintScr, Click)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names() {
+        for action in SystemAction::ALL {
+            assert_eq!(action.name().parse::<SystemAction>(), Ok(action));
+        }
+        assert_eq!(
+            " Lock_Screen ".parse::<SystemAction>(),
+            Ok(SystemAction::LockScreen)
+        );
+        assert!("reboot".parse::<SystemAction>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_utilities_without_waiting() {
+        let start = std::time::Instant::now();
+        run("sleep", &["2"]).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert!(run("gamacros-missing-utility", &[]).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use gamacros_gamepad::{BackendKind, ControllerEvent, ControllerManager};
+
+/// Drives the controller backend from the main loop instead of a
+/// runtime thread and prints events until interrupted.
+fn main() {
+    let (manager, mut runtime) =
+        match ControllerManager::with_pump(BackendKind::default()) {
+            Ok(pair) => pair,
+            Err(err) => {
+                eprintln!("Failed to open gamepad backend: {err}");
+                std::process::exit(1);
+            }
+        };
+
+    loop {
+        // An embedder would render a frame or handle its own events here
+        for timed in runtime.pump_timeout(Duration::from_millis(16)) {
+            match timed.event {
+                ControllerEvent::Connected(info) => {
+                    println!("connected: {} ({})", info.name, info.id);
+                    if let Some(handle) = manager.controller(info.id) {
+                        let _ = handle.rumble(0.5, 0.5, Duration::from_millis(100));
+                    }
+                }
+                event => println!("{event:?}"),
+            }
+        }
+    }
+}
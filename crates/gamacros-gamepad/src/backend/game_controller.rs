@@ -0,0 +1,278 @@
+use std::ffi::CStr;
+use std::thread;
+use std::time::Duration;
+
+use objc2::rc::{autoreleasepool, Retained};
+use objc2::runtime::{AnyObject, Bool, MessageReceiver, Sel};
+use objc2::{class, msg_send};
+use objc2_foundation::{NSArray, NSString};
+
+use crate::events::ControllerEvent;
+use crate::types::{Axis, Button, ControllerId, ControllerInfo};
+use crate::{Error, Result};
+
+use super::GamepadBackend;
+
+#[link(name = "GameController", kind = "framework")]
+extern "C" {}
+
+/// `GCExtendedGamepad` button properties and the buttons they map to.
+const BUTTONS: [(&CStr, Button); 13] = [
+    (c"buttonA", Button::A),
+    (c"buttonB", Button::B),
+    (c"buttonX", Button::X),
+    (c"buttonY", Button::Y),
+    (c"buttonOptions", Button::Back),
+    (c"buttonHome", Button::Guide),
+    (c"buttonMenu", Button::Start),
+    (c"leftThumbstickButton", Button::LeftStick),
+    (c"rightThumbstickButton", Button::RightStick),
+    (c"leftShoulder", Button::LeftShoulder),
+    (c"rightShoulder", Button::RightShoulder),
+    (c"leftTrigger", Button::LeftTrigger),
+    (c"rightTrigger", Button::RightTrigger),
+];
+
+/// `GCControllerDirectionPad` properties of the d-pad.
+const DPAD: [(&CStr, Button); 4] = [
+    (c"up", Button::DPadUp),
+    (c"down", Button::DPadDown),
+    (c"left", Button::DPadLeft),
+    (c"right", Button::DPadRight),
+];
+
+const AXES: [Axis; 6] = [
+    Axis::LeftX,
+    Axis::LeftY,
+    Axis::RightX,
+    Axis::RightY,
+    Axis::LeftTrigger,
+    Axis::RightTrigger,
+];
+
+/// Input snapshot of a single extended gamepad.
+#[derive(Default, Clone, Copy, PartialEq)]
+struct PadState {
+    buttons: [bool; BUTTONS.len() + DPAD.len()],
+    axes: [f32; AXES.len()],
+}
+
+struct Pad {
+    id: ControllerId,
+    controller: Retained<AnyObject>,
+    state: PadState,
+}
+
+/// GameController.framework backend.
+///
+/// The framework has no blocking event API usable without a run loop,
+/// so connected controllers are polled and diffed on every pump.
+pub(crate) struct GameControllerBackend {
+    pads: Vec<Pad>,
+    next_id: ControllerId,
+}
+
+impl GameControllerBackend {
+    pub(crate) fn new() -> Self {
+        Self {
+            pads: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn poll(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        autoreleasepool(|_| {
+            let controllers: Retained<NSArray<AnyObject>> =
+                unsafe { msg_send![class!(GCController), controllers] };
+            let controllers = controllers.to_vec();
+
+            // Disconnected controllers
+            self.pads.retain(|pad| {
+                let present = controllers
+                    .iter()
+                    .any(|c| std::ptr::eq(&**c, &*pad.controller));
+                if !present {
+                    emit(ControllerEvent::Disconnected(pad.id));
+                }
+                present
+            });
+
+            for controller in controllers {
+                // Only extended gamepads expose the full button layout.
+                let Some(gamepad) =
+                    (unsafe { property(&controller, c"extendedGamepad") })
+                else {
+                    continue;
+                };
+                let state = unsafe { read_state(&gamepad) };
+
+                let index = self
+                    .pads
+                    .iter()
+                    .position(|pad| std::ptr::eq(&*pad.controller, &*controller));
+                let pad = match index {
+                    Some(index) => &mut self.pads[index],
+                    None => {
+                        let id = self.next_id;
+                        self.next_id += 1;
+                        emit(ControllerEvent::Connected(ControllerInfo {
+                            id,
+                            name: unsafe { vendor_name(&controller) },
+                            supports_rumble: false,
+                            vendor_id: 0,
+                            product_id: 0,
+                            serial: None,
+                            extra_buttons: Vec::new(),
+                        }));
+                        self.pads.push(Pad {
+                            id,
+                            controller,
+                            state: PadState::default(),
+                        });
+                        self.pads.last_mut().expect("pad was just pushed")
+                    }
+                };
+                diff(pad.id, &pad.state, &state, emit);
+                pad.state = state;
+            }
+        });
+    }
+}
+
+impl GamepadBackend for GameControllerBackend {
+    fn enumerate(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        self.poll(emit);
+    }
+
+    fn pump(&mut self, timeout: Duration, emit: &mut dyn FnMut(ControllerEvent)) {
+        thread::sleep(timeout);
+        self.poll(emit);
+    }
+
+    fn rumble(
+        &mut self,
+        _id: ControllerId,
+        _low: u16,
+        _high: u16,
+        _ms: u32,
+    ) -> Result<()> {
+        Err(Error::Unsupported)
+    }
+
+    fn stop_rumble(&mut self, _id: ControllerId) -> Result<()> {
+        Err(Error::Unsupported)
+    }
+
+    fn controller_ids(&self) -> Vec<ControllerId> {
+        self.pads.iter().map(|pad| pad.id).collect()
+    }
+}
+
+fn diff(
+    id: ControllerId,
+    previous: &PadState,
+    current: &PadState,
+    emit: &mut dyn FnMut(ControllerEvent),
+) {
+    let buttons = BUTTONS.iter().chain(DPAD.iter()).map(|(_, b)| *b);
+    for ((button, was), is) in buttons
+        .zip(previous.buttons.iter())
+        .zip(current.buttons.iter())
+    {
+        if *is && !*was {
+            emit(ControllerEvent::ButtonPressed { id, button });
+        } else if !*is && *was {
+            emit(ControllerEvent::ButtonReleased { id, button });
+        }
+    }
+    for ((axis, was), is) in AXES
+        .iter()
+        .zip(previous.axes.iter())
+        .zip(current.axes.iter())
+    {
+        if was != is {
+            emit(ControllerEvent::AxisMotion {
+                id,
+                axis: *axis,
+                value: *is,
+            });
+        }
+    }
+}
+
+/// Reads an object property by selector name.
+///
+/// # Safety
+///
+/// `name` must be a getter returning an Objective-C object (or nil).
+unsafe fn property(object: &AnyObject, name: &CStr) -> Option<Retained<AnyObject>> {
+    let ptr: *mut AnyObject = object.send_message(Sel::register(name), ());
+    Retained::retain(ptr)
+}
+
+/// # Safety
+///
+/// `input` must be a `GCControllerButtonInput`.
+unsafe fn is_pressed(input: &AnyObject) -> bool {
+    let pressed: Bool = msg_send![input, isPressed];
+    pressed.as_bool()
+}
+
+/// # Safety
+///
+/// `input` must respond to `value` with a float (button or axis input).
+unsafe fn value(input: &AnyObject) -> f32 {
+    msg_send![input, value]
+}
+
+/// # Safety
+///
+/// `pad` must be a `GCControllerDirectionPad`.
+unsafe fn stick(pad: Option<Retained<AnyObject>>) -> (f32, f32) {
+    let Some(pad) = pad else {
+        return (0.0, 0.0);
+    };
+    let x = property(&pad, c"xAxis").map_or(0.0, |axis| value(&axis));
+    let y = property(&pad, c"yAxis").map_or(0.0, |axis| value(&axis));
+    // GameController reports up as positive, SDL as negative.
+    (x, -y)
+}
+
+/// # Safety
+///
+/// `gamepad` must be a `GCExtendedGamepad`.
+unsafe fn read_state(gamepad: &AnyObject) -> PadState {
+    let mut state = PadState::default();
+
+    for (i, (name, _)) in BUTTONS.iter().enumerate() {
+        // Optional buttons (home, options, thumbsticks) may be nil.
+        state.buttons[i] = property(gamepad, name).is_some_and(|b| is_pressed(&b));
+    }
+    if let Some(dpad) = property(gamepad, c"dpad") {
+        for (i, (name, _)) in DPAD.iter().enumerate() {
+            state.buttons[BUTTONS.len() + i] =
+                property(&dpad, name).is_some_and(|b| is_pressed(&b));
+        }
+    }
+
+    let (left_x, left_y) = stick(property(gamepad, c"leftThumbstick"));
+    let (right_x, right_y) = stick(property(gamepad, c"rightThumbstick"));
+    let trigger = |name| property(gamepad, name).map_or(0.0, |t| value(&t));
+    state.axes = [
+        left_x,
+        left_y,
+        right_x,
+        right_y,
+        trigger(c"leftTrigger"),
+        trigger(c"rightTrigger"),
+    ];
+    state
+}
+
+/// # Safety
+///
+/// `controller` must be a `GCController`.
+unsafe fn vendor_name(controller: &AnyObject) -> String {
+    let name: Option<Retained<NSString>> = msg_send![controller, vendorName];
+    name.map_or_else(|| "Game Controller".to_string(), |name| name.to_string())
+}
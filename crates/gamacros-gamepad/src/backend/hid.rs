@@ -0,0 +1,580 @@
+use std::time::Duration;
+
+use crate::events::ControllerEvent;
+use crate::hid::HidDevice;
+use crate::mapping::ControllerMappings;
+use crate::types::{Button, ControllerId, ControllerInfo};
+use crate::{Error, Result};
+
+use super::{GamepadBackend, Waker};
+
+/// Ids of HID devices start here, far above ids of the wrapped backend.
+const FIRST_ID: ControllerId = 0x8000_0000;
+
+/// An open HID device and the state of its configured switches.
+struct Device {
+    id: ControllerId,
+    device: ffi::Device,
+    /// Elements reported as buttons, with the last seen state.
+    switches: Vec<(ffi::Element, Button, bool)>,
+}
+
+/// Reads HID devices such as foot pedals next to another backend.
+///
+/// IOKit is polled and diffed on every pump, like the GameController
+/// backend, so no callbacks run on the runtime thread.
+pub(crate) struct WithHid {
+    backend: Box<dyn GamepadBackend>,
+    config: Vec<HidDevice>,
+    manager: Option<ffi::Manager>,
+    devices: Vec<Device>,
+    next_id: ControllerId,
+}
+
+impl WithHid {
+    pub(crate) fn new(backend: Box<dyn GamepadBackend>) -> Self {
+        Self {
+            backend,
+            config: Vec::new(),
+            manager: None,
+            devices: Vec::new(),
+            next_id: FIRST_ID,
+        }
+    }
+
+    fn poll(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        let Some(manager) = self.manager.as_ref() else {
+            return;
+        };
+        let present = manager.devices();
+
+        self.devices.retain(|open| {
+            let connected = present.contains(&open.device);
+            if !connected {
+                emit(ControllerEvent::Disconnected(open.id));
+            }
+            connected
+        });
+
+        for device in present {
+            if self.devices.iter().any(|open| open.device == device) {
+                continue;
+            }
+            let ids = (device.vendor_id(), device.product_id());
+            let Some(config) = self.config.iter().find(|c| c.device == ids) else {
+                continue;
+            };
+            let switches = device
+                .elements()
+                .into_iter()
+                .filter_map(|element| {
+                    let (page, usage) = element.usage();
+                    let button = config
+                        .buttons
+                        .iter()
+                        .find(|b| b.usage_page == page && b.usage == usage)?
+                        .button;
+                    Some((element, button, false))
+                })
+                .collect();
+            let id = self.next_id;
+            self.next_id += 1;
+            emit(ControllerEvent::Connected(ControllerInfo {
+                id,
+                name: device.product_name().unwrap_or_else(|| "HID device".into()),
+                supports_rumble: false,
+                vendor_id: ids.0,
+                product_id: ids.1,
+                serial: device.serial(),
+                extra_buttons: Vec::new(),
+            }));
+            self.devices.push(Device {
+                id,
+                device,
+                switches,
+            });
+        }
+
+        for open in &mut self.devices {
+            for (element, button, pressed) in &mut open.switches {
+                let is = open.device.value(element).is_some_and(|v| v != 0);
+                if is == *pressed {
+                    continue;
+                }
+                *pressed = is;
+                let (id, button) = (open.id, *button);
+                emit(if is {
+                    ControllerEvent::ButtonPressed { id, button }
+                } else {
+                    ControllerEvent::ButtonReleased { id, button }
+                });
+            }
+        }
+    }
+}
+
+impl GamepadBackend for WithHid {
+    fn enumerate(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        self.backend.enumerate(emit);
+        self.poll(emit);
+    }
+
+    fn pump(&mut self, timeout: Duration, emit: &mut dyn FnMut(ControllerEvent)) {
+        self.backend.pump(timeout, emit);
+        self.poll(emit);
+    }
+
+    fn rumble(
+        &mut self,
+        id: ControllerId,
+        low: u16,
+        high: u16,
+        ms: u32,
+    ) -> Result<()> {
+        if id >= FIRST_ID {
+            return Err(Error::Unsupported);
+        }
+        self.backend.rumble(id, low, high, ms)
+    }
+
+    fn stop_rumble(&mut self, id: ControllerId) -> Result<()> {
+        if id >= FIRST_ID {
+            return Err(Error::Unsupported);
+        }
+        self.backend.stop_rumble(id)
+    }
+
+    fn controller_ids(&self) -> Vec<ControllerId> {
+        let mut ids = self.backend.controller_ids();
+        ids.extend(self.devices.iter().map(|open| open.id));
+        ids
+    }
+
+    fn waker(&self) -> Option<Waker> {
+        self.backend.waker()
+    }
+
+    fn is_idle(&self) -> bool {
+        // HID devices are polled after every pump
+        self.config.is_empty() && self.backend.is_idle()
+    }
+
+    fn load_mappings(
+        &mut self,
+        mappings: &ControllerMappings,
+        emit: &mut dyn FnMut(ControllerEvent),
+    ) -> Result<()> {
+        self.backend.load_mappings(mappings, emit)
+    }
+
+    fn load_hid_devices(
+        &mut self,
+        devices: &[HidDevice],
+        emit: &mut dyn FnMut(ControllerEvent),
+    ) -> Result<()> {
+        for open in self.devices.drain(..) {
+            emit(ControllerEvent::Disconnected(open.id));
+        }
+        self.config = devices.to_vec();
+        self.manager = None;
+        if devices.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<_> = devices.iter().map(|device| device.device).collect();
+        self.manager = Some(ffi::Manager::open(&ids).ok_or_else(|| {
+            Error::BackendInit("failed to open IOKit HID manager".into())
+        })?);
+        self.poll(emit);
+        Ok(())
+    }
+}
+
+/// Minimal IOKit HID and CoreFoundation bindings.
+mod ffi {
+    use std::ffi::{c_char, c_void, CStr};
+    use std::ptr::{self, NonNull};
+
+    use crate::DeviceId;
+
+    type CFTypeRef = *const c_void;
+    type CFIndex = isize;
+
+    const K_CF_NUMBER_SINT32_TYPE: CFIndex = 3;
+    const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    const K_IO_HID_OPTIONS_TYPE_NONE: u32 = 0;
+    const K_IO_RETURN_SUCCESS: i32 = 0;
+
+    #[repr(C)]
+    struct CFCallBacks {
+        _private: [u8; 0],
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        static kCFTypeArrayCallBacks: CFCallBacks;
+        static kCFTypeDictionaryKeyCallBacks: CFCallBacks;
+        static kCFTypeDictionaryValueCallBacks: CFCallBacks;
+        static kCFRunLoopDefaultMode: CFTypeRef;
+
+        fn CFRelease(cf: CFTypeRef);
+        fn CFRetain(cf: CFTypeRef) -> CFTypeRef;
+        fn CFGetTypeID(cf: CFTypeRef) -> usize;
+        fn CFNumberGetTypeID() -> usize;
+        fn CFStringGetTypeID() -> usize;
+        fn CFNumberCreate(
+            allocator: CFTypeRef,
+            the_type: CFIndex,
+            value_ptr: *const c_void,
+        ) -> CFTypeRef;
+        fn CFNumberGetValue(
+            number: CFTypeRef,
+            the_type: CFIndex,
+            value_ptr: *mut c_void,
+        ) -> u8;
+        fn CFStringCreateWithCString(
+            allocator: CFTypeRef,
+            c_str: *const c_char,
+            encoding: u32,
+        ) -> CFTypeRef;
+        fn CFStringGetCString(
+            string: CFTypeRef,
+            buffer: *mut c_char,
+            buffer_size: CFIndex,
+            encoding: u32,
+        ) -> u8;
+        fn CFDictionaryCreate(
+            allocator: CFTypeRef,
+            keys: *const CFTypeRef,
+            values: *const CFTypeRef,
+            count: CFIndex,
+            key_callbacks: *const CFCallBacks,
+            value_callbacks: *const CFCallBacks,
+        ) -> CFTypeRef;
+        fn CFArrayCreate(
+            allocator: CFTypeRef,
+            values: *const CFTypeRef,
+            count: CFIndex,
+            callbacks: *const CFCallBacks,
+        ) -> CFTypeRef;
+        fn CFArrayGetCount(array: CFTypeRef) -> CFIndex;
+        fn CFArrayGetValueAtIndex(array: CFTypeRef, index: CFIndex) -> CFTypeRef;
+        fn CFSetGetCount(set: CFTypeRef) -> CFIndex;
+        fn CFSetGetValues(set: CFTypeRef, values: *mut CFTypeRef);
+        fn CFRunLoopGetCurrent() -> CFTypeRef;
+        fn CFRunLoopRunInMode(
+            mode: CFTypeRef,
+            seconds: f64,
+            return_after_source_handled: u8,
+        ) -> i32;
+    }
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOHIDManagerCreate(allocator: CFTypeRef, options: u32) -> CFTypeRef;
+        fn IOHIDManagerSetDeviceMatchingMultiple(
+            manager: CFTypeRef,
+            multiple: CFTypeRef,
+        );
+        fn IOHIDManagerScheduleWithRunLoop(
+            manager: CFTypeRef,
+            run_loop: CFTypeRef,
+            mode: CFTypeRef,
+        );
+        fn IOHIDManagerUnscheduleFromRunLoop(
+            manager: CFTypeRef,
+            run_loop: CFTypeRef,
+            mode: CFTypeRef,
+        );
+        fn IOHIDManagerOpen(manager: CFTypeRef, options: u32) -> i32;
+        fn IOHIDManagerClose(manager: CFTypeRef, options: u32) -> i32;
+        fn IOHIDManagerCopyDevices(manager: CFTypeRef) -> CFTypeRef;
+        fn IOHIDDeviceGetProperty(device: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+        fn IOHIDDeviceCopyMatchingElements(
+            device: CFTypeRef,
+            matching: CFTypeRef,
+            options: u32,
+        ) -> CFTypeRef;
+        fn IOHIDDeviceGetValue(
+            device: CFTypeRef,
+            element: CFTypeRef,
+            value: *mut CFTypeRef,
+        ) -> i32;
+        fn IOHIDElementGetUsagePage(element: CFTypeRef) -> u32;
+        fn IOHIDElementGetUsage(element: CFTypeRef) -> u32;
+        fn IOHIDValueGetIntegerValue(value: CFTypeRef) -> CFIndex;
+    }
+
+    /// An owned CoreFoundation object, released on drop.
+    struct Cf(NonNull<c_void>);
+
+    impl Cf {
+        /// Takes ownership of an object from a Create or Copy function.
+        fn create(ptr: CFTypeRef) -> Option<Self> {
+            NonNull::new(ptr as *mut c_void).map(Self)
+        }
+
+        /// Retains an object from a Get function.
+        fn retain(ptr: CFTypeRef) -> Option<Self> {
+            if ptr.is_null() {
+                return None;
+            }
+            // SAFETY: `ptr` is a live CoreFoundation object.
+            Self::create(unsafe { CFRetain(ptr) })
+        }
+
+        fn as_ptr(&self) -> CFTypeRef {
+            self.0.as_ptr()
+        }
+    }
+
+    impl Drop for Cf {
+        fn drop(&mut self) {
+            // SAFETY: the object is owned by this handle.
+            unsafe { CFRelease(self.as_ptr()) }
+        }
+    }
+
+    impl PartialEq for Cf {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    fn string(text: &CStr) -> Option<Cf> {
+        // SAFETY: `text` is nul-terminated.
+        Cf::create(unsafe {
+            CFStringCreateWithCString(
+                ptr::null(),
+                text.as_ptr(),
+                K_CF_STRING_ENCODING_UTF8,
+            )
+        })
+    }
+
+    fn number(value: i32) -> Option<Cf> {
+        // SAFETY: the value outlives the call, CoreFoundation copies it.
+        Cf::create(unsafe {
+            CFNumberCreate(
+                ptr::null(),
+                K_CF_NUMBER_SINT32_TYPE,
+                (&value as *const i32).cast(),
+            )
+        })
+    }
+
+    /// Matching dictionary for one device model.
+    fn matching((vid, pid): DeviceId) -> Option<Cf> {
+        let keys = [string(c"VendorID")?, string(c"ProductID")?];
+        let values = [number(vid.into())?, number(pid.into())?];
+        let keys = keys.each_ref().map(Cf::as_ptr);
+        let values = values.each_ref().map(Cf::as_ptr);
+        // SAFETY: keys and values are live for the call, the dictionary
+        // retains them.
+        Cf::create(unsafe {
+            CFDictionaryCreate(
+                ptr::null(),
+                keys.as_ptr(),
+                values.as_ptr(),
+                keys.len() as CFIndex,
+                &kCFTypeDictionaryKeyCallBacks,
+                &kCFTypeDictionaryValueCallBacks,
+            )
+        })
+    }
+
+    /// An open `IOHIDManager` matching the configured device models,
+    /// scheduled on the run loop of the thread that opened it.
+    pub(super) struct Manager(Cf);
+
+    impl Manager {
+        pub(super) fn open(ids: &[DeviceId]) -> Option<Self> {
+            let dictionaries: Vec<Cf> =
+                ids.iter().copied().map(matching).collect::<Option<_>>()?;
+            let values: Vec<CFTypeRef> =
+                dictionaries.iter().map(Cf::as_ptr).collect();
+            // SAFETY: all arguments are live CoreFoundation objects, the
+            // array retains its values.
+            unsafe {
+                let manager = Cf::create(IOHIDManagerCreate(
+                    ptr::null(),
+                    K_IO_HID_OPTIONS_TYPE_NONE,
+                ))?;
+                let multiple = Cf::create(CFArrayCreate(
+                    ptr::null(),
+                    values.as_ptr(),
+                    values.len() as CFIndex,
+                    &kCFTypeArrayCallBacks,
+                ))?;
+                IOHIDManagerSetDeviceMatchingMultiple(
+                    manager.as_ptr(),
+                    multiple.as_ptr(),
+                );
+                // Matching of hot-plugged devices runs on the run loop
+                IOHIDManagerScheduleWithRunLoop(
+                    manager.as_ptr(),
+                    CFRunLoopGetCurrent(),
+                    kCFRunLoopDefaultMode,
+                );
+                let result =
+                    IOHIDManagerOpen(manager.as_ptr(), K_IO_HID_OPTIONS_TYPE_NONE);
+                let manager = Self(manager);
+                (result == K_IO_RETURN_SUCCESS).then_some(manager)
+            }
+        }
+
+        /// Currently connected matching devices.
+        pub(super) fn devices(&self) -> Vec<Device> {
+            // SAFETY: the manager is open and used on its run loop thread.
+            unsafe {
+                CFRunLoopRunInMode(kCFRunLoopDefaultMode, 0.0, 0);
+                let Some(set) = Cf::create(IOHIDManagerCopyDevices(self.0.as_ptr()))
+                else {
+                    return Vec::new();
+                };
+                let count = CFSetGetCount(set.as_ptr()).max(0) as usize;
+                let mut values: Vec<CFTypeRef> = vec![ptr::null(); count];
+                CFSetGetValues(set.as_ptr(), values.as_mut_ptr());
+                values
+                    .into_iter()
+                    .filter_map(Cf::retain)
+                    .map(Device)
+                    .collect()
+            }
+        }
+    }
+
+    impl Drop for Manager {
+        fn drop(&mut self) {
+            // SAFETY: the manager was opened and scheduled on this thread.
+            unsafe {
+                IOHIDManagerUnscheduleFromRunLoop(
+                    self.0.as_ptr(),
+                    CFRunLoopGetCurrent(),
+                    kCFRunLoopDefaultMode,
+                );
+                IOHIDManagerClose(self.0.as_ptr(), K_IO_HID_OPTIONS_TYPE_NONE);
+            }
+        }
+    }
+
+    /// A connected `IOHIDDevice`.
+    #[derive(PartialEq)]
+    pub(super) struct Device(Cf);
+
+    impl Device {
+        pub(super) fn vendor_id(&self) -> u16 {
+            self.number_property(c"VendorID").unwrap_or(0) as u16
+        }
+
+        pub(super) fn product_id(&self) -> u16 {
+            self.number_property(c"ProductID").unwrap_or(0) as u16
+        }
+
+        pub(super) fn product_name(&self) -> Option<String> {
+            self.string_property(c"Product")
+        }
+
+        pub(super) fn serial(&self) -> Option<String> {
+            self.string_property(c"SerialNumber")
+                .filter(|serial| !serial.is_empty())
+        }
+
+        /// All elements of the device.
+        pub(super) fn elements(&self) -> Vec<Element> {
+            // SAFETY: the device is live, elements are retained before
+            // the array is released.
+            unsafe {
+                let Some(array) = Cf::create(IOHIDDeviceCopyMatchingElements(
+                    self.0.as_ptr(),
+                    ptr::null(),
+                    K_IO_HID_OPTIONS_TYPE_NONE,
+                )) else {
+                    return Vec::new();
+                };
+                (0..CFArrayGetCount(array.as_ptr()))
+                    .filter_map(|i| {
+                        Cf::retain(CFArrayGetValueAtIndex(array.as_ptr(), i))
+                    })
+                    .map(Element)
+                    .collect()
+            }
+        }
+
+        /// Current integer value of an input element.
+        pub(super) fn value(&self, element: &Element) -> Option<isize> {
+            let mut value: CFTypeRef = ptr::null();
+            // SAFETY: device and element are live, the value is only read
+            // before the next call.
+            unsafe {
+                let result = IOHIDDeviceGetValue(
+                    self.0.as_ptr(),
+                    element.0.as_ptr(),
+                    &mut value,
+                );
+                (result == K_IO_RETURN_SUCCESS && !value.is_null())
+                    .then(|| IOHIDValueGetIntegerValue(value))
+            }
+        }
+
+        fn property(&self, key: &CStr) -> Option<CFTypeRef> {
+            let key = string(key)?;
+            // SAFETY: the device and the key are live.
+            let value =
+                unsafe { IOHIDDeviceGetProperty(self.0.as_ptr(), key.as_ptr()) };
+            (!value.is_null()).then_some(value)
+        }
+
+        fn number_property(&self, key: &CStr) -> Option<i32> {
+            let value = self.property(key)?;
+            let mut number = 0i32;
+            // SAFETY: the property is owned by the device and checked to
+            // be a number.
+            unsafe {
+                (CFGetTypeID(value) == CFNumberGetTypeID()
+                    && CFNumberGetValue(
+                        value,
+                        K_CF_NUMBER_SINT32_TYPE,
+                        (&mut number as *mut i32).cast(),
+                    ) != 0)
+                    .then_some(number)
+            }
+        }
+
+        fn string_property(&self, key: &CStr) -> Option<String> {
+            let value = self.property(key)?;
+            let mut buffer = [0 as c_char; 256];
+            // SAFETY: the property is owned by the device and checked to
+            // be a string, the buffer length is passed along.
+            unsafe {
+                let copied = CFGetTypeID(value) == CFStringGetTypeID()
+                    && CFStringGetCString(
+                        value,
+                        buffer.as_mut_ptr(),
+                        buffer.len() as CFIndex,
+                        K_CF_STRING_ENCODING_UTF8,
+                    ) != 0;
+                copied.then(|| {
+                    CStr::from_ptr(buffer.as_ptr())
+                        .to_string_lossy()
+                        .into_owned()
+                })
+            }
+        }
+    }
+
+    /// An element of a device, such as a switch.
+    pub(super) struct Element(Cf);
+
+    impl Element {
+        /// Usage page and usage of the element.
+        pub(super) fn usage(&self) -> (u16, u16) {
+            // SAFETY: the element is live.
+            unsafe {
+                (
+                    IOHIDElementGetUsagePage(self.0.as_ptr()) as u16,
+                    IOHIDElementGetUsage(self.0.as_ptr()) as u16,
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(feature = "sdl2-backend")]
+mod sdl;
+#[cfg(all(target_os = "macos", feature = "gc-backend"))]
+mod game_controller;
+#[cfg(feature = "virtual-backend")]
+mod virtual_input;
+#[cfg(all(target_os = "macos", feature = "hid-backend"))]
+mod hid;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::events::ControllerEvent;
+use crate::hid::HidDevice;
+use crate::manager::Inner;
+use crate::mapping::ControllerMappings;
+use crate::types::ControllerId;
+use crate::{Error, Result};
+
+#[cfg(feature = "virtual-backend")]
+pub use virtual_input::VirtualInjector;
+
+/// Controller input backend selectable at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackendKind {
+    /// SDL2 game controller and joystick subsystems.
+    #[default]
+    Sdl2,
+    /// Apple GameController.framework (macOS only, `gc-backend` feature).
+    GameController,
+    /// Events injected through a `VirtualInjector` (`virtual-backend` feature).
+    Virtual,
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "sdl2" | "sdl" => Ok(Self::Sdl2),
+            "game_controller" | "gc" => Ok(Self::GameController),
+            other => Err(format!("unknown gamepad backend: {other}")),
+        }
+    }
+}
+
+/// Ends a wait of the backend from another thread.
+pub(crate) type Waker = Box<dyn Fn() + Send>;
+
+/// A source of controller events driven by the runtime thread.
+///
+/// Backends are created and used on the runtime thread only,
+/// so they are free to hold thread-bound handles.
+pub(crate) trait GamepadBackend {
+    /// Emits `Connected` for every device present at startup.
+    fn enumerate(&mut self, emit: &mut dyn FnMut(ControllerEvent));
+
+    /// Waits up to `timeout` for input and emits the resulting events.
+    fn pump(&mut self, timeout: Duration, emit: &mut dyn FnMut(ControllerEvent));
+
+    /// Plays rumble with the given motor strengths for `ms` milliseconds.
+    fn rumble(
+        &mut self,
+        id: ControllerId,
+        low: u16,
+        high: u16,
+        ms: u32,
+    ) -> Result<()>;
+
+    /// Stops rumble if it is currently active.
+    fn stop_rumble(&mut self, id: ControllerId) -> Result<()>;
+
+    /// Returns ids of all currently open devices.
+    fn controller_ids(&self) -> Vec<ControllerId>;
+
+    /// Returns a waker that ends a wait in `pump` early, e.g. so commands
+    /// run right away. Backends that poll return `None`.
+    fn waker(&self) -> Option<Waker> {
+        None
+    }
+
+    /// Whether only new devices can produce events. They end a wait in
+    /// `pump` like a waker does, so the runtime may wait longer.
+    fn is_idle(&self) -> bool {
+        false
+    }
+
+    /// Loads additional controller mappings and reconnects devices
+    /// they apply to. Backends without mapping support ignore them.
+    fn load_mappings(
+        &mut self,
+        _mappings: &ControllerMappings,
+        _emit: &mut dyn FnMut(ControllerEvent),
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Starts reading the given HID devices in place of the previous ones.
+    /// Backends without HID support ignore them.
+    fn load_hid_devices(
+        &mut self,
+        _devices: &[HidDevice],
+        _emit: &mut dyn FnMut(ControllerEvent),
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Opens a backend of the given kind on the current thread. With the
+/// `hid-backend` feature on macOS, HID devices are read next to it.
+pub(crate) fn open(
+    kind: BackendKind,
+    inner: &Inner,
+) -> Result<Box<dyn GamepadBackend>> {
+    let backend = open_kind(kind, inner)?;
+    #[cfg(all(target_os = "macos", feature = "hid-backend"))]
+    let backend = Box::new(hid::WithHid::new(backend));
+    Ok(backend)
+}
+
+#[cfg_attr(not(feature = "virtual-backend"), allow(unused_variables))]
+fn open_kind(kind: BackendKind, inner: &Inner) -> Result<Box<dyn GamepadBackend>> {
+    match kind {
+        #[cfg(feature = "sdl2-backend")]
+        BackendKind::Sdl2 => Ok(Box::new(sdl::SdlBackend::new()?)),
+        #[cfg(all(target_os = "macos", feature = "gc-backend"))]
+        BackendKind::GameController => {
+            Ok(Box::new(game_controller::GameControllerBackend::new()))
+        }
+        #[cfg(feature = "virtual-backend")]
+        BackendKind::Virtual => Ok(Box::new(virtual_input::VirtualBackend::new(
+            inner.virtual_rx.clone(),
+        ))),
+        #[allow(unreachable_patterns)]
+        _ => Err(Error::Unsupported),
+    }
+}
@@ -0,0 +1,587 @@
+use std::ffi::{c_char, CStr};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+use sdl2::controller::{Button as SdlButton, GameController, Axis as SdlAxis};
+use sdl2::event::Event;
+use sdl2::haptic::Haptic;
+use sdl2::joystick::Joystick;
+use sdl2::{
+    EventPump, EventSubsystem, GameControllerSubsystem, HapticSubsystem,
+    JoystickSubsystem, Sdl,
+};
+
+use crate::events::ControllerEvent;
+use crate::mapping::ControllerMappings;
+use crate::types::{Button, ControllerId, ControllerInfo, Axis};
+use crate::{Error, Result};
+
+use super::{GamepadBackend, Waker};
+
+/// Analog trigger value above which the trigger is reported as a pressed button.
+const TRIGGER_THRESHOLD: i16 = 20000;
+/// Delay before the first retry to reopen a haptic device, doubled per attempt.
+const HAPTIC_RETRY_DELAY: Duration = Duration::from_millis(250);
+/// Reopen attempts after a failed rumble before the device is given up on.
+const HAPTIC_RETRY_ATTEMPTS: u32 = 5;
+
+/// Pending reopen of the haptic device of a controller.
+struct HapticRetry {
+    attempt: u32,
+    at: Instant,
+}
+
+/// SDL2-backed controller backend. SDL must live entirely within one thread.
+pub(crate) struct SdlBackend {
+    _sdl: Sdl,
+    controller_subsystem: GameControllerSubsystem,
+    joystick_subsystem: JoystickSubsystem,
+    haptic_subsystem: HapticSubsystem,
+    event_subsystem: EventSubsystem,
+    /// Type of the user event pushed by wakers.
+    wake_event: u32,
+    event_pump: EventPump,
+    controllers: AHashMap<ControllerId, GameController>,
+    joysticks: AHashMap<ControllerId, Joystick>,
+    haptics: AHashMap<ControllerId, Haptic>,
+    trigger_state: AHashMap<ControllerId, (bool, bool)>,
+    haptic_retries: AHashMap<ControllerId, HapticRetry>,
+    /// Events raised outside of a pump, emitted by the next one.
+    pending: Vec<ControllerEvent>,
+}
+
+impl SdlBackend {
+    pub(crate) fn new() -> Result<Self> {
+        let sdl = sdl2::init().map_err(Error::BackendInit)?;
+        let controller_subsystem =
+            sdl.game_controller().map_err(Error::BackendInit)?;
+        let joystick_subsystem = sdl.joystick().map_err(Error::BackendInit)?;
+        let haptic_subsystem = sdl.haptic().map_err(Error::BackendInit)?;
+        let event_subsystem = sdl.event().map_err(Error::BackendInit)?;
+        // SAFETY: the event type is only pushed as a plain user event.
+        let wake_event = unsafe { event_subsystem.register_event() }
+            .map_err(Error::BackendInit)?;
+        let event_pump = sdl.event_pump().map_err(Error::BackendInit)?;
+
+        Ok(Self {
+            _sdl: sdl,
+            controller_subsystem,
+            joystick_subsystem,
+            haptic_subsystem,
+            event_subsystem,
+            wake_event,
+            event_pump,
+            controllers: AHashMap::new(),
+            joysticks: AHashMap::new(),
+            haptics: AHashMap::new(),
+            trigger_state: AHashMap::new(),
+            haptic_retries: AHashMap::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn open_controller(
+        &mut self,
+        which: u32,
+        emit: &mut dyn FnMut(ControllerEvent),
+    ) {
+        let Ok(controller) = self.controller_subsystem.open(which) else {
+            return;
+        };
+        let id: ControllerId = match self.joystick_subsystem.open(which) {
+            Ok(js) => js.instance_id() as ControllerId,
+            Err(_) => which as ControllerId,
+        };
+        let info = ControllerInfo {
+            id,
+            name: controller.name().to_string(),
+            vendor_id: controller.vendor_id().unwrap_or(0),
+            product_id: controller.product_id().unwrap_or(0),
+            serial: controller_serial(id),
+            extra_buttons: controller_extra_buttons(id),
+            supports_rumble: controller.has_rumble(),
+        };
+        self.controllers.insert(id, controller);
+        emit(ControllerEvent::Connected(info));
+    }
+
+    fn open_joystick(&mut self, which: u32, emit: &mut dyn FnMut(ControllerEvent)) {
+        let Ok(joystick) = self.joystick_subsystem.open(which) else {
+            return;
+        };
+        let id: ControllerId = joystick.instance_id() as ControllerId;
+        // Already open from enumeration, SDL reports present devices again
+        if self.joysticks.contains_key(&id) {
+            return;
+        }
+        if joystick.has_rumble() {
+            if let Ok(h) = self
+                .haptic_subsystem
+                .open_from_joystick_id(joystick.instance_id())
+            {
+                self.haptics.insert(id, h);
+            }
+        }
+        let info = ControllerInfo {
+            id,
+            name: joystick.name().to_string(),
+            vendor_id: joystick_vendor_product(id).0,
+            product_id: joystick_vendor_product(id).1,
+            serial: joystick_serial(id),
+            extra_buttons: Vec::new(),
+            supports_rumble: joystick.has_rumble(),
+        };
+        self.joysticks.insert(id, joystick);
+        emit(ControllerEvent::Connected(info));
+    }
+
+    /// Opens the haptic device of a connected controller.
+    fn open_haptic(&mut self, id: ControllerId) -> bool {
+        let Ok(num_joysticks) = self.joystick_subsystem.num_joysticks() else {
+            return false;
+        };
+        // Haptics are opened by device index, which shifts on hot-plug
+        let index = (0..num_joysticks).find(|&i| {
+            self.joystick_subsystem
+                .open(i)
+                .is_ok_and(|js| js.instance_id() as ControllerId == id)
+        });
+        let Some(Ok(haptic)) =
+            index.map(|i| self.haptic_subsystem.open_from_joystick_id(i))
+        else {
+            return false;
+        };
+        self.haptics.insert(id, haptic);
+        true
+    }
+
+    /// Falls back to the haptic device after rumble failed, reopening it
+    /// right away or scheduling retries when it is missing.
+    fn rumble_haptic(
+        &mut self,
+        id: ControllerId,
+        low: u16,
+        high: u16,
+        ms: u32,
+    ) -> bool {
+        if !self.haptics.contains_key(&id) && !self.open_haptic(id) {
+            self.haptic_retries.entry(id).or_insert(HapticRetry {
+                attempt: 0,
+                at: Instant::now() + HAPTIC_RETRY_DELAY,
+            });
+            return false;
+        }
+        let Some(haptic) = self.haptics.get_mut(&id) else {
+            return false;
+        };
+        let strength = (low.max(high) as f32) / 65535.0;
+        haptic.rumble_play(strength, ms);
+        true
+    }
+
+    /// Retries reopening haptic devices whose backoff has elapsed.
+    fn retry_haptics(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        let now = Instant::now();
+        let due: Vec<ControllerId> = self
+            .haptic_retries
+            .iter()
+            .filter(|(_, retry)| retry.at <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in due {
+            if self.open_haptic(id) {
+                self.haptic_retries.remove(&id);
+                emit(ControllerEvent::CapabilitiesChanged {
+                    id,
+                    supports_rumble: true,
+                });
+                continue;
+            }
+            let Some(retry) = self.haptic_retries.get_mut(&id) else {
+                continue;
+            };
+            retry.attempt += 1;
+            if retry.attempt >= HAPTIC_RETRY_ATTEMPTS {
+                self.haptic_retries.remove(&id);
+            } else {
+                retry.at = now + HAPTIC_RETRY_DELAY * 2u32.pow(retry.attempt);
+            }
+        }
+    }
+
+    /// Reopens plain joysticks that became game controllers, e.g. after
+    /// a mapping for them was added.
+    fn reopen_mapped_joysticks(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        let Ok(num_joysticks) = self.joystick_subsystem.num_joysticks() else {
+            return;
+        };
+        for i in 0..num_joysticks {
+            if !self.controller_subsystem.is_game_controller(i) {
+                continue;
+            }
+            let Ok(joystick) = self.joystick_subsystem.open(i) else {
+                continue;
+            };
+            let id = joystick.instance_id() as ControllerId;
+            if self.joysticks.remove(&id).is_none() {
+                continue;
+            }
+            self.haptics.remove(&id);
+            emit(ControllerEvent::Disconnected(id));
+            self.open_controller(i, emit);
+        }
+    }
+
+    fn handle_event(&mut self, event: Event, emit: &mut dyn FnMut(ControllerEvent)) {
+        match event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                self.open_controller(which, emit);
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                let id: ControllerId = which as ControllerId;
+                self.controllers.remove(&id);
+                self.joysticks.remove(&id);
+                self.haptics.remove(&id);
+                self.trigger_state.remove(&id);
+                self.haptic_retries.remove(&id);
+                emit(ControllerEvent::Disconnected(id));
+            }
+            // Game controllers are opened on `ControllerDeviceAdded`
+            Event::JoyDeviceAdded { which, .. }
+                if !self.controller_subsystem.is_game_controller(which) =>
+            {
+                self.open_joystick(which, emit);
+            }
+            Event::JoyDeviceRemoved { which, .. } => {
+                let id: ControllerId = which as ControllerId;
+                if self.joysticks.remove(&id).is_some() {
+                    self.haptics.remove(&id);
+                    self.haptic_retries.remove(&id);
+                    emit(ControllerEvent::Disconnected(id));
+                }
+            }
+            // Joystick events are also sent for game controllers, which
+            // report mapped buttons and axes instead
+            Event::JoyButtonDown {
+                which, button_idx, ..
+            } if self.joysticks.contains_key(&which) => {
+                if let Some(button) = Button::joystick(button_idx as usize) {
+                    emit(ControllerEvent::ButtonPressed { id: which, button });
+                }
+            }
+            Event::JoyButtonUp {
+                which, button_idx, ..
+            } if self.joysticks.contains_key(&which) => {
+                if let Some(button) = Button::joystick(button_idx as usize) {
+                    emit(ControllerEvent::ButtonReleased { id: which, button });
+                }
+            }
+            Event::JoyAxisMotion {
+                which,
+                axis_idx,
+                value,
+                ..
+            } if self.joysticks.contains_key(&which) => {
+                emit(ControllerEvent::JoystickAxisMotion {
+                    id: which,
+                    axis: axis_idx,
+                    value: (value as f32) / (i16::MAX as f32),
+                });
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                if let Some(btn) = map_sdl_button(button) {
+                    emit(ControllerEvent::ButtonPressed {
+                        id: which as ControllerId,
+                        button: btn,
+                    });
+                }
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                if let Some(btn) = map_sdl_button(button) {
+                    emit(ControllerEvent::ButtonReleased {
+                        id: which as ControllerId,
+                        button: btn,
+                    });
+                }
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let id = which as ControllerId;
+
+                // Emit analog event for all axes
+                if let Some(mapped) = map_sdl_axis(axis) {
+                    let norm = (value as f32) / (i16::MAX as f32);
+                    emit(ControllerEvent::AxisMotion {
+                        id,
+                        axis: mapped,
+                        value: norm,
+                    });
+                }
+
+                // Preserve trigger-as-button semantics for compatibility
+                let entry = self.trigger_state.entry(id).or_insert((false, false));
+                let (state, button) = match axis {
+                    SdlAxis::TriggerLeft => (&mut entry.0, Button::LeftTrigger),
+                    SdlAxis::TriggerRight => (&mut entry.1, Button::RightTrigger),
+                    _ => return,
+                };
+                let pressed = value > TRIGGER_THRESHOLD;
+                if pressed && !*state {
+                    emit(ControllerEvent::ButtonPressed { id, button });
+                    *state = true;
+                } else if !pressed && *state {
+                    emit(ControllerEvent::ButtonReleased { id, button });
+                    *state = false;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl GamepadBackend for SdlBackend {
+    fn enumerate(&mut self, emit: &mut dyn FnMut(ControllerEvent)) {
+        let Ok(num_joysticks) = self.joystick_subsystem.num_joysticks() else {
+            return;
+        };
+        for i in 0..num_joysticks {
+            if self.controller_subsystem.is_game_controller(i) {
+                self.open_controller(i, emit);
+            } else {
+                self.open_joystick(i, emit);
+            }
+        }
+    }
+
+    fn pump(&mut self, timeout: Duration, emit: &mut dyn FnMut(ControllerEvent)) {
+        for event in self.pending.drain(..) {
+            emit(event);
+        }
+        self.retry_haptics(emit);
+        // Wait for an SDL event or timeout to reduce idle CPU usage
+        let timeout_ms = timeout.as_millis().min(u32::MAX as u128) as u32;
+        let Some(event) = self.event_pump.wait_event_timeout(timeout_ms) else {
+            return;
+        };
+        self.handle_event(event, emit);
+        // Drain any additional queued events quickly
+        while let Some(event) = self.event_pump.poll_event() {
+            self.handle_event(event, emit);
+        }
+    }
+
+    fn rumble(
+        &mut self,
+        id: ControllerId,
+        low: u16,
+        high: u16,
+        ms: u32,
+    ) -> Result<()> {
+        let result = match self.controllers.get_mut(&id) {
+            Some(ctrl) => ctrl
+                .set_rumble(low, high, ms)
+                .map_err(|e| Error::Backend(e.to_string())),
+            None if self.joysticks.contains_key(&id) => Err(Error::Unsupported),
+            None => return Err(Error::NotFound(id)),
+        };
+        if result.is_ok() {
+            return result;
+        }
+        // Driver quirks and reconnects can leave only the haptic device working
+        let supports_rumble = self.rumble_haptic(id, low, high, ms);
+        self.pending.push(ControllerEvent::CapabilitiesChanged {
+            id,
+            supports_rumble,
+        });
+        if supports_rumble {
+            Ok(())
+        } else {
+            result
+        }
+    }
+
+    fn stop_rumble(&mut self, id: ControllerId) -> Result<()> {
+        if let Some(h) = self.haptics.get_mut(&id) {
+            h.rumble_stop();
+        }
+        if let Some(ctrl) = self.controllers.get_mut(&id) {
+            ctrl.set_rumble(0, 0, 0)
+                .map_err(|e| Error::Backend(e.to_string()))
+        } else if self.joysticks.contains_key(&id) {
+            Ok(())
+        } else {
+            Err(Error::NotFound(id))
+        }
+    }
+
+    fn controller_ids(&self) -> Vec<ControllerId> {
+        self.controllers
+            .keys()
+            .chain(self.joysticks.keys())
+            .copied()
+            .collect()
+    }
+
+    fn waker(&self) -> Option<Waker> {
+        let sender = self.event_subsystem.event_sender();
+        let type_ = self.wake_event;
+        Some(Box::new(move || {
+            // Ends `wait_event_timeout`, the event itself is ignored
+            let _ = sender.push_event(Event::User {
+                timestamp: 0,
+                window_id: 0,
+                type_,
+                code: 0,
+                data1: ptr::null_mut(),
+                data2: ptr::null_mut(),
+            });
+        }))
+    }
+
+    fn is_idle(&self) -> bool {
+        // Device events and wakers end the wait, nothing else is due
+        self.controllers.is_empty()
+            && self.joysticks.is_empty()
+            && self.haptic_retries.is_empty()
+            && self.pending.is_empty()
+    }
+
+    fn load_mappings(
+        &mut self,
+        mappings: &ControllerMappings,
+        emit: &mut dyn FnMut(ControllerEvent),
+    ) -> Result<()> {
+        // Keep loading after a failure, a broken entry should not hide the rest
+        let mut result = Ok(());
+        for path in &mappings.files {
+            if let Err(e) = self.controller_subsystem.load_mappings(path) {
+                result = Err(Error::Backend(format!("{}: {e}", path.display())));
+            }
+        }
+        for mapping in &mappings.mappings {
+            if let Err(e) = self.controller_subsystem.add_mapping(mapping) {
+                result = Err(Error::Backend(format!("{mapping}: {e}")));
+            }
+        }
+        self.reopen_mapped_joysticks(emit);
+        result
+    }
+}
+
+/// Serial number of an open game controller, when the driver reports one.
+fn controller_serial(id: ControllerId) -> Option<String> {
+    // SAFETY: the controller is open, so SDL returns a live handle, and the
+    // serial string it owns is copied before the handle is used again.
+    unsafe {
+        let raw = sdl2::sys::SDL_GameControllerFromInstanceID(id as i32);
+        if raw.is_null() {
+            return None;
+        }
+        serial(sdl2::sys::SDL_GameControllerGetSerial(raw))
+    }
+}
+
+/// Buttons beyond the standard layout an open controller has.
+fn controller_extra_buttons(id: ControllerId) -> Vec<Button> {
+    let extra = [
+        SdlButton::Misc1,
+        SdlButton::Paddle1,
+        SdlButton::Paddle2,
+        SdlButton::Paddle3,
+        SdlButton::Paddle4,
+        SdlButton::Touchpad,
+    ];
+    // SAFETY: see `controller_serial`.
+    unsafe {
+        let raw = sdl2::sys::SDL_GameControllerFromInstanceID(id as i32);
+        if raw.is_null() {
+            return Vec::new();
+        }
+        extra
+            .into_iter()
+            .filter(|button| {
+                sdl2::sys::SDL_GameControllerHasButton(raw, button.to_ll())
+                    == sdl2::sys::SDL_bool::SDL_TRUE
+            })
+            .filter_map(map_sdl_button)
+            .collect()
+    }
+}
+
+/// Serial number of an open joystick, when the driver reports one.
+fn joystick_serial(id: ControllerId) -> Option<String> {
+    // SAFETY: see `controller_serial`.
+    unsafe {
+        let raw = sdl2::sys::SDL_JoystickFromInstanceID(id as i32);
+        if raw.is_null() {
+            return None;
+        }
+        serial(sdl2::sys::SDL_JoystickGetSerial(raw))
+    }
+}
+
+/// USB vendor and product ids of an open joystick, zero when unknown.
+fn joystick_vendor_product(id: ControllerId) -> (u16, u16) {
+    // SAFETY: see `controller_serial`.
+    unsafe {
+        let raw = sdl2::sys::SDL_JoystickFromInstanceID(id as i32);
+        if raw.is_null() {
+            return (0, 0);
+        }
+        (
+            sdl2::sys::SDL_JoystickGetVendor(raw),
+            sdl2::sys::SDL_JoystickGetProduct(raw),
+        )
+    }
+}
+
+/// Copies a serial string returned by SDL.
+///
+/// # Safety
+/// `ptr` must be null or point to a nul-terminated string.
+unsafe fn serial(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let serial = CStr::from_ptr(ptr).to_string_lossy();
+    (!serial.is_empty()).then(|| serial.into_owned())
+}
+
+fn map_sdl_button(button: SdlButton) -> Option<Button> {
+    Some(match button {
+        SdlButton::A => Button::A,
+        SdlButton::B => Button::B,
+        SdlButton::X => Button::X,
+        SdlButton::Y => Button::Y,
+        SdlButton::Back => Button::Back,
+        SdlButton::Guide => Button::Guide,
+        SdlButton::Start => Button::Start,
+        SdlButton::LeftStick => Button::LeftStick,
+        SdlButton::RightStick => Button::RightStick,
+        SdlButton::LeftShoulder => Button::LeftShoulder,
+        SdlButton::RightShoulder => Button::RightShoulder,
+        SdlButton::DPadUp => Button::DPadUp,
+        SdlButton::DPadDown => Button::DPadDown,
+        SdlButton::DPadLeft => Button::DPadLeft,
+        SdlButton::DPadRight => Button::DPadRight,
+        SdlButton::Misc1 => Button::Misc,
+        SdlButton::Paddle1 => Button::Paddle1,
+        SdlButton::Paddle2 => Button::Paddle2,
+        SdlButton::Paddle3 => Button::Paddle3,
+        SdlButton::Paddle4 => Button::Paddle4,
+        SdlButton::Touchpad => Button::Touchpad,
+    })
+}
+
+fn map_sdl_axis(axis: SdlAxis) -> Option<Axis> {
+    Some(match axis {
+        SdlAxis::LeftX => Axis::LeftX,
+        SdlAxis::LeftY => Axis::LeftY,
+        SdlAxis::RightX => Axis::RightX,
+        SdlAxis::RightY => Axis::RightY,
+        SdlAxis::TriggerLeft => Axis::LeftTrigger,
+        SdlAxis::TriggerRight => Axis::RightTrigger,
+    })
+}
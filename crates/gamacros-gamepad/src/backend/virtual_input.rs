@@ -0,0 +1,278 @@
+use std::time::Duration;
+
+use ahash::AHashMap;
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::events::ControllerEvent;
+use crate::types::{Axis, Button, ControllerId, ControllerInfo};
+use crate::{Error, Result};
+
+use super::GamepadBackend;
+
+/// Handle used to inject synthetic events into the virtual backend.
+///
+/// Events behave as if they came from hardware: input for controllers
+/// that were not connected first is dropped.
+#[derive(Debug, Clone)]
+pub struct VirtualInjector {
+    tx: Sender<ControllerEvent>,
+}
+
+impl VirtualInjector {
+    pub(crate) fn new(tx: Sender<ControllerEvent>) -> Self {
+        Self { tx }
+    }
+
+    /// Injects a raw controller event.
+    pub fn send(&self, event: ControllerEvent) {
+        let _ = self.tx.send(event);
+    }
+
+    /// Connects a virtual controller.
+    pub fn connect(&self, info: ControllerInfo) {
+        self.send(ControllerEvent::Connected(info));
+    }
+
+    /// Disconnects a virtual controller.
+    pub fn disconnect(&self, id: ControllerId) {
+        self.send(ControllerEvent::Disconnected(id));
+    }
+
+    /// Changes whether a virtual controller supports rumble.
+    pub fn set_supports_rumble(&self, id: ControllerId, supports_rumble: bool) {
+        self.send(ControllerEvent::CapabilitiesChanged {
+            id,
+            supports_rumble,
+        });
+    }
+
+    /// Presses a button on a virtual controller.
+    pub fn press(&self, id: ControllerId, button: Button) {
+        self.send(ControllerEvent::ButtonPressed { id, button });
+    }
+
+    /// Releases a button on a virtual controller.
+    pub fn release(&self, id: ControllerId, button: Button) {
+        self.send(ControllerEvent::ButtonReleased { id, button });
+    }
+
+    /// Moves an axis of a virtual controller. `value` is clamped to [-1.0, 1.0].
+    pub fn axis(&self, id: ControllerId, axis: Axis, value: f32) {
+        self.send(ControllerEvent::AxisMotion {
+            id,
+            axis,
+            value: value.clamp(-1.0, 1.0),
+        });
+    }
+}
+
+/// Backend that replays events sent through a `VirtualInjector`.
+pub(crate) struct VirtualBackend {
+    events: Receiver<ControllerEvent>,
+    controllers: AHashMap<ControllerId, ControllerInfo>,
+}
+
+impl VirtualBackend {
+    pub(crate) fn new(events: Receiver<ControllerEvent>) -> Self {
+        Self {
+            events,
+            controllers: AHashMap::new(),
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        event: ControllerEvent,
+        emit: &mut dyn FnMut(ControllerEvent),
+    ) {
+        match &event {
+            ControllerEvent::Connected(info) => {
+                self.controllers.insert(info.id, info.clone());
+            }
+            ControllerEvent::Disconnected(id) => {
+                if self.controllers.remove(id).is_none() {
+                    return;
+                }
+            }
+            ControllerEvent::CapabilitiesChanged {
+                id,
+                supports_rumble,
+            } => {
+                let Some(info) = self.controllers.get_mut(id) else {
+                    return;
+                };
+                info.supports_rumble = *supports_rumble;
+            }
+            ControllerEvent::ButtonPressed { id, .. }
+            | ControllerEvent::ButtonReleased { id, .. }
+            | ControllerEvent::AxisMotion { id, .. }
+            | ControllerEvent::JoystickAxisMotion { id, .. } => {
+                if !self.controllers.contains_key(id) {
+                    return;
+                }
+            }
+        }
+        emit(event);
+    }
+}
+
+impl GamepadBackend for VirtualBackend {
+    fn enumerate(&mut self, _emit: &mut dyn FnMut(ControllerEvent)) {}
+
+    fn pump(&mut self, timeout: Duration, emit: &mut dyn FnMut(ControllerEvent)) {
+        let Ok(event) = self.events.recv_timeout(timeout) else {
+            return;
+        };
+        self.handle_event(event, emit);
+        while let Ok(event) = self.events.try_recv() {
+            self.handle_event(event, emit);
+        }
+    }
+
+    fn rumble(
+        &mut self,
+        id: ControllerId,
+        _low: u16,
+        _high: u16,
+        _ms: u32,
+    ) -> Result<()> {
+        match self.controllers.get(&id) {
+            Some(info) if info.supports_rumble => Ok(()),
+            Some(_) => Err(Error::Unsupported),
+            None => Err(Error::NotFound(id)),
+        }
+    }
+
+    fn stop_rumble(&mut self, id: ControllerId) -> Result<()> {
+        self.rumble(id, 0, 0, 0)
+    }
+
+    fn controller_ids(&self) -> Vec<ControllerId> {
+        self.controllers.keys().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackendKind, ControllerManager, EventFilter, EventKind};
+
+    const RECV_TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn info(id: ControllerId) -> ControllerInfo {
+        ControllerInfo {
+            id,
+            name: "Virtual".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn injected_events_reach_subscribers() {
+        let manager = ControllerManager::with_backend(BackendKind::Virtual)
+            .expect("virtual manager should start");
+        let rx = manager.subscribe();
+        let injector = manager.injector();
+
+        injector.connect(info(7));
+        injector.press(7, Button::A);
+        injector.axis(7, Axis::LeftX, 2.0);
+
+        assert!(matches!(
+            rx.recv_timeout(RECV_TIMEOUT).map(|timed| timed.event),
+            Ok(ControllerEvent::Connected(info)) if info.id == 7
+        ));
+        assert!(matches!(
+            rx.recv_timeout(RECV_TIMEOUT).map(|timed| timed.event),
+            Ok(ControllerEvent::ButtonPressed {
+                id: 7,
+                button: Button::A
+            })
+        ));
+        assert!(matches!(
+            rx.recv_timeout(RECV_TIMEOUT).map(|timed| timed.event),
+            Ok(ControllerEvent::AxisMotion { id: 7, axis: Axis::LeftX, value })
+                if value == 1.0
+        ));
+        assert!(manager.controller(7).is_some());
+    }
+
+    #[test]
+    fn filtered_subscription_skips_other_events() {
+        let manager = ControllerManager::with_backend(BackendKind::Virtual)
+            .expect("virtual manager should start");
+        let filter =
+            EventFilter::kinds(&[EventKind::ButtonPressed, EventKind::AxisMotion])
+                .with_controllers(&[2]);
+        let rx = manager.subscribe_filtered(filter);
+        let all = manager.subscribe();
+        let injector = manager.injector();
+
+        injector.connect(info(1));
+        injector.connect(info(2));
+        injector.press(1, Button::A);
+        injector.release(2, Button::A);
+        injector.press(2, Button::B);
+
+        assert!(matches!(
+            rx.recv_timeout(RECV_TIMEOUT).map(|timed| timed.event),
+            Ok(ControllerEvent::ButtonPressed {
+                id: 2,
+                button: Button::B
+            })
+        ));
+        for _ in 0..5 {
+            assert!(all.recv_timeout(RECV_TIMEOUT).is_ok());
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn capability_changes_update_controller_info() {
+        let manager = ControllerManager::with_backend(BackendKind::Virtual)
+            .expect("virtual manager should start");
+        let rx = manager.subscribe();
+        let injector = manager.injector();
+
+        injector.connect(info(4));
+        injector.set_supports_rumble(4, false);
+        injector.set_supports_rumble(4, true);
+        injector.set_supports_rumble(4, true);
+        injector.press(4, Button::A);
+
+        let events: Vec<_> = (0..3)
+            .filter_map(|_| rx.recv_timeout(RECV_TIMEOUT).ok())
+            .map(|timed| timed.event)
+            .collect();
+        assert!(matches!(
+            events[..],
+            [
+                ControllerEvent::Connected(_),
+                ControllerEvent::CapabilitiesChanged {
+                    id: 4,
+                    supports_rumble: true
+                },
+                ControllerEvent::ButtonPressed { id: 4, .. }
+            ]
+        ));
+        assert!(manager.controllers()[0].supports_rumble);
+    }
+
+    #[test]
+    fn input_for_unknown_controller_is_dropped() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let injector = VirtualInjector::new(tx);
+        let mut backend = VirtualBackend::new(rx);
+
+        injector.press(1, Button::B);
+        injector.disconnect(1);
+
+        let mut events = Vec::new();
+        backend.pump(Duration::ZERO, &mut |event| events.push(event));
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::types::Axis;
+
+/// Correction applied to raw values of a single axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisCalibration {
+    /// Raw value reported at rest.
+    pub offset: f32,
+    /// Multiplier that maps the maximum throw to 1.0.
+    pub scale: f32,
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+impl AxisCalibration {
+    /// Returns the corrected value, clamped to [-1.0, 1.0].
+    #[inline]
+    pub fn apply(&self, value: f32) -> f32 {
+        ((value - self.offset) * self.scale).clamp(-1.0, 1.0)
+    }
+}
+
+/// Calibration of all axes of a device.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calibration {
+    axes: [AxisCalibration; Axis::ALL.len()],
+}
+
+impl Calibration {
+    pub fn get(&self, axis: Axis) -> AxisCalibration {
+        self.axes[axis.index()]
+    }
+
+    pub fn set(&mut self, axis: Axis, calibration: AxisCalibration) {
+        self.axes[axis.index()] = calibration;
+    }
+
+    /// Returns the corrected value of the given axis.
+    #[inline]
+    pub fn apply(&self, axis: Axis, value: f32) -> f32 {
+        self.axes[axis.index()].apply(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_calibration_is_identity() {
+        let calibration = Calibration::default();
+        for value in [-1.0, -0.3, 0.0, 0.42, 1.0] {
+            assert_eq!(calibration.apply(Axis::LeftX, value), value);
+        }
+    }
+
+    #[test]
+    fn removes_offset_and_scales_throw() {
+        let mut calibration = Calibration::default();
+        calibration.set(
+            Axis::RightY,
+            AxisCalibration {
+                offset: 0.25,
+                scale: 2.0,
+            },
+        );
+        assert_eq!(calibration.apply(Axis::RightY, 0.25), 0.0);
+        assert_eq!(calibration.apply(Axis::RightY, 0.75), 1.0);
+        assert_eq!(calibration.apply(Axis::RightY, -1.0), -1.0);
+        // Other axes stay untouched
+        assert_eq!(calibration.apply(Axis::RightX, 0.25), 0.25);
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+
+use crate::events::{ControllerEvent, TimedEvent};
+
+/// Holds back axis motion for subscribers that don't need every sample,
+/// so 1000 Hz controllers don't wake them a thousand times a second.
+///
+/// Motion is collected for `interval` and then released at once, with only
+/// the latest value of each axis. Other events release the collected motion
+/// first, so the order of events and their timestamps is kept.
+pub(crate) struct AxisCoalescer {
+    interval: Duration,
+    /// When the collected motion is released.
+    due: Option<Instant>,
+    /// Latest motion of each axis, oldest first.
+    pending: Vec<TimedEvent>,
+}
+
+impl AxisCoalescer {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            due: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Changes the interval. Motion already collected keeps its release
+    /// time.
+    pub(crate) fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Whether `event` is collected instead of delivered right away.
+    pub(crate) fn holds(&self, event: &ControllerEvent) -> bool {
+        !self.interval.is_zero() && is_axis(event)
+    }
+
+    /// Collects axis motion, replacing the value held for the same axis.
+    pub(crate) fn push(&mut self, event: TimedEvent) {
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|held| same_axis(&held.event, &event.event))
+        {
+            self.pending.remove(index);
+        }
+        self.due.get_or_insert(event.at + self.interval);
+        self.pending.push(event);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Collected motion, once it is due by `now`.
+    pub(crate) fn take_due(&mut self, now: Instant) -> Vec<TimedEvent> {
+        match self.due {
+            Some(due) if due <= now => self.drain(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// All collected motion, e.g. before another event is delivered.
+    pub(crate) fn drain(&mut self) -> Vec<TimedEvent> {
+        self.due = None;
+        std::mem::take(&mut self.pending)
+    }
+}
+
+fn is_axis(event: &ControllerEvent) -> bool {
+    matches!(
+        event,
+        ControllerEvent::AxisMotion { .. }
+            | ControllerEvent::JoystickAxisMotion { .. }
+    )
+}
+
+fn same_axis(a: &ControllerEvent, b: &ControllerEvent) -> bool {
+    match (a, b) {
+        (
+            ControllerEvent::AxisMotion { id, axis, .. },
+            ControllerEvent::AxisMotion {
+                id: other_id,
+                axis: other_axis,
+                ..
+            },
+        ) => id == other_id && axis == other_axis,
+        (
+            ControllerEvent::JoystickAxisMotion { id, axis, .. },
+            ControllerEvent::JoystickAxisMotion {
+                id: other_id,
+                axis: other_axis,
+                ..
+            },
+        ) => id == other_id && axis == other_axis,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Axis;
+
+    use super::*;
+
+    fn motion(at: Instant, axis: Axis, value: f32) -> TimedEvent {
+        TimedEvent {
+            at,
+            event: ControllerEvent::AxisMotion { id: 1, axis, value },
+        }
+    }
+
+    #[test]
+    fn keeps_latest_value_per_axis() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut coalescer = AxisCoalescer::new(Duration::ZERO);
+        assert!(!coalescer.holds(&motion(start, Axis::LeftX, 0.1).event));
+
+        coalescer.set_interval(ms(8));
+        assert!(coalescer.holds(&motion(start, Axis::LeftX, 0.2).event));
+        coalescer.push(motion(start, Axis::LeftX, 0.2));
+        coalescer.push(motion(start + ms(1), Axis::LeftY, 0.3));
+        coalescer.push(motion(start + ms(2), Axis::LeftX, 0.4));
+        assert!(coalescer.take_due(start + ms(7)).is_empty());
+
+        let released: Vec<_> = coalescer
+            .take_due(start + ms(8))
+            .into_iter()
+            .map(|timed| match timed.event {
+                ControllerEvent::AxisMotion { axis, value, .. } => (axis, value),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(released, [(Axis::LeftY, 0.3), (Axis::LeftX, 0.4)]);
+        assert!(coalescer.drain().is_empty());
+        assert!(!coalescer.holds(&ControllerEvent::ButtonPressed {
+            id: 1,
+            button: crate::types::Button::A,
+        }));
+    }
+}
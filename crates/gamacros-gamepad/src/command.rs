@@ -11,4 +11,10 @@ pub(crate) enum Command {
     StopRumble {
         id: ControllerId,
     },
+    /// Load the controller mappings stored in the manager.
+    LoadMappings,
+    /// Read the HID devices stored in the manager.
+    LoadHidDevices,
+    /// Disconnect all devices and stop the runtime thread.
+    Shutdown,
 }
@@ -0,0 +1,211 @@
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+
+use crate::events::ControllerEvent;
+use crate::types::{ControllerId, ControllerInfo};
+
+/// Hides short disconnects of flaky, usually Bluetooth, controllers.
+///
+/// A disconnect is held back for the grace window. If a device with the
+/// same vendor, product and serial connects within it, the connection is
+/// bound to the id of the departed device and neither event is published.
+pub(crate) struct ReconnectDebounce {
+    grace: Duration,
+    /// Departed devices and when they disconnected.
+    departed: Vec<(ControllerInfo, Instant)>,
+    /// Backend id of a reconnected device to the id it is published with.
+    aliases: AHashMap<ControllerId, ControllerId>,
+}
+
+impl ReconnectDebounce {
+    pub(crate) fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            departed: Vec::new(),
+            aliases: AHashMap::new(),
+        }
+    }
+
+    pub(crate) fn set_grace(&mut self, grace: Duration) {
+        self.grace = grace;
+    }
+
+    /// Returns the event as it should be published, with ids of re-bound
+    /// devices replaced. `known` resolves published ids to device info.
+    pub(crate) fn apply(
+        &mut self,
+        mut event: ControllerEvent,
+        now: Instant,
+        known: impl FnOnce(ControllerId) -> Option<ControllerInfo>,
+    ) -> Option<ControllerEvent> {
+        match &mut event {
+            ControllerEvent::Connected(info) => {
+                let index = self
+                    .departed
+                    .iter()
+                    .position(|(departed, _)| same_device(departed, info));
+                if let Some(index) = index {
+                    let (departed, _) = self.departed.swap_remove(index);
+                    self.aliases.insert(info.id, departed.id);
+                    return (departed.supports_rumble != info.supports_rumble)
+                        .then_some(ControllerEvent::CapabilitiesChanged {
+                            id: departed.id,
+                            supports_rumble: info.supports_rumble,
+                        });
+                }
+            }
+            ControllerEvent::Disconnected(id) => {
+                *id = self.aliases.remove(id).unwrap_or(*id);
+                if let Some(info) = known(*id) {
+                    if !self.grace.is_zero() && is_identifiable(&info) {
+                        self.departed.push((info, now));
+                        return None;
+                    }
+                }
+            }
+            ControllerEvent::ButtonPressed { id, .. }
+            | ControllerEvent::ButtonReleased { id, .. }
+            | ControllerEvent::AxisMotion { id, .. }
+            | ControllerEvent::JoystickAxisMotion { id, .. }
+            | ControllerEvent::CapabilitiesChanged { id, .. } => {
+                *id = self.published_id(*id);
+            }
+        }
+        Some(event)
+    }
+
+    /// Returns `Disconnected` for departed devices whose window has passed.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<ControllerEvent> {
+        let grace = self.grace;
+        let mut expired = Vec::new();
+        self.departed.retain(|(info, at)| {
+            let keep = now.saturating_duration_since(*at) < grace;
+            if !keep {
+                expired.push(ControllerEvent::Disconnected(info.id));
+            }
+            keep
+        });
+        expired
+    }
+
+    /// Whether no departed device waits for its window to pass.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.departed.is_empty()
+    }
+
+    /// Returns the backend id of a published id, for commands.
+    pub(crate) fn backend_id(&self, id: ControllerId) -> ControllerId {
+        self.aliases
+            .iter()
+            .find_map(|(backend, published)| (*published == id).then_some(*backend))
+            .unwrap_or(id)
+    }
+
+    /// Published id of a backend id.
+    pub(crate) fn published_id(&self, id: ControllerId) -> ControllerId {
+        self.aliases.get(&id).copied().unwrap_or(id)
+    }
+
+    /// Forgets re-bound ids and returns `Disconnected` for every departed
+    /// device, e.g. when the runtime stops.
+    pub(crate) fn drain(&mut self) -> Vec<ControllerEvent> {
+        self.aliases.clear();
+        self.departed
+            .drain(..)
+            .map(|(info, _)| ControllerEvent::Disconnected(info.id))
+            .collect()
+    }
+}
+
+/// Devices without ids cannot be told apart from other models.
+fn is_identifiable(info: &ControllerInfo) -> bool {
+    info.vendor_id != 0 || info.product_id != 0 || info.serial.is_some()
+}
+
+fn same_device(a: &ControllerInfo, b: &ControllerInfo) -> bool {
+    a.vendor_id == b.vendor_id
+        && a.product_id == b.product_id
+        && a.serial == b.serial
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Button;
+
+    fn pad(id: ControllerId, serial: &str) -> ControllerInfo {
+        ControllerInfo {
+            id,
+            name: "Pad".to_string(),
+            supports_rumble: true,
+            vendor_id: 0x054c,
+            product_id: 0x0ce6,
+            serial: Some(serial.to_string()),
+            extra_buttons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn rebinds_device_reconnected_within_grace() {
+        let mut debounce = ReconnectDebounce::new(Duration::from_secs(2));
+        let start = Instant::now();
+        let disconnected = ControllerEvent::Disconnected(1);
+        assert!(debounce
+            .apply(disconnected, start, |_| Some(pad(1, "a")))
+            .is_none());
+
+        let connected = ControllerEvent::Connected(pad(5, "a"));
+        assert!(debounce.apply(connected, start, |_| None).is_none());
+        let pressed = ControllerEvent::ButtonPressed {
+            id: 5,
+            button: Button::A,
+        };
+        assert!(matches!(
+            debounce.apply(pressed, start, |_| None),
+            Some(ControllerEvent::ButtonPressed { id: 1, .. })
+        ));
+        assert_eq!(debounce.backend_id(1), 5);
+        assert!(debounce.expire(start + Duration::from_secs(3)).is_empty());
+
+        // Disconnects of re-bound devices are reported with the published id
+        let disconnected = ControllerEvent::Disconnected(5);
+        assert!(debounce
+            .apply(disconnected, start, |_| Some(pad(1, "a")))
+            .is_none());
+        assert!(matches!(
+            debounce.expire(start + Duration::from_secs(2))[..],
+            [ControllerEvent::Disconnected(1)]
+        ));
+    }
+
+    #[test]
+    fn other_devices_do_not_rebind() {
+        let mut debounce = ReconnectDebounce::new(Duration::from_secs(2));
+        let start = Instant::now();
+        let disconnected = ControllerEvent::Disconnected(1);
+        assert!(debounce
+            .apply(disconnected, start, |_| Some(pad(1, "a")))
+            .is_none());
+
+        let connected = ControllerEvent::Connected(pad(2, "b"));
+        assert!(matches!(
+            debounce.apply(connected, start, |_| None),
+            Some(ControllerEvent::Connected(info)) if info.id == 2
+        ));
+        assert!(matches!(
+            debounce.drain()[..],
+            [ControllerEvent::Disconnected(1)]
+        ));
+    }
+
+    #[test]
+    fn passes_disconnects_without_grace() {
+        let mut debounce = ReconnectDebounce::new(Duration::ZERO);
+        let disconnected = ControllerEvent::Disconnected(1);
+        assert!(matches!(
+            debounce.apply(disconnected, Instant::now(), |_| Some(pad(1, "a"))),
+            Some(ControllerEvent::Disconnected(1))
+        ));
+    }
+}
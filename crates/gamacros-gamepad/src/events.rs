@@ -1,9 +1,15 @@
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Instant;
+
 use crossbeam_channel::Receiver;
+use gamacros_bit_mask::Bitmask;
 
 use crate::types::{Button, ControllerId, ControllerInfo, Axis};
 
 /// Events emitted by the manager about controller lifecycle and input.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 pub enum ControllerEvent {
     /// A controller or joystick has been connected and enumerated.
     Connected(ControllerInfo),
@@ -19,7 +25,164 @@ pub enum ControllerEvent {
         axis: Axis,
         value: f32,
     },
+    /// An axis of a joystick without a game controller mapping moved;
+    /// value is normalized to [-1.0, 1.0].
+    JoystickAxisMotion {
+        id: ControllerId,
+        axis: u8,
+        value: f32,
+    },
+    /// Rumble support of a connected controller changed, e.g. after its
+    /// haptic device was reopened or stopped responding.
+    CapabilitiesChanged {
+        id: ControllerId,
+        supports_rumble: bool,
+    },
+}
+
+impl ControllerEvent {
+    /// Returns the kind of the event, for subscription filters.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::Connected(_) => EventKind::Connected,
+            Self::Disconnected(_) => EventKind::Disconnected,
+            Self::ButtonPressed { .. } => EventKind::ButtonPressed,
+            Self::ButtonReleased { .. } => EventKind::ButtonReleased,
+            Self::AxisMotion { .. } | Self::JoystickAxisMotion { .. } => {
+                EventKind::AxisMotion
+            }
+            Self::CapabilitiesChanged { .. } => EventKind::CapabilitiesChanged,
+        }
+    }
+
+    /// Returns the id of the controller the event is about.
+    pub fn controller_id(&self) -> ControllerId {
+        match self {
+            Self::Connected(info) => info.id,
+            Self::Disconnected(id)
+            | Self::ButtonPressed { id, .. }
+            | Self::ButtonReleased { id, .. }
+            | Self::AxisMotion { id, .. }
+            | Self::JoystickAxisMotion { id, .. }
+            | Self::CapabilitiesChanged { id, .. } => *id,
+        }
+    }
+}
+
+/// Kind of a `ControllerEvent` without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, gamacros_bit_derive::Bit)]
+pub enum EventKind {
+    Connected,
+    Disconnected,
+    ButtonPressed,
+    ButtonReleased,
+    AxisMotion,
+    CapabilitiesChanged,
+}
+
+/// Selects the events a subscription receives. By default every event
+/// of every controller passes.
+#[derive(Debug, Clone)]
+pub struct EventFilter {
+    kinds: Bitmask<EventKind>,
+    controllers: Option<Vec<ControllerId>>,
+    raw: bool,
+}
+
+impl EventFilter {
+    /// Passes only events of the given kinds.
+    pub fn kinds(kinds: &[EventKind]) -> Self {
+        Self {
+            kinds: Bitmask::new(kinds),
+            controllers: None,
+            raw: false,
+        }
+    }
+
+    /// Restricts the filter to events of the given controllers.
+    pub fn with_controllers(mut self, ids: &[ControllerId]) -> Self {
+        self.controllers = Some(ids.to_vec());
+        self
+    }
+
+    /// Receives every axis sample as reported, bypassing the coalescing
+    /// set with `ControllerManager::set_axis_coalescing`.
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
+    pub(crate) fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    /// Whether a subscription with this filter receives `event`.
+    pub fn matches(&self, event: &ControllerEvent) -> bool {
+        if !self.kinds.contains(event.kind()) {
+            return false;
+        }
+        match &self.controllers {
+            Some(ids) => ids.contains(&event.controller_id()),
+            None => true,
+        }
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::kinds(EventKind::ALL)
+    }
+}
+
+/// A controller event with the time the runtime received it from the backend.
+#[derive(Debug, Clone)]
+pub struct TimedEvent {
+    /// Monotonic receipt time, before filtering and calibration.
+    pub at: Instant,
+    pub event: ControllerEvent,
+}
+
+/// What happens to new events of a subscription whose channel is full,
+/// e.g. because its consumer is stuck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// The oldest queued event is dropped to make room.
+    #[default]
+    DropOldest,
+    /// The subscription is ended; its receiver disconnects once drained.
+    Disconnect,
+}
+
+/// Counters of events lost to subscriptions that fell behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BroadcastStats {
+    /// Events dropped by `Overflow::DropOldest`.
+    pub dropped: u64,
+    /// Subscriptions ended by `Overflow::Disconnect`.
+    pub disconnected: u64,
 }
 
 /// Receiving end for controller events subscription.
-pub type EventReceiver = Receiver<ControllerEvent>;
+///
+/// Events arrive in the order the backend reported them, so timestamps
+/// never decrease within a subscription. Derefs to the channel receiver;
+/// dropping every clone ends the subscription.
+#[derive(Debug, Clone)]
+pub struct EventReceiver {
+    rx: Receiver<TimedEvent>,
+    _alive: Arc<()>,
+}
+
+impl EventReceiver {
+    pub(crate) fn new(rx: Receiver<TimedEvent>, alive: Arc<()>) -> Self {
+        Self { rx, _alive: alive }
+    }
+}
+
+impl Deref for EventReceiver {
+    type Target = Receiver<TimedEvent>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rx
+    }
+}
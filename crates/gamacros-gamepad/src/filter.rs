@@ -0,0 +1,153 @@
+use ahash::AHashMap;
+
+use crate::events::ControllerEvent;
+use crate::types::{ControllerId, ControllerInfo};
+
+/// Hides Steam virtual controllers from subscribers.
+///
+/// Steam Input mirrors physical pads as virtual ones, so without
+/// filtering every press would be reported twice.
+pub(crate) struct VirtualFilter {
+    enabled: bool,
+    hidden: AHashMap<ControllerId, ControllerInfo>,
+}
+
+impl VirtualFilter {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            hidden: AHashMap::new(),
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns the event if it should reach subscribers.
+    pub(crate) fn apply(
+        &mut self,
+        event: ControllerEvent,
+    ) -> Option<ControllerEvent> {
+        match &event {
+            ControllerEvent::Connected(info) => {
+                if self.enabled && info.is_steam_virtual() {
+                    self.hidden.insert(info.id, info.clone());
+                    return None;
+                }
+            }
+            ControllerEvent::Disconnected(id) => {
+                if self.hidden.remove(id).is_some() {
+                    return None;
+                }
+            }
+            ControllerEvent::CapabilitiesChanged {
+                id,
+                supports_rumble,
+            } => {
+                if let Some(info) = self.hidden.get_mut(id) {
+                    // Revealed devices should come back with current capabilities
+                    info.supports_rumble = *supports_rumble;
+                    return None;
+                }
+            }
+            ControllerEvent::ButtonPressed { id, .. }
+            | ControllerEvent::ButtonReleased { id, .. }
+            | ControllerEvent::AxisMotion { id, .. }
+            | ControllerEvent::JoystickAxisMotion { id, .. } => {
+                if self.hidden.contains_key(id) {
+                    return None;
+                }
+            }
+        }
+        Some(event)
+    }
+
+    /// Switches filtering on or off and returns the events that make
+    /// already known devices appear or disappear accordingly.
+    pub(crate) fn set_enabled(
+        &mut self,
+        enabled: bool,
+        visible: impl IntoIterator<Item = ControllerInfo>,
+    ) -> Vec<ControllerEvent> {
+        if self.enabled == enabled {
+            return Vec::new();
+        }
+        self.enabled = enabled;
+        if enabled {
+            visible
+                .into_iter()
+                .filter(ControllerInfo::is_steam_virtual)
+                .map(|info| {
+                    let id = info.id;
+                    self.hidden.insert(id, info);
+                    ControllerEvent::Disconnected(id)
+                })
+                .collect()
+        } else {
+            self.hidden
+                .drain()
+                .map(|(_, info)| ControllerEvent::Connected(info))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Button;
+
+    fn info(id: ControllerId, name: &str, vid: u16, pid: u16) -> ControllerInfo {
+        ControllerInfo {
+            id,
+            name: name.to_string(),
+            supports_rumble: false,
+            vendor_id: vid,
+            product_id: pid,
+            serial: None,
+            extra_buttons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn detects_steam_virtual_devices() {
+        assert!(info(0, "Gamepad", 0x28de, 0x11ff).is_steam_virtual());
+        assert!(info(0, "Steam Virtual Gamepad", 0x045e, 0x028e).is_steam_virtual());
+        // Physical Steam Controller
+        assert!(!info(0, "Steam Controller", 0x28de, 0x1142).is_steam_virtual());
+        assert!(!info(0, "Xbox Controller", 0x045e, 0x0b13).is_steam_virtual());
+    }
+
+    #[test]
+    fn hides_events_of_virtual_devices() {
+        let mut filter = VirtualFilter::new(true);
+        let connected = ControllerEvent::Connected(info(3, "Pad", 0x28de, 0x11ff));
+        assert!(filter.apply(connected).is_none());
+        let pressed = ControllerEvent::ButtonPressed {
+            id: 3,
+            button: Button::A,
+        };
+        assert!(filter.apply(pressed).is_none());
+        assert!(filter.apply(ControllerEvent::Disconnected(3)).is_none());
+
+        let physical = ControllerEvent::Connected(info(4, "Pad", 0x054c, 0x0ce6));
+        assert!(filter.apply(physical).is_some());
+    }
+
+    #[test]
+    fn toggling_reveals_and_hides_known_devices() {
+        let mut filter = VirtualFilter::new(false);
+        let steam = info(1, "Steam Virtual Gamepad", 0x045e, 0x028e);
+        let physical = info(2, "DualSense", 0x054c, 0x0ce6);
+
+        let events = filter.set_enabled(true, [steam.clone(), physical]);
+        assert!(matches!(events[..], [ControllerEvent::Disconnected(1)]));
+
+        let events = filter.set_enabled(false, []);
+        assert!(matches!(
+            &events[..],
+            [ControllerEvent::Connected(info)] if info.id == 1
+        ));
+    }
+}
@@ -32,8 +32,7 @@ impl ControllerHandle {
         let high = (high_freq.clamp(0.0, 1.0) * 65535.0).round() as u16;
         let ms = duration.as_millis().min(u32::MAX as u128) as u32;
         self.inner
-            .cmd_tx
-            .send(Command::Rumble {
+            .send_command(Command::Rumble {
                 id: self.id,
                 low,
                 high,
@@ -45,8 +44,7 @@ impl ControllerHandle {
     /// Stops the controller rumble if it is currently active.
     pub fn stop_rumble(&self) -> Result<()> {
         self.inner
-            .cmd_tx
-            .send(Command::StopRumble { id: self.id })
+            .send_command(Command::StopRumble { id: self.id })
             .map_err(|e| Error::Backend(format!("{e}")))
     }
 }
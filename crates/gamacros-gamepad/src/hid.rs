@@ -0,0 +1,27 @@
+use crate::types::Button;
+use crate::DeviceId;
+
+/// A HID device that is not a game controller, such as a foot pedal or
+/// a volume knob. Its switches are reported as buttons of a controller.
+///
+/// Only the `hid-backend` feature on macOS reads these devices, other
+/// builds ignore them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HidDevice {
+    /// USB vendor and product id of the device model.
+    pub device: DeviceId,
+    /// Elements of the device reported as buttons.
+    pub buttons: Vec<HidButton>,
+}
+
+/// A switch of a HID device, identified by its usage page and usage,
+/// e.g. page `0x0c` usage `0xe9` for the volume up key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HidButton {
+    pub usage_page: u16,
+    pub usage: u16,
+    /// Button pressed while the element value is non-zero.
+    pub button: Button,
+}
@@ -1,15 +1,34 @@
+mod backend;
+mod calibration;
+mod coalesce;
 mod command;
+mod debounce;
 mod events;
+mod filter;
 mod handle;
+mod hid;
 mod manager;
+mod mapping;
 mod runtime;
 mod types;
 
 use thiserror::Error;
 
-pub use crate::events::{ControllerEvent, EventReceiver};
+pub use crate::backend::BackendKind;
+pub use crate::calibration::{AxisCalibration, Calibration};
+#[cfg(feature = "virtual-backend")]
+pub use crate::backend::VirtualInjector;
+pub use crate::events::{
+    BroadcastStats, ControllerEvent, EventFilter, EventKind, EventReceiver,
+    Overflow, TimedEvent,
+};
 pub use crate::handle::ControllerHandle;
-pub use crate::manager::ControllerManager;
+pub use crate::hid::{HidButton, HidDevice};
+pub use crate::manager::{
+    ControllerList, ControllerManager, DEFAULT_SUBSCRIPTION_CAPACITY,
+};
+pub use crate::mapping::{is_valid_mapping, ControllerMappings};
+pub use crate::runtime::Runtime;
 pub use crate::types::{Button, ControllerId, ControllerInfo, Axis};
 
 /// Error type for controller management operations.
@@ -29,5 +48,8 @@ pub enum Error {
     Backend(String),
 }
 
+/// USB vendor and product id of a device model.
+pub type DeviceId = (u16, u16);
+
 /// Convenient result alias for controller operations.
 pub type Result<T> = std::result::Result<T, Error>;
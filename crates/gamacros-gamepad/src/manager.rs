@@ -1,64 +1,414 @@
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 use ahash::AHashMap;
-use crossbeam_channel::{unbounded, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, SendError, Sender, TrySendError};
 
+use crate::backend::{BackendKind, Waker};
+#[cfg(feature = "virtual-backend")]
+use crate::backend::VirtualInjector;
 use crate::command::Command;
-use crate::Result;
-use crate::events::{ControllerEvent, EventReceiver};
+use crate::calibration::Calibration;
+use crate::mapping::ControllerMappings;
+use crate::{DeviceId, Error, Result};
+use crate::events::{BroadcastStats, EventFilter, EventReceiver, Overflow, TimedEvent};
+#[cfg(feature = "virtual-backend")]
+use crate::events::ControllerEvent;
 use crate::handle::ControllerHandle;
-use crate::runtime::start_runtime_thread;
+use crate::hid::HidDevice;
+use crate::runtime::{start_runtime_thread, Runtime};
 use crate::types::{ControllerId, ControllerInfo};
 
+/// How long to wait for the initial device enumeration of a backend.
+const READY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Events queued for a subscription by default, a few seconds of axis
+/// motion of a 1000 Hz controller.
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 4096;
+
 /// Shared state used by the manager, the runtime loop and controller handles.
 pub(crate) struct Inner {
-    pub subscribers: Mutex<Vec<Sender<ControllerEvent>>>,
+    pub subscribers: Mutex<Vec<Subscriber>>,
     pub controllers_info: RwLock<AHashMap<ControllerId, ControllerInfo>>,
     pub cmd_tx: Sender<Command>,
+    /// Ends the wait of the running backend, if it waits on events.
+    pub waker: Mutex<Option<Waker>>,
+    /// Whether Steam virtual controllers are hidden from subscribers.
+    pub ignore_virtual: AtomicBool,
+    /// Axis calibration by device model.
+    pub calibrations: RwLock<AHashMap<DeviceId, Calibration>>,
+    /// Bumped whenever `calibrations` changes.
+    pub calibration_epoch: AtomicU64,
+    /// Whether analog triggers are also reported as button presses.
+    pub trigger_buttons: AtomicBool,
+    /// Axis coalescing interval in milliseconds, `0` when off.
+    pub axis_interval_ms: AtomicU64,
+    /// Reconnect grace window in milliseconds, `0` when off.
+    pub reconnect_grace_ms: AtomicU64,
+    /// Controller mappings loaded into backends when they start.
+    pub mappings: RwLock<ControllerMappings>,
+    /// HID devices read next to the backend.
+    pub hid_devices: RwLock<Vec<HidDevice>>,
+    /// Events dropped by subscriptions that overflowed.
+    pub dropped_events: AtomicU64,
+    /// Subscriptions ended because they overflowed.
+    pub disconnected_subscribers: AtomicU64,
+    /// Events injected into the virtual backend.
+    #[cfg(feature = "virtual-backend")]
+    pub virtual_tx: Sender<ControllerEvent>,
+    #[cfg(feature = "virtual-backend")]
+    pub virtual_rx: Receiver<ControllerEvent>,
+}
+
+impl Inner {
+    /// Queues a command and wakes the runtime to run it.
+    pub(crate) fn send_command(
+        &self,
+        cmd: Command,
+    ) -> std::result::Result<(), SendError<Command>> {
+        self.cmd_tx.send(cmd)?;
+        if let Ok(waker) = self.waker.lock() {
+            if let Some(wake) = waker.as_ref() {
+                wake();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sending side of a subscription and the events it wants.
+pub(crate) struct Subscriber {
+    tx: Sender<TimedEvent>,
+    /// Receiver clone to drop the oldest event from.
+    rx: Receiver<TimedEvent>,
+    /// Gone once every `EventReceiver` of the subscription is dropped, as
+    /// the receiver clone keeps the channel connected.
+    alive: Weak<()>,
+    pub filter: EventFilter,
+    overflow: Overflow,
+}
+
+impl Subscriber {
+    /// Queues an event, applying the overflow policy when the channel is
+    /// full. Returns whether the subscription goes on.
+    pub(crate) fn deliver(&self, event: TimedEvent, inner: &Inner) -> bool {
+        if self.alive.strong_count() == 0 {
+            return false;
+        }
+        let event = match self.tx.try_send(event) {
+            Ok(()) => return true,
+            Err(TrySendError::Disconnected(_)) => return false,
+            Err(TrySendError::Full(event)) => event,
+        };
+        match self.overflow {
+            Overflow::DropOldest => {
+                if self.rx.try_recv().is_ok() {
+                    inner.dropped_events.fetch_add(1, Ordering::Relaxed);
+                }
+                // Only the runtime sends, so the freed slot stays free
+                let _ = self.tx.try_send(event);
+                true
+            }
+            Overflow::Disconnect => {
+                inner
+                    .disconnected_subscribers
+                    .fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+}
+
+/// The backend currently driving the runtime thread.
+struct RuntimeThread {
+    kind: BackendKind,
+    handle: Option<JoinHandle<()>>,
+    /// Driven by a `Runtime` the caller pumps, no thread is started.
+    pumped: bool,
 }
 
 /// Manager responsible for discovering controllers and emitting events.
 pub struct ControllerManager {
     pub(crate) inner: Arc<Inner>,
+    cmd_rx: Receiver<Command>,
+    runtime: Mutex<RuntimeThread>,
+}
+
+/// Read-only view of the controllers known to a manager.
+#[derive(Clone)]
+pub struct ControllerList {
+    inner: Arc<Inner>,
+}
+
+impl ControllerList {
+    /// Returns a snapshot of currently known controllers.
+    pub fn controllers(&self) -> Vec<ControllerInfo> {
+        if let Ok(map) = self.inner.controllers_info.read() {
+            return map.values().cloned().collect();
+        }
+        Vec::new()
+    }
 }
 
 impl ControllerManager {
-    /// Creates a new manager and starts the background runtime thread.
+    /// Creates a new manager backed by the default backend and starts the runtime thread.
     /// Blocks briefly until the initial device enumeration completes (up to 1s).
     pub fn new() -> Result<Self> {
+        Self::with_backend(BackendKind::default())
+    }
+
+    /// Creates a new manager backed by the given backend and starts the runtime thread.
+    /// Blocks briefly until the initial device enumeration completes (up to 1s).
+    pub fn with_backend(kind: BackendKind) -> Result<Self> {
+        let mut manager = Self::unstarted(kind, false);
+        // Best-effort start. A failing backend leaves the manager without devices.
+        let (handle, _) = manager.start(kind);
+        if let Ok(runtime) = manager.runtime.get_mut() {
+            runtime.handle = Some(handle);
+        }
+
+        Ok(manager)
+    }
+
+    /// Creates a manager without a runtime thread. The caller drives the
+    /// returned `Runtime` from its own loop by calling `Runtime::pump`.
+    ///
+    /// Switching backends is not supported in this mode.
+    pub fn with_pump(kind: BackendKind) -> Result<(Self, Runtime)> {
+        let manager = Self::unstarted(kind, true);
+        let runtime =
+            Runtime::open(kind, manager.inner.clone(), manager.cmd_rx.clone())?;
+        Ok((manager, runtime))
+    }
+
+    fn unstarted(kind: BackendKind, pumped: bool) -> Self {
         let (cmd_tx, cmd_rx) = unbounded::<Command>();
+        #[cfg(feature = "virtual-backend")]
+        let (virtual_tx, virtual_rx) = unbounded::<ControllerEvent>();
         let inner = Arc::new(Inner {
             subscribers: Mutex::new(Vec::new()),
             controllers_info: RwLock::new(AHashMap::new()),
             cmd_tx,
+            waker: Mutex::new(None),
+            ignore_virtual: AtomicBool::new(false),
+            calibrations: RwLock::new(AHashMap::new()),
+            calibration_epoch: AtomicU64::new(0),
+            trigger_buttons: AtomicBool::new(true),
+            axis_interval_ms: AtomicU64::new(0),
+            reconnect_grace_ms: AtomicU64::new(0),
+            mappings: RwLock::new(ControllerMappings::default()),
+            hid_devices: RwLock::new(Vec::new()),
+            dropped_events: AtomicU64::new(0),
+            disconnected_subscribers: AtomicU64::new(0),
+            #[cfg(feature = "virtual-backend")]
+            virtual_tx,
+            #[cfg(feature = "virtual-backend")]
+            virtual_rx,
         });
 
-        let inner_clone = inner.clone();
-        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
-        start_runtime_thread(inner_clone, cmd_rx, Some(ready_tx));
+        Self {
+            inner,
+            cmd_rx,
+            runtime: Mutex::new(RuntimeThread {
+                kind,
+                handle: None,
+                pumped,
+            }),
+        }
+    }
 
-        // Best-effort wait for the initial enumeration. Time out if backend fails.
-        let _ = ready_rx.recv_timeout(Duration::from_secs(1));
+    /// Returns the kind of the backend currently driving the runtime.
+    pub fn backend(&self) -> BackendKind {
+        self.runtime
+            .lock()
+            .map(|runtime| runtime.kind)
+            .unwrap_or_default()
+    }
 
-        Ok(Self { inner })
+    /// Replaces the running backend.
+    ///
+    /// Devices of the old backend are reported as disconnected and devices
+    /// of the new one as connected. Subscriptions are preserved.
+    /// If the new backend fails to start, the previous one is restored.
+    /// A backend that is not ready within `READY_TIMEOUT` is kept running,
+    /// as it may still come up, but reported as `Error::BackendInit`.
+    /// Managers created by `with_pump` return `Error::Unsupported`.
+    pub fn set_backend(&self, kind: BackendKind) -> Result<()> {
+        let mut runtime = self
+            .runtime
+            .lock()
+            .map_err(|e| Error::Backend(e.to_string()))?;
+        if runtime.pumped {
+            return Err(Error::Unsupported);
+        }
+        if runtime.kind == kind && runtime.handle.is_some() {
+            return Ok(());
+        }
+
+        self.stop(runtime.handle.take());
+        let (handle, ready) = self.start(kind);
+        if let Some(Err(e)) = ready {
+            let _ = handle.join();
+            let (previous, _) = self.start(runtime.kind);
+            runtime.handle = Some(previous);
+            return Err(e);
+        }
+        runtime.kind = kind;
+        runtime.handle = Some(handle);
+        match ready {
+            Some(_) => Ok(()),
+            None => Err(Error::BackendInit(format!(
+                "{kind:?} backend is not ready after {READY_TIMEOUT:?}"
+            ))),
+        }
+    }
+
+    /// Hides or reveals Steam Input virtual controllers.
+    ///
+    /// Already connected virtual devices are reported as disconnected
+    /// when hidden and as connected again when revealed.
+    pub fn set_ignore_virtual(&self, ignore: bool) {
+        self.inner.ignore_virtual.store(ignore, Ordering::Relaxed);
+    }
+
+    /// Replaces axis calibration of device models.
+    /// Axis values of matching devices are corrected before broadcast.
+    pub fn set_calibrations(&self, calibrations: AHashMap<DeviceId, Calibration>) {
+        if let Ok(mut map) = self.inner.calibrations.write() {
+            *map = calibrations;
+        }
+        self.inner.calibration_epoch.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Turns the `LeftTrigger` and `RightTrigger` button events off or on.
+    /// Backends report pulled triggers as button presses next to their
+    /// axis motion; profiles that read the analog values can drop them.
+    ///
+    /// A trigger held while they are turned off still reports its release.
+    pub fn set_trigger_buttons(&self, enabled: bool) {
+        self.inner.trigger_buttons.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Delivers axis motion at most once per `interval`, with the latest
+    /// value of each axis, instead of every sample of high polling rate
+    /// controllers. Subscriptions with a `raw` filter still receive every
+    /// sample. `Duration::ZERO` turns this off.
+    pub fn set_axis_coalescing(&self, interval: Duration) {
+        let ms = interval.as_millis().min(u64::MAX as u128) as u64;
+        self.inner.axis_interval_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Holds back disconnects for `grace`. A device with the same vendor,
+    /// product and serial that connects within it keeps its controller id
+    /// and neither event is reported. `Duration::ZERO` turns this off.
+    pub fn set_reconnect_grace(&self, grace: Duration) {
+        let ms = grace.as_millis().min(u64::MAX as u128) as u64;
+        self.inner.reconnect_grace_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Replaces the controller mappings loaded into the backend.
+    ///
+    /// Mappings are loaded by the runtime and again whenever a backend
+    /// starts. SDL keeps mappings that were already loaded until exit, so
+    /// removed ones stay in effect for the running backend.
+    pub fn set_mappings(&self, mappings: ControllerMappings) {
+        let Ok(mut current) = self.inner.mappings.write() else {
+            return;
+        };
+        if *current == mappings {
+            return;
+        }
+        *current = mappings;
+        let _ = self.inner.send_command(Command::LoadMappings);
+    }
+
+    /// Sets the HID devices, such as foot pedals, whose switches are
+    /// reported as buttons. Builds without the `hid-backend` feature and
+    /// platforms other than macOS ignore them.
+    pub fn set_hid_devices(&self, devices: Vec<HidDevice>) {
+        let Ok(mut current) = self.inner.hid_devices.write() else {
+            return;
+        };
+        if *current == devices {
+            return;
+        }
+        *current = devices;
+        let _ = self.inner.send_command(Command::LoadHidDevices);
+    }
+
+    /// Returns an injector that feeds events into the virtual backend.
+    /// Events are only delivered while `BackendKind::Virtual` is active.
+    #[cfg(feature = "virtual-backend")]
+    pub fn injector(&self) -> VirtualInjector {
+        VirtualInjector::new(self.inner.virtual_tx.clone())
     }
 
     /// Subscribes to controller events. Dropped subscribers are cleaned automatically.
     pub fn subscribe(&self) -> EventReceiver {
-        let (tx, rx) = unbounded();
+        self.subscribe_filtered(EventFilter::default())
+    }
+
+    /// Subscribes to the controller events passing `filter`, e.g. to skip
+    /// high-rate axis motion in consumers that only track connections.
+    ///
+    /// Up to `DEFAULT_SUBSCRIPTION_CAPACITY` events are queued, after that the oldest
+    /// ones are dropped.
+    pub fn subscribe_filtered(&self, filter: EventFilter) -> EventReceiver {
+        self.subscribe_bounded(
+            filter,
+            DEFAULT_SUBSCRIPTION_CAPACITY,
+            Overflow::DropOldest,
+        )
+    }
+
+    /// Like `subscribe_filtered`, with up to `capacity` queued events and
+    /// `overflow` deciding what happens once the consumer falls behind.
+    /// Lost events are counted in `broadcast_stats`.
+    pub fn subscribe_bounded(
+        &self,
+        filter: EventFilter,
+        capacity: usize,
+        overflow: Overflow,
+    ) -> EventReceiver {
+        // A zero capacity channel would only pass events to a waiting receiver
+        let (tx, rx) = bounded(capacity.max(1));
+        let alive = Arc::new(());
         if let Ok(mut subs) = self.inner.subscribers.lock() {
-            subs.push(tx);
+            subs.push(Subscriber {
+                tx,
+                rx: rx.clone(),
+                alive: Arc::downgrade(&alive),
+                filter,
+                overflow,
+            });
+        }
+        EventReceiver::new(rx, alive)
+    }
+
+    /// Returns how many events subscriptions lost so far.
+    pub fn broadcast_stats(&self) -> BroadcastStats {
+        BroadcastStats {
+            dropped: self.inner.dropped_events.load(Ordering::Relaxed),
+            disconnected: self
+                .inner
+                .disconnected_subscribers
+                .load(Ordering::Relaxed),
         }
-        rx
     }
 
     /// Returns a snapshot of currently known controllers.
     pub fn controllers(&self) -> Vec<ControllerInfo> {
-        if let Ok(map) = self.inner.controllers_info.read() {
-            return map.values().cloned().collect();
+        self.controller_list().controllers()
+    }
+
+    /// Returns a view of the known controllers that can be shared with
+    /// other threads, e.g. to answer api clients.
+    pub fn controller_list(&self) -> ControllerList {
+        ControllerList {
+            inner: self.inner.clone(),
         }
-        Vec::new()
     }
 
     /// Returns a handle to a controller by id if it is currently known.
@@ -73,4 +423,28 @@ impl ControllerManager {
         }
         None
     }
+
+    /// Starts a runtime thread and waits for its initial enumeration.
+    /// The readiness result is `None` if the backend did not report in time.
+    fn start(&self, kind: BackendKind) -> (JoinHandle<()>, Option<Result<()>>) {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let handle = start_runtime_thread(
+            kind,
+            self.inner.clone(),
+            self.cmd_rx.clone(),
+            Some(ready_tx),
+        );
+        (handle, ready_rx.recv_timeout(READY_TIMEOUT).ok())
+    }
+
+    /// Asks the runtime thread to shut down and waits for it.
+    fn stop(&self, handle: Option<JoinHandle<()>>) {
+        let Some(handle) = handle else {
+            return;
+        };
+        let _ = self.inner.send_command(Command::Shutdown);
+        let _ = handle.join();
+        // Drop commands addressed to devices of the stopped backend.
+        while self.cmd_rx.try_recv().is_ok() {}
+    }
 }
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+/// SDL game controller mappings to load in addition to the built-in ones,
+/// in the `gamecontrollerdb.txt` format.
+///
+/// Devices that become known game controllers once the mappings are loaded
+/// are reconnected with their buttons mapped. Backends other than SDL2
+/// ignore the mappings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControllerMappings {
+    /// Mapping database files, loaded in order.
+    pub files: Vec<PathBuf>,
+    /// Mapping lines, added after the files so they take precedence.
+    pub mappings: Vec<String>,
+}
+
+impl ControllerMappings {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.mappings.is_empty()
+    }
+}
+
+/// Checks the shape of a mapping line: `GUID,name,bindings...`.
+/// Bindings themselves are validated by SDL when the mapping is added.
+pub fn is_valid_mapping(line: &str) -> bool {
+    let mut fields = line.split(',');
+    let guid = fields.next().unwrap_or_default();
+    let name = fields.next();
+    let has_binding = fields.any(|field| field.contains(':'));
+    let valid_guid = guid == "xinput"
+        || guid.len() == 32 && guid.chars().all(|c| c.is_ascii_hexdigit());
+    valid_guid && name.is_some_and(|name| !name.is_empty()) && has_binding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_mapping_lines() {
+        assert!(is_valid_mapping(
+            "030000005e0400008e02000014010000,Xbox 360 Controller,a:b0,b:b1,platform:Linux,"
+        ));
+        assert!(is_valid_mapping("xinput,XInput Controller,a:b0,"));
+        assert!(!is_valid_mapping("0300005e04,Short guid,a:b0"));
+        assert!(!is_valid_mapping("030000005e0400008e02000014010000,,a:b0"));
+        assert!(!is_valid_mapping(
+            "030000005e0400008e02000014010000,No bindings"
+        ));
+    }
+}
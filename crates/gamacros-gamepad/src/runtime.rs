@@ -1,429 +1,650 @@
+use std::sync::mpsc;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::thread;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use crossbeam_channel::Receiver;
 use ahash::AHashMap;
-use sdl2::controller::{Button as SdlButton, GameController, Axis as SdlAxis};
-use sdl2::event::Event;
-use sdl2::haptic::Haptic;
-use sdl2::joystick::Joystick;
+use crossbeam_channel::Receiver;
 
+use crate::backend::{self, BackendKind, GamepadBackend};
+use crate::calibration::Calibration;
+use crate::coalesce::AxisCoalescer;
 use crate::command::Command;
-use crate::events::ControllerEvent;
+use crate::debounce::ReconnectDebounce;
+use crate::events::{ControllerEvent, TimedEvent};
+use crate::filter::VirtualFilter;
 use crate::manager::Inner;
-use crate::types::{Button, ControllerId, ControllerInfo, Axis};
+use crate::types::{Button, ControllerId, ControllerInfo};
+use crate::Result;
 
-/// Starts the SDL2-backed runtime thread that drives device discovery and events.
+/// Maximum time a backend waits for input before handling commands.
+const PUMP_TIMEOUT: Duration = Duration::from_millis(10);
+/// Maximum wait of an idle backend that wakes up for commands itself.
+const IDLE_PUMP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Starts the runtime thread that drives device discovery and events
+/// using the backend of the given kind.
+///
+/// The outcome of backend initialization is reported through `ready_tx`
+/// once the initial enumeration completes.
 pub(crate) fn start_runtime_thread(
+    kind: BackendKind,
     inner: Arc<Inner>,
     cmd_rx: Receiver<Command>,
-    ready_tx: Option<std::sync::mpsc::Sender<()>>,
-) {
+    ready_tx: Option<mpsc::Sender<Result<()>>>,
+) -> JoinHandle<()> {
     thread::spawn(move || {
-        // SDL must live entirely within this thread
-        let sdl_ctx = match sdl2::init() {
-            Ok(ctx) => ctx,
-            Err(_) => {
+        // Backend must live entirely within this thread
+        let backend = match backend::open(kind, &inner) {
+            Ok(backend) => backend,
+            Err(e) => {
+                if let Some(tx) = ready_tx {
+                    let _ = tx.send(Err(e));
+                }
                 return;
             }
         };
-        let controller_subsystem = match sdl_ctx.game_controller() {
-            Ok(c) => c,
-            Err(_) => return,
-        };
-        let joystick_subsystem = match sdl_ctx.joystick() {
-            Ok(j) => j,
-            Err(_) => return,
-        };
-        let haptic_subsystem = match sdl_ctx.haptic() {
-            Ok(h) => h,
-            Err(_) => return,
-        };
-        let mut event_pump = match sdl_ctx.event_pump() {
-            Ok(p) => p,
-            Err(_) => return,
+        run(backend, inner, &cmd_rx, ready_tx);
+    })
+}
+
+fn run(
+    mut backend: Box<dyn GamepadBackend>,
+    inner: Arc<Inner>,
+    cmd_rx: &Receiver<Command>,
+    ready_tx: Option<mpsc::Sender<Result<()>>>,
+) {
+    let waker = backend.waker();
+    let wakes = waker.is_some();
+    if let Ok(mut current) = inner.waker.lock() {
+        *current = waker;
+    }
+    let mut publisher = Publisher::new(inner);
+
+    load_mappings(backend.as_mut(), &mut publisher);
+    load_hid_devices(backend.as_mut(), &mut publisher);
+    backend.enumerate(&mut |event| {
+        publisher.emit(event);
+    });
+    if let Some(tx) = ready_tx {
+        let _ = tx.send(Ok(()));
+    }
+
+    loop {
+        let timeout = if wakes && backend.is_idle() && publisher.is_idle() {
+            IDLE_PUMP_TIMEOUT
+        } else {
+            PUMP_TIMEOUT
         };
+        backend.pump(timeout, &mut |event| {
+            publisher.emit(event);
+        });
+        publisher.sync_filter(&mut |_| {});
+        publisher.sync_reconnects(&mut |_| {});
+        publisher.sync_calibrations();
+        publisher.sync_axes();
+        if !handle_commands(backend.as_mut(), &mut publisher, cmd_rx) {
+            break;
+        }
+    }
+    // The waker must not outlive the backend
+    if let Ok(mut current) = publisher.inner.waker.lock() {
+        *current = None;
+    };
+}
 
-        let mut controllers: AHashMap<ControllerId, GameController> =
-            AHashMap::new();
-        let mut joysticks: AHashMap<ControllerId, Joystick> = AHashMap::new();
-        let mut haptics: AHashMap<ControllerId, Haptic> = AHashMap::new();
-        let mut trigger_state: AHashMap<ControllerId, (bool, bool)> =
-            AHashMap::new();
-
-        // Initial enumeration
-        if let Ok(num_joysticks) = joystick_subsystem.num_joysticks() {
-            for i in 0..num_joysticks {
-                if controller_subsystem.is_game_controller(i) {
-                    if let Ok(controller) = controller_subsystem.open(i) {
-                        let id: ControllerId = match joystick_subsystem.open(i) {
-                            Ok(js) => js.instance_id() as ControllerId,
-                            Err(_) => i as ControllerId,
-                        };
-                        let info = ControllerInfo {
-                            id,
-                            name: controller.name().to_string(),
-                            vendor_id: controller.vendor_id().unwrap_or(0),
-                            product_id: controller.product_id().unwrap_or(0),
-                            supports_rumble: controller.has_rumble(),
-                        };
-                        controllers.insert(id, controller);
-                        if let Ok(mut map) = inner.controllers_info.write() {
-                            map.insert(id, info.clone());
-                        }
-                        broadcast(&inner, ControllerEvent::Connected(info));
-                    }
-                } else if let Ok(joystick) = joystick_subsystem.open(i) {
-                    let id: ControllerId = joystick.instance_id() as ControllerId;
-                    if joystick.has_rumble() {
-                        if let Ok(h) = haptic_subsystem
-                            .open_from_joystick_id(joystick.instance_id())
-                        {
-                            haptics.insert(id, h);
-                        }
-                    }
-                    let info = ControllerInfo {
-                        id,
-                        name: joystick.name().to_string(),
-                        vendor_id: 0,
-                        product_id: 0,
-                        supports_rumble: joystick.has_rumble(),
-                    };
-                    joysticks.insert(id, joystick);
-                    if let Ok(mut map) = inner.controllers_info.write() {
-                        map.insert(id, info.clone());
-                    }
-                    broadcast(&inner, ControllerEvent::Connected(info));
+/// Runs queued commands. Returns `false` once the runtime should stop.
+fn handle_commands(
+    backend: &mut dyn GamepadBackend,
+    publisher: &mut Publisher,
+    cmd_rx: &Receiver<Command>,
+) -> bool {
+    while let Ok(cmd) = cmd_rx.try_recv() {
+        match cmd {
+            Command::Rumble { id, low, high, ms } => {
+                let id = publisher.debounce.backend_id(id);
+                if let Err(e) = backend.rumble(id, low, high, ms) {
+                    eprintln!("Failed to set rumble: {e}");
+                }
+            }
+            Command::StopRumble { id } => {
+                let id = publisher.debounce.backend_id(id);
+                if let Err(e) = backend.stop_rumble(id) {
+                    eprintln!("Failed to stop rumble: {e}");
                 }
             }
+            Command::LoadMappings => load_mappings(backend, publisher),
+            Command::LoadHidDevices => load_hid_devices(backend, publisher),
+            Command::Shutdown => {
+                publisher.disconnect_all(backend.controller_ids());
+                return false;
+            }
         }
+    }
+    true
+}
+
+/// Loads the controller mappings stored in the manager into the backend.
+fn load_mappings(backend: &mut dyn GamepadBackend, publisher: &mut Publisher) {
+    let mappings = match publisher.inner.mappings.read() {
+        Ok(mappings) if !mappings.is_empty() => mappings.clone(),
+        _ => return,
+    };
+    if let Err(e) = backend.load_mappings(&mappings, &mut |event| {
+        publisher.emit(event);
+    }) {
+        eprintln!("Failed to load controller mappings: {e}");
+    }
+}
+
+/// Passes the HID devices stored in the manager to the backend.
+fn load_hid_devices(backend: &mut dyn GamepadBackend, publisher: &mut Publisher) {
+    let devices = match publisher.inner.hid_devices.read() {
+        Ok(devices) => devices.clone(),
+        Err(_) => return,
+    };
+    if let Err(e) = backend.load_hid_devices(&devices, &mut |event| {
+        publisher.emit(event);
+    }) {
+        eprintln!("Failed to open HID devices: {e}");
+    }
+}
+
+/// Backend driven from the caller's own loop instead of a runtime thread,
+/// created by `ControllerManager::with_pump`.
+///
+/// Like the backend itself, the runtime must stay on the thread that
+/// created it. Subscribers and controller handles of the manager keep
+/// working, commands such as rumble run on the next pump.
+pub struct Runtime {
+    backend: Box<dyn GamepadBackend>,
+    publisher: Publisher,
+    cmd_rx: Receiver<Command>,
+    /// Events of the initial enumeration, returned by the first pump.
+    pending: Vec<TimedEvent>,
+}
+
+impl Runtime {
+    pub(crate) fn open(
+        kind: BackendKind,
+        inner: Arc<Inner>,
+        cmd_rx: Receiver<Command>,
+    ) -> Result<Self> {
+        let mut backend = backend::open(kind, &inner)?;
+        let mut publisher = Publisher::new(inner);
+        let mut pending = Vec::new();
+        load_mappings(backend.as_mut(), &mut publisher);
+        load_hid_devices(backend.as_mut(), &mut publisher);
+        backend.enumerate(&mut |event| pending.extend(publisher.emit(event)));
+        Ok(Self {
+            backend,
+            publisher,
+            cmd_rx,
+            pending,
+        })
+    }
+
+    /// Handles pending input and commands without waiting and returns
+    /// the resulting events. They are also sent to subscribers.
+    ///
+    /// Axis motion is returned as reported, coalescing only applies to
+    /// subscribers.
+    pub fn pump(&mut self) -> Vec<TimedEvent> {
+        self.pump_timeout(Duration::ZERO)
+    }
+
+    /// Like `pump`, but waits up to `timeout` for input to arrive.
+    pub fn pump_timeout(&mut self, timeout: Duration) -> Vec<TimedEvent> {
+        let mut events = std::mem::take(&mut self.pending);
+        let publisher = &mut self.publisher;
+        self.backend
+            .pump(timeout, &mut |event| events.extend(publisher.emit(event)));
+        publisher.sync_filter(&mut |event| events.push(event));
+        publisher.sync_reconnects(&mut |event| events.push(event));
+        publisher.sync_calibrations();
+        publisher.sync_axes();
+        // Shutdown is only sent to runtime threads
+        handle_commands(self.backend.as_mut(), publisher, &self.cmd_rx);
+        events
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.publisher.disconnect_all(self.backend.controller_ids());
+    }
+}
 
-        if let Some(tx) = ready_tx {
-            let _ = tx.send(());
+/// Passes backend events through the reconnect debounce, the virtual
+/// device filter, axis calibration and the trigger button switch before
+/// they are published, coalescing axis motion if enabled.
+struct Publisher {
+    inner: Arc<Inner>,
+    debounce: ReconnectDebounce,
+    filter: VirtualFilter,
+    calibration_epoch: u64,
+    /// Calibration of connected devices that have one.
+    calibrations: AHashMap<ControllerId, Calibration>,
+    /// Trigger buttons held down, so their release passes when trigger
+    /// buttons were turned off in between.
+    held_triggers: Vec<(ControllerId, Button)>,
+    coalescer: AxisCoalescer,
+}
+
+impl Publisher {
+    fn new(inner: Arc<Inner>) -> Self {
+        Self {
+            debounce: ReconnectDebounce::new(reconnect_grace(&inner)),
+            filter: VirtualFilter::new(inner.ignore_virtual.load(Ordering::Relaxed)),
+            calibration_epoch: inner.calibration_epoch.load(Ordering::Relaxed),
+            calibrations: AHashMap::new(),
+            held_triggers: Vec::new(),
+            coalescer: AxisCoalescer::new(axis_interval(&inner)),
+            inner,
         }
+    }
 
-        loop {
-            // Wait for an SDL event or timeout to reduce idle CPU usage
-            if let Some(event) = event_pump.wait_event_timeout(10) {
-                match event {
-                    Event::ControllerDeviceAdded { which, .. } => {
-                        if let Ok(controller) = controller_subsystem.open(which) {
-                            let id: ControllerId =
-                                match joystick_subsystem.open(which) {
-                                    Ok(js) => js.instance_id() as ControllerId,
-                                    Err(_) => which as ControllerId,
-                                };
-                            let info = ControllerInfo {
-                                id,
-                                name: controller.name().to_string(),
-                                vendor_id: controller.vendor_id().unwrap_or(0),
-                                product_id: controller.product_id().unwrap_or(0),
-                                supports_rumble: controller.has_rumble(),
-                            };
-                            controllers.insert(id, controller);
-                            if let Ok(mut map) = inner.controllers_info.write() {
-                                map.insert(id, info.clone());
-                            }
-                            broadcast(&inner, ControllerEvent::Connected(info));
-                        }
-                    }
-                    Event::ControllerDeviceRemoved { which, .. } => {
-                        let id: ControllerId = which as ControllerId;
-                        controllers.remove(&id);
-                        joysticks.remove(&id);
-                        haptics.remove(&id);
-                        trigger_state.remove(&id);
-                        if let Ok(mut map) = inner.controllers_info.write() {
-                            map.remove(&id);
-                        }
-                        broadcast(&inner, ControllerEvent::Disconnected(id));
-                    }
-                    Event::ControllerButtonDown { which, button, .. } => {
-                        if let Some(btn) = map_sdl_button(button) {
-                            broadcast(
-                                &inner,
-                                ControllerEvent::ButtonPressed {
-                                    id: which as ControllerId,
-                                    button: btn,
-                                },
-                            );
-                        }
-                    }
-                    Event::ControllerButtonUp { which, button, .. } => {
-                        if let Some(btn) = map_sdl_button(button) {
-                            broadcast(
-                                &inner,
-                                ControllerEvent::ButtonReleased {
-                                    id: which as ControllerId,
-                                    button: btn,
-                                },
-                            );
-                        }
-                    }
-                    Event::ControllerAxisMotion {
-                        which, axis, value, ..
-                    } => {
-                        const THRESHOLD: i16 = 20000;
-                        let id = which as ControllerId;
-                        let entry =
-                            trigger_state.entry(id).or_insert((false, false));
-
-                        // Emit analog event for all axes
-                        if let Some(mapped) = map_sdl_axis(axis) {
-                            let norm = (value as f32) / (i16::MAX as f32);
-                            broadcast(
-                                &inner,
-                                ControllerEvent::AxisMotion {
-                                    id,
-                                    axis: mapped,
-                                    value: norm,
-                                },
-                            );
-                        }
-
-                        // Preserve trigger-as-button semantics for compatibility
-                        match axis {
-                            SdlAxis::TriggerLeft => {
-                                let pressed = value > THRESHOLD;
-                                if pressed && !entry.0 {
-                                    broadcast(
-                                        &inner,
-                                        ControllerEvent::ButtonPressed {
-                                            id,
-                                            button: Button::LeftTrigger,
-                                        },
-                                    );
-                                    entry.0 = true;
-                                } else if !pressed && entry.0 {
-                                    broadcast(
-                                        &inner,
-                                        ControllerEvent::ButtonReleased {
-                                            id,
-                                            button: Button::LeftTrigger,
-                                        },
-                                    );
-                                    entry.0 = false;
-                                }
-                            }
-                            SdlAxis::TriggerRight => {
-                                let pressed = value > THRESHOLD;
-                                if pressed && !entry.1 {
-                                    broadcast(
-                                        &inner,
-                                        ControllerEvent::ButtonPressed {
-                                            id,
-                                            button: Button::RightTrigger,
-                                        },
-                                    );
-                                    entry.1 = true;
-                                } else if !pressed && entry.1 {
-                                    broadcast(
-                                        &inner,
-                                        ControllerEvent::ButtonReleased {
-                                            id,
-                                            button: Button::RightTrigger,
-                                        },
-                                    );
-                                    entry.1 = false;
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                    _ => {}
+    /// Publishes an event, returning it as subscribers received it.
+    fn emit(&mut self, event: ControllerEvent) -> Option<TimedEvent> {
+        let at = Instant::now();
+        let inner = &self.inner;
+        let event = self.debounce.apply(event, at, |id| {
+            inner.controllers_info.read().ok()?.get(&id).cloned()
+        })?;
+        self.forward(at, event)
+    }
+
+    /// Publishes an event that already passed the reconnect debounce.
+    fn forward(
+        &mut self,
+        at: Instant,
+        event: ControllerEvent,
+    ) -> Option<TimedEvent> {
+        let mut event = self.filter.apply(event)?;
+        match &mut event {
+            ControllerEvent::Connected(info) => {
+                if let Some(calibration) = self.lookup_calibration(info) {
+                    self.calibrations.insert(info.id, calibration);
                 }
-                // Drain any additional queued events quickly
-                for ev in event_pump.poll_iter() {
-                    match ev {
-                        Event::ControllerDeviceAdded { which, .. } => {
-                            if let Ok(controller) = controller_subsystem.open(which)
-                            {
-                                let id: ControllerId =
-                                    match joystick_subsystem.open(which) {
-                                        Ok(js) => js.instance_id() as ControllerId,
-                                        Err(_) => which as ControllerId,
-                                    };
-                                let info = ControllerInfo {
-                                    id,
-                                    name: controller.name().to_string(),
-                                    vendor_id: controller.vendor_id().unwrap_or(0),
-                                    product_id: controller.product_id().unwrap_or(0),
-                                    supports_rumble: controller.has_rumble(),
-                                };
-                                controllers.insert(id, controller);
-                                if let Ok(mut map) = inner.controllers_info.write() {
-                                    map.insert(id, info.clone());
-                                }
-                                broadcast(&inner, ControllerEvent::Connected(info));
-                            }
-                        }
-                        Event::ControllerDeviceRemoved { which, .. } => {
-                            let id: ControllerId = which as ControllerId;
-                            controllers.remove(&id);
-                            joysticks.remove(&id);
-                            haptics.remove(&id);
-                            trigger_state.remove(&id);
-                            if let Ok(mut map) = inner.controllers_info.write() {
-                                map.remove(&id);
-                            }
-                            broadcast(&inner, ControllerEvent::Disconnected(id));
-                        }
-                        Event::ControllerButtonDown { which, button, .. } => {
-                            if let Some(btn) = map_sdl_button(button) {
-                                broadcast(
-                                    &inner,
-                                    ControllerEvent::ButtonPressed {
-                                        id: which as ControllerId,
-                                        button: btn,
-                                    },
-                                );
-                            }
-                        }
-                        Event::ControllerButtonUp { which, button, .. } => {
-                            if let Some(btn) = map_sdl_button(button) {
-                                broadcast(
-                                    &inner,
-                                    ControllerEvent::ButtonReleased {
-                                        id: which as ControllerId,
-                                        button: btn,
-                                    },
-                                );
-                            }
-                        }
-                        Event::ControllerAxisMotion {
-                            which, axis, value, ..
-                        } => {
-                            const THRESHOLD: i16 = 20000;
-                            let id = which as ControllerId;
-                            let entry =
-                                trigger_state.entry(id).or_insert((false, false));
-                            if let Some(mapped) = map_sdl_axis(axis) {
-                                let norm = (value as f32) / (i16::MAX as f32);
-                                broadcast(
-                                    &inner,
-                                    ControllerEvent::AxisMotion {
-                                        id,
-                                        axis: mapped,
-                                        value: norm,
-                                    },
-                                );
-                            }
-                            match axis {
-                                SdlAxis::TriggerLeft => {
-                                    let pressed = value > THRESHOLD;
-                                    if pressed && !entry.0 {
-                                        broadcast(
-                                            &inner,
-                                            ControllerEvent::ButtonPressed {
-                                                id,
-                                                button: Button::LeftTrigger,
-                                            },
-                                        );
-                                        entry.0 = true;
-                                    } else if !pressed && entry.0 {
-                                        broadcast(
-                                            &inner,
-                                            ControllerEvent::ButtonReleased {
-                                                id,
-                                                button: Button::LeftTrigger,
-                                            },
-                                        );
-                                        entry.0 = false;
-                                    }
-                                }
-                                SdlAxis::TriggerRight => {
-                                    let pressed = value > THRESHOLD;
-                                    if pressed && !entry.1 {
-                                        broadcast(
-                                            &inner,
-                                            ControllerEvent::ButtonPressed {
-                                                id,
-                                                button: Button::RightTrigger,
-                                            },
-                                        );
-                                        entry.1 = true;
-                                    } else if !pressed && entry.1 {
-                                        broadcast(
-                                            &inner,
-                                            ControllerEvent::ButtonReleased {
-                                                id,
-                                                button: Button::RightTrigger,
-                                            },
-                                        );
-                                        entry.1 = false;
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    }
+            }
+            ControllerEvent::Disconnected(id) => {
+                self.calibrations.remove(id);
+                self.held_triggers.retain(|(held, _)| held != id);
+            }
+            ControllerEvent::ButtonPressed {
+                id,
+                button: button @ (Button::LeftTrigger | Button::RightTrigger),
+            } => {
+                if !self.inner.trigger_buttons.load(Ordering::Relaxed) {
+                    return None;
                 }
+                self.held_triggers.push((*id, *button));
             }
-
-            // Handle commands
-            while let Ok(cmd) = cmd_rx.try_recv() {
-                match cmd {
-                    Command::Rumble { id, low, high, ms } => {
-                        if let Some(ctrl) = controllers.get_mut(&id) {
-                            if let Err(e) = ctrl.set_rumble(low, high, ms) {
-                                println!("{}", ctrl.has_rumble());
-                                eprintln!("Failed to set rumble: {e}");
-                            }
-                        } else if let Some(h) = haptics.get_mut(&id) {
-                            let strength = (low.max(high) as f32) / 65535.0;
-                            h.rumble_play(strength, ms);
-                        }
-                    }
-                    Command::StopRumble { id } => {
-                        if let Some(ctrl) = controllers.get_mut(&id) {
-                            if let Err(e) = ctrl.set_rumble(0, 0, 0) {
-                                eprintln!("Failed to stop rumble: {e}");
-                            }
-                        } else if let Some(h) = haptics.get_mut(&id) {
-                            h.rumble_stop();
-                        }
-                    }
+            ControllerEvent::ButtonReleased {
+                id,
+                button: button @ (Button::LeftTrigger | Button::RightTrigger),
+            } => {
+                let held = (*id, *button);
+                let index = self.held_triggers.iter().position(|t| *t == held)?;
+                self.held_triggers.swap_remove(index);
+            }
+            ControllerEvent::CapabilitiesChanged {
+                id,
+                supports_rumble,
+            } => {
+                // Backends report after every rumble attempt, only changes pass
+                let map = self.inner.controllers_info.read().ok()?;
+                if map.get(id)?.supports_rumble == *supports_rumble {
+                    return None;
+                }
+            }
+            ControllerEvent::AxisMotion { id, axis, value } => {
+                if let Some(calibration) = self.calibrations.get(id) {
+                    *value = calibration.apply(*axis, *value);
                 }
             }
+            _ => {}
         }
-    });
+        let event = TimedEvent { at, event };
+        self.publish(event.clone());
+        Some(event)
+    }
+
+    fn lookup_calibration(&self, info: &ControllerInfo) -> Option<Calibration> {
+        let map = self.inner.calibrations.read().ok()?;
+        map.get(&(info.vendor_id, info.product_id)).copied()
+    }
+
+    /// Re-resolves calibration of connected devices after it was replaced.
+    fn sync_calibrations(&mut self) {
+        let epoch = self.inner.calibration_epoch.load(Ordering::Relaxed);
+        if epoch == self.calibration_epoch {
+            return;
+        }
+        self.calibration_epoch = epoch;
+        let visible: Vec<ControllerInfo> = self
+            .inner
+            .controllers_info
+            .read()
+            .map(|map| map.values().cloned().collect())
+            .unwrap_or_default();
+        self.calibrations = visible
+            .iter()
+            .filter_map(|info| Some((info.id, self.lookup_calibration(info)?)))
+            .collect();
+    }
+
+    /// Publishes disconnects of departed devices whose reconnect window
+    /// has passed, passing them to `emitted` too.
+    fn sync_reconnects(&mut self, emitted: &mut dyn FnMut(TimedEvent)) {
+        self.debounce.set_grace(reconnect_grace(&self.inner));
+        let at = Instant::now();
+        for event in self.debounce.expire(at) {
+            if let Some(event) = self.forward(at, event) {
+                emitted(event);
+            }
+        }
+    }
+
+    /// Publishes disconnects of the given backend devices and of departed
+    /// ones right away, when the runtime stops.
+    fn disconnect_all(&mut self, ids: Vec<ControllerId>) {
+        let at = Instant::now();
+        let mut events: Vec<ControllerEvent> = ids
+            .into_iter()
+            .map(|id| ControllerEvent::Disconnected(self.debounce.published_id(id)))
+            .collect();
+        events.extend(self.debounce.drain());
+        for event in events {
+            self.forward(at, event);
+        }
+    }
+
+    /// Applies a changed `ignore_virtual` setting to already known devices,
+    /// passing the resulting connection events to `emitted` too.
+    fn sync_filter(&mut self, emitted: &mut dyn FnMut(TimedEvent)) {
+        let enabled = self.inner.ignore_virtual.load(Ordering::Relaxed);
+        if enabled == self.filter.enabled() {
+            return;
+        }
+        let visible: Vec<ControllerInfo> = self
+            .inner
+            .controllers_info
+            .read()
+            .map(|map| map.values().cloned().collect())
+            .unwrap_or_default();
+        let at = Instant::now();
+        for event in self.filter.set_enabled(enabled, visible) {
+            let event = TimedEvent { at, event };
+            self.publish(event.clone());
+            emitted(event);
+        }
+    }
+
+    /// Whether no held back event is due later.
+    fn is_idle(&self) -> bool {
+        self.debounce.is_empty() && self.coalescer.is_empty()
+    }
+
+    /// Keeps the shared controller registry in sync and broadcasts the
+    /// event. Axis motion that is coalesced only reaches raw subscribers
+    /// for now, other events first release the motion held back.
+    fn publish(&mut self, event: TimedEvent) {
+        update_registry(&self.inner, &event.event);
+        if self.coalescer.holds(&event.event) {
+            broadcast(&self.inner, &event, Audience::Raw);
+            self.coalescer.push(event);
+            return;
+        }
+        for held in self.coalescer.drain() {
+            broadcast(&self.inner, &held, Audience::Coalesced);
+        }
+        broadcast(&self.inner, &event, Audience::All);
+    }
+
+    /// Applies a changed coalescing interval and releases axis motion
+    /// whose interval has passed.
+    fn sync_axes(&mut self) {
+        let interval = axis_interval(&self.inner);
+        let held = if interval.is_zero() {
+            self.coalescer.drain()
+        } else {
+            self.coalescer.take_due(Instant::now())
+        };
+        self.coalescer.set_interval(interval);
+        for event in held {
+            broadcast(&self.inner, &event, Audience::Coalesced);
+        }
+    }
 }
 
-fn map_sdl_button(button: SdlButton) -> Option<Button> {
-    Some(match button {
-        SdlButton::A => Button::A,
-        SdlButton::B => Button::B,
-        SdlButton::X => Button::X,
-        SdlButton::Y => Button::Y,
-        SdlButton::Back => Button::Back,
-        SdlButton::Guide => Button::Guide,
-        SdlButton::Start => Button::Start,
-        SdlButton::LeftStick => Button::LeftStick,
-        SdlButton::RightStick => Button::RightStick,
-        SdlButton::LeftShoulder => Button::LeftShoulder,
-        SdlButton::RightShoulder => Button::RightShoulder,
-        SdlButton::DPadUp => Button::DPadUp,
-        SdlButton::DPadDown => Button::DPadDown,
-        SdlButton::DPadLeft => Button::DPadLeft,
-        SdlButton::DPadRight => Button::DPadRight,
-        _ => return None,
-    })
+fn reconnect_grace(inner: &Inner) -> Duration {
+    Duration::from_millis(inner.reconnect_grace_ms.load(Ordering::Relaxed))
 }
 
-fn map_sdl_axis(axis: SdlAxis) -> Option<Axis> {
-    Some(match axis {
-        SdlAxis::LeftX => Axis::LeftX,
-        SdlAxis::LeftY => Axis::LeftY,
-        SdlAxis::RightX => Axis::RightX,
-        SdlAxis::RightY => Axis::RightY,
-        SdlAxis::TriggerLeft => Axis::LeftTrigger,
-        SdlAxis::TriggerRight => Axis::RightTrigger,
-    })
+fn axis_interval(inner: &Inner) -> Duration {
+    Duration::from_millis(inner.axis_interval_ms.load(Ordering::Relaxed))
 }
 
-fn broadcast(inner: &Inner, event: ControllerEvent) {
+/// Keeps the shared controller registry in sync with connection events.
+fn update_registry(inner: &Inner, event: &ControllerEvent) {
+    match event {
+        ControllerEvent::Connected(info) => {
+            if let Ok(mut map) = inner.controllers_info.write() {
+                map.insert(info.id, info.clone());
+            }
+        }
+        ControllerEvent::Disconnected(id) => {
+            if let Ok(mut map) = inner.controllers_info.write() {
+                map.remove(id);
+            }
+        }
+        ControllerEvent::CapabilitiesChanged {
+            id,
+            supports_rumble,
+        } => {
+            if let Ok(mut map) = inner.controllers_info.write() {
+                if let Some(info) = map.get_mut(id) {
+                    info.supports_rumble = *supports_rumble;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Subscribers an event is sent to.
+#[derive(Clone, Copy)]
+enum Audience {
+    All,
+    /// Subscribers that bypass axis coalescing.
+    Raw,
+    /// Subscribers that receive coalesced axis motion.
+    Coalesced,
+}
+
+fn broadcast(inner: &Inner, event: &TimedEvent, audience: Audience) {
     if let Ok(mut subs) = inner.subscribers.lock() {
-        subs.retain(|tx| tx.send(event.clone()).is_ok());
+        subs.retain(|sub| {
+            let listens = match audience {
+                Audience::All => true,
+                Audience::Raw => sub.filter.is_raw(),
+                Audience::Coalesced => !sub.filter.is_raw(),
+            };
+            !listens
+                || !sub.filter.matches(&event.event)
+                || sub.deliver(event.clone(), inner)
+        });
+    }
+}
+
+#[cfg(all(test, feature = "virtual-backend"))]
+mod tests {
+    use crate::{Axis, BroadcastStats, Button, ControllerManager, EventFilter, Overflow};
+
+    use super::*;
+
+    #[test]
+    fn pump_returns_events_synchronously() {
+        let (manager, mut runtime) =
+            ControllerManager::with_pump(BackendKind::Virtual).unwrap();
+        let rx = manager.subscribe();
+        let injector = manager.injector();
+        assert!(runtime.pump().is_empty());
+
+        injector.connect(ControllerInfo {
+            id: 3,
+            name: "Virtual".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        });
+        injector.press(3, Button::A);
+        let timed = runtime.pump();
+        assert!(timed.windows(2).all(|pair| pair[0].at <= pair[1].at));
+        let events: Vec<_> = timed.into_iter().map(|timed| timed.event).collect();
+        assert!(matches!(
+            events[..],
+            [
+                ControllerEvent::Connected(_),
+                ControllerEvent::ButtonPressed { id: 3, .. }
+            ]
+        ));
+        assert_eq!(rx.try_iter().count(), 2);
+        assert!(manager.controller(3).is_some());
+        assert!(manager.set_backend(BackendKind::Sdl2).is_err());
+
+        drop(runtime);
+        assert!(manager.controllers().is_empty());
+    }
+
+    #[test]
+    fn coalesces_axis_motion_except_for_raw_subscribers() {
+        let (manager, mut runtime) =
+            ControllerManager::with_pump(BackendKind::Virtual).unwrap();
+        manager.set_axis_coalescing(Duration::from_secs(60));
+        let injector = manager.injector();
+        injector.connect(ControllerInfo {
+            id: 1,
+            name: "Virtual".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        });
+        runtime.pump();
+        let rx = manager.subscribe();
+        let raw_rx = manager.subscribe_filtered(EventFilter::default().raw());
+        injector.axis(1, Axis::LeftX, 0.2);
+        injector.axis(1, Axis::LeftX, 0.5);
+        assert_eq!(runtime.pump().len(), 2);
+        assert_eq!(raw_rx.try_iter().count(), 2);
+        assert!(rx.try_recv().is_err());
+
+        injector.press(1, Button::A);
+        runtime.pump();
+        let events: Vec<_> = rx.try_iter().map(|timed| timed.event).collect();
+        assert!(matches!(
+            events[..],
+            [
+                ControllerEvent::AxisMotion { value: 0.5, .. },
+                ControllerEvent::ButtonPressed { .. }
+            ]
+        ));
+        assert_eq!(raw_rx.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn full_subscriptions_apply_overflow_policy() {
+        let (manager, mut runtime) =
+            ControllerManager::with_pump(BackendKind::Virtual).unwrap();
+        let injector = manager.injector();
+        injector.connect(ControllerInfo {
+            id: 1,
+            name: "Virtual".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        });
+        runtime.pump();
+        let filter = EventFilter::default;
+        let oldest = manager.subscribe_bounded(filter(), 2, Overflow::DropOldest);
+        let strict = manager.subscribe_bounded(filter(), 2, Overflow::Disconnect);
+        for button in [Button::A, Button::B, Button::X] {
+            injector.press(1, button);
+        }
+        runtime.pump();
+
+        let buttons: Vec<_> = oldest
+            .try_iter()
+            .filter_map(|timed| match timed.event {
+                ControllerEvent::ButtonPressed { button, .. } => Some(button),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(buttons, [Button::B, Button::X]);
+        assert_eq!(strict.try_iter().count(), 2);
+        assert!(strict.recv().is_err());
+        assert_eq!(
+            manager.broadcast_stats(),
+            BroadcastStats {
+                dropped: 1,
+                disconnected: 1
+            }
+        );
+    }
+
+    #[test]
+    fn trigger_buttons_can_be_turned_off() {
+        let (manager, mut runtime) =
+            ControllerManager::with_pump(BackendKind::Virtual).unwrap();
+        let injector = manager.injector();
+        injector.connect(ControllerInfo {
+            id: 1,
+            name: "Virtual".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        });
+        runtime.pump();
+        let pump = |runtime: &mut Runtime| -> Vec<_> {
+            runtime
+                .pump()
+                .into_iter()
+                .map(|timed| timed.event)
+                .collect()
+        };
+
+        // Held while turned off, the release still passes
+        injector.press(1, Button::LeftTrigger);
+        assert_eq!(pump(&mut runtime).len(), 1);
+        manager.set_trigger_buttons(false);
+        injector.release(1, Button::LeftTrigger);
+        assert!(matches!(
+            pump(&mut runtime)[..],
+            [ControllerEvent::ButtonReleased {
+                button: Button::LeftTrigger,
+                ..
+            }]
+        ));
+
+        injector.press(1, Button::RightTrigger);
+        injector.release(1, Button::RightTrigger);
+        injector.press(1, Button::A);
+        assert!(matches!(
+            pump(&mut runtime)[..],
+            [ControllerEvent::ButtonPressed {
+                button: Button::A,
+                ..
+            }]
+        ));
     }
 }
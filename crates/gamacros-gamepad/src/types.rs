@@ -3,6 +3,8 @@ pub type ControllerId = u32;
 
 /// Logical controller buttons supported by this crate.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, gamacros_bit_derive::Bit)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Button {
     A,
     B,
@@ -21,10 +23,96 @@ pub enum Button {
     DPadDown,
     DPadLeft,
     DPadRight,
+    // Virtual buttons held while a stick points in a direction. They are
+    // never reported by controllers, only used in chords.
+    LeftStickUp,
+    LeftStickDown,
+    LeftStickLeft,
+    LeftStickRight,
+    RightStickUp,
+    RightStickDown,
+    RightStickLeft,
+    RightStickRight,
+    // Extra buttons of newer controllers, such as the share or mute button,
+    // back paddles and the touchpad click. Kept last so bits of existing
+    // buttons don't move.
+    Misc,
+    Paddle1,
+    Paddle2,
+    Paddle3,
+    Paddle4,
+    Touchpad,
+    // Raw buttons of joysticks without a game controller mapping, such as
+    // HOTAS or wheels, by index.
+    Joystick0,
+    Joystick1,
+    Joystick2,
+    Joystick3,
+    Joystick4,
+    Joystick5,
+    Joystick6,
+    Joystick7,
+    Joystick8,
+    Joystick9,
+    Joystick10,
+    Joystick11,
+    Joystick12,
+    Joystick13,
+    Joystick14,
+    Joystick15,
+    Joystick16,
+    Joystick17,
+    Joystick18,
+    Joystick19,
+    Joystick20,
+    Joystick21,
+    Joystick22,
+    Joystick23,
+    Joystick24,
+    Joystick25,
+    Joystick26,
+    Joystick27,
+    Joystick28,
+    Joystick29,
+    Joystick30,
+    Joystick31,
+}
+
+impl Button {
+    /// Number of raw joystick buttons that can be bound.
+    pub const JOYSTICK_BUTTONS: usize = 32;
+
+    /// Raw button `index` of a joystick, if it can be bound.
+    pub fn joystick(index: usize) -> Option<Button> {
+        if index >= Self::JOYSTICK_BUTTONS {
+            return None;
+        }
+        Self::from_index(Button::Joystick0.index() + index as u32)
+    }
+
+    /// Index of a raw joystick button.
+    pub fn joystick_index(self) -> Option<usize> {
+        (self as usize)
+            .checked_sub(Button::Joystick0 as usize)
+            .filter(|index| *index < Self::JOYSTICK_BUTTONS)
+    }
+
+    /// Buttons only some controllers have, reported in
+    /// `ControllerInfo::extra_buttons`.
+    pub const EXTRA: [Button; 6] = [
+        Button::Misc,
+        Button::Paddle1,
+        Button::Paddle2,
+        Button::Paddle3,
+        Button::Paddle4,
+        Button::Touchpad,
+    ];
 }
 
 /// Analog axes supported by this crate.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Axis {
     LeftX,
     LeftY,
@@ -34,12 +122,55 @@ pub enum Axis {
     RightTrigger,
 }
 
+impl Axis {
+    /// All axes in index order.
+    pub const ALL: [Axis; 6] = [
+        Axis::LeftX,
+        Axis::LeftY,
+        Axis::RightX,
+        Axis::RightY,
+        Axis::LeftTrigger,
+        Axis::RightTrigger,
+    ];
+
+    /// Position of the axis in `Axis::ALL`.
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
 /// Controller meta information that remains stable across events.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 pub struct ControllerInfo {
     pub id: ControllerId,
     pub name: String,
     pub supports_rumble: bool,
     pub vendor_id: u16,
     pub product_id: u16,
+    /// Serial number, when the device reports one.
+    pub serial: Option<String>,
+    /// Buttons from `Button::EXTRA` the device has, empty when the backend
+    /// can't tell.
+    pub extra_buttons: Vec<Button>,
+}
+
+/// Valve Corporation USB vendor id.
+const VALVE_VENDOR_ID: u16 = 0x28de;
+/// Product id of the Steam Input virtual gamepad.
+const STEAM_VIRTUAL_PRODUCT_ID: u16 = 0x11ff;
+
+impl ControllerInfo {
+    /// Whether the device is a virtual controller created by Steam Input.
+    ///
+    /// Steam may also emulate an Xbox 360 pad, in which case only
+    /// the device name gives it away.
+    pub fn is_steam_virtual(&self) -> bool {
+        if self.vendor_id == VALVE_VENDOR_ID
+            && self.product_id == STEAM_VIRTUAL_PRODUCT_ID
+        {
+            return true;
+        }
+        self.name.to_ascii_lowercase().contains("steam virtual")
+    }
 }
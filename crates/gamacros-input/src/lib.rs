@@ -0,0 +1,57 @@
+//! Keyboard chords as an input source next to controllers, e.g. hyper key
+//! shortcuts bound to the same actions as buttons.
+//!
+//! On macOS keys are read by a `CGEventTap`, which needs the Accessibility
+//! permission. Listened chords are swallowed, other keys pass through, as do
+//! events injected by gamacros itself, recognized by `SYNTHETIC_EVENT_TAG`,
+//! so a keystroke action never triggers a chord again.
+//! Other platforms are not supported yet.
+
+use crossbeam_channel::Receiver;
+use gamacros_control::KeyCombo;
+use thiserror::Error;
+
+#[cfg(target_os = "macos")]
+mod tap;
+
+#[cfg(not(target_os = "macos"))]
+mod unsupported;
+
+#[cfg(not(target_os = "macos"))]
+use unsupported as tap;
+
+#[derive(Error, Debug)]
+pub enum InputError {
+    #[error("failed to create the keyboard event tap")]
+    TapFailed,
+    #[error("keyboard chords are not supported on this platform")]
+    Unsupported,
+}
+
+/// A listened chord was pressed or released.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub combo: KeyCombo,
+    pub pressed: bool,
+}
+
+/// Listens for keyboard chords until dropped.
+pub struct KeyListener {
+    tap: tap::Tap,
+}
+
+impl KeyListener {
+    /// Starts listening on a background thread, with no chords yet.
+    pub fn start() -> Result<(Self, Receiver<KeyEvent>), InputError> {
+        let (tap, events) = tap::Tap::start()?;
+        Ok((Self { tap }, events))
+    }
+
+    /// Replaces the listened chords. A chord is a single key with any
+    /// modifiers, e.g. `hyper+j`; other combos are never matched.
+    ///
+    /// A chord held while it is removed still reports its release.
+    pub fn set_combos(&self, combos: &[KeyCombo]) {
+        self.tap.set_combos(combos);
+    }
+}
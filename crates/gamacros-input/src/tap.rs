@@ -0,0 +1,351 @@
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use gamacros_control::{KeyCombo, Modifier, Modifiers, SYNTHETIC_EVENT_TAG};
+
+use crate::{InputError, KeyEvent};
+
+/// How long the tap thread waits for events before checking for a stop.
+const RUN_INTERVAL_S: f64 = 0.25;
+
+/// A chord resolved to the key code the tap sees.
+struct Trigger {
+    code: u16,
+    modifiers: Modifiers,
+    combo: KeyCombo,
+}
+
+/// State shared with the tap callback.
+struct Shared {
+    triggers: Mutex<Vec<Trigger>>,
+    /// Chords held down, by key code, so their autorepeat and release are
+    /// swallowed too.
+    held: Mutex<Vec<(u16, KeyCombo)>>,
+    events: Sender<KeyEvent>,
+    /// The tap, to enable it again after macOS disabled it.
+    port: AtomicPtr<c_void>,
+    stopped: AtomicBool,
+}
+
+impl Shared {
+    /// Reports a key event of a chord. Returns whether it is swallowed.
+    fn handle(
+        &self,
+        down: bool,
+        code: u16,
+        repeat: bool,
+        modifiers: Modifiers,
+    ) -> bool {
+        let mut held = self.held.lock().unwrap();
+        if let Some(index) = held.iter().position(|(held, _)| *held == code) {
+            if !down {
+                let (_, combo) = held.swap_remove(index);
+                let _ = self.events.send(KeyEvent {
+                    combo,
+                    pressed: false,
+                });
+            }
+            return true;
+        }
+        if !down || repeat {
+            return false;
+        }
+        // Plain modifiers of a chord match either side, right ones only
+        // the right side
+        let left = modifiers.to_left();
+        let triggers = self.triggers.lock().unwrap();
+        let Some(trigger) = triggers.iter().find(|t| {
+            t.code == code && (t.modifiers == modifiers || t.modifiers == left)
+        }) else {
+            return false;
+        };
+        held.push((code, trigger.combo.clone()));
+        let _ = self.events.send(KeyEvent {
+            combo: trigger.combo.clone(),
+            pressed: true,
+        });
+        true
+    }
+}
+
+/// Run loop of the tap thread.
+struct RunLoop(ffi::CFTypeRef);
+
+// SAFETY: the run loop is only stopped from other threads, which
+// `CFRunLoopStop` allows.
+unsafe impl Send for RunLoop {}
+
+/// An active event tap on its own thread with a run loop.
+pub(crate) struct Tap {
+    shared: Arc<Shared>,
+    run_loop: RunLoop,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Tap {
+    pub(crate) fn start() -> Result<(Self, Receiver<KeyEvent>), InputError> {
+        let (events_tx, events_rx) = unbounded();
+        let shared = Arc::new(Shared {
+            triggers: Mutex::new(Vec::new()),
+            held: Mutex::new(Vec::new()),
+            events: events_tx,
+            port: AtomicPtr::new(ptr::null_mut()),
+            stopped: AtomicBool::new(false),
+        });
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread_shared = shared.clone();
+        let thread = std::thread::Builder::new()
+            .name("key-tap".into())
+            .spawn(move || run(&thread_shared, &ready_tx))
+            .map_err(|_| InputError::TapFailed)?;
+        let Ok(Some(run_loop)) = ready_rx.recv() else {
+            let _ = thread.join();
+            return Err(InputError::TapFailed);
+        };
+        let tap = Self {
+            shared,
+            run_loop,
+            thread: Some(thread),
+        };
+        Ok((tap, events_rx))
+    }
+
+    pub(crate) fn set_combos(&self, combos: &[KeyCombo]) {
+        let triggers = combos
+            .iter()
+            .filter_map(|combo| {
+                let [key] = combo.keys.as_slice() else {
+                    return None;
+                };
+                Some(Trigger {
+                    code: key.key_code()?,
+                    modifiers: combo.modifiers,
+                    combo: combo.clone(),
+                })
+            })
+            .collect();
+        *self.shared.triggers.lock().unwrap() = triggers;
+    }
+}
+
+impl Drop for Tap {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Release);
+        // SAFETY: the run loop lives as long as its thread, joined below.
+        unsafe { ffi::CFRunLoopStop(self.run_loop.0) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Creates the tap and runs it until stopped. Sends the run loop, or
+/// `None` when the tap can't be created, e.g. without the permission.
+fn run(shared: &Shared, ready: &mpsc::Sender<Option<RunLoop>>) {
+    let mask = (1 << ffi::K_CG_EVENT_KEY_DOWN) | (1 << ffi::K_CG_EVENT_KEY_UP);
+    // SAFETY: the shared state outlives the tap, which is invalidated and
+    // released before returning.
+    unsafe {
+        let port = ffi::CGEventTapCreate(
+            ffi::K_CG_SESSION_EVENT_TAP,
+            ffi::K_CG_HEAD_INSERT_EVENT_TAP,
+            ffi::K_CG_EVENT_TAP_OPTION_DEFAULT,
+            mask,
+            callback,
+            ptr::from_ref(shared).cast_mut().cast(),
+        );
+        if port.is_null() {
+            let _ = ready.send(None);
+            return;
+        }
+        shared.port.store(port.cast_mut(), Ordering::Release);
+        let source = ffi::CFMachPortCreateRunLoopSource(ptr::null(), port, 0);
+        let run_loop = ffi::CFRunLoopGetCurrent();
+        ffi::CFRunLoopAddSource(run_loop, source, ffi::kCFRunLoopCommonModes);
+        ffi::CGEventTapEnable(port, true);
+        let _ = ready.send(Some(RunLoop(run_loop)));
+        // A stop may arrive before the run loop starts, so check in between
+        while !shared.stopped.load(Ordering::Acquire) {
+            ffi::CFRunLoopRunInMode(ffi::kCFRunLoopDefaultMode, RUN_INTERVAL_S, 0);
+        }
+        ffi::CGEventTapEnable(port, false);
+        ffi::CFMachPortInvalidate(port);
+        ffi::CFRelease(source);
+        ffi::CFRelease(port);
+    }
+}
+
+extern "C" fn callback(
+    _proxy: *mut c_void,
+    kind: u32,
+    event: ffi::CGEventRef,
+    user_info: *mut c_void,
+) -> ffi::CGEventRef {
+    // SAFETY: the user info is the shared state, alive while the tap runs.
+    let shared = unsafe { &*user_info.cast::<Shared>() };
+    if kind == ffi::K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT
+        || kind == ffi::K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT
+    {
+        // macOS disables taps that answer too slowly
+        // SAFETY: the tap is valid until the run loop returns.
+        unsafe { ffi::CGEventTapEnable(shared.port.load(Ordering::Acquire), true) };
+        return event;
+    }
+    // SAFETY: key events carry these fields.
+    let (source, code, repeat, flags) = unsafe {
+        (
+            ffi::CGEventGetIntegerValueField(
+                event,
+                ffi::K_CG_EVENT_SOURCE_USER_DATA,
+            ),
+            ffi::CGEventGetIntegerValueField(
+                event,
+                ffi::K_CG_KEYBOARD_EVENT_KEYCODE,
+            ),
+            ffi::CGEventGetIntegerValueField(
+                event,
+                ffi::K_CG_KEYBOARD_EVENT_AUTOREPEAT,
+            ),
+            ffi::CGEventGetFlags(event),
+        )
+    };
+    // Keystrokes sent by our own actions never trigger chords
+    if source == SYNTHETIC_EVENT_TAG {
+        return event;
+    }
+    let Ok(code) = u16::try_from(code) else {
+        return event;
+    };
+    let down = kind == ffi::K_CG_EVENT_KEY_DOWN;
+    if shared.handle(down, code, repeat != 0, modifiers(flags)) {
+        ptr::null_mut()
+    } else {
+        event
+    }
+}
+
+/// Modifiers held according to the flags of an event, on the side the
+/// device flags report, the left one when they don't. Caps lock, fn and
+/// the numeric pad flag are ignored.
+fn modifiers(flags: u64) -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+    for (mask, right_mask, left, right) in [
+        (
+            ffi::K_CG_EVENT_FLAG_MASK_CONTROL,
+            ffi::NX_DEVICE_RCTL_KEY_MASK,
+            Modifier::Ctrl,
+            Modifier::RCtrl,
+        ),
+        (
+            ffi::K_CG_EVENT_FLAG_MASK_COMMAND,
+            ffi::NX_DEVICE_RCMD_KEY_MASK,
+            Modifier::Meta,
+            Modifier::RMeta,
+        ),
+        (
+            ffi::K_CG_EVENT_FLAG_MASK_SHIFT,
+            ffi::NX_DEVICE_RSHIFT_KEY_MASK,
+            Modifier::Shift,
+            Modifier::RShift,
+        ),
+        (
+            ffi::K_CG_EVENT_FLAG_MASK_ALTERNATE,
+            ffi::NX_DEVICE_RALT_KEY_MASK,
+            Modifier::Alt,
+            Modifier::RAlt,
+        ),
+    ] {
+        if flags & mask == 0 {
+            continue;
+        }
+        modifiers.add(if flags & right_mask != 0 { right } else { left });
+    }
+    modifiers
+}
+
+#[allow(non_upper_case_globals)]
+mod ffi {
+    use std::ffi::c_void;
+
+    pub(super) type CFTypeRef = *const c_void;
+    pub(super) type CGEventRef = *mut c_void;
+
+    pub(super) const K_CG_SESSION_EVENT_TAP: u32 = 1;
+    pub(super) const K_CG_HEAD_INSERT_EVENT_TAP: u32 = 0;
+    pub(super) const K_CG_EVENT_TAP_OPTION_DEFAULT: u32 = 0;
+
+    pub(super) const K_CG_EVENT_KEY_DOWN: u32 = 10;
+    pub(super) const K_CG_EVENT_KEY_UP: u32 = 11;
+    pub(super) const K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+    pub(super) const K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+
+    pub(super) const K_CG_KEYBOARD_EVENT_AUTOREPEAT: u32 = 8;
+    pub(super) const K_CG_KEYBOARD_EVENT_KEYCODE: u32 = 9;
+    pub(super) const K_CG_EVENT_SOURCE_USER_DATA: u32 = 42;
+
+    pub(super) const K_CG_EVENT_FLAG_MASK_SHIFT: u64 = 0x0002_0000;
+    pub(super) const K_CG_EVENT_FLAG_MASK_CONTROL: u64 = 0x0004_0000;
+    pub(super) const K_CG_EVENT_FLAG_MASK_ALTERNATE: u64 = 0x0008_0000;
+    pub(super) const K_CG_EVENT_FLAG_MASK_COMMAND: u64 = 0x0010_0000;
+
+    // Device dependent flags of the right modifier keys, from IOLLEvent.h
+    pub(super) const NX_DEVICE_RCTL_KEY_MASK: u64 = 0x0000_2000;
+    pub(super) const NX_DEVICE_RSHIFT_KEY_MASK: u64 = 0x0000_0004;
+    pub(super) const NX_DEVICE_RCMD_KEY_MASK: u64 = 0x0000_0010;
+    pub(super) const NX_DEVICE_RALT_KEY_MASK: u64 = 0x0000_0040;
+
+    pub(super) type CGEventTapCallBack = extern "C" fn(
+        proxy: *mut c_void,
+        kind: u32,
+        event: CGEventRef,
+        user_info: *mut c_void,
+    ) -> CGEventRef;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub(super) fn CGEventTapCreate(
+            tap: u32,
+            place: u32,
+            options: u32,
+            events_of_interest: u64,
+            callback: CGEventTapCallBack,
+            user_info: *mut c_void,
+        ) -> CFTypeRef;
+        pub(super) fn CGEventTapEnable(tap: CFTypeRef, enable: bool);
+        pub(super) fn CGEventGetIntegerValueField(
+            event: CGEventRef,
+            field: u32,
+        ) -> i64;
+        pub(super) fn CGEventGetFlags(event: CGEventRef) -> u64;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        pub(super) static kCFRunLoopCommonModes: CFTypeRef;
+        pub(super) static kCFRunLoopDefaultMode: CFTypeRef;
+
+        pub(super) fn CFRelease(cf: CFTypeRef);
+        pub(super) fn CFMachPortCreateRunLoopSource(
+            allocator: CFTypeRef,
+            port: CFTypeRef,
+            order: isize,
+        ) -> CFTypeRef;
+        pub(super) fn CFMachPortInvalidate(port: CFTypeRef);
+        pub(super) fn CFRunLoopGetCurrent() -> CFTypeRef;
+        pub(super) fn CFRunLoopAddSource(
+            run_loop: CFTypeRef,
+            source: CFTypeRef,
+            mode: CFTypeRef,
+        );
+        pub(super) fn CFRunLoopRunInMode(
+            mode: CFTypeRef,
+            seconds: f64,
+            return_after_source_handled: u8,
+        ) -> i32;
+        pub(super) fn CFRunLoopStop(run_loop: CFTypeRef);
+    }
+}
@@ -0,0 +1,17 @@
+use crossbeam_channel::Receiver;
+use gamacros_control::KeyCombo;
+
+use crate::{InputError, KeyEvent};
+
+/// Keyboard chords are only read on macOS, so no tap ever exists.
+pub(crate) enum Tap {}
+
+impl Tap {
+    pub(crate) fn start() -> Result<(Self, Receiver<KeyEvent>), InputError> {
+        Err(InputError::Unsupported)
+    }
+
+    pub(crate) fn set_combos(&self, _combos: &[KeyCombo]) {
+        match *self {}
+    }
+}
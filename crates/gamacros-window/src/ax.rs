@@ -0,0 +1,206 @@
+//! Focused window arrangement via the macOS Accessibility API.
+
+use std::ffi::c_void;
+use std::ptr;
+
+use gamacros_cf::{CFStringRef, CFTypeRef, CfObject};
+
+use crate::geometry::{target_frame, Rect};
+use crate::{WindowAction, WindowError};
+
+type AXUIElementRef = CFTypeRef;
+type AXError = i32;
+
+const AX_ERROR_SUCCESS: AXError = 0;
+const AX_VALUE_CG_POINT_TYPE: u32 = 1;
+const AX_VALUE_CG_SIZE_TYPE: u32 = 2;
+/// Upper bound of displays a window can be moved between.
+const MAX_DISPLAYS: usize = 16;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CGSize {
+    width: f64,
+    height: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct CGRect {
+    origin: CGPoint,
+    size: CGSize,
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+    fn AXUIElementCopyAttributeValue(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        value: *mut CFTypeRef,
+    ) -> AXError;
+    fn AXUIElementSetAttributeValue(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        value: CFTypeRef,
+    ) -> AXError;
+    fn AXValueCreate(kind: u32, value: *const c_void) -> CFTypeRef;
+    fn AXValueGetValue(value: CFTypeRef, kind: u32, out: *mut c_void) -> u8;
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGGetActiveDisplayList(
+        max_displays: u32,
+        displays: *mut u32,
+        count: *mut u32,
+    ) -> i32;
+    fn CGDisplayBounds(display: u32) -> CGRect;
+}
+
+/// Accessibility reads and writes of an element or of an attribute value.
+trait AxObject {
+    /// Copies an accessibility attribute of this element.
+    fn attribute(&self, name: &str) -> Result<CfObject, WindowError>;
+
+    /// Sets an accessibility attribute of this element.
+    fn set_attribute(&self, name: &str, value: &CfObject)
+        -> Result<(), WindowError>;
+
+    /// Reads an `AXValue` holding a `T` of the given AX value type.
+    fn ax_value<T: Default>(&self, kind: u32) -> Option<T>;
+}
+
+impl AxObject for CfObject {
+    fn attribute(&self, name: &str) -> Result<CfObject, WindowError> {
+        let name = CfObject::string(name).ok_or(WindowError::Accessibility(-1))?;
+        let mut value: CFTypeRef = ptr::null();
+        // SAFETY: both objects are alive, the copied value is owned by us.
+        unsafe {
+            let error = AXUIElementCopyAttributeValue(
+                self.as_ptr(),
+                name.as_ptr(),
+                &mut value,
+            );
+            if error != AX_ERROR_SUCCESS {
+                return Err(WindowError::Accessibility(error));
+            }
+            CfObject::new(value).ok_or(WindowError::Accessibility(-1))
+        }
+    }
+
+    fn set_attribute(
+        &self,
+        name: &str,
+        value: &CfObject,
+    ) -> Result<(), WindowError> {
+        let name = CfObject::string(name).ok_or(WindowError::Accessibility(-1))?;
+        // SAFETY: all objects are alive for the duration of the call.
+        let error = unsafe {
+            AXUIElementSetAttributeValue(
+                self.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr(),
+            )
+        };
+        if error != AX_ERROR_SUCCESS {
+            return Err(WindowError::Accessibility(error));
+        }
+        Ok(())
+    }
+
+    fn ax_value<T: Default>(&self, kind: u32) -> Option<T> {
+        let mut value = T::default();
+        // SAFETY: kind matches the layout of T, the object is alive.
+        let ok = unsafe {
+            AXValueGetValue(self.as_ptr(), kind, (&mut value as *mut T).cast())
+        };
+        (ok != 0).then_some(value)
+    }
+}
+
+/// Wraps a `T` into an `AXValue` of the given AX value type.
+fn ax_value_of<T>(kind: u32, value: &T) -> Option<CfObject> {
+    // SAFETY: kind matches the layout of T, the value is copied into a new
+    // owned object.
+    unsafe { CfObject::new(AXValueCreate(kind, (value as *const T).cast())) }
+}
+
+pub(crate) fn perform(action: WindowAction) -> Result<(), WindowError> {
+    // SAFETY: returns a new owned element or null.
+    let system = unsafe { CfObject::new(AXUIElementCreateSystemWide()) }
+        .ok_or(WindowError::NoFocusedWindow)?;
+    let window = system
+        .attribute("AXFocusedApplication")
+        .and_then(|application| application.attribute("AXFocusedWindow"))
+        .map_err(|_| WindowError::NoFocusedWindow)?;
+
+    let origin: CGPoint = window
+        .attribute("AXPosition")?
+        .ax_value(AX_VALUE_CG_POINT_TYPE)
+        .ok_or(WindowError::NoFocusedWindow)?;
+    let size: CGSize = window
+        .attribute("AXSize")?
+        .ax_value(AX_VALUE_CG_SIZE_TYPE)
+        .ok_or(WindowError::NoFocusedWindow)?;
+    let current = Rect::new(origin.x, origin.y, size.width, size.height);
+    let Some(frame) = target_frame(action, current, &display_bounds()) else {
+        return Ok(());
+    };
+
+    let origin = CGPoint {
+        x: frame.x,
+        y: frame.y,
+    };
+    let size = CGSize {
+        width: frame.width,
+        height: frame.height,
+    };
+    let position = ax_value_of(AX_VALUE_CG_POINT_TYPE, &origin)
+        .ok_or(WindowError::Accessibility(-1))?;
+    let size = ax_value_of(AX_VALUE_CG_SIZE_TYPE, &size)
+        .ok_or(WindowError::Accessibility(-1))?;
+    // Moving first lets a window that grows onto another display keep the
+    // new size, the system clamps the size to the current display.
+    window.set_attribute("AXPosition", &position)?;
+    window.set_attribute("AXSize", &size)?;
+    window.set_attribute("AXPosition", &position)
+}
+
+/// Bounds of the active displays in global coordinates. The system keeps
+/// windows below the menu bar and above the Dock on its own.
+fn display_bounds() -> Vec<Rect> {
+    let mut displays = [0u32; MAX_DISPLAYS];
+    let mut count = 0u32;
+    // SAFETY: the buffer holds MAX_DISPLAYS ids, count is a valid out pointer.
+    let error = unsafe {
+        CGGetActiveDisplayList(
+            MAX_DISPLAYS as u32,
+            displays.as_mut_ptr(),
+            &mut count,
+        )
+    };
+    if error != 0 {
+        return Vec::new();
+    }
+    displays[..count as usize]
+        .iter()
+        .map(|display| {
+            // SAFETY: the id comes from the active display list.
+            let bounds = unsafe { CGDisplayBounds(*display) };
+            Rect::new(
+                bounds.origin.x,
+                bounds.origin.y,
+                bounds.size.width,
+                bounds.size.height,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,132 @@
+use crate::WindowAction;
+
+/// A rectangle in global screen coordinates, origin at the top left.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        x >= self.x
+            && x < self.x + self.width
+            && y >= self.y
+            && y < self.y + self.height
+    }
+}
+
+/// Index of the display showing the center of `window`, the first display
+/// when the window is off screen.
+fn display_of(window: Rect, displays: &[Rect]) -> usize {
+    let (cx, cy) = (
+        window.x + window.width / 2.0,
+        window.y + window.height / 2.0,
+    );
+    displays
+        .iter()
+        .position(|display| display.contains_point(cx, cy))
+        .unwrap_or(0)
+}
+
+/// Frame of `window` after applying `action`, `None` without displays.
+pub fn target_frame(
+    action: WindowAction,
+    window: Rect,
+    displays: &[Rect],
+) -> Option<Rect> {
+    let index = display_of(window, displays);
+    let display = *displays.get(index)?;
+    let half = display.width / 2.0;
+    let frame = match action {
+        WindowAction::LeftHalf => {
+            Rect::new(display.x, display.y, half, display.height)
+        }
+        WindowAction::RightHalf => {
+            Rect::new(display.x + half, display.y, half, display.height)
+        }
+        WindowAction::Maximize => display,
+        WindowAction::NextDisplay => {
+            let next = displays[(index + 1) % displays.len()];
+            let scale_x = next.width / display.width;
+            let scale_y = next.height / display.height;
+            let width = (window.width * scale_x).min(next.width);
+            let height = (window.height * scale_y).min(next.height);
+            Rect::new(
+                (next.x + (window.x - display.x) * scale_x)
+                    .clamp(next.x, next.x + next.width - width),
+                (next.y + (window.y - display.y) * scale_y)
+                    .clamp(next.y, next.y + next.height - height),
+                width,
+                height,
+            )
+        }
+    };
+    Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: Rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 1600.0,
+        height: 1000.0,
+    };
+    const SIDE: Rect = Rect {
+        x: 1600.0,
+        y: 0.0,
+        width: 800.0,
+        height: 500.0,
+    };
+
+    #[test]
+    fn snaps_to_halves_of_the_window_display() {
+        let window = Rect::new(1800.0, 100.0, 300.0, 200.0);
+        assert_eq!(
+            target_frame(WindowAction::LeftHalf, window, &[MAIN, SIDE]),
+            Some(Rect::new(1600.0, 0.0, 400.0, 500.0))
+        );
+        assert_eq!(
+            target_frame(WindowAction::RightHalf, window, &[MAIN, SIDE]),
+            Some(Rect::new(2000.0, 0.0, 400.0, 500.0))
+        );
+        assert_eq!(
+            target_frame(WindowAction::Maximize, window, &[MAIN, SIDE]),
+            Some(SIDE)
+        );
+    }
+
+    #[test]
+    fn moves_to_next_display_keeping_proportions() {
+        let window = Rect::new(400.0, 200.0, 800.0, 600.0);
+        assert_eq!(
+            target_frame(WindowAction::NextDisplay, window, &[MAIN, SIDE]),
+            Some(Rect::new(1800.0, 100.0, 400.0, 300.0))
+        );
+        let back = Rect::new(1800.0, 100.0, 400.0, 300.0);
+        assert_eq!(
+            target_frame(WindowAction::NextDisplay, back, &[MAIN, SIDE]),
+            Some(window)
+        );
+    }
+
+    #[test]
+    fn needs_a_display() {
+        let window = Rect::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(target_frame(WindowAction::Maximize, window, &[]), None);
+    }
+}
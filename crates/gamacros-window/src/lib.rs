@@ -0,0 +1,100 @@
+//! Window management for the focused window: snapping to display halves,
+//! maximizing and moving between displays.
+//!
+//! On macOS windows are arranged through the Accessibility API, which needs
+//! the same permission as sending key events.
+
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+mod geometry;
+
+#[cfg(target_os = "macos")]
+mod ax;
+
+pub use geometry::{target_frame, Rect};
+
+#[derive(Error, Debug)]
+pub enum WindowError {
+    #[error("no focused window")]
+    NoFocusedWindow,
+    #[error("accessibility error {0}")]
+    Accessibility(i32),
+    #[error("window management is not supported on this platform")]
+    Unsupported,
+}
+
+/// An arrangement applied to the focused window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WindowAction {
+    LeftHalf,
+    RightHalf,
+    Maximize,
+    /// Move to the next display, keeping the relative position and size.
+    NextDisplay,
+}
+
+impl WindowAction {
+    pub const ALL: [WindowAction; 4] = [
+        WindowAction::LeftHalf,
+        WindowAction::RightHalf,
+        WindowAction::Maximize,
+        WindowAction::NextDisplay,
+    ];
+
+    /// Name of the action as used in profiles.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowAction::LeftHalf => "left_half",
+            WindowAction::RightHalf => "right_half",
+            WindowAction::Maximize => "maximize",
+            WindowAction::NextDisplay => "next_display",
+        }
+    }
+}
+
+impl fmt::Display for WindowAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WindowAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        WindowAction::ALL
+            .into_iter()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| format!("Invalid window action: {s}"))
+    }
+}
+
+/// Applies `action` to the focused window of the frontmost application.
+#[cfg(target_os = "macos")]
+pub fn perform(action: WindowAction) -> Result<(), WindowError> {
+    ax::perform(action)
+}
+
+/// Applies `action` to the focused window of the frontmost application.
+#[cfg(not(target_os = "macos"))]
+pub fn perform(_action: WindowAction) -> Result<(), WindowError> {
+    Err(WindowError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names() {
+        for action in WindowAction::ALL {
+            assert_eq!(action.name().parse::<WindowAction>(), Ok(action));
+        }
+        assert!("top_half".parse::<WindowAction>().is_err());
+    }
+}
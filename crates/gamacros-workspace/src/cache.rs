@@ -0,0 +1,130 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::profile::{Profile, ProfileError};
+use crate::profile_parse::{load_profile, overlay_paths};
+use crate::workspace::app_cache_dir;
+use crate::PROFILE_SCHEMA;
+
+/// Bumped when the cached format changes in a way the schema doesn't show.
+const CACHE_VERSION: u32 = 1;
+
+/// Load a profile like [`load_profile`], but reuse the compiled profile
+/// cached while none of its files changed, see [`cache_path`].
+///
+/// The cache is best effort: when it is missing, stale or unreadable the
+/// profile is parsed and the cache written again.
+pub fn load_profile_cached(path: &Path) -> Result<Profile, ProfileError> {
+    load_with_cache(path, cache_path(path).as_deref())
+}
+
+/// Cache of the profile at `path` in the app cache directory, e.g.
+/// `~/.cache/gamacros/profile-<hash>.cache`. It is named after the profile
+/// path, so each workspace has its own. `None` when `HOME` is not set.
+pub fn cache_path(path: &Path) -> Option<PathBuf> {
+    app_cache_dir().ok().map(|dir| cache_file(&dir, path))
+}
+
+fn cache_file(dir: &Path, path: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_owned())
+        .hash(&mut hasher);
+    dir.join(format!("profile-{:016x}.cache", hasher.finish()))
+}
+
+fn load_with_cache(
+    path: &Path,
+    cache: Option<&Path>,
+) -> Result<Profile, ProfileError> {
+    let (Some(cache), Some(hash)) = (cache, source_hash(path)) else {
+        return load_profile(path);
+    };
+    if let Some(profile) = read_cache(cache, hash) {
+        return Ok(profile);
+    }
+    let profile = load_profile(path)?;
+    if let Ok(bytes) = bitcode::serialize(&(hash, &profile)) {
+        if let Some(dir) = cache.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let _ = std::fs::write(cache, bytes);
+    }
+    Ok(profile)
+}
+
+fn read_cache(cache: &Path, hash: u64) -> Option<Profile> {
+    let bytes = std::fs::read(cache).ok()?;
+    let (cached, profile) = bitcode::deserialize::<(u64, Profile)>(&bytes).ok()?;
+    (cached == hash).then_some(profile)
+}
+
+/// Hash of everything a parsed profile depends on: the profile, its
+/// fragments and host override, the home directory paths expand to, the
+/// profile format and the build reading it. `None` when a file can't be
+/// read, so parsing reports it.
+fn source_hash(path: &Path) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    CACHE_VERSION.hash(&mut hasher);
+    PROFILE_SCHEMA.hash(&mut hasher);
+    build_id().hash(&mut hasher);
+    std::env::var_os("HOME").hash(&mut hasher);
+    let overlays = overlay_paths(path).ok()?;
+    for source in std::iter::once(path).chain(overlays.iter().map(PathBuf::as_path))
+    {
+        source.hash(&mut hasher);
+        std::fs::read(source).ok()?.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+/// Identifies the running build. Library crates stay at version 0.0.0, so
+/// the executable's size and modification time stand in for the daemon
+/// version: an upgraded daemon never decodes a cache of another build.
+fn build_id() -> Option<(u64, std::time::SystemTime)> {
+    let metadata = std::fs::metadata(std::env::current_exe().ok()?).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_cache_until_sources_change() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-cache-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("profiles.d")).unwrap();
+        let path = dir.join("gc_profile.yaml");
+        std::fs::write(
+            &path,
+            "version: 1\nrules:\n  com.apple.Safari:\n    buttons:\n      \
+             a: { keystroke: enter }\n",
+        )
+        .unwrap();
+
+        let cache = cache_file(&dir.join("cache"), &path);
+        let profile = load_with_cache(&path, Some(&cache)).unwrap();
+        let cached = read_cache(&cache, source_hash(&path).unwrap());
+        let other = cache_file(&dir.join("cache"), &dir.join("other.yaml"));
+        assert_ne!(cache, other);
+        assert_eq!(cached.unwrap().rules.len(), 1);
+
+        // A new fragment invalidates the cache
+        std::fs::write(
+            dir.join("profiles.d").join("mail.yaml"),
+            "rules:\n  com.apple.mail:\n    buttons:\n      b: { keystroke: esc }\n",
+        )
+        .unwrap();
+        let merged = load_with_cache(&path, Some(&cache)).unwrap();
+
+        // A corrupt cache is parsed over
+        std::fs::write(&cache, b"garbage").unwrap();
+        let reparsed = load_with_cache(&path, Some(&cache)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(profile.rules.len(), 1);
+        assert_eq!(merged.rules.len(), 2);
+        assert_eq!(reparsed.rules.len(), 2);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+use gamacros_gamepad::{Axis, AxisCalibration};
+
+use crate::{v1, CalibrationMap};
+
+/// Top-level profile key holding calibration.
+const SECTION_KEY: &str = "calibration:";
+
+/// Renders calibration as a top-level profile section.
+/// Devices are sorted and identity axes are omitted to keep diffs small.
+pub(crate) fn render_section(calibration: &CalibrationMap) -> String {
+    let mut devices: Vec<_> = calibration.iter().collect();
+    devices.sort_by_key(|(device, _)| **device);
+
+    let mut out = String::from(SECTION_KEY);
+    out.push('\n');
+    for ((vid, pid), device) in devices {
+        let _ = writeln!(out, "  - vid: {vid:#06x}");
+        let _ = writeln!(out, "    pid: {pid:#06x}");
+        let axes: Vec<_> = Axis::ALL
+            .into_iter()
+            .map(|axis| (axis, device.get(axis)))
+            .filter(|(_, axis)| *axis != AxisCalibration::default())
+            .collect();
+        if axes.is_empty() {
+            continue;
+        }
+        out.push_str("    axes:\n");
+        for (axis, AxisCalibration { offset, scale }) in axes {
+            let name = v1::axis_name(axis);
+            let _ = writeln!(
+                out,
+                "      {name}: {{ offset: {offset:.4}, scale: {scale:.4} }}"
+            );
+        }
+    }
+    out
+}
+
+/// Replaces the calibration section of a profile, or appends it if missing.
+/// The rest of the document, including comments, is kept intact.
+pub(crate) fn splice_section(profile: &str, section: &str) -> String {
+    let lines: Vec<&str> = profile.lines().collect();
+    let Some(start) = lines.iter().position(|line| line.starts_with(SECTION_KEY))
+    else {
+        let mut out = profile.trim_end().to_string();
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        out.push_str(section);
+        return out;
+    };
+    // The section ends at the next top-level key.
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| {
+            line.chars()
+                .next()
+                .is_some_and(|c| !c.is_whitespace() && c != '-')
+        })
+        .map_or(lines.len(), |offset| start + 1 + offset);
+
+    let mut out = String::new();
+    for line in &lines[..start] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(section);
+    for line in &lines[end..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ahash::AHashMap;
+    use gamacros_gamepad::Calibration;
+
+    use crate::parse_profile;
+
+    fn calibration() -> CalibrationMap {
+        let mut device = Calibration::default();
+        device.set(
+            Axis::LeftX,
+            AxisCalibration {
+                offset: 0.0125,
+                scale: 1.05,
+            },
+        );
+        let mut map = AHashMap::new();
+        map.insert((0x054c, 0x0ce6), device);
+        map
+    }
+
+    #[test]
+    fn rendered_section_round_trips() {
+        let profile = format!("version: 1\n{}", render_section(&calibration()));
+        let parsed = parse_profile(&profile).unwrap();
+        let device = parsed.calibration[&(0x054c, 0x0ce6)];
+        assert_eq!(device.get(Axis::LeftX).offset, 0.0125);
+        assert_eq!(device.get(Axis::LeftX).scale, 1.05);
+        assert_eq!(device.get(Axis::RightY), AxisCalibration::default());
+    }
+
+    #[test]
+    fn splice_appends_missing_section() {
+        let out = splice_section("version: 1\n# keep me\n", "calibration:\n  - x\n");
+        assert_eq!(out, "version: 1\n# keep me\n\ncalibration:\n  - x\n");
+    }
+
+    #[test]
+    fn splice_replaces_existing_section() {
+        let profile =
+            "version: 1\ncalibration:\n  - old\n- older\nshell: /bin/zsh\n";
+        let out = splice_section(profile, "calibration:\n  - new\n");
+        assert_eq!(out, "version: 1\ncalibration:\n  - new\nshell: /bin/zsh\n");
+    }
+}
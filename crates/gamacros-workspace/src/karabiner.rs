@@ -0,0 +1,474 @@
+//! Converts Karabiner-Elements complex modifications into profile rules.
+//!
+//! Karabiner reports game pad buttons as pointing buttons `button1`,
+//! `button2`, …, so only manipulators triggered by those are converted.
+//! Buttons follow the W3C standard gamepad layout: `button1` is `a`,
+//! `button5` is `lb`, `button9` is `back` and so on.
+
+use std::fmt::Write;
+
+use gamacros_control::KeyCombo;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+use crate::v1::COMMON_BUNDLE_ID;
+
+/// Profile button names of `button1`, `button2`, … in order.
+const BUTTONS: [&str; 17] = [
+    "a",
+    "b",
+    "x",
+    "y",
+    "lb",
+    "rb",
+    "lt",
+    "rt",
+    "back",
+    "start",
+    "ls",
+    "rs",
+    "dpad_up",
+    "dpad_down",
+    "dpad_left",
+    "dpad_right",
+    "guide",
+];
+
+#[derive(Error, Debug)]
+pub enum KarabinerError {
+    #[error("invalid Karabiner JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("failed to write rules: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+}
+
+/// Rules converted from Karabiner-Elements.
+#[derive(Debug)]
+pub struct KarabinerImport {
+    /// Converted rules in the format of a `profiles.d` fragment.
+    pub rules: String,
+    /// Number of converted manipulators.
+    pub converted: usize,
+    /// Why manipulators were skipped, one entry each.
+    pub skipped: Vec<String>,
+}
+
+impl KarabinerImport {
+    /// The rules with a comment listing skipped manipulators, ready to
+    /// save into `profiles.d`.
+    pub fn to_fragment(&self) -> String {
+        let mut out = format!(
+            "# Imported from Karabiner-Elements: {} converted, {} skipped\n",
+            self.converted,
+            self.skipped.len()
+        );
+        for reason in &self.skipped {
+            let _ = writeln!(out, "# skipped: {reason}");
+        }
+        out.push_str(&self.rules);
+        out
+    }
+}
+
+/// Converts the complex modifications in `json`, either a `karabiner.json`
+/// configuration or a single complex modification file.
+pub fn import_karabiner(json: &str) -> Result<KarabinerImport, KarabinerError> {
+    let file: KarabinerFile = serde_json::from_str(json)?;
+    let rules = file.rules.iter().chain(
+        file.profiles
+            .iter()
+            .flat_map(|profile| &profile.complex_modifications.rules),
+    );
+
+    let mut selectors = Mapping::new();
+    let mut converted = 0;
+    let mut skipped = Vec::new();
+    for rule in rules {
+        for manipulator in &rule.manipulators {
+            let converted_rule =
+                convert(manipulator).and_then(|(selector, chord, action)| {
+                    let buttons = selectors
+                        .entry(Value::from(selector.clone()))
+                        .or_insert_with(|| {
+                            Value::from(Mapping::from_iter([(
+                                Value::from("buttons"),
+                                Value::from(Mapping::new()),
+                            )]))
+                        })
+                        .get_mut("buttons")
+                        .and_then(Value::as_mapping_mut)
+                        .expect("buttons are a mapping");
+                    if buttons.contains_key(chord.as_str()) {
+                        return Err(format!(
+                            "{chord} is already bound in {selector}"
+                        ));
+                    }
+                    buttons.insert(Value::from(chord), Value::from(action));
+                    Ok(())
+                });
+            match converted_rule {
+                Ok(()) => converted += 1,
+                Err(reason) => {
+                    skipped.push(format!("{}: {reason}", rule.description))
+                }
+            }
+        }
+    }
+
+    let rules = if selectors.is_empty() {
+        String::new()
+    } else {
+        serde_yaml::to_string(&Mapping::from_iter([(
+            Value::from("rules"),
+            Value::from(selectors),
+        )]))?
+    };
+    Ok(KarabinerImport {
+        rules,
+        converted,
+        skipped,
+    })
+}
+
+/// Selector, chord and action of a manipulator, or why it can't be converted.
+fn convert(manipulator: &Manipulator) -> Result<(String, String, Mapping), String> {
+    if manipulator.kind != "basic" {
+        return Err(format!(
+            "{} manipulators are not supported",
+            manipulator.kind
+        ));
+    }
+    let chord = convert_from(&manipulator.from)?;
+    let selector = convert_conditions(&manipulator.conditions)?;
+    let action = convert_to(&manipulator.to)?;
+    Ok((selector, chord, action))
+}
+
+/// Chord of the game pad buttons that trigger a manipulator.
+fn convert_from(from: &FromEvent) -> Result<String, String> {
+    if from.key_code.is_some() || from.consumer_key_code.is_some() {
+        return Err("not triggered by a game pad button".to_string());
+    }
+    let mandatory = from
+        .modifiers
+        .as_ref()
+        .is_some_and(|modifiers| !modifiers.mandatory.is_empty());
+    if mandatory {
+        return Err("triggers with modifiers are not supported".to_string());
+    }
+    let events: Vec<&FromEvent> = if from.simultaneous.is_empty() {
+        vec![from]
+    } else {
+        from.simultaneous.iter().collect()
+    };
+    let buttons = events
+        .iter()
+        .map(|event| {
+            let name = event
+                .pointing_button
+                .as_deref()
+                .ok_or("not triggered by a game pad button")?;
+            name.strip_prefix("button")
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| BUTTONS.get(index.checked_sub(1)?))
+                .copied()
+                .ok_or(format!("unknown game pad button {name}"))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(buttons.join("+"))
+}
+
+/// Selector of the apps the manipulator is limited to, `common` for all.
+fn convert_conditions(conditions: &[Condition]) -> Result<String, String> {
+    let mut apps = Vec::new();
+    for condition in conditions {
+        if condition.kind != "frontmost_application_if" {
+            return Err(format!("{} conditions are not supported", condition.kind));
+        }
+        for pattern in &condition.bundle_identifiers {
+            apps.push(convert_bundle_pattern(pattern)?);
+        }
+    }
+    if apps.is_empty() {
+        return Ok(COMMON_BUNDLE_ID.to_string());
+    }
+    Ok(apps.join(" | "))
+}
+
+/// Turns a bundle id regex like `^com\.apple\.Safari$` into an app pattern.
+fn convert_bundle_pattern(pattern: &str) -> Result<String, String> {
+    let unsupported =
+        || format!("bundle identifier regex {pattern} is not supported");
+    let mut rest = pattern.strip_prefix('^').unwrap_or(pattern);
+    let anchored = rest.ends_with('$') && !rest.ends_with("\\$");
+    if anchored {
+        rest = &rest[..rest.len() - 1];
+    }
+    let mut out = String::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if !escaped.is_alphanumeric() => out.push(escaped),
+                _ => return Err(unsupported()),
+            },
+            '.' if chars.peek() == Some(&'*') => {
+                chars.next();
+                out.push('*');
+            }
+            '.' => out.push('?'),
+            '(' | ')' | '[' | ']' | '{' | '}' | '|' | '+' | '*' | '?' | '^'
+            | '$' => return Err(unsupported()),
+            c => out.push(c),
+        }
+    }
+    // Unanchored regexes match anywhere in the id
+    if !anchored && !out.ends_with('*') {
+        out.push('*');
+    }
+    if !pattern.starts_with('^') && !out.starts_with('*') {
+        out.insert(0, '*');
+    }
+    Ok(out)
+}
+
+/// Action of the events a manipulator sends.
+fn convert_to(to: &[ToEvent]) -> Result<Mapping, String> {
+    let action =
+        |name: &str, value: Value| Mapping::from_iter([(Value::from(name), value)]);
+    match to {
+        [] => Err("no events to send".to_string()),
+        [ToEvent {
+            shell_command: Some(command),
+            key_code: None,
+            ..
+        }] => Ok(action("shell", Value::from(command.as_str()))),
+        [event] => Ok(action("keystroke", Value::from(convert_keystroke(event)?))),
+        events => {
+            let keystrokes = events
+                .iter()
+                .map(|event| convert_keystroke(event).map(Value::from))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(action("macros", Value::from(keystrokes)))
+        }
+    }
+}
+
+/// Profile keystroke of a key event, e.g. `cmd+shift+a`.
+fn convert_keystroke(event: &ToEvent) -> Result<String, String> {
+    let Some(key_code) = event.key_code.as_deref() else {
+        return Err("only key events and shell commands are supported".to_string());
+    };
+    let mut parts = Vec::new();
+    for modifier in event.modifiers.iter().flat_map(Modifiers::names) {
+        let name = match modifier
+            .trim_start_matches("left_")
+            .trim_start_matches("right_")
+        {
+            "command" => "cmd",
+            "shift" => "shift",
+            "option" => "option",
+            "control" => "ctrl",
+            _ => return Err(format!("modifier {modifier} is not supported")),
+        };
+        if !parts.contains(&name) {
+            parts.push(name);
+        }
+    }
+    let key = convert_key_code(key_code);
+    parts.push(key);
+    let keystroke = parts.join("+");
+    keystroke
+        .parse::<KeyCombo>()
+        .map_err(|_| format!("key {key_code} is not supported"))?;
+    Ok(keystroke)
+}
+
+/// Profile key name of a Karabiner key code. Names the two share, like
+/// letters, `tab` or `f5`, pass through.
+fn convert_key_code(key_code: &str) -> &str {
+    match key_code {
+        "return_or_enter" => "enter",
+        "delete_or_backspace" => "backspace",
+        "delete_forward" => "delete",
+        "spacebar" => "space",
+        "hyphen" => "minus",
+        "equal_sign" => "equal",
+        "grave_accent_and_tilde" => "grave",
+        "up_arrow" => "arrow_up",
+        "down_arrow" => "arrow_down",
+        "left_arrow" => "arrow_left",
+        "right_arrow" => "arrow_right",
+        "volume_increment" => "volume_up",
+        "volume_decrement" => "volume_down",
+        "mute" => "volume_mute",
+        "fastforward" => "media_forward",
+        "rewind" => "media_rewind",
+        "display_brightness_increment" => "brightness_up",
+        "display_brightness_decrement" => "brightness_down",
+        "illumination_increment" => "illumination_up",
+        "illumination_decrement" => "illumination_down",
+        "application" => "menu",
+        "japanese_eisuu" => "eisu",
+        "japanese_kana" => "kana",
+        "international3" => "yen",
+        "international1" => "ro",
+        other => other,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KarabinerFile {
+    #[serde(default)]
+    rules: Vec<Rule>,
+    #[serde(default)]
+    profiles: Vec<KarabinerProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KarabinerProfile {
+    #[serde(default)]
+    complex_modifications: ComplexModifications,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ComplexModifications {
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    manipulators: Vec<Manipulator>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manipulator {
+    #[serde(rename = "type")]
+    kind: String,
+    from: FromEvent,
+    #[serde(default)]
+    to: Vec<ToEvent>,
+    #[serde(default)]
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FromEvent {
+    key_code: Option<String>,
+    consumer_key_code: Option<String>,
+    pointing_button: Option<String>,
+    modifiers: Option<FromModifiers>,
+    #[serde(default)]
+    simultaneous: Vec<FromEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FromModifiers {
+    #[serde(default)]
+    mandatory: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToEvent {
+    key_code: Option<String>,
+    modifiers: Option<Modifiers>,
+    shell_command: Option<String>,
+}
+
+/// Modifiers of a `to` event, a single name or a list.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Modifiers {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Modifiers {
+    fn names(&self) -> &[String] {
+        match self {
+            Modifiers::One(name) => std::slice::from_ref(name),
+            Modifiers::Many(names) => names,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Condition {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    bundle_identifiers: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_profile;
+
+    #[test]
+    fn converts_game_pad_manipulators() {
+        let json = r#"{
+            "title": "Game pad",
+            "rules": [{
+                "description": "Safari tabs",
+                "manipulators": [
+                    {
+                        "type": "basic",
+                        "from": { "pointing_button": "button5" },
+                        "to": [{ "key_code": "tab", "modifiers": ["left_control", "left_shift"] }],
+                        "conditions": [{
+                            "type": "frontmost_application_if",
+                            "bundle_identifiers": ["^com\\.apple\\.Safari$"]
+                        }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "simultaneous": [
+                            { "pointing_button": "button1" },
+                            { "pointing_button": "button2" }
+                        ] },
+                        "to": [{ "shell_command": "open -a Notes" }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "caps_lock" },
+                        "to": [{ "key_code": "escape" }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "pointing_button": "button3" },
+                        "to": [{ "key_code": "a" }],
+                        "conditions": [{ "type": "variable_if", "name": "x", "value": 1 }]
+                    }
+                ]
+            }]
+        }"#;
+        let import = import_karabiner(json).unwrap();
+        assert_eq!(import.converted, 2);
+        assert_eq!(import.skipped.len(), 2);
+        assert!(import.skipped[0].contains("not triggered by a game pad button"));
+
+        let profile =
+            parse_profile(&format!("version: 1\n{}", import.to_fragment())).unwrap();
+        let safari = profile.resolve_app_rules("com.apple.Safari").unwrap();
+        assert_eq!(safari.buttons.len(), 2);
+    }
+
+    #[test]
+    fn converts_bundle_patterns() {
+        assert_eq!(
+            convert_bundle_pattern("^com\\.apple\\.Safari$").as_deref(),
+            Ok("com.apple.Safari")
+        );
+        assert_eq!(
+            convert_bundle_pattern("^com\\.jetbrains\\..*").as_deref(),
+            Ok("com.jetbrains.*")
+        );
+        assert!(convert_bundle_pattern("^com\\.(a|b)$").is_err());
+    }
+}
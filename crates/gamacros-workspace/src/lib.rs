@@ -1,24 +1,39 @@
 mod profile;
 mod profile_parse;
+mod cache;
 mod v1;
 mod profile_watcher;
 mod workspace;
+mod calibration;
+mod pattern;
+mod karabiner;
+mod mapping;
 
 use thiserror::Error;
 
 use gamacros_bit_mask::Bitmask;
-use gamacros_gamepad::Button;
+use gamacros_gamepad::{Axis as CtrlAxis, Button};
 
 pub use profile_watcher::{ProfileWatcher, ProfileEvent};
 
-pub use profile_parse::parse_profile;
+pub use profile_parse::{load_profile, parse_profile, FRAGMENTS_DIR_NAME};
+pub use cache::{cache_path, load_profile_cached};
 pub use profile::{
     Profile, ButtonAction, ButtonRule, ControllerSettings, ControllerSettingsMap,
-    StickRules, ArrowsParams, Axis, MouseParams, ScrollParams, StepperParams,
-    StickMode, StickSide, AppRules, RuleMap, ButtonRules, Macros,
+    SerialSettingsMap, StickRules, ArrowsParams, Axis, MouseParams, ScrollParams,
+    SeekParams, StepperParams, StickMode, StickRule, StickSide, StickToggle,
+    HeldStickMode, AppRules, RuleMap, ButtonRules, KeyRules, Macros, CalibrationMap,
+    GlobalRules, PatternRules, TitleRules, ShellLimits, ShellOverflow, OscSettings,
+    HapticFeedback, AxisFilter, DeadzoneShape, DwellClick, OverlaySettings,
+    SpeechSettings, WebSocketSettings, ProfileSet, SetTarget, RuleSource,
+    RepeatCurve, LogLevels, LogModule, LogLevel, WatchdogSettings,
 };
+pub use pattern::AppPattern;
+pub use karabiner::{import_karabiner, KarabinerError, KarabinerImport};
+pub use mapping::MappingEntry;
+pub use v1::PROFILE_SCHEMA;
 // pub use profile::resolve_profile;
-pub use workspace::Workspace;
+pub use workspace::{Workspace, CONFIG_ENV};
 
 /// A macOS application bundle ID.
 pub type BundleId = Box<str>;
@@ -30,10 +45,25 @@ pub type ControllerId = (u16, u16);
 /// A chord of buttons.
 pub type ButtonChord = Bitmask<Button>;
 
+/// Parses a button name as written in profiles (e.g. `a`, `lb`, `dpad_up`).
+pub fn parse_button(name: &str) -> Option<Button> {
+    v1::parse_button_name(name).ok()
+}
+
+/// Name of a button as written in profiles.
+pub fn button_name(button: Button) -> &'static str {
+    v1::button_name(button)
+}
+
+/// Name of an axis as written in profiles.
+pub fn axis_name(axis: CtrlAxis) -> &'static str {
+    v1::axis_name(axis)
+}
+
 #[derive(Debug, Error)]
 pub enum WorkspaceError {
     #[error("profile error: {0}")]
-    ProfileError(#[from] profile::ProfileError),
+    ProfileError(#[source] profile::ProfileError),
     #[error("watcher error: {0}")]
     WatcherError(#[from] profile_watcher::WatcherError),
 
@@ -44,3 +74,12 @@ pub enum WorkspaceError {
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+impl From<profile::ProfileError> for WorkspaceError {
+    fn from(error: profile::ProfileError) -> Self {
+        match error.missing_env_var() {
+            Some(name) => WorkspaceError::EnvVarNotSet(name.to_string()),
+            None => WorkspaceError::ProfileError(error),
+        }
+    }
+}
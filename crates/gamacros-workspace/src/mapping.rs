@@ -0,0 +1,153 @@
+//! Effective mapping of an app, for cheat sheets.
+
+use serde::Serialize;
+
+use crate::{button_name, ButtonAction, ButtonChord, Profile, StickRule, StickSide};
+
+/// Second and held modes of a stick, e.g. `scroll with ls, arrows while lt`.
+fn describe_stick_modes(rule: &StickRule) -> String {
+    let toggle = rule.toggle.iter().map(|toggle| {
+        format!("{} with {}", toggle.mode.name(), button_name(toggle.button))
+    });
+    let held = rule.held.iter().map(|held| {
+        format!("{} while {}", held.mode.name(), chord_name(&held.chord))
+    });
+    toggle.chain(held).collect::<Vec<_>>().join(", ")
+}
+
+/// A bound input in the effective rules of an app.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MappingEntry {
+    /// Chord such as `a+b`, a keyboard chord such as `cmd+ctrl+j`, or
+    /// `left_stick` and `right_stick`.
+    pub input: String,
+    /// Action as named in profiles, e.g. `keystroke`, or the stick mode.
+    pub action: &'static str,
+    /// Argument of the action, e.g. `cmd+s`. For sticks their other modes,
+    /// e.g. `scroll with ls, arrows while lt`, otherwise empty.
+    pub detail: String,
+    /// Action run when the chord is released, as `name: detail`.
+    pub on_release: Option<String>,
+    /// Selector and file the rule comes from, empty for sticks.
+    pub source: String,
+}
+
+impl Profile {
+    /// Resolves the rules of `app` the way the daemon does, with global,
+    /// `common`, group and pattern rules merged, and lists what every bound
+    /// input does. Chords come first, fewer buttons first, then keyboard
+    /// chords and sticks.
+    /// Empty when no rules apply to the app.
+    pub fn mapping(&self, app: &str) -> Vec<MappingEntry> {
+        let Some(rules) = self.resolve_app_rules(app) else {
+            return Vec::new();
+        };
+        let mut chords: Vec<_> = rules
+            .buttons
+            .iter()
+            .map(|(chord, rule)| (chord_name(chord), chord.count(), rule))
+            .collect();
+        chords.sort_by(|(a, a_len, _), (b, b_len, _)| {
+            a_len.cmp(b_len).then_with(|| a.cmp(b))
+        });
+        let mut keys: Vec<_> = rules
+            .keys
+            .iter()
+            .map(|(combo, rule)| (combo.to_string(), 0, rule))
+            .collect();
+        keys.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+        let buttons =
+            chords
+                .into_iter()
+                .chain(keys)
+                .map(|(input, _, rule)| MappingEntry {
+                    input,
+                    action: rule.action.name(),
+                    detail: rule.action.detail(),
+                    on_release: rule.on_release.as_ref().map(describe),
+                    source: rule.source.to_string(),
+                });
+        let sticks = [
+            (StickSide::Left, "left_stick"),
+            (StickSide::Right, "right_stick"),
+        ]
+        .into_iter()
+        .filter_map(|(side, input)| {
+            let rule = rules.sticks.get(&side)?;
+            Some(MappingEntry {
+                input: input.to_string(),
+                action: rule.mode.name(),
+                detail: describe_stick_modes(rule),
+                on_release: None,
+                source: String::new(),
+            })
+        });
+        buttons.chain(sticks).collect()
+    }
+}
+
+/// Buttons of a chord joined with `+`, as written in profiles.
+fn chord_name(chord: &ButtonChord) -> String {
+    chord.iter().map(button_name).collect::<Vec<_>>().join("+")
+}
+
+fn describe(action: &ButtonAction) -> String {
+    format!("{}: {}", action.name(), action.detail())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_profile;
+
+    #[test]
+    fn lists_merged_rules() {
+        let profile = parse_profile(
+            "
+version: 1
+groups:
+  ide: [com.microsoft.VSCode]
+rules:
+  common:
+    buttons:
+      b: { keystroke: cmd+w }
+      a: { keystroke: enter }
+  $ide:
+    buttons:
+      a: { keystroke: cmd+s }
+      lb+rb: { shell: make, on_release: { keystroke: escape } }
+    keys:
+      hyper+j: { keystroke: cmd+s }
+    sticks:
+      left:
+        - { mode: scroll }
+        - { mode: arrows, while_held: lt }
+      right:
+        mode: mouse_move
+        toggle_with: rs
+        toggle_mode: { mode: arrows }
+",
+        )
+        .unwrap();
+
+        let mapping = profile.mapping("com.microsoft.VSCode");
+        let rows: Vec<_> = mapping
+            .iter()
+            .map(|entry| (entry.input.as_str(), entry.action, entry.detail.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("a", "keystroke", "cmd+s"),
+                ("b", "keystroke", "cmd+w"),
+                ("lb+rb", "shell", "make"),
+                ("hyper+j", "keystroke", "cmd+s"),
+                ("left_stick", "scroll", "arrows while lt"),
+                ("right_stick", "mouse_move", "arrows with rs"),
+            ]
+        );
+        assert_eq!(mapping[2].on_release.as_deref(), Some("keystroke: escape"));
+        assert_eq!(mapping[0].source, "$ide");
+        assert_eq!(mapping[1].source, "common");
+        assert!(profile.mapping("com.apple.Safari").is_empty());
+    }
+}
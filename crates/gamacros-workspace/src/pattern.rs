@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+/// A glob pattern matched against app identifiers at runtime,
+/// e.g. `com.jetbrains.*`.
+///
+/// `*` matches any sequence of characters, `?` matches a single character.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppPattern(Box<str>);
+
+impl AppPattern {
+    pub fn new(pattern: &str) -> Self {
+        Self(pattern.into())
+    }
+
+    /// Whether the selector term is a pattern rather than an exact bundle id.
+    pub fn is_pattern(term: &str) -> bool {
+        term.contains(['*', '?'])
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Number of literal characters. More specific patterns take precedence.
+    pub fn specificity(&self) -> usize {
+        self.0.chars().filter(|c| !matches!(c, '*' | '?')).count()
+    }
+
+    pub fn matches(&self, app: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let app: Vec<char> = app.chars().collect();
+
+        let (mut p, mut a) = (0, 0);
+        // Position of the last `*` and the app position it was tried at
+        let mut backtrack: Option<(usize, usize)> = None;
+        while a < app.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, a));
+                    p += 1;
+                }
+                Some(c) if *c == '?' || *c == app[a] => {
+                    p += 1;
+                    a += 1;
+                }
+                _ => {
+                    let Some((star, tried)) = backtrack else {
+                        return false;
+                    };
+                    // Let the last `*` consume one more character
+                    backtrack = Some((star, tried + 1));
+                    p = star + 1;
+                    a = tried + 1;
+                }
+            }
+        }
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        let pattern = AppPattern::new("com.jetbrains.*");
+        assert!(pattern.matches("com.jetbrains.rustrover"));
+        assert!(pattern.matches("com.jetbrains."));
+        assert!(!pattern.matches("com.jetbrains"));
+        assert!(!pattern.matches("org.jetbrains.idea"));
+
+        let pattern = AppPattern::new("com.*.Safari?");
+        assert!(pattern.matches("com.apple.SafariX"));
+        assert!(!pattern.matches("com.apple.Safari"));
+        assert!(AppPattern::new("*").matches(""));
+        assert!(AppPattern::new("*a*b").matches("xaxxab"));
+    }
+
+    #[test]
+    fn detects_patterns() {
+        assert!(AppPattern::is_pattern("com.jetbrains.*"));
+        assert!(!AppPattern::is_pattern("com.apple.Safari"));
+        assert_eq!(AppPattern::new("com.*").specificity(), 4);
+    }
+}
@@ -1,9 +1,21 @@
+use std::fmt;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use core::str;
 use ahash::{AHashMap, AHashSet};
 
-use gamacros_control::KeyCombo;
-use gamacros_gamepad::Button;
+use gamacros_control::{
+    KeyCombo, KeyboardBackendKind, MouseButton, MouseTarget, ScrollUnit,
+    SpaceTarget, SystemAction,
+};
+use gamacros_window::WindowAction;
+use gamacros_gamepad::{
+    Axis as CtrlAxis, BackendKind, Button, Calibration, ControllerMappings,
+    HidDevice,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use thiserror::Error;
 
@@ -15,45 +27,400 @@ pub enum ProfileError {
     UnsupportedVersion(u8),
     #[error("v1 profile error: {0}")]
     V1Profile(#[from] v1::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}: {1}")]
+    InFile(String, Box<ProfileError>),
+    /// Errors of several files, e.g. of fragments checked in parallel.
+    #[error("{}", display_all(.0))]
+    Multiple(Vec<ProfileError>),
 }
 
-use crate::{v1, BundleId, ButtonChord, ControllerId};
+impl ProfileError {
+    /// Environment variable the profile needs that is not set, e.g. `HOME`
+    /// to expand `~`.
+    pub(crate) fn missing_env_var(&self) -> Option<&'static str> {
+        match self {
+            ProfileError::V1Profile(v1::Error::HomeNotSet(_)) => Some("HOME"),
+            ProfileError::InFile(_, error) => error.missing_env_var(),
+            ProfileError::Multiple(errors) => {
+                errors.iter().find_map(ProfileError::missing_env_var)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn display_all(errors: &[ProfileError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+use crate::v1::COMMON_BUNDLE_ID;
+use crate::{v1, AppPattern, BundleId, ButtonChord, ControllerId};
 
 /// A set of rules to handle button presses for an app.
 pub type ButtonRules = AHashMap<ButtonChord, ButtonRule>;
+/// Rules triggered by keyboard chords, e.g. `hyper+j`.
+pub type KeyRules = AHashMap<KeyCombo, ButtonRule>;
 
 /// A set of rules to handle stick movements for an app.
-pub type StickRules = AHashMap<StickSide, StickMode>;
+pub type StickRules = AHashMap<StickSide, StickRule>;
+
+/// Log levels of daemon modules set apart from the default one.
+pub type LogLevels = AHashMap<LogModule, LogLevel>;
 
 /// Profile is a collection of rules and settings for controllers and applications.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
     /// Controller settings.
     pub controllers: ControllerSettingsMap,
+    /// Settings of single controllers by serial number, taking precedence
+    /// over `controllers`.
+    pub serial_controllers: SerialSettingsMap,
     /// Blacklist apps.
     pub blacklist: AHashSet<String>,
     /// App rules.
     pub rules: RuleMap,
     /// Shell to run for shell actions.
     pub shell: Option<Box<str>>,
+    /// Gamepad backend to drive controllers with.
+    pub gamepad_backend: Option<BackendKind>,
+    /// Backend keystrokes are sent with.
+    pub keyboard_backend: KeyboardBackendKind,
+    /// Ignore virtual controllers created by Steam Input.
+    pub ignore_virtual: bool,
+    /// Report pulled analog triggers as `lt`/`rt` button presses too.
+    pub trigger_buttons: bool,
+    /// Suspend actions while the focused window is fullscreen.
+    pub pause_on_fullscreen: bool,
+    /// Rumble and log the player slot when a controller connects.
+    pub announce_on_connect: bool,
+    /// Axis calibration by device.
+    pub calibration: CalibrationMap,
+    /// SDL mappings for controllers missing from the built-in database.
+    pub controller_mappings: ControllerMappings,
+    /// HID devices, such as foot pedals, whose switches act as buttons.
+    pub hid_devices: Vec<HidDevice>,
+    /// How long single-button presses wait for a larger chord to complete.
+    pub chord_window: Duration,
+    /// How long app switches after injected input count as caused by it.
+    pub ignore_own_activity: Duration,
+    /// How long an app must stay frontmost before its rules apply.
+    pub activation_debounce: Duration,
+    /// Apps that take focus briefly without switching rules, on top of
+    /// the built-in ones such as Spotlight.
+    pub transient_apps: Vec<Box<str>>,
+    /// How long a disconnected controller may take to come back as the same one.
+    pub reconnect_grace: Duration,
+    /// How often axis motion is delivered at most, zero for every sample.
+    pub axis_interval: Duration,
+    /// Wait between injected key events, zero to send them back to back.
+    pub key_delay: Duration,
+    /// Rules applied regardless of the frontmost app.
+    pub global: Option<GlobalRules>,
+    /// Rules of pattern selectors, least specific first.
+    pub patterns: Vec<PatternRules>,
+    /// Named rule sets selected from the controller, in profile order.
+    pub sets: Vec<ProfileSet>,
+    /// OSC output of controller state.
+    pub osc: Option<OscSettings>,
+    /// On-screen overlay of stick modes and fired actions.
+    pub overlay: Option<OverlaySettings>,
+    /// Spoken announcements of app switches.
+    pub speech: Option<SpeechSettings>,
+    /// WebSocket event bridge.
+    pub websocket: Option<WebSocketSettings>,
+    /// Loopback address of the HTTP status endpoint.
+    pub http: Option<SocketAddr>,
+    /// Log levels by daemon module.
+    pub log: LogLevels,
+    /// Watchdog of the event loop.
+    pub watchdog: WatchdogSettings,
+}
+
+impl Profile {
+    /// Settings of a connected controller: the ones for its serial number,
+    /// else the ones for its model.
+    pub fn controller_settings(
+        &self,
+        device: ControllerId,
+        serial: Option<&str>,
+    ) -> Option<&ControllerSettings> {
+        serial
+            .and_then(|serial| self.serial_controllers.get(&(device, serial.into())))
+            .or_else(|| self.controllers.get(&device))
+    }
+
+    /// Resolves the rules for a browser app showing a site on `host`.
+    ///
+    /// Rules of the `bundle_id#host` selector apply on top of the app rules.
+    /// Parent domains match too, so `github.com` covers `gist.github.com`.
+    pub fn resolve_site_rules(&self, app: &str, host: &str) -> Option<AppRules> {
+        if host.is_empty() {
+            return self.resolve_app_rules(app);
+        }
+        let site = domain_suffixes(host)
+            .find_map(|domain| self.rules.get(format!("{app}#{domain}").as_str()));
+        self.resolve_layers(app, site)
+    }
+
+    /// Resolves the rules of an app showing a site on `host` with the rules
+    /// of profile set `set` on top. Blacklisted apps get no set rules.
+    pub fn resolve_set_rules(
+        &self,
+        app: &str,
+        host: &str,
+        set: Option<&str>,
+    ) -> Option<AppRules> {
+        let rules = self.resolve_site_rules(app, host);
+        let Some(set) = set.and_then(|name| self.set(name)) else {
+            return rules;
+        };
+        if self.blacklist.contains(app) {
+            return rules;
+        }
+        let mut rules = rules.unwrap_or_default();
+        rules.extend(&set.rules);
+        Some(rules)
+    }
+
+    /// Profile set named `name`.
+    pub fn set(&self, name: &str) -> Option<&ProfileSet> {
+        self.sets.iter().find(|set| set.name.as_ref() == name)
+    }
+
+    /// Whether any app rules bind keyboard chords, so keys need listening.
+    pub fn has_key_rules(&self) -> bool {
+        self.rules
+            .values()
+            .chain(self.patterns.iter().map(|p| &p.rules))
+            .any(|rules| !rules.keys.is_empty())
+    }
+
+    /// Whether actions are suspended while `app` is frontmost: blacklisted
+    /// apps are, unless global rules opt in.
+    pub fn pauses_app(&self, app: &str) -> bool {
+        self.blacklist.contains(app)
+            && !self.global.as_ref().is_some_and(|g| g.include_blacklisted)
+    }
+
+    /// Resolves the rules for an app.
+    ///
+    /// Precedence from lowest to highest: global rules, matching patterns
+    /// from least to most specific, exact bundle id rules. Blacklisted apps
+    /// get no rules, unless global rules opt in. Title rules are kept,
+    /// see `AppRules::with_titles`. Common rules sit right above global
+    /// ones, for apps that have rules of their own.
+    pub fn resolve_app_rules(&self, app: &str) -> Option<AppRules> {
+        self.resolve_layers(app, None)
+    }
+
+    /// Rules of `app` resolved as `resolve_app_rules` does, with `site`
+    /// rules on top.
+    fn resolve_layers(
+        &self,
+        app: &str,
+        site: Option<&AppRules>,
+    ) -> Option<AppRules> {
+        let global = self.global.as_ref();
+        if self.blacklist.contains(app) {
+            return global
+                .filter(|g| g.include_blacklisted)
+                .map(|g| g.rules.clone());
+        }
+        let mut layers = self
+            .patterns
+            .iter()
+            .filter(|p| p.pattern.matches(app))
+            .map(|p| &p.rules)
+            .chain(self.rules.get(app))
+            .chain(site)
+            .peekable();
+        if global.is_none() && layers.peek().is_none() {
+            return None;
+        }
+        let mut rules = global.map(|g| g.rules.clone()).unwrap_or_default();
+        if app != COMMON_BUNDLE_ID && layers.peek().is_some() {
+            if let Some(common) = self.rules.get(COMMON_BUNDLE_ID) {
+                rules.extend(common);
+            }
+        }
+        for layer in layers {
+            rules.extend(layer);
+        }
+        Some(rules)
+    }
+}
+
+/// Host followed by its parent domains: `a.b.com`, `b.com`, `com`.
+fn domain_suffixes(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(host), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
+}
+
+/// Rules of a selector pattern, matched against the active app at runtime.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternRules {
+    pub pattern: AppPattern,
+    pub rules: AppRules,
+}
+
+/// Rules applied on top of every app while the set is selected, e.g. for
+/// media or emulation on a couch setup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSet {
+    pub name: Box<str>,
+    pub rules: AppRules,
+}
+
+/// Profile set chosen by a `profile_set` action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SetTarget {
+    /// The set after the selected one, in profile order.
+    Next,
+    /// The set before the selected one.
+    Prev,
+    /// No set, only the app rules.
+    Default,
+    Named(Box<str>),
+}
+
+impl SetTarget {
+    /// Names that can't be used for sets.
+    pub const RESERVED: [&'static str; 3] = ["next", "prev", "default"];
+}
+
+impl fmt::Display for SetTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetTarget::Next => f.write_str("next"),
+            SetTarget::Prev => f.write_str("prev"),
+            SetTarget::Default => f.write_str("default"),
+            SetTarget::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+impl str::FromStr for SetTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim() {
+            "" => return Err("empty profile set".to_string()),
+            "next" => SetTarget::Next,
+            "prev" => SetTarget::Prev,
+            "default" => SetTarget::Default,
+            name => SetTarget::Named(name.into()),
+        })
+    }
+}
+
+/// Rules that apply independently of the frontmost app.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GlobalRules {
+    pub rules: AppRules,
+    /// Also apply to blacklisted apps.
+    pub include_blacklisted: bool,
 }
 
 /// A set of rules to handle controller settings for an app.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppRules {
     pub buttons: ButtonRules,
+    pub keys: KeyRules,
     pub sticks: StickRules,
+    /// Rules applied on top when the focused window title matches.
+    pub titles: Vec<TitleRules>,
+    /// Rumble after any rule fires, `None` when not set by these rules.
+    pub haptic_feedback: Option<HapticFeedback>,
+}
+
+impl AppRules {
+    /// Merges `other` on top of these rules.
+    pub fn extend(&mut self, other: &AppRules) {
+        self.buttons.extend(other.buttons.clone());
+        self.keys.extend(other.keys.clone());
+        self.sticks.extend(other.sticks.clone());
+        self.titles.extend(other.titles.iter().cloned());
+        if other.haptic_feedback.is_some() {
+            self.haptic_feedback = other.haptic_feedback;
+        }
+    }
+
+    /// Indices of title rules matching the window title.
+    pub fn matching_titles(&self, title: &str) -> Vec<usize> {
+        self.titles
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.title.is_match(title))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Rules with the given title rules applied on top.
+    /// The result has no title rules left.
+    pub fn with_titles(&self, matched: &[usize]) -> AppRules {
+        let mut rules = AppRules {
+            buttons: self.buttons.clone(),
+            keys: self.keys.clone(),
+            sticks: self.sticks.clone(),
+            titles: Vec::new(),
+            haptic_feedback: self.haptic_feedback,
+        };
+        for index in matched {
+            rules.extend(&self.titles[*index].rules);
+        }
+        rules
+    }
+}
+
+/// Rules of an app that apply only to windows with a matching title.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TitleRules {
+    #[serde(with = "regex_source")]
+    pub title: Regex,
+    pub rules: AppRules,
+}
+
+/// Regexes are stored as their source and compiled again when read.
+mod regex_source {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        regex: &Regex,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Regex, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Regex::new(&source).map_err(D::Error::custom)
+    }
 }
 
 /// Controller parameters.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ControllerSettings {
     pub mapping: AHashMap<Button, Button>,
+    /// Logical axes raw joystick axes act as, by raw axis index.
+    pub axes: AHashMap<u8, CtrlAxis>,
 }
 
 impl ControllerSettings {
     pub fn new(mapping: AHashMap<Button, Button>) -> Self {
-        Self { mapping }
+        Self {
+            mapping,
+            axes: AHashMap::new(),
+        }
     }
 }
 
@@ -63,84 +430,495 @@ pub type RuleMap = AHashMap<BundleId, AppRules>;
 /// A set of rules to handle app settings for an app.
 pub type ControllerSettingsMap = AHashMap<ControllerId, ControllerSettings>;
 
+/// Controller settings by device and serial number.
+pub type SerialSettingsMap = AHashMap<(ControllerId, Box<str>), ControllerSettings>;
+
+/// Axis calibration by device.
+pub type CalibrationMap = AHashMap<ControllerId, Calibration>;
+
 /// A set of macros.
 pub type Macros = SmallVec<[KeyCombo; 4]>;
 
 /// A action for a gamepad button.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ButtonAction {
     Keystroke(Arc<KeyCombo>),
     Macros(Arc<Macros>),
     Shell(String),
+    /// Name of a macOS Shortcuts workflow to run.
+    Shortcut(String),
+    /// A system function such as locking the screen.
+    System(SystemAction),
+    /// An arrangement of the focused window.
+    Window(WindowAction),
+    /// Switch to another macOS Space.
+    Space(SpaceTarget),
+    /// Activate the next running app of a group, in group order.
+    CycleApp(Arc<[AppPattern]>),
+    /// Jump the cursor, e.g. to another display.
+    Mouse(MouseTarget),
+    /// Select a profile set.
+    ProfileSet(SetTarget),
+}
+
+impl ButtonAction {
+    /// Name of the action as used in profiles, e.g. `keystroke`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ButtonAction::Keystroke(_) => "keystroke",
+            ButtonAction::Macros(_) => "macros",
+            ButtonAction::Shell(_) => "shell",
+            ButtonAction::Shortcut(_) => "shortcut",
+            ButtonAction::System(_) => "system",
+            ButtonAction::Window(_) => "window",
+            ButtonAction::Space(_) => "space",
+            ButtonAction::CycleApp(_) => "cycle_app",
+            ButtonAction::Mouse(_) => "mouse",
+            ButtonAction::ProfileSet(_) => "profile_set",
+        }
+    }
+
+    /// Argument of the action as written in profiles, e.g. `cmd+s`.
+    pub fn detail(&self) -> String {
+        match self {
+            ButtonAction::Keystroke(combo) => combo.to_string(),
+            ButtonAction::Macros(macros) => macros
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            ButtonAction::Shell(command) => command.clone(),
+            ButtonAction::Shortcut(name) => name.clone(),
+            ButtonAction::System(action) => action.to_string(),
+            ButtonAction::Window(action) => action.to_string(),
+            ButtonAction::Space(target) => target.to_string(),
+            ButtonAction::CycleApp(apps) => apps
+                .iter()
+                .map(AppPattern::as_str)
+                .collect::<Vec<_>>()
+                .join(" | "),
+            ButtonAction::Mouse(target) => target.to_string(),
+            ButtonAction::ProfileSet(target) => target.to_string(),
+        }
+    }
 }
 
 /// A rule for a gamepad button.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ButtonRule {
     pub action: ButtonAction,
     pub vibrate: Option<u16>,
+    /// Action to run when the chord is released.
+    pub on_release: Option<ButtonAction>,
+    /// Latch the keystroke: one press holds it down, the next releases it.
+    pub toggle: bool,
+    /// Limits for the rule's shell commands and shortcuts.
+    pub shell_limits: ShellLimits,
+    /// Wait between the rule's key events instead of the profile's.
+    pub key_delay: Option<Duration>,
+    /// Bundle id of the app the rule's keys are posted to, even in the
+    /// background. `None` sends them to the frontmost app.
+    pub target: Option<Box<str>>,
+    /// Where the rule was defined, to tell which one won a merge.
+    pub source: RuleSource,
+}
+
+/// Selector and file a rule comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleSource {
+    /// Selector the rule is listed under, e.g. `common`, `$ide`, `global`,
+    /// `set work` or `com.apple.Safari title ^Inbox`.
+    pub selector: Box<str>,
+    /// Fragment or host override relative to the profile, `None` for the
+    /// profile itself.
+    pub file: Option<Box<str>>,
+}
+
+impl fmt::Display for RuleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{} in {file}", self.selector),
+            None => f.write_str(&self.selector),
+        }
+    }
+}
+
+/// Limits for running a shell command or a shortcut.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShellLimits {
+    /// How many instances of the command may run at once, unlimited if `None`.
+    pub max_concurrent: Option<usize>,
+    /// Triggers within this period after the last accepted one are ignored.
+    pub debounce: Duration,
+    /// What to do with a trigger while `max_concurrent` instances are running.
+    pub overflow: ShellOverflow,
+}
+
+/// Policy for a command triggered while it is at its concurrency limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShellOverflow {
+    /// Ignore the trigger.
+    #[default]
+    Drop,
+    /// Run the command once a running instance finishes.
+    Queue,
+}
+
+/// Strength of the rumble played whenever a rule of an app fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HapticFeedback {
+    Off,
+    Light,
+    Medium,
 }
 
 /// A side of a stick.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum StickSide {
     Left,
     Right,
 }
 
 /// An axis of a stick.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Axis {
     X,
     Y,
 }
 
+/// Shape of the area around the center of a stick that is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeadzoneShape {
+    /// The stick is ignored closer to the center than the deadzone.
+    Circular,
+    /// The stick is ignored while both axes are within the deadzone.
+    Square,
+    /// Each axis is ignored within the deadzone on its own, so a push
+    /// along one axis does not leak into the other.
+    Cross,
+}
+
+/// Mode of a stick, the second mode a stick click switches it to, and
+/// modes that apply while a chord is held.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StickRule {
+    pub mode: StickMode,
+    pub toggle: Option<StickToggle>,
+    /// Modes taking over while their chord is held, larger chords first.
+    pub held: Vec<HeldStickMode>,
+}
+
+impl From<StickMode> for StickRule {
+    fn from(mode: StickMode) -> Self {
+        Self {
+            mode,
+            toggle: None,
+            held: Vec::new(),
+        }
+    }
+}
+
+/// Mode of a stick while a chord is held, over the mode it is toggled to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldStickMode {
+    pub chord: ButtonChord,
+    pub mode: StickMode,
+}
+
+/// Second mode of a stick. Each controller toggles between the modes on
+/// its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StickToggle {
+    /// Stick click toggling the modes, `LeftStick` or `RightStick`.
+    pub button: Button,
+    pub mode: StickMode,
+}
+
 /// A mode of a gamepad stick.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StickMode {
     Arrows(ArrowsParams),
     Volume(StepperParams),
     Brightness(StepperParams),
+    KeyboardBacklight(StepperParams),
+    Seek(SeekParams),
     MouseMove(MouseParams),
     Scroll(ScrollParams),
 }
 
+impl StickMode {
+    /// Name of the mode as used in profiles.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StickMode::Arrows(_) => "arrows",
+            StickMode::Volume(_) => "volume",
+            StickMode::Brightness(_) => "brightness",
+            StickMode::KeyboardBacklight(_) => "keyboard_backlight",
+            StickMode::Seek(_) => "seek",
+            StickMode::MouseMove(_) => "mouse_move",
+            StickMode::Scroll(_) => "scroll",
+        }
+    }
+
+    /// Filter applied to the stick before the mode reads it.
+    pub fn filter(&self) -> &AxisFilter {
+        match self {
+            StickMode::Arrows(params) => &params.filter,
+            StickMode::Volume(params)
+            | StickMode::Brightness(params)
+            | StickMode::KeyboardBacklight(params) => &params.filter,
+            StickMode::Seek(params) => &params.filter,
+            StickMode::MouseMove(params) => &params.filter,
+            StickMode::Scroll(params) => &params.filter,
+        }
+    }
+}
+
+/// Low-pass filtering of raw stick values, steadies noisy pads.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisFilter {
+    /// Weight of a new sample in the exponential moving average, `1.0`
+    /// passes samples through.
+    pub alpha: f32,
+    /// Filtered values only follow moves longer than this, `0.0` is off.
+    pub jitter_clamp: f32,
+}
+
+impl AxisFilter {
+    pub fn is_off(&self) -> bool {
+        self.alpha >= 1.0 && self.jitter_clamp <= 0.0
+    }
+}
+
+impl Default for AxisFilter {
+    fn default() -> Self {
+        Self {
+            alpha: 1.0,
+            jitter_clamp: 0.0,
+        }
+    }
+}
+
 /// Parameters for the arrows mode.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrowsParams {
     pub deadzone: f32,
+    pub deadzone_shape: DeadzoneShape,
     pub repeat_delay_ms: u64,
     pub repeat_interval_ms: u64,
+    /// Diagonals press the arrow keys of both directions.
+    pub eight_way: bool,
     pub invert_x: bool,
     pub invert_y: bool,
+    pub filter: AxisFilter,
 }
 
-/// Parameters for the volume/brightness modes.
-#[derive(Debug, Clone)]
+/// Parameters for the volume, brightness and keyboard backlight modes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepperParams {
     pub axis: Axis,
     pub deadzone: f32,
+    /// Part of the stick travel at the edge treated as full deflection.
+    pub outer_deadzone: f32,
+    pub min_interval_ms: u64,
+    pub max_interval_ms: u64,
+    /// Replaces the linear interpolation between the intervals.
+    pub curve: Option<RepeatCurve>,
+    pub invert: bool,
+    pub filter: AxisFilter,
+}
+
+/// Interval between repeated steps by stick deflection, sampled from
+/// piecewise linear points so ticks only look it up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepeatCurve {
+    /// Interval in ms at evenly spaced deflections from 0 to 1.
+    samples: Vec<u32>,
+}
+
+impl RepeatCurve {
+    const SAMPLES: usize = 101;
+
+    /// Samples the curve through `(deflection, interval_ms)` points sorted by
+    /// deflection. The interval is held before the first point and after the
+    /// last one.
+    pub fn from_points(points: &[(f32, f32)]) -> Self {
+        let samples = (0..Self::SAMPLES)
+            .map(|i| {
+                let t = i as f32 / (Self::SAMPLES - 1) as f32;
+                let next = points.iter().position(|(x, _)| *x >= t);
+                let ms = match next {
+                    Some(0) => points[0].1,
+                    Some(i) => {
+                        let ((x0, y0), (x1, y1)) = (points[i - 1], points[i]);
+                        y0 + (y1 - y0) * (t - x0) / (x1 - x0)
+                    }
+                    None => points.last().map_or(0.0, |(_, ms)| *ms),
+                };
+                ms.round() as u32
+            })
+            .collect();
+        Self { samples }
+    }
+
+    /// Interval at deflection `t`, clamped to [0, 1].
+    pub fn interval_ms(&self, t: f32) -> u64 {
+        let last = self.samples.len().saturating_sub(1);
+        let index = (t.clamp(0.0, 1.0) * last as f32).round() as usize;
+        self.samples.get(index).copied().map_or(0, u64::from)
+    }
+}
+
+/// Parameters for the seek mode: media fast-forward and rewind, or custom
+/// keystrokes, repeated faster the further the stick is pushed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeekParams {
+    pub axis: Axis,
+    pub deadzone: f32,
+    /// Part of the stick travel at the edge treated as full deflection.
+    pub outer_deadzone: f32,
+    /// Interval between steps just past the deadzone.
     pub min_interval_ms: u64,
+    /// Interval between steps at full deflection.
     pub max_interval_ms: u64,
+    /// Response curve exponent applied to the deflection.
+    pub gamma: f32,
+    /// Replaces the intervals and gamma.
+    pub curve: Option<RepeatCurve>,
     pub invert: bool,
+    /// Sent while the stick points right or up.
+    pub forward: KeyCombo,
+    /// Sent while the stick points left or down.
+    pub backward: KeyCombo,
+    pub filter: AxisFilter,
+}
+
+/// Streaming of axis values and button events as OSC messages over UDP.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OscSettings {
+    /// Address messages are sent to.
+    pub target: SocketAddr,
+    /// Prefix of message addresses, e.g. `/gamacros`.
+    pub prefix: String,
+    /// Stick and trigger deadzone applied before sending.
+    pub deadzone: f32,
+    /// Response curve exponent applied after the deadzone.
+    pub gamma: f32,
+}
+
+/// Watchdog noticing the event loop stuck in an action, e.g. a keystroke
+/// blocked by the system.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogSettings {
+    /// How long an action may run before the loop counts as stuck, zero
+    /// turns the watchdog off.
+    pub timeout: Duration,
+    /// Restart the daemon once stuck instead of only logging it.
+    pub restart: bool,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            restart: false,
+        }
+    }
+}
+
+/// Part of the daemon whose logs can be turned up or down on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LogModule {
+    /// Controllers, buttons and sticks.
+    Gamepad,
+    /// App, window title and site switches.
+    Activity,
+    /// Keystrokes, shell commands and other performed actions.
+    Actions,
+    /// Control socket, WebSocket and HTTP servers.
+    Api,
+}
+
+/// Log level, from quietest to most verbose.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// On-screen overlay flashing the stick modes when the active rules change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverlaySettings {
+    /// Also flash the name of every fired button action.
+    pub actions: bool,
+    /// How long a flash stays on screen.
+    pub duration: Duration,
+}
+
+/// Spoken announcements of the active app, for users who can't see which
+/// rules apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeechSettings {
+    /// Name of the system voice, the default voice when unset.
+    pub voice: Option<String>,
+}
+
+/// WebSocket event bridge for Stream Deck plugins and dashboards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebSocketSettings {
+    /// Loopback address to listen on.
+    pub address: SocketAddr,
+    /// Origins of web pages allowed to connect. Clients sending no origin,
+    /// i.e. not browsers, always are.
+    pub origins: Vec<String>,
 }
 
 /// Parameters for the mouse move mode.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MouseParams {
     pub deadzone: f32,
+    pub deadzone_shape: DeadzoneShape,
+    /// Part of the stick travel at the edge treated as full deflection.
+    pub outer_deadzone: f32,
     pub max_speed_px_s: f32,
     pub gamma: f32,
     pub invert_x: bool,
     pub invert_y: bool,
+    pub filter: AxisFilter,
+    /// Click once the cursor rests after moving.
+    pub dwell: Option<DwellClick>,
+}
+
+/// Dwell clicking, for users who cannot press buttons reliably.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DwellClick {
+    /// How long the cursor has to rest before the click.
+    pub delay: Duration,
+    pub button: MouseButton,
 }
 
 /// Parameters for the scroll mode.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrollParams {
     pub deadzone: f32,
+    pub deadzone_shape: DeadzoneShape,
+    /// Part of the stick travel at the edge treated as full deflection.
+    pub outer_deadzone: f32,
+    /// Scroll speed, in `unit`s per second.
     pub speed_lines_s: f32,
+    pub unit: ScrollUnit,
     pub horizontal: bool,
+    /// Deadzone of the horizontal axis, `deadzone` applies to the vertical.
+    pub horizontal_deadzone: f32,
+    /// Horizontal scroll speed, `speed_lines_s` applies to the vertical.
+    pub horizontal_speed_lines_s: f32,
     pub invert_x: bool,
     pub invert_y: bool,
+    pub filter: AxisFilter,
 }
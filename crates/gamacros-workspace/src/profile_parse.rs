@@ -1,6 +1,167 @@
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
+
 use serde::Deserialize;
 
-use crate::{v1::ProfileV1, Profile, profile::ProfileError};
+use crate::{
+    v1::{ProfileV1, ProfileV1Fragment},
+    Profile,
+    profile::ProfileError,
+};
+
+/// Directory next to the profile holding profile fragments.
+pub const FRAGMENTS_DIR_NAME: &str = "profiles.d";
+
+/// Load a profile file together with fragments from the `profiles.d`
+/// directory next to it. Fragments are merged in file path order, then the
+/// override for this machine, see [`host_profile_path`].
+/// Errors name the file that caused them.
+pub fn load_profile(path: &Path) -> Result<Profile, ProfileError> {
+    let input = std::fs::read_to_string(path).map_err(|e| in_file(path, e))?;
+    let fragment_paths = overlay_paths(path)?;
+    if fragment_paths.is_empty() {
+        return parse_profile(&input).map_err(|e| in_file(path, e));
+    }
+
+    let version = parse_version(&input).map_err(|e| in_file(path, e))?;
+    if version != 1 {
+        return Err(in_file(path, ProfileError::UnsupportedVersion(version)));
+    }
+    let mut profile: ProfileV1 =
+        serde_yaml::from_str(&input).map_err(|e| in_file(path, e))?;
+    let workspace = path.parent().unwrap_or(Path::new(""));
+    let fragments = parse_fragments(&profile, &fragment_paths)?;
+    for (fragment_path, mut fragment) in fragment_paths.iter().zip(fragments) {
+        let file = fragment_path
+            .strip_prefix(workspace)
+            .unwrap_or(fragment_path);
+        fragment.set_source(&file.display().to_string());
+        profile.merge_fragment(fragment);
+    }
+    profile.parse().map_err(|e| in_file(path, e))
+}
+
+/// Files merged over the profile at `path`, in merge order: fragments,
+/// then the override for this machine.
+pub(crate) fn overlay_paths(path: &Path) -> Result<Vec<PathBuf>, ProfileError> {
+    let mut paths = fragment_paths(&path.with_file_name(FRAGMENTS_DIR_NAME))?;
+    if let Some(host_path) = hostname()
+        .map(|host| host_profile_path(path, &host))
+        .filter(|host_path| host_path.is_file())
+    {
+        paths.push(host_path);
+    }
+    Ok(paths)
+}
+
+/// Override of the profile for machine `host`, e.g. `gc_profile.studio.yaml`
+/// next to `gc_profile.yaml`. It has the format of a fragment, so dotfiles
+/// synced across machines can vary mappings per machine.
+pub fn host_profile_path(path: &Path, host: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{host}.{extension}"))
+}
+
+/// Short name of this machine in lowercase, e.g. `studio` for `Studio.local`.
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for its length.
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if result != 0 {
+        return None;
+    }
+    let name = CStr::from_bytes_until_nul(&buf).ok()?.to_str().ok()?;
+    short_hostname(name)
+}
+
+fn short_hostname(name: &str) -> Option<String> {
+    let short = name.split('.').next()?.trim();
+    (!short.is_empty()).then(|| short.to_lowercase())
+}
+
+fn in_file(path: &Path, error: impl Into<ProfileError>) -> ProfileError {
+    ProfileError::InFile(path.display().to_string(), Box::new(error.into()))
+}
+
+/// Parse and check fragments on all cores, in the order of `paths`.
+/// Errors of all broken fragments are reported together.
+fn parse_fragments(
+    profile: &ProfileV1,
+    paths: &[PathBuf],
+) -> Result<Vec<ProfileV1Fragment>, ProfileError> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = paths.len().div_ceil(threads).max(1);
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| {
+                            parse_fragment(profile, path)
+                                .map_err(|e| in_file(path, e))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("fragment parser panicked"))
+            .collect()
+    });
+
+    let mut fragments = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(fragment) => fragments.push(fragment),
+            Err(e) => errors.push(e),
+        }
+    }
+    match errors.len() {
+        0 => Ok(fragments),
+        1 => Err(errors.remove(0)),
+        _ => Err(ProfileError::Multiple(errors)),
+    }
+}
+
+/// Parse a fragment and check it on its own, so errors point at it.
+fn parse_fragment(
+    profile: &ProfileV1,
+    path: &Path,
+) -> Result<ProfileV1Fragment, ProfileError> {
+    let fragment: ProfileV1Fragment =
+        serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    profile.with_fragment_rules(&fragment).parse()?;
+    Ok(fragment)
+}
+
+/// YAML files in the fragments directory and its subdirectories, sorted.
+fn fragment_paths(dir: &Path) -> Result<Vec<PathBuf>, ProfileError> {
+    let mut paths = Vec::new();
+    if !dir.is_dir() {
+        return Ok(paths);
+    }
+    let mut pending = vec![dir.to_owned()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("yaml" | "yml")
+            ) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
 
 /// Parse yaml profile.
 pub fn parse_profile(input: &str) -> Result<Profile, ProfileError> {
@@ -29,7 +190,10 @@ fn parse_version(input: &str) -> Result<u8, ProfileError> {
 
 #[cfg(test)]
 mod tests {
+    use gamacros_bit_mask::Bitable;
+
     use super::*;
+    use crate::WorkspaceError;
 
     #[test]
     fn parse_profile_yaml_error_when_version_missing() {
@@ -39,4 +203,1196 @@ mod tests {
             Err(ProfileError::YamlDeserializeError(_))
         ));
     }
+
+    #[test]
+    fn parse_profile_gamepad_backend() {
+        let yaml = "version: 1\ngamepad_backend: game_controller\n";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        assert_eq!(
+            profile.gamepad_backend,
+            Some(gamacros_gamepad::BackendKind::GameController)
+        );
+    }
+
+    #[test]
+    fn parse_profile_invalid_gamepad_backend() {
+        let yaml = "version: 1\ngamepad_backend: xinput\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidBackend(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_keyboard_backend() {
+        use gamacros_control::KeyboardBackendKind;
+
+        let profile = parse_profile("version: 1\n").unwrap();
+        assert_eq!(profile.keyboard_backend, KeyboardBackendKind::Enigo);
+        let profile = parse_profile("version: 1\nkeyboard_backend: native\n")
+            .expect("keyboard_backend should parse");
+        assert_eq!(profile.keyboard_backend, KeyboardBackendKind::Native);
+        assert!(matches!(
+            parse_profile("version: 1\nkeyboard_backend: uinput\n"),
+            Err(ProfileError::V1Profile(
+                crate::v1::Error::InvalidKeyboardBackend(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_log_levels() {
+        use crate::{LogLevel, LogModule};
+
+        let profile =
+            parse_profile("version: 1\nlog: { gamepad: debug, api: off }\n")
+                .expect("log levels should parse");
+        assert_eq!(profile.log.len(), 2);
+        assert_eq!(profile.log[&LogModule::Gamepad], LogLevel::Debug);
+        assert_eq!(profile.log[&LogModule::Api], LogLevel::Off);
+        for invalid in ["log: { audio: debug }", "log: { gamepad: loud }"] {
+            assert!(matches!(
+                parse_profile(&format!("version: 1\n{invalid}\n")),
+                Err(ProfileError::V1Profile(crate::v1::Error::InvalidLog(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_profile_controller_serial() {
+        use gamacros_gamepad::Button;
+
+        let profile = parse_profile(
+            r#"
+version: 1
+controllers:
+  - vid: 0x045e
+    pid: 0x0b13
+    preset: nintendo_swap
+  - vid: 0x045e
+    pid: 0x0b13
+    serial: "3039374B5A"
+    remap: { a: x }
+"#,
+        )
+        .expect("controller serial should parse");
+        let device = (0x045e, 0x0b13);
+        let by_serial = profile
+            .controller_settings(device, Some("3039374B5A"))
+            .expect("serial settings should be found");
+        assert_eq!(by_serial.mapping[&Button::A], Button::X);
+        assert_eq!(by_serial.mapping.len(), 1);
+        for serial in [None, Some("other")] {
+            let by_model = profile.controller_settings(device, serial).unwrap();
+            assert_eq!(by_model.mapping[&Button::A], Button::B);
+        }
+        assert!(matches!(
+            parse_profile(
+                "version: 1\ncontrollers: [{ vid: 1, pid: 2, serial: \"\" }]\n"
+            ),
+            Err(ProfileError::V1Profile(
+                crate::v1::Error::InvalidControllerSerial(1, 2)
+            ))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_watchdog() {
+        let profile = parse_profile("version: 1\n").expect("profile should parse");
+        assert_eq!(profile.watchdog.timeout, std::time::Duration::from_secs(5));
+        assert!(!profile.watchdog.restart);
+
+        let profile = parse_profile(
+            "version: 1\nwatchdog: { timeout_ms: 2000, restart: true }\n",
+        )
+        .expect("watchdog should parse");
+        assert_eq!(
+            profile.watchdog.timeout,
+            std::time::Duration::from_millis(2000)
+        );
+        assert!(profile.watchdog.restart);
+    }
+
+    #[test]
+    fn parse_profile_controller_mappings() {
+        let yaml = r#"
+version: 1
+vars:
+  db: ~/sdl
+controller_db: $db/gamecontrollerdb.txt
+controller_mappings:
+  - "03000000c82d00000631000000000000,8BitDo Pad,a:b0,b:b1,platform:Mac OS X,"
+"#;
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let home = std::env::var("HOME").unwrap();
+        let mappings = profile.controller_mappings;
+        assert_eq!(
+            mappings.files,
+            [std::path::PathBuf::from(format!(
+                "{home}/sdl/gamecontrollerdb.txt"
+            ))]
+        );
+        assert_eq!(mappings.mappings.len(), 1);
+
+        let yaml = "version: 1\ncontroller_mappings: [\"8BitDo Pad,a:b0\"]\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidMapping(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_remap_presets() {
+        use gamacros_gamepad::Button;
+
+        let yaml = "
+version: 1
+controllers:
+  - vid: 0x057e
+    pid: 0x2009
+    preset: nintendo_swap+triggers_to_bumpers
+    remap:
+      x: start
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mapping = &profile.controllers[&(0x057e, 0x2009)].mapping;
+        assert_eq!(mapping.get(&Button::A), Some(&Button::B));
+        assert_eq!(mapping.get(&Button::Y), Some(&Button::X));
+        assert_eq!(mapping.get(&Button::X), Some(&Button::Start));
+        assert_eq!(
+            mapping.get(&Button::RightTrigger),
+            Some(&Button::RightShoulder)
+        );
+
+        let yaml =
+            "version: 1\ncontrollers:\n  - { vid: 1, pid: 2, preset: xbox }\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(
+                crate::v1::Error::InvalidRemapPreset(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_osc() {
+        let yaml = "version: 1\nosc:\n  address: 127.0.0.1:9000\n  prefix: /pad/\n";
+        let osc = parse_profile(yaml)
+            .expect("profile should parse")
+            .osc
+            .expect("osc is set");
+        assert_eq!(osc.target, "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(osc.prefix, "/pad");
+
+        let yaml = "version: 1\nosc:\n  address: 127.0.0.1:9000\n  prefix: pad\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidOsc(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_overlay() {
+        let profile = parse_profile("version: 1\n").expect("profile should parse");
+        assert_eq!(profile.overlay, None);
+
+        let yaml = "version: 1\noverlay:\n  actions: true\n  duration_ms: 500\n";
+        let overlay = parse_profile(yaml)
+            .expect("profile should parse")
+            .overlay
+            .expect("overlay is set");
+        assert!(overlay.actions);
+        assert_eq!(overlay.duration, std::time::Duration::from_millis(500));
+    }
+
+    #[test]
+    fn parse_profile_speech() {
+        let yaml = "version: 1\nspeech: {}\n";
+        let speech = parse_profile(yaml).unwrap().speech.expect("speech is set");
+        assert_eq!(speech.voice, None);
+
+        let yaml = "version: 1\nspeech:\n  voice: Samantha\n";
+        let speech = parse_profile(yaml).unwrap().speech.expect("speech is set");
+        assert_eq!(speech.voice.as_deref(), Some("Samantha"));
+    }
+
+    #[test]
+    fn parse_profile_websocket() {
+        let yaml = "
+version: 1
+websocket:
+  address: 127.0.0.1:8765
+  origins: [http://localhost:3000]
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let websocket = profile.websocket.expect("websocket is set");
+        assert_eq!(websocket.address, "127.0.0.1:8765".parse().unwrap());
+        assert_eq!(websocket.origins, ["http://localhost:3000"]);
+
+        for address in ["localhost", "0.0.0.0:8765"] {
+            let yaml = format!("version: 1\nwebsocket:\n  address: {address}\n");
+            assert!(matches!(
+                parse_profile(&yaml),
+                Err(ProfileError::V1Profile(crate::v1::Error::InvalidWebSocket(
+                    _
+                )))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_profile_http() {
+        let yaml = "version: 1\nhttp:\n  address: 127.0.0.1:8766\n";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        assert_eq!(profile.http, Some("127.0.0.1:8766".parse().unwrap()));
+
+        let yaml = "version: 1\nhttp:\n  address: 0.0.0.0:8766\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidHttp(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_toggle_requires_keystroke() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        shell: ls
+        toggle: true
+";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidToggle(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_expands_vars_in_shell() {
+        let yaml = "
+version: 1
+vars:
+  notes: /srv/notes
+rules:
+  common:
+    buttons:
+      a:
+        shell: open ${notes}/today.md && echo $USER
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        assert!(matches!(
+            &profile.rules["common"].buttons[&chord].action,
+            crate::ButtonAction::Shell(s) if s == "open /srv/notes/today.md && echo $USER"
+        ));
+    }
+
+    #[test]
+    fn parse_profile_shell_limits() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        shell: make build
+        max_concurrent: 2
+        debounce_ms: 300
+        overflow: queue
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        let limits = profile.rules["common"].buttons[&chord].shell_limits;
+        assert_eq!(limits.max_concurrent, Some(2));
+        assert_eq!(limits.debounce, std::time::Duration::from_millis(300));
+        assert_eq!(limits.overflow, crate::ShellOverflow::Queue);
+
+        let yaml = yaml.replace("shell: make build", "keystroke: enter");
+        assert!(matches!(
+            parse_profile(&yaml),
+            Err(ProfileError::V1Profile(
+                crate::v1::Error::InvalidShellLimits(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_stick_smoothing() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    sticks:
+      right:
+        mode: mouse_move
+        smoothing: 0.3
+        jitter_clamp: 0.02
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mode = &profile.rules["common"].sticks[&crate::StickSide::Right].mode;
+        assert_eq!(
+            *mode.filter(),
+            crate::AxisFilter {
+                alpha: 0.3,
+                jitter_clamp: 0.02
+            }
+        );
+
+        let yaml = yaml.replace("smoothing: 0.3", "smoothing: 0");
+        assert!(matches!(
+            parse_profile(&yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidStick(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_deadzones() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        mode: scroll
+        deadzone: 0.2
+        outer_deadzone: 0.1
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mode = &profile.rules["common"].sticks[&crate::StickSide::Left].mode;
+        assert!(matches!(
+            mode,
+            crate::StickMode::Scroll(params)
+                if params.outer_deadzone == 0.1 && params.horizontal_deadzone == 0.2
+        ));
+
+        // No travel is left between the deadzones
+        let yaml = yaml.replace("outer_deadzone: 0.1", "deadzone_shape: cross");
+        let profile = parse_profile(&yaml).expect("profile should parse");
+        let mode = &profile.rules["common"].sticks[&crate::StickSide::Left].mode;
+        assert!(matches!(
+            mode,
+            crate::StickMode::Scroll(params)
+                if params.deadzone_shape == crate::DeadzoneShape::Cross
+        ));
+
+        let yaml = yaml.replace("deadzone_shape: cross", "outer_deadzone: 0.8");
+        assert!(matches!(
+            parse_profile(&yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidStick(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_stick_toggle() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    sticks:
+      right:
+        mode: mouse_move
+        toggle_with: rs
+        toggle_mode: { mode: scroll, speed_lines_s: 50 }
+";
+        let profile = parse_profile(yaml).expect("stick toggle should parse");
+        let rule = &profile.rules["common"].sticks[&crate::StickSide::Right];
+        assert!(matches!(rule.mode, crate::StickMode::MouseMove(_)));
+        let toggle = rule.toggle.as_ref().expect("toggle should be set");
+        assert_eq!(toggle.button, gamacros_gamepad::Button::RightStick);
+        assert!(matches!(
+            &toggle.mode,
+            crate::StickMode::Scroll(params) if params.speed_lines_s == 50.0
+        ));
+
+        for invalid in [
+            yaml.replace("toggle_with: rs", "toggle_with: a"),
+            yaml.replace("        toggle_with: rs\n", ""),
+            yaml.replace("mode: scroll,", "mode: scroll, toggle_with: ls,"),
+        ] {
+            assert!(matches!(
+                parse_profile(&invalid),
+                Err(ProfileError::V1Profile(
+                    crate::v1::Error::InvalidStickToggle(_)
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_profile_held_stick_modes() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        - { mode: mouse_move }
+        - { mode: scroll, while_held: lt }
+        - { mode: arrows, while_held: lt+rt }
+";
+        let profile = parse_profile(yaml).expect("held stick modes should parse");
+        let rule = &profile.rules["common"].sticks[&crate::StickSide::Left];
+        assert!(matches!(rule.mode, crate::StickMode::MouseMove(_)));
+        let held: Vec<_> = rule
+            .held
+            .iter()
+            .map(|held| (held.chord.count(), held.mode.name()))
+            .collect();
+        assert_eq!(held, [(2, "arrows"), (1, "scroll")]);
+
+        for invalid in [
+            yaml.replace("{ mode: mouse_move }", "{ mode: volume, while_held: a }"),
+            yaml.replace(", while_held: lt }", " }"),
+            yaml.replace("while_held: lt }", "while_held: lt, toggle_with: ls }"),
+        ] {
+            assert!(matches!(
+                parse_profile(&invalid),
+                Err(ProfileError::V1Profile(crate::v1::Error::InvalidHeldStick(
+                    _
+                )))
+            ));
+        }
+        // Typos are reported by name, in a list or a single mode
+        let single = "version: 1\nrules:\n  common:\n    sticks:\n      left: { mode: scroll, gama: 2 }\n";
+        let list =
+            yaml.replace("{ mode: mouse_move }", "{ mode: mouse_move, gama: 2 }");
+        for typo in [single, &list] {
+            let error = parse_profile(typo).unwrap_err().to_string();
+            assert!(error.contains("unknown field `gama`"), "{error}");
+        }
+    }
+
+    #[test]
+    fn parse_profile_repeat_curve() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    sticks:
+      left: { mode: volume, curve: [[0.2, 400], [0.6, 120], [1.0, 30]] }
+      right: { mode: seek }
+";
+        let profile = parse_profile(yaml).expect("repeat curve should parse");
+        let sticks = &profile.rules["common"].sticks;
+        let crate::StickMode::Volume(params) = &sticks[&crate::StickSide::Left].mode
+        else {
+            panic!("left stick should be volume");
+        };
+        let curve = params.curve.as_ref().expect("curve should be set");
+        let intervals: Vec<_> = [0.0, 0.2, 0.4, 0.6, 0.8, 1.0, 1.5]
+            .into_iter()
+            .map(|t| curve.interval_ms(t))
+            .collect();
+        assert_eq!(intervals, [400, 400, 260, 120, 75, 30, 30]);
+        assert!(matches!(
+            &sticks[&crate::StickSide::Right].mode,
+            crate::StickMode::Seek(params) if params.curve.is_none()
+        ));
+
+        for invalid in [
+            "[]",
+            "[[0.6, 120], [0.2, 400]]",
+            "[[1.2, 30]]",
+            "[[0.5, 0]]",
+        ] {
+            let invalid =
+                yaml.replace("[[0.2, 400], [0.6, 120], [1.0, 30]]", invalid);
+            assert!(matches!(
+                parse_profile(&invalid),
+                Err(ProfileError::V1Profile(crate::v1::Error::InvalidStick(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn resolve_app_rules_with_global() {
+        let yaml = "
+version: 1
+blacklist: [com.valve.game]
+global:
+  buttons:
+    a:
+      keystroke: enter
+    b:
+      keystroke: escape
+rules:
+  com.apple.Safari:
+    buttons:
+      a:
+        keystroke: space
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let rules = profile
+            .resolve_app_rules("com.apple.Safari")
+            .expect("safari has rules");
+        assert_eq!(rules.buttons.len(), 2);
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        assert!(matches!(
+            &rules.buttons[&chord].action,
+            crate::ButtonAction::Keystroke(k) if **k == "space".parse().unwrap()
+        ));
+        assert!(profile.resolve_app_rules("org.unknown.app").is_some());
+        assert!(profile.resolve_app_rules("com.valve.game").is_none());
+        assert!(profile.pauses_app("com.valve.game"));
+        assert!(!profile.pauses_app("com.apple.Safari"));
+    }
+
+    #[test]
+    fn resolve_set_rules_on_top_of_app_rules() {
+        let yaml = r#"
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: enter
+      b:
+        keystroke: esc
+sets:
+  - name: media
+    buttons:
+      a:
+        system: play_pause
+      start:
+        profile_set: next
+"#;
+        use gamacros_gamepad::Button;
+
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let rules = profile
+            .resolve_set_rules("common", "", Some("media"))
+            .expect("rules are resolved");
+        let chord = |button| crate::ButtonChord::new(&[button]);
+        let (a, b) = (chord(Button::A), chord(Button::B));
+        assert!(matches!(
+            rules.buttons[&a].action,
+            crate::ButtonAction::System(_)
+        ));
+        assert!(matches!(
+            rules.buttons[&b].action,
+            crate::ButtonAction::Keystroke(_)
+        ));
+        let rules = profile.resolve_set_rules("common", "", None).unwrap();
+        assert!(matches!(
+            rules.buttons[&a].action,
+            crate::ButtonAction::Keystroke(_)
+        ));
+
+        let yaml = "version: 1\nsets:\n  - name: next\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(
+                crate::v1::Error::InvalidProfileSet(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn resolve_app_rules_with_patterns() {
+        let yaml = "
+version: 1
+rules:
+  com.jetbrains.*:
+    buttons:
+      a:
+        keystroke: enter
+      b:
+        keystroke: escape
+  com.jetbrains.rust*:
+    buttons:
+      b:
+        keystroke: space
+  com.jetbrains.rustrover:
+    buttons:
+      x:
+        keystroke: tab
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let rules = profile
+            .resolve_app_rules("com.jetbrains.rustrover")
+            .expect("rustrover has rules");
+        assert_eq!(rules.buttons.len(), 3);
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::B);
+        assert!(matches!(
+            &rules.buttons[&chord].action,
+            crate::ButtonAction::Keystroke(k) if **k == "space".parse().unwrap()
+        ));
+        let rules = profile
+            .resolve_app_rules("com.jetbrains.goland")
+            .expect("pattern matches");
+        assert_eq!(rules.buttons.len(), 2);
+        assert!(profile.resolve_app_rules("com.apple.Safari").is_none());
+    }
+
+    #[test]
+    fn resolve_app_rules_layers_common_under_patterns() {
+        use gamacros_gamepad::Button;
+
+        let yaml = "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: enter
+      y:
+        keystroke: cmd+z
+  com.foo.*:
+    buttons:
+      a:
+        keystroke: space
+  com.foo.bar:
+    buttons:
+      x:
+        keystroke: tab
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let keystroke = |app: &str, button| {
+            let rules = profile.resolve_app_rules(app).expect("app has rules");
+            let mut chord = crate::ButtonChord::empty();
+            chord.insert(button);
+            match &rules.buttons[&chord].action {
+                crate::ButtonAction::Keystroke(k) => (**k).clone(),
+                action => panic!("unexpected action {action:?}"),
+            }
+        };
+        // The pattern overrides common, the exact entry doesn't undo it
+        assert_eq!(
+            keystroke("com.foo.bar", Button::A),
+            "space".parse().unwrap()
+        );
+        assert_eq!(keystroke("com.foo.bar", Button::X), "tab".parse().unwrap());
+        assert_eq!(
+            keystroke("com.foo.bar", Button::Y),
+            "cmd+z".parse().unwrap()
+        );
+        assert_eq!(
+            keystroke("com.foo.baz", Button::A),
+            "space".parse().unwrap()
+        );
+        assert_eq!(keystroke("common", Button::A), "enter".parse().unwrap());
+        assert!(profile.resolve_app_rules("com.apple.Safari").is_none());
+    }
+
+    #[test]
+    fn resolve_app_rules_with_title() {
+        let yaml = "
+version: 1
+rules:
+  com.google.Chrome:
+    buttons:
+      a:
+        keystroke: enter
+    titles:
+      ' - YouTube$':
+        buttons:
+          a:
+            keystroke: space
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        let app_rules = profile
+            .resolve_app_rules("com.google.Chrome")
+            .expect("chrome has rules");
+        let keystroke = |title: &str| {
+            let rules = app_rules.with_titles(&app_rules.matching_titles(title));
+            assert!(rules.titles.is_empty());
+            match &rules.buttons[&chord].action {
+                crate::ButtonAction::Keystroke(k) => (**k).clone(),
+                action => panic!("unexpected action {action:?}"),
+            }
+        };
+        assert_eq!(keystroke("Cats - YouTube"), "space".parse().unwrap());
+        assert_eq!(keystroke("GitHub"), "enter".parse().unwrap());
+    }
+
+    #[test]
+    fn parse_profile_invalid_title_regex() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    titles:
+      '(': {}
+";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidTitle(..)))
+        ));
+    }
+
+    #[test]
+    fn resolve_site_rules_matches_parent_domains() {
+        let yaml = "
+version: 1
+rules:
+  com.apple.Safari:
+    buttons:
+      a:
+        keystroke: enter
+  com.apple.Safari#github.com:
+    buttons:
+      b:
+        keystroke: escape
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let buttons = |host: &str| {
+            profile
+                .resolve_site_rules("com.apple.Safari", host)
+                .expect("safari has rules")
+                .buttons
+                .len()
+        };
+        assert_eq!(buttons("gist.github.com"), 2);
+        assert_eq!(buttons("github.com"), 2);
+        assert_eq!(buttons("example.com"), 1);
+        assert_eq!(buttons(""), 1);
+    }
+
+    #[test]
+    fn resolve_site_rules_keeps_app_rules_over_common() {
+        use gamacros_gamepad::Button;
+
+        let yaml = "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: enter
+  com.apple.Safari:
+    buttons:
+      a:
+        keystroke: space
+  com.apple.Safari#github.com:
+    buttons:
+      b:
+        keystroke: escape
+  com.google.Chrome#github.com:
+    buttons:
+      b:
+        keystroke: escape
+";
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let keystroke = |app: &str, button| {
+            let rules = profile
+                .resolve_site_rules(app, "github.com")
+                .expect("site has rules");
+            let mut chord = crate::ButtonChord::empty();
+            chord.insert(button);
+            match &rules.buttons[&chord].action {
+                crate::ButtonAction::Keystroke(k) => (**k).clone(),
+                action => panic!("unexpected action {action:?}"),
+            }
+        };
+        let safari = "com.apple.Safari";
+        assert_eq!(keystroke(safari, Button::A), "space".parse().unwrap());
+        assert_eq!(keystroke(safari, Button::B), "escape".parse().unwrap());
+        // Apps with only site rules still get common ones under them
+        let chrome = "com.google.Chrome";
+        assert_eq!(keystroke(chrome, Button::A), "enter".parse().unwrap());
+        assert_eq!(keystroke(chrome, Button::B), "escape".parse().unwrap());
+    }
+
+    #[test]
+    fn parse_profile_binds_extra_buttons() {
+        let profile = parse_profile(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      paddle1+share: { keystroke: cmd+s }
+",
+        )
+        .expect("extra buttons should parse");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::Paddle1);
+        chord.insert(gamacros_gamepad::Button::Misc);
+        assert!(profile.rules["common"].buttons.contains_key(&chord));
+        for &button in gamacros_gamepad::Button::ALL {
+            assert_eq!(
+                crate::parse_button(crate::button_name(button)),
+                Some(button)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_profile_binds_raw_joystick_inputs() {
+        let profile = parse_profile(
+            "
+version: 1
+controllers:
+  - vid: 0x044f
+    pid: 0xb10a
+    remap: { js:0: a }
+    axes: { jaxis:2: left_y }
+rules:
+  common:
+    buttons:
+      js:5+js:31: { keystroke: space }
+",
+        )
+        .expect("raw joystick inputs should parse");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::Joystick5);
+        chord.insert(gamacros_gamepad::Button::Joystick31);
+        assert!(profile.rules["common"].buttons.contains_key(&chord));
+        let settings = &profile.controllers[&(0x044f, 0xb10a)];
+        assert_eq!(
+            settings.mapping[&gamacros_gamepad::Button::Joystick0],
+            gamacros_gamepad::Button::A
+        );
+        assert_eq!(settings.axes[&2], gamacros_gamepad::Axis::LeftY);
+        assert!(parse_profile(
+            "version: 1\nrules:\n  common: { buttons: { js:32: {} } }\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_profile_hid_devices() {
+        let profile = parse_profile(
+            "
+version: 1
+controllers:
+  - vid: 0x05f3
+    pid: 0x00ff
+    hid: { 0x0c:0xe9: js:1, 9:1: js:0 }
+",
+        )
+        .expect("hid switches should parse");
+        let buttons = &profile.hid_devices[0].buttons;
+        assert_eq!(profile.hid_devices[0].device, (0x05f3, 0x00ff));
+        assert_eq!(
+            buttons
+                .iter()
+                .map(|b| (b.usage_page, b.usage, b.button))
+                .collect::<Vec<_>>(),
+            [
+                (0x09, 1, gamacros_gamepad::Button::Joystick0),
+                (0x0c, 0xe9, gamacros_gamepad::Button::Joystick1),
+            ]
+        );
+        let broken =
+            "version: 1\ncontrollers:\n  - { vid: 1, pid: 1, hid: { volume: a } }\n";
+        assert!(matches!(
+            parse_profile(broken),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidHidUsage(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_binds_keyboard_chords() {
+        let profile = parse_profile(
+            "
+version: 1
+rules:
+  com.apple.Safari:
+    keys:
+      hyper+j: { keystroke: cmd+l }
+",
+        )
+        .expect("keyboard chords should parse");
+        assert!(profile.has_key_rules());
+        let combo = "cmd+ctrl+alt+shift+j".parse().unwrap();
+        let rules = profile.resolve_app_rules("com.apple.Safari").unwrap();
+        assert_eq!(rules.keys[&combo].source.to_string(), "com.apple.Safari");
+        assert!(!parse_profile("version: 1\n").unwrap().has_key_rules());
+
+        let broken = "version: 1\nrules:\n  common: { keys: { cmd+a+b: {} } }\n";
+        assert!(matches!(
+            parse_profile(broken),
+            Err(ProfileError::V1Profile(crate::v1::Error::InvalidTrigger(_)))
+        ));
+    }
+
+    #[test]
+    fn parse_profile_activation_filter() {
+        let profile = parse_profile(
+            "
+version: 1
+vars:
+  launcher: com.raycast.macos
+activation_debounce_ms: 150
+transient_apps: [\"${launcher}\"]
+",
+        )
+        .expect("activation settings should parse");
+        assert_eq!(profile.activation_debounce.as_millis(), 150);
+        assert_eq!(profile.transient_apps, ["com.raycast.macos".into()]);
+        let defaults = parse_profile("version: 1\n").unwrap();
+        assert!(defaults.activation_debounce.is_zero());
+        assert!(defaults.transient_apps.is_empty());
+    }
+
+    #[test]
+    fn parse_profile_trigger_buttons() {
+        assert!(parse_profile("version: 1\n").unwrap().trigger_buttons);
+        let profile = parse_profile("version: 1\ntrigger_buttons: false\n")
+            .expect("trigger_buttons should parse");
+        assert!(!profile.trigger_buttons);
+    }
+
+    #[test]
+    fn parse_profile_axis_interval() {
+        let profile = parse_profile("version: 1\naxis_interval_ms: 4\n")
+            .expect("axis_interval_ms should parse");
+        assert_eq!(profile.axis_interval.as_millis(), 4);
+        assert!(parse_profile("version: 1\n")
+            .unwrap()
+            .axis_interval
+            .is_zero());
+    }
+
+    #[test]
+    fn parse_profile_key_delay() {
+        let yaml = "
+version: 1
+key_delay_ms: 10
+rules:
+  common:
+    buttons:
+      a: { macros: [cmd+c, cmd+v], key_delay_ms: 30 }
+      b: { shell: make, key_delay_ms: 30 }
+";
+        let Err(error) = parse_profile(yaml) else {
+            panic!("key delay of a shell rule should be rejected");
+        };
+        assert!(matches!(
+            error,
+            ProfileError::V1Profile(crate::v1::Error::InvalidKeyDelay(_))
+        ));
+
+        let yaml = yaml.replace("shell: make", "keystroke: b");
+        let profile = parse_profile(&yaml).expect("key_delay_ms should parse");
+        assert_eq!(profile.key_delay.as_millis(), 10);
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        let rule = &profile.rules["common"].buttons[&chord];
+        assert_eq!(rule.key_delay, Some(std::time::Duration::from_millis(30)));
+
+        for yaml in [
+            yaml.replace("key_delay_ms: 10", "key_delay_ms: 1001"),
+            yaml.replace("b, key_delay_ms: 30", "b, key_delay_ms: 60000"),
+        ] {
+            assert!(matches!(
+                parse_profile(&yaml),
+                Err(ProfileError::V1Profile(crate::v1::Error::KeyDelayTooLong(
+                    _
+                )))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_profile_rule_target() {
+        let yaml = "
+version: 1
+rules:
+  common:
+    buttons:
+      a: { keystroke: space, target: com.apple.Music }
+      b: { macros: [cmd+c, cmd+v], target: frontmost }
+";
+        let profile = parse_profile(yaml).expect("target should parse");
+        let rule = |button| {
+            let mut chord = crate::ButtonChord::empty();
+            chord.insert(button);
+            profile.rules["common"].buttons[&chord].target.clone()
+        };
+        assert_eq!(
+            rule(gamacros_gamepad::Button::A).as_deref(),
+            Some("com.apple.Music")
+        );
+        assert_eq!(rule(gamacros_gamepad::Button::B), None);
+
+        for invalid in [
+            "{ shell: make, target: com.apple.Music }",
+            "{ keystroke: a, target: '' }",
+        ] {
+            let yaml = format!(
+                "version: 1\nrules:\n  common:\n    buttons:\n      a: {invalid}\n"
+            );
+            assert!(matches!(
+                parse_profile(&yaml),
+                Err(ProfileError::V1Profile(crate::v1::Error::InvalidTarget(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_profile_interpolates_vars() {
+        let yaml = r#"
+version: 1
+vars:
+  editor: com.microsoft.VSCode
+  mod: cmd
+groups:
+  ide: ["${editor}"]
+rules:
+  $ide:
+    buttons:
+      a: { keystroke: "${mod}+s" }
+"#;
+        let profile = parse_profile(yaml).expect("profile should parse");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        assert!(matches!(
+            &profile.rules["com.microsoft.VSCode"].buttons[&chord].action,
+            crate::ButtonAction::Keystroke(k) if **k == "cmd+s".parse().unwrap()
+        ));
+
+        let yaml = "version: 1\nrules:\n  common:\n    buttons:\n      a: { keystroke: \"${nope}\" }\n";
+        assert!(matches!(
+            parse_profile(yaml),
+            Err(ProfileError::V1Profile(crate::v1::Error::UnknownVar(name, _)))
+                if name == "nope"
+        ));
+    }
+
+    #[test]
+    fn missing_home_is_env_var_not_set() {
+        let home = ProfileError::V1Profile(crate::v1::Error::HomeNotSet(
+            "cd ~".to_string(),
+        ));
+        let error = ProfileError::InFile("gc_profile.yaml".into(), Box::new(home));
+        assert!(matches!(
+            WorkspaceError::from(error),
+            WorkspaceError::EnvVarNotSet(name) if name == "HOME"
+        ));
+        let error = ProfileError::UnsupportedVersion(2);
+        assert!(matches!(
+            WorkspaceError::from(error),
+            WorkspaceError::ProfileError(_)
+        ));
+    }
+
+    #[test]
+    fn host_profile_overrides_base_rules() {
+        assert_eq!(short_hostname("Studio.local").as_deref(), Some("studio"));
+        assert_eq!(short_hostname(""), None);
+        let Some(host) = hostname() else {
+            return;
+        };
+
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-host-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let profile_path = dir.join("gc_profile.yaml");
+        let host_path = host_profile_path(&profile_path, &host);
+        assert_eq!(host_path, dir.join(format!("gc_profile.{host}.yaml")));
+        std::fs::write(
+            &profile_path,
+            "version: 1\nrules:\n  common:\n    buttons:\n      a: { keystroke: enter }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &host_path,
+            "rules:\n  common:\n    buttons:\n      a: { keystroke: space }\n",
+        )
+        .unwrap();
+        let profile = load_profile(&profile_path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let profile = profile.expect("profile should load");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        assert!(matches!(
+            &profile.rules["common"].buttons[&chord].action,
+            crate::ButtonAction::Keystroke(k) if **k == "space".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn load_profile_merges_fragments_in_order() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-fragments-{}", std::process::id()));
+        let fragments = dir.join(FRAGMENTS_DIR_NAME);
+        std::fs::create_dir_all(fragments.join("ide")).unwrap();
+        let profile_path = dir.join("gc_profile.yaml");
+        std::fs::write(
+            &profile_path,
+            "version: 1\ngroups:\n  ide: [com.microsoft.VSCode]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            fragments.join("a.yaml"),
+            "rules:\n  $ide:\n    buttons:\n      a: { keystroke: enter }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            fragments.join("ide/b.yml"),
+            "rules:\n  $ide:\n    buttons:\n      a: { keystroke: space }\n",
+        )
+        .unwrap();
+
+        let profile = load_profile(&profile_path);
+
+        std::fs::write(
+            fragments.join("c.yaml"),
+            "rules:\n  x: { buttons: { z: {} } }\n",
+        )
+        .unwrap();
+        let broken = load_profile(&profile_path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let profile = profile.expect("profile should load");
+        let mut chord = crate::ButtonChord::empty();
+        chord.insert(gamacros_gamepad::Button::A);
+        let rule = &profile.rules["com.microsoft.VSCode"].buttons[&chord];
+        assert!(matches!(
+            &rule.action,
+            crate::ButtonAction::Keystroke(k) if **k == "space".parse().unwrap()
+        ));
+        assert_eq!(rule.source.to_string(), "$ide in profiles.d/ide/b.yml");
+        assert!(matches!(
+            broken,
+            Err(ProfileError::InFile(path, _)) if path.ends_with("c.yaml")
+        ));
+    }
+
+    #[test]
+    fn load_profile_reports_all_broken_fragments() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-broken-fragments-{}", std::process::id()));
+        let fragments = dir.join(FRAGMENTS_DIR_NAME);
+        std::fs::create_dir_all(&fragments).unwrap();
+        let profile_path = dir.join("gc_profile.yaml");
+        std::fs::write(&profile_path, "version: 1\n").unwrap();
+        for name in ["a", "b", "c", "d"] {
+            let button = if name == "b" || name == "d" { "z" } else { "a" };
+            std::fs::write(
+                fragments.join(format!("{name}.yaml")),
+                format!("rules:\n  x: {{ buttons: {{ {button}: {{ keystroke: a }} }} }}\n"),
+            )
+            .unwrap();
+        }
+
+        let broken = load_profile(&profile_path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let Err(ProfileError::Multiple(errors)) = broken else {
+            panic!("both broken fragments should be reported");
+        };
+        let files: Vec<_> = errors
+            .iter()
+            .map(|e| match e {
+                ProfileError::InFile(path, _) => path.rsplit('/').next().unwrap(),
+                _ => panic!("errors should name their file"),
+            })
+            .collect();
+        assert_eq!(files, ["b.yaml", "d.yaml"]);
+    }
 }
@@ -1,5 +1,5 @@
 use std::time::Duration;
-use std::{fs, path::Path};
+use std::path::Path;
 use std::sync::mpsc;
 
 use thiserror::Error;
@@ -8,7 +8,8 @@ use notify_debouncer_mini::{
     new_debouncer_opt, DebounceEventResult, DebouncedEventKind, Debouncer,
 };
 
-use crate::profile_parse::parse_profile;
+use crate::cache::load_profile_cached;
+use crate::profile_parse::FRAGMENTS_DIR_NAME;
 use crate::profile::{ProfileError, Profile};
 
 #[derive(Error, Debug)]
@@ -21,6 +22,8 @@ pub enum WatcherError {
     Parse(#[from] ProfileError),
 }
 
+// Profile changes are rare, boxing would only add noise at use sites.
+#[allow(clippy::large_enum_variant)]
 pub enum ProfileEvent {
     Changed(Profile),
     Removed,
@@ -31,23 +34,54 @@ type ProfileEventSender = mpsc::Sender<ProfileEvent>;
 pub type ProfileEventReceiver = mpsc::Receiver<ProfileEvent>;
 
 fn send_profile_event(path: &Path, tx: &ProfileEventSender) {
-    match fs::read_to_string(path) {
-        Ok(content) => match parse_profile(&content) {
-            Ok(workspace) => {
-                let _ = tx.send(ProfileEvent::Changed(workspace));
-            }
-            Err(e) => {
-                let error = WatcherError::Parse(e);
-                let _ = tx.send(ProfileEvent::Error(error));
-            }
-        },
+    match load_profile_cached(path) {
+        Ok(workspace) => {
+            let _ = tx.send(ProfileEvent::Changed(workspace));
+        }
         Err(e) => {
-            let error = WatcherError::Io(e);
+            let error = WatcherError::Parse(e);
             let _ = tx.send(ProfileEvent::Error(error));
         }
     };
 }
 
+/// Whether a change of `changed` affects the profile at `profile_path`.
+fn affects_profile(profile_path: &Path, changed: &Path) -> bool {
+    let Some(dir) = profile_path.parent() else {
+        return changed == profile_path;
+    };
+    let Ok(relative) = changed.strip_prefix(dir) else {
+        // Watcher backends may report canonical paths
+        return changed.file_name() == profile_path.file_name()
+            || changed
+                .file_name()
+                .is_some_and(|name| is_host_profile(profile_path, Path::new(name)))
+            || changed
+                .components()
+                .any(|c| c.as_os_str() == FRAGMENTS_DIR_NAME);
+    };
+    relative == Path::new(profile_path.file_name().unwrap_or_default())
+        || relative.starts_with(FRAGMENTS_DIR_NAME)
+        || is_host_profile(profile_path, relative)
+}
+
+/// Whether `relative` may be a host override of the profile, e.g.
+/// `gc_profile.studio.yaml`.
+fn is_host_profile(profile_path: &Path, relative: &Path) -> bool {
+    let (Some(stem), Some(name)) = (
+        profile_path.file_stem().and_then(|s| s.to_str()),
+        relative.to_str(),
+    ) else {
+        return false;
+    };
+    name.strip_prefix(stem)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .is_some_and(|rest| {
+            !rest.contains('/')
+                && (rest.ends_with(".yaml") || rest.ends_with(".yml"))
+        })
+}
+
 #[allow(dead_code)]
 pub struct ProfileWatcher<W: notify::Watcher> {
     watcher: Debouncer<W>,
@@ -69,18 +103,20 @@ impl<W: notify::Watcher> ProfileWatcher<W> {
             debouncer_config,
             move |events: DebounceEventResult| match events {
                 Ok(events) => {
-                    for event in events {
-                        match event.kind {
+                    let changed = events.iter().any(|event| {
+                        matches!(
+                            event.kind,
                             DebouncedEventKind::Any
-                            | DebouncedEventKind::AnyContinuous => {
-                                if !path_c.exists() {
-                                    let _ = tx_c.send(ProfileEvent::Removed);
-                                } else {
-                                    send_profile_event(&path_c, &tx_c);
-                                }
-                            }
-                            _ => {}
-                        }
+                                | DebouncedEventKind::AnyContinuous
+                        ) && affects_profile(&path_c, &event.path)
+                    });
+                    if !changed {
+                        return;
+                    }
+                    if !path_c.exists() {
+                        let _ = tx_c.send(ProfileEvent::Removed);
+                    } else {
+                        send_profile_event(&path_c, &tx_c);
                     }
                 }
                 Err(event) => {
@@ -90,9 +126,12 @@ impl<W: notify::Watcher> ProfileWatcher<W> {
             },
         )?;
 
-        debouncer
-            .watcher()
-            .watch(path, RecursiveMode::NonRecursive)?;
+        // Watch the whole directory, so fragments and a recreated profile are seen
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        debouncer.watcher().watch(dir, RecursiveMode::Recursive)?;
 
         Ok(Self { watcher: debouncer })
     }
@@ -113,3 +152,27 @@ impl<W: notify::Watcher> ProfileWatcher<W> {
         Ok((Self::new_with_sender(path, tx)?, rx))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_and_fragment_changes_affect_profile() {
+        let profile = Path::new("/ws/gc_profile.yaml");
+        assert!(affects_profile(profile, Path::new("/ws/gc_profile.yaml")));
+        assert!(affects_profile(
+            profile,
+            Path::new("/ws/profiles.d/ide/jb.yaml")
+        ));
+        assert!(affects_profile(
+            profile,
+            Path::new("/ws/gc_profile.studio.yaml")
+        ));
+        assert!(!affects_profile(profile, Path::new("/ws/api.sock")));
+        assert!(!affects_profile(
+            profile,
+            Path::new("/ws/other/gc_profile.yaml")
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use ahash::AHashMap;
+
+use super::Error;
+
+const HOME_VAR: &str = "HOME";
+
+/// Expands `~`, `$HOME` and profile `vars` in shell commands and paths,
+/// and interpolates `${var}` in other strings, see [`Expander::interpolate`].
+///
+/// Other `$NAME` references are left untouched, so the shell still
+/// expands them when the command runs.
+pub(crate) struct Expander {
+    home: Option<String>,
+    vars: AHashMap<String, String>,
+}
+
+impl Expander {
+    /// Creates an expander using `HOME` from the environment.
+    pub(crate) fn from_env(vars: &AHashMap<String, String>) -> Result<Self, Error> {
+        Self::new(std::env::var(HOME_VAR).ok(), vars)
+    }
+
+    /// Creates an expander. Variable values may use `~`, `$HOME` and other
+    /// variables themselves, as long as they don't reference each other in
+    /// a cycle.
+    pub(crate) fn new(
+        home: Option<String>,
+        vars: &AHashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let mut expander = Self {
+            home,
+            vars: AHashMap::with_capacity(vars.len()),
+        };
+        // Sorted, so the same cycle is always reported the same way
+        let mut names: Vec<_> = vars.keys().collect();
+        names.sort();
+        for name in names {
+            expander.resolve(name, vars, &mut Vec::new())?;
+        }
+        Ok(expander)
+    }
+
+    /// Expands the variable `name` after the variables its value uses.
+    fn resolve<'a>(
+        &mut self,
+        name: &'a str,
+        raw: &'a AHashMap<String, String>,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<(), Error> {
+        if self.vars.contains_key(name) {
+            return Ok(());
+        }
+        if let Some(start) = stack.iter().position(|entry| *entry == name) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(name);
+            return Err(Error::VarCycle(cycle.join(" -> ")));
+        }
+        let value = &raw[name];
+        stack.push(name);
+        for reference in references(value) {
+            if let Some((reference, _)) = raw.get_key_value(reference) {
+                self.resolve(reference, raw, stack)?;
+            }
+        }
+        stack.pop();
+        let expanded = self.expand(value)?;
+        self.vars.insert(name.to_string(), expanded);
+        Ok(())
+    }
+
+    /// Replaces `${var}` with profile variables in strings that are not
+    /// shell commands, e.g. keystrokes and app ids. Unlike [`Self::expand`],
+    /// unknown variables are an error and `$name` without braces is kept,
+    /// as selectors use it for groups.
+    pub(crate) fn interpolate(&self, input: &str) -> Result<String, Error> {
+        if !input.contains("${") {
+            return Ok(input.to_string());
+        }
+        let mut out = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(start) = rest.find("${") {
+            out.push_str(&rest[..start]);
+            match parse_var(&rest[start + 1..]) {
+                (Some(name), len) => {
+                    let value = self.vars.get(name).ok_or_else(|| {
+                        Error::UnknownVar(name.to_string(), input.to_string())
+                    })?;
+                    out.push_str(value);
+                    rest = &rest[start + 1 + len..];
+                }
+                (None, _) => {
+                    out.push('$');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    pub(crate) fn expand(&self, input: &str) -> Result<String, Error> {
+        let mut out = String::with_capacity(input.len());
+        let mut rest = input;
+        let mut word_start = true;
+        while let Some(c) = rest.chars().next() {
+            if c == '~' && word_start && ends_tilde_prefix(&rest[1..]) {
+                out.push_str(self.home(input)?);
+                rest = &rest[1..];
+            } else if c == '$' {
+                let (name, len) = parse_var(&rest[1..]);
+                let value = name.map(|name| self.lookup(name, input));
+                match value.transpose()?.flatten() {
+                    Some(value) => {
+                        out.push_str(value);
+                        rest = &rest[1 + len..];
+                    }
+                    None => {
+                        out.push('$');
+                        rest = &rest[1..];
+                    }
+                }
+            } else {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+            word_start = c.is_whitespace() || c == '=' || c == ':';
+        }
+        Ok(out)
+    }
+
+    fn lookup(&self, name: &str, input: &str) -> Result<Option<&str>, Error> {
+        if name == HOME_VAR {
+            return self.home(input).map(Some);
+        }
+        Ok(self.vars.get(name).map(String::as_str))
+    }
+
+    /// `HOME` from vars, then from the environment.
+    fn home(&self, input: &str) -> Result<&str, Error> {
+        self.vars
+            .get(HOME_VAR)
+            .or(self.home.as_ref())
+            .map(String::as_str)
+            .ok_or_else(|| Error::HomeNotSet(input.to_string()))
+    }
+}
+
+/// Names of the variables referenced in `input`.
+fn references(input: &str) -> impl Iterator<Item = &str> {
+    input.match_indices('$').filter_map(|(index, _)| {
+        let (name, _) = parse_var(&input[index + 1..]);
+        name
+    })
+}
+
+/// `~` expands only when it is the whole word or followed by a path.
+fn ends_tilde_prefix(after: &str) -> bool {
+    after
+        .chars()
+        .next()
+        .map_or(true, |c| c == '/' || c.is_whitespace())
+}
+
+/// Parses `NAME` or `{NAME}` after a `$`.
+/// Returns the name and the length of the consumed input.
+fn parse_var(input: &str) -> (Option<&str>, usize) {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    if let Some(braced) = input.strip_prefix('{') {
+        return match braced.find('}') {
+            Some(end) if end > 0 && braced[..end].chars().all(is_name_char) => {
+                (Some(&braced[..end]), end + 2)
+            }
+            _ => (None, 0),
+        };
+    }
+    let len = input
+        .find(|c: char| !is_name_char(c))
+        .unwrap_or(input.len());
+    if len == 0 || input.starts_with(|c: char| c.is_ascii_digit()) {
+        return (None, 0);
+    }
+    (Some(&input[..len]), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expander() -> Expander {
+        let mut vars = AHashMap::new();
+        vars.insert("notes".to_string(), "~/Notes".to_string());
+        Expander::new(Some("/Users/me".to_string()), &vars).unwrap()
+    }
+
+    #[test]
+    fn expands_home_and_vars() {
+        let expander = expander();
+        assert_eq!(
+            expander
+                .expand("open ~/Downloads $notes ${HOME}/x")
+                .unwrap(),
+            "open /Users/me/Downloads /Users/me/Notes /Users/me/x"
+        );
+        assert_eq!(expander.expand("~").unwrap(), "/Users/me");
+    }
+
+    #[test]
+    fn keeps_unknown_references() {
+        let expander = expander();
+        assert_eq!(
+            expander.expand("echo $USER $1 ~user a~b ${} $").unwrap(),
+            "echo $USER $1 ~user a~b ${} $"
+        );
+    }
+
+    #[test]
+    fn resolves_nested_vars_and_detects_cycles() {
+        let mut vars = AHashMap::new();
+        vars.insert("mod".to_string(), "cmd".to_string());
+        vars.insert("save".to_string(), "${mod}+s".to_string());
+        let expander = Expander::new(None, &vars).unwrap();
+        assert_eq!(expander.interpolate("${save}").unwrap(), "cmd+s");
+        assert_eq!(expander.interpolate("$ide | ${mod}").unwrap(), "$ide | cmd");
+        assert!(matches!(
+            expander.interpolate("${missing}+a"),
+            Err(Error::UnknownVar(name, _)) if name == "missing"
+        ));
+
+        vars.insert("a".to_string(), "$b".to_string());
+        vars.insert("b".to_string(), "${a}".to_string());
+        assert!(matches!(
+            Expander::new(None, &vars),
+            Err(Error::VarCycle(cycle)) if cycle == "a -> b -> a"
+        ));
+    }
+
+    #[test]
+    fn missing_home_is_an_error() {
+        let expander = Expander::new(None, &AHashMap::new()).unwrap();
+        assert!(matches!(
+            expander.expand("cd ~"),
+            Err(Error::HomeNotSet(input)) if input == "cd ~"
+        ));
+        assert_eq!(expander.expand("echo $PATH").unwrap(), "echo $PATH");
+
+        let mut vars = AHashMap::new();
+        vars.insert("HOME".to_string(), "/home/me".to_string());
+        let expander = Expander::new(None, &vars).unwrap();
+        assert_eq!(expander.expand("cd ~").unwrap(), "cd /home/me");
+    }
+}
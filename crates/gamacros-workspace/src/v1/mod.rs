@@ -3,10 +3,15 @@ mod parse;
 mod profile;
 mod selector;
 mod combo;
+mod expand;
+mod schema;
 
 use thiserror::Error;
 
-pub use profile::ProfileV1;
+pub use profile::{ProfileV1, ProfileV1Fragment};
+pub use schema::PROFILE_SCHEMA;
+pub(crate) use parse::{axis_name, button_name, parse_button_name};
+pub(crate) use strings::COMMON_BUNDLE_ID;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -18,6 +23,26 @@ pub enum Error {
     InvalidTrigger(String),
     #[error("invalid actions for {0}")]
     InvalidActions(String),
+    #[error("toggle requires a keystroke action for {0}")]
+    InvalidToggle(String),
+    #[error("invalid shell limits for {0}")]
+    InvalidShellLimits(String),
+    #[error("key delay requires a keystroke or macros action for {0}")]
+    InvalidKeyDelay(String),
+    #[error("key delay of {0}ms is longer than the maximum of {max}ms", max = parse::MAX_KEY_DELAY_MS)]
+    KeyDelayTooLong(u64),
+    #[error("invalid target for {0}: requires a keystroke or macros action and frontmost or a bundle id")]
+    InvalidTarget(String),
+    #[error("invalid overflow policy: {0}")]
+    InvalidOverflow(String),
+    #[error("invalid osc settings: {0}")]
+    InvalidOsc(String),
+    #[error("invalid websocket address, expected a loopback host:port: {0}")]
+    InvalidWebSocket(String),
+    #[error("invalid http address, expected a loopback host:port: {0}")]
+    InvalidHttp(String),
+    #[error("invalid title regex {0}: {1}")]
+    InvalidTitle(String, String),
     #[error("invalid id: {0} -> {1}")]
     InvalidId(String, String),
     #[error("invalid button: {0}")]
@@ -26,12 +51,48 @@ pub enum Error {
     InvalidStick(String),
     #[error("invalid stick side: {0}")]
     InvalidStickSide(String),
+    #[error("invalid stick toggle: {0}")]
+    InvalidStickToggle(String),
+    #[error("invalid while_held stick modes: {0}")]
+    InvalidHeldStick(String),
+    #[error("invalid gamepad backend: {0}")]
+    InvalidBackend(String),
+    #[error("invalid keyboard backend: {0}")]
+    InvalidKeyboardBackend(String),
+    #[error("invalid log level: {0}")]
+    InvalidLog(String),
+    #[error("invalid controller mapping: {0}")]
+    InvalidMapping(String),
     #[error("invalid axis: {0}")]
     InvalidAxis(String),
+    #[error("invalid HID usage \"{0}\", expected page:usage")]
+    InvalidHidUsage(String),
     #[error("key parse error: {0}")]
     KeyParse(String),
+    #[error("unknown system action \"{0}\"")]
+    InvalidSystemAction(String),
+    #[error("unknown window action \"{0}\"")]
+    InvalidWindowAction(String),
+    #[error("unknown mouse target \"{0}\"")]
+    InvalidMouseTarget(String),
+    #[error("invalid profile set \"{0}\", names must be unique and not next, prev or default")]
+    InvalidProfileSet(String),
+    #[error("unknown haptic feedback \"{0}\", expected light, medium or off")]
+    InvalidHapticFeedback(String),
+    #[error("unknown remap preset \"{0}\"")]
+    InvalidRemapPreset(String),
+    #[error("empty serial number of controller {0:04x}:{1:04x}")]
+    InvalidControllerSerial(u16, u16),
+    #[error("invalid space \"{0}\", expected next, prev or 1-16")]
+    InvalidSpace(String),
     #[error("no profile matches path \"{0}\"")]
     ProfileNotFound(String),
+    #[error("HOME is not set, it is needed to expand \"{0}\"")]
+    HomeNotSet(String),
+    #[error("unknown variable \"{0}\" in \"{1}\", define it in vars")]
+    UnknownVar(String, String),
+    #[error("variables reference each other in a cycle: {0}")]
+    VarCycle(String),
     #[error("selector error: {0}")]
     BadSelector(#[from] selector::SelectorError),
 }
@@ -1,23 +1,59 @@
+use std::cmp::Reverse;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use ahash::AHashMap;
-use gamacros_control::KeyCombo;
-use gamacros_gamepad::Button;
+use gamacros_control::{Key, KeyCombo, MouseButton, ScrollUnit};
+use regex::Regex;
+use gamacros_gamepad::{
+    is_valid_mapping, Axis as CtrlAxis, AxisCalibration, Button, Calibration,
+    ControllerMappings, HidButton, HidDevice,
+};
 
-use crate::v1::profile::{ProfileV1ButtonRule, ProfileV1Stick};
+use crate::v1::profile::{
+    ProfileV1ButtonRule, ProfileV1Calibration, ProfileV1Osc, ProfileV1ReleaseAction,
+    ProfileV1Stick, ProfileV1Sticks,
+};
 use crate::profile::{
-    AppRules, ArrowsParams, Axis, ButtonAction, ButtonRule, ButtonRules,
-    ControllerSettings, ControllerSettingsMap, Macros, MouseParams, Profile,
-    RuleMap, ScrollParams, StepperParams, StickMode, StickRules, StickSide,
+    AppRules, ArrowsParams, Axis, AxisFilter, ButtonAction, CalibrationMap,
+    DeadzoneShape, DwellClick, ButtonRule, GlobalRules, ButtonRules, HapticFeedback,
+    KeyRules, PatternRules, TitleRules, ControllerSettings, ControllerSettingsMap,
+    SerialSettingsMap, Macros, MouseParams, OscSettings, OverlaySettings, Profile,
+    RuleMap, ScrollParams, RuleSource, SeekParams, ProfileSet, SetTarget,
+    ShellLimits, ShellOverflow, SpeechSettings, RepeatCurve, LogLevel, LogLevels,
+    LogModule, WatchdogSettings, WebSocketSettings, StepperParams, StickMode,
+    StickRule, StickRules, StickSide, StickToggle, HeldStickMode,
 };
-use crate::ButtonChord;
+use crate::{AppPattern, ButtonChord};
 
 use super::Error;
-use super::profile::{ProfileV1, ProfileV1App, ProfileV1ControllerSettings};
-use super::strings::COMMON_BUNDLE_ID;
+use super::expand::Expander;
+use super::profile::{
+    ProfileV1, ProfileV1App, ProfileV1ControllerSettings, ProfileV1Global,
+    ProfileV1Set,
+};
 use super::selector::Selector;
 use super::combo::parse_terms_with_delim;
 
+/// Name used for global rules in error messages.
+const GLOBAL_RULES_NAME: &str = "global";
+
+/// Default chord settle window.
+const DEFAULT_CHORD_WINDOW_MS: u64 = 50;
+
+/// Longest wait between injected key events. Events of a combo or macro
+/// wait in the action runner's queue, so longer delays would only hold up
+/// every key action after them.
+pub(super) const MAX_KEY_DELAY_MS: u64 = 1000;
+
+/// Default prefix of OSC message addresses.
+const DEFAULT_OSC_PREFIX: &str = "/gamacros";
+
+/// Default time an overlay flash stays on screen.
+const DEFAULT_OVERLAY_DURATION_MS: u64 = 1000;
+
 impl ProfileV1 {
     pub fn parse(&self) -> Result<Profile, Error> {
         if self.version != 1 {
@@ -25,69 +61,229 @@ impl ProfileV1 {
             panic!("unsupported version: {}", self.version);
         }
 
+        let expander = Expander::from_env(&self.vars)?;
+        let groups = interpolate_groups(&self.groups, &expander)?;
         let mut rules: RuleMap = AHashMap::new();
 
-        let common_rules = self
-            .rules
-            .get(COMMON_BUNDLE_ID)
-            .map(|r| parse_app_rules(r.clone(), COMMON_BUNDLE_ID))
-            .transpose()?;
-
-        if let Some(common_rules) = common_rules.clone() {
-            rules.insert(COMMON_BUNDLE_ID.into(), common_rules);
-        }
-
+        let mut patterns: Vec<PatternRules> = Vec::new();
         for (selector, app_actions) in self.rules.clone().into_iter() {
+            let selector = expander.interpolate(&selector)?;
             let parsed_selector = Selector::parse(&selector)?;
-            let bundle_ids = parsed_selector.materialize(&self.groups)?;
-            let app_rules = parse_app_rules(app_actions, &selector)?;
+            let bundle_ids = parsed_selector.materialize(&groups)?;
+            let app_rules =
+                parse_app_rules(app_actions, &selector, &expander, &groups)?;
 
             for bundle_id in bundle_ids {
-                // Using common rules as default. If there are no common rules, use empty rules.
-                // If there are common rules, merge them with the app rules.
-                let current_rules = {
-                    if let Some(current_rules) = rules.get_mut(&bundle_id) {
-                        current_rules.buttons.extend(app_rules.buttons.clone());
-                        current_rules.sticks.extend(app_rules.sticks.clone());
-
-                        current_rules.clone()
-                    } else {
-                        let mut default_rules =
-                            common_rules.clone().unwrap_or_default();
-                        default_rules.buttons.extend(app_rules.buttons.clone());
-                        default_rules.sticks.extend(app_rules.sticks.clone());
-
-                        rules.insert(bundle_id.clone(), default_rules.clone());
-                        default_rules
-                    }
-                };
-
-                rules.insert(bundle_id, current_rules);
+                // Common rules are layered under these when resolving
+                if AppPattern::is_pattern(&bundle_id) {
+                    patterns.push(PatternRules {
+                        pattern: AppPattern::new(&bundle_id),
+                        rules: app_rules.clone(),
+                    });
+                    continue;
+                }
+                rules.entry(bundle_id).or_default().extend(&app_rules);
             }
         }
 
-        let controllers = parse_controller_settings(&self.controllers)?;
-        let blacklist = self.blacklist.clone().into_iter().collect();
+        // Rules are unordered, so precedence between patterns comes from specificity
+        patterns.sort_by(|a, b| {
+            a.pattern
+                .specificity()
+                .cmp(&b.pattern.specificity())
+                .then_with(|| a.pattern.as_str().cmp(b.pattern.as_str()))
+        });
+
+        let (controllers, serial_controllers) =
+            parse_controller_settings(&self.controllers)?;
+        let blacklist = self
+            .blacklist
+            .iter()
+            .map(|id| expander.interpolate(id))
+            .collect::<Result<_, _>>()?;
+        let transient_apps = self
+            .transient_apps
+            .iter()
+            .map(|id| expander.interpolate(id).map(Into::into))
+            .collect::<Result<_, _>>()?;
+        let gamepad_backend = self
+            .gamepad_backend
+            .as_deref()
+            .map(|name| name.parse().map_err(|_| Error::InvalidBackend(name.into())))
+            .transpose()?;
+        let keyboard_backend = self
+            .keyboard_backend
+            .as_deref()
+            .map(|name| {
+                name.parse()
+                    .map_err(|_| Error::InvalidKeyboardBackend(name.into()))
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let calibration = parse_calibration(&self.calibration)?;
+        let hid_devices = parse_hid_devices(&self.controllers)?;
+        let controller_mappings = parse_controller_mappings(
+            self.controller_db.as_deref(),
+            &self.controller_mappings,
+            &expander,
+        )?;
+        let osc = self.osc.as_ref().map(parse_osc).transpose()?;
+        let overlay = self.overlay.as_ref().map(|raw| OverlaySettings {
+            actions: raw.actions,
+            duration: Duration::from_millis(
+                raw.duration_ms.unwrap_or(DEFAULT_OVERLAY_DURATION_MS),
+            ),
+        });
+        let speech = self.speech.as_ref().map(|raw| SpeechSettings {
+            voice: raw.voice.clone().filter(|voice| !voice.trim().is_empty()),
+        });
+        let websocket = self
+            .websocket
+            .as_ref()
+            .map(|raw| {
+                parse_socket_addr(&raw.address)
+                    .filter(|address| address.ip().is_loopback())
+                    .map(|address| WebSocketSettings {
+                        address,
+                        origins: raw.origins.clone(),
+                    })
+                    .ok_or_else(|| Error::InvalidWebSocket(raw.address.clone()))
+            })
+            .transpose()?;
+        let http = self
+            .http
+            .as_ref()
+            .map(|raw| {
+                parse_socket_addr(&raw.address)
+                    .filter(|address| address.ip().is_loopback())
+                    .ok_or_else(|| Error::InvalidHttp(raw.address.clone()))
+            })
+            .transpose()?;
+        let log = parse_log_levels(&self.log)?;
+        let watchdog =
+            self.watchdog
+                .as_ref()
+                .map_or_else(WatchdogSettings::default, |raw| WatchdogSettings {
+                    timeout: raw.timeout_ms.map_or(
+                        WatchdogSettings::default().timeout,
+                        Duration::from_millis,
+                    ),
+                    restart: raw.restart,
+                });
+        let global = self
+            .global
+            .clone()
+            .map(|raw| parse_global(raw, &expander, &groups))
+            .transpose()?;
+        let sets = self
+            .sets
+            .iter()
+            .map(|raw| parse_set(raw, &expander, &groups))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (index, set) in sets.iter().enumerate() {
+            if sets[..index].iter().any(|other| other.name == set.name) {
+                return Err(Error::InvalidProfileSet(set.name.to_string()));
+            }
+        }
+        let shell = self
+            .shell
+            .as_deref()
+            .map(|shell| expander.expand(shell).map(Into::into))
+            .transpose()?;
 
         Ok(Profile {
             blacklist,
             controllers,
+            serial_controllers,
             rules,
-            shell: self.shell.clone(),
+            shell,
+            gamepad_backend,
+            keyboard_backend,
+            ignore_virtual: self.ignore_virtual,
+            trigger_buttons: self.trigger_buttons.unwrap_or(true),
+            pause_on_fullscreen: self.pause_on_fullscreen,
+            announce_on_connect: self.announce_on_connect,
+            calibration,
+            controller_mappings,
+            hid_devices,
+            chord_window: Duration::from_millis(
+                self.chord_window_ms.unwrap_or(DEFAULT_CHORD_WINDOW_MS),
+            ),
+            ignore_own_activity: Duration::from_millis(self.ignore_own_activity_ms),
+            activation_debounce: Duration::from_millis(self.activation_debounce_ms),
+            transient_apps,
+            reconnect_grace: Duration::from_millis(self.reconnect_grace_ms),
+            axis_interval: Duration::from_millis(self.axis_interval_ms),
+            key_delay: parse_key_delay(self.key_delay_ms)?,
+            global,
+            patterns,
+            sets,
+            osc,
+            overlay,
+            speech,
+            websocket,
+            http,
+            log,
+            watchdog,
         })
     }
 }
 
+fn parse_log_levels(raw: &AHashMap<String, String>) -> Result<LogLevels, Error> {
+    raw.iter()
+        .map(|(name, level)| {
+            let module = match name.as_str() {
+                "gamepad" => LogModule::Gamepad,
+                "activity" => LogModule::Activity,
+                "actions" => LogModule::Actions,
+                "api" => LogModule::Api,
+                other => {
+                    return Err(Error::InvalidLog(format!(
+                        "unknown module: {other}"
+                    )))
+                }
+            };
+            let level = match level.to_lowercase().as_str() {
+                "off" => LogLevel::Off,
+                "error" => LogLevel::Error,
+                "warn" => LogLevel::Warn,
+                "info" => LogLevel::Info,
+                "debug" => LogLevel::Debug,
+                "trace" => LogLevel::Trace,
+                other => return Err(Error::InvalidLog(format!("{name}: {other}"))),
+            };
+            Ok((module, level))
+        })
+        .collect()
+}
+
+/// Parse controller settings into the ones by model and the ones of single
+/// controllers picked by serial number.
 fn parse_controller_settings(
     raw: &Vec<ProfileV1ControllerSettings>,
-) -> Result<ControllerSettingsMap, Error> {
+) -> Result<(ControllerSettingsMap, SerialSettingsMap), Error> {
     let mut settings: ControllerSettingsMap = AHashMap::new();
+    let mut by_serial: SerialSettingsMap = AHashMap::new();
     for raw_settings in raw {
         let device_id = (raw_settings.vid, raw_settings.pid);
         let device_settings = parse_device_remap(raw_settings)?;
-        settings.insert(device_id, device_settings);
+        match raw_settings.serial.as_deref() {
+            Some("") => {
+                return Err(Error::InvalidControllerSerial(
+                    raw_settings.vid,
+                    raw_settings.pid,
+                ))
+            }
+            Some(serial) => {
+                by_serial.insert((device_id, serial.into()), device_settings);
+            }
+            None => {
+                settings.insert(device_id, device_settings);
+            }
+        }
     }
-    Ok(settings)
+    Ok((settings, by_serial))
 }
 
 /// Parse a v1 device remap.
@@ -95,16 +291,234 @@ fn parse_device_remap(
     raw: &ProfileV1ControllerSettings,
 ) -> Result<ControllerSettings, Error> {
     let mut remap = AHashMap::new();
+    if let Some(presets) = raw.preset.as_deref() {
+        let names = parse_terms_with_delim(presets, '+')
+            .map_err(|_| Error::InvalidRemapPreset(presets.to_string()))?;
+        for name in names {
+            remap.extend(remap_preset(name.trim())?.iter().copied());
+        }
+    }
+    // Explicit pairs override the presets
     for (k, v) in raw.remap.iter() {
         let from = parse_button_name(k)?;
         let to = parse_button_name(v)?;
         remap.insert(from, to);
     }
-    Ok(ControllerSettings { mapping: remap })
+    let mut axes = AHashMap::new();
+    for (k, v) in raw.axes.iter() {
+        axes.insert(parse_joystick_axis(k)?, parse_axis_name(v)?);
+    }
+    Ok(ControllerSettings {
+        mapping: remap,
+        axes,
+    })
+}
+
+/// Parse HID switches of controllers into the devices to read.
+fn parse_hid_devices(
+    raw: &[ProfileV1ControllerSettings],
+) -> Result<Vec<HidDevice>, Error> {
+    let mut devices = Vec::new();
+    for raw_settings in raw.iter().filter(|raw| !raw.hid.is_empty()) {
+        let mut buttons = Vec::with_capacity(raw_settings.hid.len());
+        for (usage, button) in raw_settings.hid.iter() {
+            let (usage_page, usage) = parse_hid_usage(usage)?;
+            buttons.push(HidButton {
+                usage_page,
+                usage,
+                button: parse_button_name(button)?,
+            });
+        }
+        // Keys are unordered in YAML, keep the order stable
+        buttons.sort_by_key(|b| (b.usage_page, b.usage));
+        devices.push(HidDevice {
+            device: (raw_settings.vid, raw_settings.pid),
+            buttons,
+        });
+    }
+    Ok(devices)
+}
+
+/// Parse a HID usage written as `page:usage`, e.g. `0x0c:0xe9` or `9:1`.
+fn parse_hid_usage(raw: &str) -> Result<(u16, u16), Error> {
+    let number = |text: &str| {
+        let text = text.trim();
+        match text.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => text.parse().ok(),
+        }
+    };
+    raw.split_once(':')
+        .and_then(|(page, usage)| Some((number(page)?, number(usage)?)))
+        .ok_or_else(|| Error::InvalidHidUsage(raw.to_string()))
+}
+
+/// Parse a raw joystick axis name such as `jaxis:2` into its index.
+fn parse_joystick_axis(name: &str) -> Result<u8, Error> {
+    name.strip_prefix(JOYSTICK_AXIS_PREFIX)
+        .and_then(|index| index.parse().ok())
+        .ok_or_else(|| Error::InvalidAxis(name.to_string()))
+}
+
+/// Button pairs of a named remap preset.
+fn remap_preset(name: &str) -> Result<&'static [(Button, Button)], Error> {
+    Ok(match name {
+        // Nintendo layouts label the east and south buttons the other way
+        "nintendo_swap" => &[
+            (Button::A, Button::B),
+            (Button::B, Button::A),
+            (Button::X, Button::Y),
+            (Button::Y, Button::X),
+        ],
+        "triggers_to_bumpers" => &[
+            (Button::LeftTrigger, Button::LeftShoulder),
+            (Button::RightTrigger, Button::RightShoulder),
+        ],
+        "bumpers_to_triggers" => &[
+            (Button::LeftShoulder, Button::LeftTrigger),
+            (Button::RightShoulder, Button::RightTrigger),
+        ],
+        _ => return Err(Error::InvalidRemapPreset(name.to_string())),
+    })
+}
+
+fn parse_controller_mappings(
+    db: Option<&str>,
+    mappings: &[String],
+    expander: &Expander,
+) -> Result<ControllerMappings, Error> {
+    if let Some(mapping) = mappings.iter().find(|m| !is_valid_mapping(m)) {
+        return Err(Error::InvalidMapping(mapping.clone()));
+    }
+    let files = db
+        .map(|path| expander.expand(path).map(PathBuf::from))
+        .transpose()?
+        .into_iter()
+        .collect();
+    Ok(ControllerMappings {
+        files,
+        mappings: mappings.to_vec(),
+    })
+}
+
+fn parse_calibration(
+    raw: &Vec<ProfileV1Calibration>,
+) -> Result<CalibrationMap, Error> {
+    let mut map: CalibrationMap = AHashMap::new();
+    for raw_device in raw {
+        let mut calibration = Calibration::default();
+        for (name, axis) in raw_device.axes.iter() {
+            calibration.set(
+                parse_axis_name(name)?,
+                AxisCalibration {
+                    offset: axis.offset,
+                    scale: axis.scale,
+                },
+            );
+        }
+        map.insert((raw_device.vid, raw_device.pid), calibration);
+    }
+    Ok(map)
+}
+
+/// Resolves a `host:port` address.
+fn parse_socket_addr(raw: &str) -> Option<SocketAddr> {
+    raw.to_socket_addrs().ok()?.next()
+}
+
+/// Parse the OSC output settings.
+fn parse_osc(raw: &ProfileV1Osc) -> Result<OscSettings, Error> {
+    let target = parse_socket_addr(&raw.address)
+        .ok_or_else(|| Error::InvalidOsc(format!("address {}", raw.address)))?;
+    let prefix = raw.prefix.as_deref().unwrap_or(DEFAULT_OSC_PREFIX);
+    if !prefix.starts_with('/') || prefix.contains([' ', '#', ',']) {
+        return Err(Error::InvalidOsc(format!("prefix {prefix}")));
+    }
+    Ok(OscSettings {
+        target,
+        prefix: prefix.trim_end_matches('/').to_string(),
+        deadzone: raw.deadzone.unwrap_or(0.15),
+        gamma: raw.gamma.unwrap_or(1.0),
+    })
+}
+
+/// Parse a controller axis name into an `Axis` enum.
+fn parse_axis_name(name: &str) -> Result<CtrlAxis, Error> {
+    CtrlAxis::ALL
+        .into_iter()
+        .find(|axis| axis_name(*axis) == name)
+        .ok_or_else(|| Error::InvalidAxis(name.to_string()))
+}
+
+/// Name of a controller axis as written in profiles.
+pub(crate) fn axis_name(axis: CtrlAxis) -> &'static str {
+    match axis {
+        CtrlAxis::LeftX => "left_x",
+        CtrlAxis::LeftY => "left_y",
+        CtrlAxis::RightX => "right_x",
+        CtrlAxis::RightY => "right_y",
+        CtrlAxis::LeftTrigger => "left_trigger",
+        CtrlAxis::RightTrigger => "right_trigger",
+    }
+}
+
+/// Names of raw joystick buttons, by index.
+const JOYSTICK_BUTTON_NAMES: [&str; Button::JOYSTICK_BUTTONS] = [
+    "js:0", "js:1", "js:2", "js:3", "js:4", "js:5", "js:6", "js:7", "js:8", "js:9",
+    "js:10", "js:11", "js:12", "js:13", "js:14", "js:15", "js:16", "js:17", "js:18",
+    "js:19", "js:20", "js:21", "js:22", "js:23", "js:24", "js:25", "js:26", "js:27",
+    "js:28", "js:29", "js:30", "js:31",
+];
+
+/// Prefix of raw joystick button names.
+const JOYSTICK_BUTTON_PREFIX: &str = "js:";
+
+/// Prefix of raw joystick axis names.
+const JOYSTICK_AXIS_PREFIX: &str = "jaxis:";
+
+/// Name of a button as written in profiles.
+pub(crate) fn button_name(button: Button) -> &'static str {
+    if let Some(index) = button.joystick_index() {
+        return JOYSTICK_BUTTON_NAMES[index];
+    }
+    match button {
+        Button::A => "a",
+        Button::B => "b",
+        Button::X => "x",
+        Button::Y => "y",
+        Button::Back => "back",
+        Button::Guide => "guide",
+        Button::Start => "start",
+        Button::LeftStick => "ls",
+        Button::RightStick => "rs",
+        Button::LeftShoulder => "lb",
+        Button::RightShoulder => "rb",
+        Button::LeftTrigger => "lt",
+        Button::RightTrigger => "rt",
+        Button::DPadUp => "dpad_up",
+        Button::DPadDown => "dpad_down",
+        Button::DPadLeft => "dpad_left",
+        Button::DPadRight => "dpad_right",
+        Button::LeftStickUp => "stick_left_up",
+        Button::LeftStickDown => "stick_left_down",
+        Button::LeftStickLeft => "stick_left_left",
+        Button::LeftStickRight => "stick_left_right",
+        Button::RightStickUp => "stick_right_up",
+        Button::RightStickDown => "stick_right_down",
+        Button::RightStickLeft => "stick_right_left",
+        Button::RightStickRight => "stick_right_right",
+        Button::Misc => "misc",
+        Button::Paddle1 => "paddle1",
+        Button::Paddle2 => "paddle2",
+        Button::Paddle3 => "paddle3",
+        Button::Paddle4 => "paddle4",
+        Button::Touchpad => "touchpad",
+        _ => unreachable!("raw joystick buttons are named above"),
+    }
 }
 
 /// Parse a button name into a `Button` enum.
-fn parse_button_name(name: &str) -> Result<Button, Error> {
+pub(crate) fn parse_button_name(name: &str) -> Result<Button, Error> {
     Ok(match name {
         "a" => Button::A,
         "b" => Button::B,
@@ -128,30 +542,139 @@ fn parse_button_name(name: &str) -> Result<Button, Error> {
         "dpad_left" => Button::DPadLeft,
         "dpad_right" => Button::DPadRight,
 
-        _ => return Err(Error::InvalidButton(name.to_string())),
+        "stick_left_up" => Button::LeftStickUp,
+        "stick_left_down" => Button::LeftStickDown,
+        "stick_left_left" => Button::LeftStickLeft,
+        "stick_left_right" => Button::LeftStickRight,
+        "stick_right_up" => Button::RightStickUp,
+        "stick_right_down" => Button::RightStickDown,
+        "stick_right_left" => Button::RightStickLeft,
+        "stick_right_right" => Button::RightStickRight,
+
+        "misc" | "share" | "capture" | "mute" => Button::Misc,
+        "paddle1" => Button::Paddle1,
+        "paddle2" => Button::Paddle2,
+        "paddle3" => Button::Paddle3,
+        "paddle4" => Button::Paddle4,
+        "touchpad" => Button::Touchpad,
+
+        _ => name
+            .strip_prefix(JOYSTICK_BUTTON_PREFIX)
+            .and_then(|index| index.parse().ok())
+            .and_then(Button::joystick)
+            .ok_or_else(|| Error::InvalidButton(name.to_string()))?,
     })
 }
 
 /// Parse a v1 app rules.
-fn parse_app_rules(raw: ProfileV1App, bundle_id: &str) -> Result<AppRules, Error> {
+fn parse_app_rules(
+    raw: ProfileV1App,
+    bundle_id: &str,
+    expander: &Expander,
+    groups: &AHashMap<String, Vec<Box<str>>>,
+) -> Result<AppRules, Error> {
     let mut button_rules: ButtonRules = AHashMap::new();
+    let mut key_rules: KeyRules = AHashMap::new();
     let mut stick_rules: StickRules = AHashMap::new();
 
     for (chord_str, rule) in raw.buttons.into_iter() {
         let chord = parse_chord(&chord_str)?;
-        let rule = parse_button_rule(rule, bundle_id)?;
+        let rule = parse_button_rule(rule, bundle_id, expander, groups)?;
         button_rules.insert(chord, rule);
     }
 
+    for (combo_str, rule) in raw.keys.into_iter() {
+        let combo = parse_key_trigger(&expander.interpolate(&combo_str)?)?;
+        let rule = parse_button_rule(rule, bundle_id, expander, groups)?;
+        key_rules.insert(combo, rule);
+    }
+
     for (side, stick_raw) in raw.sticks.into_iter() {
         let side = parse_stick_side(&side)?;
-        let mode = parse_stick_mode(stick_raw)?;
-        stick_rules.insert(side, mode);
+        stick_rules.insert(side, parse_stick_rule(stick_raw)?);
+    }
+
+    let haptic_feedback = raw
+        .haptic_feedback
+        .as_deref()
+        .map(parse_haptic_feedback)
+        .transpose()?;
+
+    let mut raw_titles: Vec<_> = raw.titles.into_iter().collect();
+    // Titles are unordered in YAML, sort them to apply overlapping ones deterministically
+    raw_titles.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut titles = Vec::with_capacity(raw_titles.len());
+    for (pattern, title_raw) in raw_titles {
+        let title = Regex::new(&pattern)
+            .map_err(|e| Error::InvalidTitle(pattern.clone(), e.to_string()))?;
+        let app = ProfileV1App {
+            buttons: title_raw.buttons,
+            sticks: title_raw.sticks,
+            ..Default::default()
+        };
+        titles.push(TitleRules {
+            title,
+            rules: parse_app_rules(
+                app,
+                &format!("{bundle_id} title {pattern}"),
+                expander,
+                groups,
+            )?,
+        });
     }
 
     Ok(AppRules {
         buttons: button_rules,
+        keys: key_rules,
         sticks: stick_rules,
+        titles,
+        haptic_feedback,
+    })
+}
+
+fn parse_haptic_feedback(raw: &str) -> Result<HapticFeedback, Error> {
+    Ok(match raw {
+        "off" => HapticFeedback::Off,
+        "light" => HapticFeedback::Light,
+        "medium" => HapticFeedback::Medium,
+        other => return Err(Error::InvalidHapticFeedback(other.to_string())),
+    })
+}
+
+/// Parse v1 global rules.
+fn parse_global(
+    raw: ProfileV1Global,
+    expander: &Expander,
+    groups: &AHashMap<String, Vec<Box<str>>>,
+) -> Result<GlobalRules, Error> {
+    let app = ProfileV1App {
+        buttons: raw.buttons,
+        sticks: raw.sticks,
+        ..Default::default()
+    };
+    Ok(GlobalRules {
+        rules: parse_app_rules(app, GLOBAL_RULES_NAME, expander, groups)?,
+        include_blacklisted: raw.include_blacklisted,
+    })
+}
+
+fn parse_set(
+    raw: &ProfileV1Set,
+    expander: &Expander,
+    groups: &AHashMap<String, Vec<Box<str>>>,
+) -> Result<ProfileSet, Error> {
+    let name = raw.name.trim();
+    if name.is_empty() || SetTarget::RESERVED.contains(&name) {
+        return Err(Error::InvalidProfileSet(raw.name.clone()));
+    }
+    let app = ProfileV1App {
+        buttons: raw.buttons.clone(),
+        sticks: raw.sticks.clone(),
+        ..Default::default()
+    };
+    Ok(ProfileSet {
+        name: name.into(),
+        rules: parse_app_rules(app, &format!("set {name}"), expander, groups)?,
     })
 }
 
@@ -178,29 +701,363 @@ fn parse_chord(input: &str) -> Result<ButtonChord, Error> {
     }
 }
 
+/// Parse a keyboard chord: one key with any modifiers, e.g. `hyper+j`.
+fn parse_key_trigger(input: &str) -> Result<KeyCombo, Error> {
+    let combo = parse_keystroke(input)?;
+    if combo.keys.len() != 1 {
+        return Err(Error::InvalidTrigger(input.to_string()));
+    }
+    Ok(combo)
+}
+
+/// Groups with `${var}` interpolated in their app ids.
+fn interpolate_groups(
+    groups: &AHashMap<String, Vec<Box<str>>>,
+    expander: &Expander,
+) -> Result<AHashMap<String, Vec<Box<str>>>, Error> {
+    groups
+        .iter()
+        .map(|(name, ids)| {
+            let ids = ids
+                .iter()
+                .map(|id| expander.interpolate(id).map(Into::into))
+                .collect::<Result<_, Error>>()?;
+            Ok((name.clone(), ids))
+        })
+        .collect()
+}
+
 fn parse_button_rule(
     raw: ProfileV1ButtonRule,
     target_name: &str,
+    expander: &Expander,
+    groups: &AHashMap<String, Vec<Box<str>>>,
 ) -> Result<ButtonRule, Error> {
-    let action = match (raw.keystroke, raw.macros, raw.shell) {
-        (Some(keystroke), None, None) => {
-            let keystroke = parse_keystroke(&keystroke)?;
-            ButtonAction::Keystroke(Arc::new(keystroke))
-        }
-        (None, Some(macros), None) => {
-            let macros = parse_macros(&macros)?;
-            ButtonAction::Macros(Arc::new(macros))
-        }
-        (None, None, Some(shell)) => ButtonAction::Shell(shell),
-        _ => return Err(Error::InvalidActions(target_name.to_string())),
+    let action = parse_action(
+        ProfileV1ReleaseAction {
+            keystroke: raw.keystroke,
+            macros: raw.macros,
+            shell: raw.shell,
+            shortcut: raw.shortcut,
+            system: raw.system,
+            window: raw.window,
+            space: raw.space,
+            cycle_app: raw.cycle_app,
+            mouse: raw.mouse,
+            profile_set: raw.profile_set,
+        },
+        target_name,
+        expander,
+        groups,
+    )?;
+    let on_release = match raw.on_release {
+        Some(release) => Some(parse_action(release, target_name, expander, groups)?),
+        None => None,
+    };
+    if raw.toggle && !matches!(action, ButtonAction::Keystroke(_)) {
+        return Err(Error::InvalidToggle(target_name.to_string()));
+    }
+    let has_limits = raw.max_concurrent.is_some()
+        || raw.debounce_ms.is_some()
+        || raw.overflow.is_some();
+    let is_process = |action: &ButtonAction| {
+        matches!(action, ButtonAction::Shell(_) | ButtonAction::Shortcut(_))
+    };
+    let runs_process =
+        is_process(&action) || on_release.as_ref().is_some_and(is_process);
+    if (has_limits && !runs_process) || raw.max_concurrent == Some(0) {
+        return Err(Error::InvalidShellLimits(target_name.to_string()));
+    }
+    let is_keys = |action: &ButtonAction| {
+        matches!(action, ButtonAction::Keystroke(_) | ButtonAction::Macros(_))
+    };
+    if raw.key_delay_ms.is_some()
+        && !is_keys(&action)
+        && !on_release.as_ref().is_some_and(is_keys)
+    {
+        return Err(Error::InvalidKeyDelay(target_name.to_string()));
+    }
+    let target = match raw.target.as_deref().map(str::trim) {
+        None | Some("frontmost") => None,
+        Some(bundle_id) => Some(Box::<str>::from(bundle_id)),
+    };
+    if raw.target.is_some()
+        && (target.as_deref().is_some_and(str::is_empty)
+            || !is_keys(&action) && !on_release.as_ref().is_some_and(is_keys))
+    {
+        return Err(Error::InvalidTarget(target_name.to_string()));
+    }
+    let shell_limits = ShellLimits {
+        max_concurrent: raw.max_concurrent,
+        debounce: Duration::from_millis(raw.debounce_ms.unwrap_or(0)),
+        overflow: raw
+            .overflow
+            .as_deref()
+            .map(parse_overflow)
+            .transpose()?
+            .unwrap_or_default(),
     };
 
     Ok(ButtonRule {
         vibrate: raw.vibrate,
         action,
+        on_release,
+        toggle: raw.toggle,
+        shell_limits,
+        key_delay: raw.key_delay_ms.map(parse_key_delay).transpose()?,
+        target,
+        source: RuleSource {
+            selector: target_name.into(),
+            file: raw.source_file,
+        },
+    })
+}
+
+fn parse_key_delay(ms: u64) -> Result<Duration, Error> {
+    if ms > MAX_KEY_DELAY_MS {
+        return Err(Error::KeyDelayTooLong(ms));
+    }
+    Ok(Duration::from_millis(ms))
+}
+
+fn parse_overflow(raw: &str) -> Result<ShellOverflow, Error> {
+    Ok(match raw {
+        "drop" => ShellOverflow::Drop,
+        "queue" => ShellOverflow::Queue,
+        other => return Err(Error::InvalidOverflow(other.to_string())),
+    })
+}
+
+/// Parses the single action set in `raw`, button rules share the action
+/// fields with release actions.
+fn parse_action(
+    raw: ProfileV1ReleaseAction,
+    target_name: &str,
+    expander: &Expander,
+    groups: &AHashMap<String, Vec<Box<str>>>,
+) -> Result<ButtonAction, Error> {
+    let ProfileV1ReleaseAction {
+        keystroke,
+        macros,
+        shell,
+        shortcut,
+        system,
+        window,
+        space,
+        cycle_app,
+        mouse,
+        profile_set,
+    } = raw;
+    let set = [
+        keystroke.is_some(),
+        macros.is_some(),
+        shell.is_some(),
+        shortcut
+            .as_ref()
+            .is_some_and(|name| !name.trim().is_empty()),
+        system.is_some(),
+        window.is_some(),
+        space.is_some(),
+        cycle_app.is_some(),
+        mouse.is_some(),
+        profile_set.is_some(),
+    ];
+    if set.iter().filter(|set| **set).count() != 1 {
+        return Err(Error::InvalidActions(target_name.to_string()));
+    }
+
+    let action = if let Some(keystroke) = keystroke {
+        let keystroke = expander.interpolate(&keystroke)?;
+        ButtonAction::Keystroke(Arc::new(parse_keystroke(&keystroke)?))
+    } else if let Some(macros) = macros {
+        let macros = macros
+            .iter()
+            .map(|keystroke| expander.interpolate(keystroke))
+            .collect::<Result<Vec<_>, _>>()?;
+        ButtonAction::Macros(Arc::new(parse_macros(&macros)?))
+    } else if let Some(shell) = shell {
+        ButtonAction::Shell(expander.expand(&shell)?)
+    } else if let Some(name) = shortcut {
+        ButtonAction::Shortcut(expander.interpolate(&name)?)
+    } else if let Some(system) = system {
+        let action = system
+            .parse()
+            .map_err(|_| Error::InvalidSystemAction(system))?;
+        ButtonAction::System(action)
+    } else if let Some(window) = window {
+        let action = window
+            .parse()
+            .map_err(|_| Error::InvalidWindowAction(window))?;
+        ButtonAction::Window(action)
+    } else if let Some(space) = space {
+        let target = space.parse().map_err(|_| Error::InvalidSpace(space))?;
+        ButtonAction::Space(target)
+    } else if let Some(selector) = cycle_app {
+        let selector = expander.interpolate(&selector)?;
+        let apps = Selector::parse(&selector)?.materialize(groups)?;
+        if apps.is_empty() {
+            return Err(Error::InvalidActions(target_name.to_string()));
+        }
+        ButtonAction::CycleApp(apps.iter().map(|app| AppPattern::new(app)).collect())
+    } else if let Some(mouse) = mouse {
+        let target = mouse
+            .parse()
+            .map_err(|_| Error::InvalidMouseTarget(mouse))?;
+        ButtonAction::Mouse(target)
+    } else if let Some(set) = profile_set {
+        let target = set.parse().map_err(|_| Error::InvalidProfileSet(set))?;
+        ButtonAction::ProfileSet(target)
+    } else {
+        unreachable!("exactly one action is set")
+    };
+    Ok(action)
+}
+
+fn parse_stepper(
+    raw: &ProfileV1Stick,
+    deadzone: f32,
+    outer_deadzone: f32,
+    filter: AxisFilter,
+) -> Result<StepperParams, Error> {
+    Ok(StepperParams {
+        axis: parse_stepper_axis(raw, "y")?,
+        deadzone,
+        outer_deadzone,
+        filter,
+        invert: raw.invert.unwrap_or(false),
+        min_interval_ms: raw.min_interval_ms.unwrap_or(250),
+        max_interval_ms: raw.max_interval_ms.unwrap_or(40),
+        curve: parse_repeat_curve(raw)?,
+    })
+}
+
+/// Reads the `[deflection, interval_ms]` points of a repeat curve, with
+/// deflections increasing within [0, 1] and positive intervals.
+fn parse_repeat_curve(raw: &ProfileV1Stick) -> Result<Option<RepeatCurve>, Error> {
+    let Some(points) = raw.curve.as_deref() else {
+        return Ok(None);
+    };
+    if points.is_empty() {
+        return Err(Error::InvalidStick("curve needs at least one point".into()));
+    }
+    let mut previous = None;
+    for [x, ms] in points {
+        if !(0.0..=1.0).contains(x) {
+            return Err(Error::InvalidStick(format!(
+                "curve deflection must be in [0, 1]: {x}"
+            )));
+        }
+        if previous.is_some_and(|previous| *x <= previous) {
+            return Err(Error::InvalidStick(format!(
+                "curve deflections must increase: {x}"
+            )));
+        }
+        if !(ms.is_finite() && *ms > 0.0) {
+            return Err(Error::InvalidStick(format!(
+                "curve interval must be positive: {ms}"
+            )));
+        }
+        previous = Some(*x);
+    }
+    let points: Vec<_> = points.iter().map(|[x, ms]| (*x, *ms)).collect();
+    Ok(Some(RepeatCurve::from_points(&points)))
+}
+
+fn parse_axis_filter(raw: &ProfileV1Stick) -> Result<AxisFilter, Error> {
+    let filter = AxisFilter {
+        alpha: raw.smoothing.unwrap_or(1.0),
+        jitter_clamp: raw.jitter_clamp.unwrap_or(0.0),
+    };
+    if !(filter.alpha > 0.0 && filter.alpha <= 1.0) {
+        return Err(Error::InvalidStick(format!(
+            "smoothing must be in (0, 1]: {}",
+            filter.alpha
+        )));
+    }
+    if !(0.0..1.0).contains(&filter.jitter_clamp) {
+        return Err(Error::InvalidStick(format!(
+            "jitter_clamp must be in [0, 1): {}",
+            filter.jitter_clamp
+        )));
+    }
+    Ok(filter)
+}
+
+/// Reads the outer deadzone, which must leave room past the inner one.
+fn parse_outer_deadzone(raw: &ProfileV1Stick, deadzone: f32) -> Result<f32, Error> {
+    let outer_deadzone = raw.outer_deadzone.unwrap_or(0.0);
+    if !(0.0..1.0 - deadzone).contains(&outer_deadzone) {
+        return Err(Error::InvalidStick(format!(
+            "outer_deadzone must be in [0, {}): {outer_deadzone}",
+            1.0 - deadzone
+        )));
+    }
+    Ok(outer_deadzone)
+}
+
+fn parse_deadzone_shape(
+    raw: &ProfileV1Stick,
+    default: DeadzoneShape,
+) -> Result<DeadzoneShape, Error> {
+    let Some(shape) = raw.deadzone_shape.as_deref() else {
+        return Ok(default);
+    };
+    Ok(match shape.to_lowercase().as_str() {
+        "circular" => DeadzoneShape::Circular,
+        "square" => DeadzoneShape::Square,
+        "cross" => DeadzoneShape::Cross,
+        other => {
+            return Err(Error::InvalidStick(format!(
+                "invalid deadzone shape: {other}"
+            )))
+        }
     })
 }
 
+fn parse_dwell(raw: &ProfileV1Stick) -> Result<Option<DwellClick>, Error> {
+    let button = match raw.dwell_button.as_deref() {
+        Some(button) => button.parse().map_err(Error::InvalidStick)?,
+        None => MouseButton::Left,
+    };
+    match raw.dwell_ms {
+        Some(ms) if ms > 0 => Ok(Some(DwellClick {
+            delay: Duration::from_millis(ms),
+            button,
+        })),
+        _ if raw.dwell_button.is_some() => Err(Error::InvalidStick(
+            "dwell_button requires dwell_ms".to_string(),
+        )),
+        _ => Ok(None),
+    }
+}
+
+fn parse_scroll_unit(raw: &ProfileV1Stick) -> Result<ScrollUnit, Error> {
+    let Some(unit) = raw.unit.as_deref() else {
+        return Ok(ScrollUnit::default());
+    };
+    Ok(match unit.to_lowercase().as_str() {
+        "lines" => ScrollUnit::Lines,
+        "pixels" => ScrollUnit::Pixels,
+        other => {
+            return Err(Error::InvalidStick(format!("invalid scroll unit: {other}")))
+        }
+    })
+}
+
+fn parse_stepper_axis(raw: &ProfileV1Stick, default: &str) -> Result<Axis, Error> {
+    match raw
+        .axis
+        .as_deref()
+        .unwrap_or(default)
+        .to_lowercase()
+        .as_str()
+    {
+        "x" => Ok(Axis::X),
+        "y" => Ok(Axis::Y),
+        other => Err(Error::InvalidTrigger(format!("invalid axis: {other}"))),
+    }
+}
+
 fn parse_keystroke(input: &str) -> Result<KeyCombo, Error> {
     input.parse::<KeyCombo>().map_err(Error::KeyParse)
 }
@@ -213,78 +1070,159 @@ fn parse_macros(input: &[String]) -> Result<Macros, Error> {
         .collect::<Result<Macros, _>>()
 }
 
+fn parse_stick_rule(raw: ProfileV1Sticks) -> Result<StickRule, Error> {
+    let mut base = None;
+    let mut held = Vec::new();
+    for entry in raw.into_vec() {
+        let Some(chord) = entry.while_held.as_deref() else {
+            if base.replace(entry).is_some() {
+                return Err(Error::InvalidHeldStick(
+                    "only one mode can go without while_held".into(),
+                ));
+            }
+            continue;
+        };
+        if entry.toggle_with.is_some() || entry.toggle_mode.is_some() {
+            return Err(Error::InvalidHeldStick(
+                "while_held modes can't toggle".into(),
+            ));
+        }
+        held.push(HeldStickMode {
+            chord: parse_chord(chord)?,
+            mode: parse_stick_mode(entry)?,
+        });
+    }
+    let Some(mut base) = base else {
+        return Err(Error::InvalidHeldStick(
+            "a mode without while_held is needed".into(),
+        ));
+    };
+    // Larger chords win over the chords they contain
+    held.sort_by_key(|held| Reverse(held.chord.count()));
+
+    let toggle = match (base.toggle_with.take(), base.toggle_mode.take()) {
+        (None, None) => None,
+        (Some(button), Some(toggle_mode)) => {
+            if toggle_mode.toggle_with.is_some()
+                || toggle_mode.toggle_mode.is_some()
+                || toggle_mode.while_held.is_some()
+            {
+                return Err(Error::InvalidStickToggle(
+                    "toggle_mode can't toggle again".into(),
+                ));
+            }
+            let button = parse_button_name(&button)
+                .ok()
+                .filter(|button| {
+                    matches!(button, Button::LeftStick | Button::RightStick)
+                })
+                .ok_or_else(|| {
+                    Error::InvalidStickToggle(format!(
+                        "toggle_with must be ls or rs, got {button}"
+                    ))
+                })?;
+            Some(StickToggle {
+                button,
+                mode: parse_stick_mode(*toggle_mode)?,
+            })
+        }
+        _ => {
+            return Err(Error::InvalidStickToggle(
+                "toggle_with and toggle_mode go together".into(),
+            ))
+        }
+    };
+    Ok(StickRule {
+        mode: parse_stick_mode(base)?,
+        toggle,
+        held,
+    })
+}
+
 fn parse_stick_mode(raw: ProfileV1Stick) -> Result<StickMode, Error> {
     let deadzone = raw.deadzone.unwrap_or(0.15);
+    let filter = parse_axis_filter(&raw)?;
+    let outer_deadzone = parse_outer_deadzone(&raw, deadzone)?;
     let mode = match raw.mode.to_lowercase().as_str() {
         "arrows" => {
             let params = ArrowsParams {
                 deadzone,
+                deadzone_shape: parse_deadzone_shape(&raw, DeadzoneShape::Circular)?,
                 repeat_delay_ms: raw.repeat_delay_ms.unwrap_or(300),
                 repeat_interval_ms: raw.repeat_interval_ms.unwrap_or(40),
+                eight_way: raw.eight_way.unwrap_or(false),
                 invert_x: raw.invert_x.unwrap_or(false),
                 invert_y: raw.invert_y.unwrap_or(false),
+                filter,
             };
             StickMode::Arrows(params)
         }
         "mouse_move" => {
             let params = MouseParams {
                 deadzone,
+                deadzone_shape: parse_deadzone_shape(&raw, DeadzoneShape::Circular)?,
+                outer_deadzone,
                 max_speed_px_s: raw.max_speed_px_s.unwrap_or(1600.0),
                 gamma: raw.gamma.unwrap_or(1.5),
                 invert_x: raw.invert_x.unwrap_or(false),
                 invert_y: raw.invert_y.unwrap_or(false),
+                filter,
+                dwell: parse_dwell(&raw)?,
             };
             StickMode::MouseMove(params)
         }
         "scroll" => {
+            let speed_lines_s = raw.speed_lines_s.unwrap_or(100.0);
             let params = ScrollParams {
                 deadzone,
-                speed_lines_s: raw.speed_lines_s.unwrap_or(100.0),
+                deadzone_shape: parse_deadzone_shape(&raw, DeadzoneShape::Square)?,
+                outer_deadzone,
+                speed_lines_s,
+                unit: parse_scroll_unit(&raw)?,
                 horizontal: raw.horizontal.unwrap_or(false),
+                horizontal_deadzone: raw.horizontal_deadzone.unwrap_or(deadzone),
+                horizontal_speed_lines_s: raw
+                    .horizontal_speed_lines_s
+                    .unwrap_or(speed_lines_s),
                 invert_x: raw.invert_x.unwrap_or(false),
                 invert_y: raw.invert_y.unwrap_or(false),
+                filter,
             };
             StickMode::Scroll(params)
         }
         "volume" => {
-            let axis =
-                match raw.axis.as_deref().unwrap_or("y").to_lowercase().as_str() {
-                    "x" => Axis::X,
-                    "y" => Axis::Y,
-                    other => {
-                        return Err(Error::InvalidTrigger(format!(
-                            "invalid axis: {other}"
-                        )))
-                    }
-                };
-            let params = StepperParams {
-                axis,
-                deadzone,
-                invert: raw.invert.unwrap_or(false),
-                min_interval_ms: raw.min_interval_ms.unwrap_or(250),
-                max_interval_ms: raw.max_interval_ms.unwrap_or(40),
-            };
-            StickMode::Volume(params)
+            StickMode::Volume(parse_stepper(&raw, deadzone, outer_deadzone, filter)?)
         }
-        "brightness" => {
-            let axis =
-                match raw.axis.as_deref().unwrap_or("y").to_lowercase().as_str() {
-                    "x" => Axis::X,
-                    "y" => Axis::Y,
-                    other => {
-                        return Err(Error::InvalidTrigger(format!(
-                            "invalid axis: {other}"
-                        )))
-                    }
-                };
-            let params = StepperParams {
-                axis,
+        "brightness" => StickMode::Brightness(parse_stepper(
+            &raw,
+            deadzone,
+            outer_deadzone,
+            filter,
+        )?),
+        "keyboard_backlight" => StickMode::KeyboardBacklight(parse_stepper(
+            &raw,
+            deadzone,
+            outer_deadzone,
+            filter,
+        )?),
+        "seek" => {
+            let key = |raw: &Option<String>, default: Key| match raw {
+                Some(keystroke) => parse_keystroke(keystroke),
+                None => Ok(KeyCombo::from_key(default)),
+            };
+            StickMode::Seek(SeekParams {
+                axis: parse_stepper_axis(&raw, "x")?,
                 deadzone,
-                invert: raw.invert.unwrap_or(false),
-                min_interval_ms: raw.min_interval_ms.unwrap_or(250),
+                outer_deadzone,
+                min_interval_ms: raw.min_interval_ms.unwrap_or(400),
                 max_interval_ms: raw.max_interval_ms.unwrap_or(40),
-            };
-            StickMode::Brightness(params)
+                gamma: raw.gamma.unwrap_or(1.5),
+                curve: parse_repeat_curve(&raw)?,
+                invert: raw.invert.unwrap_or(false),
+                forward: key(&raw.forward, Key::MediaFastForward)?,
+                backward: key(&raw.backward, Key::MediaRewind)?,
+                filter,
+            })
         }
         other => {
             return Err(Error::InvalidTrigger(format!(
@@ -1,7 +1,11 @@
+use std::fmt;
+
 use ahash::AHashMap;
-use serde::Deserialize;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
 pub struct ProfileV1 {
     pub version: u8,
@@ -15,6 +19,137 @@ pub struct ProfileV1 {
     pub rules: AHashMap<Box<str>, ProfileV1App>, // bundle_id -> app mapping
     #[serde(default)]
     pub shell: Option<Box<str>>,
+    #[serde(default)]
+    pub gamepad_backend: Option<String>, // sdl2 | game_controller
+    #[serde(default)]
+    pub keyboard_backend: Option<String>, // enigo | native
+    #[serde(default)]
+    pub ignore_virtual: bool,
+    #[serde(default)]
+    pub trigger_buttons: Option<bool>,
+    #[serde(default)]
+    pub pause_on_fullscreen: bool,
+    #[serde(default)]
+    pub announce_on_connect: bool,
+    #[serde(default)]
+    pub calibration: Vec<ProfileV1Calibration>,
+    #[serde(default)]
+    pub controller_db: Option<String>, // path to gamecontrollerdb.txt
+    #[serde(default)]
+    pub controller_mappings: Vec<String>,
+    #[serde(default)]
+    pub chord_window_ms: Option<u64>,
+    #[serde(default)]
+    pub ignore_own_activity_ms: u64,
+    #[serde(default)]
+    pub activation_debounce_ms: u64,
+    #[serde(default)]
+    pub transient_apps: Vec<String>,
+    #[serde(default)]
+    pub reconnect_grace_ms: u64,
+    #[serde(default)]
+    pub axis_interval_ms: u64,
+    #[serde(default)]
+    pub key_delay_ms: u64,
+    #[serde(default)]
+    pub global: Option<ProfileV1Global>,
+    #[serde(default)]
+    pub sets: Vec<ProfileV1Set>,
+    #[serde(default)]
+    pub vars: AHashMap<String, String>,
+    #[serde(default)]
+    pub osc: Option<ProfileV1Osc>,
+    #[serde(default)]
+    pub overlay: Option<ProfileV1Overlay>,
+    #[serde(default)]
+    pub speech: Option<ProfileV1Speech>,
+    #[serde(default)]
+    pub websocket: Option<ProfileV1WebSocket>,
+    #[serde(default)]
+    pub http: Option<ProfileV1Http>,
+    #[serde(default)]
+    pub log: AHashMap<String, String>, // module -> level
+    #[serde(default)]
+    pub watchdog: Option<ProfileV1Watchdog>,
+}
+
+/// A file from `profiles.d` contributing groups and rules to a profile.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileV1Fragment {
+    #[serde(default)]
+    pub groups: AHashMap<String, Vec<Box<str>>>,
+    #[serde(default)]
+    pub rules: AHashMap<Box<str>, ProfileV1App>, // selector -> app mapping
+}
+
+impl ProfileV1Fragment {
+    /// Marks the fragment's button rules as coming from `file`.
+    pub fn set_source(&mut self, file: &str) {
+        for app in self.rules.values_mut() {
+            let titles = app.titles.values_mut().map(|title| &mut title.buttons);
+            let own = [&mut app.buttons, &mut app.keys];
+            for buttons in own.into_iter().chain(titles) {
+                for rule in buttons.values_mut() {
+                    rule.source_file = Some(file.into());
+                }
+            }
+        }
+    }
+}
+
+impl ProfileV1 {
+    /// Merges a fragment into the profile. Group members are appended,
+    /// rules of the same selector are merged with the fragment taking precedence.
+    pub fn merge_fragment(&mut self, fragment: ProfileV1Fragment) {
+        for (name, ids) in fragment.groups {
+            self.groups.entry(name).or_default().extend(ids);
+        }
+        for (selector, app) in fragment.rules {
+            let current = self.rules.entry(selector).or_default();
+            current.buttons.extend(app.buttons);
+            current.keys.extend(app.keys);
+            current.sticks.extend(app.sticks);
+            current.titles.extend(app.titles);
+            if app.haptic_feedback.is_some() {
+                current.haptic_feedback = app.haptic_feedback;
+            }
+        }
+    }
+
+    /// A standalone profile with the fragment's rules, used to validate it.
+    /// Groups of this profile are included, so fragments can refer to them.
+    pub fn with_fragment_rules(&self, fragment: &ProfileV1Fragment) -> ProfileV1 {
+        let mut profile = ProfileV1 {
+            version: self.version,
+            groups: self.groups.clone(),
+            vars: self.vars.clone(),
+            ..Default::default()
+        };
+        profile.merge_fragment(fragment.clone());
+        profile
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Global {
+    #[serde(default)]
+    pub include_blacklisted: bool,
+    #[serde(default)]
+    pub buttons: AHashMap<String, ProfileV1ButtonRule>, // chord -> button rule
+    #[serde(default)]
+    pub sticks: AHashMap<String, ProfileV1Sticks>, // side -> stick rules
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Set {
+    pub name: String,
+    #[serde(default)]
+    pub buttons: AHashMap<String, ProfileV1ButtonRule>, // chord -> button rule
+    #[serde(default)]
+    pub sticks: AHashMap<String, ProfileV1Sticks>, // side -> stick rules
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -23,7 +158,22 @@ pub(crate) struct ProfileV1App {
     #[serde(default)]
     pub buttons: AHashMap<String, ProfileV1ButtonRule>, // chord -> button rule
     #[serde(default)]
-    pub sticks: AHashMap<String, ProfileV1Stick>, // side -> stick rules
+    pub keys: AHashMap<String, ProfileV1ButtonRule>, // key combo -> button rule
+    #[serde(default)]
+    pub sticks: AHashMap<String, ProfileV1Sticks>, // side -> stick rules
+    #[serde(default)]
+    pub titles: AHashMap<String, ProfileV1TitleRules>, // title regex -> rules
+    #[serde(default)]
+    pub haptic_feedback: Option<String>, // light | medium | off
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1TitleRules {
+    #[serde(default)]
+    pub buttons: AHashMap<String, ProfileV1ButtonRule>, // chord -> button rule
+    #[serde(default)]
+    pub sticks: AHashMap<String, ProfileV1Sticks>, // side -> stick rules
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -37,6 +187,114 @@ pub(crate) struct ProfileV1ButtonRule {
     pub macros: Option<Vec<String>>,
     #[serde(default)]
     pub shell: Option<String>,
+    #[serde(default)]
+    pub shortcut: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub window: Option<String>,
+    #[serde(default)]
+    pub space: Option<String>, // next | prev | index
+    #[serde(default)]
+    pub cycle_app: Option<String>, // selector
+    #[serde(default)]
+    pub mouse: Option<String>, // center | next_display | prev_display
+    #[serde(default)]
+    pub profile_set: Option<String>, // next | prev | default | <name>
+    #[serde(default)]
+    pub on_release: Option<ProfileV1ReleaseAction>,
+    #[serde(default)]
+    pub toggle: bool,
+    /// File the rule was merged from, set for fragments.
+    #[serde(skip)]
+    pub source_file: Option<Box<str>>,
+    // shell limits
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+    #[serde(default)]
+    pub overflow: Option<String>, // drop | queue
+    #[serde(default)]
+    pub key_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub target: Option<String>, // frontmost | <bundle id>
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1ReleaseAction {
+    #[serde(default)]
+    pub keystroke: Option<String>,
+    #[serde(default)]
+    pub macros: Option<Vec<String>>,
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub shortcut: Option<String>,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub window: Option<String>,
+    #[serde(default)]
+    pub space: Option<String>, // next | prev | index
+    #[serde(default)]
+    pub cycle_app: Option<String>, // selector
+    #[serde(default)]
+    pub mouse: Option<String>, // center | next_display | prev_display
+    #[serde(default)]
+    pub profile_set: Option<String>, // next | prev | default | <name>
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Osc {
+    pub address: String, // host:port
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub deadzone: Option<f32>,
+    #[serde(default)]
+    pub gamma: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Watchdog {
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub restart: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Overlay {
+    #[serde(default)]
+    pub actions: bool,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Speech {
+    #[serde(default)]
+    pub voice: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1WebSocket {
+    pub address: String, // host:port, loopback only
+    #[serde(default)]
+    pub origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Http {
+    pub address: String, // host:port, loopback only
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -45,25 +303,68 @@ pub(crate) struct ProfileV1ControllerSettings {
     pub vid: u16,
     pub pid: u16,
     #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub preset: Option<String>, // preset names joined with +
+    #[serde(default)]
     pub remap: AHashMap<String, String>, // button -> button
+    #[serde(default)]
+    pub axes: AHashMap<String, String>, // jaxis:N -> axis
+    #[serde(default)]
+    pub hid: AHashMap<String, String>, // usage_page:usage -> button
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1Calibration {
+    pub vid: u16,
+    pub pid: u16,
+    #[serde(default)]
+    pub axes: AHashMap<String, ProfileV1AxisCalibration>, // axis -> calibration
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileV1AxisCalibration {
+    #[serde(default)]
+    pub offset: f32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct ProfileV1Stick {
-    pub mode: String, // arrows | volume | brightness | scroll | mouse_move
+    pub mode: String, // arrows | volume | brightness | keyboard_backlight | seek | scroll | mouse_move
     #[serde(default)]
     pub deadzone: Option<f32>,
+    // filtering, all modes
+    #[serde(default)]
+    pub smoothing: Option<f32>, // moving average weight of new samples
+    #[serde(default)]
+    pub jitter_clamp: Option<f32>,
+    // arrows, mouse_move, scroll
+    #[serde(default)]
+    pub deadzone_shape: Option<String>, // circular | square | cross
+    // all modes but arrows
+    #[serde(default)]
+    pub outer_deadzone: Option<f32>,
     // arrows
     #[serde(default)]
     pub repeat_delay_ms: Option<u64>,
     #[serde(default)]
     pub repeat_interval_ms: Option<u64>,
     #[serde(default)]
+    pub eight_way: Option<bool>,
+    #[serde(default)]
     pub invert_x: Option<bool>,
     #[serde(default)]
     pub invert_y: Option<bool>,
-    // stepper (volume/brightness)
+    // stepper (volume/brightness/keyboard_backlight)
     #[serde(default)]
     pub axis: Option<String>, // x | y
     #[serde(default)]
@@ -72,14 +373,89 @@ pub(crate) struct ProfileV1Stick {
     pub min_interval_ms: Option<u64>,
     #[serde(default)]
     pub max_interval_ms: Option<u64>,
+    /// `[deflection, interval_ms]` points, also for seek.
+    #[serde(default)]
+    pub curve: Option<Vec<[f32; 2]>>,
     // mouse
     #[serde(default)]
     pub max_speed_px_s: Option<f32>,
     #[serde(default)]
     pub gamma: Option<f32>,
+    #[serde(default)]
+    pub dwell_ms: Option<u64>,
+    #[serde(default)]
+    pub dwell_button: Option<String>, // left | right | middle
     // scroll
     #[serde(default)]
     pub speed_lines_s: Option<f32>,
     #[serde(default)]
     pub horizontal: Option<bool>,
+    #[serde(default)]
+    pub unit: Option<String>, // lines | pixels
+    #[serde(default)]
+    pub horizontal_deadzone: Option<f32>,
+    #[serde(default)]
+    pub horizontal_speed_lines_s: Option<f32>,
+    // seek
+    #[serde(default)]
+    pub forward: Option<String>,
+    #[serde(default)]
+    pub backward: Option<String>,
+    // toggle, all modes
+    #[serde(default)]
+    pub toggle_with: Option<String>, // ls | rs
+    #[serde(default)]
+    pub toggle_mode: Option<Box<ProfileV1Stick>>,
+    // shift, all modes
+    #[serde(default)]
+    pub while_held: Option<String>, // chord
+}
+
+/// Modes of a stick side, a single mode or a list with `while_held` modes.
+#[derive(Debug, Clone)]
+pub(crate) enum ProfileV1Sticks {
+    One(Box<ProfileV1Stick>),
+    Many(Vec<ProfileV1Stick>),
+}
+
+impl ProfileV1Sticks {
+    pub fn into_vec(self) -> Vec<ProfileV1Stick> {
+        match self {
+            ProfileV1Sticks::One(stick) => vec![*stick],
+            ProfileV1Sticks::Many(sticks) => sticks,
+        }
+    }
+}
+
+// Not untagged, so a single mode keeps the errors of its fields.
+impl<'de> Deserialize<'de> for ProfileV1Sticks {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SticksVisitor;
+
+        impl<'de> Visitor<'de> for SticksVisitor {
+            type Value = ProfileV1Sticks;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a stick mode or a list of stick modes")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                ProfileV1Stick::deserialize(MapAccessDeserializer::new(map))
+                    .map(|stick| ProfileV1Sticks::One(Box::new(stick)))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq))
+                    .map(ProfileV1Sticks::Many)
+            }
+        }
+
+        deserializer.deserialize_any(SticksVisitor)
+    }
 }
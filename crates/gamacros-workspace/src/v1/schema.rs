@@ -0,0 +1,120 @@
+/// JSON Schema of the v1 profile, for editors to validate and complete
+/// `.gc_profile.yaml`. Tests keep it in sync with the profile structs.
+pub const PROFILE_SCHEMA: &str = include_str!("schema.json");
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde::de::{self, value, DeserializeOwned, Visitor};
+    use serde::forward_to_deserialize_any;
+    use serde_json::Value;
+
+    use super::PROFILE_SCHEMA;
+    use crate::v1::profile::*;
+
+    /// Deserializer that records the field names of the struct asked for.
+    struct FieldRecorder<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for FieldRecorder<'_> {
+        type Error = value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(
+            self,
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    fn fields<T: DeserializeOwned>() -> BTreeSet<&'static str> {
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(FieldRecorder(&mut fields));
+        fields.iter().copied().collect()
+    }
+
+    fn properties(schema: &Value) -> BTreeSet<&str> {
+        schema["properties"]
+            .as_object()
+            .expect("schema has properties")
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn schema_matches_profile_structs() {
+        let schema: Value = serde_json::from_str(PROFILE_SCHEMA).unwrap();
+        let defs = &schema["$defs"];
+        let root = &schema["properties"];
+
+        assert_eq!(properties(&schema), fields::<ProfileV1>());
+        assert_eq!(properties(&defs["AppRules"]), fields::<ProfileV1App>());
+        assert_eq!(
+            properties(&defs["TitleRules"]),
+            fields::<ProfileV1TitleRules>()
+        );
+        assert_eq!(
+            properties(&defs["GlobalRules"]),
+            fields::<ProfileV1Global>()
+        );
+        assert_eq!(
+            properties(&defs["ButtonRule"]),
+            fields::<ProfileV1ButtonRule>()
+        );
+        assert_eq!(
+            properties(&defs["ReleaseAction"]),
+            fields::<ProfileV1ReleaseAction>()
+        );
+        assert_eq!(
+            properties(&defs["ControllerSettings"]),
+            fields::<ProfileV1ControllerSettings>()
+        );
+        assert_eq!(
+            properties(&defs["Calibration"]),
+            fields::<ProfileV1Calibration>()
+        );
+        assert_eq!(
+            properties(
+                &defs["Calibration"]["properties"]["axes"]["additionalProperties"]
+            ),
+            fields::<ProfileV1AxisCalibration>()
+        );
+        assert_eq!(properties(&root["osc"]), fields::<ProfileV1Osc>());
+        assert_eq!(
+            properties(&root["websocket"]),
+            fields::<ProfileV1WebSocket>()
+        );
+        assert_eq!(properties(&root["http"]), fields::<ProfileV1Http>());
+        assert_eq!(properties(&root["watchdog"]), fields::<ProfileV1Watchdog>());
+
+        // Each stick mode documents its own subset of the stick fields
+        let stick: BTreeSet<&str> = defs["Stick"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|variant| {
+                let name = variant["$ref"].as_str().unwrap();
+                let name = name.trim_start_matches("#/$defs/");
+                properties(&defs[name])
+            })
+            .collect();
+        assert_eq!(stick, fields::<ProfileV1Stick>());
+    }
+}
@@ -1,6 +1,7 @@
 use ahash::AHashMap;
 use thiserror::Error;
 use super::combo::{parse_terms_with_delim, SequenceError, SequenceErrorKind};
+use crate::AppPattern;
 
 pub(crate) type SelectorResult<T> = Result<T, SelectorError>;
 
@@ -14,6 +15,9 @@ pub enum SelectorError {
 
     #[error("group and bundle id must be separated by an operator")]
     InvalidGroupAndBundleId(String),
+
+    #[error("invalid site selector \"{0}\", expected bundle_id#host")]
+    InvalidSite(String),
 }
 
 /// A lexem is a token in a selector string.
@@ -21,6 +25,7 @@ pub enum SelectorError {
 enum Lexem<'a> {
     Group(&'a str),
     BundleId(&'a str),
+    Pattern(&'a str),
     OperatorOr,
 }
 
@@ -32,18 +37,22 @@ impl<'a> Lexem<'a> {
         if let Some(stripped) = token.strip_prefix('$') {
             return Self::Group(stripped);
         }
+        if AppPattern::is_pattern(token) {
+            return Self::Pattern(token);
+        }
         Self::BundleId(token)
     }
 }
 
-/// A selector is an app list with groups and bundle ids.
-/// It looks like this: `$ide | $browser | com.google.Chrome`.
+/// A selector is an app list with groups, bundle ids and patterns.
+/// It looks like this: `$ide | $browser | com.google.Chrome | com.jetbrains.*`.
 #[derive(Debug)]
 pub(crate) struct Selector<'a>(Vec<Lexem<'a>>);
 
 impl<'a> Selector<'a> {
     /// Materializes the selector into a vector of bundle ids.
     /// Groups are replaced with their bundle ids.
+    /// Patterns are kept as is, to be matched at runtime.
     /// Or operator is ignored.
     pub(crate) fn materialize(
         &self,
@@ -54,7 +63,9 @@ impl<'a> Selector<'a> {
         let mut bundle_ids: Vec<Box<str>> = Vec::with_capacity(self.0.len());
         for token in self.0.iter() {
             match token {
-                Lexem::BundleId(bundle_id) => bundle_ids.push((*bundle_id).into()),
+                Lexem::BundleId(bundle_id) | Lexem::Pattern(bundle_id) => {
+                    bundle_ids.push((*bundle_id).into())
+                }
                 Lexem::Group(group) => {
                     let Some(ids) = groups.get(*group) else {
                         return Err(SelectorError::UnknownGroup(group.to_string()));
@@ -88,11 +99,28 @@ impl<'a> Selector<'a> {
         };
 
         let selector = terms.into_iter().map(Lexem::parse).collect::<Vec<_>>();
+        for lexem in selector.iter() {
+            if let Lexem::BundleId(bundle_id) = lexem {
+                if !is_valid_site(bundle_id) {
+                    return Err(SelectorError::InvalidSite(bundle_id.to_string()));
+                }
+            }
+        }
 
         Ok(Self(selector))
     }
 }
 
+/// Bundle ids may target a browser site as `bundle_id#host`.
+fn is_valid_site(bundle_id: &str) -> bool {
+    match bundle_id.split_once('#') {
+        Some((app, host)) => {
+            !app.is_empty() && !host.is_empty() && !host.contains('#')
+        }
+        None => true,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -157,6 +185,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lexer_parses_pattern() {
+        assert_eq!(
+            Lexem::parse("com.jetbrains.*"),
+            Lexem::Pattern("com.jetbrains.*")
+        );
+    }
+
     // -------- parser (Selector::parse)
     #[test]
     fn parser_accepts_valid_sequence() {
@@ -200,6 +236,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parser_validates_site_selectors() {
+        assert!(Selector::parse("com.apple.Safari#github.com").is_ok());
+        match Selector::parse("com.apple.Safari#") {
+            Err(SelectorError::InvalidSite(_)) => {}
+            _ => panic!("expected InvalidSite"),
+        }
+    }
+
     #[test]
     fn parser_accepts_adjacent_pipes_without_spaces() {
         let s = Selector::parse("$ide|$browser|com.apple.Safari");
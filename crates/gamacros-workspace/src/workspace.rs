@@ -1,13 +1,23 @@
 use std::path::{Path, PathBuf};
 
-use crate::WorkspaceError;
+use gamacros_gamepad::Calibration;
+
+use crate::calibration::{render_section, splice_section};
+use crate::{parse_profile, ControllerId, WorkspaceError};
 use crate::{profile_watcher::ProfileEventReceiver, ProfileWatcher};
 
-const DEFAULT_WORKSPACE_PATH: &str = "Library/Application Support/gamacros";
+/// Environment variable with the profile to use, see [`Workspace::locate`].
+pub const CONFIG_ENV: &str = "GAMACROS_CONFIG";
+
+const APP_SUPPORT_WORKSPACE_PATH: &str = "Library/Application Support/gamacros";
+const CACHES_PATH: &str = "Library/Caches/gamacros";
+const XDG_WORKSPACE_PATH: &str = "gamacros";
 const PROFILE_FILE_NAME: &str = "gc_profile.yaml";
+const STATE_FILE_NAME: &str = "state.json";
 
 pub struct Workspace {
     path: PathBuf,
+    profile: PathBuf,
 }
 
 impl Workspace {
@@ -28,7 +38,47 @@ impl Workspace {
             ));
         }
 
-        Ok(Self { path })
+        let profile = path.join(PROFILE_FILE_NAME);
+        Ok(Self { path, profile })
+    }
+
+    /// Opens the workspace of a profile file. The workspace is the directory
+    /// of the file, or `path` itself if it is a directory.
+    pub fn with_profile(path: &Path) -> Result<Self, WorkspaceError> {
+        if path.is_dir() {
+            return Self::new(Some(path));
+        }
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut workspace = Self::new(Some(dir))?;
+        workspace.profile = path.to_owned();
+        Ok(workspace)
+    }
+
+    /// Finds the workspace to use, in order of precedence:
+    ///
+    /// 1. the `workspace` directory (`--workspace`);
+    /// 2. the `config` profile (`--config`);
+    /// 3. the profile in the `GAMACROS_CONFIG` environment variable;
+    /// 4. the [default path](Self::default_path).
+    pub fn locate(
+        workspace: Option<&Path>,
+        config: Option<&Path>,
+    ) -> Result<Self, WorkspaceError> {
+        if workspace.is_some() {
+            return Self::new(workspace);
+        }
+        let config = config.map(Path::to_owned).or_else(|| {
+            std::env::var_os(CONFIG_ENV)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        });
+        match config {
+            Some(config) => Self::with_profile(&config),
+            None => Self::new(None),
+        }
     }
 
     #[cfg(target_os = "macos")]
@@ -64,15 +114,176 @@ impl Workspace {
     }
 
     pub fn profile_path(&self) -> PathBuf {
-        self.path.join(PROFILE_FILE_NAME)
+        self.profile.clone()
     }
 
+    /// File the daemon keeps its runtime state in across restarts, whichever
+    /// workspace is used: `~/Library/Application Support/gamacros/state.json`
+    /// on macOS, `$XDG_STATE_HOME/gamacros/state.json`
+    /// (`~/.local/state/gamacros/state.json`) elsewhere.
+    pub fn state_path() -> Result<PathBuf, WorkspaceError> {
+        let home = home_dir()?;
+        let state_home = xdg_home("XDG_STATE_HOME", &home, ".local/state");
+        Ok(state_dir(&home, state_home).join(STATE_FILE_NAME))
+    }
+
+    /// Stores calibration of a device in the profile's `calibration` section.
+    /// Calibration of other devices is preserved.
+    pub fn save_calibration(
+        &self,
+        device: ControllerId,
+        calibration: Calibration,
+    ) -> Result<(), WorkspaceError> {
+        let path = self.profile_path();
+        let content = if path.exists() {
+            std::fs::read_to_string(&path)?
+        } else {
+            "version: 1\n".to_string()
+        };
+
+        let mut map = parse_profile(&content)?.calibration;
+        map.insert(device, calibration);
+        let content = splice_section(&content, &render_section(&map));
+        std::fs::write(&path, content)?;
+        Ok(())
+    }
+
+    /// `$XDG_CONFIG_HOME/gamacros` (`~/.config/gamacros`) if it exists,
+    /// then `~/Library/Application Support/gamacros`. Without either, the
+    /// latter on macOS and the former elsewhere.
     pub fn default_path() -> Result<PathBuf, WorkspaceError> {
-        let path = std::env::var("HOME")
-            .map(PathBuf::from)
-            .map(|p| p.join(DEFAULT_WORKSPACE_PATH))
-            .map_err(|_| WorkspaceError::EnvVarNotSet("HOME".to_string()))?;
+        let home = home_dir()?;
+        let config_home = xdg_home("XDG_CONFIG_HOME", &home, ".config");
+
+        Ok(pick_default_path(
+            config_home.join(XDG_WORKSPACE_PATH),
+            home.join(APP_SUPPORT_WORKSPACE_PATH),
+            Path::is_dir,
+        ))
+    }
+}
+
+fn home_dir() -> Result<PathBuf, WorkspaceError> {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .map_err(|_| WorkspaceError::EnvVarNotSet("HOME".to_string()))
+}
+
+/// The XDG base directory in `var`, `home/fallback` when it is not set.
+fn xdg_home(var: &str, home: &Path, fallback: &str) -> PathBuf {
+    std::env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(fallback))
+}
+
+/// App cache directory, `~/Library/Caches/gamacros` on macOS and
+/// `$XDG_CACHE_HOME/gamacros` elsewhere.
+pub(crate) fn app_cache_dir() -> Result<PathBuf, WorkspaceError> {
+    let home = home_dir()?;
+    let cache_home = xdg_home("XDG_CACHE_HOME", &home, ".cache");
+    Ok(cache_dir(&home, cache_home))
+}
+
+fn cache_dir(home: &Path, cache_home: PathBuf) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        home.join(CACHES_PATH)
+    } else {
+        cache_home.join(XDG_WORKSPACE_PATH)
+    }
+}
+
+fn state_dir(home: &Path, state_home: PathBuf) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        home.join(APP_SUPPORT_WORKSPACE_PATH)
+    } else {
+        state_home.join(XDG_WORKSPACE_PATH)
+    }
+}
+
+fn pick_default_path(
+    xdg: PathBuf,
+    app_support: PathBuf,
+    exists: impl Fn(&Path) -> bool,
+) -> PathBuf {
+    if exists(&xdg) {
+        xdg
+    } else if exists(&app_support) || cfg!(target_os = "macos") {
+        app_support
+    } else {
+        xdg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_path_prefers_existing_directories() {
+        let xdg = PathBuf::from("/home/user/.config/gamacros");
+        let app_support =
+            PathBuf::from("/home/user/Library/Application Support/gamacros");
+        let pick = |existing: &[&PathBuf]| {
+            pick_default_path(xdg.clone(), app_support.clone(), |path| {
+                existing.iter().any(|existing| existing.as_path() == path)
+            })
+        };
+
+        assert_eq!(pick(&[&xdg, &app_support]), xdg);
+        assert_eq!(pick(&[&app_support]), app_support);
+        let fallback = if cfg!(target_os = "macos") {
+            &app_support
+        } else {
+            &xdg
+        };
+        assert_eq!(&pick(&[]), fallback);
+    }
+
+    #[test]
+    fn app_dirs_are_per_platform() {
+        let home = Path::new("/home/user");
+        let dir = state_dir(home, home.join(".local/state"));
+        let expected = if cfg!(target_os = "macos") {
+            home.join("Library/Application Support/gamacros")
+        } else {
+            home.join(".local/state/gamacros")
+        };
+        assert_eq!(dir, expected);
+
+        let dir = cache_dir(home, home.join(".cache"));
+        let expected = if cfg!(target_os = "macos") {
+            home.join("Library/Caches/gamacros")
+        } else {
+            home.join(".cache/gamacros")
+        };
+        assert_eq!(dir, expected);
+    }
+
+    #[test]
+    fn workspace_takes_precedence_over_config() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-locate-{}", std::process::id()));
+        let workspace_dir = dir.join("workspace");
+        let config = dir.join("config").join("desk.yaml");
+
+        let workspace = Workspace::locate(None, Some(&config)).unwrap();
+        assert_eq!(workspace.path(), dir.join("config"));
+        assert_eq!(workspace.profile_path(), config);
+
+        let workspace =
+            Workspace::locate(Some(&workspace_dir), Some(&config)).unwrap();
+        assert_eq!(
+            workspace.profile_path(),
+            workspace_dir.join(PROFILE_FILE_NAME)
+        );
 
-        Ok(path)
+        // A directory as config uses the profile in it
+        let workspace = Workspace::locate(None, Some(&workspace_dir)).unwrap();
+        assert_eq!(
+            workspace.profile_path(),
+            workspace_dir.join(PROFILE_FILE_NAME)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -16,6 +16,12 @@ fn build_profile_simple(button: Button, combo: KeyCombo) -> Profile {
         ButtonRule {
             action: ButtonAction::Keystroke(Arc::new(combo)),
             vibrate: None,
+            on_release: None,
+            toggle: false,
+            shell_limits: Default::default(),
+            key_delay: None,
+            target: None,
+            source: Default::default(),
         },
     );
     app.buttons = buttons;
@@ -23,9 +29,36 @@ fn build_profile_simple(button: Button, combo: KeyCombo) -> Profile {
     rules.insert("bench.app".into(), app);
     Profile {
         controllers: Default::default(),
+        serial_controllers: Default::default(),
         blacklist: Default::default(),
         rules,
         shell: None,
+        gamepad_backend: None,
+        keyboard_backend: Default::default(),
+        ignore_virtual: false,
+        trigger_buttons: true,
+        pause_on_fullscreen: false,
+        announce_on_connect: false,
+        calibration: Default::default(),
+        controller_mappings: Default::default(),
+        hid_devices: Vec::new(),
+        chord_window: Default::default(),
+        ignore_own_activity: Default::default(),
+        activation_debounce: Default::default(),
+        transient_apps: Vec::new(),
+        reconnect_grace: Default::default(),
+        axis_interval: Default::default(),
+        key_delay: Default::default(),
+        global: None,
+        patterns: Vec::new(),
+        osc: None,
+        overlay: None,
+        speech: None,
+        sets: Vec::new(),
+        websocket: None,
+        http: None,
+        log: Default::default(),
+        watchdog: Default::default(),
     }
 }
 
@@ -38,13 +71,18 @@ pub fn bench_button_path(c: &mut Criterion) {
     g.set_workspace(profile);
     g.set_active_app("bench.app");
     let id: ControllerId = 1;
-    g.add_controller(ControllerInfo {
-        id,
-        name: "bench".to_string(),
-        supports_rumble: false,
-        vendor_id: 0,
-        product_id: 0,
-    });
+    g.add_controller_with(
+        ControllerInfo {
+            id,
+            name: "bench".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        },
+        |_| {},
+    );
     let button = Button::A;
 
     c.bench_function("buttons_press_release_single", |b| {
@@ -58,9 +96,18 @@ pub fn bench_button_path(c: &mut Criterion) {
                     match a {
                         Action::KeyPress(_)
                         | Action::Rumble { .. }
-                        | Action::Shell(_)
+                        | Action::Shell { .. }
+                        | Action::Shortcut { .. }
+                        | Action::System(_)
+                        | Action::Window(_)
+                        | Action::SwitchSpace(_)
+                        | Action::CycleApp(_)
                         | Action::Macros(_)
+                        | Action::WithKeyDelay(..)
+                        | Action::WithTarget(..)
                         | Action::MouseMove { .. }
+                        | Action::MouseWarp(_)
+                        | Action::MouseClick(_)
                         | Action::Scroll { .. }
                         | Action::KeyTap(_)
                         | Action::KeyRelease(_) => {
@@ -78,9 +125,18 @@ pub fn bench_button_path(c: &mut Criterion) {
                     match a {
                         Action::KeyPress(_)
                         | Action::Rumble { .. }
-                        | Action::Shell(_)
+                        | Action::Shell { .. }
+                        | Action::Shortcut { .. }
+                        | Action::System(_)
+                        | Action::Window(_)
+                        | Action::SwitchSpace(_)
+                        | Action::CycleApp(_)
                         | Action::Macros(_)
+                        | Action::WithKeyDelay(..)
+                        | Action::WithTarget(..)
                         | Action::MouseMove { .. }
+                        | Action::MouseWarp(_)
+                        | Action::MouseClick(_)
                         | Action::Scroll { .. }
                         | Action::KeyTap(_)
                         | Action::KeyRelease(_) => {
@@ -1,7 +1,7 @@
 use codspeed_criterion_compat::{black_box, criterion_group, criterion_main, Criterion};
 use gamacros_gamepad::{Axis as CtrlAxis, ControllerId, ControllerInfo};
 use gamacros_workspace::{
-    AppRules, Profile, StickMode, StickRules, ArrowsParams, StickSide,
+    AppRules, Profile, StickMode, StickRules, ArrowsParams, DeadzoneShape, StickSide,
 };
 use gamacrosd::app::{Action, Gamacros};
 
@@ -13,19 +13,50 @@ fn build_profile_arrows() -> Profile {
         StickSide::Left,
         StickMode::Arrows(ArrowsParams {
             deadzone: 0.2,
+            deadzone_shape: DeadzoneShape::Circular,
             repeat_delay_ms: 200,
             repeat_interval_ms: 40,
+            eight_way: false,
             invert_x: false,
             invert_y: false,
-        }),
+            filter: Default::default(),
+        })
+        .into(),
     );
     app.sticks = sticks;
     rules.insert("bench.app".into(), app);
     Profile {
         controllers: Default::default(),
+        serial_controllers: Default::default(),
         blacklist: Default::default(),
         rules,
         shell: None,
+        gamepad_backend: None,
+        keyboard_backend: Default::default(),
+        ignore_virtual: false,
+        trigger_buttons: true,
+        pause_on_fullscreen: false,
+        announce_on_connect: false,
+        calibration: Default::default(),
+        controller_mappings: Default::default(),
+        hid_devices: Vec::new(),
+        chord_window: Default::default(),
+        ignore_own_activity: Default::default(),
+        activation_debounce: Default::default(),
+        transient_apps: Vec::new(),
+        reconnect_grace: Default::default(),
+        axis_interval: Default::default(),
+        key_delay: Default::default(),
+        global: None,
+        patterns: Vec::new(),
+        osc: None,
+        overlay: None,
+        speech: None,
+        sets: Vec::new(),
+        websocket: None,
+        http: None,
+        log: Default::default(),
+        watchdog: Default::default(),
     }
 }
 
@@ -36,13 +67,18 @@ pub fn bench_sticks_arrows(c: &mut Criterion) {
     g.set_workspace(profile);
     g.set_active_app("bench.app");
     let id: ControllerId = 1;
-    g.add_controller(ControllerInfo {
-        id,
-        name: "bench".to_string(),
-        supports_rumble: false,
-        vendor_id: 0,
-        product_id: 0,
-    });
+    g.add_controller_with(
+        ControllerInfo {
+            id,
+            name: "bench".to_string(),
+            supports_rumble: false,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        },
+        |_| {},
+    );
 
     // Simulate diagonal movement around unit circle
     c.bench_function("sticks_arrows_tick", |b| {
@@ -51,20 +87,29 @@ pub fn bench_sticks_arrows(c: &mut Criterion) {
                 let angle = (t as f32) * 0.3926991; // ~22.5 deg steps
                 let x = angle.cos();
                 let y = angle.sin();
-                g.on_axis_motion(id, CtrlAxis::LeftX, x);
-                g.on_axis_motion(id, CtrlAxis::LeftY, y);
+                g.on_axis_motion_with(id, CtrlAxis::LeftX, x, |_| {});
+                g.on_axis_motion_with(id, CtrlAxis::LeftY, y, |_| {});
                 let mut n = 0usize;
                 g.on_tick_with(|a| {
                     {
                         match a {
                             Action::KeyTap(_)
                             | Action::MouseMove { .. }
+                            | Action::MouseWarp(_)
+                            | Action::MouseClick(_)
                             | Action::Scroll { .. }
                             | Action::KeyPress(_)
                             | Action::KeyRelease(_)
                             | Action::Rumble { .. }
-                            | Action::Shell(_)
-                            | Action::Macros(_) => {
+                            | Action::Shell { .. }
+                            | Action::Shortcut { .. }
+                            | Action::System(_)
+                            | Action::Window(_)
+                            | Action::SwitchSpace(_)
+                            | Action::CycleApp(_)
+                            | Action::Macros(_)
+                            | Action::WithKeyDelay(..)
+                            | Action::WithTarget(..) => {
                                 n += 1;
                             }
                         };
@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bitcode::{Decode, Encode};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use crate::app::Action;
+use super::{describe_action, latency_us, ControllerStatus};
+
+/// Daemon state change sent to socket subscribers.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum StateEvent {
+    App {
+        bundle_id: String,
+    },
+    Controllers {
+        controllers: Vec<ControllerStatus>,
+    },
+    Action {
+        action: String,
+        detail: Option<String>,
+        /// Microseconds from the controller event to the action, `None`
+        /// for actions not caused by one.
+        latency_us: Option<u64>,
+    },
+    /// Pause state after a manual or automatic change.
+    Paused {
+        manual: bool,
+        automatic: bool,
+    },
+}
+
+impl StateEvent {
+    /// Event for an executed action, `None` for stick movement.
+    pub fn action(action: &Action, latency: Option<Duration>) -> Option<Self> {
+        let (action, detail) = describe_action(action)?;
+        Some(Self::Action {
+            action: action.to_string(),
+            detail,
+            latency_us: latency.map(latency_us),
+        })
+    }
+}
+
+#[derive(Default)]
+struct FeedState {
+    subscribers: Vec<Sender<StateEvent>>,
+    app: Option<StateEvent>,
+    controllers: Option<StateEvent>,
+    action: Option<StateEvent>,
+    paused: Option<StateEvent>,
+}
+
+/// Fans state events out to subscribers. New subscribers first receive
+/// the latest app, controllers, action and pause state.
+#[derive(Clone, Default)]
+pub struct StateFeed {
+    state: Arc<Mutex<FeedState>>,
+}
+
+impl StateFeed {
+    pub fn publish(&self, event: StateEvent) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        let latest = match event {
+            StateEvent::App { .. } => &mut state.app,
+            StateEvent::Controllers { .. } => &mut state.controllers,
+            StateEvent::Action { .. } => &mut state.action,
+            StateEvent::Paused { .. } => &mut state.paused,
+        };
+        *latest = Some(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<StateEvent> {
+        let (tx, rx) = unbounded();
+        let mut state = self.state.lock().unwrap();
+        for event in [&state.app, &state.controllers, &state.action, &state.paused]
+            .into_iter()
+            .flatten()
+        {
+            let _ = tx.send(event.clone());
+        }
+        state.subscribers.push(tx);
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_latest_state_to_new_subscribers() {
+        let feed = StateFeed::default();
+        let early = feed.subscribe();
+        feed.publish(StateEvent::App {
+            bundle_id: "com.apple.Safari".into(),
+        });
+        feed.publish(StateEvent::App {
+            bundle_id: "com.apple.Terminal".into(),
+        });
+        assert_eq!(early.try_iter().count(), 2);
+
+        let late = feed.subscribe();
+        assert_eq!(
+            late.try_iter().collect::<Vec<_>>(),
+            [StateEvent::App {
+                bundle_id: "com.apple.Terminal".into()
+            }]
+        );
+    }
+}
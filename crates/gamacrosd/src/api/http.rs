@@ -0,0 +1,194 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bitcode::{Decode, Encode};
+use colored::Colorize;
+use gamacros_gamepad::ControllerId;
+use serde::Serialize;
+
+use crate::{print_debug, print_error, print_info};
+use super::ApiResult;
+
+/// How often the listener checks for shutdown while idle.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time a client has to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A connected controller as reported by `/status`.
+#[derive(Debug, Clone, Serialize, PartialEq, Encode, Decode)]
+pub struct ControllerStatus {
+    pub id: ControllerId,
+    pub name: String,
+}
+
+/// Privacy permissions reported by `/status`, see `gamacrosd doctor`.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PermissionsStatus {
+    pub accessibility: bool,
+    pub input_monitoring: bool,
+}
+
+/// Daemon state reported by `/status`, updated by the event loop.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct DaemonStatus {
+    pub active_app: String,
+    pub profile_loaded: bool,
+    pub paused: bool,
+    pub auto_paused: bool,
+    pub controllers: Vec<ControllerStatus>,
+    pub permissions: PermissionsStatus,
+}
+
+/// Status shared between the event loop and the HTTP server.
+pub type SharedStatus = Arc<Mutex<DaemonStatus>>;
+
+/// Response body of `/status`.
+#[derive(Serialize)]
+struct StatusBody<'a> {
+    status: &'static str,
+    version: &'static str,
+    uptime_secs: u64,
+    #[serde(flatten)]
+    daemon: &'a DaemonStatus,
+}
+
+/// Minimal HTTP server answering `GET /healthz` and `GET /status` with JSON.
+/// The server stops when dropped.
+pub struct HttpStatusServer {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl HttpStatusServer {
+    pub fn start(address: SocketAddr, status: SharedStatus) -> ApiResult<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        print_info!("http status listening at {address}");
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let started = Instant::now();
+        thread::Builder::new()
+            .name("gamacrosd-http-api".into())
+            .spawn(move || {
+                while !stop_flag.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(e) = handle(stream, &status, started) {
+                                print_debug!("http request failed: {e}");
+                            }
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(POLL_INTERVAL);
+                        }
+                        Err(e) => {
+                            print_error!("http accept error: {e}");
+                            break;
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { address, stop })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for HttpStatusServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn handle(
+    mut stream: TcpStream,
+    status: &SharedStatus,
+    started: Instant,
+) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let (code, body) = respond(&request_line, status, started);
+    let response = format!(
+        "HTTP/1.1 {code}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes())
+}
+
+/// Status line and JSON body for a request line like `GET /status HTTP/1.1`.
+fn respond(
+    request_line: &str,
+    status: &SharedStatus,
+    started: Instant,
+) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return ("400 Bad Request", r#"{"error":"bad request"}"#.into());
+    };
+    let path = target.split('?').next().unwrap_or(target);
+    if method != "GET" {
+        return (
+            "405 Method Not Allowed",
+            r#"{"error":"method not allowed"}"#.into(),
+        );
+    }
+    match path {
+        "/healthz" => ("200 OK", r#"{"status":"ok"}"#.into()),
+        "/status" => {
+            let daemon = status.lock().unwrap();
+            let body = StatusBody {
+                status: "ok",
+                version: env!("CARGO_PKG_VERSION"),
+                uptime_secs: started.elapsed().as_secs(),
+                daemon: &daemon,
+            };
+            match serde_json::to_string(&body) {
+                Ok(json) => ("200 OK", json),
+                Err(e) => {
+                    ("500 Internal Server Error", format!(r#"{{"error":"{e}"}}"#))
+                }
+            }
+        }
+        _ => ("404 Not Found", r#"{"error":"not found"}"#.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responds_to_routes() {
+        let status = SharedStatus::default();
+        status.lock().unwrap().active_app = "com.apple.Safari".into();
+        let started = Instant::now();
+
+        let (code, body) = respond("GET /healthz HTTP/1.1\r\n", &status, started);
+        assert_eq!((code, body.as_str()), ("200 OK", r#"{"status":"ok"}"#));
+
+        let (code, body) = respond("GET /status HTTP/1.1\r\n", &status, started);
+        assert_eq!(code, "200 OK");
+        assert!(body.contains(r#""active_app":"com.apple.Safari""#));
+        assert!(body.contains(r#""permissions":{"accessibility":false"#));
+
+        assert_eq!(
+            respond("GET /x HTTP/1.1", &status, started).0,
+            "404 Not Found"
+        );
+        assert_eq!(
+            respond("POST /status HTTP/1.1", &status, started).0,
+            "405 Method Not Allowed"
+        );
+        assert_eq!(respond("", &status, started).0, "400 Bad Request");
+    }
+}
@@ -1,34 +1,110 @@
+mod feed;
 mod unix_sock;
+mod websocket;
+mod http;
 
 use std::thread::JoinHandle;
+use std::time::Duration;
 
-pub use unix_sock::{UnixSocket};
+pub use feed::{StateEvent, StateFeed};
+pub use unix_sock::{read_token, UnixSocket};
+pub use websocket::{BridgeEvent, Broadcaster, WebSocketBridge};
+pub use http::{ControllerStatus, HttpStatusServer, PermissionsStatus, SharedStatus};
 
 use bitcode::{Decode, Encode};
 use crossbeam_channel::Sender;
 use gamacros_gamepad::ControllerId;
+use serde::Deserialize;
 use thiserror::Error;
 
+use crate::app::Action;
+
 /// Error type for api operations.
 #[derive(Error, Debug)]
 pub enum ApiError {
     #[error("failed to send event")]
     IoError(#[from] std::io::Error),
+    #[error("failed to decode event: {0}")]
+    Decode(#[from] bitcode::Error),
 }
 
 /// Convenient result alias for api operations.
 pub type ApiResult<T> = std::result::Result<T, ApiError>;
 
 /// gamacrosd api control command.
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Command {
-    Rumble { id: Option<ControllerId>, ms: u32 },
+    Rumble {
+        #[serde(default)]
+        id: Option<ControllerId>,
+        ms: u32,
+    },
+    /// Keep the connection open and stream [`StateEvent`]s to it.
+    Subscribe,
+    /// Release held keys, stop rumble and exit.
+    Shutdown,
+    /// Stop performing actions until resumed.
+    Pause,
+    Resume,
+    /// Apply the rules of `app` whichever app is frontmost, `None` goes back
+    /// to the rules of the frontmost app.
+    PinApp {
+        #[serde(default)]
+        app: Option<String>,
+    },
+    /// Reply with the connected controllers, so clients started after the
+    /// daemon do not wait for a reconnect. Unix socket only.
+    GetControllers,
+}
+
+/// Name and detail of an executed action as reported to api clients.
+/// Stick movement is not reported, it fires on every tick.
+pub(crate) fn describe_action(
+    action: &Action,
+) -> Option<(&'static str, Option<String>)> {
+    let description = match action {
+        Action::KeyPress(combo) => ("key_press", Some(combo.to_string())),
+        Action::KeyRelease(combo) => ("key_release", Some(combo.to_string())),
+        Action::KeyTap(combo) => ("key_tap", Some(combo.to_string())),
+        Action::Macros(macros) => {
+            let combos: Vec<String> =
+                macros.iter().map(ToString::to_string).collect();
+            ("macros", Some(combos.join(", ")))
+        }
+        Action::Shell { command, .. } => ("shell", Some(command.clone())),
+        Action::Shortcut { name, .. } => ("shortcut", Some(name.clone())),
+        Action::System(action) => ("system", Some(action.to_string())),
+        Action::Window(action) => ("window", Some(action.to_string())),
+        Action::SwitchSpace(target) => ("space", Some(target.to_string())),
+        Action::MouseWarp(target) => ("mouse", Some(target.to_string())),
+        Action::MouseClick(button) => ("mouse_click", Some(button.to_string())),
+        Action::CycleApp(apps) => {
+            let apps: Vec<&str> = apps.iter().map(|app| app.as_str()).collect();
+            ("cycle_app", Some(apps.join(" | ")))
+        }
+        Action::Rumble { id, .. } => ("rumble", Some(id.to_string())),
+        Action::MouseMove { .. } | Action::Scroll { .. } => return None,
+        Action::WithKeyDelay(action, _) | Action::WithTarget(action, _) => {
+            return describe_action(action);
+        }
+    };
+    Some(description)
+}
+
+/// Latency from a controller event to its action as reported to api clients.
+pub(crate) fn latency_us(latency: Duration) -> u64 {
+    latency.as_micros().try_into().unwrap_or(u64::MAX)
 }
 
 /// gamacrosd api events transport.
 /// listener that can receive api commands from the outer world,
 /// and sender that can send api commands from the outer world to the gamacrosd.
 pub trait ApiTransport {
-    fn listen_events(&self, tx: Sender<Command>) -> ApiResult<JoinHandle<()>>;
+    fn listen_events(
+        &self,
+        tx: Sender<Command>,
+        feed: StateFeed,
+    ) -> ApiResult<JoinHandle<()>>;
     fn send_event(&self, event: Command) -> ApiResult<()>;
 }
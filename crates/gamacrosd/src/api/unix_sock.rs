@@ -1,5 +1,7 @@
 use std::fs;
-use std::io::{BufWriter, Read, Write};
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::PermissionsExt;
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
 use std::thread::{self, JoinHandle};
@@ -7,81 +9,227 @@ use std::thread::{self, JoinHandle};
 use crossbeam_channel::Sender;
 use colored::Colorize;
 use bitcode::{Encode, Decode};
+use gamacros_gamepad::{ControllerInfo, ControllerList};
 
-use crate::{print_error, print_info};
-use super::{Command, ApiTransport, ApiResult};
+use crate::{print_debug, print_error, print_info};
+use super::{Command, ApiTransport, ApiResult, StateEvent, StateFeed};
 
 const SOCKET_FILE_NAME: &str = "api.sock";
+/// Optional file with a token clients must send along with each command.
+const TOKEN_FILE_NAME: &str = "api.token";
+/// Largest frame accepted, well above any command or reply.
+const MAX_FRAME_LEN: usize = 256 * 1024;
 
 #[derive(Encode, Decode)]
 pub struct SocketCommand {
     command: Command,
+    token: Option<String>,
 }
 
+/// Unix socket api. Both sides exchange length-prefixed bitcode frames.
+/// A connection may carry several commands; after `Command::Subscribe`
+/// the daemon streams `StateEvent` frames until the client disconnects.
+/// `Command::GetControllers` is answered with a frame of `ControllerInfo`s.
+///
+/// Only processes of the daemon's user may connect. If the workspace
+/// contains an `api.token` file, commands must also carry its contents.
 pub struct UnixSocket {
     socket_path: PathBuf,
+    token: Option<String>,
+    controllers: Option<ControllerList>,
 }
 
 impl UnixSocket {
     pub fn new<P: AsRef<Path>>(workspace_path: P) -> Self {
         let socket_path = workspace_path.as_ref().join(SOCKET_FILE_NAME);
+        let token = read_token(workspace_path.as_ref());
 
-        Self { socket_path }
+        Self {
+            socket_path,
+            token,
+            controllers: None,
+        }
     }
-}
 
-impl UnixSocket {
-    fn handle_connection(mut stream: UnixStream, tx: &Sender<Command>) {
-        let mut length_buffer = [0u8; 4];
-        let _ = stream.read_exact(&mut length_buffer);
-        if length_buffer == [0u8; 4] {
-            let _ = stream.write_all(b"ERR empty\n");
-            return;
+    /// Answers `Command::GetControllers` from `controllers`.
+    pub fn with_controllers(mut self, controllers: ControllerList) -> Self {
+        self.controllers = Some(controllers);
+        self
+    }
+
+    fn command(&self, command: Command) -> SocketCommand {
+        SocketCommand {
+            command,
+            token: self.token.clone(),
         }
+    }
+
+    /// Subscribes to daemon state and returns the events as they arrive.
+    pub fn subscribe(&self) -> ApiResult<Subscription> {
+        let mut stream = UnixStream::connect(&self.socket_path)?;
+        let cmd = self.command(Command::Subscribe);
+        write_frame(&mut stream, &bitcode::encode(&cmd))?;
+        Ok(Subscription { stream })
+    }
 
-        let length = u32::from_be_bytes(length_buffer) as usize;
-        if length == 0 {
-            let _ = stream.write_all(b"ERR empty\n");
-            return;
+    /// Returns the controllers connected to the daemon.
+    pub fn controllers(&self) -> ApiResult<Vec<ControllerInfo>> {
+        let mut stream = UnixStream::connect(&self.socket_path)?;
+        let cmd = self.command(Command::GetControllers);
+        write_frame(&mut stream, &bitcode::encode(&cmd))?;
+        match read_frame(&mut stream)? {
+            Some(data) => Ok(bitcode::decode(&data)?),
+            None => Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
         }
+    }
+}
 
-        // Читаем данные
-        let mut data_buffer = vec![0u8; length];
-        let Ok(_) = stream.read_exact(&mut data_buffer) else {
-            let _ = stream.write_all(b"ERR read failed\n");
-            return;
-        };
+/// Secret of the `api.token` file in the workspace, if there is one.
+pub fn read_token(workspace_path: &Path) -> Option<String> {
+    fs::read_to_string(workspace_path.join(TOKEN_FILE_NAME))
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Stream of state events from a subscribed connection.
+pub struct Subscription {
+    stream: UnixStream,
+}
+
+impl Iterator for Subscription {
+    type Item = ApiResult<StateEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match read_frame(&mut self.stream) {
+            Ok(Some(data)) => Some(bitcode::decode(&data).map_err(Into::into)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
 
-        // Десериализуем
-        let command = match bitcode::decode(&data_buffer) {
-            Ok(cmd) => cmd,
-            Err(err) => {
-                print_error!("failed to decode command: {err}");
-                let _ = stream.write_all(format!("ERR {err}\n").as_bytes());
+impl UnixSocket {
+    fn handle_connection(
+        mut stream: UnixStream,
+        token: Option<&str>,
+        tx: &Sender<Command>,
+        feed: &StateFeed,
+        controllers: Option<&ControllerList>,
+    ) {
+        match peer_uid(&stream) {
+            // SAFETY: geteuid has no preconditions and cannot fail.
+            Ok(uid) if uid == unsafe { libc::geteuid() } => {}
+            Ok(uid) => {
+                print_error!("rejected api client of user {uid}");
+                let _ = stream.write_all(b"ERR unauthorized\n");
                 return;
             }
-        };
+            Err(e) => {
+                print_error!("failed to read api client credentials: {e}");
+                return;
+            }
+        }
 
-        tx.send(command).unwrap();
+        loop {
+            let data = match read_frame(&mut stream) {
+                Ok(Some(data)) if data.is_empty() => {
+                    let _ = stream.write_all(b"ERR empty\n");
+                    return;
+                }
+                Ok(Some(data)) => data,
+                Ok(None) => return,
+                Err(_) => {
+                    let _ = stream.write_all(b"ERR read failed\n");
+                    return;
+                }
+            };
+
+            let command = match bitcode::decode::<SocketCommand>(&data) {
+                Ok(cmd) if token.is_some() && cmd.token.as_deref() != token => {
+                    print_error!("rejected api command with a wrong token");
+                    let _ = stream.write_all(b"ERR unauthorized\n");
+                    return;
+                }
+                Ok(cmd) => cmd.command,
+                Err(err) => {
+                    print_error!("failed to decode command: {err}");
+                    let _ = stream.write_all(format!("ERR {err}\n").as_bytes());
+                    return;
+                }
+            };
+
+            match command {
+                Command::Subscribe => {
+                    Self::stream_events(stream, feed);
+                    return;
+                }
+                Command::GetControllers => {
+                    let mut list = controllers
+                        .map(ControllerList::controllers)
+                        .unwrap_or_default();
+                    list.sort_by_key(|info| info.id);
+                    if write_frame(&mut stream, &bitcode::encode(&list)).is_err() {
+                        return;
+                    }
+                }
+                command => {
+                    if tx.send(command).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes state events to the client until it disconnects.
+    fn stream_events(mut stream: UnixStream, feed: &StateFeed) {
+        for event in feed.subscribe() {
+            if let Err(e) = write_frame(&mut stream, &bitcode::encode(&event)) {
+                print_debug!("subscriber disconnected: {e}");
+                return;
+            }
+        }
     }
 }
 
 impl ApiTransport for UnixSocket {
-    fn listen_events(&self, tx: Sender<Command>) -> ApiResult<JoinHandle<()>> {
+    fn listen_events(
+        &self,
+        tx: Sender<Command>,
+        feed: StateFeed,
+    ) -> ApiResult<JoinHandle<()>> {
         let socket_path = self.socket_path.clone();
         if socket_path.exists() {
             fs::remove_file(&socket_path)?;
         }
         let listener = UnixListener::bind(&socket_path)?;
+        fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o600))?;
         print_info!("unix socket api listening at {}", socket_path.display());
 
+        let token = self.token.clone();
+        let controllers = self.controllers.clone();
         let handle = thread::Builder::new()
             .name("gamacrosd-socket-api".into())
             .spawn(move || {
                 for stream in listener.incoming() {
                     match stream {
                         Ok(stream) => {
-                            Self::handle_connection(stream, &tx);
+                            let tx = tx.clone();
+                            let feed = feed.clone();
+                            let token = token.clone();
+                            let controllers = controllers.clone();
+                            let _ = thread::Builder::new()
+                                .name("gamacrosd-socket-client".into())
+                                .spawn(move || {
+                                    Self::handle_connection(
+                                        stream,
+                                        token.as_deref(),
+                                        &tx,
+                                        &feed,
+                                        controllers.as_ref(),
+                                    );
+                                });
                         }
                         Err(e) => {
                             print_error!("control socket accept error: {}", e);
@@ -97,12 +245,204 @@ impl ApiTransport for UnixSocket {
         let socket_path = self.socket_path.clone();
         let stream = UnixStream::connect(&socket_path)?;
         let mut writer = BufWriter::new(stream);
-        let cmd = SocketCommand { command: event };
-        let encoded = bitcode::encode(&cmd);
-        let length = encoded.len() as u32;
-        writer.write_all(&length.to_be_bytes())?;
-        writer.write_all(&encoded)?;
+        let cmd = self.command(event);
+        write_frame(&mut writer, &bitcode::encode(&cmd))?;
 
         Ok(())
     }
 }
+
+/// Effective user id of the process on the other end of the socket.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred and len point to a valid ucred and its size.
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// Effective user id of the process on the other end of the socket.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: uid and gid are valid out pointers.
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+fn write_frame(writer: &mut impl Write, data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(data)?;
+    writer.flush()
+}
+
+/// Reads a frame, `None` if the peer closed the connection between frames.
+/// Frames longer than `MAX_FRAME_LEN` are rejected before allocating.
+fn read_frame(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut length_buffer = [0u8; 4];
+    match reader.read_exact(&mut length_buffer) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = u32::from_be_bytes(length_buffer) as usize;
+    if length > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("frame of {length} bytes exceeds {MAX_FRAME_LEN}"),
+        ));
+    }
+    let mut data = vec![0u8; length];
+    reader.read_exact(&mut data)?;
+    Ok(Some(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crossbeam_channel::unbounded;
+    use gamacros_gamepad::{BackendKind, ControllerManager};
+
+    #[test]
+    fn streams_state_to_subscribers() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-api-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let socket = UnixSocket::new(&dir);
+        let (tx, rx) = unbounded();
+        let feed = StateFeed::default();
+        feed.publish(StateEvent::App {
+            bundle_id: "com.apple.Safari".into(),
+        });
+        socket.listen_events(tx, feed.clone()).unwrap();
+
+        socket
+            .send_event(Command::Rumble {
+                id: Some(1),
+                ms: 50,
+            })
+            .unwrap();
+        assert!(matches!(
+            rx.recv().unwrap(),
+            Command::Rumble {
+                id: Some(1),
+                ms: 50
+            }
+        ));
+
+        let mut events = socket.subscribe().unwrap();
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            StateEvent::App {
+                bundle_id: "com.apple.Safari".into()
+            }
+        );
+        let action = StateEvent::Action {
+            action: "shell".into(),
+            detail: Some("ls".into()),
+            latency_us: Some(1200),
+        };
+        feed.publish(action.clone());
+        assert_eq!(events.next().unwrap().unwrap(), action);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn answers_controller_snapshots() {
+        let dir = std::env::temp_dir().join(format!(
+            "gamacros-api-controllers-test-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let manager = ControllerManager::with_backend(BackendKind::Virtual).unwrap();
+        let rx = manager.subscribe();
+        manager.injector().connect(ControllerInfo {
+            id: 3,
+            name: "Pad".into(),
+            supports_rumble: false,
+            vendor_id: 0x054c,
+            product_id: 0x0ce6,
+            serial: None,
+            extra_buttons: Vec::new(),
+        });
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+
+        let socket =
+            UnixSocket::new(&dir).with_controllers(manager.controller_list());
+        let (tx, rx) = unbounded();
+        socket.listen_events(tx, StateFeed::default()).unwrap();
+        let controllers = socket.controllers().unwrap();
+        assert_eq!(controllers.len(), 1);
+        assert_eq!(controllers[0].name, "Pad");
+        // The event loop is not involved
+        assert!(rx.try_recv().is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let mut frame = u32::MAX.to_be_bytes().to_vec();
+        frame.extend_from_slice(b"data");
+        let error = read_frame(&mut frame.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let mut frame = Vec::new();
+        write_frame(&mut frame, b"data").unwrap();
+        assert_eq!(
+            read_frame(&mut frame.as_slice()).unwrap().as_deref(),
+            Some(&b"data"[..])
+        );
+    }
+
+    #[test]
+    fn rejects_commands_with_wrong_token() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-api-token-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(TOKEN_FILE_NAME), "secret\n").unwrap();
+        let socket = UnixSocket::new(&dir);
+        let (tx, rx) = unbounded();
+        socket.listen_events(tx, StateFeed::default()).unwrap();
+
+        let mut stream = UnixStream::connect(&socket.socket_path).unwrap();
+        let cmd = SocketCommand {
+            command: Command::Rumble { id: None, ms: 50 },
+            token: Some("wrong".into()),
+        };
+        write_frame(&mut stream, &bitcode::encode(&cmd)).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "ERR unauthorized\n");
+
+        socket
+            .send_event(Command::Rumble { id: None, ms: 50 })
+            .unwrap();
+        assert!(matches!(
+            rx.recv().unwrap(),
+            Command::Rumble { id: None, .. }
+        ));
+        assert!(rx.try_recv().is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
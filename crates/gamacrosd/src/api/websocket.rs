@@ -0,0 +1,339 @@
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use colored::Colorize;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use gamacros_gamepad::ControllerId;
+use gamacros_workspace::{button_name, ButtonChord, WebSocketSettings};
+use serde::Serialize;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::{header, StatusCode};
+use tungstenite::{Message, WebSocket};
+
+use crate::app::Action;
+use crate::{print_debug, print_error, print_info};
+use super::{describe_action, latency_us, ApiResult, Command};
+
+/// How often idle threads check for new clients, events and shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An event broadcast to WebSocket clients as JSON.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BridgeEvent {
+    App {
+        bundle_id: String,
+    },
+    Chord {
+        controller: ControllerId,
+        buttons: Vec<&'static str>,
+    },
+    Action {
+        action: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        latency_us: Option<u64>,
+    },
+}
+
+impl BridgeEvent {
+    pub fn chord(controller: ControllerId, chord: ButtonChord) -> Self {
+        Self::Chord {
+            controller,
+            buttons: chord.iter().map(button_name).collect(),
+        }
+    }
+
+    /// Event for an executed action, `None` for stick movement.
+    pub fn action(action: &Action, latency: Option<Duration>) -> Option<Self> {
+        let (action, detail) = describe_action(action)?;
+        Some(Self::Action {
+            action,
+            detail,
+            latency_us: latency.map(latency_us),
+        })
+    }
+}
+
+/// Sends events to every connected client.
+#[derive(Clone, Default)]
+pub struct Broadcaster {
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl Broadcaster {
+    pub fn send(&self, event: &BridgeEvent) {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(event) else {
+            return;
+        };
+        clients.retain(|client| client.send(json.clone()).is_ok());
+    }
+}
+
+/// WebSocket server broadcasting daemon events and accepting api commands
+/// as JSON, e.g. `{"rumble": {"id": 0, "ms": 200}}`.
+/// Browsers may only connect from the origins in the settings. Commands
+/// are only accepted from clients that connect with the `api.token` secret,
+/// e.g. `ws://127.0.0.1:8765/?token=…`; without one the bridge only sends
+/// events. The server stops when dropped.
+pub struct WebSocketBridge {
+    settings: WebSocketSettings,
+    broadcaster: Broadcaster,
+    stop: Arc<AtomicBool>,
+}
+
+impl WebSocketBridge {
+    pub fn start(
+        settings: WebSocketSettings,
+        token: Option<String>,
+        tx: Sender<Command>,
+    ) -> ApiResult<Self> {
+        let address = settings.address;
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        print_info!("websocket api listening at {address}");
+
+        let broadcaster = Broadcaster::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients = broadcaster.clients.clone();
+        let access = Arc::new(Access {
+            origins: settings.origins.clone(),
+            token,
+        });
+        let stop_flag = stop.clone();
+        thread::Builder::new()
+            .name("gamacrosd-websocket-api".into())
+            .spawn(move || accept(listener, clients, access, tx, stop_flag))?;
+
+        Ok(Self {
+            settings,
+            broadcaster,
+            stop,
+        })
+    }
+
+    pub fn settings(&self) -> &WebSocketSettings {
+        &self.settings
+    }
+
+    pub fn broadcaster(&self) -> Broadcaster {
+        self.broadcaster.clone()
+    }
+}
+
+impl Drop for WebSocketBridge {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Who may connect to the bridge and send commands.
+struct Access {
+    origins: Vec<String>,
+    token: Option<String>,
+}
+
+fn accept(
+    listener: TcpListener,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    access: Arc<Access>,
+    tx: Sender<Command>,
+    stop: Arc<AtomicBool>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let (events_tx, events_rx) = unbounded();
+                clients.lock().unwrap().push(events_tx);
+                let access = access.clone();
+                let tx = tx.clone();
+                let stop = stop.clone();
+                let _ = thread::Builder::new()
+                    .name("gamacrosd-websocket-client".into())
+                    .spawn(move || {
+                        let client = serve(stream, &access, &events_rx, &tx, &stop);
+                        if let Err(e) = client {
+                            print_debug!("websocket client {peer} closed: {e}");
+                        }
+                    });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) => {
+                print_error!("websocket accept error: {e}");
+                break;
+            }
+        }
+    }
+}
+
+/// Relays events to a client and commands from it until either side closes.
+// The error is tungstenite's own, it only ends the client thread
+#[allow(clippy::result_large_err)]
+fn serve(
+    stream: TcpStream,
+    access: &Access,
+    events: &Receiver<String>,
+    tx: &Sender<Command>,
+    stop: &AtomicBool,
+) -> tungstenite::Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut authorized = false;
+    let check_origin = |request: &Request, response: Response| {
+        let origin = request.headers().get(header::ORIGIN);
+        let origin = origin.map(|origin| origin.to_str().unwrap_or_default());
+        if origin_allowed(origin, &access.origins) {
+            authorized =
+                token_matches(request.uri().query(), access.token.as_deref());
+            return Ok(response);
+        }
+        print_error!("rejected websocket client from origin {origin:?}");
+        let mut rejection = ErrorResponse::new(Some("origin not allowed".into()));
+        *rejection.status_mut() = StatusCode::FORBIDDEN;
+        Err(rejection)
+    };
+    let mut socket =
+        tungstenite::accept_hdr(stream, check_origin).map_err(|e| match e {
+            tungstenite::HandshakeError::Failure(e) => e,
+            tungstenite::HandshakeError::Interrupted(_) => {
+                tungstenite::Error::Io(ErrorKind::WouldBlock.into())
+            }
+        })?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    while !stop.load(Ordering::Relaxed) {
+        for event in events.try_iter() {
+            socket.send(Message::text(event))?;
+        }
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                handle_command(&mut socket, &text, authorized, tx)?
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    socket.close(None)
+}
+
+/// Whether a client sending `origin` may connect. Browsers always send
+/// the origin of the page, other clients usually send none.
+fn origin_allowed(origin: Option<&str>, allowed: &[String]) -> bool {
+    origin.map_or(true, |origin| {
+        allowed.iter().any(|allowed| allowed == origin)
+    })
+}
+
+/// Whether the query of the handshake URL carries `token`. Without a token
+/// nobody may send commands.
+fn token_matches(query: Option<&str>, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return false;
+    };
+    query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .any(|pair| pair.strip_prefix("token=") == Some(token))
+}
+
+#[allow(clippy::result_large_err)]
+fn handle_command(
+    socket: &mut WebSocket<TcpStream>,
+    text: &str,
+    authorized: bool,
+    tx: &Sender<Command>,
+) -> tungstenite::Result<()> {
+    if !authorized {
+        print_error!("rejected websocket command without the api token");
+        return socket.send(Message::text("ERR unauthorized"));
+    }
+    match serde_json::from_str(text) {
+        Ok(command) => {
+            let _ = tx.send(command);
+            Ok(())
+        }
+        Err(e) => socket.send(Message::text(format!("ERR {e}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gamacros_gamepad::Button;
+
+    #[test]
+    fn serializes_events() {
+        let mut chord = ButtonChord::empty();
+        chord.insert(Button::A);
+        chord.insert(Button::LeftShoulder);
+        assert_eq!(
+            serde_json::to_string(&BridgeEvent::chord(0, chord)).unwrap(),
+            r#"{"event":"chord","controller":0,"buttons":["a","lb"]}"#
+        );
+        let action = Action::Shell {
+            command: "ls".into(),
+            limits: Default::default(),
+        };
+        assert_eq!(
+            serde_json::to_string(&BridgeEvent::action(&action, None).unwrap())
+                .unwrap(),
+            r#"{"event":"action","action":"shell","detail":"ls"}"#
+        );
+        let latency = Some(Duration::from_micros(1500));
+        assert_eq!(
+            serde_json::to_string(&BridgeEvent::action(&action, latency).unwrap())
+                .unwrap(),
+            r#"{"event":"action","action":"shell","detail":"ls","latency_us":1500}"#
+        );
+        let scroll = Action::Scroll {
+            h: 1,
+            v: 0,
+            unit: gamacros_control::ScrollUnit::Lines,
+        };
+        assert!(BridgeEvent::action(&scroll, latency).is_none());
+    }
+
+    #[test]
+    fn allows_only_listed_browser_origins() {
+        let allowed = ["http://localhost:3000".to_string()];
+        assert!(origin_allowed(None, &allowed));
+        assert!(origin_allowed(Some("http://localhost:3000"), &allowed));
+        assert!(!origin_allowed(Some("https://example.com"), &allowed));
+        assert!(!origin_allowed(Some("null"), &[]));
+    }
+
+    #[test]
+    fn requires_api_token_for_commands() {
+        let token = Some("secret");
+        assert!(token_matches(Some("token=secret"), token));
+        assert!(token_matches(Some("v=1&token=secret"), token));
+        assert!(!token_matches(Some("token=wrong"), token));
+        assert!(!token_matches(None, token));
+        // Commands are refused while the workspace has no token
+        assert!(!token_matches(Some("token="), None));
+        assert!(!token_matches(None, None));
+    }
+
+    #[test]
+    fn parses_commands() {
+        let command: Command =
+            serde_json::from_str(r#"{"rumble": {"ms": 200}}"#).unwrap();
+        assert!(matches!(command, Command::Rumble { id: None, ms: 200 }));
+    }
+}
@@ -1,19 +1,37 @@
 use std::cell::RefCell;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use ahash::AHashMap;
 
 use colored::Colorize;
 
-use gamacros_control::KeyCombo;
+use gamacros_control::{
+    KeyCombo, MouseButton, MouseTarget, ScrollUnit, SpaceTarget, SystemAction,
+};
+use gamacros_activity::AppNames;
+use gamacros_window::WindowAction;
 use gamacros_bit_mask::Bitmask;
 use gamacros_gamepad::{Button, ControllerId, ControllerInfo, Axis as CtrlAxis};
 use gamacros_workspace::{
-    ButtonAction, ControllerSettings, Macros, Profile, StickRules, StickMode,
+    button_name, AppPattern, AppRules, ButtonAction, ButtonChord, ButtonRule,
+    ControllerSettings, HapticFeedback, Macros, Profile, SetTarget, ShellLimits,
+    StickRules, StickMode, StickSide,
 };
 
-use crate::{app::ButtonPhase, print_debug, print_info};
+use crate::logging::{ACTIONS, ACTIVITY};
+use crate::{app::ButtonPhase, print_debug, print_info, print_warning};
+use super::clock::{Clock, SystemClock};
+use super::scheduler::{Scheduler, Timer, TICK_PERIOD};
 use super::stick::{StickProcessor, CompiledStickRules};
-use super::stick::util::axis_index as stick_axis_index;
+use super::stick::buttons::direction_button;
+use super::stick::util::{axes_for_side, axis_index as stick_axis_index, side_index};
+
+/// Length of the rumble that identifies a newly connected controller.
+const ANNOUNCE_RUMBLE_MS: u32 = 150;
+/// Length of the haptic feedback pulse played after a rule fires.
+const FEEDBACK_RUMBLE_MS: u32 = 40;
+/// Ids of keyboard chords start here, far above controller and HID ids.
+const FIRST_KEY_ID: ControllerId = 0xC000_0000;
 
 #[derive(Debug, Clone)]
 pub enum Action {
@@ -21,10 +39,91 @@ pub enum Action {
     KeyRelease(KeyCombo),
     KeyTap(KeyCombo),
     Macros(Arc<Macros>),
-    Shell(String),
-    MouseMove { dx: i32, dy: i32 },
-    Scroll { h: i32, v: i32 },
-    Rumble { id: ControllerId, ms: u32 },
+    Shell {
+        command: String,
+        limits: ShellLimits,
+    },
+    Shortcut {
+        name: String,
+        limits: ShellLimits,
+    },
+    System(SystemAction),
+    Window(WindowAction),
+    SwitchSpace(SpaceTarget),
+    /// Activate the next running app matching one of the patterns.
+    CycleApp(Arc<[AppPattern]>),
+    MouseMove {
+        dx: i32,
+        dy: i32,
+    },
+    MouseWarp(MouseTarget),
+    MouseClick(MouseButton),
+    Scroll {
+        h: i32,
+        v: i32,
+        unit: ScrollUnit,
+    },
+    Rumble {
+        id: ControllerId,
+        ms: u32,
+        /// Motor strength in `0.0..=1.0`.
+        strength: f32,
+    },
+    /// Run a key action with a rule's own wait between key events.
+    WithKeyDelay(Box<Action>, Duration),
+    /// Run a key action posting the keys to the app with this bundle id,
+    /// whether it is frontmost or not.
+    WithTarget(Box<Action>, Box<str>),
+}
+
+/// Wraps key actions of a rule with its key delay and target app, if it
+/// has them.
+fn with_key_settings(action: Action, rule: &ButtonRule) -> Action {
+    let action = match &rule.target {
+        Some(bundle_id) => Action::WithTarget(Box::new(action), bundle_id.clone()),
+        None => action,
+    };
+    match rule.key_delay {
+        Some(delay) => Action::WithKeyDelay(Box::new(action), delay),
+        None => action,
+    }
+}
+
+/// Press of a chord deferred while a larger chord may still complete.
+#[derive(Debug)]
+struct PendingChord {
+    id: ControllerId,
+    chord: ButtonChord,
+    rules: Vec<ButtonRule>,
+    due: Instant,
+}
+
+/// Actions fired for one controller, to decide on haptic feedback.
+#[derive(Default)]
+struct FiredActions {
+    fired: bool,
+    vibrated: bool,
+}
+
+impl FiredActions {
+    fn track(&mut self, action: &Action) {
+        match action {
+            Action::Rumble { .. } => self.vibrated = true,
+            // Releasing a held key finishes a rule fired before
+            Action::KeyRelease(_) => {}
+            _ => self.fired = true,
+        }
+    }
+}
+
+/// Keystroke held down by a rule: until its chord is released, or for
+/// toggle rules until the chord is pressed again.
+#[derive(Debug)]
+struct HeldKey {
+    id: ControllerId,
+    chord: ButtonChord,
+    keys: KeyCombo,
+    toggle: bool,
 }
 
 #[derive(Debug)]
@@ -33,16 +132,56 @@ struct ControllerState {
     pressed: Bitmask<Button>,
     rumble: bool,
     axes: [f32; 6],
+    /// Direction buttons held by the left and right stick.
+    stick_buttons: [Option<Button>; 2],
+    /// Left and right stick switched to their second mode by a stick
+    /// click. Kept across app switches, sides without a second mode
+    /// ignore it.
+    stick_toggled: [bool; 2],
+    /// Player number, the lowest one free when the controller connected.
+    slot: usize,
 }
 
 pub struct Gamacros {
     pub workspace: Option<Profile>,
     active_app: Box<str>,
+    /// App whose rules apply instead of the frontmost app's.
+    pinned_app: Option<Box<str>>,
+    /// Title of the focused window.
+    window_title: Box<str>,
+    /// Host of the active browser tab, empty when unknown.
+    site_host: Box<str>,
     controllers: AHashMap<ControllerId, ControllerState>,
     sticks: RefCell<StickProcessor>,
+    /// Rules of the active app, before title rules.
+    app_rules: Option<Arc<AppRules>>,
+    /// Title rules of `app_rules` matching the focused window.
+    matched_titles: Vec<usize>,
+    /// Selected profile set, applied on top of the app rules.
+    active_set: Option<Box<str>>,
+    /// Set selected by a fired rule, applied once the event is handled.
+    set_request: RefCell<Option<SetTarget>>,
+    /// Resolved rules of the active app.
+    active_rules: Option<Arc<AppRules>>,
     active_stick_rules: Option<Arc<StickRules>>, // keep original for potential future use
     compiled_stick_rules: Option<CompiledStickRules>,
     axes_scratch: Vec<(ControllerId, [f32; 6])>,
+    pending_chords: Vec<PendingChord>,
+    /// Chords whose press was superseded, so their release is skipped too.
+    cancelled_chords: Vec<(ControllerId, ButtonChord)>,
+    /// Keystrokes held down by rules, released on demand so a focus change
+    /// or disconnect never leaves modifiers stuck.
+    held: RefCell<Vec<HeldKey>>,
+    /// Chords fired since the last `take_fired_chords`, if recording is enabled.
+    fired_chords: Option<RefCell<Vec<(ControllerId, ButtonChord)>>>,
+    clock: Arc<dyn Clock>,
+    /// Deadlines of stick ticks, repeats, deferred chords and paced keys.
+    scheduler: Scheduler,
+    /// Display names of apps for logs.
+    app_names: AppNames,
+    /// Keyboard chords seen so far. Each acts as a controller of its own,
+    /// with the id `FIRST_KEY_ID` plus its index.
+    key_ids: Vec<KeyCombo>,
 }
 
 impl Default for Gamacros {
@@ -53,14 +192,36 @@ impl Default for Gamacros {
 
 impl Gamacros {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Creates an instance reading time from `clock`, e.g. a `ManualClock`
+    /// to drive chord windows and repeats deterministically.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         Self {
             workspace: None,
             active_app: "".into(),
+            pinned_app: None,
+            window_title: "".into(),
+            site_host: "".into(),
             controllers: AHashMap::new(),
-            sticks: RefCell::new(StickProcessor::new()),
+            sticks: RefCell::new(StickProcessor::with_clock(clock.clone())),
+            app_rules: None,
+            matched_titles: Vec::new(),
+            active_set: None,
+            set_request: RefCell::new(None),
+            active_rules: None,
             active_stick_rules: None,
             compiled_stick_rules: None,
             axes_scratch: Vec::new(),
+            pending_chords: Vec::new(),
+            cancelled_chords: Vec::new(),
+            held: RefCell::new(Vec::new()),
+            fired_chords: None,
+            clock,
+            scheduler: Scheduler::new(),
+            app_names: AppNames::new(),
+            key_ids: Vec::new(),
         }
     }
 
@@ -70,31 +231,79 @@ impl Gamacros {
 
     pub fn remove_workspace(&mut self) {
         self.workspace = None;
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        self.app_rules = None;
+        self.matched_titles.clear();
+        self.active_rules = None;
         self.active_stick_rules = None;
         self.compiled_stick_rules = None;
     }
 
     pub fn set_workspace(&mut self, workspace: Profile) {
-        self.workspace = Some(workspace);
-        // Recompute stick rules for current active app (workspace may have changed)
-        if !self.active_app.is_empty() {
-            if let Some(ws) = self.workspace.as_ref() {
-                if let Some(app_rules) = ws.rules.get(&*self.active_app) {
-                    self.active_stick_rules =
-                        Some(Arc::new(app_rules.sticks.clone()));
-                    self.compiled_stick_rules = self
-                        .active_stick_rules
-                        .as_deref()
-                        .map(CompiledStickRules::from_rules);
-                } else {
-                    self.active_stick_rules = None;
-                    self.compiled_stick_rules = None;
-                }
+        if let Some(set) = self.active_set.as_deref() {
+            if workspace.set(set).is_none() {
+                print_info!("profile set {set} was removed");
+                self.active_set = None;
             }
         }
+        self.workspace = Some(workspace);
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        // Recompute rules for current active app (workspace may have changed)
+        if !self.active_app.is_empty() || self.pinned_app.is_some() {
+            self.resolve_active_rules();
+        }
+    }
+
+    /// Resolve rules of the active app, including global rules. The host and
+    /// window title belong to the frontmost app, a pinned app ignores them.
+    fn resolve_active_rules(&mut self) {
+        let (app, host, title) = match self.pinned_app.as_deref() {
+            Some(app) => (app, "", ""),
+            None => (&*self.active_app, &*self.site_host, &*self.window_title),
+        };
+        let app_rules = self
+            .workspace
+            .as_ref()
+            .and_then(|ws| {
+                ws.resolve_set_rules(app, host, self.active_set.as_deref())
+            })
+            .map(Arc::new);
+        self.matched_titles = app_rules
+            .as_ref()
+            .map(|r| r.matching_titles(title))
+            .unwrap_or_default();
+        self.app_rules = app_rules;
+        self.apply_title_rules();
+    }
+
+    /// Apply matched title rules on top of the app rules.
+    fn apply_title_rules(&mut self) {
+        self.active_rules = self.app_rules.as_ref().map(|rules| {
+            if rules.titles.is_empty() {
+                Arc::clone(rules)
+            } else {
+                Arc::new(rules.with_titles(&self.matched_titles))
+            }
+        });
+        self.active_stick_rules = self
+            .active_rules
+            .as_ref()
+            .map(|r| Arc::new(r.sticks.clone()));
+        self.compiled_stick_rules = self
+            .active_stick_rules
+            .as_deref()
+            .map(CompiledStickRules::from_rules);
     }
 
-    pub fn add_controller(&mut self, info: ControllerInfo) {
+    /// Adds a connected controller, announcing its player slot with a
+    /// rumble when the profile asks for it.
+    pub fn add_controller_with<F: FnMut(Action)>(
+        &mut self,
+        info: ControllerInfo,
+        mut sink: F,
+    ) {
         print_info!(
             "add controller - {0} id={1} vid=0x{2:x} pid=0x{3:x}",
             info.name,
@@ -102,29 +311,78 @@ impl Gamacros {
             info.vendor_id,
             info.product_id
         );
+        if !info.extra_buttons.is_empty() {
+            let names: Vec<_> = info
+                .extra_buttons
+                .iter()
+                .copied()
+                .map(button_name)
+                .collect();
+            print_info!(
+                "controller {0} has extra buttons - {1}",
+                info.id,
+                names.join(", ")
+            );
+        }
 
         let Some(workspace) = self.workspace.as_ref() else {
             return;
         };
         let settings = workspace
-            .controllers
-            .get(&(info.vendor_id, info.product_id))
+            .controller_settings(
+                (info.vendor_id, info.product_id),
+                info.serial.as_deref(),
+            )
             .cloned();
+        let announce = workspace.announce_on_connect;
+        if self.is_known(info.id) {
+            print_debug!("controller already known - id={0}", info.id);
+        }
+        let slot = (1..)
+            .find(|slot| {
+                !self
+                    .controllers
+                    .iter()
+                    .any(|(id, state)| *id != info.id && state.slot == *slot)
+            })
+            .unwrap_or(1);
         let state = ControllerState {
             mapping: settings.unwrap_or_default(),
             pressed: Bitmask::empty(),
             rumble: info.supports_rumble,
             axes: [0.0; 6],
+            stick_buttons: [None; 2],
+            stick_toggled: [false; 2],
+            slot,
         };
-        if self.is_known(info.id) {
-            print_debug!("controller already known - id={0}", info.id);
-        }
         self.controllers.insert(info.id, state);
+        if !announce {
+            return;
+        }
+        print_info!("controller {0} is player {slot}", info.id);
+        if info.supports_rumble {
+            sink(Action::Rumble {
+                id: info.id,
+                ms: ANNOUNCE_RUMBLE_MS,
+                strength: 1.0,
+            });
+        }
     }
 
     pub fn remove_controller(&mut self, id: ControllerId) {
         print_info!("remove device - {id:x}");
         self.controllers.remove(&id);
+        self.pending_chords.retain(|p| p.id != id);
+        self.cancelled_chords.retain(|(cid, _)| *cid != id);
+    }
+
+    pub fn set_supports_rumble(&mut self, id: ControllerId, supported: bool) {
+        print_debug!(
+            "controller rumble support changed - id={id} rumble={supported}"
+        );
+        if let Some(state) = self.controllers.get_mut(&id) {
+            state.rumble = supported;
+        }
     }
 
     pub fn supports_rumble(&self, id: ControllerId) -> bool {
@@ -136,40 +394,220 @@ impl Gamacros {
             return;
         }
         if self.active_app.as_ref() == "" {
-            print_debug!("got active app - {app}");
+            print_debug!(target: ACTIVITY, "got active app - {}", self.app_names.describe(app));
         } else {
-            print_debug!("app change - {app}");
+            print_debug!(target: ACTIVITY, "app change - {}", self.app_names.describe(app));
         }
 
         self.active_app = app.into();
+        // The host belongs to the previous app's tab
+        self.site_host = "".into();
+        if self.pinned_app.is_some() {
+            return;
+        }
         self.sticks.borrow_mut().on_app_change();
-        let Some(workspace) = self.workspace.as_ref() else {
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        self.resolve_active_rules();
+    }
+
+    /// Updates the host of the active browser tab and re-resolves site rules.
+    pub fn set_site_host(&mut self, host: &str) {
+        if self.site_host.as_ref() == host {
+            return;
+        }
+        print_debug!(target: ACTIVITY, "site change - {host}");
+        self.site_host = host.into();
+        if self.pinned_app.is_some() {
+            return;
+        }
+        self.sticks.borrow_mut().on_app_change();
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        self.resolve_active_rules();
+    }
+
+    /// Updates the focused window title and re-resolves title rules.
+    pub fn set_window_title(&mut self, title: &str) {
+        if self.window_title.as_ref() == title {
+            return;
+        }
+        self.window_title = title.into();
+        if self.pinned_app.is_some() {
+            return;
+        }
+        let Some(app_rules) = self.app_rules.as_ref() else {
             return;
         };
+        let matched = app_rules.matching_titles(title);
+        if matched == self.matched_titles {
+            return;
+        }
+        print_debug!(target: ACTIVITY, "title rules change - {title}");
+        self.matched_titles = matched;
+        self.sticks.borrow_mut().on_app_change();
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        self.apply_title_rules();
+    }
 
-        self.active_stick_rules = workspace
-            .rules
-            .get(&*self.active_app)
-            .map(|r| Arc::new(r.sticks.clone()));
+    /// App whose rules apply whichever app is frontmost.
+    pub fn pinned_app(&self) -> Option<&str> {
+        self.pinned_app.as_deref()
+    }
 
-        self.compiled_stick_rules = self
-            .active_stick_rules
+    /// Applies the rules of `app` whichever app is frontmost, `None` goes
+    /// back to the rules of the frontmost app.
+    pub fn pin_app(&mut self, app: Option<&str>) {
+        if self.pinned_app.as_deref() == app {
+            return;
+        }
+        match app {
+            Some(app) => {
+                print_info!("pinned app - {}", self.app_names.describe(app))
+            }
+            None => print_info!("unpinned app"),
+        }
+        self.pinned_app = app.map(Into::into);
+        self.sticks.borrow_mut().on_app_change();
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        self.resolve_active_rules();
+    }
+
+    /// Name of the selected profile set.
+    pub fn active_set(&self) -> Option<&str> {
+        self.active_set.as_deref()
+    }
+
+    /// Selects the profile set saved by an earlier run. It is dropped
+    /// when the workspace has no such set.
+    pub fn restore_set(&mut self, name: &str) {
+        if self.workspace.is_some() {
+            self.select_set(&SetTarget::Named(name.into()));
+        } else {
+            self.active_set = Some(name.into());
+        }
+    }
+
+    /// Selects a profile set. `next` and `prev` cycle through the sets in
+    /// profile order and back to the app rules.
+    pub fn select_set(&mut self, target: &SetTarget) {
+        let Some(workspace) = self.workspace.as_ref() else {
+            return;
+        };
+        let current = self
+            .active_set
             .as_deref()
-            .map(CompiledStickRules::from_rules);
+            .and_then(|name| workspace.sets.iter().position(|s| *s.name == *name));
+        // Slot 0 is the app rules without a set
+        let slots = workspace.sets.len() + 1;
+        let slot = current.map_or(0, |index| index + 1);
+        let slot = match target {
+            SetTarget::Next => (slot + 1) % slots,
+            SetTarget::Prev => (slot + slots - 1) % slots,
+            SetTarget::Default => 0,
+            SetTarget::Named(name) => {
+                match workspace.sets.iter().position(|s| s.name == *name) {
+                    Some(index) => index + 1,
+                    None => {
+                        print_warning!("unknown profile set {name}");
+                        return;
+                    }
+                }
+            }
+        };
+        let set = slot
+            .checked_sub(1)
+            .map(|index| workspace.sets[index].name.clone());
+        if set == self.active_set {
+            return;
+        }
+        print_info!("profile set - {}", set.as_deref().unwrap_or("default"));
+        self.active_set = set;
+        self.sticks.borrow_mut().on_app_change();
+        self.pending_chords.clear();
+        self.cancelled_chords.clear();
+        self.resolve_active_rules();
+    }
+
+    /// Applies a set selected by a rule fired while handling an event.
+    fn apply_set_request(&mut self) {
+        if let Some(target) = self.set_request.get_mut().take() {
+            self.select_set(&target);
+        }
     }
 
     pub fn get_active_app(&self) -> &str {
         &self.active_app
     }
 
+    /// Display name of the app whose rules apply, its id when the name is
+    /// unknown.
+    pub fn active_app_name(&mut self) -> String {
+        let app = self.pinned_app.as_ref().unwrap_or(&self.active_app);
+        self.app_names.name(app).unwrap_or(app).to_string()
+    }
+
+    /// Whether any rules apply to the active app.
+    pub fn has_active_rules(&self) -> bool {
+        self.active_rules.is_some()
+    }
+
+    /// Stick rules of the active app, including title rules.
+    pub fn active_stick_rules(&self) -> Option<&StickRules> {
+        self.active_stick_rules.as_deref()
+    }
+
+    /// Keyboard chords bound by the active rules.
+    pub fn active_key_combos(&self) -> Vec<KeyCombo> {
+        self.active_rules
+            .as_ref()
+            .map(|rules| rules.keys.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
     pub fn get_compiled_stick_rules(&self) -> Option<&CompiledStickRules> {
         self.compiled_stick_rules.as_ref()
     }
 
-    pub fn on_axis_motion(&mut self, id: ControllerId, axis: CtrlAxis, value: f32) {
+    /// Logical axis raw axis `index` of a joystick is mapped to.
+    pub fn joystick_axis(&self, id: ControllerId, index: u8) -> Option<CtrlAxis> {
+        self.controllers.get(&id)?.mapping.axes.get(&index).copied()
+    }
+
+    /// Records an axis value. Stick motion presses and releases the stick
+    /// direction buttons, firing chords that include them.
+    pub fn on_axis_motion_with<F: FnMut(Action)>(
+        &mut self,
+        id: ControllerId,
+        axis: CtrlAxis,
+        value: f32,
+        mut sink: F,
+    ) {
         let idx = stick_axis_index(axis);
-        if let Some(st) = self.controllers.get_mut(&id) {
-            st.axes[idx] = value;
+        let Some(st) = self.controllers.get_mut(&id) else {
+            return;
+        };
+        st.axes[idx] = value;
+        let side = match axis {
+            CtrlAxis::LeftX | CtrlAxis::LeftY => StickSide::Left,
+            CtrlAxis::RightX | CtrlAxis::RightY => StickSide::Right,
+            CtrlAxis::LeftTrigger | CtrlAxis::RightTrigger => return,
+        };
+        let (x, y) = axes_for_side(st.axes, &side);
+        let slot = &mut st.stick_buttons[side_index(&side)];
+        let held = *slot;
+        let next = direction_button(side, x, y, held);
+        if next == held {
+            return;
+        }
+        *slot = next;
+        if let Some(button) = held {
+            self.on_button_with(id, button, ButtonPhase::Released, &mut sink);
+        }
+        if let Some(button) = next {
+            self.on_button_with(id, button, ButtonPhase::Pressed, &mut sink);
         }
     }
 
@@ -178,7 +616,14 @@ impl Gamacros {
     }
 
     pub fn on_tick_with<F: FnMut(Action)>(&mut self, sink: F) {
-        let bindings_owned = self.get_compiled_stick_rules().cloned();
+        let mut bindings_owned = self.get_compiled_stick_rules().cloned();
+        if let Some(bindings) = bindings_owned.as_mut() {
+            bindings.set_controllers(
+                self.controllers
+                    .iter()
+                    .map(|(id, st)| (*id, st.stick_toggled, st.pressed)),
+            );
+        }
         self.axes_scratch.clear();
         self.axes_scratch.reserve(self.controllers.len());
         for (id, st) in self.controllers.iter() {
@@ -191,6 +636,47 @@ impl Gamacros {
         );
     }
 
+    /// Earliest time `run_due` has work to do, after arming the timers for
+    /// the current state. Stick ticks run every `TICK_PERIOD` while a stick
+    /// is deflected or keys repeat.
+    pub fn next_due(&mut self) -> Option<Instant> {
+        let tick = self.needs_tick().then(|| {
+            self.scheduler
+                .due(Timer::Tick)
+                .unwrap_or_else(|| self.clock.now() + TICK_PERIOD)
+        });
+        self.scheduler.set(Timer::Tick, tick);
+        let repeat = self.next_repeat_due();
+        self.scheduler.set(Timer::Repeat, repeat);
+        self.scheduler.set(Timer::Chord, self.next_chord_due());
+        self.scheduler.next_due()
+    }
+
+    /// Arms the timer of key events paced by a key delay, which the action
+    /// runner sends once `next_due` passes. Returns whether it moved.
+    pub fn schedule_keys(&mut self, due: Option<Instant>) -> bool {
+        let moved = self.scheduler.due(Timer::Keys) != due;
+        self.scheduler.set(Timer::Keys, due);
+        moved
+    }
+
+    /// Runs the work due by the current clock time: the stick tick, then
+    /// key repeats and deferred chord presses.
+    pub fn run_due<F: FnMut(Action)>(&mut self, mut sink: F) {
+        let now = self.clock.now();
+        if let Some(due) = self.scheduler.take_due(Timer::Tick, now) {
+            self.on_tick_with(&mut sink);
+            // Keep the cadence, or restart it when ticks fell behind
+            let next = Some(due + TICK_PERIOD)
+                .filter(|next| *next > now)
+                .unwrap_or(now + TICK_PERIOD);
+            self.scheduler.set(Timer::Tick, Some(next));
+        }
+        // Both check their own deadlines, which ticks may have moved
+        self.process_due_repeats(&mut sink);
+        self.process_due_chords(now, &mut sink);
+    }
+
     /// Return next due time for any repeat task, if any.
     pub fn next_repeat_due(&self) -> Option<std::time::Instant> {
         // Borrow mutably internally to read/update heap staleness cheaply.
@@ -198,53 +684,231 @@ impl Gamacros {
         self.sticks.borrow_mut().next_repeat_due()
     }
 
-    /// Process repeat tasks due up to `now`.
-    pub fn process_due_repeats<F: FnMut(Action)>(
+    /// Process repeat tasks due by the current clock time.
+    pub fn process_due_repeats<F: FnMut(Action)>(&self, mut sink: F) {
+        self.sticks.borrow_mut().process_due_repeats(&mut sink);
+    }
+
+    /// Return next due time of a deferred chord press, if any.
+    pub fn next_chord_due(&self) -> Option<Instant> {
+        self.pending_chords.iter().map(|p| p.due).min()
+    }
+
+    /// Fire deferred chord presses whose settle window elapsed by `now`.
+    pub fn process_due_chords<F: FnMut(Action)>(
+        &mut self,
+        now: Instant,
+        mut sink: F,
+    ) {
+        let mut i = 0;
+        while i < self.pending_chords.len() {
+            if self.pending_chords[i].due <= now {
+                let pending = self.pending_chords.swap_remove(i);
+                let id = pending.id;
+                let mut fired = FiredActions::default();
+                self.fire_pending(pending, &mut |action| {
+                    fired.track(&action);
+                    sink(action);
+                });
+                self.haptic_feedback(id, fired, &mut sink);
+            } else {
+                i += 1;
+            }
+        }
+        self.apply_set_request();
+    }
+
+    fn fire_pending<F: FnMut(Action)>(&self, pending: PendingChord, sink: &mut F) {
+        for rule in pending.rules.iter() {
+            self.fire_press(pending.id, pending.chord, rule, sink);
+        }
+    }
+
+    fn fire_press<F: FnMut(Action)>(
         &self,
-        now: std::time::Instant,
+        id: ControllerId,
+        chord: ButtonChord,
+        rule: &ButtonRule,
+        sink: &mut F,
+    ) {
+        // Keyboard chords have no buttons to report
+        if let Some(fired) = self.fired_chords.as_ref().filter(|_| !chord.is_empty())
+        {
+            fired.borrow_mut().push((id, chord));
+        }
+        print_debug!(target: ACTIONS, "{} rule from {}", rule.action.name(), rule.source);
+        if let Some(ms) = rule.vibrate {
+            if self.supports_rumble(id) {
+                sink(Action::Rumble {
+                    id,
+                    ms: ms as u32,
+                    strength: 1.0,
+                });
+            }
+        }
+        match rule.action.clone() {
+            ButtonAction::Keystroke(k) => {
+                let mut held = self.held.borrow_mut();
+                if let Some(index) = held
+                    .iter()
+                    .position(|h| h.toggle && h.id == id && h.chord == chord)
+                {
+                    let latch = held.swap_remove(index);
+                    sink(Action::KeyRelease(latch.keys));
+                } else {
+                    held.push(HeldKey {
+                        id,
+                        chord,
+                        keys: (*k).clone(),
+                        toggle: rule.toggle,
+                    });
+                    let press = Action::KeyPress((*k).clone());
+                    sink(with_key_settings(press, rule));
+                }
+            }
+            ButtonAction::Macros(m) => {
+                sink(with_key_settings(Action::Macros(m), rule));
+            }
+            ButtonAction::Shell(s) => {
+                print_debug!(target: ACTIONS, "shell command: {}", s);
+                sink(Action::Shell {
+                    command: s,
+                    limits: rule.shell_limits,
+                });
+            }
+            ButtonAction::Shortcut(name) => {
+                print_debug!(target: ACTIONS, "shortcut: {}", name);
+                sink(Action::Shortcut {
+                    name,
+                    limits: rule.shell_limits,
+                });
+            }
+            ButtonAction::System(action) => {
+                sink(Action::System(action));
+            }
+            ButtonAction::Window(action) => {
+                sink(Action::Window(action));
+            }
+            ButtonAction::Space(target) => {
+                sink(Action::SwitchSpace(target));
+            }
+            ButtonAction::CycleApp(apps) => {
+                sink(Action::CycleApp(apps));
+            }
+            ButtonAction::Mouse(target) => {
+                sink(Action::MouseWarp(target));
+            }
+            ButtonAction::ProfileSet(target) => {
+                *self.set_request.borrow_mut() = Some(target);
+            }
+        }
+    }
+
+    /// Start or stop recording fired chords for `take_fired_chords`.
+    pub fn record_fired_chords(&mut self, enabled: bool) {
+        self.fired_chords = enabled.then(RefCell::default);
+    }
+
+    /// Chords fired since the last call, oldest first.
+    pub fn take_fired_chords(&mut self) -> Vec<(ControllerId, ButtonChord)> {
+        self.fired_chords
+            .as_mut()
+            .map(|fired| std::mem::take(fired.get_mut()))
+            .unwrap_or_default()
+    }
+
+    /// Release every held keystroke, including ones latched by toggle rules.
+    /// Call before changing the active app or the workspace and on shutdown.
+    pub fn release_held_with<F: FnMut(Action)>(&mut self, mut sink: F) {
+        for held in self.held.get_mut().drain(..) {
+            sink(Action::KeyRelease(held.keys));
+        }
+    }
+
+    /// Release keystrokes held by a single controller.
+    pub fn release_held_for<F: FnMut(Action)>(
+        &mut self,
+        id: ControllerId,
         mut sink: F,
     ) {
-        self.sticks.borrow_mut().process_due_repeats(now, &mut sink);
+        self.held.get_mut().retain(|held| {
+            if held.id != id {
+                return true;
+            }
+            sink(Action::KeyRelease(held.keys.clone()));
+            false
+        });
+    }
+
+    /// Run a rule's `on_release` action. Keystrokes are tapped.
+    fn fire_release<F: FnMut(Action)>(
+        &self,
+        action: ButtonAction,
+        rule: &ButtonRule,
+        sink: &mut F,
+    ) {
+        let limits = rule.shell_limits;
+        match action {
+            ButtonAction::Keystroke(k) => {
+                sink(with_key_settings(Action::KeyTap((*k).clone()), rule));
+            }
+            ButtonAction::Macros(m) => {
+                sink(with_key_settings(Action::Macros(m), rule));
+            }
+            ButtonAction::Shell(s) => {
+                print_debug!(target: ACTIONS, "shell command on release: {}", s);
+                sink(Action::Shell { command: s, limits });
+            }
+            ButtonAction::Shortcut(name) => {
+                print_debug!(target: ACTIONS, "shortcut on release: {}", name);
+                sink(Action::Shortcut { name, limits });
+            }
+            ButtonAction::System(action) => {
+                sink(Action::System(action));
+            }
+            ButtonAction::Window(action) => {
+                sink(Action::Window(action));
+            }
+            ButtonAction::Space(target) => {
+                sink(Action::SwitchSpace(target));
+            }
+            ButtonAction::CycleApp(apps) => {
+                sink(Action::CycleApp(apps));
+            }
+            ButtonAction::Mouse(target) => {
+                sink(Action::MouseWarp(target));
+            }
+            ButtonAction::ProfileSet(target) => {
+                *self.set_request.borrow_mut() = Some(target);
+            }
+        }
     }
 
     /// Whether any periodic processing is needed right now.
     /// True when there are tick-requiring stick modes and some axis deviates from neutral,
     /// or when repeat tasks are active (to drain their timers).
-    pub fn needs_tick(&self) -> bool {
+    fn needs_tick(&self) -> bool {
         (self.has_tick_modes() && self.has_axis_activity(0.05))
             || self.sticks.borrow().has_active_repeats()
     }
 
-    /// Hint whether a faster tick would improve responsiveness.
-    /// True when there is recent/ongoing axis activity or repeat tasks are active.
-    pub fn wants_fast_tick(&self) -> bool {
-        self.has_axis_activity(0.05) || self.sticks.borrow().has_active_repeats()
-    }
-
     /// Whether the current profile has any stick modes that require periodic ticks.
     fn has_tick_modes(&self) -> bool {
         let Some(bindings) = self.get_compiled_stick_rules() else {
             return false;
         };
-        matches!(
-            bindings.left(),
-            Some(
+        bindings.any_mode(|mode| {
+            matches!(
+                mode,
                 StickMode::Arrows(_)
                     | StickMode::Volume(_)
                     | StickMode::Brightness(_)
+                    | StickMode::KeyboardBacklight(_)
+                    | StickMode::Seek(_)
                     | StickMode::MouseMove(_)
                     | StickMode::Scroll(_)
             )
-        ) || matches!(
-            bindings.right(),
-            Some(
-                StickMode::Arrows(_)
-                    | StickMode::Volume(_)
-                    | StickMode::Brightness(_)
-                    | StickMode::MouseMove(_)
-                    | StickMode::Scroll(_)
-            )
-        )
+        })
     }
 
     /// Detect if any controller axis deviates beyond a small threshold.
@@ -269,14 +933,121 @@ impl Gamacros {
         phase: ButtonPhase,
         mut sink: F,
     ) {
-        print_debug!("handle button - {id} {button:?} {phase:?}");
-        let active_app = self.get_active_app();
-        let Some(workspace) = self.workspace.as_ref() else {
+        if matches!(phase, ButtonPhase::Pressed) {
+            self.toggle_stick_modes(id, button);
+        }
+        let mut fired = FiredActions::default();
+        self.handle_button(id, button, phase, |action| {
+            fired.track(&action);
+            sink(action);
+        });
+        self.haptic_feedback(id, fired, &mut sink);
+        self.apply_set_request();
+    }
+
+    /// Switches the sticks toggled by `button` between their modes on
+    /// controller `id`. Rules bound to the button still fire.
+    fn toggle_stick_modes(&mut self, id: ControllerId, button: Button) {
+        let Some(bindings) = self.compiled_stick_rules.as_ref() else {
             return;
         };
-        let Some(app_rules) = workspace.rules.get(active_app) else {
+        let Some(st) = self.controllers.get_mut(&id) else {
+            return;
+        };
+        let mut toggled = false;
+        for side in bindings.sides_toggled_by(button) {
+            st.stick_toggled[side] = !st.stick_toggled[side];
+            toggled = true;
+        }
+        if toggled {
+            print_debug!("toggle stick modes - id={id} {:?}", st.stick_toggled);
+            // Start the new mode from rest, without the old mode's repeats
+            self.sticks.borrow_mut().release_all_for(id);
+        }
+    }
+
+    /// Runs the rule bound to a keyboard chord. Keystrokes are held until
+    /// the chord is released, as for buttons.
+    pub fn on_key_with<F: FnMut(Action)>(
+        &mut self,
+        combo: &KeyCombo,
+        phase: ButtonPhase,
+        mut sink: F,
+    ) {
+        print_debug!("handle key - {combo} {phase:?}");
+        let id = self.key_id(combo);
+        let rule = self
+            .active_rules
+            .as_ref()
+            .and_then(|rules| rules.keys.get(combo))
+            .cloned();
+        match phase {
+            ButtonPhase::Pressed => {
+                if let Some(rule) = rule {
+                    self.fire_press(id, ButtonChord::empty(), &rule, &mut sink);
+                }
+            }
+            ButtonPhase::Released => {
+                // The rules may have changed since the press, release anyway
+                let held = self.held.get_mut();
+                if let Some(index) =
+                    held.iter().position(|h| !h.toggle && h.id == id)
+                {
+                    sink(Action::KeyRelease(held.swap_remove(index).keys));
+                }
+                if let Some(rule) = rule {
+                    if let Some(action) = rule.on_release.clone() {
+                        self.fire_release(action, &rule, &mut sink);
+                    }
+                }
+            }
+        }
+        self.apply_set_request();
+    }
+
+    fn key_id(&mut self, combo: &KeyCombo) -> ControllerId {
+        let index = match self.key_ids.iter().position(|seen| seen == combo) {
+            Some(index) => index,
+            None => {
+                self.key_ids.push(combo.clone());
+                self.key_ids.len() - 1
+            }
+        };
+        FIRST_KEY_ID + index as ControllerId
+    }
+
+    /// Rumbles after a rule fired when the active app asks for haptic
+    /// feedback, unless the rule vibrated on its own.
+    fn haptic_feedback<F: FnMut(Action)>(
+        &self,
+        id: ControllerId,
+        fired: FiredActions,
+        sink: &mut F,
+    ) {
+        if !fired.fired || fired.vibrated || !self.supports_rumble(id) {
             return;
+        }
+        let feedback = self.active_rules.as_ref().and_then(|r| r.haptic_feedback);
+        let strength = match feedback {
+            Some(HapticFeedback::Light) => 0.3,
+            Some(HapticFeedback::Medium) => 0.6,
+            Some(HapticFeedback::Off) | None => return,
         };
+        sink(Action::Rumble {
+            id,
+            ms: FEEDBACK_RUMBLE_MS,
+            strength,
+        });
+    }
+
+    fn handle_button<F: FnMut(Action)>(
+        &mut self,
+        id: ControllerId,
+        button: Button,
+        phase: ButtonPhase,
+        mut sink: F,
+    ) {
+        print_debug!("handle button - {id} {button:?} {phase:?}");
         let state = self
             .controllers
             .get_mut(&id)
@@ -296,8 +1067,9 @@ impl Gamacros {
         let now_pressed = state.pressed;
 
         // First pass: find max_bits among rules that should fire
+        let app_rules = self.active_rules.clone();
         let mut max_bits: u32 = 0;
-        for (target, _rule) in app_rules.buttons.iter() {
+        for (target, _rule) in app_rules.iter().flat_map(|r| r.buttons.iter()) {
             let was = prev_pressed.is_superset(target);
             let is_now = now_pressed.is_superset(target);
             let fire = match phase {
@@ -311,11 +1083,54 @@ impl Gamacros {
                 }
             }
         }
+
+        // Resolve a deferred press of this controller: a larger chord
+        // completing cancels it, anything else lets it fire right away.
+        if let Some(index) = self.pending_chords.iter().position(|p| p.id == id) {
+            let pending = &self.pending_chords[index];
+            let superseded = phase == ButtonPhase::Pressed
+                && max_bits > pending.chord.count()
+                && now_pressed.is_superset(&pending.chord);
+            let still_forming = phase == ButtonPhase::Pressed && max_bits == 0;
+            if superseded {
+                print_debug!("chord superseded - {:?}", pending.chord);
+                let pending = self.pending_chords.swap_remove(index);
+                self.cancelled_chords.push((id, pending.chord));
+            } else if !still_forming {
+                let pending = self.pending_chords.swap_remove(index);
+                self.fire_pending(pending, &mut sink);
+            }
+        }
+
+        // Held keys are released with their chord, even when the rules that
+        // pressed them changed since, e.g. with the window title
+        if phase == ButtonPhase::Released {
+            self.held.get_mut().retain(|h| {
+                let released = !h.toggle
+                    && h.id == id
+                    && prev_pressed.is_superset(&h.chord)
+                    && !now_pressed.is_superset(&h.chord);
+                if released {
+                    sink(Action::KeyRelease(h.keys.clone()));
+                }
+                !released
+            });
+        }
+        let Some(app_rules) = app_rules else {
+            return;
+        };
         if max_bits == 0 {
             return;
         }
 
+        let Some(workspace) = self.workspace.as_ref() else {
+            return;
+        };
+        let chord_window = workspace.chord_window;
+
         // Second pass: execute only rules with that cardinality
+        let mut deferred: Vec<ButtonRule> = Vec::new();
+        let mut deferred_chord = ButtonChord::empty();
         for (target, rule) in app_rules.buttons.iter() {
             let was = prev_pressed.is_superset(target);
             let is_now = now_pressed.is_superset(target);
@@ -328,30 +1143,722 @@ impl Gamacros {
             }
             match phase {
                 ButtonPhase::Pressed => {
-                    if let Some(ms) = rule.vibrate {
-                        if self.supports_rumble(id) {
-                            sink(Action::Rumble { id, ms: ms as u32 });
-                        }
-                    }
-                    match rule.action.clone() {
-                        ButtonAction::Keystroke(k) => {
-                            sink(Action::KeyPress((*k).clone()));
-                        }
-                        ButtonAction::Macros(m) => {
-                            sink(Action::Macros(m));
-                        }
-                        ButtonAction::Shell(s) => {
-                            print_debug!("shell command: {}", s);
-                            sink(Action::Shell(s));
-                        }
+                    // Defer while a larger chord containing this one may still complete.
+                    let may_grow = !chord_window.is_zero()
+                        && app_rules.buttons.keys().any(|other| {
+                            other.count() > target.count()
+                                && other.is_superset(target)
+                        });
+                    if may_grow {
+                        deferred.push(rule.clone());
+                        deferred_chord = *target;
+                        continue;
                     }
+                    self.fire_press(id, *target, rule, &mut sink);
                 }
                 ButtonPhase::Released => {
-                    if let ButtonAction::Keystroke(k) = rule.action.clone() {
-                        sink(Action::KeyRelease((*k).clone()));
+                    if let Some(index) = self
+                        .cancelled_chords
+                        .iter()
+                        .position(|(cid, chord)| *cid == id && chord == target)
+                    {
+                        self.cancelled_chords.swap_remove(index);
+                        continue;
+                    }
+                    if let Some(action) = rule.on_release.clone() {
+                        self.fire_release(action, rule, &mut sink);
                     }
                 }
             }
         }
+        if !deferred.is_empty() {
+            self.pending_chords.push(PendingChord {
+                id,
+                chord: deferred_chord,
+                rules: deferred,
+                due: self.clock.now() + chord_window,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gamacros_workspace::parse_profile;
+
+    const PROFILE: &str = "
+version: 1
+rules:
+  common:
+    buttons:
+      select:
+        keystroke: cmd+c
+      select+start:
+        keystroke: cmd+v
+";
+
+    fn gamacros() -> Gamacros {
+        gamacros_with(PROFILE)
+    }
+
+    fn gamacros_with(profile: &str) -> Gamacros {
+        let mut gamacros = Gamacros::new();
+        gamacros.set_workspace(parse_profile(profile).unwrap());
+        gamacros.set_active_app("common");
+        gamacros.add_controller_with(
+            ControllerInfo {
+                id: 0,
+                name: "Test".to_string(),
+                supports_rumble: false,
+                vendor_id: 0,
+                product_id: 0,
+                serial: None,
+                extra_buttons: Vec::new(),
+            },
+            |_| {},
+        );
+        gamacros
+    }
+
+    fn press(gamacros: &mut Gamacros, button: Button) -> Vec<Action> {
+        let mut actions = Vec::new();
+        gamacros
+            .on_button_with(0, button, ButtonPhase::Pressed, |a| actions.push(a));
+        actions
+    }
+
+    fn release(gamacros: &mut Gamacros, button: Button) -> Vec<Action> {
+        let mut actions = Vec::new();
+        gamacros
+            .on_button_with(0, button, ButtonPhase::Released, |a| actions.push(a));
+        actions
+    }
+
+    #[test]
+    fn announces_player_slots_on_connect() {
+        let mut gamacros = gamacros_with("version: 1\nannounce_on_connect: true\n");
+        let pad = |id| ControllerInfo {
+            id,
+            name: "Pad".to_string(),
+            supports_rumble: true,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        };
+        let mut actions = Vec::new();
+        gamacros.add_controller_with(pad(1), |a| actions.push(a));
+        assert!(matches!(actions[..], [Action::Rumble { id: 1, .. }]));
+        assert_eq!(gamacros.controllers[&1].slot, 2);
+
+        // Slots of disconnected controllers are reused
+        gamacros.remove_controller(0);
+        gamacros.add_controller_with(pad(2), |_| {});
+        assert_eq!(gamacros.controllers[&2].slot, 1);
+    }
+
+    #[test]
+    fn identical_pads_pick_settings_by_serial() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+controllers:
+  - { vid: 1, pid: 2, remap: { a: b } }
+  - { vid: 1, pid: 2, serial: P2, remap: { a: x } }
+",
+        );
+        let pad = |id, serial: Option<&str>| ControllerInfo {
+            id,
+            name: "Pad".to_string(),
+            supports_rumble: false,
+            vendor_id: 1,
+            product_id: 2,
+            serial: serial.map(str::to_string),
+            extra_buttons: Vec::new(),
+        };
+        gamacros.add_controller_with(pad(1, Some("P1")), |_| {});
+        gamacros.add_controller_with(pad(2, Some("P2")), |_| {});
+        let remapped = |gamacros: &Gamacros, id| {
+            gamacros.controllers[&id].mapping.mapping[&Button::A]
+        };
+        assert_eq!(remapped(&gamacros, 1), Button::B);
+        assert_eq!(remapped(&gamacros, 2), Button::X);
+    }
+
+    #[test]
+    fn haptic_feedback_follows_fired_rules() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    haptic_feedback: light
+    buttons:
+      a:
+        keystroke: cmd+c
+      b:
+        vibrate: 100
+        keystroke: cmd+v
+",
+        );
+        gamacros.add_controller_with(
+            ControllerInfo {
+                id: 1,
+                name: "Pad".to_string(),
+                supports_rumble: true,
+                vendor_id: 0,
+                product_id: 0,
+                serial: None,
+                extra_buttons: Vec::new(),
+            },
+            |_| {},
+        );
+        let mut actions = Vec::new();
+        let mut sink = |a| actions.push(a);
+        gamacros.on_button_with(1, Button::A, ButtonPhase::Pressed, &mut sink);
+        gamacros.on_button_with(1, Button::A, ButtonPhase::Released, &mut sink);
+        gamacros.on_button_with(1, Button::B, ButtonPhase::Pressed, &mut sink);
+        // Releasing the held key is not a rule firing
+        assert!(matches!(
+            actions[..],
+            [
+                Action::KeyPress(_),
+                Action::Rumble { id: 1, ms: 40, strength },
+                Action::KeyRelease(_),
+                Action::Rumble { ms: 100, .. },
+                Action::KeyPress(_),
+            ] if strength < 0.5
+        ));
+
+        // No rumble for controllers without it
+        assert!(matches!(
+            press(&mut gamacros, Button::A)[..],
+            [Action::KeyPress(_)]
+        ));
+    }
+
+    #[test]
+    fn completed_chord_cancels_deferred_press() {
+        let mut gamacros = gamacros();
+        assert!(press(&mut gamacros, Button::Back).is_empty());
+        assert!(gamacros.next_chord_due().is_some());
+
+        let actions = press(&mut gamacros, Button::Start);
+        assert!(matches!(actions[..], [Action::KeyPress(_)]));
+        assert!(gamacros.next_chord_due().is_none());
+
+        assert_eq!(release(&mut gamacros, Button::Start).len(), 1);
+        // The cancelled single-button press must not be released
+        assert!(release(&mut gamacros, Button::Back).is_empty());
+    }
+
+    #[test]
+    fn deferred_press_fires_after_window() {
+        let mut gamacros = gamacros();
+        assert!(press(&mut gamacros, Button::Back).is_empty());
+
+        let mut actions = Vec::new();
+        let due = gamacros.next_chord_due().unwrap();
+        gamacros.process_due_chords(due, |a| actions.push(a));
+        assert!(matches!(actions[..], [Action::KeyPress(_)]));
+    }
+
+    #[test]
+    fn quick_tap_flushes_deferred_press() {
+        let mut gamacros = gamacros();
+        assert!(press(&mut gamacros, Button::Back).is_empty());
+        let actions = release(&mut gamacros, Button::Back);
+        assert!(matches!(
+            actions[..],
+            [Action::KeyPress(_), Action::KeyRelease(_)]
+        ));
+    }
+
+    #[test]
+    fn release_runs_on_release_action() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      l1+r1:
+        shell: mic on
+        on_release:
+          shell: mic off
+",
+        );
+        assert!(press(&mut gamacros, Button::LeftShoulder).is_empty());
+        let actions = press(&mut gamacros, Button::RightShoulder);
+        assert!(
+            matches!(&actions[..], [Action::Shell { command: s, .. }] if s == "mic on")
+        );
+        let actions = release(&mut gamacros, Button::LeftShoulder);
+        assert!(
+            matches!(&actions[..], [Action::Shell { command: s, .. }] if s == "mic off")
+        );
+    }
+
+    #[test]
+    fn title_change_keeps_held_key_releasable() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    titles:
+      ' - YouTube$':
+        buttons:
+          a:
+            keystroke: space
+",
+        );
+        gamacros.set_window_title("Cats - YouTube");
+        let actions = press(&mut gamacros, Button::A);
+        assert!(matches!(actions[..], [Action::KeyPress(_)]));
+
+        // The rule holding the key no longer applies
+        gamacros.set_window_title("GitHub");
+        let actions = release(&mut gamacros, Button::A);
+        assert!(matches!(actions[..], [Action::KeyRelease(_)]));
+        assert!(gamacros.held.get_mut().is_empty());
+    }
+
+    #[test]
+    fn press_runs_shortcut_with_limits() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      y:
+        shortcut: Start Focus
+        max_concurrent: 1
+",
+        );
+        let actions = press(&mut gamacros, Button::Y);
+        assert!(matches!(
+            &actions[..],
+            [Action::Shortcut { name, limits }]
+                if name == "Start Focus" && limits.max_concurrent == Some(1)
+        ));
+    }
+
+    #[test]
+    fn paced_keys_wake_the_event_loop() {
+        let mut gamacros = gamacros();
+        assert_eq!(gamacros.next_due(), None);
+        let due = Instant::now() + Duration::from_millis(20);
+        assert!(gamacros.schedule_keys(Some(due)));
+        assert!(!gamacros.schedule_keys(Some(due)));
+        assert_eq!(gamacros.next_due(), Some(due));
+        assert!(gamacros.schedule_keys(None));
+        assert_eq!(gamacros.next_due(), None);
+    }
+
+    #[test]
+    fn press_runs_keys_with_rule_key_delay() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      a: { keystroke: cmd+s, key_delay_ms: 20 }
+      b: { macros: [cmd+c, cmd+v] }
+",
+        );
+        let actions = press(&mut gamacros, Button::A);
+        assert!(matches!(
+            &actions[..],
+            [Action::WithKeyDelay(action, delay)]
+                if matches!(**action, Action::KeyPress(_))
+                    && *delay == Duration::from_millis(20)
+        ));
+        let actions = press(&mut gamacros, Button::B);
+        assert!(matches!(&actions[..], [Action::Macros(_)]));
+    }
+
+    #[test]
+    fn press_and_release_post_keys_to_rule_target() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: space
+        target: com.apple.Music
+        key_delay_ms: 5
+        on_release: { macros: [shift+n] }
+",
+        );
+        let actions = press(&mut gamacros, Button::A);
+        let [Action::WithKeyDelay(action, _)] = &actions[..] else {
+            panic!("expected a delayed key press, got {actions:?}");
+        };
+        assert!(matches!(
+            &**action,
+            Action::WithTarget(action, bundle_id)
+                if matches!(**action, Action::KeyPress(_))
+                    && &**bundle_id == "com.apple.Music"
+        ));
+        let actions = release(&mut gamacros, Button::A);
+        assert!(matches!(&actions[0], Action::KeyRelease(_)));
+        assert!(matches!(
+            &actions[1],
+            Action::WithKeyDelay(action, _)
+                if matches!(&**action, Action::WithTarget(..))
+        ));
+    }
+
+    #[test]
+    fn press_and_release_run_system_actions() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      guide:
+        system: mission_control
+        on_release:
+          system: play_pause
+",
+        );
+        let actions = press(&mut gamacros, Button::Guide);
+        assert!(matches!(
+            &actions[..],
+            [Action::System(SystemAction::MissionControl)]
+        ));
+        let actions = release(&mut gamacros, Button::Guide);
+        assert!(matches!(
+            &actions[..],
+            [Action::System(SystemAction::PlayPause)]
+        ));
+    }
+
+    #[test]
+    fn press_and_release_run_window_actions() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      lb:
+        window: left_half
+        on_release:
+          window: next_display
+",
+        );
+        let actions = press(&mut gamacros, Button::LeftShoulder);
+        assert!(matches!(
+            &actions[..],
+            [Action::Window(WindowAction::LeftHalf)]
+        ));
+        let actions = release(&mut gamacros, Button::LeftShoulder);
+        assert!(matches!(
+            &actions[..],
+            [Action::Window(WindowAction::NextDisplay)]
+        ));
+    }
+
+    #[test]
+    fn space_targets_parse_from_names_and_numbers() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      rb:
+        space: next
+      lb:
+        space: 3
+",
+        );
+        let actions = press(&mut gamacros, Button::RightShoulder);
+        assert!(matches!(
+            &actions[..],
+            [Action::SwitchSpace(SpaceTarget::Next)]
+        ));
+        let actions = press(&mut gamacros, Button::LeftShoulder);
+        assert!(matches!(
+            &actions[..],
+            [Action::SwitchSpace(SpaceTarget::Index(3))]
+        ));
+    }
+
+    #[test]
+    fn mouse_targets_warp_the_cursor() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      l2+r2:
+        mouse: next_display
+",
+        );
+        press(&mut gamacros, Button::LeftTrigger);
+        let actions = press(&mut gamacros, Button::RightTrigger);
+        assert!(matches!(
+            &actions[..],
+            [Action::MouseWarp(MouseTarget::NextDisplay)]
+        ));
+    }
+
+    #[test]
+    fn cycle_app_expands_groups() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+groups:
+  ide: [com.microsoft.VSCode, dev.zed.Zed]
+rules:
+  common:
+    buttons:
+      back:
+        cycle_app: $ide | com.jetbrains.*
+",
+        );
+        let actions = press(&mut gamacros, Button::Back);
+        let [Action::CycleApp(apps)] = &actions[..] else {
+            panic!("expected cycle_app, got {actions:?}");
+        };
+        let apps: Vec<&str> = apps.iter().map(AppPattern::as_str).collect();
+        assert_eq!(
+            apps,
+            ["com.microsoft.VSCode", "dev.zed.Zed", "com.jetbrains.*"]
+        );
+    }
+
+    #[test]
+    fn stick_directions_complete_chords() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      lt+stick_right_up:
+        keystroke: cmd+arrow_up
+",
+        );
+        let mut actions = Vec::new();
+        press(&mut gamacros, Button::LeftTrigger);
+        gamacros.on_axis_motion_with(0, CtrlAxis::RightY, -0.5, |a| actions.push(a));
+        assert!(actions.is_empty());
+        gamacros.on_axis_motion_with(0, CtrlAxis::RightY, -0.9, |a| actions.push(a));
+        assert!(matches!(&actions[..], [Action::KeyPress(_)]));
+        // Within the hysteresis band the direction stays held
+        gamacros.on_axis_motion_with(0, CtrlAxis::RightY, -0.5, |a| actions.push(a));
+        assert_eq!(actions.len(), 1);
+        gamacros.on_axis_motion_with(0, CtrlAxis::RightY, 0.0, |a| actions.push(a));
+        assert!(matches!(
+            &actions[..],
+            [Action::KeyPress(_), Action::KeyRelease(_)]
+        ));
+    }
+
+    #[test]
+    fn records_fired_chords_when_enabled() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: enter
+",
+        );
+        press(&mut gamacros, Button::A);
+        assert!(gamacros.take_fired_chords().is_empty());
+
+        gamacros.record_fired_chords(true);
+        release(&mut gamacros, Button::A);
+        press(&mut gamacros, Button::A);
+        let mut chord = ButtonChord::empty();
+        chord.insert(Button::A);
+        assert_eq!(gamacros.take_fired_chords(), vec![(0, chord)]);
+        assert!(gamacros.take_fired_chords().is_empty());
+    }
+
+    #[test]
+    fn toggle_latches_until_next_press() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: shift
+        toggle: true
+",
+        );
+        assert!(matches!(
+            press(&mut gamacros, Button::A)[..],
+            [Action::KeyPress(_)]
+        ));
+        assert!(release(&mut gamacros, Button::A).is_empty());
+        assert!(matches!(
+            press(&mut gamacros, Button::A)[..],
+            [Action::KeyRelease(_)]
+        ));
+        assert!(release(&mut gamacros, Button::A).is_empty());
+
+        press(&mut gamacros, Button::A);
+        let mut actions = Vec::new();
+        gamacros.release_held_for(0, |a| actions.push(a));
+        assert!(matches!(actions[..], [Action::KeyRelease(_)]));
+        actions.clear();
+        gamacros.release_held_with(|a| actions.push(a));
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn held_keystroke_is_released_once() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: shift
+",
+        );
+        assert!(matches!(
+            press(&mut gamacros, Button::A)[..],
+            [Action::KeyPress(_)]
+        ));
+        let mut actions = Vec::new();
+        gamacros.release_held_with(|a| actions.push(a));
+        assert!(matches!(actions[..], [Action::KeyRelease(_)]));
+        assert!(release(&mut gamacros, Button::A).is_empty());
+
+        press(&mut gamacros, Button::A);
+        assert!(matches!(
+            release(&mut gamacros, Button::A)[..],
+            [Action::KeyRelease(_)]
+        ));
+    }
+
+    #[test]
+    fn keyboard_chords_fire_rules() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    keys:
+      hyper+j:
+        keystroke: shift
+        on_release: { keystroke: cmd+s }
+      hyper+k:
+        keystroke: alt
+        toggle: true
+",
+        );
+        let combo = |s: &str| s.parse::<KeyCombo>().unwrap();
+        let mut key = |s: &str, phase| {
+            let mut actions = Vec::new();
+            gamacros.on_key_with(&combo(s), phase, |a| actions.push(a));
+            actions
+        };
+        assert!(matches!(
+            key("hyper+k", ButtonPhase::Pressed)[..],
+            [Action::KeyPress(_)]
+        ));
+        assert!(key("hyper+k", ButtonPhase::Released).is_empty());
+        // Latched chords of other keys are left alone
+        assert!(matches!(
+            key("hyper+j", ButtonPhase::Pressed)[..],
+            [Action::KeyPress(_)]
+        ));
+        assert!(matches!(
+            key("hyper+j", ButtonPhase::Released)[..],
+            [Action::KeyRelease(_), Action::KeyTap(_)]
+        ));
+        assert!(matches!(
+            key("hyper+k", ButtonPhase::Pressed)[..],
+            [Action::KeyRelease(_)]
+        ));
+        assert!(key("hyper+l", ButtonPhase::Pressed).is_empty());
+        assert_eq!(gamacros.active_key_combos().len(), 2);
+    }
+
+    #[test]
+    fn window_title_switches_rules() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        shell: default
+    titles:
+      YouTube:
+        buttons:
+          a:
+            shell: video
+",
+        );
+        let shell = |gamacros: &mut Gamacros| {
+            let actions = press(gamacros, Button::A);
+            release(gamacros, Button::A);
+            match &actions[..] {
+                [Action::Shell { command: s, .. }] => s.clone(),
+                actions => panic!("unexpected actions {actions:?}"),
+            }
+        };
+        assert_eq!(shell(&mut gamacros), "default");
+        gamacros.set_window_title("Cats - YouTube");
+        assert_eq!(shell(&mut gamacros), "video");
+        gamacros.set_window_title("Inbox");
+        assert_eq!(shell(&mut gamacros), "default");
+    }
+
+    #[test]
+    fn pinned_app_rules_apply_in_any_app() {
+        let mut gamacros = gamacros_with(
+            "
+version: 1
+rules:
+  com.apple.Safari:
+    buttons:
+      a:
+        shell: browser
+  com.spotify.client:
+    buttons:
+      a:
+        shell: music
+",
+        );
+        let shell = |gamacros: &mut Gamacros| {
+            let actions = press(gamacros, Button::A);
+            release(gamacros, Button::A);
+            match &actions[..] {
+                [Action::Shell { command: s, .. }] => s.clone(),
+                actions => panic!("unexpected actions {actions:?}"),
+            }
+        };
+        gamacros.set_active_app("com.apple.Safari");
+        assert_eq!(shell(&mut gamacros), "browser");
+        gamacros.pin_app(Some("com.spotify.client"));
+        assert_eq!(shell(&mut gamacros), "music");
+        gamacros.set_active_app("com.apple.Terminal");
+        assert_eq!(shell(&mut gamacros), "music");
+        gamacros.set_active_app("com.apple.Safari");
+        gamacros.pin_app(None);
+        assert_eq!(shell(&mut gamacros), "browser");
     }
 }
@@ -1,4 +1,6 @@
+pub mod clock;
 pub mod gamacros;
+pub mod scheduler;
 pub mod stick;
 
 pub use gamacros::{Gamacros, Action};
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+/// Period of stick ticks while a stick is deflected or keys repeat.
+pub const TICK_PERIOD: Duration = Duration::from_millis(10);
+
+/// Timed work of [`Gamacros`](super::Gamacros), each with at most one
+/// deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    /// Stick movement tick, every `TICK_PERIOD` while needed.
+    Tick,
+    /// Earliest stick key repeat or dwell click.
+    Repeat,
+    /// Earliest chord press waiting for its settle window.
+    Chord,
+    /// Next key event of a combo or macro waiting for the key delay, sent
+    /// by the action runner.
+    Keys,
+}
+
+impl Timer {
+    const COUNT: usize = 4;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Deadlines of every timer in one place, so the event loop sleeps until
+/// `next_due` instead of polling.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    deadlines: [Option<Instant>; Timer::COUNT],
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the deadline of `timer`, `None` disarms it.
+    pub fn set(&mut self, timer: Timer, due: Option<Instant>) {
+        self.deadlines[timer.index()] = due;
+    }
+
+    pub fn due(&self, timer: Timer) -> Option<Instant> {
+        self.deadlines[timer.index()]
+    }
+
+    /// The deadline of `timer` if it passed by `now`, disarming it.
+    pub fn take_due(&mut self, timer: Timer, now: Instant) -> Option<Instant> {
+        let slot = &mut self.deadlines[timer.index()];
+        slot.filter(|due| *due <= now).inspect(|_| *slot = None)
+    }
+
+    /// Earliest deadline of any timer.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.deadlines.iter().flatten().min().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_due_timers_and_reports_earliest() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut scheduler = Scheduler::new();
+        assert_eq!(scheduler.next_due(), None);
+
+        scheduler.set(Timer::Tick, Some(start + ms(10)));
+        scheduler.set(Timer::Chord, Some(start + ms(5)));
+        assert_eq!(scheduler.next_due(), Some(start + ms(5)));
+        assert_eq!(scheduler.take_due(Timer::Tick, start + ms(5)), None);
+        assert_eq!(
+            scheduler.take_due(Timer::Chord, start + ms(5)),
+            Some(start + ms(5))
+        );
+        assert_eq!(scheduler.due(Timer::Chord), None);
+        assert_eq!(scheduler.next_due(), Some(start + ms(10)));
+
+        scheduler.set(Timer::Tick, None);
+        assert_eq!(scheduler.next_due(), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Virtual buttons held while a stick points in a direction, so chords can
+//! combine stick directions with buttons, e.g. `lt+stick_right_up`.
+
+use gamacros_gamepad::Button;
+use gamacros_workspace::StickSide;
+
+use super::util::magnitude2d;
+
+/// Deflection that presses a direction button.
+const PRESS_THRESHOLD: f32 = 0.6;
+/// Deflection below which a held direction button is released.
+const RELEASE_THRESHOLD: f32 = 0.4;
+/// A held direction is kept until the stick leaves its quadrant by this
+/// much, `cos(45° + 10°)`, so diagonals do not flip between directions.
+const KEEP_COS: f32 = 0.573_576_4;
+
+/// Direction buttons of a stick: up, down, left, right.
+fn buttons(side: StickSide) -> [Button; 4] {
+    match side {
+        StickSide::Left => [
+            Button::LeftStickUp,
+            Button::LeftStickDown,
+            Button::LeftStickLeft,
+            Button::LeftStickRight,
+        ],
+        StickSide::Right => [
+            Button::RightStickUp,
+            Button::RightStickDown,
+            Button::RightStickLeft,
+            Button::RightStickRight,
+        ],
+    }
+}
+
+/// Unit vector of a direction button, in axis coordinates (up is negative).
+fn unit(index: usize) -> (f32, f32) {
+    [(0.0, -1.0), (0.0, 1.0), (-1.0, 0.0), (1.0, 0.0)][index]
+}
+
+/// The direction button that should be held for a stick at `x`, `y`, given
+/// the button held so far.
+pub(crate) fn direction_button(
+    side: StickSide,
+    x: f32,
+    y: f32,
+    held: Option<Button>,
+) -> Option<Button> {
+    let buttons = buttons(side);
+    let held = held.and_then(|held| buttons.iter().position(|b| *b == held));
+    let magnitude = magnitude2d(x, y);
+    let threshold = if held.is_some() {
+        RELEASE_THRESHOLD
+    } else {
+        PRESS_THRESHOLD
+    };
+    if magnitude < threshold {
+        return None;
+    }
+    if let Some(index) = held {
+        let (ux, uy) = unit(index);
+        if (x * ux + y * uy) / magnitude >= KEEP_COS {
+            return Some(buttons[index]);
+        }
+    }
+    let index = if x.abs() > y.abs() {
+        if x > 0.0 {
+            3
+        } else {
+            2
+        }
+    } else if y < 0.0 {
+        0
+    } else {
+        1
+    };
+    Some(buttons[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_and_releases_with_hysteresis() {
+        let side = StickSide::Right;
+        assert_eq!(direction_button(side, 0.0, -0.5, None), None);
+        let held = direction_button(side, 0.0, -0.7, None);
+        assert_eq!(held, Some(Button::RightStickUp));
+        assert_eq!(direction_button(side, 0.0, -0.5, held), held);
+        assert_eq!(direction_button(side, 0.0, -0.3, held), None);
+    }
+
+    #[test]
+    fn keeps_direction_across_the_diagonal() {
+        let side = StickSide::Left;
+        let held = Some(Button::LeftStickRight);
+        // Slightly past the diagonal towards down
+        assert_eq!(direction_button(side, 0.6, 0.65, held), held);
+        assert_eq!(
+            direction_button(side, 0.6, 0.65, None),
+            Some(Button::LeftStickDown)
+        );
+        assert_eq!(
+            direction_button(side, 0.2, 0.8, held),
+            Some(Button::LeftStickDown)
+        );
+    }
+}
@@ -1,29 +1,103 @@
-use gamacros_workspace::{StickMode, StickRules, StickSide};
+use gamacros_gamepad::{Button, ControllerId};
+use gamacros_workspace::{ButtonChord, StickMode, StickRules, StickSide};
 
 #[derive(Debug, Clone, Default)]
 pub struct CompiledStickRules {
     pub(super) sides: [Option<StickMode>; 2],
+    /// Second modes of the sides, with the stick click toggling them.
+    toggles: [Option<(Button, StickMode)>; 2],
+    /// Modes of the sides while a chord is held, larger chords first.
+    held: [Vec<(ButtonChord, StickMode)>; 2],
+    /// Controllers with a side in its second mode or buttons held, with
+    /// the toggled sides and the buttons.
+    controllers: Vec<(ControllerId, [bool; 2], ButtonChord)>,
 }
 
 impl CompiledStickRules {
     pub fn from_rules(rules: &StickRules) -> Self {
-        let mut sides: [Option<StickMode>; 2] = [None, None];
-        if let Some(mode) = rules.get(&StickSide::Left) {
-            sides[0] = Some(mode.clone());
+        let mut compiled = Self::default();
+        for (index, side) in [StickSide::Left, StickSide::Right].iter().enumerate() {
+            if let Some(rule) = rules.get(side) {
+                compiled.sides[index] = Some(rule.mode.clone());
+                compiled.toggles[index] = rule
+                    .toggle
+                    .as_ref()
+                    .map(|toggle| (toggle.button, toggle.mode.clone()));
+                compiled.held[index] = rule
+                    .held
+                    .iter()
+                    .map(|held| (held.chord, held.mode.clone()))
+                    .collect();
+            }
         }
-        if let Some(mode) = rules.get(&StickSide::Right) {
-            sides[1] = Some(mode.clone());
+        compiled
+    }
+
+    /// Sets the sides each controller has toggled, as indices of `sides`,
+    /// and the buttons it holds.
+    pub fn set_controllers(
+        &mut self,
+        controllers: impl IntoIterator<Item = (ControllerId, [bool; 2], ButtonChord)>,
+    ) {
+        self.controllers.clear();
+        self.controllers.extend(controllers.into_iter().filter(
+            |(_, toggled, pressed)| toggled.contains(&true) || !pressed.is_empty(),
+        ));
+    }
+
+    /// Indices of the sides `button` toggles.
+    pub fn sides_toggled_by(
+        &self,
+        button: Button,
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.toggles
+            .iter()
+            .enumerate()
+            .filter(move |(_, toggle)| {
+                toggle.as_ref().is_some_and(|(with, _)| *with == button)
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Mode of the side at `index` on controller `id`: the mode of the
+    /// largest chord held, else the second mode if toggled, else the first.
+    pub fn mode(&self, id: ControllerId, index: usize) -> Option<&StickMode> {
+        let Some((_, toggled, pressed)) = self
+            .controllers
+            .iter()
+            .find(|(state_id, ..)| *state_id == id)
+        else {
+            return self.sides[index].as_ref();
+        };
+        if let Some((_, mode)) = self.held[index]
+            .iter()
+            .find(|(chord, _)| pressed.is_superset(chord))
+        {
+            return Some(mode);
+        }
+        match &self.toggles[index] {
+            Some((_, mode)) if toggled[index] => Some(mode),
+            _ => self.sides[index].as_ref(),
         }
-        Self { sides }
     }
 
     #[inline]
-    pub fn left(&self) -> Option<&StickMode> {
-        self.sides[0].as_ref()
+    pub fn left_for(&self, id: ControllerId) -> Option<&StickMode> {
+        self.mode(id, 0)
     }
 
     #[inline]
-    pub fn right(&self) -> Option<&StickMode> {
-        self.sides[1].as_ref()
+    pub fn right_for(&self, id: ControllerId) -> Option<&StickMode> {
+        self.mode(id, 1)
+    }
+
+    /// Whether some side has a mode matching `f`, first, second or held.
+    pub fn any_mode(&self, f: impl Fn(&StickMode) -> bool) -> bool {
+        self.sides
+            .iter()
+            .flatten()
+            .chain(self.toggles.iter().flatten().map(|(_, mode)| mode))
+            .chain(self.held.iter().flatten().map(|(_, mode)| mode))
+            .any(f)
     }
 }
@@ -0,0 +1,67 @@
+//! Dwell clicking: a click once the cursor moved by a stick comes to rest,
+//! for users who cannot press buttons reliably.
+
+use std::time::Instant;
+
+use gamacros_control::MouseButton;
+use gamacros_workspace::DwellClick;
+
+/// Dwell click of a stick in mouse mode.
+#[derive(Debug, Default)]
+pub(super) struct DwellState {
+    /// Pending click, pushed back by every move. Cleared once it fires,
+    /// so a resting cursor clicks only once.
+    pending: Option<(Instant, MouseButton)>,
+}
+
+impl DwellState {
+    /// Records cursor movement at `now`.
+    pub(super) fn moved(&mut self, dwell: &DwellClick, now: Instant) {
+        self.pending = Some((now + dwell.delay, dwell.button));
+    }
+
+    pub(super) fn due(&self) -> Option<Instant> {
+        self.pending.map(|(due, _)| due)
+    }
+
+    /// Returns the button to click if the cursor rested long enough.
+    pub(super) fn take_due(&mut self, now: Instant) -> Option<MouseButton> {
+        match self.pending {
+            Some((due, button)) if due <= now => {
+                self.pending = None;
+                Some(button)
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn cancel(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn clicks_once_after_rest() {
+        let dwell = DwellClick {
+            delay: Duration::from_millis(500),
+            button: MouseButton::Left,
+        };
+        let mut state = DwellState::default();
+        let start = Instant::now();
+        state.moved(&dwell, start);
+        state.moved(&dwell, start + Duration::from_millis(300));
+        assert_eq!(state.take_due(start + Duration::from_millis(600)), None);
+        assert_eq!(
+            state.take_due(start + Duration::from_millis(800)),
+            Some(MouseButton::Left)
+        );
+        assert_eq!(state.take_due(start + Duration::from_secs(2)), None);
+        assert_eq!(state.due(), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Smoothing of noisy stick values, see `AxisFilter`.
+
+use std::time::{Duration, Instant};
+
+use gamacros_workspace::AxisFilter;
+
+use super::util::magnitude2d;
+
+/// A filter idle for this long restarts from the raw value, so a stick
+/// moved after a pause does not start from a stale average.
+const RESET_AFTER: Duration = Duration::from_millis(100);
+
+/// Filtered position of a stick.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct FilterState {
+    x: f32,
+    y: f32,
+    at: Instant,
+}
+
+/// Feeds a raw stick position into the filter and returns the filtered one.
+pub(super) fn filter_xy(
+    filter: &AxisFilter,
+    state: &mut Option<FilterState>,
+    x: f32,
+    y: f32,
+    now: Instant,
+) -> (f32, f32) {
+    let previous =
+        state.filter(|state| now.saturating_duration_since(state.at) < RESET_AFTER);
+    let (fx, fy) = match previous {
+        // Moves within the clamp are jitter, the stick is held in place
+        Some(p) if magnitude2d(x - p.x, y - p.y) < filter.jitter_clamp => (p.x, p.y),
+        Some(p) => (
+            p.x + filter.alpha * (x - p.x),
+            p.y + filter.alpha * (y - p.y),
+        ),
+        None => (x, y),
+    };
+    *state = Some(FilterState {
+        x: fx,
+        y: fy,
+        at: now,
+    });
+    (fx, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooths_and_clamps_jitter() {
+        let filter = AxisFilter {
+            alpha: 0.5,
+            jitter_clamp: 0.05,
+        };
+        let mut state = None;
+        let start = Instant::now();
+        assert_eq!(filter_xy(&filter, &mut state, 0.4, 0.0, start), (0.4, 0.0));
+        assert_eq!(filter_xy(&filter, &mut state, 0.8, 0.0, start), (0.6, 0.0));
+        // Shake smaller than the clamp is ignored
+        assert_eq!(filter_xy(&filter, &mut state, 0.63, 0.0, start), (0.6, 0.0));
+
+        // A stale average is dropped
+        let later = start + RESET_AFTER;
+        assert_eq!(
+            filter_xy(&filter, &mut state, -0.5, 0.2, later),
+            (-0.5, 0.2)
+        );
+    }
+}
@@ -1,4 +1,7 @@
+pub(crate) mod buttons;
 mod compiled;
+mod dwell;
+mod filter;
 mod repeat;
 mod tick;
 pub(crate) mod util;
@@ -10,6 +13,7 @@ pub(crate) use repeat::StickProcessor;
 pub(super) enum StepperMode {
     Volume,
     Brightness,
+    KeyboardBacklight,
 }
 
 impl StepperMode {
@@ -34,6 +38,13 @@ impl StepperMode {
             StepperMode::Brightness => {
                 unimplemented!()
             }
+            StepperMode::KeyboardBacklight => {
+                if positive {
+                    gamacros_control::Key::IlluminationUp
+                } else {
+                    gamacros_control::Key::IlluminationDown
+                }
+            }
         }
     }
     pub(super) fn kind_for(
@@ -46,6 +57,9 @@ impl StepperMode {
             StepperMode::Brightness => {
                 repeat::RepeatKind::Brightness { axis, positive }
             }
+            StepperMode::KeyboardBacklight => {
+                repeat::RepeatKind::KeyboardBacklight { axis, positive }
+            }
         }
     }
 }
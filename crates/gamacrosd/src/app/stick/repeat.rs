@@ -1,13 +1,17 @@
 use ahash::AHashMap;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
+use std::sync::Arc;
 use std::time::Instant;
-use gamacros_control::Key;
+use gamacros_control::KeyCombo;
 use gamacros_gamepad::ControllerId;
 use gamacros_workspace::{Axis as ProfileAxis, StickSide};
 
+use crate::app::clock::Clock;
 use crate::app::gamacros::Action;
 
+use super::dwell::DwellState;
+use super::filter::FilterState;
 use super::util::{side_index};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -16,13 +20,19 @@ pub(crate) enum Direction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
-#[derive(Default)]
 pub(crate) struct StickProcessor {
     pub(super) controllers: AHashMap<ControllerId, ControllerRepeatState>,
     pub(super) generation: u64,
     pub(super) regs: Vec<RepeatReg>,
+    /// Axes after filtering, reused between ticks.
+    pub(super) filtered_axes: Vec<(ControllerId, [f32; 6])>,
+    pub(super) clock: Arc<dyn Clock>,
     schedule: BinaryHeap<SchedEntry>,
     seq_counter: u64,
 }
@@ -35,9 +45,15 @@ pub(super) struct ControllerRepeatState {
 #[derive(Default)]
 pub(super) struct SideRepeatState {
     pub(super) scroll_accum: (f32, f32),
-    pub(super) arrows: [Option<RepeatTaskState>; 4],
+    /// Mouse movement not yet sent as a whole pixel.
+    pub(super) mouse_accum: (f32, f32),
+    pub(super) dwell: DwellState,
+    pub(super) filter: Option<FilterState>,
+    pub(super) arrows: [Option<RepeatTaskState>; 8],
     pub(super) volume: [Option<RepeatTaskState>; 4],
     pub(super) brightness: [Option<RepeatTaskState>; 4],
+    pub(super) keyboard_backlight: [Option<RepeatTaskState>; 4],
+    pub(super) seek: [Option<RepeatTaskState>; 4],
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -45,6 +61,8 @@ pub enum RepeatKind {
     Arrow(Direction),
     Volume { axis: ProfileAxis, positive: bool },
     Brightness { axis: ProfileAxis, positive: bool },
+    KeyboardBacklight { axis: ProfileAxis, positive: bool },
+    Seek { axis: ProfileAxis, positive: bool },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -55,7 +73,7 @@ pub(super) struct RepeatTaskId {
 }
 
 pub(super) struct RepeatTaskState {
-    pub(super) key: Key,
+    pub(super) combo: KeyCombo,
     pub(super) fire_on_activate: bool,
     pub(super) initial_delay_ms: u64,
     pub(super) interval_ms: u64,
@@ -67,15 +85,24 @@ pub(super) struct RepeatTaskState {
 
 pub(super) struct RepeatReg {
     pub(super) id: RepeatTaskId,
-    pub(super) key: Key,
+    pub(super) combo: KeyCombo,
     pub(super) fire_on_activate: bool,
     pub(super) initial_delay_ms: u64,
     pub(super) interval_ms: u64,
 }
 
 impl StickProcessor {
-    pub fn new() -> Self {
-        Self::default()
+    /// Creates a processor that schedules repeats on `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            controllers: AHashMap::new(),
+            generation: 0,
+            regs: Vec::new(),
+            filtered_axes: Vec::new(),
+            clock,
+            schedule: BinaryHeap::new(),
+            seq_counter: 0,
+        }
     }
 
     pub(super) fn dir_index(dir: Direction) -> usize {
@@ -84,6 +111,10 @@ impl StickProcessor {
             Direction::Down => 1,
             Direction::Left => 2,
             Direction::Right => 3,
+            Direction::UpLeft => 4,
+            Direction::UpRight => 5,
+            Direction::DownLeft => 6,
+            Direction::DownRight => 7,
         }
     }
 
@@ -115,6 +146,8 @@ impl StickProcessor {
         for (_cid, state) in self.controllers.iter_mut() {
             for s in 0..2 {
                 state.sides[s].scroll_accum = (0.0, 0.0);
+                state.sides[s].mouse_accum = (0.0, 0.0);
+                state.sides[s].dwell.cancel();
             }
         }
     }
@@ -149,15 +182,23 @@ impl StickProcessor {
                     let idx = Self::step_slot_index(axis, positive);
                     &mut side.brightness[idx]
                 }
+                RepeatKind::KeyboardBacklight { axis, positive } => {
+                    let idx = Self::step_slot_index(axis, positive);
+                    &mut side.keyboard_backlight[idx]
+                }
+                RepeatKind::Seek { axis, positive } => {
+                    let idx = Self::step_slot_index(axis, positive);
+                    &mut side.seek[idx]
+                }
             };
 
             match slot {
                 Some(st) => {
-                    let changed = st.key != reg.key
+                    let changed = st.combo != reg.combo
                         || st.interval_ms != reg.interval_ms
                         || st.initial_delay_ms != reg.initial_delay_ms
                         || st.fire_on_activate != reg.fire_on_activate;
-                    st.key = reg.key;
+                    st.combo = reg.combo;
                     st.interval_ms = reg.interval_ms;
                     st.initial_delay_ms = reg.initial_delay_ms;
                     st.fire_on_activate = reg.fire_on_activate;
@@ -165,24 +206,29 @@ impl StickProcessor {
 
                     if changed {
                         st.seq = seq_new;
-                        let due_ms = if st.delay_done {
-                            st.interval_ms
+                        // A repeating task keeps its rhythm: the next step is
+                        // due an interval after the last one, so a stick that
+                        // jitters between ticks does not postpone it forever.
+                        let due = if st.delay_done {
+                            let next = st.last_fire
+                                + std::time::Duration::from_millis(st.interval_ms);
+                            Some(next.max(now))
                         } else {
-                            st.initial_delay_ms
+                            (st.initial_delay_ms > 0).then(|| {
+                                now + std::time::Duration::from_millis(
+                                    st.initial_delay_ms,
+                                )
+                            })
                         };
-                        if due_ms > 0 {
-                            schedule_next = Some((
-                                reg.id,
-                                st.seq,
-                                now + std::time::Duration::from_millis(due_ms),
-                            ));
+                        if let Some(due) = due {
+                            schedule_next = Some((reg.id, st.seq, due));
                         }
                     }
                 }
                 None => {
                     let delay_done = reg.initial_delay_ms == 0;
                     let st = RepeatTaskState {
-                        key: reg.key,
+                        combo: reg.combo.clone(),
                         fire_on_activate: reg.fire_on_activate,
                         initial_delay_ms: reg.initial_delay_ms,
                         interval_ms: reg.interval_ms,
@@ -193,9 +239,7 @@ impl StickProcessor {
                     };
                     *slot = Some(st);
                     if reg.fire_on_activate {
-                        action = Some(Action::KeyTap(
-                            gamacros_control::KeyCombo::from_key(reg.key),
-                        ));
+                        action = Some(Action::KeyTap(reg.combo));
                     }
                     let due_ms = if delay_done {
                         reg.interval_ms
@@ -221,21 +265,31 @@ impl StickProcessor {
     }
 
     pub fn next_repeat_due(&mut self) -> Option<Instant> {
+        let mut next = None;
         while let Some(entry) = self.schedule.peek() {
             if self.entry_is_stale(entry) {
                 let _ = self.schedule.pop();
                 continue;
             }
-            return Some(entry.due);
+            next = Some(entry.due);
+            break;
+        }
+        // Dwell clicks share the repeat timer
+        let dwell = self
+            .controllers
+            .values()
+            .flat_map(|ctrl| ctrl.sides.iter())
+            .filter_map(|side| side.dwell.due())
+            .min();
+        match (next, dwell) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
         }
-        None
     }
 
-    pub fn process_due_repeats(
-        &mut self,
-        now: Instant,
-        sink: &mut impl FnMut(Action),
-    ) {
+    /// Fires repeat tasks due by the current clock time.
+    pub fn process_due_repeats(&mut self, sink: &mut impl FnMut(Action)) {
+        let now = self.clock.now();
         loop {
             let entry = match self.schedule.peek() {
                 Some(top) if self.entry_is_stale(top) => {
@@ -251,9 +305,7 @@ impl StickProcessor {
                 if let Some(slot) = self.slot_for_mut(&entry.id) {
                     if let Some(st) = slot.as_mut() {
                         if st.seq == entry.seq {
-                            (sink)(Action::KeyTap(
-                                gamacros_control::KeyCombo::from_key(st.key),
-                            ));
+                            (sink)(Action::KeyTap(st.combo.clone()));
                             st.last_fire = now;
                             st.delay_done = true;
                             let next_due = now
@@ -267,6 +319,13 @@ impl StickProcessor {
                 self.push_due(id, seq, due);
             }
         }
+        for ctrl in self.controllers.values_mut() {
+            for side in ctrl.sides.iter_mut() {
+                if let Some(button) = side.dwell.take_due(now) {
+                    (sink)(Action::MouseClick(button));
+                }
+            }
+        }
     }
 
     pub(super) fn repeater_cleanup_inactive(&mut self) {
@@ -294,6 +353,20 @@ impl StickProcessor {
                         }
                     }
                 }
+                for slot in side.keyboard_backlight.iter_mut() {
+                    if let Some(st) = slot.as_ref() {
+                        if st.last_seen_generation != gen {
+                            *slot = None;
+                        }
+                    }
+                }
+                for slot in side.seek.iter_mut() {
+                    if let Some(st) = slot.as_ref() {
+                        if st.last_seen_generation != gen {
+                            *slot = None;
+                        }
+                    }
+                }
             }
         }
     }
@@ -328,6 +401,13 @@ impl StickProcessor {
             RepeatKind::Brightness { axis, positive } => {
                 side.brightness[Self::step_slot_index(axis, positive)].as_ref()
             }
+            RepeatKind::KeyboardBacklight { axis, positive } => {
+                let idx = Self::step_slot_index(axis, positive);
+                side.keyboard_backlight[idx].as_ref()
+            }
+            RepeatKind::Seek { axis, positive } => {
+                side.seek[Self::step_slot_index(axis, positive)].as_ref()
+            }
         }
     }
 
@@ -346,6 +426,13 @@ impl StickProcessor {
             RepeatKind::Brightness { axis, positive } => {
                 Some(&mut side.brightness[Self::step_slot_index(axis, positive)])
             }
+            RepeatKind::KeyboardBacklight { axis, positive } => {
+                let idx = Self::step_slot_index(axis, positive);
+                Some(&mut side.keyboard_backlight[idx])
+            }
+            RepeatKind::Seek { axis, positive } => {
+                Some(&mut side.seek[Self::step_slot_index(axis, positive)])
+            }
         }
     }
 }
@@ -374,3 +461,83 @@ impl Ord for SchedEntry {
         other.due.cmp(&self.due)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use gamacros_control::Key;
+
+    use super::*;
+    use crate::app::clock::ManualClock;
+
+    fn arrow_reg(initial_delay_ms: u64, interval_ms: u64) -> RepeatReg {
+        RepeatReg {
+            id: RepeatTaskId {
+                controller: 0,
+                side: StickSide::Left,
+                kind: RepeatKind::Arrow(Direction::Right),
+            },
+            combo: KeyCombo::from_key(Key::RightArrow),
+            fire_on_activate: true,
+            initial_delay_ms,
+            interval_ms,
+        }
+    }
+
+    /// Advances the clock by `ms` in 1ms steps, returning the tap times.
+    fn run_for(
+        sticks: &mut StickProcessor,
+        clock: &ManualClock,
+        from_ms: u64,
+        ms: u64,
+    ) -> Vec<u64> {
+        let mut taps = Vec::new();
+        for t in from_ms + 1..=from_ms + ms {
+            clock.advance(Duration::from_millis(1));
+            sticks.process_due_repeats(&mut |_| taps.push(t));
+        }
+        taps
+    }
+
+    #[test]
+    fn repeats_after_initial_delay_at_interval() {
+        let clock = ManualClock::new();
+        let mut sticks = StickProcessor::with_clock(Arc::new(clock.clone()));
+        let now = clock.now();
+        assert!(sticks.repeater_register(arrow_reg(200, 50), now).is_some());
+        assert_eq!(
+            sticks.next_repeat_due(),
+            Some(now + Duration::from_millis(200))
+        );
+
+        assert_eq!(run_for(&mut sticks, &clock, 0, 300), [200, 250, 300]);
+    }
+
+    #[test]
+    fn released_direction_stops_repeating() {
+        let clock = ManualClock::new();
+        let mut sticks = StickProcessor::with_clock(Arc::new(clock.clone()));
+        sticks.repeater_register(arrow_reg(0, 30), clock.now());
+        assert_eq!(run_for(&mut sticks, &clock, 0, 60), [30, 60]);
+
+        sticks.release_all_arrows();
+        assert!(run_for(&mut sticks, &clock, 60, 100).is_empty());
+        assert_eq!(sticks.next_repeat_due(), None);
+    }
+
+    #[test]
+    fn changed_interval_keeps_rhythm() {
+        let clock = ManualClock::new();
+        let mut sticks = StickProcessor::with_clock(Arc::new(clock.clone()));
+        sticks.repeater_register(arrow_reg(0, 100), clock.now());
+        assert_eq!(run_for(&mut sticks, &clock, 0, 100), [100]);
+
+        assert!(run_for(&mut sticks, &clock, 100, 50).is_empty());
+
+        // The next step is due an interval after the last one, not after
+        // the change
+        sticks.repeater_register(arrow_reg(0, 80), clock.now());
+        assert_eq!(run_for(&mut sticks, &clock, 150, 50), [180]);
+    }
+}
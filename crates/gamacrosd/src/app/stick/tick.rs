@@ -1,13 +1,16 @@
-use gamacros_gamepad::ControllerId;
+use gamacros_control::KeyCombo;
+use gamacros_gamepad::{Axis as CtrlAxis, ControllerId};
 use gamacros_workspace::{Axis as ProfileAxis, StickMode, StickSide};
 
 use crate::app::gamacros::Action;
 
 use super::compiled::CompiledStickRules;
+use super::filter::filter_xy;
 use super::repeat::{Direction, RepeatKind, RepeatTaskId, RepeatReg, StickProcessor};
 use super::StepperMode;
 use super::util::{
-    axis_index, axes_for_side, invert_xy, magnitude2d, normalize_after_deadzone,
+    apply_deadzone, apply_deadzone_xy, axis_index, axes_for_side, fast_gamma,
+    invert_xy, magnitude2d, normalize_after_deadzone, scale_to_range, side_index,
 };
 
 impl StickProcessor {
@@ -24,17 +27,20 @@ impl StickProcessor {
             return;
         };
 
-        let now = std::time::Instant::now();
         self.generation = self.generation.wrapping_add(1);
-
-        if matches!(bindings.left(), Some(StickMode::Arrows(_)))
-            || matches!(bindings.right(), Some(StickMode::Arrows(_)))
+        let now = self.clock.now();
+        let mut filtered = std::mem::take(&mut self.filtered_axes);
+        let axes_list = if self.filter_axes(bindings, axes_list, now, &mut filtered)
         {
+            &filtered[..]
+        } else {
+            axes_list
+        };
+
+        if bindings.any_mode(|mode| matches!(mode, StickMode::Arrows(_))) {
             self.tick_arrows(now, &mut sink, axes_list, bindings);
         }
-        if matches!(bindings.left(), Some(StickMode::Volume(_)))
-            || matches!(bindings.right(), Some(StickMode::Volume(_)))
-        {
+        if bindings.any_mode(|mode| matches!(mode, StickMode::Volume(_))) {
             self.tick_stepper(
                 now,
                 &mut sink,
@@ -43,9 +49,7 @@ impl StickProcessor {
                 StepperMode::Volume,
             );
         }
-        if matches!(bindings.left(), Some(StickMode::Brightness(_)))
-            || matches!(bindings.right(), Some(StickMode::Brightness(_)))
-        {
+        if bindings.any_mode(|mode| matches!(mode, StickMode::Brightness(_))) {
             self.tick_stepper(
                 now,
                 &mut sink,
@@ -54,19 +58,74 @@ impl StickProcessor {
                 StepperMode::Brightness,
             );
         }
-        if matches!(bindings.left(), Some(StickMode::MouseMove(_)))
-            || matches!(bindings.right(), Some(StickMode::MouseMove(_)))
+        if bindings.any_mode(|mode| matches!(mode, StickMode::KeyboardBacklight(_)))
         {
-            self.tick_mouse(&mut sink, axes_list, bindings);
+            self.tick_stepper(
+                now,
+                &mut sink,
+                axes_list,
+                bindings,
+                StepperMode::KeyboardBacklight,
+            );
         }
-        if matches!(bindings.left(), Some(StickMode::Scroll(_)))
-            || matches!(bindings.right(), Some(StickMode::Scroll(_)))
-        {
+        if bindings.any_mode(|mode| matches!(mode, StickMode::Seek(_))) {
+            self.tick_seek(now, &mut sink, axes_list, bindings);
+        }
+        if bindings.any_mode(|mode| matches!(mode, StickMode::MouseMove(_))) {
+            self.tick_mouse(now, &mut sink, axes_list, bindings);
+        }
+        if bindings.any_mode(|mode| matches!(mode, StickMode::Scroll(_))) {
             self.tick_scroll(&mut sink, axes_list, bindings);
         }
 
         // Repeat draining is now event-driven, cleanup still needs to run per generation
         self.repeater_cleanup_inactive();
+        self.filtered_axes = filtered;
+    }
+
+    /// Writes axes with the filters of the bound modes applied to `out`.
+    /// Returns `false`, leaving `out` untouched, when no filter is set.
+    fn filter_axes(
+        &mut self,
+        bindings: &CompiledStickRules,
+        axes_list: &[(ControllerId, [f32; 6])],
+        now: std::time::Instant,
+        out: &mut Vec<(ControllerId, [f32; 6])>,
+    ) -> bool {
+        if !bindings.any_mode(|mode| !mode.filter().is_off()) {
+            return false;
+        }
+        out.clear();
+        for (id, mut axes) in axes_list.iter().copied() {
+            let state = self.controllers.entry(id).or_default();
+            let sides = [
+                (StickSide::Left, bindings.left_for(id)),
+                (StickSide::Right, bindings.right_for(id)),
+            ];
+            for (side, mode) in &sides {
+                let Some(filter) = mode.map(StickMode::filter) else {
+                    continue;
+                };
+                if filter.is_off() {
+                    continue;
+                }
+                let (x_axis, y_axis) = match side {
+                    StickSide::Left => (CtrlAxis::LeftX, CtrlAxis::LeftY),
+                    StickSide::Right => (CtrlAxis::RightX, CtrlAxis::RightY),
+                };
+                let (x, y) = filter_xy(
+                    filter,
+                    &mut state.sides[side_index(side)].filter,
+                    axes[axis_index(x_axis)],
+                    axes[axis_index(y_axis)],
+                    now,
+                );
+                axes[axis_index(x_axis)] = x;
+                axes[axis_index(y_axis)] = y;
+            }
+            out.push((id, axes));
+        }
+        true
     }
 
     pub fn has_active_repeats(&self) -> bool {
@@ -75,6 +134,8 @@ impl StickProcessor {
                 if side.arrows.iter().any(|s| s.is_some())
                     || side.volume.iter().any(|s| s.is_some())
                     || side.brightness.iter().any(|s| s.is_some())
+                    || side.keyboard_backlight.iter().any(|s| s.is_some())
+                    || side.seek.iter().any(|s| s.is_some())
                 {
                     return true;
                 }
@@ -93,13 +154,13 @@ impl StickProcessor {
         let mut regs = std::mem::take(&mut self.regs);
         regs.clear();
         for (id, axes) in axes_list.iter().cloned() {
-            if let Some(StickMode::Arrows(params)) = bindings.left() {
+            if let Some(StickMode::Arrows(params)) = bindings.left_for(id) {
                 let (x0, y0) = axes_for_side(axes, &StickSide::Left);
                 let (x, y) = invert_xy(x0, y0, params.invert_x, !params.invert_y);
-                let mag2 = x * x + y * y;
-                let dead2 = params.deadzone * params.deadzone;
-                let new_dir = if mag2 < dead2 {
-                    None
+                let (x, y) =
+                    apply_deadzone(x, y, params.deadzone, params.deadzone_shape);
+                let new_dir = if params.eight_way {
+                    Self::quantize_direction8(x, y)
                 } else {
                     Self::quantize_direction(x, y)
                 };
@@ -109,23 +170,22 @@ impl StickProcessor {
                         side: StickSide::Left,
                         kind: RepeatKind::Arrow(dir),
                     };
-                    let key = Self::get_direction_key(dir);
                     regs.push(RepeatReg {
                         id: task_id,
-                        key,
+                        combo: Self::get_direction_combo(dir),
                         fire_on_activate: true,
                         initial_delay_ms: params.repeat_delay_ms,
                         interval_ms: params.repeat_interval_ms,
                     });
                 }
             }
-            if let Some(StickMode::Arrows(params)) = bindings.right() {
+            if let Some(StickMode::Arrows(params)) = bindings.right_for(id) {
                 let (x0, y0) = axes_for_side(axes, &StickSide::Right);
                 let (x, y) = invert_xy(x0, y0, params.invert_x, !params.invert_y);
-                let mag2 = x * x + y * y;
-                let dead2 = params.deadzone * params.deadzone;
-                let new_dir = if mag2 < dead2 {
-                    None
+                let (x, y) =
+                    apply_deadzone(x, y, params.deadzone, params.deadzone_shape);
+                let new_dir = if params.eight_way {
+                    Self::quantize_direction8(x, y)
                 } else {
                     Self::quantize_direction(x, y)
                 };
@@ -135,10 +195,9 @@ impl StickProcessor {
                         side: StickSide::Right,
                         kind: RepeatKind::Arrow(dir),
                     };
-                    let key = Self::get_direction_key(dir);
                     regs.push(RepeatReg {
                         id: task_id,
-                        key,
+                        combo: Self::get_direction_combo(dir),
                         fire_on_activate: true,
                         initial_delay_ms: params.repeat_delay_ms,
                         interval_ms: params.repeat_interval_ms,
@@ -165,9 +224,13 @@ impl StickProcessor {
         let mut regs = std::mem::take(&mut self.regs);
         regs.clear();
         for (cid, axes) in axes_list.iter().cloned() {
-            if let Some(step_params) = match (&mode, bindings.left()) {
+            if let Some(step_params) = match (&mode, bindings.left_for(cid)) {
                 (StepperMode::Volume, Some(StickMode::Volume(p))) => Some(p),
                 (StepperMode::Brightness, Some(StickMode::Brightness(p))) => Some(p),
+                (
+                    StepperMode::KeyboardBacklight,
+                    Some(StickMode::KeyboardBacklight(p)),
+                ) => Some(p),
                 _ => None,
             } {
                 let (vx, vy) = (
@@ -180,11 +243,16 @@ impl StickProcessor {
                 };
                 let mag = v.abs();
                 if mag >= step_params.deadzone {
-                    let t = mag;
-                    let interval_ms = (step_params.max_interval_ms as f32)
-                        + (1.0 - t)
-                            * ((step_params.min_interval_ms as f32)
-                                - (step_params.max_interval_ms as f32));
+                    let t = scale_to_range(mag, step_params.outer_deadzone);
+                    let interval_ms = match &step_params.curve {
+                        Some(curve) => curve.interval_ms(t) as f32,
+                        None => {
+                            (step_params.max_interval_ms as f32)
+                                + (1.0 - t)
+                                    * ((step_params.min_interval_ms as f32)
+                                        - (step_params.max_interval_ms as f32))
+                        }
+                    };
                     let positive = v >= 0.0;
                     let key = mode.key_for(positive);
                     let kind = mode.kind_for(step_params.axis, positive);
@@ -195,16 +263,20 @@ impl StickProcessor {
                     };
                     regs.push(RepeatReg {
                         id: task_id,
-                        key,
+                        combo: KeyCombo::from_key(key),
                         fire_on_activate: true,
                         initial_delay_ms: 0,
                         interval_ms: interval_ms as u64,
                     });
                 }
             }
-            if let Some(step_params) = match (&mode, bindings.right()) {
+            if let Some(step_params) = match (&mode, bindings.right_for(cid)) {
                 (StepperMode::Volume, Some(StickMode::Volume(p))) => Some(p),
                 (StepperMode::Brightness, Some(StickMode::Brightness(p))) => Some(p),
+                (
+                    StepperMode::KeyboardBacklight,
+                    Some(StickMode::KeyboardBacklight(p)),
+                ) => Some(p),
                 _ => None,
             } {
                 let (vx, vy) = (
@@ -217,11 +289,16 @@ impl StickProcessor {
                 };
                 let mag = v.abs();
                 if mag >= step_params.deadzone {
-                    let t = mag;
-                    let interval_ms = (step_params.max_interval_ms as f32)
-                        + (1.0 - t)
-                            * ((step_params.min_interval_ms as f32)
-                                - (step_params.max_interval_ms as f32));
+                    let t = scale_to_range(mag, step_params.outer_deadzone);
+                    let interval_ms = match &step_params.curve {
+                        Some(curve) => curve.interval_ms(t) as f32,
+                        None => {
+                            (step_params.max_interval_ms as f32)
+                                + (1.0 - t)
+                                    * ((step_params.min_interval_ms as f32)
+                                        - (step_params.max_interval_ms as f32))
+                        }
+                    };
                     let positive = v >= 0.0;
                     let key = mode.key_for(positive);
                     let kind = mode.kind_for(step_params.axis, positive);
@@ -232,7 +309,7 @@ impl StickProcessor {
                     };
                     regs.push(RepeatReg {
                         id: task_id,
-                        key,
+                        combo: KeyCombo::from_key(key),
                         fire_on_activate: true,
                         initial_delay_ms: 0,
                         interval_ms: interval_ms as u64,
@@ -248,74 +325,173 @@ impl StickProcessor {
         self.regs = regs;
     }
 
+    fn tick_seek(
+        &mut self,
+        now: std::time::Instant,
+        sink: &mut impl FnMut(Action),
+        axes_list: &[(ControllerId, [f32; 6])],
+        bindings: &CompiledStickRules,
+    ) {
+        let mut regs = std::mem::take(&mut self.regs);
+        regs.clear();
+        for (cid, axes) in axes_list.iter().cloned() {
+            for (side, mode) in [
+                (StickSide::Left, bindings.left_for(cid)),
+                (StickSide::Right, bindings.right_for(cid)),
+            ] {
+                let Some(StickMode::Seek(params)) = mode else {
+                    continue;
+                };
+                let (x, y) = axes_for_side(axes, &side);
+                // Stick up reports negative values
+                let v = match params.axis {
+                    ProfileAxis::X => x,
+                    ProfileAxis::Y => -y,
+                };
+                let v = if params.invert { -v } else { v };
+                let t = normalize_after_deadzone(
+                    v.abs(),
+                    params.deadzone,
+                    params.outer_deadzone,
+                );
+                if t <= 0.0 {
+                    continue;
+                }
+                let interval_ms = match &params.curve {
+                    Some(curve) => curve.interval_ms(t) as f32,
+                    None => {
+                        let rate = fast_gamma(t, params.gamma);
+                        (params.min_interval_ms as f32)
+                            + rate
+                                * ((params.max_interval_ms as f32)
+                                    - (params.min_interval_ms as f32))
+                    }
+                };
+                let positive = v > 0.0;
+                let combo = if positive {
+                    params.forward.clone()
+                } else {
+                    params.backward.clone()
+                };
+                regs.push(RepeatReg {
+                    id: RepeatTaskId {
+                        controller: cid,
+                        side,
+                        kind: RepeatKind::Seek {
+                            axis: params.axis,
+                            positive,
+                        },
+                    },
+                    combo,
+                    fire_on_activate: true,
+                    initial_delay_ms: 0,
+                    interval_ms: interval_ms.round() as u64,
+                });
+            }
+        }
+        for reg in regs.drain(..) {
+            if let Some(a) = self.repeater_register(reg, now) {
+                (sink)(a);
+            }
+        }
+        self.regs = regs;
+    }
+
     fn tick_mouse(
         &mut self,
+        now: std::time::Instant,
         sink: &mut impl FnMut(Action),
         axes_list: &[(ControllerId, [f32; 6])],
         bindings: &CompiledStickRules,
     ) {
-        for (_cid, axes) in axes_list.iter().cloned() {
-            if let Some(StickMode::MouseMove(params)) = bindings.left() {
+        for (cid, axes) in axes_list.iter().cloned() {
+            if let Some(StickMode::MouseMove(params)) = bindings.left_for(cid) {
                 let (x0, y0) = axes_for_side(axes, &StickSide::Left);
                 let (x, y) = invert_xy(x0, y0, params.invert_x, params.invert_y);
+                let (x, y) =
+                    apply_deadzone(x, y, params.deadzone, params.deadzone_shape);
                 let mag_raw = magnitude2d(x, y);
-                if mag_raw >= params.deadzone {
-                    let base = normalize_after_deadzone(mag_raw, params.deadzone);
-                    let mag = Self::fast_gamma(base, params.gamma);
+                if mag_raw > 0.0 {
+                    let base = normalize_after_deadzone(
+                        mag_raw,
+                        params.deadzone,
+                        params.outer_deadzone,
+                    );
+                    let mag = fast_gamma(base, params.gamma);
                     if mag > 0.0 {
                         let dir_x = x / mag_raw;
                         let dir_y = y / mag_raw;
                         let speed_px_s = params.max_speed_px_s * mag;
                         let dt_s = 0.010;
-                        let dx = (speed_px_s * dir_x * dt_s).round() as i32;
-                        let dy = (speed_px_s * dir_y * dt_s).round() as i32;
+                        let sidx = side_index(&StickSide::Left);
+                        let state =
+                            &mut self.controllers.entry(cid).or_default().sides
+                                [sidx];
+                        let accum = &mut state.mouse_accum;
+                        // Fractions of a pixel carry over to the next tick
+                        accum.0 += speed_px_s * dir_x * dt_s;
+                        accum.1 += speed_px_s * dir_y * dt_s;
+                        let dx = accum.0.trunc() as i32;
+                        let dy = accum.1.trunc() as i32;
                         if dx != 0 || dy != 0 {
+                            accum.0 -= dx as f32;
+                            accum.1 -= dy as f32;
                             (sink)(Action::MouseMove { dx, dy });
+                            if let Some(dwell) = &params.dwell {
+                                state.dwell.moved(dwell, now);
+                            }
                         }
                     }
+                } else if let Some(state) = self.controllers.get_mut(&cid) {
+                    state.sides[side_index(&StickSide::Left)].mouse_accum =
+                        (0.0, 0.0);
                 }
             }
-            if let Some(StickMode::MouseMove(params)) = bindings.right() {
+            if let Some(StickMode::MouseMove(params)) = bindings.right_for(cid) {
                 let (x0, y0) = axes_for_side(axes, &StickSide::Right);
                 let (x, y) = invert_xy(x0, y0, params.invert_x, params.invert_y);
+                let (x, y) =
+                    apply_deadzone(x, y, params.deadzone, params.deadzone_shape);
                 let mag_raw = magnitude2d(x, y);
-                if mag_raw >= params.deadzone {
-                    let base = normalize_after_deadzone(mag_raw, params.deadzone);
-                    let mag = Self::fast_gamma(base, params.gamma);
+                if mag_raw > 0.0 {
+                    let base = normalize_after_deadzone(
+                        mag_raw,
+                        params.deadzone,
+                        params.outer_deadzone,
+                    );
+                    let mag = fast_gamma(base, params.gamma);
                     if mag > 0.0 {
                         let dir_x = x / mag_raw;
                         let dir_y = y / mag_raw;
                         let speed_px_s = params.max_speed_px_s * mag;
                         let dt_s = 0.010;
-                        let dx = (speed_px_s * dir_x * dt_s).round() as i32;
-                        let dy = (speed_px_s * dir_y * dt_s).round() as i32;
+                        let sidx = side_index(&StickSide::Right);
+                        let state =
+                            &mut self.controllers.entry(cid).or_default().sides
+                                [sidx];
+                        let accum = &mut state.mouse_accum;
+                        // Fractions of a pixel carry over to the next tick
+                        accum.0 += speed_px_s * dir_x * dt_s;
+                        accum.1 += speed_px_s * dir_y * dt_s;
+                        let dx = accum.0.trunc() as i32;
+                        let dy = accum.1.trunc() as i32;
                         if dx != 0 || dy != 0 {
+                            accum.0 -= dx as f32;
+                            accum.1 -= dy as f32;
                             (sink)(Action::MouseMove { dx, dy });
+                            if let Some(dwell) = &params.dwell {
+                                state.dwell.moved(dwell, now);
+                            }
                         }
                     }
+                } else if let Some(state) = self.controllers.get_mut(&cid) {
+                    state.sides[side_index(&StickSide::Right)].mouse_accum =
+                        (0.0, 0.0);
                 }
             }
         }
     }
 
-    #[inline]
-    fn fast_gamma(base: f32, gamma: f32) -> f32 {
-        let g = gamma.max(0.1);
-        if (g - 1.0).abs() < 1e-6 {
-            base
-        } else if (g - 0.5).abs() < 1e-6 {
-            base.sqrt()
-        } else if (g - 1.5).abs() < 1e-6 {
-            base * base.sqrt()
-        } else if (g - 2.0).abs() < 1e-6 {
-            base * base
-        } else if (g - 3.0).abs() < 1e-6 {
-            base * base * base
-        } else {
-            base.powf(g)
-        }
-    }
-
     fn tick_scroll(
         &mut self,
         sink: &mut impl FnMut(Action),
@@ -323,58 +499,88 @@ impl StickProcessor {
         bindings: &CompiledStickRules,
     ) {
         for (cid, axes) in axes_list.iter().cloned() {
-            if let Some(StickMode::Scroll(params)) = bindings.left() {
+            if let Some(StickMode::Scroll(params)) = bindings.left_for(cid) {
                 let (x0, y0) = axes_for_side(axes, &StickSide::Left);
                 let (mut x, y) =
                     invert_xy(x0, y0, params.invert_x, !params.invert_y);
                 if !params.horizontal {
                     x = 0.0;
                 }
-                let mag_raw = x.abs().max(y.abs());
-                if mag_raw > params.deadzone {
+                let (x, y) = apply_deadzone_xy(
+                    x,
+                    y,
+                    (params.horizontal_deadzone, params.deadzone),
+                    params.deadzone_shape,
+                );
+                if x != 0.0 || y != 0.0 {
+                    let x = scale_to_range(x, params.outer_deadzone);
+                    let y = scale_to_range(y, params.outer_deadzone);
                     let dt_s = 0.1;
                     let sidx = super::util::side_index(&StickSide::Left);
                     let accum = &mut self.controllers.entry(cid).or_default().sides
                         [sidx]
                         .scroll_accum;
-                    accum.0 += params.speed_lines_s * x * dt_s;
+                    accum.0 += params.horizontal_speed_lines_s * x * dt_s;
                     accum.1 += params.speed_lines_s * y * dt_s;
                     let h = accum.0.round() as i32;
                     let v = accum.1.round() as i32;
                     if h != 0 {
-                        (sink)(Action::Scroll { h, v: 0 });
+                        (sink)(Action::Scroll {
+                            h,
+                            v: 0,
+                            unit: params.unit,
+                        });
                         accum.0 -= h as f32;
                     }
                     if v != 0 {
-                        (sink)(Action::Scroll { h: 0, v });
+                        (sink)(Action::Scroll {
+                            h: 0,
+                            v,
+                            unit: params.unit,
+                        });
                         accum.1 -= v as f32;
                     }
                 }
             }
-            if let Some(StickMode::Scroll(params)) = bindings.right() {
+            if let Some(StickMode::Scroll(params)) = bindings.right_for(cid) {
                 let (x0, y0) = axes_for_side(axes, &StickSide::Right);
                 let (mut x, y) =
                     invert_xy(x0, y0, params.invert_x, !params.invert_y);
                 if !params.horizontal {
                     x = 0.0;
                 }
-                let mag_raw = x.abs().max(y.abs());
-                if mag_raw > params.deadzone {
+                let (x, y) = apply_deadzone_xy(
+                    x,
+                    y,
+                    (params.horizontal_deadzone, params.deadzone),
+                    params.deadzone_shape,
+                );
+                if x != 0.0 || y != 0.0 {
+                    let x = scale_to_range(x, params.outer_deadzone);
+                    let y = scale_to_range(y, params.outer_deadzone);
                     let dt_s = 0.1;
                     let sidx = super::util::side_index(&StickSide::Right);
                     let accum = &mut self.controllers.entry(cid).or_default().sides
                         [sidx]
                         .scroll_accum;
-                    accum.0 += params.speed_lines_s * x * dt_s;
+                    accum.0 += params.horizontal_speed_lines_s * x * dt_s;
                     accum.1 += params.speed_lines_s * y * dt_s;
                     let h = accum.0.round() as i32;
                     let v = accum.1.round() as i32;
                     if h != 0 {
-                        (sink)(Action::Scroll { h, v: 0 });
+                        (sink)(Action::Scroll {
+                            h,
+                            v: 0,
+                            unit: params.unit,
+                        });
                         accum.0 -= h as f32;
                     }
                     if v != 0 {
-                        (sink)(Action::Scroll { h: 0, v });
+                        (sink)(Action::Scroll {
+                            h: 0,
+                            v,
+                            unit: params.unit,
+                        });
                         accum.1 -= v as f32;
                     }
                 }
@@ -410,13 +616,65 @@ impl StickProcessor {
         }
     }
 
+    /// Like `quantize_direction`, with diagonals in the 45° sectors between
+    /// the axes.
     #[inline]
-    pub fn get_direction_key(dir: Direction) -> gamacros_control::Key {
-        match dir {
-            Direction::Up => gamacros_control::Key::UpArrow,
-            Direction::Down => gamacros_control::Key::DownArrow,
-            Direction::Left => gamacros_control::Key::LeftArrow,
-            Direction::Right => gamacros_control::Key::RightArrow,
+    pub fn quantize_direction8(x: f32, y: f32) -> Option<Direction> {
+        // tan(22.5°), where the sector of an axis ends
+        const SECTOR_TAN: f32 = 0.414_213_57;
+        let ax = x.abs();
+        let ay = y.abs();
+        if ay <= ax * SECTOR_TAN || ax <= ay * SECTOR_TAN {
+            return Self::quantize_direction(x, y);
         }
+        Some(match (x > 0.0, y > 0.0) {
+            (false, true) => Direction::UpLeft,
+            (true, true) => Direction::UpRight,
+            (false, false) => Direction::DownLeft,
+            (true, false) => Direction::DownRight,
+        })
+    }
+
+    /// Arrow keys sent for a direction, both keys of a diagonal.
+    #[inline]
+    pub fn get_direction_combo(dir: Direction) -> KeyCombo {
+        use gamacros_control::Key;
+        let (first, second) = match dir {
+            Direction::Up => (Key::UpArrow, None),
+            Direction::Down => (Key::DownArrow, None),
+            Direction::Left => (Key::LeftArrow, None),
+            Direction::Right => (Key::RightArrow, None),
+            Direction::UpLeft => (Key::UpArrow, Some(Key::LeftArrow)),
+            Direction::UpRight => (Key::UpArrow, Some(Key::RightArrow)),
+            Direction::DownLeft => (Key::DownArrow, Some(Key::LeftArrow)),
+            Direction::DownRight => (Key::DownArrow, Some(Key::RightArrow)),
+        };
+        let mut combo = KeyCombo::from_key(first);
+        combo.keys.extend(second);
+        combo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizes_diagonals_in_eight_way_mode() {
+        let quantize = StickProcessor::quantize_direction8;
+        assert_eq!(quantize(0.0, 0.0), None);
+        assert_eq!(quantize(0.7, 0.2), Some(Direction::Right));
+        assert_eq!(quantize(-0.5, 0.5), Some(Direction::UpLeft));
+        assert_eq!(quantize(0.4, -0.6), Some(Direction::DownRight));
+        assert_eq!(quantize(0.1, -0.6), Some(Direction::Down));
+
+        let combo = StickProcessor::get_direction_combo(Direction::UpLeft);
+        assert_eq!(
+            combo.keys.as_slice(),
+            [
+                gamacros_control::Key::UpArrow,
+                gamacros_control::Key::LeftArrow
+            ]
+        );
     }
 }
@@ -1,5 +1,5 @@
 use gamacros_gamepad::Axis as CtrlAxis;
-use gamacros_workspace::StickSide;
+use gamacros_workspace::{DeadzoneShape, StickSide};
 
 #[inline]
 pub(crate) fn axis_index(axis: CtrlAxis) -> usize {
@@ -52,11 +52,123 @@ pub(crate) fn magnitude2d(x: f32, y: f32) -> f32 {
     (x * x + y * y).sqrt()
 }
 
+/// Applies a response curve exponent, with fast paths for common values.
 #[inline]
-pub(crate) fn normalize_after_deadzone(mag: f32, deadzone: f32) -> f32 {
+pub(crate) fn fast_gamma(base: f32, gamma: f32) -> f32 {
+    let g = gamma.max(0.1);
+    if (g - 1.0).abs() < 1e-6 {
+        base
+    } else if (g - 0.5).abs() < 1e-6 {
+        base.sqrt()
+    } else if (g - 1.5).abs() < 1e-6 {
+        base * base.sqrt()
+    } else if (g - 2.0).abs() < 1e-6 {
+        base * base
+    } else if (g - 3.0).abs() < 1e-6 {
+        base * base * base
+    } else {
+        base.powf(g)
+    }
+}
+
+/// Zeroes the parts of a stick position that fall inside the deadzone.
+#[inline]
+pub(crate) fn apply_deadzone(
+    x: f32,
+    y: f32,
+    deadzone: f32,
+    shape: DeadzoneShape,
+) -> (f32, f32) {
+    apply_deadzone_xy(x, y, (deadzone, deadzone), shape)
+}
+
+/// Like `apply_deadzone`, with separate deadzones of the x and y axes that
+/// stretch the shape into an ellipse or a rectangle.
+#[inline]
+pub(crate) fn apply_deadzone_xy(
+    x: f32,
+    y: f32,
+    (deadzone_x, deadzone_y): (f32, f32),
+    shape: DeadzoneShape,
+) -> (f32, f32) {
+    // Position in deadzone units, past 1.0 is outside
+    let ratio = |v: f32, deadzone: f32| {
+        if deadzone > 0.0 {
+            v.abs() / deadzone
+        } else if v == 0.0 {
+            0.0
+        } else {
+            f32::INFINITY
+        }
+    };
+    let (rx, ry) = (ratio(x, deadzone_x), ratio(y, deadzone_y));
+    let inside = match shape {
+        DeadzoneShape::Circular => rx * rx + ry * ry < 1.0,
+        DeadzoneShape::Square => rx.max(ry) < 1.0,
+        DeadzoneShape::Cross => {
+            let x = if rx < 1.0 { 0.0 } else { x };
+            let y = if ry < 1.0 { 0.0 } else { y };
+            return (x, y);
+        }
+    };
+    if inside {
+        (0.0, 0.0)
+    } else {
+        (x, y)
+    }
+}
+
+/// Maps deflection between the deadzones to `0.0..=1.0`, so the
+/// practical maximum of a pad that never reaches the edge is full speed.
+#[inline]
+pub(crate) fn normalize_after_deadzone(
+    mag: f32,
+    deadzone: f32,
+    outer_deadzone: f32,
+) -> f32 {
     if mag <= deadzone {
         0.0
     } else {
-        ((mag - deadzone) / (1.0 - deadzone)).clamp(0.0, 1.0)
+        ((mag - deadzone) / (1.0 - outer_deadzone - deadzone)).clamp(0.0, 1.0)
+    }
+}
+
+/// Stretches an axis value so the outer deadzone reads as full deflection.
+#[inline]
+pub(crate) fn scale_to_range(v: f32, outer_deadzone: f32) -> f32 {
+    (v / (1.0 - outer_deadzone)).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outer_deadzone_reaches_full_deflection() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert_eq!(normalize_after_deadzone(0.1, 0.1, 0.1), 0.0);
+        assert!(close(normalize_after_deadzone(0.5, 0.1, 0.1), 0.5));
+        assert!(close(normalize_after_deadzone(0.9, 0.1, 0.1), 1.0));
+        assert_eq!(normalize_after_deadzone(1.0, 0.1, 0.1), 1.0);
+        assert!(close(normalize_after_deadzone(0.55, 0.1, 0.0), 0.5));
+        assert_eq!(scale_to_range(-0.9, 0.2), -1.0);
+        assert!(close(scale_to_range(0.4, 0.2), 0.5));
+    }
+
+    #[test]
+    fn deadzone_shapes() {
+        let (x, y) = (0.15, 0.15);
+        assert_eq!(apply_deadzone(x, y, 0.2, DeadzoneShape::Circular), (x, y));
+        assert_eq!(apply_deadzone(x, y, 0.2, DeadzoneShape::Square), (0.0, 0.0));
+        assert_eq!(apply_deadzone(0.5, y, 0.2, DeadzoneShape::Square), (0.5, y));
+        assert_eq!(
+            apply_deadzone(0.5, y, 0.2, DeadzoneShape::Cross),
+            (0.5, 0.0)
+        );
+
+        let deadzone = (0.3, 0.1);
+        let shape = DeadzoneShape::Circular;
+        assert_eq!(apply_deadzone_xy(0.25, 0.0, deadzone, shape), (0.0, 0.0));
+        assert_eq!(apply_deadzone_xy(0.0, 0.15, deadzone, shape), (0.0, 0.15));
     }
 }
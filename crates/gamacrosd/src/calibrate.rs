@@ -0,0 +1,181 @@
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use gamacros_gamepad::{
+    Axis, AxisCalibration, Calibration, ControllerEvent, ControllerId,
+    ControllerManager, EventFilter, EventKind, EventReceiver,
+};
+use gamacros_workspace::{Workspace, WorkspaceError};
+
+use crate::print_info;
+
+/// How long axes are sampled at rest.
+const REST_DURATION: Duration = Duration::from_secs(2);
+/// How long axes are sampled while the user moves them.
+const THROW_DURATION: Duration = Duration::from_secs(5);
+/// Throws below this are treated as untouched axes and left unscaled.
+const MIN_THROW: f32 = 0.5;
+
+#[derive(Debug, Error)]
+pub(crate) enum CalibrateError {
+    #[error("controller manager error: {0}")]
+    Manager(#[from] gamacros_gamepad::Error),
+    #[error("controller not found: {0}")]
+    NotFound(ControllerId),
+    #[error("workspace error: {0}")]
+    Workspace(#[from] WorkspaceError),
+}
+
+/// Raw value statistics of a single axis.
+#[derive(Debug, Clone, Copy)]
+struct AxisStats {
+    sum: f32,
+    count: u32,
+    min: f32,
+    max: f32,
+}
+
+impl Default for AxisStats {
+    fn default() -> Self {
+        Self {
+            sum: 0.0,
+            count: 0,
+            min: f32::MAX,
+            max: f32::MIN,
+        }
+    }
+}
+
+impl AxisStats {
+    fn push(&mut self, value: f32) {
+        self.sum += value;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn mean(&self) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum / self.count as f32
+    }
+
+    /// Largest distance from `center` seen on this axis.
+    fn spread(&self, center: f32) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        (self.max - center).abs().max((self.min - center).abs())
+    }
+}
+
+type Samples = [AxisStats; Axis::ALL.len()];
+
+/// Samples resting noise and maximum throws of a controller
+/// and stores the resulting calibration in the workspace profile.
+pub(crate) fn run(
+    workspace: &Workspace,
+    id: ControllerId,
+) -> Result<(), CalibrateError> {
+    let manager = ControllerManager::new()?;
+    let rx = manager.subscribe_filtered(
+        EventFilter::kinds(&[EventKind::AxisMotion])
+            .with_controllers(&[id])
+            .raw(),
+    );
+    let info = manager
+        .controllers()
+        .into_iter()
+        .find(|info| info.id == id)
+        .ok_or(CalibrateError::NotFound(id))?;
+    print_info!(
+        "calibrating {} (vid={:#06x} pid={:#06x})",
+        info.name,
+        info.vendor_id,
+        info.product_id
+    );
+
+    print_info!("leave sticks and triggers at rest...");
+    let rest = sample(&rx, id, REST_DURATION);
+    for axis in Axis::ALL {
+        let stats = rest[axis.index()];
+        let noise = stats.spread(stats.mean());
+        print_info!("{axis:?}: rest {:.4}, noise {noise:.4}", stats.mean());
+    }
+
+    print_info!("move sticks in full circles and fully press triggers...");
+    let throw = sample(&rx, id, THROW_DURATION);
+
+    let calibration = compute(&rest, &throw);
+    workspace.save_calibration((info.vendor_id, info.product_id), calibration)?;
+    print_info!(
+        "calibration saved to {}",
+        workspace.profile_path().display()
+    );
+    Ok(())
+}
+
+fn sample(rx: &EventReceiver, id: ControllerId, duration: Duration) -> Samples {
+    let mut samples = Samples::default();
+    let deadline = Instant::now() + duration;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        match rx.recv_timeout(deadline - now).map(|timed| timed.event) {
+            Ok(ControllerEvent::AxisMotion {
+                id: event_id,
+                axis,
+                value,
+            }) if event_id == id => samples[axis.index()].push(value),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    samples
+}
+
+fn compute(rest: &Samples, throw: &Samples) -> Calibration {
+    let mut calibration = Calibration::default();
+    for axis in Axis::ALL {
+        let offset = rest[axis.index()].mean();
+        let spread = throw[axis.index()].spread(offset);
+        let scale = if spread >= MIN_THROW {
+            1.0 / spread
+        } else {
+            1.0
+        };
+        calibration.set(axis, AxisCalibration { offset, scale });
+    }
+    calibration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_offset_and_scale() {
+        let mut rest = Samples::default();
+        rest[Axis::LeftX.index()].push(0.02);
+        rest[Axis::LeftX.index()].push(0.04);
+        let mut throw = Samples::default();
+        throw[Axis::LeftX.index()].push(-0.77);
+        throw[Axis::LeftX.index()].push(0.83);
+        // A barely touched axis keeps its scale
+        throw[Axis::RightY.index()].push(0.2);
+
+        let calibration = compute(&rest, &throw);
+        let left_x = calibration.get(Axis::LeftX);
+        assert!((left_x.offset - 0.03).abs() < 1e-6);
+        assert!((left_x.scale - 1.25).abs() < 1e-4);
+        assert_eq!(calibration.get(Axis::RightY).scale, 1.0);
+        assert_eq!(
+            calibration.get(Axis::LeftTrigger),
+            AxisCalibration::default()
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+use gamacros_workspace::MappingEntry;
+use serde::Serialize;
+
+use crate::cli::SheetFormat;
+
+#[derive(Serialize)]
+struct Sheet<'a> {
+    app: &'a str,
+    mapping: &'a [MappingEntry],
+}
+
+/// Renders the effective mapping of `app` as a cheat sheet.
+pub(crate) fn render(
+    app: &str,
+    mapping: &[MappingEntry],
+    format: SheetFormat,
+) -> String {
+    match format {
+        SheetFormat::Md => render_markdown(app, mapping),
+        SheetFormat::Html => render_html(app, mapping),
+        SheetFormat::Json => serde_json::to_string_pretty(&Sheet { app, mapping })
+            .expect("mapping serializes to JSON"),
+    }
+}
+
+fn render_markdown(app: &str, mapping: &[MappingEntry]) -> String {
+    let mut out = format!("# {app}\n\n");
+    if mapping.is_empty() {
+        out.push_str("No rules apply.\n");
+        return out;
+    }
+    out.push_str("| Input | Action | Details | On release | Source |\n");
+    out.push_str("| --- | --- | --- | --- | --- |\n");
+    for entry in mapping {
+        let cell = |text: &str| text.replace('|', "\\|");
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} |",
+            cell(&entry.input),
+            entry.action,
+            cell(&entry.detail),
+            cell(entry.on_release.as_deref().unwrap_or_default()),
+            cell(&entry.source)
+        );
+    }
+    out
+}
+
+fn render_html(app: &str, mapping: &[MappingEntry]) -> String {
+    let app = escape_html(app);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{app}</title>\n</head>\n<body>\n<h1>{app}</h1>\n"
+    );
+    if mapping.is_empty() {
+        out.push_str("<p>No rules apply.</p>\n");
+    } else {
+        out.push_str(
+            "<table>\n<tr><th>Input</th><th>Action</th><th>Details</th>\
+             <th>On release</th><th>Source</th></tr>\n",
+        );
+        for entry in mapping {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&entry.input),
+                entry.action,
+                escape_html(&entry.detail),
+                escape_html(entry.on_release.as_deref().unwrap_or_default()),
+                escape_html(&entry.source)
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_markdown_and_html() {
+        let mapping = [MappingEntry {
+            input: "a".to_string(),
+            action: "shell",
+            detail: "ls | grep <x>".to_string(),
+            on_release: None,
+            source: "common".to_string(),
+        }];
+        let markdown = render("common", &mapping, SheetFormat::Md);
+        assert!(markdown.ends_with("| a | shell | ls \\| grep <x> |  | common |\n"));
+        let html = render("common", &mapping, SheetFormat::Html);
+        assert!(html.contains("<td>ls | grep &lt;x&gt;</td>"));
+        assert_eq!(
+            render("Notes", &[], SheetFormat::Md),
+            "# Notes\n\nNo rules apply.\n"
+        );
+    }
+}
@@ -1,5 +1,14 @@
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
+
+/// Output format of the cheat sheet.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub(crate) enum SheetFormat {
+    Md,
+    Json,
+    Html,
+}
 
 #[derive(Debug, Subcommand, PartialEq)]
 pub(crate) enum ControlCommand {
@@ -12,6 +21,41 @@ pub(crate) enum ControlCommand {
         #[clap(short, long)]
         ms: u32,
     },
+    /// Print daemon state changes as they happen
+    Subscribe,
+    /// Print the connected controllers
+    Controllers,
+    /// Release held keys, stop rumble and exit the daemon
+    Quit,
+    /// Stop performing actions, e.g. to hand the controller to a game
+    Pause,
+    /// Resume performing actions after a pause
+    Resume,
+    /// Apply the rules of an app whichever app is frontmost
+    Pin {
+        /// Bundle id of the app
+        app: String,
+    },
+    /// Apply the rules of the frontmost app again
+    Unpin,
+    /// Print the effective mapping of an app as a cheat sheet
+    Cheatsheet {
+        /// Bundle id of the app, the `common` rules if not set
+        #[clap(long)]
+        app: Option<String>,
+        /// Output format
+        #[clap(long, value_enum, default_value_t = SheetFormat::Md)]
+        format: SheetFormat,
+    },
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+pub(crate) enum ImportSource {
+    /// Convert game pad rules from a Karabiner-Elements JSON file
+    Karabiner {
+        /// karabiner.json or a complex modification file
+        file: String,
+    },
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -22,6 +66,9 @@ pub(crate) enum Command {
         /// The profile to run
         #[clap(short, long)]
         workspace: Option<String>,
+        /// Record controller events to a .gmrec file
+        #[clap(long)]
+        record: Option<String>,
     },
     /// Start daemon in the background.
     Start {
@@ -31,10 +78,53 @@ pub(crate) enum Command {
     },
     /// Stop the daemon.
     Stop,
+    /// Stop the daemon and remove its launch agent.
+    Uninstall,
     /// Show the status of the daemon.
     Status,
+    /// Check that the daemon has the permissions it needs.
+    Doctor {
+        /// Open System Settings for missing permissions
+        #[clap(long)]
+        open_settings: bool,
+    },
+    /// Print the JSON Schema of the profile for editor validation.
+    Schema,
+    /// Convert rules from another tool and print them as a profile fragment.
+    Import {
+        /// The tool to import from
+        #[clap(subcommand)]
+        source: ImportSource,
+    },
     /// Observe the daemon's events.
     Observe,
+    /// Replay a controller script against a profile without hardware.
+    Simulate {
+        /// The directory containing the profile
+        #[clap(short, long)]
+        workspace: Option<String>,
+        /// JSON script with controller events to inject
+        script: String,
+    },
+    /// Replay a recorded controller session against a profile.
+    Replay {
+        /// The directory containing the profile
+        #[clap(short, long)]
+        workspace: Option<String>,
+        /// Playback speed multiplier
+        #[clap(short, long, default_value_t = 1.0)]
+        speed: f32,
+        /// The .gmrec file to replay
+        file: String,
+    },
+    /// Calibrate controller axes and store the result in the profile.
+    Calibrate {
+        /// The directory containing the profile
+        #[clap(short, long)]
+        workspace: Option<String>,
+        /// The controller ID to calibrate
+        id: u32,
+    },
     /// Send a command to the daemon.
     Command {
         /// The workspace to send the command to
@@ -58,6 +148,11 @@ pub(crate) struct Cli {
     #[arg(long)]
     pub no_color: bool,
 
+    /// The profile file to use, its directory holds the daemon state.
+    /// Overrides GAMACROS_CONFIG, `--workspace` takes precedence
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
     /// The command to run
     #[clap(subcommand)]
     pub command: Command,
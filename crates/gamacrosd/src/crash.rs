@@ -0,0 +1,82 @@
+//! Panic handling of the daemon: a panic writes a crash report next to the
+//! agent logs, releases held keys and rumble and exits with `EXIT_CODE`,
+//! so the agent restarts instead of running on with a dead thread.
+
+use std::backtrace::Backtrace;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Local;
+
+use crate::daemon::LOG_DIR;
+use crate::history;
+
+/// Exit code after a panic, `EX_SOFTWARE` from sysexits.h.
+pub(crate) const EXIT_CODE: i32 = 70;
+/// Name of the thread running the event loop.
+pub(crate) const EVENT_LOOP_THREAD: &str = "event-loop";
+
+static CRASHED: AtomicBool = AtomicBool::new(false);
+
+/// Installs the panic hook. A panic in the event loop releases held keys
+/// and rumble as the loop unwinds, see `ExitOnPanic`; other threads ask it
+/// to stop with `shutdown`.
+pub(crate) fn install(shutdown: impl Fn() + Send + Sync + 'static) {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default(info);
+        CRASHED.store(true, Ordering::SeqCst);
+        match write_report(info) {
+            Ok(path) => eprintln!("crash report written to {}", path.display()),
+            Err(e) => eprintln!("failed to write crash report: {e}"),
+        }
+        if std::thread::current().name() != Some(EVENT_LOOP_THREAD) {
+            shutdown();
+        }
+    }));
+}
+
+/// Whether a thread panicked. The daemon then exits with `EXIT_CODE`.
+pub(crate) fn crashed() -> bool {
+    CRASHED.load(Ordering::SeqCst)
+}
+
+/// Exits with `EXIT_CODE` when dropped by a panicking thread. Bind it first
+/// in the event loop, so it drops last, after the action runner released
+/// held keys.
+pub(crate) struct ExitOnPanic;
+
+impl Drop for ExitOnPanic {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            std::process::exit(EXIT_CODE);
+        }
+    }
+}
+
+/// Writes the panic, a backtrace and the latest events to a new file in
+/// `LOG_DIR`, e.g. `gamacros-crash-20250101-120000.log`.
+fn write_report(info: &dyn fmt::Display) -> std::io::Result<PathBuf> {
+    let now = Local::now();
+    let path = Path::new(LOG_DIR).join(format!(
+        "gamacros-crash-{}.log",
+        now.format("%Y%m%d-%H%M%S")
+    ));
+    let thread = std::thread::current();
+    let events = history::dump()
+        .unwrap_or_else(|| "history is locked by the panicking thread\n".into());
+    let report = format!(
+        "gamacrosd {} crashed at {}\n\
+         thread '{}' {info}\n\n\
+         backtrace:\n{}\n\n\
+         last {} events, oldest first:\n{events}",
+        env!("CARGO_PKG_VERSION"),
+        now.format("%Y.%m.%d %H:%M:%S"),
+        thread.name().unwrap_or("<unnamed>"),
+        Backtrace::force_capture(),
+        history::CAPACITY,
+    );
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
@@ -0,0 +1,145 @@
+use std::ptr;
+
+use lunchctl::{LaunchAgent, LaunchControllable};
+use objc2::msg_send;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject, Bool};
+use objc2_foundation::NSString;
+
+use super::{AgentBackend, Error, Result, StartOutcome};
+
+#[link(name = "ServiceManagement", kind = "framework")]
+extern "C" {}
+
+/// `SMAppServiceStatusEnabled`.
+const STATUS_ENABLED: isize = 1;
+/// `SMAppServiceStatusRequiresApproval`.
+const STATUS_REQUIRES_APPROVAL: isize = 2;
+
+/// Agent registered with `SMAppService`, available on macOS 13+.
+///
+/// The service runs the agent plist shipped in the app bundle under
+/// `Contents/Library/LaunchAgents`, so the command line is defined by the
+/// bundle and `start` arguments are ignored.
+pub(crate) struct AppService {
+    label: String,
+    service: Retained<AnyObject>,
+}
+
+impl AppService {
+    /// Returns `None` on older systems or when the daemon does not run
+    /// from a bundle that ships the agent plist.
+    pub(crate) fn new(label: &str) -> Option<Self> {
+        let class = AnyClass::get(c"SMAppService")?;
+        let exe = std::env::current_exe().ok()?;
+        // <name>.app/Contents/MacOS/<exe>
+        let contents = exe.parent()?.parent()?;
+        let plist_name = format!("{label}.plist");
+        if !contents
+            .join("Library/LaunchAgents")
+            .join(&plist_name)
+            .is_file()
+        {
+            return None;
+        }
+        let name = NSString::from_str(&plist_name);
+        let service: Option<Retained<AnyObject>> =
+            unsafe { msg_send![class, agentServiceWithPlistName: &*name] };
+        Some(Self {
+            label: label.to_string(),
+            service: service?,
+        })
+    }
+
+    fn status(&self) -> isize {
+        unsafe { msg_send![&*self.service, status] }
+    }
+
+    fn register(&self) -> Result<()> {
+        let mut error: *mut AnyObject = ptr::null_mut();
+        let ok: Bool =
+            unsafe { msg_send![&*self.service, registerAndReturnError: &mut error] };
+        check(ok, error)
+    }
+
+    fn unregister(&self) -> Result<()> {
+        let mut error: *mut AnyObject = ptr::null_mut();
+        let ok: Bool = unsafe {
+            msg_send![&*self.service, unregisterAndReturnError: &mut error]
+        };
+        check(ok, error)
+    }
+}
+
+impl AgentBackend for AppService {
+    fn name(&self) -> &'static str {
+        "SMAppService"
+    }
+
+    fn is_installed(&self) -> bool {
+        matches!(self.status(), STATUS_ENABLED | STATUS_REQUIRES_APPROVAL)
+    }
+
+    fn is_running(&self) -> Result<bool> {
+        if self.status() != STATUS_ENABLED {
+            return Ok(false);
+        }
+        Ok(LaunchAgent::new(&self.label).is_running()?)
+    }
+
+    fn start(&self, _arguments: &[String]) -> Result<StartOutcome> {
+        // A plist agent left by an earlier install would run a second daemon.
+        let replaced = LaunchAgent::exists(&self.label);
+        if replaced {
+            let agent = LaunchAgent::new(&self.label);
+            agent.boot_out()?;
+            agent.remove()?;
+        } else if self.is_running()? {
+            return Ok(StartOutcome::AlreadyRunning);
+        }
+
+        self.register()?;
+        if self.status() == STATUS_REQUIRES_APPROVAL {
+            return Err(Error::RequiresApproval);
+        }
+        Ok(if replaced {
+            StartOutcome::Reinstalled
+        } else {
+            StartOutcome::Started
+        })
+    }
+
+    /// The service cannot be stopped while registered, so stopping
+    /// unregisters it. `start` registers it again.
+    fn stop(&self) -> Result<bool> {
+        if !self.is_running()? {
+            return Ok(false);
+        }
+        self.unregister()?;
+        Ok(true)
+    }
+
+    fn uninstall(&self) -> Result<bool> {
+        if !self.is_installed() {
+            return Ok(false);
+        }
+        self.unregister()?;
+        Ok(true)
+    }
+}
+
+/// Converts the result of an `...AndReturnError:` call.
+fn check(ok: Bool, error: *mut AnyObject) -> Result<()> {
+    if ok.as_bool() {
+        return Ok(());
+    }
+    let message = match unsafe { error.as_ref() } {
+        Some(error) => {
+            let description: Retained<NSString> =
+                unsafe { msg_send![error, localizedDescription] };
+            description.to_string()
+        }
+        None => "unknown error".to_string(),
+    };
+    Err(Error::ServiceManagement(message))
+}
@@ -0,0 +1,100 @@
+use lunchctl::{LaunchAgent, LaunchControllable};
+
+use super::{AgentBackend, Result, StartOutcome};
+
+/// Directory of the agent logs, where crash reports go too.
+pub(crate) const LOG_DIR: &str = "/tmp";
+const STANDARD_OUT_PATH: &str = "/tmp/gamacros.out";
+const STANDARD_ERROR_PATH: &str = "/tmp/gamacros.err";
+
+/// Agent installed as a plist in `~/Library/LaunchAgents`.
+pub(crate) struct PlistAgent {
+    label: String,
+}
+
+impl PlistAgent {
+    pub(crate) fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+        }
+    }
+
+    fn agent(&self, arguments: &[String]) -> LaunchAgent {
+        LaunchAgent {
+            label: self.label.clone(),
+            program_arguments: arguments.to_vec(),
+            standard_out_path: STANDARD_OUT_PATH.to_string(),
+            standard_error_path: STANDARD_ERROR_PATH.to_string(),
+            keep_alive: true,
+            run_at_load: true,
+        }
+    }
+}
+
+impl AgentBackend for PlistAgent {
+    fn name(&self) -> &'static str {
+        "launchd plist"
+    }
+
+    fn is_installed(&self) -> bool {
+        LaunchAgent::exists(&self.label)
+    }
+
+    fn is_running(&self) -> Result<bool> {
+        Ok(LaunchAgent::new(&self.label).is_running()?)
+    }
+
+    fn start(&self, arguments: &[String]) -> Result<StartOutcome> {
+        let agent = self.agent(arguments);
+        // A moved binary or changed arguments leave the installed agent
+        // pointing at a stale command, reinstall it then.
+        let outdated = self.is_installed()
+            && LaunchAgent::from_file(&self.label)
+                .map_or(true, |installed| !same_agent(&installed, &agent));
+        if !outdated && agent.is_running()? {
+            return Ok(StartOutcome::AlreadyRunning);
+        }
+        if outdated {
+            // The stale agent may be loaded without running, e.g. when its
+            // binary is gone, so it is booted out either way.
+            agent.boot_out()?;
+        }
+
+        agent.write()?;
+        agent.bootstrap()?;
+        Ok(if outdated {
+            StartOutcome::Reinstalled
+        } else {
+            StartOutcome::Started
+        })
+    }
+
+    fn stop(&self) -> Result<bool> {
+        let agent = LaunchAgent::new(&self.label);
+        if !agent.is_running()? {
+            return Ok(false);
+        }
+        agent.boot_out()?;
+        Ok(true)
+    }
+
+    fn uninstall(&self) -> Result<bool> {
+        if !self.is_installed() {
+            return Ok(false);
+        }
+        let agent = LaunchAgent::new(&self.label);
+        // Unloads an agent that is loaded but not running as well.
+        agent.boot_out()?;
+        agent.remove()?;
+        Ok(true)
+    }
+}
+
+/// Whether an installed agent runs the same command as `desired`.
+fn same_agent(installed: &LaunchAgent, desired: &LaunchAgent) -> bool {
+    installed.program_arguments == desired.program_arguments
+        && installed.standard_out_path == desired.standard_out_path
+        && installed.standard_error_path == desired.standard_error_path
+        && installed.keep_alive == desired.keep_alive
+        && installed.run_at_load == desired.run_at_load
+}
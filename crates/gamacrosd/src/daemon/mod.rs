@@ -0,0 +1,62 @@
+//! Background agent that runs the daemon at login.
+
+mod launch_agent;
+#[cfg(target_os = "macos")]
+mod app_service;
+
+use lunchctl::LaunchAgentError;
+use thiserror::Error;
+
+pub(crate) use launch_agent::{PlistAgent, LOG_DIR};
+
+#[derive(Error, Debug)]
+pub(crate) enum Error {
+    #[error("launchd error: {0}")]
+    LaunchAgent(#[from] LaunchAgentError),
+    #[cfg(target_os = "macos")]
+    #[error("service management error: {0}")]
+    ServiceManagement(String),
+    #[cfg(target_os = "macos")]
+    #[error("agent requires approval in System Settings > General > Login Items")]
+    RequiresApproval,
+}
+
+pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+/// How `start` brought the agent up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StartOutcome {
+    AlreadyRunning,
+    Started,
+    /// The installed agent ran a stale command and was replaced.
+    Reinstalled,
+}
+
+/// A way to register the daemon with the system.
+pub(crate) trait AgentBackend {
+    fn name(&self) -> &'static str;
+
+    fn is_installed(&self) -> bool;
+
+    fn is_running(&self) -> Result<bool>;
+
+    /// Installs the agent running `arguments`, or updates an outdated one,
+    /// and starts it.
+    fn start(&self, arguments: &[String]) -> Result<StartOutcome>;
+
+    /// Stops the agent. Returns whether it was running.
+    fn stop(&self) -> Result<bool>;
+
+    /// Stops and removes the agent. Returns whether it was installed.
+    fn uninstall(&self) -> Result<bool>;
+}
+
+/// Picks `SMAppService` on macOS 13+ when the daemon runs from an app
+/// bundle, and a launchd plist otherwise.
+pub(crate) fn backend(label: &str) -> Box<dyn AgentBackend> {
+    #[cfg(target_os = "macos")]
+    if let Some(service) = app_service::AppService::new(label) {
+        return Box::new(service);
+    }
+    Box::new(PlistAgent::new(label))
+}
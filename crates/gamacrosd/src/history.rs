@@ -0,0 +1,107 @@
+//! Latest input of the daemon, kept for crash reports.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use gamacros_gamepad::{ControllerEvent, EventKind};
+use gamacros_input::KeyEvent;
+
+/// Events kept in the history, older ones are dropped.
+pub(crate) const CAPACITY: usize = 100;
+
+static HISTORY: Mutex<EventHistory> = Mutex::new(EventHistory::new(CAPACITY));
+
+#[derive(Debug, Clone)]
+pub(crate) enum HistoryEvent {
+    Controller(ControllerEvent),
+    Key(KeyEvent),
+    /// An app came to the front.
+    App(String),
+}
+
+impl fmt::Display for HistoryEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryEvent::Controller(event) => write!(f, "{event:?}"),
+            HistoryEvent::Key(KeyEvent { combo, pressed }) => {
+                let phase = if *pressed { "pressed" } else { "released" };
+                write!(f, "key {combo} {phase}")
+            }
+            HistoryEvent::App(bundle_id) => write!(f, "app {bundle_id}"),
+        }
+    }
+}
+
+/// Ring buffer of the latest events, with the time they arrived.
+pub(crate) struct EventHistory {
+    capacity: usize,
+    events: VecDeque<(SystemTime, HistoryEvent)>,
+}
+
+impl EventHistory {
+    pub(crate) const fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, at: SystemTime, event: HistoryEvent) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back((at, event));
+    }
+
+    /// Events one per line, oldest first.
+    pub(crate) fn format(&self) -> String {
+        let mut lines = String::new();
+        for (at, event) in &self.events {
+            let at: DateTime<Local> = (*at).into();
+            let _ = writeln!(lines, "[{}] {event}", at.format("%H:%M:%S%.3f"));
+        }
+        lines
+    }
+}
+
+/// Records `event` in the history of the process. Axis motion is left out,
+/// it would push every other event out of the history within a second.
+pub(crate) fn record(event: HistoryEvent) {
+    if let HistoryEvent::Controller(event) = &event {
+        if event.kind() == EventKind::AxisMotion {
+            return;
+        }
+    }
+    if let Ok(mut history) = HISTORY.lock() {
+        history.push(SystemTime::now(), event);
+    }
+}
+
+/// The history of the process, see `EventHistory::format`. `None` when it
+/// is locked, e.g. by the thread that panicked while recording.
+pub(crate) fn dump() -> Option<String> {
+    HISTORY.try_lock().ok().map(|history| history.format())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_latest_events() {
+        let mut history = EventHistory::new(2);
+        for id in 0..3 {
+            history.push(
+                SystemTime::now(),
+                HistoryEvent::Controller(ControllerEvent::Disconnected(id)),
+            );
+        }
+        let lines: Vec<_> = history.format().lines().map(str::to_owned).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("] Disconnected(1)"), "{lines:?}");
+        assert!(lines[1].ends_with("] Disconnected(2)"), "{lines:?}");
+    }
+}
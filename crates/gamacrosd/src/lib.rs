@@ -1,6 +1,10 @@
 pub mod app;
 pub mod runner;
+mod shell_pool;
 pub mod logging;
+pub mod watchdog;
+#[cfg(feature = "test-support")]
+pub mod sim;
 
 pub use app::Gamacros;
 pub use app::Action;
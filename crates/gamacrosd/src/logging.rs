@@ -6,6 +6,37 @@ use std::sync::{OnceLock, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use chrono::{Local, TimeZone};
+use gamacros_workspace::{LogLevel, LogLevels, LogModule};
+use log::LevelFilter;
+
+/// Log target of controller logs outside the `app` module.
+pub const GAMEPAD: &str = "gamacrosd::gamepad";
+/// Log target of app, window title and site switches.
+pub const ACTIVITY: &str = "gamacrosd::activity";
+/// Log target of performed actions outside the runner.
+pub const ACTIONS: &str = "gamacrosd::actions";
+
+/// Log targets of each module, matched with their submodules.
+const MODULE_TARGETS: [(LogModule, &[&str]); 4] = [
+    (LogModule::Gamepad, &[GAMEPAD, "gamacrosd::app"]),
+    (LogModule::Activity, &[ACTIVITY]),
+    (
+        LogModule::Actions,
+        &[ACTIONS, "gamacrosd::runner", "gamacrosd::shell_pool"],
+    ),
+    (LogModule::Api, &["gamacrosd::api"]),
+];
+
+/// Level of gamacrosd logs, and of the modules set apart from it.
+struct Levels {
+    default: LevelFilter,
+    modules: Vec<(LogModule, LevelFilter)>,
+}
+
+static LEVELS: RwLock<Levels> = RwLock::new(Levels {
+    default: LevelFilter::Info,
+    modules: Vec::new(),
+});
 
 #[inline(always)]
 pub(crate) fn format_log(message: &str) -> String {
@@ -46,61 +77,154 @@ fn cached_now_string() -> String {
     formatted
 }
 
+// The macros log to the module they are called from, or to `target:`,
+// e.g. `print_debug!(target: logging::ACTIVITY, "...")`.
+
 #[macro_export]
 macro_rules! print_error {
-    ($($arg:tt)*) => {
-        if log::log_enabled!(log::Level::Error) {
+    (target: $target:expr, $($arg:tt)*) => {
+        if log::log_enabled!(target: $target, log::Level::Error) {
             let __message = $crate::logging::format_log(&format!($($arg)*));
-            log::error!("{}", __message.bright_red());
+            log::error!(target: $target, "{}", __message.bright_red());
         }
-    }
+    };
+    ($($arg:tt)*) => {
+        $crate::print_error!(target: module_path!(), $($arg)*)
+    };
 }
 
 #[macro_export]
 macro_rules! print_info {
-    ($($arg:tt)*) => {
-        if log::log_enabled!(log::Level::Info) {
+    (target: $target:expr, $($arg:tt)*) => {
+        if log::log_enabled!(target: $target, log::Level::Info) {
             let __message = $crate::logging::format_log(&format!($($arg)*));
-            log::info!("{__message}");
+            log::info!(target: $target, "{__message}");
         }
-    }
+    };
+    ($($arg:tt)*) => {
+        $crate::print_info!(target: module_path!(), $($arg)*)
+    };
 }
 
 #[macro_export]
 macro_rules! print_debug {
-    ($($arg:tt)*) => {
-        if log::log_enabled!(log::Level::Debug) {
+    (target: $target:expr, $($arg:tt)*) => {
+        if log::log_enabled!(target: $target, log::Level::Debug) {
             let __message = $crate::logging::format_log(&format!($($arg)*));
-            log::debug!("{}", __message.dimmed());
+            log::debug!(target: $target, "{}", __message.dimmed());
         }
-    }
+    };
+    ($($arg:tt)*) => {
+        $crate::print_debug!(target: module_path!(), $($arg)*)
+    };
 }
 
 #[macro_export]
 macro_rules! print_warning {
-    ($($arg:tt)*) => {
-        if log::log_enabled!(log::Level::Info) {
+    (target: $target:expr, $($arg:tt)*) => {
+        if log::log_enabled!(target: $target, log::Level::Warn) {
             let __message = $crate::logging::format_log(&format!($($arg)*));
-            log::info!("{}", __message.bright_yellow());
+            log::warn!(target: $target, "{}", __message.bright_yellow());
         }
-    }
+    };
+    ($($arg:tt)*) => {
+        $crate::print_warning!(target: module_path!(), $($arg)*)
+    };
 }
 
 /// Setup the logger.
 pub fn setup(verbose: bool, no_color: bool) {
-    let log_level = if verbose {
-        log::LevelFilter::Debug
+    LEVELS.write().expect("log levels poisoned").default = if verbose {
+        LevelFilter::Debug
     } else {
-        log::LevelFilter::Info
+        LevelFilter::Info
     };
     Dispatch::new()
-        .level(log::LevelFilter::Error) // Hide enigo logs
-        .level_for("gamacrosd", log_level)
+        .filter(|metadata| metadata.level() <= level_for(metadata.target()))
         .chain(std::io::stdout())
         .apply()
         .expect("Unable to set up logger");
+    update_max_level();
 
     if no_color {
         colored::control::set_override(false);
     }
 }
+
+/// Sets the levels of modules apart from the default one, e.g. from the
+/// profile. Modules left out log at the default level again.
+pub fn configure(levels: &LogLevels) {
+    let modules = levels
+        .iter()
+        .map(|(module, level)| (*module, level_filter(*level)))
+        .collect();
+    LEVELS.write().expect("log levels poisoned").modules = modules;
+    update_max_level();
+}
+
+/// Level logs of `target` are shown up to.
+fn level_for(target: &str) -> LevelFilter {
+    // Hide enigo logs
+    if !is_within(target, "gamacrosd") {
+        return LevelFilter::Error;
+    }
+    let levels = LEVELS.read().expect("log levels poisoned");
+    MODULE_TARGETS
+        .iter()
+        .find(|(_, targets)| targets.iter().any(|prefix| is_within(target, prefix)))
+        .and_then(|(module, _)| {
+            levels.modules.iter().find(|(other, _)| other == module)
+        })
+        .map_or(levels.default, |(_, level)| *level)
+}
+
+/// Lets `log_enabled!` through for the most verbose level in use.
+fn update_max_level() {
+    let levels = LEVELS.read().expect("log levels poisoned");
+    let max = levels
+        .modules
+        .iter()
+        .map(|(_, level)| *level)
+        .fold(levels.default.max(LevelFilter::Error), Ord::max);
+    log::set_max_level(max);
+}
+
+fn is_within(target: &str, module: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+fn level_filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Off => LevelFilter::Off,
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+        LogLevel::Trace => LevelFilter::Trace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_levels_override_the_default() {
+        configure(&LogLevels::from_iter([
+            (LogModule::Gamepad, LogLevel::Debug),
+            (LogModule::Api, LogLevel::Off),
+        ]));
+        assert_eq!(level_for("gamacrosd::app::gamacros"), LevelFilter::Debug);
+        assert_eq!(level_for(GAMEPAD), LevelFilter::Debug);
+        assert_eq!(level_for("gamacrosd::api::http"), LevelFilter::Off);
+        assert_eq!(level_for("gamacrosd::main"), LevelFilter::Info);
+        assert_eq!(level_for("gamacrosd::applet"), LevelFilter::Info);
+        assert_eq!(level_for("enigo"), LevelFilter::Error);
+        assert_eq!(log::max_level(), LevelFilter::Debug);
+
+        configure(&LogLevels::default());
+        assert_eq!(level_for(GAMEPAD), LevelFilter::Info);
+    }
+}
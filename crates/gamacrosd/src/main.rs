@@ -2,28 +2,59 @@ mod app;
 mod logging;
 mod cli;
 mod runner;
+mod shell_pool;
 mod api;
-mod activity;
+mod simulate;
+mod recording;
+mod calibrate;
+mod cheatsheet;
+mod osc;
+mod overlay;
+mod speech;
+mod state;
+mod daemon;
+mod crash;
+mod history;
+mod watchdog;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{process, time::Duration};
 
 use colored::Colorize;
 use crossbeam_channel::{select, unbounded};
 use clap::Parser;
-use lunchctl::{LaunchAgent, LaunchControllable};
-use crate::activity::{ActivityEvent, Monitor, NotificationListener};
+use gamacros_activity::{
+    spawn_browser_monitor, spawn_fullscreen_monitor, spawn_title_monitor,
+    ActivationFilter, Event as ActivityEvent, Monitor, NotificationListener,
+};
 
 use gamacros_gamepad::{ControllerEvent, ControllerManager};
-use gamacros_control::Performer;
-use gamacros_workspace::{Workspace, ProfileEvent};
+use gamacros_control::{missing_permissions, Performer, Permission};
+use gamacros_input::{KeyEvent, KeyListener};
+use gamacros_workspace::{
+    import_karabiner, load_profile, load_profile_cached, Workspace, ProfileEvent,
+};
 
 use crate::app::{Gamacros, ButtonPhase};
-use crate::cli::{Cli, Command, ControlCommand};
-use crate::runner::ActionRunner;
-use crate::api::{UnixSocket, ApiTransport, Command as ApiCommand};
+use crate::cli::{Cli, Command, ControlCommand, ImportSource};
+use crate::daemon::StartOutcome;
+use crate::history::HistoryEvent;
+use crate::watchdog::{Heartbeat, Stage};
+use crate::runner::{ActionObserver, ActionRunner};
+use crate::osc::OscOutput;
+use crate::overlay::Overlay;
+use crate::speech::Speaker;
+use crate::state::State;
+use crate::recording::Recorder;
+use crate::api::{
+    read_token, ApiTransport, BridgeEvent, Broadcaster, Command as ApiCommand,
+    ControllerStatus, HttpStatusServer, PermissionsStatus, SharedStatus, StateEvent,
+    StateFeed, UnixSocket, WebSocketBridge,
+};
 
 const APP_LABEL: &str = "co.myrt.gamacros";
+/// App of the cheat sheet without `--app`, showing the `common` rules.
+const COMMON_APP: &str = "common";
 
 fn main() -> process::ExitCode {
     let cli = Cli::parse();
@@ -32,89 +63,82 @@ fn main() -> process::ExitCode {
     }
 
     let bin_path = std::env::current_exe().unwrap();
+    let config = cli.config.as_deref().map(Path::new);
 
     match cli.command {
-        Command::Run { workspace } => {
-            let workspace_path = resolve_workspace_path(workspace.as_deref());
-            run_event_loop(Some(workspace_path));
+        Command::Run { workspace, record } => {
+            let workspace = resolve_workspace(workspace.as_deref(), config);
+            run_event_loop(Some(workspace), record.map(PathBuf::from));
         }
         Command::Start { workspace } => {
-            let workspace_path = resolve_workspace_path(workspace.as_deref());
+            let workspace = resolve_workspace(workspace.as_deref(), config);
 
             let mut arguments = vec![bin_path.display().to_string()];
             if cli.verbose {
                 arguments.push("--verbose".to_string());
             }
             arguments.push("run".to_string());
-            arguments.push("--workspace".to_string());
-            arguments.push(workspace_path.display().to_string());
-
-            let agent = LaunchAgent {
-                label: APP_LABEL.to_string(),
-                program_arguments: arguments,
-                standard_out_path: "/tmp/gamacros.out".to_string(),
-                standard_error_path: "/tmp/gamacros.err".to_string(),
-                keep_alive: true,
-                run_at_load: true,
-            };
-
-            if let Err(e) = agent.write() {
-                print_error!("Failed to write agent: {}", e);
-                return process::ExitCode::FAILURE;
-            }
+            arguments.push("--config".to_string());
+            arguments.push(workspace.profile_path().display().to_string());
 
-            match agent.is_running() {
-                Ok(true) => {
+            let backend = daemon::backend(APP_LABEL);
+            print_debug!("Using {} agent", backend.name());
+            match backend.start(&arguments) {
+                Ok(StartOutcome::AlreadyRunning) => {
                     print_info!("Agent is already running");
                 }
-                Ok(false) => {
-                    print_info!("Starting agent");
-                    if let Err(e) = agent.bootstrap() {
-                        print_error!("Failed to bootstrap agent: {}", e);
-                        return process::ExitCode::FAILURE;
-                    }
+                Ok(StartOutcome::Started) => {
                     print_info!("Agent started");
                 }
+                Ok(StartOutcome::Reinstalled) => {
+                    print_info!("Outdated agent reinstalled and started");
+                }
                 Err(e) => {
-                    print_error!("Failed to check if agent is running: {}", e);
+                    print_error!("Failed to start agent: {e}");
                     return process::ExitCode::FAILURE;
                 }
             }
         }
         Command::Stop => {
-            if !LaunchAgent::exists(APP_LABEL) {
+            let backend = daemon::backend(APP_LABEL);
+            if !backend.is_installed() {
                 print_error!("Agent does not exist");
                 return process::ExitCode::FAILURE;
             }
 
-            let agent = LaunchAgent::from_file(APP_LABEL).unwrap();
-
-            match agent.is_running() {
+            match backend.stop() {
                 Ok(true) => {
-                    print_info!("Stopping agent");
-                    if let Err(e) = agent.boot_out() {
-                        print_error!("Failed to stop agent: {}", e);
-                        return process::ExitCode::FAILURE;
-                    }
                     print_info!("Agent stopped");
                 }
                 Ok(false) => {
                     print_info!("Agent is not running");
                 }
                 Err(e) => {
-                    print_error!("Failed to check if agent is running: {}", e);
+                    print_error!("Failed to stop agent: {e}");
                     return process::ExitCode::FAILURE;
                 }
             }
         }
+        Command::Uninstall => match daemon::backend(APP_LABEL).uninstall() {
+            Ok(true) => {
+                print_info!("Agent uninstalled");
+            }
+            Ok(false) => {
+                print_info!("Agent is not installed");
+            }
+            Err(e) => {
+                print_error!("Failed to uninstall agent: {e}");
+                return process::ExitCode::FAILURE;
+            }
+        },
         Command::Status => {
-            if !LaunchAgent::exists(APP_LABEL) {
+            let backend = daemon::backend(APP_LABEL);
+            if !backend.is_installed() {
                 print_info!("Agent does not exist");
                 return process::ExitCode::FAILURE;
             }
 
-            let agent = LaunchAgent::from_file(APP_LABEL).unwrap();
-            match agent.is_running() {
+            match backend.is_running() {
                 Ok(true) => {
                     print_info!("Agent is running");
                 }
@@ -122,18 +146,84 @@ fn main() -> process::ExitCode {
                     print_info!("Agent is not running");
                 }
                 Err(e) => {
-                    print_error!("Failed to check if agent is running: {}", e);
+                    print_error!("Failed to check if agent is running: {e}");
                     return process::ExitCode::FAILURE;
                 }
             }
         }
+        Command::Doctor { open_settings } => {
+            let missing = missing_permissions();
+            for permission in Permission::ALL {
+                if !missing.contains(&permission) {
+                    print_info!("{permission}: granted");
+                    continue;
+                }
+                print_warning!("{permission}: missing");
+                guide_permission(permission);
+                if open_settings {
+                    if let Err(e) = permission.open_settings() {
+                        print_error!("failed to open System Settings: {e}");
+                    }
+                }
+            }
+            if !missing.is_empty() {
+                return process::ExitCode::FAILURE;
+            }
+        }
+        Command::Schema => {
+            println!("{}", gamacros_workspace::PROFILE_SCHEMA.trim_end());
+        }
+        Command::Import {
+            source: ImportSource::Karabiner { file },
+        } => {
+            let import = match std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|json| import_karabiner(&json).map_err(|e| e.to_string()))
+            {
+                Ok(import) => import,
+                Err(e) => {
+                    print_error!("failed to import {file}: {e}");
+                    return process::ExitCode::FAILURE;
+                }
+            };
+            print!("{}", import.to_fragment());
+        }
         Command::Observe => {
             logging::setup(true, cli.no_color);
-            run_event_loop(None);
+            run_event_loop(None, None);
+        }
+        Command::Simulate { workspace, script } => {
+            let profile_path =
+                resolve_workspace(workspace.as_deref(), config).profile_path();
+            if let Err(e) = simulate::run(&profile_path, Path::new(&script)) {
+                print_error!("simulation failed: {e}");
+                return process::ExitCode::FAILURE;
+            }
+        }
+        Command::Replay {
+            workspace,
+            speed,
+            file,
+        } => {
+            let profile_path =
+                resolve_workspace(workspace.as_deref(), config).profile_path();
+            if let Err(e) = simulate::replay(&profile_path, Path::new(&file), speed)
+            {
+                print_error!("replay failed: {e}");
+                return process::ExitCode::FAILURE;
+            }
+        }
+        Command::Calibrate { workspace, id } => {
+            let workspace = resolve_workspace(workspace.as_deref(), config);
+            if let Err(e) = calibrate::run(&workspace, id) {
+                print_error!("calibration failed: {e}");
+                return process::ExitCode::FAILURE;
+            }
         }
         Command::Command { workspace, command } => match command {
             ControlCommand::Rumble { id, ms } => {
-                let workspace_path = resolve_workspace_path(workspace.as_deref());
+                let workspace_path =
+                    resolve_workspace(workspace.as_deref(), config).path();
                 match UnixSocket::new(workspace_path)
                     .send_event(ApiCommand::Rumble { id, ms })
                 {
@@ -145,29 +235,215 @@ fn main() -> process::ExitCode {
                     }
                 };
             }
+            ControlCommand::Quit => {
+                let workspace_path =
+                    resolve_workspace(workspace.as_deref(), config).path();
+                if let Err(e) =
+                    UnixSocket::new(workspace_path).send_event(ApiCommand::Shutdown)
+                {
+                    print_error!("failed to send quit command: {e}");
+                    return process::ExitCode::FAILURE;
+                }
+                print_info!("Daemon is shutting down");
+            }
+            ControlCommand::Pause | ControlCommand::Resume => {
+                let workspace_path =
+                    resolve_workspace(workspace.as_deref(), config).path();
+                let (event, done) = if command == ControlCommand::Pause {
+                    (ApiCommand::Pause, "Paused")
+                } else {
+                    (ApiCommand::Resume, "Resumed")
+                };
+                if let Err(e) = UnixSocket::new(workspace_path).send_event(event) {
+                    print_error!("failed to send command: {e}");
+                    return process::ExitCode::FAILURE;
+                }
+                print_info!("{done}");
+            }
+            ControlCommand::Pin { .. } | ControlCommand::Unpin => {
+                let workspace_path =
+                    resolve_workspace(workspace.as_deref(), config).path();
+                let app = match command {
+                    ControlCommand::Pin { app } => Some(app),
+                    _ => None,
+                };
+                let done = app.as_deref().map_or_else(
+                    || "Unpinned".to_string(),
+                    |app| format!("Pinned {app}"),
+                );
+                let event = ApiCommand::PinApp { app };
+                if let Err(e) = UnixSocket::new(workspace_path).send_event(event) {
+                    print_error!("failed to send command: {e}");
+                    return process::ExitCode::FAILURE;
+                }
+                print_info!("{done}");
+            }
+            ControlCommand::Controllers => {
+                let workspace_path =
+                    resolve_workspace(workspace.as_deref(), config).path();
+                let controllers = match UnixSocket::new(workspace_path).controllers()
+                {
+                    Ok(controllers) => controllers,
+                    Err(e) => {
+                        print_error!("failed to get controllers: {e}");
+                        return process::ExitCode::FAILURE;
+                    }
+                };
+                if controllers.is_empty() {
+                    print_info!("No controllers connected");
+                }
+                for info in controllers {
+                    let serial = info
+                        .serial
+                        .map(|serial| format!(", serial {serial}"))
+                        .unwrap_or_default();
+                    print_info!(
+                        "{}: {} ({:04x}:{:04x}{serial})",
+                        info.id,
+                        info.name,
+                        info.vendor_id,
+                        info.product_id
+                    );
+                }
+            }
+            ControlCommand::Cheatsheet { app, format } => {
+                let profile_path =
+                    resolve_workspace(workspace.as_deref(), config).profile_path();
+                let profile = match load_profile(&profile_path) {
+                    Ok(profile) => profile,
+                    Err(e) => {
+                        print_error!("failed to load profile: {e}");
+                        return process::ExitCode::FAILURE;
+                    }
+                };
+                let app = app.as_deref().unwrap_or(COMMON_APP);
+                print!("{}", cheatsheet::render(app, &profile.mapping(app), format));
+            }
+            ControlCommand::Subscribe => {
+                let workspace_path =
+                    resolve_workspace(workspace.as_deref(), config).path();
+                let events = match UnixSocket::new(workspace_path).subscribe() {
+                    Ok(events) => events,
+                    Err(e) => {
+                        print_error!("failed to subscribe: {e}");
+                        return process::ExitCode::FAILURE;
+                    }
+                };
+                for event in events {
+                    match event {
+                        Ok(event) => print_info!("{event:?}"),
+                        Err(e) => {
+                            print_error!("failed to read event: {e}");
+                            return process::ExitCode::FAILURE;
+                        }
+                    }
+                }
+            }
         },
     }
 
     process::ExitCode::SUCCESS
 }
 
-fn resolve_workspace_path(workspace: Option<&str>) -> PathBuf {
-    let workspace = workspace.map(PathBuf::from);
-    if let Some(workspace) = workspace {
-        return workspace;
-    }
+/// Prints how to grant a missing permission.
+fn guide_permission(permission: Permission) {
+    let exe = std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "gamacrosd".to_string());
+    print_info!(
+        "  gamacros needs it to {}. Add {exe} in System Settings > Privacy & Security > {permission}, \
+         or run `gamacrosd doctor --open-settings`, then restart the daemon.",
+        permission.purpose()
+    );
+}
 
-    match Workspace::default_path() {
-        Ok(path) => path,
+/// Opens the workspace from `--workspace` or `--config`, see
+/// [`Workspace::locate`] for the precedence.
+fn resolve_workspace(workspace: Option<&str>, config: Option<&Path>) -> Workspace {
+    match Workspace::locate(workspace.map(Path::new), config) {
+        Ok(workspace) => workspace,
         Err(e) => {
-            print_error!("failed to resolve workspace: {e}");
+            print_error!("failed to open workspace: {e}");
 
             process::exit(1);
         }
     }
 }
 
-fn run_event_loop(maybe_workspace_path: Option<PathBuf>) {
+/// Connected controllers ordered by id.
+fn controller_statuses(manager: &ControllerManager) -> Vec<ControllerStatus> {
+    let mut controllers: Vec<_> = manager
+        .controllers()
+        .into_iter()
+        .map(|info| ControllerStatus {
+            id: info.id,
+            name: info.name,
+        })
+        .collect();
+    controllers.sort_by_key(|controller| controller.id);
+    controllers
+}
+
+/// Copies the state reported by the http status endpoint.
+fn update_status(
+    status: &SharedStatus,
+    gamacros: &Gamacros,
+    action_runner: &ActionRunner,
+    manager: &ControllerManager,
+) {
+    let controllers = controller_statuses(manager);
+    let mut status = status.lock().unwrap();
+    status.active_app = gamacros.get_active_app().to_string();
+    status.profile_loaded = gamacros.workspace.is_some();
+    status.paused = action_runner.is_paused();
+    status.auto_paused = action_runner.is_auto_paused();
+    status.controllers = controllers;
+}
+
+fn publish_pause(feed: &StateFeed, action_runner: &ActionRunner) {
+    feed.publish(StateEvent::Paused {
+        manual: action_runner.is_paused(),
+        automatic: action_runner.is_auto_paused(),
+    });
+}
+
+/// Reports executed actions to socket subscribers and the websocket bridge.
+fn action_observer(
+    feed: StateFeed,
+    broadcaster: Option<Broadcaster>,
+    overlay: Option<Overlay>,
+) -> ActionObserver<'static> {
+    Box::new(move |action, latency| {
+        if let Some(overlay) = overlay.as_ref() {
+            overlay.action(action);
+        }
+        if let Some(event) = StateEvent::action(action, latency) {
+            feed.publish(event);
+        }
+        if let Some(broadcaster) = broadcaster.as_ref() {
+            if let Some(event) = BridgeEvent::action(action, latency) {
+                broadcaster.send(&event);
+            }
+        }
+    })
+}
+
+fn run_event_loop(
+    maybe_workspace: Option<Workspace>,
+    maybe_record_path: Option<PathBuf>,
+) {
+    let missing = missing_permissions();
+    if maybe_workspace.is_some() {
+        for permission in &missing {
+            print_warning!("{permission} permission is missing");
+            guide_permission(*permission);
+        }
+    }
+    let permissions = PermissionsStatus {
+        accessibility: !missing.contains(&Permission::Accessibility),
+        input_monitoring: !missing.contains(&Permission::InputMonitoring),
+    };
+
     // Activity monitor must run on the main thread.
     // We keep its std::mpsc receiver and poll it from the event loop (no bridge thread).
     let Some((monitor, activity_std_rx, monitor_stop_tx)) = Monitor::new() else {
@@ -176,49 +452,68 @@ fn run_event_loop(maybe_workspace_path: Option<PathBuf>) {
     };
 
     monitor.subscribe(NotificationListener::DidActivateApplication);
+    monitor.subscribe_screen_lock();
     let mut gamacros = Gamacros::new();
     if let Some(app) = monitor.get_active_application() {
         gamacros.set_active_app(&app)
     }
 
+    let (title_rx, title_stop_tx) = spawn_title_monitor();
+    let (fullscreen_rx, fullscreen_stop_tx) = spawn_fullscreen_monitor();
+    let (browser_tx, host_rx) = spawn_browser_monitor();
+    if let Some(app) = monitor.get_active_application() {
+        let _ = browser_tx.send(app);
+    }
+
     // Handle Ctrl+C to exit cleanly
     let (stop_tx, stop_rx) = unbounded::<()>();
-    ctrlc::set_handler(move || {
+    let shutdown = move || {
         let _ = stop_tx.send(());
         let _ = monitor_stop_tx.send(());
-    })
-    .expect("failed to set Ctrl+C handler");
+        let _ = title_stop_tx.send(());
+        let _ = fullscreen_stop_tx.send(());
+    };
+    let api_shutdown = shutdown.clone();
+    crash::install(shutdown.clone());
+    ctrlc::set_handler(shutdown).expect("failed to set Ctrl+C handler");
 
-    let workspace_path = maybe_workspace_path.to_owned();
+    let workspace_path = maybe_workspace.as_ref().map(Workspace::path);
 
     // Start control socket on the main thread and forward commands into the event loop.
     let (api_tx, api_rx) = unbounded::<ApiCommand>();
+    let bridge_tx = api_tx.clone();
+    let feed = StateFeed::default();
+    // Start with the backend of the profile instead of switching once it
+    // loads. The watcher loads it again, from the cache.
+    let backend = maybe_workspace
+        .as_ref()
+        .and_then(|workspace| load_profile_cached(&workspace.profile_path()).ok())
+        .and_then(|profile| profile.gamepad_backend)
+        .unwrap_or_default();
+    let manager = ControllerManager::with_backend(backend)
+        .expect("failed to start controller manager");
     let _control_handle = workspace_path.clone().map(|workspace_path| {
         UnixSocket::new(workspace_path)
-            .listen_events(api_tx)
+            .with_controllers(manager.controller_list())
+            .listen_events(api_tx, feed.clone())
             .expect("failed to start api server")
     });
 
     // Run the main event loop in a background thread while the main thread runs the monitor loop.
     let event_loop = std::thread::Builder::new()
-        .name("event-loop".into())
+        .name(crash::EVENT_LOOP_THREAD.into())
         .stack_size(512 * 1024)
         .spawn(move || {
-        let manager =
-            ControllerManager::new().expect("failed to start controller manager");
+        // Dropped last, once held keys were released
+        let _exit_on_panic = crash::ExitOnPanic;
         let rx = manager.subscribe();
         let mut keypress = Performer::new().expect("failed to start keypress");
-        // Single coalesced wake timer: earliest of movement tick and repeat deadlines.
+        // Single wake timer for the earliest deadline of any timer
         let mut wake_rx = crossbeam_channel::never::<std::time::Instant>();
-        let idle_period = Duration::from_millis(16);
-        let fast_period = Duration::from_millis(10);
-        let mut ticking_enabled = false;
-        let mut fast_mode = false;
-        let mut fast_until = std::time::Instant::now();
-        let mut next_tick_due: Option<std::time::Instant> = None;
         let mut need_reschedule_wake = true;
 
-        let workspace = match Workspace::new(workspace_path.as_deref()) {
+        let watch_profile = maybe_workspace.is_some();
+        let workspace = match maybe_workspace.map_or_else(|| Workspace::new(None), Ok) {
             Ok(workspace) => workspace,
             Err(e) => {
                 print_error!("failed to start workspace: {e}");
@@ -226,58 +521,212 @@ fn run_event_loop(maybe_workspace_path: Option<PathBuf>) {
             }
         };
 
-        let maybe_watcher = workspace_path
-            .as_ref()
-            .map(|_| workspace.start_profile_watcher())
+        let state_path = Workspace::state_path()
+            .inspect_err(|e| print_error!("runtime state is not kept: {e}"))
+            .ok();
+        let workspace_dir = workspace.path();
+        let mut state = state_path.as_deref().map(State::load).unwrap_or_default();
+        let save_state = |state: &State| {
+            if let Some(Err(e)) = state_path.as_deref().map(|path| state.save(path)) {
+                print_error!("failed to save state: {e}");
+            }
+        };
+        if let Some(set) = state.profile_set.as_deref() {
+            gamacros.restore_set(set);
+        }
+        if let Some(app) = state.pinned_app.as_deref() {
+            gamacros.pin_app(Some(app));
+        }
+        manager.set_calibrations(state.calibration());
+
+        let maybe_watcher = watch_profile
+            .then(|| workspace.start_profile_watcher())
             .transpose()
             .expect("failed to start workspace watcher");
 
         let maybe_workspace_rx = maybe_watcher.map(|(_watcher, rx)| rx);
 
         let mut action_runner = ActionRunner::new(&mut keypress, &manager);
+        let heartbeat = Heartbeat::new();
+        action_runner.set_heartbeat(Some(heartbeat.clone()));
+        if let Err(e) = watchdog::spawn(heartbeat.clone()) {
+            print_error!("failed to start watchdog: {e}");
+        }
+        action_runner.set_observer(Some(action_observer(feed.clone(), None, None)));
+        if state.paused {
+            print_info!("paused since the last run");
+            action_runner.set_paused(true);
+            publish_pause(&feed, &action_runner);
+        }
+        let mut maybe_osc: Option<OscOutput> = None;
+        let mut maybe_overlay: Option<Overlay> = None;
+        let mut maybe_speaker: Option<Speaker> = None;
+        // Active rules may have changed since the overlay last showed them
+        let mut rules_changed = false;
+        let mut maybe_bridge: Option<WebSocketBridge> = None;
+        let mut maybe_http: Option<HttpStatusServer> = None;
+        // Keyboard chords are only listened for while app rules bind some
+        let mut maybe_keys: Option<KeyListener> = None;
+        let mut keys_rx = crossbeam_channel::never::<KeyEvent>();
+        // Chords listened for may differ from the active rules
+        let mut keys_changed = false;
+        let mut keys_suspended = false;
+        let status = SharedStatus::default();
+        status.lock().unwrap().permissions = permissions;
+        let mut status_changed = true;
+        let mut fullscreen = false;
+        let mut screen_locked = false;
+        // Latest app switch held back as caused by our own input, and until when.
+        let mut deferred_app: Option<(String, std::time::Instant)> = None;
+        let mut activation = ActivationFilter::new();
+
+        let mut maybe_recorder = None;
+        if let Some(path) = maybe_record_path.as_deref() {
+            match Recorder::create(path) {
+                Ok(recorder) => {
+                    print_info!("recording controller events to {}", path.display());
+                    maybe_recorder = Some(recorder);
+                }
+                Err(e) => print_error!("failed to start recording: {e}"),
+            }
+        }
 
         print_info!(
             "gamacrosd started. Listening for controller and activity events."
         );
+        // Wakes the loop while idle, so the watchdog sees it beat
+        let beat_rx = crossbeam_channel::tick(watchdog::BEAT_INTERVAL);
         loop {
+            heartbeat.beat();
             select! {
                 recv(stop_rx) -> _ => {
+                    print_info!("shutting down");
+                    gamacros.release_held_with(|action| {
+                        action_runner.run(action);
+                    });
+                    action_runner.release_held_keys();
+                    action_runner.stop_rumble();
+                    log::logger().flush();
                     break;
                 }
                 recv(rx) -> msg => {
-                    match msg {
-                        Ok(ControllerEvent::Connected(info)) => {
+                    let timed = match msg {
+                        Ok(timed) => timed,
+                        Err(err) => {
+                            print_error!("event channel closed: {err}");
+                            break;
+                        }
+                    };
+                    if let Some(recorder) = maybe_recorder.as_mut() {
+                        if let Err(e) = recorder.record(&timed) {
+                            print_error!("failed to record event, recording stopped: {e}");
+                            maybe_recorder = None;
+                        }
+                    }
+                    history::record(HistoryEvent::Controller(timed.event.clone()));
+                    let received = timed.at;
+                    match timed.event {
+                        ControllerEvent::Connected(info) => {
                             let id = info.id;
                             if gamacros.is_known(id) {
                                 continue;
                             }
 
-                            gamacros.add_controller(info);
+                            gamacros.add_controller_with(info, |action| {
+                                action_runner.run(action);
+                            });
+                            feed.publish(StateEvent::Controllers {
+                                controllers: controller_statuses(&manager),
+                            });
+                            status_changed = true;
                             need_reschedule_wake = true;
                         }
-                        Ok(ControllerEvent::Disconnected(id)) => {
+                        ControllerEvent::Disconnected(id) => {
+                            if let Some(osc) = maybe_osc.as_mut() {
+                                osc.remove_controller(id);
+                            }
+                            gamacros.release_held_for(id, |action| {
+                                action_runner.run_for_input(action, received);
+                            });
                             gamacros.remove_controller(id);
                             gamacros.on_controller_disconnected(id);
+                            feed.publish(StateEvent::Controllers {
+                                controllers: controller_statuses(&manager),
+                            });
+                            status_changed = true;
                             need_reschedule_wake = true;
                         }
-                        Ok(ControllerEvent::ButtonPressed { id, button }) => {
+                        ControllerEvent::CapabilitiesChanged { id, supports_rumble } => {
+                            gamacros.set_supports_rumble(id, supports_rumble);
+                            feed.publish(StateEvent::Controllers {
+                                controllers: controller_statuses(&manager),
+                            });
+                            status_changed = true;
+                        }
+                        ControllerEvent::ButtonPressed { id, button } => {
+                            if let Some(osc) = maybe_osc.as_mut() {
+                                osc.button(id, button, true);
+                            }
                             gamacros.on_button_with(id, button, ButtonPhase::Pressed, |action| {
-                                action_runner.run(action);
+                                action_runner.run_for_input(action, received);
                             });
+                            // Press may have been deferred until its chord settles.
+                            need_reschedule_wake = true;
                         }
-                        Ok(ControllerEvent::ButtonReleased { id, button }) => {
+                        ControllerEvent::ButtonReleased { id, button } => {
+                            if let Some(osc) = maybe_osc.as_mut() {
+                                osc.button(id, button, false);
+                            }
                             gamacros.on_button_with(id, button, ButtonPhase::Released, |action| {
-                                action_runner.run(action);
+                                action_runner.run_for_input(action, received);
                             });
+                            need_reschedule_wake = true;
                         }
-                        Ok(ControllerEvent::AxisMotion { id, axis, value }) => {
-                            gamacros.on_axis_motion(id, axis, value);
+                        ControllerEvent::AxisMotion { id, axis, value } => {
+                            if let Some(osc) = maybe_osc.as_mut() {
+                                osc.axis(id, axis, value);
+                            }
+                            gamacros.on_axis_motion_with(id, axis, value, |action| {
+                                action_runner.run_for_input(action, received);
+                            });
                             // Axis moved: if previously gated by neutral, re-arm wake.
                             need_reschedule_wake = true;
                         }
-                        Err(err) => {
-                            print_error!("event channel closed: {err}");
-                            break;
+                        ControllerEvent::JoystickAxisMotion { id, axis, value } => {
+                            // Raw axes act as the logical axes the profile maps them to
+                            let Some(axis) = gamacros.joystick_axis(id, axis) else {
+                                continue;
+                            };
+                            if let Some(osc) = maybe_osc.as_mut() {
+                                osc.axis(id, axis, value);
+                            }
+                            gamacros.on_axis_motion_with(id, axis, value, |action| {
+                                action_runner.run_for_input(action, received);
+                            });
+                            need_reschedule_wake = true;
+                        }
+                    }
+                }
+                recv(keys_rx) -> event => {
+                    if let Ok(event) = &event {
+                        history::record(HistoryEvent::Key(event.clone()));
+                    }
+                    match event {
+                        Ok(KeyEvent { combo, pressed }) => {
+                            let phase = if pressed {
+                                ButtonPhase::Pressed
+                            } else {
+                                ButtonPhase::Released
+                            };
+                            gamacros.on_key_with(&combo, phase, |action| {
+                                action_runner.run(action);
+                            });
+                            need_reschedule_wake = true;
+                        }
+                        Err(_) => {
+                            print_error!("keyboard listener stopped");
+                            maybe_keys = None;
+                            keys_rx = crossbeam_channel::never();
                         }
                     }
                 }
@@ -286,65 +735,294 @@ fn run_event_loop(maybe_workspace_path: Option<PathBuf>) {
                         Ok(ApiCommand::Rumble { id, ms }) => {
                             match id {
                                 Some(cid) => {
-                                    action_runner.run(crate::app::Action::Rumble { id: cid, ms });
+                                    action_runner.run(crate::app::Action::Rumble { id: cid, ms, strength: 1.0 });
                                 }
                                 None => {
                                     for info in manager.controllers() {
-                                        action_runner.run(crate::app::Action::Rumble { id: info.id, ms });
+                                        action_runner.run(crate::app::Action::Rumble { id: info.id, ms, strength: 1.0 });
                                     }
                                 }
                             }
                         }
+                        Ok(ApiCommand::Shutdown) => api_shutdown(),
+                        Ok(ApiCommand::Pause) => {
+                            if !action_runner.is_paused() {
+                                print_info!("paused");
+                                gamacros.release_held_with(|action| {
+                                    action_runner.run(action);
+                                });
+                                action_runner.set_paused(true);
+                                publish_pause(&feed, &action_runner);
+                                status_changed = true;
+                            }
+                        }
+                        Ok(ApiCommand::Resume) => {
+                            if action_runner.is_paused() {
+                                print_info!("resumed");
+                                action_runner.set_paused(false);
+                                publish_pause(&feed, &action_runner);
+                                status_changed = true;
+                            }
+                        }
+                        Ok(ApiCommand::PinApp { app }) => {
+                            gamacros.release_held_with(|action| {
+                                action_runner.run(action);
+                            });
+                            gamacros.pin_app(app.as_deref());
+                            need_reschedule_wake = true;
+                            rules_changed = true;
+                        }
+                        // The Unix socket answers these itself and websocket clients
+                        // always receive events, so there is nothing to do here
+                        Ok(ApiCommand::Subscribe | ApiCommand::GetControllers) => {}
                         Err(_) => {
                             // control channel closed; continue running
                         }
                     }
                 }
                 recv(wake_rx) -> _ => {
-                    let now = std::time::Instant::now();
-                    // Run movement tick if due
-                    if let Some(due) = next_tick_due {
-                        if now >= due {
-                            gamacros.on_tick_with(|action| {
-                                action_runner.run(action);
-                            });
-                            // Update adaptive mode hints
-                            if gamacros.wants_fast_tick() {
-                                fast_mode = true;
-                                fast_until = now + Duration::from_millis(250);
-                            } else if fast_mode && now >= fast_until {
-                                fast_mode = false;
-                            }
-                        }
-                    }
-                    // Run repeats due (may be multiple)
-                    gamacros.process_due_repeats(now, |action| { action_runner.run(action); });
+                    gamacros.run_due(|action| { action_runner.run(action); });
+                    action_runner.run_due();
                     need_reschedule_wake = true;
                 }
+                recv(beat_rx) -> _ => {}
             }
+            heartbeat.enter(Stage::Activity);
+            let mut activated = Vec::new();
+            let now = std::time::Instant::now();
             while let Ok(msg) = activity_std_rx.try_recv() {
-                let ActivityEvent::DidActivateApplication(bundle_id) = msg else {
-                    continue;
-                };
+                match msg {
+                    ActivityEvent::DidActivateApplication(bundle_id) => {
+                        history::record(HistoryEvent::App(bundle_id.clone()));
+                        activated.extend(activation.push(bundle_id, now));
+                        need_reschedule_wake = true;
+                    }
+                    ActivityEvent::ScreenLocked => screen_locked = true,
+                    ActivityEvent::ScreenUnlocked => screen_locked = false,
+                    ActivityEvent::Error(error) => {
+                        print_error!(target: logging::ACTIVITY, "activity monitor error: {error}");
+                    }
+                    _ => {}
+                }
+            }
+            activated.extend(activation.take_due(now));
+            // App switches right after our own input may be caused by it.
+            // They only apply if the app is still frontmost after the window.
+            let own_activity_until = gamacros
+                .workspace
+                .as_ref()
+                .map(|workspace| workspace.ignore_own_activity)
+                .filter(|window| !window.is_zero())
+                .and_then(|window| Some(action_runner.last_injection()? + window))
+                .filter(|until| *until > std::time::Instant::now());
+            match (own_activity_until, activated.pop()) {
+                (Some(until), Some(bundle_id)) => {
+                    print_debug!(target: logging::ACTIVITY, "deferring app change after own input - {bundle_id}");
+                    activated.clear();
+                    deferred_app = Some((bundle_id, until));
+                    need_reschedule_wake = true;
+                }
+                (_, Some(bundle_id)) => {
+                    activated.push(bundle_id);
+                    deferred_app = None;
+                }
+                (Some(until), None) => {
+                    if let Some((_, due)) = deferred_app.as_mut() {
+                        *due = until;
+                        need_reschedule_wake = true;
+                    }
+                }
+                (None, None) => activated.extend(deferred_app.take().map(|(id, _)| id)),
+            }
+            for bundle_id in activated {
+                let app_changed = gamacros.get_active_app() != bundle_id;
+                if app_changed {
+                    gamacros.release_held_with(|action| {
+                        action_runner.run(action);
+                    });
+                    if let Some(bridge) = maybe_bridge.as_ref() {
+                        bridge.broadcaster().send(&BridgeEvent::App {
+                            bundle_id: bundle_id.clone(),
+                        });
+                    }
+                    feed.publish(StateEvent::App {
+                        bundle_id: bundle_id.clone(),
+                    });
+                    status_changed = true;
+                }
+                let _ = browser_tx.send(bundle_id.clone());
                 gamacros.set_active_app(&bundle_id);
+                if let Some(speaker) = maybe_speaker.as_mut().filter(|_| app_changed) {
+                    let app = gamacros.active_app_name();
+                    speaker.app_changed(&app, gamacros.has_active_rules());
+                }
                 // App change may alter stick modes; mark for reschedule
                 need_reschedule_wake = true;
+                rules_changed = true;
+            }
+            if let Some(host) = host_rx.try_iter().last() {
+                gamacros.set_site_host(&host);
+                need_reschedule_wake = true;
+                rules_changed = true;
+            }
+            // Only the latest title matters
+            if let Some(title) = title_rx.try_iter().last() {
+                gamacros.set_window_title(&title);
+                need_reschedule_wake = true;
+                rules_changed = true;
+            }
+            if let Some(state) = fullscreen_rx.try_iter().last() {
+                fullscreen = state;
+            }
+
+            if let Some(bridge) = maybe_bridge.as_ref() {
+                let broadcaster = bridge.broadcaster();
+                for (id, chord) in gamacros.take_fired_chords() {
+                    broadcaster.send(&BridgeEvent::chord(id, chord));
+                }
+            }
+            let set_changed = gamacros.active_set() != state.profile_set.as_deref();
+            if set_changed
+                || action_runner.is_paused() != state.paused
+                || gamacros.pinned_app() != state.pinned_app.as_deref()
+            {
+                state.profile_set = gamacros.active_set().map(str::to_string);
+                state.paused = action_runner.is_paused();
+                state.pinned_app = gamacros.pinned_app().map(str::to_string);
+                save_state(&state);
+            }
+            if set_changed {
+                need_reschedule_wake = true;
+                rules_changed = true;
             }
             let Some(workspace_rx) = maybe_workspace_rx.as_ref() else {
                 continue;
             };
 
+            heartbeat.enter(Stage::Profile);
             while let Ok(msg) = workspace_rx.try_recv() {
                 match msg {
                     ProfileEvent::Changed(workspace) => {
                         print_info!("profile changed, updating workspace");
+                        logging::configure(&workspace.log);
+                        heartbeat.configure(workspace.watchdog.clone());
                         if let Some(shell) = workspace.shell.clone() {
                             action_runner.set_shell(shell);
                         }
+                        action_runner.set_key_delay(workspace.key_delay);
+                        let keyboard = workspace.keyboard_backend;
+                        if keyboard != action_runner.keyboard_backend() {
+                            print_info!("switching keyboard backend to {keyboard}");
+                            if let Err(e) = action_runner.set_keyboard_backend(keyboard) {
+                                print_error!("failed to switch keyboard backend: {e}");
+                            }
+                        }
+                        if let Some(backend) = workspace.gamepad_backend {
+                            if backend != manager.backend() {
+                                print_info!("switching gamepad backend to {backend:?}");
+                                if let Err(e) = manager.set_backend(backend) {
+                                    print_error!("failed to switch gamepad backend: {e}");
+                                }
+                            }
+                        }
+                        manager.set_ignore_virtual(workspace.ignore_virtual);
+                        manager.set_trigger_buttons(workspace.trigger_buttons);
+                        manager.set_calibrations(workspace.calibration.clone());
+                        if state.set_calibration(&workspace.calibration) {
+                            save_state(&state);
+                        }
+                        manager.set_mappings(workspace.controller_mappings.clone());
+                        manager.set_hid_devices(workspace.hid_devices.clone());
+                        manager.set_reconnect_grace(workspace.reconnect_grace);
+                        manager.set_axis_coalescing(workspace.axis_interval);
+                        activation.configure(
+                            workspace.activation_debounce,
+                            workspace.transient_apps.clone(),
+                        );
+                        if workspace.osc.as_ref() != maybe_osc.as_ref().map(OscOutput::settings) {
+                            maybe_osc = workspace.osc.clone().and_then(|settings| {
+                                print_info!("sending OSC to {}", settings.target);
+                                OscOutput::new(settings)
+                                    .inspect_err(|e| print_error!("failed to start OSC output: {e}"))
+                                    .ok()
+                            });
+                        }
+                        if workspace.speech.as_ref() != maybe_speaker.as_ref().map(Speaker::settings) {
+                            maybe_speaker = workspace.speech.clone().map(Speaker::new);
+                        }
+                        let mut observer_changed = false;
+                        if workspace.overlay.as_ref() != maybe_overlay.as_ref().map(Overlay::settings) {
+                            maybe_overlay = workspace.overlay.clone().map(Overlay::new);
+                            observer_changed = true;
+                        }
+                        if workspace.websocket.as_ref() != maybe_bridge.as_ref().map(WebSocketBridge::settings) {
+                            // Drop the running bridge first, it may hold the address
+                            drop(maybe_bridge.take());
+                            maybe_bridge = workspace.websocket.clone().and_then(|settings| {
+                                let token = read_token(&workspace_dir);
+                                WebSocketBridge::start(settings, token, bridge_tx.clone())
+                                    .inspect_err(|e| print_error!("failed to start websocket api: {e}"))
+                                    .ok()
+                            });
+                            gamacros.record_fired_chords(maybe_bridge.is_some());
+                            observer_changed = true;
+                        }
+                        if observer_changed {
+                            let broadcaster = maybe_bridge.as_ref().map(WebSocketBridge::broadcaster);
+                            action_runner.set_observer(Some(action_observer(
+                                feed.clone(),
+                                broadcaster,
+                                maybe_overlay.clone(),
+                            )));
+                        }
+                        if workspace.http != maybe_http.as_ref().map(HttpStatusServer::address) {
+                            drop(maybe_http.take());
+                            maybe_http = workspace.http.and_then(|address| {
+                                HttpStatusServer::start(address, status.clone())
+                                    .inspect_err(|e| print_error!("failed to start http status: {e}"))
+                                    .ok()
+                            });
+                        }
+                        gamacros.release_held_with(|action| {
+                            action_runner.run(action);
+                        });
                         gamacros.set_workspace(workspace);
+                        let wants_keys = gamacros
+                            .workspace
+                            .as_ref()
+                            .is_some_and(|workspace| workspace.has_key_rules());
+                        if wants_keys && maybe_keys.is_none() {
+                            match KeyListener::start() {
+                                Ok((listener, rx)) => {
+                                    print_info!("listening for keyboard chords");
+                                    maybe_keys = Some(listener);
+                                    keys_rx = rx;
+                                }
+                                Err(e) => print_error!("failed to listen for keyboard chords: {e}"),
+                            }
+                        } else if !wants_keys && maybe_keys.is_some() {
+                            maybe_keys = None;
+                            keys_rx = crossbeam_channel::never();
+                        }
+                        status_changed = true;
                         need_reschedule_wake = true;
+                        rules_changed = true;
                     }
                     ProfileEvent::Removed => {
+                        logging::configure(&Default::default());
+                        heartbeat.configure(Default::default());
+                        maybe_osc = None;
+                        maybe_bridge = None;
+                        maybe_http = None;
+                        maybe_overlay = None;
+                        maybe_speaker = None;
+                        maybe_keys = None;
+                        keys_rx = crossbeam_channel::never();
+                        action_runner.set_observer(Some(action_observer(feed.clone(), None, None)));
+                        gamacros.record_fired_chords(false);
+                        gamacros.release_held_with(|action| {
+                            action_runner.run(action);
+                        });
                         gamacros.remove_workspace();
                         need_reschedule_wake = true;
                     }
@@ -353,33 +1031,75 @@ fn run_event_loop(maybe_workspace_path: Option<PathBuf>) {
                     }
                 }
             }
+            heartbeat.enter(Stage::Update);
+            if rules_changed {
+                if let Some(overlay) = maybe_overlay.as_mut() {
+                    let app = gamacros.active_app_name();
+                    overlay.rules_changed(
+                        &app,
+                        gamacros.active_set(),
+                        gamacros.active_stick_rules(),
+                    );
+                }
+                keys_changed = true;
+                rules_changed = false;
+            }
+            let auto_pause = gamacros.workspace.as_ref().and_then(|workspace| {
+                if screen_locked {
+                    Some("screen locked")
+                } else if workspace.pauses_app(gamacros.get_active_app()) {
+                    Some("blacklisted app")
+                } else if workspace.pause_on_fullscreen && fullscreen {
+                    Some("fullscreen window")
+                } else {
+                    None
+                }
+            });
+            if auto_pause.is_some() != action_runner.is_auto_paused() {
+                match auto_pause {
+                    Some(reason) => {
+                        print_info!("actions paused: {reason}");
+                        gamacros.release_held_with(|action| {
+                            action_runner.run(action);
+                        });
+                    }
+                    None => print_info!("actions resumed"),
+                }
+                action_runner.set_auto_paused(auto_pause.is_some());
+                publish_pause(&feed, &action_runner);
+                status_changed = true;
+            }
+            // Chords pass through to apps while actions are paused
+            if keys_changed || keys_suspended != action_runner.is_suspended() {
+                keys_suspended = action_runner.is_suspended();
+                if let Some(keys) = maybe_keys.as_ref() {
+                    let combos = if keys_suspended {
+                        Vec::new()
+                    } else {
+                        gamacros.active_key_combos()
+                    };
+                    keys.set_combos(&combos);
+                }
+                keys_changed = false;
+            }
+            if status_changed && maybe_http.is_some() {
+                update_status(&status, &gamacros, &action_runner, &manager);
+                status_changed = false;
+            }
+            if gamacros.schedule_keys(action_runner.next_due()) {
+                need_reschedule_wake = true;
+            }
             if need_reschedule_wake {
                 let now = std::time::Instant::now();
-                // Recompute next tick due
-                if gamacros.needs_tick() {
-                    if !ticking_enabled {
-                        fast_mode = gamacros.wants_fast_tick();
-                        if fast_mode {
-                            fast_until = now + Duration::from_millis(250);
-                        }
-                    }
-                    let period = if fast_mode { fast_period } else { idle_period };
-                    next_tick_due = Some(now + period);
-                    ticking_enabled = true;
-                } else {
-                    next_tick_due = None;
-                    ticking_enabled = false;
-                }
-                // Recompute next repeat due
-                let repeat_due = gamacros.next_repeat_due();
-
-                // Arm single wake for the earliest deadline
-                let next_due = match (next_tick_due, repeat_due) {
-                    (Some(a), Some(b)) => Some(core::cmp::min(a, b)),
-                    (Some(a), None) => Some(a),
-                    (None, Some(b)) => Some(b),
-                    (None, None) => None,
-                };
+                // App switches held back share the wake timer
+                let next_due = [
+                    gamacros.next_due(),
+                    deferred_app.as_ref().map(|(_, until)| *until),
+                    activation.next_due(),
+                ]
+                .into_iter()
+                .flatten()
+                .min();
                 if let Some(due) = next_due {
                     let dur = if due > now { due - now } else { Duration::ZERO };
                     wake_rx = crossbeam_channel::after(dur);
@@ -396,4 +1116,7 @@ fn run_event_loop(maybe_workspace_path: Option<PathBuf>) {
     if let Err(e) = event_loop.join() {
         print_error!("event loop error: {e:?}");
     }
+    if crash::crashed() {
+        process::exit(crash::EXIT_CODE);
+    }
 }
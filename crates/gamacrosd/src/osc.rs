@@ -0,0 +1,168 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use ahash::AHashMap;
+use gamacros_gamepad::{Axis, Button, ControllerId};
+use gamacros_workspace::{axis_name, button_name, OscSettings};
+
+use crate::app::stick::util::{
+    axis_index, fast_gamma, magnitude2d, normalize_after_deadzone,
+};
+
+/// Streams controller state as OSC messages over UDP.
+///
+/// Axes are sent as floats to `<prefix>/<id>/axis/<name>` after the deadzone
+/// and response curve are applied, only when the shaped value changes.
+/// Buttons are sent as `1` or `0` to `<prefix>/<id>/button/<name>`.
+pub(crate) struct OscOutput {
+    socket: UdpSocket,
+    settings: OscSettings,
+    /// Raw axes by controller.
+    axes: AHashMap<ControllerId, [f32; 6]>,
+    /// Last shaped axes sent by controller.
+    sent: AHashMap<ControllerId, [f32; 6]>,
+}
+
+/// An OSC message argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arg {
+    Int(i32),
+    Float(f32),
+}
+
+impl OscOutput {
+    pub(crate) fn new(settings: OscSettings) -> io::Result<Self> {
+        let local: SocketAddr = match settings.target {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(settings.target)?;
+        Ok(Self {
+            socket,
+            settings,
+            axes: AHashMap::new(),
+            sent: AHashMap::new(),
+        })
+    }
+
+    pub(crate) fn settings(&self) -> &OscSettings {
+        &self.settings
+    }
+
+    pub(crate) fn axis(&mut self, id: ControllerId, axis: Axis, value: f32) {
+        let axes = self.axes.entry(id).or_default();
+        axes[axis_index(axis)] = value;
+        let shaped = shape(axes, self.settings.deadzone, self.settings.gamma);
+        let sent = self.sent.entry(id).or_default();
+        // A stick's shaped axes depend on each other, so both may change
+        for axis in Axis::ALL {
+            let index = axis_index(axis);
+            if shaped[index] != sent[index] {
+                sent[index] = shaped[index];
+                let address = format!(
+                    "{}/{id}/axis/{}",
+                    self.settings.prefix,
+                    axis_name(axis)
+                );
+                let _ = self
+                    .socket
+                    .send(&encode(&address, Arg::Float(shaped[index])));
+            }
+        }
+    }
+
+    pub(crate) fn button(
+        &mut self,
+        id: ControllerId,
+        button: Button,
+        pressed: bool,
+    ) {
+        let address = format!(
+            "{}/{id}/button/{}",
+            self.settings.prefix,
+            button_name(button)
+        );
+        let _ = self
+            .socket
+            .send(&encode(&address, Arg::Int(i32::from(pressed))));
+    }
+
+    pub(crate) fn remove_controller(&mut self, id: ControllerId) {
+        self.axes.remove(&id);
+        self.sent.remove(&id);
+    }
+}
+
+/// Applies a radial deadzone and the response curve to each stick,
+/// and a linear one to each trigger.
+fn shape(axes: &[f32; 6], deadzone: f32, gamma: f32) -> [f32; 6] {
+    let mut shaped = [0.0; 6];
+    for (x, y) in [(Axis::LeftX, Axis::LeftY), (Axis::RightX, Axis::RightY)] {
+        let (x, y) = (axis_index(x), axis_index(y));
+        let mag = magnitude2d(axes[x], axes[y]);
+        if mag > deadzone {
+            let scale =
+                fast_gamma(normalize_after_deadzone(mag, deadzone, 0.0), gamma)
+                    / mag;
+            shaped[x] = axes[x] * scale;
+            shaped[y] = axes[y] * scale;
+        }
+    }
+    for trigger in [Axis::LeftTrigger, Axis::RightTrigger] {
+        let index = axis_index(trigger);
+        let value = normalize_after_deadzone(axes[index].abs(), deadzone, 0.0);
+        shaped[index] = fast_gamma(value, gamma);
+    }
+    shaped
+}
+
+/// Encodes an OSC message with a single argument.
+fn encode(address: &str, arg: Arg) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(address.len() + 12);
+    push_padded(&mut packet, address.as_bytes());
+    match arg {
+        Arg::Int(value) => {
+            push_padded(&mut packet, b",i");
+            packet.extend_from_slice(&value.to_be_bytes());
+        }
+        Arg::Float(value) => {
+            push_padded(&mut packet, b",f");
+            packet.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    packet
+}
+
+/// Appends a null-terminated string padded to a multiple of four bytes.
+fn push_padded(packet: &mut Vec<u8>, bytes: &[u8]) {
+    packet.extend_from_slice(bytes);
+    let padding = 4 - bytes.len() % 4;
+    packet.extend(std::iter::repeat(0).take(padding));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_messages() {
+        assert_eq!(
+            encode("/a", Arg::Int(1)),
+            [b'/', b'a', 0, 0, b',', b'i', 0, 0, 0, 0, 0, 1]
+        );
+        let packet = encode("/abcd", Arg::Float(0.5));
+        assert_eq!(&packet[..8], b"/abcd\0\0\0");
+        assert_eq!(&packet[8..12], b",f\0\0");
+        assert_eq!(&packet[12..], 0.5f32.to_be_bytes());
+    }
+
+    #[test]
+    fn shapes_sticks_and_triggers() {
+        let shaped = shape(&[0.1, 0.0, 0.0, -1.0, 0.6, 0.0], 0.2, 1.0);
+        assert_eq!(shaped[0], 0.0);
+        assert_eq!(shaped[3], -1.0);
+        assert!((shaped[4] - 0.5).abs() < 1e-6);
+        assert_eq!(shaped[5], 0.0);
+    }
+}
@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use gamacros_workspace::{OverlaySettings, StickRules, StickSide};
+
+use crate::api::describe_action;
+use crate::app::Action;
+
+/// Flashes a HUD, like the system volume OSD, when the stick modes or the
+/// profile set of the active rules change and, if enabled, when a button
+/// action fires.
+///
+/// Only macOS draws the HUD, other platforms ignore flashes.
+#[derive(Clone)]
+pub(crate) struct Overlay {
+    settings: OverlaySettings,
+    /// Profile set and stick modes shown last, so apps sharing modes
+    /// switch quietly.
+    shown: Option<(Option<String>, String)>,
+}
+
+impl Overlay {
+    pub(crate) fn new(settings: OverlaySettings) -> Self {
+        Self {
+            settings,
+            shown: None,
+        }
+    }
+
+    pub(crate) fn settings(&self) -> &OverlaySettings {
+        &self.settings
+    }
+
+    /// Flashes `app`, the profile set and the stick modes if they differ
+    /// from the last ones.
+    pub(crate) fn rules_changed(
+        &mut self,
+        app: &str,
+        set: Option<&str>,
+        sticks: Option<&StickRules>,
+    ) {
+        let shown = (
+            set.map(str::to_string),
+            sticks.map(describe_sticks).unwrap_or_default(),
+        );
+        if self.shown.as_ref() == Some(&shown) {
+            return;
+        }
+        let (set, modes) = &shown;
+        let mut text = match set {
+            Some(set) => format!("{app} · {set}"),
+            None => app.to_string(),
+        };
+        if !modes.is_empty() {
+            text.push('\n');
+            text.push_str(modes);
+        }
+        self.shown = Some(shown);
+        flash(text, self.settings.duration);
+    }
+
+    /// Flashes the name of a fired action, if actions are shown.
+    pub(crate) fn action(&self, action: &Action) {
+        if !self.settings.actions {
+            return;
+        }
+        if let Some(text) = action_label(action) {
+            flash(text, self.settings.duration);
+        }
+    }
+}
+
+/// `left: mouse_move · right: scroll`, empty without stick rules.
+fn describe_sticks(sticks: &StickRules) -> String {
+    [(StickSide::Left, "left"), (StickSide::Right, "right")]
+        .into_iter()
+        .filter_map(|(side, name)| {
+            Some(format!("{name}: {}", sticks.get(&side)?.mode.name()))
+        })
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Label of an action worth flashing. Releases and rumble follow other
+/// actions and would only replace their label.
+fn action_label(action: &Action) -> Option<String> {
+    if matches!(action, Action::KeyRelease(_) | Action::Rumble { .. }) {
+        return None;
+    }
+    let (name, detail) = describe_action(action)?;
+    Some(match detail {
+        Some(detail) => format!("{name}: {detail}"),
+        None => name.to_string(),
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn flash(text: String, duration: Duration) {
+    native::flash(text, duration);
+}
+
+#[cfg(not(target_os = "macos"))]
+fn flash(_text: String, _duration: Duration) {}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use std::cell::RefCell;
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use objc2::rc::Retained;
+    use objc2::{MainThreadMarker, MainThreadOnly};
+    use objc2_app_kit::{
+        NSBackingStoreType, NSColor, NSFont, NSPanel, NSScreen, NSStatusWindowLevel,
+        NSTextAlignment, NSTextField, NSWindowCollectionBehavior, NSWindowStyleMask,
+    };
+    use objc2_foundation::{NSPoint, NSRect, NSSize, NSString};
+
+    /// Space between the text and the edge of the HUD.
+    const PADDING: f64 = 16.0;
+
+    /// Number of flashes so far, so only the latest one hides the HUD.
+    static FLASHES: AtomicU64 = AtomicU64::new(0);
+
+    thread_local! {
+        /// The HUD, created on the main thread by the first flash.
+        static HUD: RefCell<Option<Hud>> = const { RefCell::new(None) };
+    }
+
+    struct Hud {
+        panel: Retained<NSPanel>,
+        label: Retained<NSTextField>,
+    }
+
+    pub(super) fn flash(text: String, duration: Duration) {
+        let flash = FLASHES.fetch_add(1, Ordering::Relaxed) + 1;
+        on_main(Duration::ZERO, move |mtm| {
+            HUD.with_borrow_mut(|hud| {
+                hud.get_or_insert_with(|| Hud::new(mtm)).show(mtm, &text)
+            });
+        });
+        on_main(duration, move |_| {
+            if FLASHES.load(Ordering::Relaxed) != flash {
+                return;
+            }
+            HUD.with_borrow(|hud| {
+                if let Some(hud) = hud {
+                    hud.panel.orderOut(None);
+                }
+            });
+        });
+    }
+
+    impl Hud {
+        fn new(mtm: MainThreadMarker) -> Self {
+            // SAFETY: AppKit objects are created and set up on the main thread.
+            unsafe {
+                let panel = NSPanel::initWithContentRect_styleMask_backing_defer(
+                    NSPanel::alloc(mtm),
+                    NSRect::ZERO,
+                    NSWindowStyleMask::Borderless
+                        | NSWindowStyleMask::NonactivatingPanel,
+                    NSBackingStoreType::Buffered,
+                    false,
+                );
+                panel.setReleasedWhenClosed(false);
+                panel.setLevel(NSStatusWindowLevel);
+                panel.setIgnoresMouseEvents(true);
+                panel.setOpaque(false);
+                panel.setHasShadow(false);
+                panel.setBackgroundColor(Some(&NSColor::colorWithWhite_alpha(
+                    0.0, 0.75,
+                )));
+                // Show over every space, including fullscreen apps
+                panel.setCollectionBehavior(
+                    NSWindowCollectionBehavior::CanJoinAllSpaces
+                        | NSWindowCollectionBehavior::Stationary
+                        | NSWindowCollectionBehavior::FullScreenAuxiliary,
+                );
+
+                let label =
+                    NSTextField::labelWithString(&NSString::from_str(""), mtm);
+                label.setFont(Some(&NSFont::boldSystemFontOfSize(20.0)));
+                label.setTextColor(Some(&NSColor::whiteColor()));
+                label.setAlignment(NSTextAlignment::Center);
+                if let Some(content) = panel.contentView() {
+                    content.addSubview(&label);
+                }
+                Self { panel, label }
+            }
+        }
+
+        fn show(&self, mtm: MainThreadMarker, text: &str) {
+            let Some(screen) = NSScreen::mainScreen(mtm) else {
+                return;
+            };
+            let screen = screen.frame();
+            // SAFETY: the HUD is only used on the main thread.
+            unsafe {
+                self.label.setStringValue(&NSString::from_str(text));
+                let text_size = self.label.fittingSize();
+                self.label.setFrame(NSRect::new(
+                    NSPoint::new(PADDING, PADDING),
+                    text_size,
+                ));
+                let size = NSSize::new(
+                    text_size.width + 2.0 * PADDING,
+                    text_size.height + 2.0 * PADDING,
+                );
+                // Centered in the lower third of the screen
+                let origin = NSPoint::new(
+                    screen.origin.x + (screen.size.width - size.width) / 2.0,
+                    screen.origin.y + screen.size.height / 6.0,
+                );
+                self.panel.setFrame_display(NSRect::new(origin, size), true);
+                self.panel.orderFrontRegardless();
+            }
+        }
+    }
+
+    /// Runs `work` on the main thread after `delay`, through the main
+    /// dispatch queue drained by the `NSApplication` run loop.
+    fn on_main(
+        delay: Duration,
+        work: impl FnOnce(MainThreadMarker) + Send + 'static,
+    ) {
+        type Work = Box<dyn FnOnce(MainThreadMarker) + Send>;
+
+        extern "C" fn run(context: *mut c_void) {
+            // SAFETY: the context is the work leaked below, run exactly once.
+            let work = unsafe { Box::from_raw(context.cast::<Work>()) };
+            if let Some(mtm) = MainThreadMarker::new() {
+                work(mtm);
+            }
+        }
+
+        let work: Box<Work> = Box::new(Box::new(work));
+        let delay = i64::try_from(delay.as_nanos()).unwrap_or(i64::MAX);
+        // SAFETY: the main queue lives for the whole process and `run`
+        // takes ownership of the context.
+        unsafe {
+            let when = dispatch_time(DISPATCH_TIME_NOW, delay);
+            dispatch_after_f(
+                when,
+                std::ptr::addr_of!(_dispatch_main_q),
+                Box::into_raw(work).cast(),
+                run,
+            );
+        }
+    }
+
+    const DISPATCH_TIME_NOW: u64 = 0;
+
+    #[repr(C)]
+    struct DispatchQueue {
+        _private: [u8; 0],
+    }
+
+    // libdispatch is part of libSystem, linked by default.
+    extern "C" {
+        static _dispatch_main_q: DispatchQueue;
+        fn dispatch_time(when: u64, delta: i64) -> u64;
+        fn dispatch_after_f(
+            when: u64,
+            queue: *const DispatchQueue,
+            context: *mut c_void,
+            work: extern "C" fn(*mut c_void),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gamacros_control::SystemAction;
+    use gamacros_workspace::parse_profile;
+
+    use super::*;
+
+    #[test]
+    fn describes_stick_modes_and_actions() {
+        let profile = parse_profile(
+            "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        mode: mouse_move
+      right:
+        mode: scroll
+",
+        )
+        .unwrap();
+        let rules = profile.resolve_app_rules("common").unwrap();
+        assert_eq!(
+            describe_sticks(&rules.sticks),
+            "left: mouse_move · right: scroll"
+        );
+        assert_eq!(
+            action_label(&Action::System(SystemAction::LockScreen)).as_deref(),
+            Some("system: lock_screen")
+        );
+        assert_eq!(
+            action_label(&Action::KeyTap("shift+cmd+p".parse().unwrap())).as_deref(),
+            Some("key_tap: cmd+shift+p")
+        );
+        assert_eq!(
+            action_label(&Action::Rumble {
+                id: 0,
+                ms: 50,
+                strength: 1.0
+            }),
+            None
+        );
+    }
+}
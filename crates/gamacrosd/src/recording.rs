@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use bitcode::{Decode, Encode};
+use gamacros_gamepad::{ControllerEvent, TimedEvent};
+use thiserror::Error;
+
+/// Leading bytes of a `.gmrec` file, including the format version.
+const MAGIC: &[u8; 6] = b"GMREC3";
+
+#[derive(Error, Debug)]
+pub(crate) enum RecordingError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to decode event: {0}")]
+    Decode(#[from] bitcode::Error),
+    #[error("not a gamacros recording")]
+    BadMagic,
+}
+
+/// A single recorded controller event.
+#[derive(Encode, Decode)]
+pub(crate) struct Frame {
+    /// Time since the start of the recording, in microseconds.
+    pub elapsed_us: u64,
+    pub event: ControllerEvent,
+}
+
+impl Frame {
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.elapsed_us)
+    }
+}
+
+/// Writes controller events to a `.gmrec` file.
+///
+/// Events are stored as length-prefixed bitcode frames, each flushed to the
+/// file as it is recorded, so a recording interrupted by a crash stays
+/// readable up to the last complete frame.
+pub(crate) struct Recorder {
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, RecordingError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+        })
+    }
+
+    /// Records an event at the time it was received from the device.
+    pub fn record(&mut self, timed: &TimedEvent) -> Result<(), RecordingError> {
+        let elapsed = timed.at.saturating_duration_since(self.started);
+        let frame = Frame {
+            elapsed_us: elapsed.as_micros() as u64,
+            event: timed.event.clone(),
+        };
+        let data = bitcode::encode(&frame);
+        self.writer.write_all(&(data.len() as u32).to_be_bytes())?;
+        self.writer.write_all(&data)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads all frames of a `.gmrec` file.
+pub(crate) fn read(path: &Path) -> Result<Vec<Frame>, RecordingError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(RecordingError::BadMagic);
+    }
+
+    let mut frames = Vec::new();
+    let mut length_buffer = [0u8; 4];
+    loop {
+        match reader.read_exact(&mut length_buffer) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let mut data = vec![0u8; u32::from_be_bytes(length_buffer) as usize];
+        match reader.read_exact(&mut data) {
+            Ok(()) => {}
+            // Truncated trailing frame
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        frames.push(bitcode::decode(&data)?);
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gamacros_gamepad::{Axis, Button, ControllerInfo};
+
+    fn timed(event: ControllerEvent) -> TimedEvent {
+        TimedEvent {
+            at: Instant::now(),
+            event,
+        }
+    }
+
+    #[test]
+    fn recording_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("gamacros-{}.gmrec", std::process::id()));
+        {
+            let mut recorder = Recorder::create(&path).unwrap();
+            let events = [
+                ControllerEvent::Connected(ControllerInfo {
+                    id: 1,
+                    name: "Pad".to_string(),
+                    supports_rumble: true,
+                    vendor_id: 0x054c,
+                    product_id: 0x0ce6,
+                    serial: None,
+                    extra_buttons: Vec::new(),
+                }),
+                ControllerEvent::ButtonPressed {
+                    id: 1,
+                    button: Button::A,
+                },
+                ControllerEvent::AxisMotion {
+                    id: 1,
+                    axis: Axis::RightY,
+                    value: -0.5,
+                },
+            ];
+            for event in events {
+                recorder.record(&timed(event)).unwrap();
+            }
+            // Readable while still recording, as after a crash
+            assert_eq!(read(&path).unwrap().len(), 3);
+        }
+
+        let frames = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), 3);
+        assert!(frames.windows(2).all(|w| w[0].elapsed() <= w[1].elapsed()));
+        assert!(matches!(
+            &frames[0].event,
+            ControllerEvent::Connected(info) if info.vendor_id == 0x054c
+        ));
+        assert!(matches!(
+            frames[2].event,
+            ControllerEvent::AxisMotion {
+                id: 1,
+                axis: Axis::RightY,
+                value
+            } if value == -0.5
+        ));
+    }
+
+    #[test]
+    fn rejects_foreign_file() {
+        let path = std::env::temp_dir()
+            .join(format!("gamacros-foreign-{}.gmrec", std::process::id()));
+        std::fs::write(&path, b"NOTREC").unwrap();
+        let result = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(RecordingError::BadMagic)));
+    }
+}
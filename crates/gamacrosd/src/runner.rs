@@ -1,17 +1,47 @@
-use std::{process::Command, time::Duration};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 use colored::Colorize;
-use gamacros_control::Performer;
+use enigo::Direction::{Click, Press, Release};
+use gamacros_control::{KeyCombo, KeyEvent, KeyboardBackendKind, Performer};
 use gamacros_gamepad::ControllerManager;
 
-use crate::{app::Action, print_error, print_info};
+use crate::app::Action;
+use crate::{print_debug, print_error};
+use crate::shell_pool::{Job, ShellPool};
+use crate::watchdog::{Heartbeat, Stage};
 
 const DEFAULT_SHELL: &str = "/bin/zsh";
 
+/// Callback invoked with every action before it runs, with the time since
+/// the controller event that caused it, if any.
+pub type ActionObserver<'a> = Box<dyn FnMut(&Action, Option<Duration>) + 'a>;
+
+/// Key event waiting for the key delay, with the process it is posted to.
+struct PacedKey {
+    due: Instant,
+    event: KeyEvent,
+    target: Option<i32>,
+}
+
 pub struct ActionRunner<'a> {
     keypress: &'a mut Performer,
     manager: &'a ControllerManager,
     shell: Option<Box<str>>,
+    shell_pool: ShellPool,
+    observer: Option<ActionObserver<'a>>,
+    /// Tells the watchdog when the event loop is running an action.
+    heartbeat: Option<Heartbeat>,
+    /// Keys pressed by `Action::KeyPress` and not released yet, with the
+    /// key delay and target process they were pressed with.
+    held: Vec<(KeyCombo, Duration, Option<i32>)>,
+    /// Wait between injected key events unless a rule has its own.
+    key_delay: Duration,
+    /// Key events of combos and macros waiting for their turn, sent by
+    /// `run_due` so the event loop never sleeps between them.
+    paced: VecDeque<PacedKey>,
+    paused: bool,
+    auto_paused: bool,
 }
 
 impl<'a> ActionRunner<'a> {
@@ -20,69 +50,311 @@ impl<'a> ActionRunner<'a> {
             keypress,
             manager,
             shell: None,
+            shell_pool: ShellPool::new(),
+            observer: None,
+            heartbeat: None,
+            held: Vec::new(),
+            key_delay: Duration::ZERO,
+            paced: VecDeque::new(),
+            paused: false,
+            auto_paused: false,
         }
     }
 
     pub fn run(&mut self, action: Action) {
+        self.run_with_latency(action, None);
+    }
+
+    /// Runs an action caused by a controller event received at `received`,
+    /// reporting the latency from device to action to the observer.
+    pub fn run_for_input(&mut self, action: Action, received: Instant) {
+        if self.is_suspended() {
+            return;
+        }
+        let latency = received.elapsed();
+        print_debug!("action {action:?} - {}us after input", latency.as_micros());
+        self.run_with_latency(action, Some(latency));
+    }
+
+    fn run_with_latency(&mut self, action: Action, latency: Option<Duration>) {
+        if self.is_suspended() {
+            return;
+        }
+        if let Some(observer) = self.observer.as_mut() {
+            observer(&action, latency);
+        }
+        let outer = self
+            .heartbeat
+            .as_ref()
+            .map(|heartbeat| heartbeat.enter(Stage::Action));
+        self.execute(action);
+        if let (Some(heartbeat), Some(outer)) = (self.heartbeat.as_ref(), outer) {
+            heartbeat.enter(outer);
+        }
+    }
+
+    fn execute(&mut self, action: Action) {
         match action {
-            Action::KeyTap(k) => {
-                let _ = self.keypress.perform(&k);
-            }
+            Action::KeyTap(k) => self.send_keys(k.events(Click)),
             Action::KeyPress(k) => {
-                let _ = self.keypress.press(&k);
+                self.send_keys(k.events(Press));
+                self.held.push((k, self.key_delay, self.keypress.target()));
             }
             Action::KeyRelease(k) => {
-                let _ = self.keypress.release(&k);
+                match self.held.iter().position(|(held, ..)| *held == k) {
+                    Some(index) => {
+                        let (k, delay, target) = self.held.swap_remove(index);
+                        self.release_with(&k, delay, target);
+                    }
+                    None => self.send_keys(k.events(Release)),
+                }
             }
             Action::Macros(m) => {
-                for k in m.iter() {
-                    let _ = self.keypress.perform(k);
+                self.send_keys(m.iter().flat_map(|k| k.events(Click)));
+            }
+            Action::Shell { command, limits } => {
+                let shell = self.shell.clone().unwrap_or(DEFAULT_SHELL.into());
+                self.shell_pool.submit(Job::shell(shell, command), limits);
+            }
+            Action::Shortcut { name, limits } => {
+                self.shell_pool.submit(Job::shortcut(name), limits);
+            }
+            Action::System(action) => {
+                if let Err(e) = self.keypress.system(action) {
+                    print_error!("failed to run {action}: {e}");
+                }
+            }
+            Action::Window(action) => {
+                if let Err(e) = gamacros_window::perform(action) {
+                    print_error!("failed to run {action}: {e}");
                 }
             }
-            Action::Shell(s) => {
-                let _ = self.run_shell(&s);
+            Action::SwitchSpace(target) => {
+                if let Err(e) = self.keypress.switch_space(target) {
+                    print_error!("failed to switch to {target} space: {e}");
+                }
+            }
+            Action::CycleApp(apps) => {
+                let running = gamacros_activity::running_applications();
+                let next = gamacros_activity::next_application(&running, |id| {
+                    apps.iter().position(|app| app.matches(id))
+                });
+                match next {
+                    Some(app) => {
+                        if !gamacros_activity::activate_application(&app.id) {
+                            print_error!("failed to activate {}", app.id);
+                        }
+                    }
+                    None => print_debug!("no other running app to cycle to"),
+                }
             }
             Action::MouseMove { dx, dy } => {
                 let _ = self.keypress.mouse_move(dx, dy);
             }
-            Action::Scroll { h, v } => {
+            Action::MouseClick(button) => {
+                if let Err(e) = self.keypress.click(button) {
+                    print_error!("failed to click the {button} mouse button: {e}");
+                }
+            }
+            Action::MouseWarp(target) => {
+                if let Err(e) = self.keypress.warp_mouse(target) {
+                    print_error!("failed to move the cursor to {target}: {e}");
+                }
+            }
+            Action::Scroll { h, v, unit } => {
                 if h != 0 {
-                    let _ = self.keypress.scroll_x(h);
+                    let _ = self.keypress.scroll_x(h, unit);
                 }
                 if v != 0 {
-                    let _ = self.keypress.scroll_y(v);
+                    let _ = self.keypress.scroll_y(v, unit);
                 }
             }
-            Action::Rumble { id, ms } => {
+            Action::Rumble { id, ms, strength } => {
                 if let Some(h) = self.manager.controller(id) {
-                    let _ = h.rumble(1.0, 1.0, Duration::from_millis(ms as u64));
+                    let ms = Duration::from_millis(ms as u64);
+                    let _ = h.rumble(strength, strength, ms);
                 }
             }
+            Action::WithKeyDelay(action, delay) => {
+                let default = std::mem::replace(&mut self.key_delay, delay);
+                self.execute(*action);
+                self.key_delay = default;
+            }
+            Action::WithTarget(action, bundle_id) => {
+                let Some(pid) = gamacros_activity::application_pid(&bundle_id)
+                else {
+                    print_error!("failed to send keys to {bundle_id}: not running");
+                    return;
+                };
+                let default = self.keypress.target();
+                if let Err(e) = self.keypress.set_target(Some(pid)) {
+                    print_error!("failed to send keys to {bundle_id}: {e}");
+                    return;
+                }
+                self.execute(*action);
+                let _ = self.keypress.set_target(default);
+            }
+        }
+    }
+
+    pub fn keyboard_backend(&self) -> KeyboardBackendKind {
+        self.keypress.keyboard_backend()
+    }
+
+    /// Switches the backend keys are sent with. Held keys are released
+    /// first, through the backend that pressed them.
+    pub fn set_keyboard_backend(
+        &mut self,
+        kind: KeyboardBackendKind,
+    ) -> enigo::InputResult<()> {
+        self.release_held_keys();
+        self.keypress.set_keyboard_backend(kind)
+    }
+
+    /// Wait between injected key events unless a rule has its own.
+    pub fn set_key_delay(&mut self, delay: Duration) {
+        self.key_delay = delay;
+    }
+
+    /// Sends key events `key_delay` apart. Events that have to wait are
+    /// queued after the ones already waiting, so keys of consecutive
+    /// actions never interleave.
+    fn send_keys(&mut self, events: impl IntoIterator<Item = KeyEvent>) {
+        let now = Instant::now();
+        let target = self.keypress.target();
+        let mut due = self
+            .paced
+            .back()
+            .map_or(now, |last| last.due + self.key_delay);
+        for event in events {
+            if self.paced.is_empty() && due <= now {
+                let _ = self.keypress.send(&event);
+            } else {
+                self.paced.push_back(PacedKey { due, event, target });
+            }
+            due += self.key_delay;
         }
     }
 
-    fn run_shell(&mut self, cmd: &str) -> Result<String, String> {
-        let shell = self.shell.clone().unwrap_or(DEFAULT_SHELL.into());
-        let result = Command::new(shell.into_string().as_str())
-            .args(["-c", cmd])
-            .output();
+    /// When the next queued key event is due.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.paced.front().map(|paced| paced.due)
+    }
 
-        match result {
-            Ok(output) => {
-                print_info!(
-                    "shell command output: {}",
-                    String::from_utf8_lossy(&output.stdout)
-                );
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    /// Sends the queued key events due by now.
+    pub fn run_due(&mut self) {
+        let now = Instant::now();
+        while let Some(paced) = self.paced.pop_front() {
+            if paced.due > now {
+                self.paced.push_front(paced);
+                break;
             }
-            Err(e) => {
-                print_error!("shell command error: {}", e);
-                Err(e.to_string())
+            self.send_paced(paced);
+        }
+    }
+
+    /// Sends every queued key event right away, so no key stays down.
+    fn flush_keys(&mut self) {
+        while let Some(paced) = self.paced.pop_front() {
+            self.send_paced(paced);
+        }
+    }
+
+    fn send_paced(&mut self, paced: PacedKey) {
+        let default = self.keypress.target();
+        if paced.target == default {
+            let _ = self.keypress.send(&paced.event);
+            return;
+        }
+        // The combo was sent to this process before, so its keyboard exists
+        let _ = self.keypress.set_target(paced.target);
+        let _ = self.keypress.send(&paced.event);
+        let _ = self.keypress.set_target(default);
+    }
+
+    /// Releases keys with the key delay and in the process they were
+    /// pressed with.
+    fn release_with(&mut self, k: &KeyCombo, delay: Duration, target: Option<i32>) {
+        let default = std::mem::replace(&mut self.key_delay, delay);
+        let default_target = self.keypress.target();
+        // The keys were pressed in this process, so its keyboard exists
+        let _ = self.keypress.set_target(target);
+        self.send_keys(k.events(Release));
+        self.key_delay = default;
+        let _ = self.keypress.set_target(default_target);
+    }
+
+    /// Returns when the performer injected input last.
+    pub fn last_injection(&self) -> Option<Instant> {
+        self.keypress.last_injection()
+    }
+
+    /// Release every key that is still held down, sending queued key
+    /// events without waiting for their key delay.
+    pub fn release_held_keys(&mut self) {
+        for (k, delay, target) in std::mem::take(&mut self.held) {
+            self.release_with(&k, delay, target);
+        }
+        self.flush_keys();
+    }
+
+    /// Stop rumble on all connected controllers.
+    pub fn stop_rumble(&self) {
+        for info in self.manager.controllers() {
+            if let Some(h) = self.manager.controller(info.id) {
+                let _ = h.stop_rumble();
             }
         }
     }
 
+    /// Drop every action until resumed. Held keys are released on pause.
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused {
+            self.release_held_keys();
+        }
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Like `set_paused`, for pauses the daemon decides on. A manual
+    /// pause outlives an automatic one.
+    pub fn set_auto_paused(&mut self, paused: bool) {
+        if paused {
+            self.release_held_keys();
+        }
+        self.auto_paused = paused;
+    }
+
+    pub fn is_auto_paused(&self) -> bool {
+        self.auto_paused
+    }
+
+    /// Whether actions are dropped, by either kind of pause.
+    pub fn is_suspended(&self) -> bool {
+        self.paused || self.auto_paused
+    }
+
+    pub fn set_observer(&mut self, observer: Option<ActionObserver<'a>>) {
+        self.observer = observer;
+    }
+
+    pub fn set_heartbeat(&mut self, heartbeat: Option<Heartbeat>) {
+        self.heartbeat = heartbeat;
+    }
+
     pub fn set_shell(&mut self, shell: Box<str>) {
         self.shell = Some(shell);
     }
 }
+
+impl Drop for ActionRunner<'_> {
+    /// Never leaves keys held or controllers rumbling, even when the event
+    /// loop unwinds from a panic.
+    fn drop(&mut self) {
+        self.release_held_keys();
+        self.stop_rumble();
+    }
+}
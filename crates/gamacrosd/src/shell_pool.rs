@@ -0,0 +1,218 @@
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use ahash::AHashMap;
+use colored::Colorize;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use gamacros_workspace::{ShellLimits, ShellOverflow};
+
+use crate::{print_debug, print_error, print_info, print_warning};
+
+/// Number of threads running shell commands.
+const WORKERS: usize = 4;
+/// Upper bound of commands waiting for a running instance to finish.
+const MAX_QUEUED: usize = 16;
+
+/// A process to run. Limits are tracked per distinct job.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Job {
+    program: Box<str>,
+    args: Vec<String>,
+}
+
+impl Job {
+    /// A command run with `shell -c`.
+    pub(crate) fn shell(shell: Box<str>, command: String) -> Self {
+        Self {
+            program: shell,
+            args: vec!["-c".to_string(), command],
+        }
+    }
+
+    /// A macOS Shortcuts workflow run with the `shortcuts` tool.
+    pub(crate) fn shortcut(name: String) -> Self {
+        Self {
+            program: "shortcuts".into(),
+            args: vec!["run".to_string(), name],
+        }
+    }
+
+    /// The command or shortcut name, for logs.
+    fn name(&self) -> &str {
+        self.args.last().map_or(&self.program, |arg| arg)
+    }
+}
+
+/// Outcome of submitting a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Admission {
+    Run,
+    Queue,
+    Debounced,
+    Dropped,
+}
+
+/// Instances of a single job.
+#[derive(Debug, Default)]
+struct JobState {
+    /// Submitted to the workers, running or waiting for a free one.
+    active: usize,
+    /// Instances waiting for an active one to finish.
+    queued: usize,
+    last_accepted: Option<Instant>,
+}
+
+impl JobState {
+    fn admit(&mut self, limits: &ShellLimits, now: Instant) -> Admission {
+        if let Some(last) = self.last_accepted {
+            if now.saturating_duration_since(last) < limits.debounce {
+                return Admission::Debounced;
+            }
+        }
+        let admission = match limits.max_concurrent {
+            Some(max) if self.active >= max => match limits.overflow {
+                ShellOverflow::Queue if self.queued < MAX_QUEUED => Admission::Queue,
+                _ => return Admission::Dropped,
+            },
+            _ => Admission::Run,
+        };
+        self.last_accepted = Some(now);
+        admission
+    }
+}
+
+type Jobs = Arc<Mutex<AHashMap<Job, JobState>>>;
+
+/// Runs shell commands and shortcuts on a fixed set of worker threads,
+/// enforcing per-job limits.
+pub(crate) struct ShellPool {
+    queue: Sender<Job>,
+    jobs: Jobs,
+}
+
+impl ShellPool {
+    pub(crate) fn new() -> Self {
+        let (queue, rx) = unbounded();
+        let jobs = Jobs::default();
+        for index in 0..WORKERS {
+            let rx = rx.clone();
+            let jobs = jobs.clone();
+            let _ = std::thread::Builder::new()
+                .name(format!("shell-{index}"))
+                .spawn(move || work(rx, jobs));
+        }
+        Self { queue, jobs }
+    }
+
+    pub(crate) fn submit(&self, job: Job, limits: ShellLimits) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let state = jobs.entry(job.clone()).or_default();
+        match state.admit(&limits, Instant::now()) {
+            Admission::Run => {
+                state.active += 1;
+                let _ = self.queue.send(job);
+            }
+            Admission::Queue => state.queued += 1,
+            Admission::Debounced => {
+                print_debug!("command debounced: {}", job.name());
+            }
+            Admission::Dropped => {
+                print_warning!("command dropped, already running: {}", job.name());
+            }
+        }
+    }
+}
+
+fn work(rx: Receiver<Job>, jobs: Jobs) {
+    for job in rx {
+        loop {
+            run(&job);
+            let mut jobs = jobs.lock().unwrap();
+            let Some(state) = jobs.get_mut(&job) else {
+                break;
+            };
+            // A queued instance takes over the finished one's slot
+            if state.queued > 0 {
+                state.queued -= 1;
+            } else {
+                state.active -= 1;
+                break;
+            }
+        }
+    }
+}
+
+fn run(job: &Job) {
+    let started = Instant::now();
+    match Command::new(&*job.program).args(&job.args).output() {
+        Ok(output) => {
+            print_info!(
+                "command output: {}",
+                String::from_utf8_lossy(&output.stdout)
+            );
+            print_debug!(
+                "command finished in {:?}: {}",
+                started.elapsed(),
+                job.name()
+            );
+        }
+        Err(e) => {
+            print_error!("command error: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn limits(max: usize, overflow: ShellOverflow) -> ShellLimits {
+        ShellLimits {
+            max_concurrent: Some(max),
+            debounce: Duration::ZERO,
+            overflow,
+        }
+    }
+
+    #[test]
+    fn admit_applies_concurrency_limit() {
+        let now = Instant::now();
+        let mut state = JobState {
+            active: 1,
+            ..Default::default()
+        };
+        let drop = limits(1, ShellOverflow::Drop);
+        assert_eq!(state.admit(&drop, now), Admission::Dropped);
+        let queue = limits(1, ShellOverflow::Queue);
+        assert_eq!(state.admit(&queue, now), Admission::Queue);
+        assert_eq!(
+            state.admit(&limits(2, ShellOverflow::Drop), now),
+            Admission::Run
+        );
+
+        state.queued = MAX_QUEUED;
+        assert_eq!(state.admit(&queue, now), Admission::Dropped);
+    }
+
+    #[test]
+    fn admit_debounces_after_accepted_trigger() {
+        let now = Instant::now();
+        let mut state = JobState::default();
+        let limits = ShellLimits {
+            debounce: Duration::from_millis(100),
+            ..Default::default()
+        };
+        assert_eq!(state.admit(&limits, now), Admission::Run);
+        assert_eq!(
+            state.admit(&limits, now + Duration::from_millis(50)),
+            Admission::Debounced
+        );
+        assert_eq!(
+            state.admit(&limits, now + Duration::from_millis(100)),
+            Admission::Run
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Deterministic simulation of profile behavior for tests.
+//!
+//! A [`Simulation`] drives [`Gamacros`] with a virtual clock, so chord
+//! windows and stick repeats can be tested without hardware or sleeping:
+//!
+//! ```
+//! use gamacros_gamepad::Button;
+//! use gamacrosd::sim::Simulation;
+//!
+//! let mut sim = Simulation::from_yaml(
+//!     "
+//! version: 1
+//! rules:
+//!   common:
+//!     buttons:
+//!       a:
+//!         keystroke: enter
+//! ",
+//! )
+//! .unwrap();
+//! sim.connect(0).press(0, Button::A).wait_ms(100);
+//! assert_eq!(sim.take_actions().len(), 1);
+//! ```
+//!
+//! Scenarios may also be written as JSON scripts, see [`Step`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use gamacros_gamepad::{Axis, Button, ControllerId, ControllerInfo};
+use gamacros_workspace::{axis_name, parse_button, parse_profile, Profile};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::app::clock::{Clock, ManualClock};
+use crate::app::{Action, ButtonPhase, Gamacros};
+
+pub use crate::app::scheduler::TICK_PERIOD;
+/// Rules key applied to every app, active until another app is selected.
+const COMMON_APP: &str = "common";
+
+#[derive(Debug, Error)]
+pub enum SimError {
+    #[error("profile error: {0}")]
+    Profile(String),
+    #[error("invalid script: {0}")]
+    Script(#[from] serde_json::Error),
+    #[error("unknown button: {0}")]
+    Button(String),
+    #[error("unknown axis: {0}")]
+    Axis(String),
+}
+
+/// A single step of a simulation script, e.g. `{"press": {"button": "a"}}`.
+/// The controller `id` defaults to `0`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    Connect {
+        #[serde(default)]
+        id: ControllerId,
+    },
+    Disconnect {
+        #[serde(default)]
+        id: ControllerId,
+    },
+    Press {
+        #[serde(default)]
+        id: ControllerId,
+        button: String,
+    },
+    Release {
+        #[serde(default)]
+        id: ControllerId,
+        button: String,
+    },
+    Axis {
+        #[serde(default)]
+        id: ControllerId,
+        axis: String,
+        value: f32,
+    },
+    App {
+        bundle_id: String,
+    },
+    Wait {
+        ms: u64,
+    },
+}
+
+/// Profile behavior driven by a virtual clock. Actions are recorded with
+/// the virtual time they happened at instead of being performed.
+pub struct Simulation {
+    gamacros: Gamacros,
+    clock: ManualClock,
+    elapsed: Duration,
+    actions: Vec<(Duration, Action)>,
+}
+
+impl Simulation {
+    pub fn new(profile: Profile) -> Self {
+        let clock = ManualClock::new();
+        let mut gamacros = Gamacros::with_clock(Arc::new(clock.clone()));
+        gamacros.set_workspace(profile);
+        gamacros.set_active_app(COMMON_APP);
+        Self {
+            gamacros,
+            clock,
+            elapsed: Duration::ZERO,
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, SimError> {
+        parse_profile(yaml)
+            .map(Self::new)
+            .map_err(|e| SimError::Profile(e.to_string()))
+    }
+
+    /// Connects a controller with rumble support and no vendor settings.
+    pub fn connect(&mut self, id: ControllerId) -> &mut Self {
+        self.connect_with(ControllerInfo {
+            id,
+            name: "Simulated Controller".to_string(),
+            supports_rumble: true,
+            vendor_id: 0,
+            product_id: 0,
+            serial: None,
+            extra_buttons: Vec::new(),
+        })
+    }
+
+    pub fn connect_with(&mut self, info: ControllerInfo) -> &mut Self {
+        let Self {
+            gamacros,
+            elapsed,
+            actions,
+            ..
+        } = self;
+        gamacros
+            .add_controller_with(info, |action| actions.push((*elapsed, action)));
+        self
+    }
+
+    /// Disconnects a controller, releasing its held keys as the daemon does.
+    pub fn disconnect(&mut self, id: ControllerId) -> &mut Self {
+        let Self {
+            gamacros,
+            elapsed,
+            actions,
+            ..
+        } = self;
+        gamacros.release_held_for(id, |action| actions.push((*elapsed, action)));
+        gamacros.remove_controller(id);
+        gamacros.on_controller_disconnected(id);
+        self
+    }
+
+    pub fn press(&mut self, id: ControllerId, button: Button) -> &mut Self {
+        self.button(id, button, ButtonPhase::Pressed)
+    }
+
+    pub fn release(&mut self, id: ControllerId, button: Button) -> &mut Self {
+        self.button(id, button, ButtonPhase::Released)
+    }
+
+    fn button(
+        &mut self,
+        id: ControllerId,
+        button: Button,
+        phase: ButtonPhase,
+    ) -> &mut Self {
+        let Self {
+            gamacros,
+            elapsed,
+            actions,
+            ..
+        } = self;
+        gamacros.on_button_with(id, button, phase, |action| {
+            actions.push((*elapsed, action))
+        });
+        self
+    }
+
+    pub fn axis(&mut self, id: ControllerId, axis: Axis, value: f32) -> &mut Self {
+        let Self {
+            gamacros,
+            elapsed,
+            actions,
+            ..
+        } = self;
+        gamacros.on_axis_motion_with(id, axis, value, |action| {
+            actions.push((*elapsed, action))
+        });
+        self
+    }
+
+    /// Makes `bundle_id` the frontmost app, releasing held keys on change.
+    pub fn app(&mut self, bundle_id: &str) -> &mut Self {
+        let Self {
+            gamacros,
+            elapsed,
+            actions,
+            ..
+        } = self;
+        if gamacros.get_active_app() != bundle_id {
+            gamacros.release_held_with(|action| actions.push((*elapsed, action)));
+        }
+        gamacros.set_active_app(bundle_id);
+        self
+    }
+
+    /// Advances virtual time from one deadline to the next, running stick
+    /// ticks, repeats and deferred chords as the daemon would.
+    pub fn wait(&mut self, duration: Duration) -> &mut Self {
+        let end = self.clock.now() + duration;
+        loop {
+            let Self {
+                gamacros,
+                clock,
+                elapsed,
+                actions,
+            } = self;
+            let now = clock.now();
+            let Some(due) = gamacros.next_due().filter(|due| *due <= end) else {
+                clock.advance(end - now);
+                *elapsed += end - now;
+                return self;
+            };
+            let step = due.saturating_duration_since(now);
+            clock.advance(step);
+            *elapsed += step;
+            gamacros.run_due(|action| actions.push((*elapsed, action)));
+        }
+    }
+
+    pub fn wait_ms(&mut self, ms: u64) -> &mut Self {
+        self.wait(Duration::from_millis(ms))
+    }
+
+    /// Runs a single script step.
+    pub fn step(&mut self, step: Step) -> Result<&mut Self, SimError> {
+        Ok(match step {
+            Step::Connect { id } => self.connect(id),
+            Step::Disconnect { id } => self.disconnect(id),
+            Step::Press { id, button } => self.press(id, find_button(button)?),
+            Step::Release { id, button } => self.release(id, find_button(button)?),
+            Step::Axis { id, axis, value } => self.axis(id, find_axis(axis)?, value),
+            Step::App { bundle_id } => self.app(&bundle_id),
+            Step::Wait { ms } => self.wait_ms(ms),
+        })
+    }
+
+    /// Runs a JSON array of steps.
+    pub fn run_script(&mut self, script: &str) -> Result<&mut Self, SimError> {
+        let steps: Vec<Step> = serde_json::from_str(script)?;
+        for step in steps {
+            self.step(step)?;
+        }
+        Ok(self)
+    }
+
+    /// Virtual time since the simulation started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Actions recorded since the last call, oldest first.
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        self.actions.drain(..).map(|(_, action)| action).collect()
+    }
+
+    /// Like `take_actions`, with the virtual time of each action.
+    pub fn take_timed_actions(&mut self) -> Vec<(Duration, Action)> {
+        std::mem::take(&mut self.actions)
+    }
+
+    pub fn gamacros(&self) -> &Gamacros {
+        &self.gamacros
+    }
+}
+
+fn find_button(name: String) -> Result<Button, SimError> {
+    parse_button(&name).ok_or(SimError::Button(name))
+}
+
+fn find_axis(name: String) -> Result<Axis, SimError> {
+    Axis::ALL
+        .into_iter()
+        .find(|axis| axis_name(*axis) == name)
+        .ok_or(SimError::Axis(name))
+}
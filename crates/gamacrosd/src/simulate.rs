@@ -0,0 +1,318 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use colored::Colorize;
+use serde::Deserialize;
+use thiserror::Error;
+
+use gamacros_gamepad::{
+    Axis, BackendKind, ControllerEvent, ControllerId, ControllerInfo,
+    ControllerManager, EventReceiver, VirtualInjector,
+};
+use gamacros_workspace::{load_profile, parse_button};
+
+use crate::app::clock::{Clock, ManualClock};
+use crate::app::{Action, ButtonPhase, Gamacros};
+use crate::recording::{self, RecordingError};
+use crate::{print_info, print_warning};
+
+/// How long to wait for an injected event to come back from the runtime.
+const EVENT_TIMEOUT: Duration = Duration::from_millis(100);
+/// Rules key applied to every app, used until the script selects an app.
+const COMMON_APP: &str = "common";
+
+#[derive(Debug, Error)]
+pub(crate) enum SimulateError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid script: {0}")]
+    Script(#[from] serde_json::Error),
+    #[error("profile error: {0}")]
+    Profile(String),
+    #[error("controller manager error: {0}")]
+    Manager(#[from] gamacros_gamepad::Error),
+    #[error("unknown button: {0}")]
+    Button(String),
+    #[error("unknown axis: {0}")]
+    Axis(String),
+    #[error("recording error: {0}")]
+    Recording(#[from] RecordingError),
+    #[error("invalid replay speed: {0}")]
+    Speed(f32),
+}
+
+/// A single step of a simulation script.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum Step {
+    Connect {
+        #[serde(default)]
+        id: ControllerId,
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        vid: u16,
+        #[serde(default)]
+        pid: u16,
+        #[serde(default)]
+        rumble: bool,
+    },
+    Disconnect {
+        #[serde(default)]
+        id: ControllerId,
+    },
+    Press {
+        #[serde(default)]
+        id: ControllerId,
+        button: String,
+    },
+    Release {
+        #[serde(default)]
+        id: ControllerId,
+        button: String,
+    },
+    Axis {
+        #[serde(default)]
+        id: ControllerId,
+        axis: String,
+        value: f32,
+    },
+    App {
+        bundle_id: String,
+    },
+    Wait {
+        ms: u64,
+    },
+}
+
+/// Replays a JSON script against a profile and prints the resulting actions.
+/// Actions are never performed. Waits run on a virtual clock, so scripts
+/// finish instantly and repeat timing is exact.
+pub(crate) fn run(
+    profile_path: &Path,
+    script_path: &Path,
+) -> Result<(), SimulateError> {
+    let clock = ManualClock::new();
+    let mut gamacros = load(profile_path, Arc::new(clock.clone()))?;
+    let steps: Vec<Step> =
+        serde_json::from_str(&std::fs::read_to_string(script_path)?)?;
+
+    let manager = ControllerManager::with_backend(BackendKind::Virtual)?;
+    let rx = manager.subscribe();
+    let injector = manager.injector();
+
+    for step in steps {
+        match step {
+            Step::App { bundle_id } => {
+                if gamacros.get_active_app() != bundle_id {
+                    gamacros.release_held_with(report);
+                }
+                gamacros.set_active_app(&bundle_id);
+            }
+            Step::Wait { ms } => {
+                advance(&mut gamacros, &clock, Duration::from_millis(ms), None)
+            }
+            step => {
+                inject(&injector, step)?;
+                match rx.recv_timeout(EVENT_TIMEOUT) {
+                    Ok(timed) => dispatch(&mut gamacros, timed.event),
+                    Err(_) => print_warning!("event dropped: unknown controller"),
+                }
+            }
+        }
+        drain(&rx, &mut gamacros);
+    }
+
+    Ok(())
+}
+
+/// Replays a recorded session against a profile and prints the resulting actions.
+/// `speed` scales playback time, e.g. `2.0` plays twice as fast.
+pub(crate) fn replay(
+    profile_path: &Path,
+    recording_path: &Path,
+    speed: f32,
+) -> Result<(), SimulateError> {
+    if speed.is_nan() || speed <= 0.0 {
+        return Err(SimulateError::Speed(speed));
+    }
+    // Timing follows the recording, only the pacing is scaled
+    let clock = ManualClock::new();
+    let mut gamacros = load(profile_path, Arc::new(clock.clone()))?;
+    let frames = recording::read(recording_path)?;
+
+    let mut previous = Duration::ZERO;
+    for frame in frames {
+        let delay = frame.elapsed().saturating_sub(previous);
+        advance(&mut gamacros, &clock, delay, Some(speed));
+        previous = frame.elapsed();
+        dispatch(&mut gamacros, frame.event);
+    }
+
+    Ok(())
+}
+
+fn load(
+    profile_path: &Path,
+    clock: Arc<dyn Clock>,
+) -> Result<Gamacros, SimulateError> {
+    let profile = load_profile(profile_path)
+        .map_err(|e| SimulateError::Profile(e.to_string()))?;
+    let mut gamacros = Gamacros::with_clock(clock);
+    gamacros.set_workspace(profile);
+    gamacros.set_active_app(COMMON_APP);
+    Ok(gamacros)
+}
+
+fn inject(injector: &VirtualInjector, step: Step) -> Result<(), SimulateError> {
+    match step {
+        Step::Connect {
+            id,
+            name,
+            vid,
+            pid,
+            rumble,
+        } => injector.connect(ControllerInfo {
+            id,
+            name: name.unwrap_or_else(|| "Virtual Controller".to_string()),
+            supports_rumble: rumble,
+            vendor_id: vid,
+            product_id: pid,
+            serial: None,
+            extra_buttons: Vec::new(),
+        }),
+        Step::Disconnect { id } => injector.disconnect(id),
+        Step::Press { id, button } => {
+            let button =
+                parse_button(&button).ok_or(SimulateError::Button(button))?;
+            injector.press(id, button);
+        }
+        Step::Release { id, button } => {
+            let button =
+                parse_button(&button).ok_or(SimulateError::Button(button))?;
+            injector.release(id, button);
+        }
+        Step::Axis { id, axis, value } => {
+            injector.axis(id, parse_axis(&axis)?, value);
+        }
+        Step::App { .. } | Step::Wait { .. } => {}
+    }
+    Ok(())
+}
+
+fn parse_axis(name: &str) -> Result<Axis, SimulateError> {
+    Ok(match name {
+        "left_x" => Axis::LeftX,
+        "left_y" => Axis::LeftY,
+        "right_x" => Axis::RightX,
+        "right_y" => Axis::RightY,
+        "lt" | "left_trigger" => Axis::LeftTrigger,
+        "rt" | "right_trigger" => Axis::RightTrigger,
+        _ => return Err(SimulateError::Axis(name.to_string())),
+    })
+}
+
+fn drain(rx: &EventReceiver, gamacros: &mut Gamacros) {
+    while let Ok(timed) = rx.try_recv() {
+        dispatch(gamacros, timed.event);
+    }
+}
+
+fn dispatch(gamacros: &mut Gamacros, event: ControllerEvent) {
+    match event {
+        ControllerEvent::Connected(info) => {
+            print_info!("controller {} connected: {}", info.id, info.name);
+            gamacros.add_controller_with(info, report);
+        }
+        ControllerEvent::Disconnected(id) => {
+            print_info!("controller {id} disconnected");
+            gamacros.release_held_for(id, report);
+            gamacros.remove_controller(id);
+            gamacros.on_controller_disconnected(id);
+        }
+        ControllerEvent::CapabilitiesChanged {
+            id,
+            supports_rumble,
+        } => {
+            gamacros.set_supports_rumble(id, supports_rumble);
+        }
+        ControllerEvent::ButtonPressed { id, button } => {
+            gamacros.on_button_with(id, button, ButtonPhase::Pressed, report);
+        }
+        ControllerEvent::ButtonReleased { id, button } => {
+            gamacros.on_button_with(id, button, ButtonPhase::Released, report);
+        }
+        ControllerEvent::AxisMotion { id, axis, value } => {
+            gamacros.on_axis_motion_with(id, axis, value, report);
+        }
+        ControllerEvent::JoystickAxisMotion { id, axis, value } => {
+            if let Some(axis) = gamacros.joystick_axis(id, axis) {
+                gamacros.on_axis_motion_with(id, axis, value, report);
+            }
+        }
+    }
+}
+
+/// Advances virtual time from one deadline to the next, running stick
+/// ticks, repeats and deferred chords. With a `speed`, sleeps for each step
+/// scaled by it, otherwise returns without sleeping.
+fn advance(
+    gamacros: &mut Gamacros,
+    clock: &ManualClock,
+    duration: Duration,
+    speed: Option<f32>,
+) {
+    let end = clock.now() + duration;
+    loop {
+        let now = clock.now();
+        let due = gamacros.next_due().filter(|due| *due <= end);
+        let step = due.unwrap_or(end).saturating_duration_since(now);
+        if let Some(speed) = speed {
+            std::thread::sleep(step.div_f32(speed));
+        }
+        clock.advance(step);
+        if due.is_none() {
+            return;
+        }
+        gamacros.run_due(report);
+    }
+}
+
+fn report(action: Action) {
+    print_info!("action: {action:?}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_script_steps() {
+        let script = r#"[
+            {"connect": {"vid": 1356, "pid": 3302}},
+            {"app": {"bundle_id": "com.apple.Safari"}},
+            {"press": {"button": "a"}},
+            {"axis": {"id": 0, "axis": "left_x", "value": 0.5}},
+            {"wait": {"ms": 50}},
+            {"release": {"button": "a"}},
+            {"disconnect": {}}
+        ]"#;
+        let steps: Vec<Step> = serde_json::from_str(script).unwrap();
+        assert_eq!(steps.len(), 7);
+        assert!(matches!(
+            steps[0],
+            Step::Connect {
+                id: 0,
+                vid: 1356,
+                pid: 3302,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_axis() {
+        assert!(matches!(parse_axis("z"), Err(SimulateError::Axis(_))));
+    }
+}
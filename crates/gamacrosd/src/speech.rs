@@ -0,0 +1,184 @@
+use std::io;
+use std::process::{Child, Command, Stdio};
+
+use colored::Colorize;
+use gamacros_workspace::SpeechSettings;
+
+use crate::print_error;
+
+/// Speaks the name of the active app when it changes, for users who can't
+/// see which rules apply.
+///
+/// macOS speaks with `AVSpeechSynthesizer`, falling back to the `say`
+/// command. Other platforms use `spd-say` from speech-dispatcher. A new
+/// announcement cuts off the previous one, so quick switches don't queue.
+pub(crate) struct Speaker {
+    settings: SpeechSettings,
+    #[cfg(target_os = "macos")]
+    synthesizer: Option<native::Synthesizer>,
+    /// Running speech command, if any.
+    child: Option<Child>,
+}
+
+impl Speaker {
+    pub(crate) fn new(settings: SpeechSettings) -> Self {
+        Self {
+            #[cfg(target_os = "macos")]
+            synthesizer: native::Synthesizer::new(settings.voice.as_deref()),
+            settings,
+            child: None,
+        }
+    }
+
+    pub(crate) fn settings(&self) -> &SpeechSettings {
+        &self.settings
+    }
+
+    /// Announces a switch to `app`, saying so when it has no rules.
+    pub(crate) fn app_changed(&mut self, app: &str, has_rules: bool) {
+        if let Err(e) = self.say(&announcement(app, has_rules)) {
+            print_error!("failed to announce app change: {e}");
+        }
+    }
+
+    fn say(&mut self, text: &str) -> io::Result<()> {
+        #[cfg(target_os = "macos")]
+        if let Some(synthesizer) = self.synthesizer.as_ref() {
+            synthesizer.speak(text);
+            return Ok(());
+        }
+        if let Some(mut child) = self.child.take() {
+            // Already finished if this fails
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let mut command = speech_command(self.settings.voice.as_deref());
+        let child = command
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        self.child = Some(child);
+        Ok(())
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn announcement(app: &str, has_rules: bool) -> String {
+    if has_rules {
+        app.to_string()
+    } else {
+        format!("{app}, no rules")
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn speech_command(voice: Option<&str>) -> Command {
+    let mut command = Command::new("say");
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    command
+}
+
+#[cfg(not(target_os = "macos"))]
+fn speech_command(voice: Option<&str>) -> Command {
+    let mut command = Command::new("spd-say");
+    if let Some(voice) = voice {
+        command.args(["-y", voice]);
+    }
+    command
+}
+
+#[cfg(target_os = "macos")]
+mod native {
+    use colored::Colorize;
+    use objc2::msg_send;
+    use objc2::rc::Retained;
+    use objc2::runtime::{AnyClass, AnyObject};
+    use objc2_foundation::{NSArray, NSString};
+
+    use crate::print_warning;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {}
+
+    /// `AVSpeechBoundaryImmediate`.
+    const BOUNDARY_IMMEDIATE: isize = 0;
+
+    pub(super) struct Synthesizer {
+        synthesizer: Retained<AnyObject>,
+        utterance_class: &'static AnyClass,
+        voice: Option<Retained<AnyObject>>,
+    }
+
+    impl Synthesizer {
+        /// Returns `None` when `AVSpeechSynthesizer` is unavailable.
+        pub(super) fn new(voice: Option<&str>) -> Option<Self> {
+            let class = AnyClass::get(c"AVSpeechSynthesizer")?;
+            let utterance_class = AnyClass::get(c"AVSpeechUtterance")?;
+            let synthesizer: Option<Retained<AnyObject>> =
+                unsafe { msg_send![class, new] };
+            Some(Self {
+                synthesizer: synthesizer?,
+                utterance_class,
+                voice: voice.and_then(find_voice),
+            })
+        }
+
+        pub(super) fn speak(&self, text: &str) {
+            let text = NSString::from_str(text);
+            unsafe {
+                let _: bool = msg_send![
+                    &*self.synthesizer,
+                    stopSpeakingAtBoundary: BOUNDARY_IMMEDIATE
+                ];
+                let utterance: Retained<AnyObject> = msg_send![
+                    self.utterance_class,
+                    speechUtteranceWithString: &*text
+                ];
+                if let Some(voice) = self.voice.as_deref() {
+                    let _: () = msg_send![&*utterance, setVoice: voice];
+                }
+                let _: () =
+                    msg_send![&*self.synthesizer, speakUtterance: &*utterance];
+            }
+        }
+    }
+
+    /// Installed voice named `name`, ignoring case.
+    fn find_voice(name: &str) -> Option<Retained<AnyObject>> {
+        let class = AnyClass::get(c"AVSpeechSynthesisVoice")?;
+        let voices: Retained<NSArray<AnyObject>> =
+            unsafe { msg_send![class, speechVoices] };
+        let voice = voices.iter().find(|voice| {
+            let voice_name: Retained<NSString> =
+                unsafe { msg_send![&**voice, name] };
+            voice_name.to_string().eq_ignore_ascii_case(name)
+        });
+        if voice.is_none() {
+            print_warning!("voice {name} is not installed, using the default");
+        }
+        voice
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn announces_apps_without_rules() {
+        assert_eq!(announcement("Safari", true), "Safari");
+        assert_eq!(announcement("Notes", false), "Notes, no rules");
+    }
+}
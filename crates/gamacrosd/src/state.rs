@@ -0,0 +1,141 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use ahash::AHashMap;
+use colored::Colorize;
+use gamacros_gamepad::{Calibration, DeviceId};
+use serde::{Deserialize, Serialize};
+
+use crate::print_warning;
+
+/// Runtime state kept across daemon restarts, stored as JSON in the file of
+/// `Workspace::state_path`. Saved whenever it changes and restored on
+/// startup.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct State {
+    /// Profile set selected from the controller, `None` for the default.
+    pub(crate) profile_set: Option<String>,
+    /// Whether actions were paused through the API.
+    pub(crate) paused: bool,
+    /// App pinned through the API, whose rules apply in any app.
+    pub(crate) pinned_app: Option<String>,
+    /// Axis calibration of the last loaded profile, applied on startup
+    /// before the profile is loaded again.
+    calibration: Vec<DeviceCalibration>,
+}
+
+/// Calibration of a device, JSON maps can't have its id as a key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct DeviceCalibration {
+    vendor_id: u16,
+    product_id: u16,
+    calibration: Calibration,
+}
+
+impl State {
+    pub(crate) fn calibration(&self) -> AHashMap<DeviceId, Calibration> {
+        self.calibration
+            .iter()
+            .map(|entry| ((entry.vendor_id, entry.product_id), entry.calibration))
+            .collect()
+    }
+
+    /// Replaces the calibration, returns whether it changed.
+    pub(crate) fn set_calibration(
+        &mut self,
+        calibration: &AHashMap<DeviceId, Calibration>,
+    ) -> bool {
+        let mut entries: Vec<_> = calibration
+            .iter()
+            .map(
+                |(&(vendor_id, product_id), &calibration)| DeviceCalibration {
+                    vendor_id,
+                    product_id,
+                    calibration,
+                },
+            )
+            .collect();
+        entries.sort_by_key(|entry| (entry.vendor_id, entry.product_id));
+        if entries == self.calibration {
+            return false;
+        }
+        self.calibration = entries;
+        true
+    }
+
+    /// Reads the state at `path`. A missing or unreadable file yields the
+    /// default state, so a broken file never keeps the daemon from starting.
+    pub(crate) fn load(path: &Path) -> Self {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                print_warning!("failed to read {}: {e}", path.display());
+                return Self::default();
+            }
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            print_warning!("ignoring invalid state in {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    /// Writes the state to `path` through a temporary file, so a crash
+    /// mid-write leaves the previous state intact.
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_vec_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gamacros_gamepad::{Axis, AxisCalibration};
+
+    #[test]
+    fn round_trips_and_tolerates_bad_files() {
+        let dir = std::env::temp_dir()
+            .join(format!("gamacros-state-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gamacros").join("state.json");
+
+        assert_eq!(State::load(&path), State::default());
+        let mut state = State {
+            profile_set: Some("media".to_string()),
+            paused: true,
+            pinned_app: Some("com.spotify.client".to_string()),
+            ..State::default()
+        };
+        let mut calibration = Calibration::default();
+        calibration.set(
+            Axis::LeftX,
+            AxisCalibration {
+                offset: 0.1,
+                scale: 1.2,
+            },
+        );
+        let calibration = AHashMap::from_iter([((0x054c, 0x0ce6), calibration)]);
+        assert!(state.set_calibration(&calibration));
+        assert!(!state.set_calibration(&calibration));
+        state.save(&path).unwrap();
+        let loaded = State::load(&path);
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.calibration(), calibration);
+
+        // Files from older versions lack newer fields
+        fs::write(&path, r#"{"profile_set": "media"}"#).unwrap();
+        assert!(!State::load(&path).paused);
+
+        fs::write(&path, "{not json").unwrap();
+        assert_eq!(State::load(&path), State::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
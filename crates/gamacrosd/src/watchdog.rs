@@ -0,0 +1,205 @@
+//! Watchdog noticing the event loop stuck, e.g. in a keystroke call blocked
+//! by the window server, which otherwise looks like a daemon that silently
+//! stopped reacting.
+//!
+//! The event loop beats once per iteration and wakes at least every
+//! `BEAT_INTERVAL`, so a beat older than the timeout means an iteration
+//! never finished.
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use gamacros_workspace::WatchdogSettings;
+
+use crate::{print_error, print_info};
+
+/// Exit code after the event loop got stuck, `EX_TEMPFAIL` from sysexits.h.
+pub const EXIT_CODE: i32 = 75;
+/// Longest the event loop waits for events between two beats.
+pub const BEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the watchdog checks on the event loop.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Part of an event loop iteration, logged when the loop gets stuck in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Event,
+    Action,
+    Activity,
+    Profile,
+    Update,
+}
+
+impl Stage {
+    const ALL: [Stage; 5] = [
+        Stage::Event,
+        Stage::Action,
+        Stage::Activity,
+        Stage::Profile,
+        Stage::Update,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Event => "handling an event",
+            Stage::Action => "running an action",
+            Stage::Activity => "handling app activity",
+            Stage::Profile => "reloading the profile",
+            Stage::Update => "applying rules",
+        }
+    }
+}
+
+/// Progress of the event loop, reported by the loop and read by the
+/// watchdog. Clones share the same state.
+#[derive(Clone)]
+pub struct Heartbeat {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    settings: Mutex<WatchdogSettings>,
+    start: Instant,
+    /// Nanoseconds from `start` to the last beat.
+    beat: AtomicU64,
+    /// Index of the current `Stage`.
+    stage: AtomicU8,
+}
+
+/// The event loop has not finished an iteration within the timeout.
+#[derive(Debug)]
+pub struct Stuck {
+    pub since: Instant,
+    pub stage: Stage,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                settings: Mutex::default(),
+                start: Instant::now(),
+                beat: AtomicU64::new(0),
+                stage: AtomicU8::new(0),
+            }),
+        }
+    }
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn configure(&self, settings: WatchdogSettings) {
+        *self.shared.settings.lock().unwrap() = settings;
+    }
+
+    pub fn settings(&self) -> WatchdogSettings {
+        self.shared.settings.lock().unwrap().clone()
+    }
+
+    /// Marks the start of an event loop iteration.
+    pub fn beat(&self) {
+        let nanos = self.shared.start.elapsed().as_nanos() as u64;
+        self.shared.beat.store(nanos, Ordering::Relaxed);
+        self.enter(Stage::Event);
+    }
+
+    /// Marks the loop as in `stage` and returns the stage it was in before.
+    pub fn enter(&self, stage: Stage) -> Stage {
+        let previous = self.shared.stage.swap(stage as u8, Ordering::Relaxed);
+        Stage::ALL[usize::from(previous)]
+    }
+
+    /// The stage the loop is stuck in by `now`, if any. Timeouts shorter than
+    /// two beat intervals would report a loop that is just waiting.
+    pub fn stuck(&self, now: Instant) -> Option<Stuck> {
+        let timeout = self.shared.settings.lock().unwrap().timeout;
+        if timeout.is_zero() {
+            return None;
+        }
+        let nanos = self.shared.beat.load(Ordering::Relaxed);
+        let since = self.shared.start + Duration::from_nanos(nanos);
+        let stage =
+            Stage::ALL[usize::from(self.shared.stage.load(Ordering::Relaxed))];
+        (now.saturating_duration_since(since) >= timeout.max(BEAT_INTERVAL * 2))
+            .then_some(Stuck { since, stage })
+    }
+}
+
+/// Starts the watchdog thread. It logs once each time the event loop gets
+/// stuck, and when the loop recovers. With `restart` set the daemon does
+/// not restart itself: the process exits with `EXIT_CODE`, and the launch
+/// agent, or whatever supervises it, starts it again.
+pub fn spawn(heartbeat: Heartbeat) -> std::io::Result<JoinHandle<()>> {
+    thread::Builder::new()
+        .name("watchdog".into())
+        .spawn(move || {
+            let mut reported: Option<Instant> = None;
+            loop {
+                thread::sleep(CHECK_INTERVAL);
+                let now = Instant::now();
+                match heartbeat.stuck(now) {
+                    Some(stuck) if reported != Some(stuck.since) => {
+                        reported = Some(stuck.since);
+                        let secs = now.duration_since(stuck.since).as_secs_f32();
+                        print_error!(
+                            "event loop stuck for {secs:.1}s {}",
+                            stuck.stage.label()
+                        );
+                        if heartbeat.settings().restart {
+                            print_error!("exiting so the daemon is started again");
+                            log::logger().flush();
+                            std::process::exit(EXIT_CODE);
+                        }
+                    }
+                    Some(_) => {}
+                    None => {
+                        if let Some(since) = reported.take() {
+                            let secs = now.duration_since(since).as_secs_f32();
+                            print_info!(
+                                "event loop recovered after about {secs:.0}s"
+                            );
+                        }
+                    }
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_stage_of_iteration_running_past_timeout() {
+        let heartbeat = Heartbeat::new();
+        heartbeat.configure(WatchdogSettings {
+            timeout: Duration::from_secs(3),
+            restart: false,
+        });
+        heartbeat.beat();
+        let now = Instant::now();
+        assert_eq!(heartbeat.enter(Stage::Action), Stage::Event);
+        assert!(heartbeat.stuck(now).is_none());
+
+        let stuck = heartbeat
+            .stuck(now + Duration::from_secs(3))
+            .expect("iteration should be stuck");
+        assert_eq!(stuck.stage, Stage::Action);
+
+        heartbeat.beat();
+        assert!(heartbeat.stuck(Instant::now()).is_none());
+    }
+
+    #[test]
+    fn stage_indices_match() {
+        for (index, stage) in Stage::ALL.into_iter().enumerate() {
+            assert_eq!(stage as usize, index);
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! Profile behavior scenarios on a virtual clock. Add new cases by building
+//! a `Simulation` from a profile and asserting on the recorded actions.
+
+use std::time::Duration;
+
+use gamacros_control::{Key, KeyCombo, MouseButton, SystemAction};
+use gamacros_gamepad::{Axis, Button};
+use gamacrosd::sim::Simulation;
+use gamacrosd::Action;
+
+fn key(combo: &str) -> KeyCombo {
+    combo.parse().unwrap()
+}
+
+#[test]
+fn larger_chord_takes_precedence() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: x
+      a+b:
+        keystroke: y
+",
+    )
+    .unwrap();
+    sim.connect(0)
+        .press(0, Button::A)
+        .wait_ms(20)
+        .press(0, Button::B);
+    let actions = sim.take_actions();
+    assert!(matches!(&actions[..], [Action::KeyPress(k)] if *k == key("y")));
+
+    sim.release(0, Button::B).release(0, Button::A).wait_ms(100);
+    let actions = sim.take_actions();
+    assert!(matches!(&actions[..], [Action::KeyRelease(k)] if *k == key("y")));
+}
+
+#[test]
+fn single_button_fires_after_chord_window() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+chord_window_ms: 50
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: x
+      a+b:
+        keystroke: y
+",
+    )
+    .unwrap();
+    sim.connect(0).press(0, Button::A).wait_ms(40);
+    assert!(sim.take_actions().is_empty());
+
+    sim.wait_ms(20);
+    let actions = sim.take_timed_actions();
+    assert!(matches!(
+        &actions[..],
+        [(at, Action::KeyPress(k))] if *k == key("x") && *at == Duration::from_millis(50)
+    ));
+}
+
+#[test]
+fn deadlines_fire_on_time_and_ticks_stop_at_rest() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+chord_window_ms: 45
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: x
+      a+b:
+        keystroke: y
+    sticks:
+      left:
+        mode: mouse_move
+",
+    )
+    .unwrap();
+    // Chord presses fire at their deadline, between ticks
+    sim.connect(0).press(0, Button::A).wait_ms(100);
+    let actions = sim.take_timed_actions();
+    assert!(matches!(
+        &actions[..],
+        [(at, Action::KeyPress(_))] if *at == Duration::from_millis(45)
+    ));
+
+    sim.axis(0, Axis::LeftX, 1.0).wait_ms(35);
+    let moves: Vec<_> = sim
+        .take_timed_actions()
+        .into_iter()
+        .map(|(at, _)| at.as_millis())
+        .collect();
+    assert_eq!(moves, [110, 120, 130]);
+
+    sim.axis(0, Axis::LeftX, 0.0).wait_ms(100);
+    assert!(sim.take_actions().is_empty());
+}
+
+#[test]
+fn app_switch_releases_held_keystroke() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  com.apple.Terminal:
+    buttons:
+      a:
+        keystroke: shift
+",
+    )
+    .unwrap();
+    sim.connect(0).app("com.apple.Terminal").press(0, Button::A);
+    assert!(matches!(&sim.take_actions()[..], [Action::KeyPress(_)]));
+
+    sim.app("com.apple.Safari");
+    let actions = sim.take_actions();
+    assert!(matches!(&actions[..], [Action::KeyRelease(k)] if *k == key("shift")));
+
+    sim.release(0, Button::A);
+    assert!(sim.take_actions().is_empty());
+}
+
+#[test]
+fn arrows_repeat_after_delay_at_interval() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        mode: arrows
+        repeat_delay_ms: 200
+        repeat_interval_ms: 50
+",
+    )
+    .unwrap();
+    sim.connect(0).axis(0, Axis::LeftX, 1.0).wait_ms(400);
+    let taps: Vec<_> = sim
+        .take_timed_actions()
+        .into_iter()
+        .filter(|(_, action)| matches!(action, Action::KeyTap(_)))
+        .map(|(at, _)| at.as_millis())
+        .collect();
+    assert_eq!(taps, [10, 210, 260, 310, 360]);
+}
+
+#[test]
+fn keyboard_backlight_steps_with_stick() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      right:
+        mode: keyboard_backlight
+",
+    )
+    .unwrap();
+    sim.connect(0).axis(0, Axis::RightY, 1.0).wait_ms(100);
+    let actions = sim.take_actions();
+    assert!(!actions.is_empty());
+    assert!(actions.iter().all(|action| matches!(
+        action,
+        Action::KeyTap(k) if k.keys[..] == [Key::IlluminationUp]
+    )));
+}
+
+#[test]
+fn seek_rate_follows_deflection() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      right:
+        mode: seek
+        deadzone: 0
+        gamma: 1
+        min_interval_ms: 400
+        max_interval_ms: 40
+        forward: shift+arrow_right
+",
+    )
+    .unwrap();
+    let taps = |sim: &mut Simulation| {
+        sim.wait_ms(400);
+        sim.take_actions()
+            .into_iter()
+            .filter(|action| matches!(action, Action::KeyTap(_)))
+            .collect::<Vec<_>>()
+    };
+
+    sim.connect(0).axis(0, Axis::RightX, 1.0);
+    let fast = taps(&mut sim);
+    assert!(fast
+        .iter()
+        .all(|a| matches!(a, Action::KeyTap(k) if *k == key("shift+arrow_right"))));
+
+    sim.axis(0, Axis::RightX, -0.5);
+    let slow = taps(&mut sim);
+    assert!(slow
+        .iter()
+        .all(|a| matches!(a, Action::KeyTap(k) if *k == key("media_rewind"))));
+    assert_eq!(fast.len(), 10);
+    assert_eq!(slow.len(), 2);
+}
+
+#[test]
+fn slow_mouse_movement_adds_up() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        mode: mouse_move
+        deadzone: 0
+        gamma: 1
+        max_speed_px_s: 100
+",
+    )
+    .unwrap();
+    // 20 px/s moves a fifth of a pixel per tick
+    sim.connect(0).axis(0, Axis::LeftX, 0.2).wait_ms(1000);
+    let dx: i32 = sim
+        .take_actions()
+        .iter()
+        .map(|action| match action {
+            Action::MouseMove { dx, .. } => *dx,
+            _ => 0,
+        })
+        .sum();
+    assert!((15..=25).contains(&dx), "moved {dx} px");
+}
+
+#[test]
+fn dwell_clicks_once_after_cursor_rests() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        mode: mouse_move
+        dwell_ms: 300
+        dwell_button: right
+",
+    )
+    .unwrap();
+    sim.connect(0).axis(0, Axis::LeftX, 1.0).wait_ms(100);
+    sim.axis(0, Axis::LeftX, 0.0).wait_ms(100);
+    assert!(!sim
+        .take_actions()
+        .iter()
+        .any(|action| matches!(action, Action::MouseClick(_))));
+    sim.wait_ms(1000);
+    assert!(matches!(
+        sim.take_actions()[..],
+        [Action::MouseClick(MouseButton::Right)]
+    ));
+}
+
+#[test]
+fn stick_click_toggles_mode_per_controller() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      left:
+        mode: mouse_move
+        toggle_with: ls
+        toggle_mode: { mode: scroll }
+",
+    )
+    .unwrap();
+    let moves = |actions: &[Action]| {
+        let mouse = actions
+            .iter()
+            .filter(|action| matches!(action, Action::MouseMove { .. }))
+            .count();
+        let scroll = actions
+            .iter()
+            .filter(|action| matches!(action, Action::Scroll { .. }))
+            .count();
+        (mouse > 0, scroll > 0)
+    };
+    sim.connect(0).connect(1).press(0, Button::LeftStick);
+    sim.release(0, Button::LeftStick);
+    sim.axis(0, Axis::LeftY, 1.0).wait_ms(100);
+    assert_eq!(moves(&sim.take_actions()), (false, true));
+
+    sim.axis(0, Axis::LeftY, 0.0)
+        .axis(1, Axis::LeftY, 1.0)
+        .wait_ms(100);
+    assert_eq!(moves(&sim.take_actions()), (true, false));
+
+    sim.axis(1, Axis::LeftY, 0.0).press(0, Button::LeftStick);
+    sim.axis(0, Axis::LeftY, 1.0).wait_ms(100);
+    assert_eq!(moves(&sim.take_actions()), (true, false));
+}
+
+#[test]
+fn held_chord_shifts_stick_mode() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    sticks:
+      right:
+        - { mode: mouse_move }
+        - { mode: scroll, while_held: lt }
+",
+    )
+    .unwrap();
+    let moves = |actions: Vec<Action>| {
+        let mouse = actions
+            .iter()
+            .any(|action| matches!(action, Action::MouseMove { .. }));
+        let scroll = actions
+            .iter()
+            .any(|action| matches!(action, Action::Scroll { .. }));
+        (mouse, scroll)
+    };
+    sim.connect(0).press(0, Button::LeftTrigger);
+    sim.axis(0, Axis::RightY, 1.0).wait_ms(100);
+    assert_eq!(moves(sim.take_actions()), (false, true));
+
+    sim.release(0, Button::LeftTrigger).wait_ms(100);
+    assert_eq!(moves(sim.take_actions()), (true, false));
+}
+
+#[test]
+fn profile_set_switches_from_controller() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: enter
+      start:
+        profile_set: next
+sets:
+  - name: media
+    buttons:
+      a:
+        system: play_pause
+",
+    )
+    .unwrap();
+    sim.connect(0)
+        .press(0, Button::Start)
+        .release(0, Button::Start)
+        .press(0, Button::A);
+    assert!(matches!(
+        sim.take_actions()[..],
+        [Action::System(SystemAction::PlayPause)]
+    ));
+
+    sim.release(0, Button::A)
+        .press(0, Button::Start)
+        .release(0, Button::Start)
+        .press(0, Button::A);
+    assert!(
+        matches!(&sim.take_actions()[..], [Action::KeyPress(k)] if *k == key("enter"))
+    );
+}
+
+#[test]
+fn runs_json_scripts() {
+    let mut sim = Simulation::from_yaml(
+        "
+version: 1
+rules:
+  common:
+    buttons:
+      a:
+        keystroke: enter
+",
+    )
+    .unwrap();
+    sim.run_script(
+        r#"[
+            {"connect": {}},
+            {"press": {"button": "a"}},
+            {"wait": {"ms": 100}},
+            {"release": {"button": "a"}}
+        ]"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        &sim.take_actions()[..],
+        [Action::KeyPress(_), Action::KeyRelease(_)]
+    ));
+    assert_eq!(sim.elapsed(), Duration::from_millis(100));
+}